@@ -247,6 +247,11 @@ impl LspClient {
         }
     }
 
+    /// Checks if the connection to the client is closed.
+    pub fn is_closed(&self) -> bool {
+        self.sender.strong_count() == 0
+    }
+
     /// Checks if there are pending requests.
     pub fn has_pending_requests(&self) -> bool {
         self.req_queue.lock().incoming.has_pending()
@@ -14,12 +14,38 @@
 //! }
 //! ```
 //! However, this is out of scope for now.
+//!
+//! ## Retention
+//!
+//! Each interned object counts the collections it has been unreferenced for,
+//! which is reset whenever it is interned again. Once an interner has been
+//! collected, an object that is no longer referenced is kept in the map for
+//! [`Internable::RETAINED_COLLECTIONS`] collections, so the hot objects, e.g.
+//! the names, are not reallocated on every edit, and is freed by
+//! [`collect_garbage`] afterwards. Before that, an object is freed as soon as
+//! it is no longer referenced.
+//!
+//! The counts don't depend on the revisions of any analysis, so the
+//! collections run by the different analyses never free an object that is
+//! referenced, and they only age the unreferenced objects.
+//!
+//! ## Sharing
+//!
+//! The interned objects are shared by the analyses on the rayon pool. The
+//! objects are interned and freed with their shards locked, so an object is
+//! never interned twice nor freed while it is referenced. However, when the
+//! last two references are dropped concurrently, both of them may miss the
+//! chance to free the object, which is then kept in the map until it is freed
+//! by [`collect_garbage`].
 
 use std::{
     fmt::{self, Debug, Display},
     hash::{BuildHasherDefault, Hash, Hasher},
     ops::Deref,
-    sync::{LazyLock, OnceLock},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        LazyLock, OnceLock,
+    },
 };
 
 use dashmap::{DashMap, SharedValue};
@@ -30,12 +56,19 @@ use rustc_hash::FxHasher;
 use triomphe::Arc;
 use typst::{foundations::Str, syntax::ast::Ident};
 
-type InternMap<T> = DashMap<Arc<T>, (), BuildHasherDefault<FxHasher>>;
+type InternMap<T> = DashMap<Arc<T>, AtomicU64, BuildHasherDefault<FxHasher>>;
 type Guard<T> = dashmap::RwLockWriteGuard<
     'static,
-    HashMap<Arc<T>, SharedValue<()>, BuildHasherDefault<FxHasher>>,
+    HashMap<Arc<T>, SharedValue<AtomicU64>, BuildHasherDefault<FxHasher>>,
 >;
 
+/// Frees the objects that are no longer referenced nor retained from all the
+/// interners. Returns the number of freed objects.
+pub fn collect_garbage() -> usize {
+    let collectors = COLLECTORS.lock().clone();
+    collectors.iter().map(|collect| collect()).sum()
+}
+
 // https://news.ycombinator.com/item?id=22220342
 
 pub struct Interned<T: Internable + ?Sized> {
@@ -53,14 +86,18 @@ impl<T: Internable> Interned<T> {
         // which could insert the same object between us looking it up and
         // inserting it.
         match shard.raw_entry_mut().from_key_hashed_nocheck(hash, &obj) {
-            RawEntryMut::Occupied(occ) => Self {
-                arc: occ.key().clone(),
-            },
+            RawEntryMut::Occupied(occ) => {
+                occ.get().get().store(0, Ordering::Relaxed);
+                Self {
+                    arc: occ.key().clone(),
+                }
+            }
             RawEntryMut::Vacant(vac) => {
                 T::storage().alloc().increment();
+                let idle = SharedValue::new(AtomicU64::new(0));
                 Self {
                     arc: vac
-                        .insert_hashed_nocheck(hash, Arc::new(obj), SharedValue::new(()))
+                        .insert_hashed_nocheck(hash, Arc::new(obj), idle)
                         .0
                         .clone(),
                 }
@@ -86,15 +123,19 @@ impl Interned<str> {
         // which could insert the same object between us looking it up and
         // inserting it.
         match shard.raw_entry_mut().from_key_hashed_nocheck(hash, s) {
-            RawEntryMut::Occupied(occ) => Self {
-                arc: occ.key().clone(),
-            },
+            RawEntryMut::Occupied(occ) => {
+                occ.get().get().store(0, Ordering::Relaxed);
+                Self {
+                    arc: occ.key().clone(),
+                }
+            }
             RawEntryMut::Vacant(vac) => {
                 str::storage().alloc().increment();
 
+                let idle = SharedValue::new(AtomicU64::new(0));
                 Self {
                     arc: vac
-                        .insert_hashed_nocheck(hash, Arc::from(s), SharedValue::new(()))
+                        .insert_hashed_nocheck(hash, Arc::from(s), idle)
                         .0
                         .clone(),
                 }
@@ -205,7 +246,14 @@ impl<T: Internable + ?Sized> Interned<T> {
             .raw_entry_mut()
             .from_key_hashed_nocheck(hash, &self.arc)
         {
-            RawEntryMut::Occupied(occ) => occ.remove(),
+            RawEntryMut::Occupied(occ) => {
+                // Once the interner is collected, an unreferenced object is
+                // kept for the next collections, and is freed by them.
+                if T::storage().retaining.load(Ordering::Relaxed) {
+                    return;
+                }
+                occ.remove()
+            }
             RawEntryMut::Vacant(_) => unreachable!(),
         };
 
@@ -298,6 +346,20 @@ impl<'de> serde::Deserialize<'de> for Interned<str> {
     }
 }
 
+impl<T: Internable + serde::Serialize> serde::Serialize for Interned<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.arc.serialize(serializer)
+    }
+}
+
+/// The objects are interned again when they are read, so they are shared with
+/// the objects interned in the current process.
+impl<'de, T: Internable + serde::Deserialize<'de>> serde::Deserialize<'de> for Interned<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Interned::new)
+    }
+}
+
 impl<T: Internable + ?Sized> Hash for Interned<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         // NOTE: Cast disposes vtable pointer / slice/str length.
@@ -343,8 +405,14 @@ impl<T: Display + Internable + ?Sized> Display for Interned<T> {
 
 pub static MAPS: Mutex<EcoVec<(&'static str, usize, Arc<AllocStats>)>> = Mutex::new(EcoVec::new());
 
+/// The garbage collectors of the interners.
+static COLLECTORS: Mutex<Vec<fn() -> usize>> = Mutex::new(Vec::new());
+
 pub struct InternStorage<T: ?Sized> {
     alloc: OnceLock<Arc<AllocStats>>,
+    /// Whether the unreferenced objects are retained, which is enabled by the
+    /// first collection.
+    retaining: AtomicBool,
     map: OnceLock<InternMap<T>>,
 }
 
@@ -355,6 +423,7 @@ impl<T: InternSize + ?Sized> InternStorage<T> {
     pub const fn new() -> Self {
         Self {
             alloc: OnceLock::new(),
+            retaining: AtomicBool::new(false),
             map: OnceLock::new(),
         }
     }
@@ -369,9 +438,50 @@ impl<T: Internable + ?Sized> InternStorage<T> {
         self.map.get_or_init(|| {
             MAPS.lock()
                 .push((std::any::type_name::<T>(), Self::SIZE, self.alloc().clone()));
+            COLLECTORS.lock().push(|| T::storage().collect());
             DashMap::default()
         })
     }
+
+    /// Ages the objects that are only referenced by the map, and frees the ones
+    /// that have been unreferenced for more than
+    /// [`Internable::RETAINED_COLLECTIONS`] collections. Returns the number of
+    /// freed objects.
+    fn collect(&self) -> usize {
+        self.retaining.store(true, Ordering::Relaxed);
+
+        let mut collected = 0;
+        for shard in self.get().shards() {
+            // The objects are dropped after the shard is unlocked, as dropping
+            // them may drop the interned objects in the same shard.
+            let mut garbage = vec![];
+            let mut shard = shard.write();
+            shard.retain(|arc, idle| {
+                let idle = idle.get_mut().get_mut();
+                if Arc::count(arc) > 1 {
+                    *idle = 0;
+                    return true;
+                }
+                *idle += 1;
+                let keep = *idle <= T::RETAINED_COLLECTIONS;
+                if !keep {
+                    garbage.push(arc.clone());
+                }
+                keep
+            });
+            if shard.len() * 2 < shard.capacity() {
+                shard.shrink_to_fit();
+            }
+            drop(shard);
+
+            collected += garbage.len();
+        }
+
+        let alloc = self.alloc();
+        alloc.dropped.fetch_add(collected, Ordering::Relaxed);
+        alloc.collected.fetch_add(collected, Ordering::Relaxed);
+        collected
+    }
 }
 
 pub trait InternSize {
@@ -387,6 +497,10 @@ impl InternSize for str {
 }
 
 pub trait Internable: InternSize + Hash + Eq + 'static {
+    /// The number of the collections for which an unreferenced object is kept,
+    /// which is higher for the objects that are likely to be interned again.
+    const RETAINED_COLLECTIONS: u64 = 1;
+
     fn storage() -> &'static InternStorage<Self>;
 }
 
@@ -395,17 +509,97 @@ pub trait Internable: InternSize + Hash + Eq + 'static {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _impl_internable {
-    ( $($t:ty),+ $(,)? ) => { $(
+    ( retain($n:expr): $($t:ty),+ $(,)? ) => { $(
         impl $crate::adt::interner::Internable for $t {
+            const RETAINED_COLLECTIONS: u64 = $n;
+
             fn storage() -> &'static $crate::adt::interner::InternStorage<Self> {
                 static STORAGE: $crate::adt::interner::InternStorage<$t> = $crate::adt::interner::InternStorage::new();
                 &STORAGE
             }
         }
     )+ };
+    ( $($t:ty),+ $(,)? ) => {
+        $crate::adt::interner::impl_internable!(retain(1): $($t),+);
+    };
 }
 
 pub use crate::_impl_internable as impl_internable;
 use crate::stats::AllocStats;
 
-impl_internable!(str,);
+// The names are interned again on almost every edit.
+impl_internable!(retain(8): str);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Object(u32);
+
+    impl_internable!(retain(2): Object);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Shared(u32);
+
+    impl_internable!(Shared);
+
+    fn interned() -> usize {
+        Object::storage().get().len()
+    }
+
+    #[test]
+    fn retention() {
+        let storage = Object::storage();
+
+        // Freed as soon as it is unreferenced before the first collection.
+        drop(Interned::new(Object(0)));
+        assert_eq!(interned(), 0);
+
+        let obj = Interned::new(Object(1));
+        assert_eq!(storage.collect(), 0);
+        drop(obj);
+        // Retained for the next collections.
+        assert_eq!(interned(), 1);
+        assert_eq!(storage.collect(), 0);
+        assert_eq!(interned(), 1);
+
+        // Revived by interning it again.
+        let obj = Interned::new(Object(1));
+        storage.collect();
+        drop(obj);
+        storage.collect();
+        storage.collect();
+        assert_eq!(interned(), 1);
+
+        assert_eq!(storage.collect(), 1);
+        assert_eq!(interned(), 0);
+        assert_eq!(
+            Object::storage().alloc().collected.load(Ordering::Relaxed),
+            1
+        );
+    }
+
+    #[test]
+    fn shared() {
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for idx in 0..1000 {
+                        let obj = Interned::new(Shared(idx % 16));
+                        let copy = obj.clone();
+                        drop(obj);
+                        assert_eq!(Interned::new(Shared(idx % 16)), copy);
+                    }
+                });
+            }
+        });
+
+        // The objects missed by the concurrent drops are freed by the
+        // collection.
+        for _ in 0..=Shared::RETAINED_COLLECTIONS {
+            Shared::storage().collect();
+        }
+        assert_eq!(Shared::storage().get().len(), 0);
+    }
+}
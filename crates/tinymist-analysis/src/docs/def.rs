@@ -11,11 +11,12 @@ use crate::ty::{Interned, ParamAttrs, ParamTy, StrRef, Ty, TypeVarBounds};
 use crate::upstream::plain_docs_sentence;
 
 /// The documentation string of an item
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DocString {
     /// The documentation of the item
     pub docs: Option<EcoString>,
     /// The typing on definitions
+    #[serde(with = "crate::persist::pairs")]
     pub var_bounds: HashMap<DeclExpr, TypeVarBounds>,
     /// The variable doc associated with the item
     pub vars: BTreeMap<StrRef, VarDoc>,
@@ -44,7 +45,7 @@ impl DocString {
 }
 
 /// The documentation string of a variable associated with some item.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VarDoc {
     /// The documentation of the variable
     pub docs: EcoString,
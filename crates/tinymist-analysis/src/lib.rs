@@ -3,6 +3,7 @@
 pub mod adt;
 pub mod docs;
 pub mod location;
+pub mod persist;
 mod sig;
 pub mod stats;
 pub mod syntax;
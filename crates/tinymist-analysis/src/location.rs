@@ -26,6 +26,40 @@ pub enum PositionEncoding {
     Utf16,
     /// "1 character" means "1 byte"
     Utf8,
+    /// "1 character" means "1 Unicode code point"
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Gets the length of a text in the units of the encoding.
+    pub fn len_of(self, text: &str) -> usize {
+        match self {
+            PositionEncoding::Utf8 => text.len(),
+            PositionEncoding::Utf16 => text.chars().map(char::len_utf16).sum(),
+            PositionEncoding::Utf32 => text.chars().count(),
+        }
+    }
+
+    /// Gets the byte offset of a column in a line of text, given in the units of
+    /// the encoding. A column inside a character is rounded down to the start
+    /// of the character, and a column beyond the line is clamped to its end.
+    pub fn offset_of(self, line: &str, column: usize) -> usize {
+        if self == PositionEncoding::Utf8 {
+            return floor_char_boundary(line, column);
+        }
+
+        let mut units = 0;
+        for (offset, ch) in line.char_indices() {
+            units += match self {
+                PositionEncoding::Utf16 => ch.len_utf16(),
+                _ => 1,
+            };
+            if units > column {
+                return offset;
+            }
+        }
+        line.len()
+    }
 }
 
 impl From<PositionEncoding> for tinymist_world::debug_loc::PositionEncodingKind {
@@ -33,6 +67,7 @@ impl From<PositionEncoding> for tinymist_world::debug_loc::PositionEncodingKind
         match position_encoding {
             PositionEncoding::Utf16 => Self::UTF16,
             PositionEncoding::Utf8 => Self::UTF8,
+            PositionEncoding::Utf32 => Self::UTF32,
         }
     }
 }
@@ -52,12 +87,7 @@ pub fn to_typst_position(
             Ordering::Less if lsp_position.line + 1 == lines => {
                 let last_line_offset = typst_source.line_to_byte(lines as usize - 1)?;
                 let last_line_chars = &typst_source.text()[last_line_offset..];
-                let len = match lsp_position_encoding {
-                    PositionEncoding::Utf8 => last_line_chars.len(),
-                    PositionEncoding::Utf16 => {
-                        last_line_chars.chars().map(char::len_utf16).sum::<usize>()
-                    }
-                };
+                let len = lsp_position_encoding.len_of(last_line_chars);
 
                 match lsp_position.character.cmp(&(len as u32)) {
                     Ordering::Less => break 'bound_checking,
@@ -78,41 +108,15 @@ pub fn to_typst_position(
         return Some(typst_source.len_bytes());
     }
 
-    match lsp_position_encoding {
-        PositionEncoding::Utf8 => {
-            let line_index = lsp_position.line as usize;
-            let column_index = lsp_position.character as usize;
-            typst_source.line_column_to_byte(line_index, column_index)
-        }
-        PositionEncoding::Utf16 => {
-            // We have a line number and a UTF-16 offset into that line. We want a byte
-            // offset into the file.
-            //
-            // Typst's `Source` provides several UTF-16 methods:
-            //  - `len_utf16` for the length of the file
-            //  - `byte_to_utf16` to convert a byte offset from the start of the file to a
-            //    UTF-16 offset from the start of the file
-            //  - `utf16_to_byte` to do the opposite of `byte_to_utf16`
-            //
-            // Unfortunately, none of these address our needs well, so we do some math
-            // instead. This is not the fastest possible implementation, but
-            // it's the most reasonable without access to the internal state
-            // of `Source`.
-
-            // TODO: Typst's `Source` could easily provide an implementation of the method
-            // we need   here. Submit a PR against `typst` to add it, then
-            // update this if/when merged.
-
-            let line_index = lsp_position.line as usize;
-            let utf16_offset_in_line = lsp_position.character as usize;
-
-            let byte_line_offset = typst_source.line_to_byte(line_index)?;
-            let utf16_line_offset = typst_source.byte_to_utf16(byte_line_offset)?;
-            let utf16_offset = utf16_line_offset + utf16_offset_in_line;
-
-            typst_source.utf16_to_byte(utf16_offset)
-        }
-    }
+    // We have a line number and an offset into that line in the units of the
+    // encoding. A character beyond the line defaults back to the end of the
+    // line, as specified by the LSP.
+    let line_range = typst_source.line_to_range(lsp_position.line as usize)?;
+    let line_start = line_range.start;
+    let line = typst_source.text()[line_range].trim_end_matches(is_newline);
+
+    let column = lsp_position.character as usize;
+    Some(line_start + lsp_position_encoding.offset_of(line, column))
 }
 
 /// Convert a Typst position to an LSP position.
@@ -125,31 +129,111 @@ pub fn to_lsp_position(
         return LspPosition::new(typst_source.len_lines() as u32, 0);
     }
 
+    let text = typst_source.text();
+    let typst_offset = floor_char_boundary(text, typst_offset);
     let line_index = typst_source.byte_to_line(typst_offset).unwrap();
-    let column_index = typst_source.byte_to_column(typst_offset).unwrap();
+    let line_start = typst_source.line_to_byte(line_index).unwrap();
 
     let lsp_line = line_index as u32;
-    let lsp_column = match lsp_position_encoding {
-        PositionEncoding::Utf8 => column_index as u32,
-        PositionEncoding::Utf16 => {
-            // See the implementation of `position_to_offset` for discussion
-            // relevant to this function.
+    let lsp_column = lsp_position_encoding.len_of(&text[line_start..typst_offset]) as u32;
+
+    LspPosition::new(lsp_line, lsp_column)
+}
 
-            // TODO: Typst's `Source` could easily provide an implementation of the method
-            // we   need here. Submit a PR to `typst` to add it, then update
-            // this if/when merged.
+/// Rounds a byte offset down to the start of the character containing it.
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    if offset >= text.len() {
+        return text.len();
+    }
+    (0..=offset)
+        .rev()
+        .find(|&offset| text.is_char_boundary(offset))
+        .unwrap_or_default()
+}
 
-            let utf16_offset = typst_source.byte_to_utf16(typst_offset).unwrap();
+/// Whether a character is interpreted as a newline by Typst.
+fn is_newline(ch: char) -> bool {
+    matches!(
+        ch,
+        '\n' | '\x0B' | '\x0C' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}'
+    )
+}
 
-            let byte_line_offset = typst_source.line_to_byte(line_index).unwrap();
-            let utf16_line_offset = typst_source.byte_to_utf16(byte_line_offset).unwrap();
+/// The length of a line in bytes, above which the line is considered as very
+/// long, e.g. in a generated or minified source.
+pub const LONG_LINE_THRESHOLD: usize = 10_000;
+
+/// Checks whether a source has very long lines.
+pub fn has_long_lines(source: &Source) -> bool {
+    let mut line_start = 0;
+    for line in 1..=source.len_lines() {
+        let line_end = source.line_to_byte(line).unwrap_or(source.len_bytes());
+        if line_end - line_start > LONG_LINE_THRESHOLD {
+            return true;
+        }
+        line_start = line_end;
+    }
+    false
+}
 
-            let utf16_column_offset = utf16_offset - utf16_line_offset;
-            utf16_column_offset as u32
+/// Converts Typst offsets into LSP positions incrementally.
+///
+/// Converting an offset counts the characters from the start of its line, which
+/// gets quadratic when converting many offsets on a very long line. The cursor
+/// remembers the last converted offset, so converting the offsets in ascending
+/// order only counts the characters between them.
+#[derive(Debug, Clone)]
+pub struct LspPositionCursor {
+    encoding: PositionEncoding,
+    /// The line of the last converted offset.
+    line: usize,
+    /// The byte offset where the next line starts.
+    next_line_start: Option<usize>,
+    /// The last converted offset.
+    offset: usize,
+    /// The column of the last converted offset.
+    column: usize,
+}
+
+impl LspPositionCursor {
+    /// Creates a cursor at the start of a source.
+    pub fn new(encoding: PositionEncoding, source: &Source) -> Self {
+        Self {
+            encoding,
+            line: 0,
+            next_line_start: source.line_to_byte(1),
+            offset: 0,
+            column: 0,
         }
-    };
+    }
 
-    LspPosition::new(lsp_line, lsp_column)
+    /// Converts a Typst offset to an LSP position.
+    pub fn to_lsp_position(&mut self, typst_offset: usize, source: &Source) -> LspPosition {
+        if typst_offset > source.len_bytes() {
+            return LspPosition::new(source.len_lines() as u32, 0);
+        }
+
+        let in_line = typst_offset >= self.offset
+            && self.next_line_start.is_none_or(|next| typst_offset < next);
+        if !in_line {
+            let Some(line) = source.byte_to_line(typst_offset) else {
+                return to_lsp_position(typst_offset, self.encoding, source);
+            };
+            self.line = line;
+            self.next_line_start = source.line_to_byte(line + 1);
+            self.offset = source.line_to_byte(line).unwrap_or_default();
+            self.column = 0;
+        }
+
+        let Some(text) = source.text().get(self.offset..typst_offset) else {
+            // Not at a char boundary.
+            return to_lsp_position(typst_offset, self.encoding, source);
+        };
+        self.column += self.encoding.len_of(text);
+        self.offset = typst_offset;
+
+        LspPosition::new(self.line as u32, self.column as u32)
+    }
 }
 
 /// Convert an LSP range to a Typst range.
@@ -283,6 +367,56 @@ mod test {
         );
     }
 
+    #[test]
+    fn position_cursor() {
+        let source = Source::detached("ab 🥺 c\nd 🥺\n\ne");
+
+        for encoding in [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ] {
+            let mut cursor = LspPositionCursor::new(encoding, &source);
+            let offsets =
+                (0..=source.len_bytes()).filter(|&offset| source.text().is_char_boundary(offset));
+            // Converts in ascending order, then again in descending order.
+            for offset in offsets.clone().chain(offsets.rev()) {
+                assert_eq!(
+                    cursor.to_lsp_position(offset, &source),
+                    to_lsp_position(offset, encoding, &source),
+                    "offset: {offset}, encoding: {encoding:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn position_cursor_fallbacks() {
+        let source = Source::detached("a\n🥺b");
+        let encoding = PositionEncoding::Utf16;
+
+        // The offset at the start of the emoji is converted in its line, while
+        // the one inside the emoji falls back to the free function, and the
+        // one beyond the source is out of bounds.
+        for (offset, position) in [
+            (2, Position::new(1, 0)),
+            (3, Position::new(1, 0)),
+            (source.len_bytes() + 1, Position::new(2, 0)),
+        ] {
+            let mut cursor = LspPositionCursor::new(encoding, &source);
+            assert_eq!(cursor.to_lsp_position(2, &source), Position::new(1, 0));
+            assert_eq!(cursor.to_lsp_position(offset, &source), position);
+            assert_eq!(to_lsp_position(offset, encoding, &source), position);
+        }
+    }
+
+    #[test]
+    fn long_lines() {
+        assert!(!has_long_lines(&Source::detached("a\nb")));
+        let long = "a".repeat(LONG_LINE_THRESHOLD + 1);
+        assert!(has_long_lines(&Source::detached(format!("a\n{long}\nb"))));
+    }
+
     #[test]
     fn utf16_position_to_utf8_offset() {
         let source = Source::detached(ENCODING_TEST_STRING);
@@ -361,4 +495,48 @@ mod test {
         assert_eq!(post_emoji_position, post_emoji_actual);
         assert_eq!(end_position, end_actual);
     }
+
+    #[test]
+    fn encodings() {
+        let source = Source::detached("a🥺b\r\n字c");
+
+        // The offsets with their lines and columns in UTF-8, UTF-16 and UTF-32.
+        let cases = [
+            (0, 0, [0, 0, 0]),
+            (5, 0, [5, 3, 2]),
+            (6, 0, [6, 4, 3]),
+            (8, 1, [0, 0, 0]),
+            (11, 1, [3, 1, 1]),
+            (12, 1, [4, 2, 2]),
+        ];
+        let encodings = [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ];
+        for (offset, line, columns) in cases {
+            for (encoding, character) in encodings.into_iter().zip(columns) {
+                let position = LspPosition { line, character };
+                assert_eq!(to_lsp_position(offset, encoding, &source), position);
+                assert_eq!(to_typst_position(position, encoding, &source), Some(offset));
+            }
+        }
+
+        // A column inside a character is rounded down, and a column beyond the
+        // line is clamped to the end of the line.
+        let inside = LspPosition::new(0, 2);
+        assert_eq!(
+            to_typst_position(inside, PositionEncoding::Utf8, &source),
+            Some(1)
+        );
+        assert_eq!(
+            to_typst_position(inside, PositionEncoding::Utf16, &source),
+            Some(1)
+        );
+        let beyond = LspPosition::new(0, 100);
+        assert_eq!(
+            to_typst_position(beyond, PositionEncoding::Utf16, &source),
+            Some(6)
+        );
+    }
 }
@@ -0,0 +1,558 @@
+//! Portable representations of the analysis results, which are persisted on
+//! disk and read back by the later server processes.
+//!
+//! The file ids and the spans are only meaningful within a process, so they
+//! are written by the packages and the paths of the files. The values are
+//! written only if they are literals or items of the standard library, and
+//! the other values fail the serialization, so that the results containing
+//! them are not persisted.
+
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU64;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+use rustc_hash::FxHashMap;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use tinymist_std::hash::hash128;
+use tinymist_std::path::unix_slash;
+use tinymist_std::DefId;
+use typst::foundations::{Element, Func, Scope, Type, Value};
+use typst::layout::{Abs, Angle, Em, Fr, Length, Ratio, Rel};
+use typst::syntax::ast::BinOp;
+use typst::syntax::Span;
+use typst::utils::LazyHash;
+
+use crate::adt::interner::Interned;
+use crate::docs::{DocString, UntypedDefDocs};
+use crate::prelude::*;
+use crate::syntax::{DeclExpr, Expr, ExprInfo, ExprInfoRepr, LexicalScope, RefExpr};
+use crate::ty::{Ty, TypeInfo, TypeVarBounds};
+use crate::upstream::LIBRARY;
+
+/// The number of the low bits of a span, which locate the span in its file.
+const SPAN_NUMBER_MASK: u64 = (1 << 48) - 1;
+
+/// A file id, which is written by its package and its path.
+#[derive(Debug, Clone, Copy)]
+pub struct PortableFileId(pub TypstFileId);
+
+impl Serialize for PortableFileId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let package = self.0.package().map(ToString::to_string);
+        let path = unix_slash(self.0.vpath().as_rooted_path());
+        (package, path).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PortableFileId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (package, path) = <(Option<String>, String)>::deserialize(deserializer)?;
+        let package = package
+            .map(|spec| PackageSpec::from_str(&spec).map_err(de::Error::custom))
+            .transpose()?;
+        Ok(Self(TypstFileId::new(package, VirtualPath::new(path))))
+    }
+}
+
+/// A span, which is written by its file and its number in the file.
+#[derive(Debug, Clone, Copy)]
+pub struct PortableSpan(pub Span);
+
+impl Serialize for PortableSpan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let number = self.0.into_raw().get() & SPAN_NUMBER_MASK;
+        let repr = self.0.id().map(|id| (PortableFileId(id), number));
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PortableSpan {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Option::<(PortableFileId, u64)>::deserialize(deserializer)?;
+        let Some((PortableFileId(id), number)) = repr else {
+            return Ok(Self(Span::detached()));
+        };
+
+        let raw = ((id.into_raw().get() as u64) << 48) | (number & SPAN_NUMBER_MASK);
+        let raw = NonZeroU64::new(raw).ok_or_else(|| de::Error::custom("invalid span"))?;
+        Ok(Self(Span::from_raw(raw)))
+    }
+}
+
+/// Persists a [`TypstFileId`] field.
+pub mod file_id {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &TypstFileId, serializer: S) -> Result<S::Ok, S::Error> {
+        PortableFileId(*id).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<TypstFileId, D::Error> {
+        Ok(PortableFileId::deserialize(deserializer)?.0)
+    }
+}
+
+/// Persists a [`Span`] field.
+pub mod span {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(span: &Span, serializer: S) -> Result<S::Ok, S::Error> {
+        PortableSpan(*span).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Span, D::Error> {
+        Ok(PortableSpan::deserialize(deserializer)?.0)
+    }
+}
+
+/// Persists the location of a bibliography entry.
+pub mod name_range {
+    use super::*;
+
+    type Repr = Box<(TypstFileId, Range<usize>, Option<Range<usize>>)>;
+
+    pub fn serialize<S: Serializer>(at: &Repr, serializer: S) -> Result<S::Ok, S::Error> {
+        let (id, name_range, range) = at.as_ref();
+        (PortableFileId(*id), name_range, range).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Repr, D::Error> {
+        let (PortableFileId(id), name_range, range) = Deserialize::deserialize(deserializer)?;
+        Ok(Box::new((id, name_range, range)))
+    }
+}
+
+/// Persists a [`DefId`] field. The ids are only unique within a docstring, so
+/// they are written as they are.
+pub mod def_id {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &DefId, serializer: S) -> Result<S::Ok, S::Error> {
+        id.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DefId, D::Error> {
+        Ok(DefId(u64::deserialize(deserializer)?))
+    }
+}
+
+/// Persists the name node of a type source, which is either a synthesized
+/// identifier or an empty node.
+pub mod name_node {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(node: &SyntaxNode, serializer: S) -> Result<S::Ok, S::Error> {
+        (node.text(), PortableSpan(node.span())).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SyntaxNode, D::Error> {
+        let (text, PortableSpan(span)) = <(EcoString, PortableSpan)>::deserialize(deserializer)?;
+        if text.is_empty() && span.is_detached() {
+            return Ok(SyntaxNode::default());
+        }
+
+        let mut node = SyntaxNode::leaf(SyntaxKind::Ident, text);
+        node.synthesize(span);
+        Ok(node)
+    }
+}
+
+/// All the binary operators, which are written by their symbols.
+const BIN_OPS: [BinOp; 19] = [
+    BinOp::Add,
+    BinOp::Sub,
+    BinOp::Mul,
+    BinOp::Div,
+    BinOp::And,
+    BinOp::Or,
+    BinOp::Eq,
+    BinOp::Neq,
+    BinOp::Lt,
+    BinOp::Leq,
+    BinOp::Gt,
+    BinOp::Geq,
+    BinOp::Assign,
+    BinOp::In,
+    BinOp::NotIn,
+    BinOp::AddAssign,
+    BinOp::SubAssign,
+    BinOp::MulAssign,
+    BinOp::DivAssign,
+];
+
+/// Persists a [`BinOp`] field.
+pub mod bin_op {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(op: &BinOp, serializer: S) -> Result<S::Ok, S::Error> {
+        op.as_str().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BinOp, D::Error> {
+        let op = String::deserialize(deserializer)?;
+        BIN_OPS
+            .into_iter()
+            .find(|known| known.as_str() == op)
+            .ok_or_else(|| de::Error::custom(format_args!("unknown binary operator {op}")))
+    }
+}
+
+/// The paths of the items in the standard library by the hashes of the items.
+static LIBRARY_PATHS: LazyLock<HashMap<u128, Vec<EcoString>>> = LazyLock::new(|| {
+    let mut paths = HashMap::new();
+    let mut scopes: VecDeque<(Vec<EcoString>, &'static Scope)> = VecDeque::from([
+        (vec!["global".into()], LIBRARY.global.scope()),
+        (vec!["math".into()], LIBRARY.math.scope()),
+    ]);
+
+    // Visits the scopes breadth-first, so an item gets its shortest path.
+    while let Some((base, scope)) = scopes.pop_front() {
+        for (name, bind) in scope.iter() {
+            let value = bind.read();
+            let key = hash128(value);
+            if paths.contains_key(&key) {
+                continue;
+            }
+
+            let mut path = base.clone();
+            path.push(name.clone());
+            let scope = match value {
+                Value::Func(func) => func.scope(),
+                Value::Type(ty) => Some(ty.scope()),
+                Value::Module(module) => Some(module.scope()),
+                _ => None,
+            };
+            if let Some(scope) = scope {
+                scopes.push_back((path.clone(), scope));
+            }
+            paths.insert(key, path);
+        }
+    }
+
+    paths
+});
+
+/// Gets the item in the standard library by its path.
+fn library_item(path: &[EcoString]) -> Option<&'static Value> {
+    let (root, path) = path.split_first()?;
+    let mut scope = match root.as_str() {
+        "global" => LIBRARY.global.scope(),
+        "math" => LIBRARY.math.scope(),
+        _ => return None,
+    };
+
+    let (name, path) = path.split_last()?;
+    for name in path {
+        scope = match scope.get(name)?.read() {
+            Value::Func(func) => func.scope()?,
+            Value::Type(ty) => ty.scope(),
+            Value::Module(module) => module.scope(),
+            _ => return None,
+        };
+    }
+    Some(scope.get(name)?.read())
+}
+
+/// Gets the path of an item in the standard library.
+fn library_path(value: &Value) -> Option<&'static [EcoString]> {
+    let path = LIBRARY_PATHS.get(&hash128(value))?;
+    (library_item(path)? == value).then_some(path.as_slice())
+}
+
+fn library_path_or_err<E: ser::Error>(value: &Value) -> Result<&'static [EcoString], E> {
+    library_path(value).ok_or_else(|| {
+        let ty = value.ty().short_name();
+        E::custom(format_args!(
+            "a value of type {ty} is not in the standard library"
+        ))
+    })
+}
+
+fn library_item_or_err<E: de::Error>(path: &[EcoString]) -> Result<&'static Value, E> {
+    library_item(path).ok_or_else(|| {
+        let path = path.join(".");
+        E::custom(format_args!("{path} is not in the standard library"))
+    })
+}
+
+/// A value, whose floats are written by their bits so that they are restored
+/// exactly.
+#[derive(Serialize, Deserialize)]
+enum ValueRepr {
+    None,
+    Auto,
+    Bool(bool),
+    Int(i64),
+    Float(u64),
+    Str(EcoString),
+    Length(u64, u64),
+    Angle(u64),
+    Ratio(u64),
+    Relative(u64, u64, u64),
+    Fraction(u64),
+    Library(Vec<EcoString>),
+}
+
+/// Persists a [`Value`] field.
+pub mod value {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match value {
+            Value::None => ValueRepr::None,
+            Value::Auto => ValueRepr::Auto,
+            Value::Bool(v) => ValueRepr::Bool(*v),
+            Value::Int(v) => ValueRepr::Int(*v),
+            Value::Float(v) => ValueRepr::Float(v.to_bits()),
+            Value::Str(v) => ValueRepr::Str(v.as_str().into()),
+            Value::Length(v) => ValueRepr::Length(v.abs.to_pt().to_bits(), v.em.get().to_bits()),
+            Value::Angle(v) => ValueRepr::Angle(v.to_rad().to_bits()),
+            Value::Ratio(v) => ValueRepr::Ratio(v.get().to_bits()),
+            Value::Relative(v) => ValueRepr::Relative(
+                v.rel.get().to_bits(),
+                v.abs.abs.to_pt().to_bits(),
+                v.abs.em.get().to_bits(),
+            ),
+            Value::Fraction(v) => ValueRepr::Fraction(v.get().to_bits()),
+            value => ValueRepr::Library(library_path_or_err(value)?.to_vec()),
+        };
+        repr.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        let length = |abs: u64, em: u64| Length {
+            abs: Abs::pt(f64::from_bits(abs)),
+            em: Em::new(f64::from_bits(em)),
+        };
+
+        Ok(match ValueRepr::deserialize(deserializer)? {
+            ValueRepr::None => Value::None,
+            ValueRepr::Auto => Value::Auto,
+            ValueRepr::Bool(v) => Value::Bool(v),
+            ValueRepr::Int(v) => Value::Int(v),
+            ValueRepr::Float(v) => Value::Float(f64::from_bits(v)),
+            ValueRepr::Str(v) => Value::Str(v.into()),
+            ValueRepr::Length(abs, em) => Value::Length(length(abs, em)),
+            ValueRepr::Angle(v) => Value::Angle(Angle::rad(f64::from_bits(v))),
+            ValueRepr::Ratio(v) => Value::Ratio(Ratio::new(f64::from_bits(v))),
+            ValueRepr::Relative(rel, abs, em) => {
+                Value::Relative(Rel::new(Ratio::new(f64::from_bits(rel)), length(abs, em)))
+            }
+            ValueRepr::Fraction(v) => Value::Fraction(Fr::new(f64::from_bits(v))),
+            ValueRepr::Library(path) => library_item_or_err(&path)?.clone(),
+        })
+    }
+}
+
+/// Persists an [`Element`] field by its path in the standard library.
+pub mod element {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(elem: &Element, serializer: S) -> Result<S::Ok, S::Error> {
+        library_path_or_err(&Value::Func(Func::from(*elem)))?.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Element, D::Error> {
+        let path = Vec::<EcoString>::deserialize(deserializer)?;
+        let elem = match library_item_or_err(&path)? {
+            Value::Func(func) => func.element(),
+            _ => None,
+        };
+        elem.ok_or_else(|| de::Error::custom("not an element"))
+    }
+}
+
+/// Persists an optional [`Element`] field.
+pub mod element_opt {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Repr(#[serde(with = "element")] Element);
+
+    pub fn serialize<S: Serializer>(
+        elem: &Option<Element>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        elem.map(Repr).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Element>, D::Error> {
+        Ok(Option::<Repr>::deserialize(deserializer)?.map(|Repr(elem)| elem))
+    }
+}
+
+/// Persists a Typst [`Type`] field by its path in the standard library.
+pub mod typst_type {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(ty: &Type, serializer: S) -> Result<S::Ok, S::Error> {
+        library_path_or_err(&Value::Type(*ty))?.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Type, D::Error> {
+        let path = Vec::<EcoString>::deserialize(deserializer)?;
+        match library_item_or_err(&path)? {
+            Value::Type(ty) => Ok(*ty),
+            _ => Err(de::Error::custom("not a type")),
+        }
+    }
+}
+
+/// Persists a map whose keys are not strings as a sequence of pairs.
+pub mod pairs {
+    use super::*;
+
+    pub fn serialize<'a, M, K, V, S>(map: &'a M, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+        K: Serialize + 'a,
+        V: Serialize + 'a,
+        S: Serializer,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, M, K, V, D>(deserializer: D) -> Result<M, D::Error>
+    where
+        M: FromIterator<(K, V)>,
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// The expression information of a file to persist. The source and the
+/// closures are not persisted, as the source is read again and the closures
+/// are only reused by the next revisions of the file.
+#[derive(Serialize, Deserialize)]
+pub struct PersistedExprInfo {
+    resolves: Vec<(PortableSpan, Interned<RefExpr>)>,
+    module_docstring: DocString,
+    docstrings: Vec<(DeclExpr, DocString)>,
+    exprs: Vec<(PortableSpan, Expr)>,
+    imports: Vec<(PortableFileId, Vec<(Interned<str>, Expr)>)>,
+    exports: Vec<(Interned<str>, Expr)>,
+    root: Expr,
+}
+
+impl PersistedExprInfo {
+    /// Creates the persisted form of the expression information.
+    pub fn new(ei: &ExprInfoRepr) -> Self {
+        Self {
+            resolves: (ei.resolves.iter())
+                .map(|(span, expr)| (PortableSpan(*span), expr.clone()))
+                .collect(),
+            module_docstring: ei.module_docstring.as_ref().clone(),
+            docstrings: (ei.docstrings.iter())
+                .map(|(decl, docs)| (decl.clone(), docs.as_ref().clone()))
+                .collect(),
+            exprs: (ei.exprs.iter())
+                .map(|(span, expr)| (PortableSpan(*span), expr.clone()))
+                .collect(),
+            imports: (ei.imports.iter())
+                .map(|(fid, imported)| (PortableFileId(*fid), scope_items(imported)))
+                .collect(),
+            exports: scope_items(&ei.exports),
+            root: ei.root.clone(),
+        }
+    }
+
+    /// Restores the expression information of the source at the revision.
+    pub fn restore(self, source: Source, revision: usize) -> ExprInfo {
+        let scope = |items: Vec<(Interned<str>, Expr)>| {
+            let mut scope = LexicalScope::default();
+            for (name, expr) in items {
+                scope.insert_mut(name, expr);
+            }
+            Arc::new(LazyHash::new(scope))
+        };
+
+        ExprInfo::new(ExprInfoRepr {
+            fid: source.id(),
+            revision,
+            source,
+            resolves: (self.resolves.into_iter())
+                .map(|(span, expr)| (span.0, expr))
+                .collect(),
+            module_docstring: Arc::new(self.module_docstring),
+            docstrings: (self.docstrings.into_iter())
+                .map(|(decl, docs)| (decl, Arc::new(docs)))
+                .collect(),
+            exprs: (self.exprs.into_iter())
+                .map(|(span, expr)| (span.0, expr))
+                .collect(),
+            imports: (self.imports.into_iter())
+                .map(|(fid, imported)| (fid.0, scope(imported)))
+                .collect(),
+            exports: scope(self.exports),
+            root: self.root,
+            closures: FxHashMap::default(),
+        })
+    }
+}
+
+fn scope_items(scope: &LexicalScope) -> Vec<(Interned<str>, Expr)> {
+    let items = scope.iter();
+    items
+        .map(|(name, expr)| (name.clone(), expr.clone()))
+        .collect()
+}
+
+/// The type information of a file to persist. The local bindings and the
+/// canonicalization caches are not persisted, as they are only used during
+/// the type checking.
+#[derive(Serialize, Deserialize)]
+pub struct PersistedTypeInfo {
+    valid: bool,
+    exports: Vec<(Interned<str>, Ty)>,
+    vars: Vec<(DeclExpr, TypeVarBounds)>,
+    var_docs: Vec<(DeclExpr, UntypedDefDocs)>,
+    mapping: Vec<(PortableSpan, Vec<Ty>)>,
+}
+
+impl PersistedTypeInfo {
+    /// Creates the persisted form of the type information.
+    pub fn new(ti: &TypeInfo) -> Self {
+        Self {
+            valid: ti.valid,
+            exports: (ti.exports.iter())
+                .map(|(name, ty)| (name.clone(), ty.clone()))
+                .collect(),
+            vars: (ti.vars.iter())
+                .map(|(decl, bounds)| (decl.clone(), bounds.clone()))
+                .collect(),
+            var_docs: (ti.var_docs.iter())
+                .map(|(decl, docs)| (decl.clone(), docs.as_ref().clone()))
+                .collect(),
+            mapping: (ti.mapping.iter())
+                .map(|(span, types)| (PortableSpan(*span), types.iter().cloned().collect()))
+                .collect(),
+        }
+    }
+
+    /// Restores the type information of the file at the revision.
+    pub fn restore(self, fid: TypstFileId, revision: usize) -> TypeInfo {
+        let mut info = TypeInfo::default();
+        info.valid = self.valid;
+        info.fid = Some(fid);
+        info.revision = revision;
+        info.exports = self.exports.into_iter().collect();
+        info.vars = self.vars.into_iter().collect();
+        info.var_docs = (self.var_docs.into_iter())
+            .map(|(decl, docs)| (decl, Arc::new(docs)))
+            .collect();
+        info.mapping = (self.mapping.into_iter())
+            .map(|(span, types)| (span.0, types.into_iter().collect()))
+            .collect();
+        info
+    }
+}
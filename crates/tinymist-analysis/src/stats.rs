@@ -2,6 +2,8 @@
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use serde::{Deserialize, Serialize};
+
 /// Statistics about the allocation
 
 #[derive(Debug, Default)]
@@ -10,6 +12,9 @@ pub struct AllocStats {
     pub allocated: AtomicUsize,
     /// The number of dropped objects.
     pub dropped: AtomicUsize,
+    /// The number of the dropped objects that are freed by the garbage
+    /// collection.
+    pub collected: AtomicUsize,
 }
 
 impl AllocStats {
@@ -23,19 +28,33 @@ impl AllocStats {
         self.dropped.fetch_add(1, Ordering::Relaxed);
     }
 
-    /// Report the statistics of the allocation.
-    pub fn report() -> String {
+    /// Collects the statistics of the interners, sorted by the number of the
+    /// alive objects.
+    pub fn collect() -> Vec<InternerStats> {
         let maps = crate::adt::interner::MAPS.lock().clone();
         let mut data = Vec::new();
         for (name, sz, map) in maps {
-            let allocated = map.allocated.load(std::sync::atomic::Ordering::Relaxed);
-            let dropped = map.dropped.load(std::sync::atomic::Ordering::Relaxed);
+            let allocated = map.allocated.load(Ordering::Relaxed);
+            let dropped = map.dropped.load(Ordering::Relaxed);
+            let collected = map.collected.load(Ordering::Relaxed);
             let alive = allocated.saturating_sub(dropped);
-            data.push((name, sz * alive, allocated, dropped, alive));
+            data.push(InternerStats {
+                name: name.to_owned(),
+                alive,
+                allocated,
+                dropped,
+                collected,
+                size: sz * alive,
+            });
         }
 
-        // sort by total
-        data.sort_by(|x, y| y.4.cmp(&x.4));
+        data.sort_by(|x, y| y.alive.cmp(&x.alive));
+        data
+    }
+
+    /// Report the statistics of the allocation.
+    pub fn report() -> String {
+        let data = Self::collect();
 
         // format to html
 
@@ -50,15 +69,16 @@ table.alloc-stats tr:nth-child(odd) { background-color: rgba(242, 242, 242, 0.8)
     table.alloc-stats tr:nth-child(odd) { background-color: rgba(50, 50, 50, 0.8); }
 }
 </style>
-<table class="alloc-stats"><tr><th class="name-column">Name</th><th>Alive</th><th>Allocated</th><th>Dropped</th><th>Size</th></tr>"#);
+<table class="alloc-stats"><tr><th class="name-column">Name</th><th>Alive</th><th>Allocated</th><th>Dropped</th><th>Collected</th><th>Size</th></tr>"#);
 
-        for (name, sz, allocated, dropped, alive) in data {
+        for stats in data {
             html.push_str("<tr>");
-            html.push_str(&format!(r#"<td class="name-column">{name}</td>"#));
-            html.push_str(&format!("<td>{alive}</td>"));
-            html.push_str(&format!("<td>{allocated}</td>"));
-            html.push_str(&format!("<td>{dropped}</td>"));
-            html.push_str(&format!("<td>{}</td>", human_size(sz)));
+            html.push_str(&format!(r#"<td class="name-column">{}</td>"#, stats.name));
+            html.push_str(&format!("<td>{}</td>", stats.alive));
+            html.push_str(&format!("<td>{}</td>", stats.allocated));
+            html.push_str(&format!("<td>{}</td>", stats.dropped));
+            html.push_str(&format!("<td>{}</td>", stats.collected));
+            html.push_str(&format!("<td>{}</td>", human_size(stats.size)));
             html.push_str("</tr>");
         }
         html.push_str("</table>");
@@ -68,6 +88,24 @@ table.alloc-stats tr:nth-child(odd) { background-color: rgba(242, 242, 242, 0.8)
     }
 }
 
+/// Statistics about an interner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternerStats {
+    /// The type of the interned objects.
+    pub name: String,
+    /// The number of the alive objects.
+    pub alive: usize,
+    /// The number of the allocated objects.
+    pub allocated: usize,
+    /// The number of the dropped objects.
+    pub dropped: usize,
+    /// The number of the dropped objects freed by the garbage collection.
+    pub collected: usize,
+    /// The estimated size of the alive objects in bytes.
+    pub size: usize,
+}
+
 fn human_size(size: usize) -> String {
     let units = ["B", "KB", "MB", "GB", "TB"];
     let mut unit = 0;
@@ -23,7 +23,7 @@ use crate::{
     ty::{InsTy, Interned, SelectTy, Ty, TypeVar},
 };
 
-use super::{ExprDescriber, ExprPrinter};
+use super::{ExprDescriber, ExprPrinter, InterpretMode};
 
 #[derive(Debug, Clone, Hash)]
 pub struct ExprInfo(Arc<LazyHash<ExprInfoRepr>>);
@@ -54,6 +54,31 @@ pub struct ExprInfoRepr {
     pub imports: FxHashMap<TypstFileId, Arc<LazyHash<LexicalScope>>>,
     pub exports: Arc<LazyHash<LexicalScope>>,
     pub root: Expr,
+    /// The analyzed closures by their spans, which are reused in the next
+    /// revision of the file.
+    pub closures: FxHashMap<Span, Arc<ClosureInfo>>,
+}
+
+/// The expressions of a closure, which are reused in the next revision of the
+/// file if neither the syntax of the closure nor the names it captures change.
+#[derive(Debug)]
+pub struct ClosureInfo {
+    /// The syntax of the closure, including the spans.
+    pub node: SyntaxNode,
+    /// The names looked up outside of the closure and their results.
+    pub captures: Vec<(Interned<str>, InterpretMode, Option<Expr>, Option<Ty>)>,
+    /// The parameters of the closure.
+    pub params: PatternSig,
+    /// The body of the closure.
+    pub body: Expr,
+    /// The resolved references in the closure.
+    pub resolves: Vec<(Span, Interned<RefExpr>)>,
+    /// The expressions in the closure.
+    pub exprs: Vec<(Span, Expr)>,
+    /// The docstrings of the declarations in the closure.
+    pub docstrings: Vec<(DeclExpr, Arc<DocString>)>,
+    /// The closures nested in the closure.
+    pub closures: Vec<(Span, Arc<ClosureInfo>)>,
 }
 
 impl std::hash::Hash for ExprInfoRepr {
@@ -133,7 +158,7 @@ impl ExprInfoRepr {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Expr {
     /// A sequence of expressions
     Block(Interned<Vec<Expr>>),
@@ -341,7 +366,7 @@ impl fmt::Display for DefKind {
 
 pub type DeclExpr = Interned<Decl>;
 
-#[derive(Clone, PartialEq, Eq, Hash, DeclEnum)]
+#[derive(Clone, PartialEq, Eq, Hash, DeclEnum, Serialize, Deserialize)]
 pub enum Decl {
     Func(SpannedDecl),
     ImportAlias(SpannedDecl),
@@ -563,6 +588,7 @@ impl Decl {
         match self {
             Self::Module(ModuleDecl { fid, .. }) => Some(*fid),
             Self::BibEntry(NameRangeDecl { at, .. }) => Some(at.0),
+            Self::Docs(DocsDecl { base, .. }) => base.file_id(),
             that => that.span().id(),
         }
     }
@@ -667,9 +693,10 @@ impl From<DeclExpr> for Expr {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SpannedDecl {
     name: Interned<str>,
+    #[serde(with = "crate::persist::span")]
     at: Span,
 }
 
@@ -689,9 +716,10 @@ impl fmt::Debug for SpannedDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NameRangeDecl {
     pub name: Interned<str>,
+    #[serde(with = "crate::persist::name_range")]
     pub at: Box<(TypstFileId, Range<usize>, Option<Range<usize>>)>,
 }
 
@@ -711,9 +739,10 @@ impl fmt::Debug for NameRangeDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ModuleDecl {
     pub name: Interned<str>,
+    #[serde(with = "crate::persist::file_id")]
     pub fid: TypstFileId,
 }
 
@@ -733,13 +762,18 @@ impl fmt::Debug for ModuleDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DocsDecl {
     base: Interned<Decl>,
     var: Interned<TypeVar>,
 }
 
 impl DocsDecl {
+    /// The declaration documented by the docs.
+    pub fn base(&self) -> &Interned<Decl> {
+        &self.base
+    }
+
     fn name(&self) -> &Interned<str> {
         Interned::empty()
     }
@@ -755,8 +789,8 @@ impl fmt::Debug for DocsDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub struct SpanDecl(Span);
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpanDecl(#[serde(with = "crate::persist::span")] Span);
 
 impl SpanDecl {
     fn name(&self) -> &Interned<str> {
@@ -774,8 +808,8 @@ impl fmt::Debug for SpanDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub struct GeneratedDecl(DefId);
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GeneratedDecl(#[serde(with = "crate::persist::def_id")] DefId);
 
 impl GeneratedDecl {
     fn name(&self) -> &Interned<str> {
@@ -798,7 +832,7 @@ pub type BinExpr = BinInst<Expr>;
 
 pub type ExportMap = BTreeMap<Interned<str>, Expr>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ArgExpr {
     Pos(Expr),
     Named(Box<(DeclExpr, Expr)>),
@@ -806,7 +840,7 @@ pub enum ArgExpr {
     Spread(Expr),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Pattern {
     Expr(Expr),
     Simple(Interned<Decl>),
@@ -827,7 +861,7 @@ impl Pattern {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PatternSig {
     pub pos: EcoVec<Interned<Pattern>>,
     pub named: EcoVec<(DeclExpr, Interned<Pattern>)>,
@@ -839,12 +873,12 @@ impl Pattern {}
 
 impl_internable!(Decl,);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ContentSeqExpr {
     pub ty: Ty,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RefExpr {
     pub decl: DeclExpr,
     pub step: Option<Expr>,
@@ -852,17 +886,18 @@ pub struct RefExpr {
     pub term: Option<Ty>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ContentRefExpr {
     pub ident: DeclExpr,
     pub of: Option<DeclExpr>,
     pub body: Option<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SelectExpr {
     pub lhs: Expr,
     pub key: DeclExpr,
+    #[serde(with = "crate::persist::span")]
     pub span: Span,
 }
 
@@ -876,9 +911,10 @@ impl SelectExpr {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ArgsExpr {
     pub args: Vec<ArgExpr>,
+    #[serde(with = "crate::persist::span")]
     pub span: Span,
 }
 
@@ -888,71 +924,74 @@ impl ArgsExpr {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ElementExpr {
+    #[serde(with = "crate::persist::element")]
     pub elem: Element,
     pub content: EcoVec<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ApplyExpr {
     pub callee: Expr,
     pub args: Expr,
+    #[serde(with = "crate::persist::span")]
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FuncExpr {
     pub decl: DeclExpr,
     pub params: PatternSig,
     pub body: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LetExpr {
     /// Span of the pattern
+    #[serde(with = "crate::persist::span")]
     pub span: Span,
     pub pattern: Interned<Pattern>,
     pub body: Option<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ShowExpr {
     pub selector: Option<Expr>,
     pub edit: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SetExpr {
     pub target: Expr,
     pub args: Expr,
     pub cond: Option<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ImportExpr {
     pub decl: Interned<RefExpr>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct IncludeExpr {
     pub source: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct IfExpr {
     pub cond: Expr,
     pub then: Expr,
     pub else_: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct WhileExpr {
     pub cond: Expr,
     pub body: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ForExpr {
     pub pattern: Interned<Pattern>,
     pub iter: Expr,
@@ -960,7 +999,7 @@ pub struct ForExpr {
 }
 
 /// The kind of unary operation
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnaryOp {
     /// The (arithmetic) positive operation
     /// `+t`
@@ -992,7 +1031,7 @@ pub enum UnaryOp {
 }
 
 /// A unary operation type
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UnInst<T> {
     /// The operand of the unary operation
     pub lhs: T,
@@ -1034,11 +1073,12 @@ impl<T> UnInst<T> {
 pub type BinaryOp = ast::BinOp;
 
 /// A binary operation type
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BinInst<T> {
     /// The operands of the binary operation
     pub operands: (T, T),
     /// The kind of the binary operation
+    #[serde(with = "crate::persist::bin_op")]
     pub op: BinaryOp,
 }
 
@@ -4,6 +4,7 @@ use std::sync::LazyLock;
 
 use ecow::{eco_format, EcoString};
 use regex::RegexSet;
+use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
 use typst::foundations::{CastInfo, Regex};
 use typst::layout::Ratio;
@@ -16,7 +17,7 @@ use typst::{
 use crate::syntax::Decl;
 use crate::ty::*;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, EnumIter)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, EnumIter, Serialize, Deserialize)]
 pub enum PathPreference {
     Source { allow_package: bool },
     Wasm,
@@ -180,7 +181,7 @@ pub enum BuiltinSig<'a> {
 }
 
 /// A package identifier.
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct PackageId {
     pub namespace: StrRef,
     pub name: StrRef,
@@ -206,7 +207,7 @@ impl TryFrom<FileId> for PackageId {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum BuiltinTy {
     Clause,
     Undef,
@@ -242,13 +243,13 @@ pub enum BuiltinTy {
     Tag(Box<(StrRef, Option<Interned<PackageId>>)>),
 
     /// A value having a specific type.
-    Type(typst::foundations::Type),
+    Type(#[serde(with = "crate::persist::typst_type")] typst::foundations::Type),
     /// A value of some type.
-    TypeType(typst::foundations::Type),
+    TypeType(#[serde(with = "crate::persist::typst_type")] typst::foundations::Type),
     /// A content having a specific element type.
-    Content(Option<typst::foundations::Element>),
+    Content(#[serde(with = "crate::persist::element_opt")] Option<typst::foundations::Element>),
     /// A value of some element type.
-    Element(typst::foundations::Element),
+    Element(#[serde(with = "crate::persist::element")] typst::foundations::Element),
 
     Module(Interned<Decl>),
     Path(PathPreference),
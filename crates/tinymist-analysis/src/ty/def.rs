@@ -34,7 +34,7 @@ pub(crate) type TyRef = Interned<Ty>;
 pub(crate) type StrRef = Interned<str>;
 
 /// All possible types in tinymist
-#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Ty {
     // Simple Types
     /// A top type, whose negation is bottom type.
@@ -313,11 +313,13 @@ impl fmt::Debug for TypeSigParam<'_> {
 
 /// The syntax source (definition) of a type node
 /// todo: whether we should store them in the type node
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeSource {
     /// A name node with span
+    #[serde(with = "crate::persist::name_node")]
     pub name_node: SyntaxNode,
     /// A lazy evaluated name
+    #[serde(skip)]
     pub name_repr: OnceLock<StrRef>,
     /// Attached documentation
     pub doc: StrRef,
@@ -330,6 +332,16 @@ impl Hash for TypeSource {
     }
 }
 
+/// The lazily evaluated name is derived from the name node, so it is not
+/// compared.
+impl PartialEq for TypeSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.name_node == other.name_node && self.doc == other.doc
+    }
+}
+
+impl Eq for TypeSource {}
+
 impl TypeSource {
     /// Get name of the type node
     pub fn name(&self) -> StrRef {
@@ -347,7 +359,7 @@ impl TypeSource {
 }
 
 /// An ordered list of names
-#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct NameBone {
     /// The names in the bone
     pub names: Box<[StrRef]>,
@@ -437,7 +449,7 @@ impl DynTypeBounds {
 /// A frozen type variable (bounds of some type in program)
 /// `t :> t1 | ... | tn <: f1 & ... & fn`
 /// `  lbs------------- ubs-------------`
-#[derive(Hash, Clone, PartialEq, Eq, Default, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, Default, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TypeBounds {
     /// The lower bounds
     pub lbs: Vec<Ty>,
@@ -504,9 +516,10 @@ pub trait TypeInterfaceExt: TypeInterface {
 impl<T: TypeInterface> TypeInterfaceExt for T {}
 
 /// An instance of a typst type
-#[derive(Debug, Hash, Clone, PartialEq)]
+#[derive(Debug, Hash, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InsTy {
     /// The value of the instance
+    #[serde(with = "crate::persist::value")]
     pub val: Value,
     /// The syntax source of the instance
     pub syntax: Option<Interned<TypeSource>>,
@@ -637,7 +650,7 @@ impl From<&ParamInfo> for ParamAttrs {
 }
 
 /// Describes a parameter type.
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ParamTy {
     /// The name of the parameter.
     pub name: StrRef,
@@ -670,7 +683,7 @@ impl ParamTy {
 }
 
 /// A type variable
-#[derive(Hash, Clone, PartialEq, Eq)]
+#[derive(Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TypeVar {
     /// The name of the type variable
     pub name: StrRef,
@@ -718,7 +731,7 @@ impl TypeVar {
 }
 
 /// A record type
-#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RecordTy {
     /// The names of the fields
     pub names: Interned<NameBone>,
@@ -775,7 +788,7 @@ impl fmt::Debug for RecordTy {
 }
 
 /// A typst function type
-#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SigTy {
     /// The input types of the function
     pub inputs: Interned<Vec<Ty>>,
@@ -1067,7 +1080,7 @@ pub type ArgsTy = SigTy;
 pub type PatternTy = SigTy;
 
 /// A type with partially applied arguments
-#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SigWithTy {
     /// The signature of the function
     pub sig: TyRef,
@@ -1089,7 +1102,7 @@ impl fmt::Debug for SigWithTy {
 }
 
 /// A field selection type
-#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SelectTy {
     /// The type to select from
     pub ty: TyRef,
@@ -1111,7 +1124,7 @@ impl fmt::Debug for SelectTy {
 }
 
 /// A unary operation type
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TypeUnary {
     /// The operand of the unary operation
     pub lhs: Ty,
@@ -1151,11 +1164,12 @@ impl TypeUnary {
 pub type BinaryOp = ast::BinOp;
 
 /// A binary operation type
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TypeBinary {
     /// The operands of the binary operation
     pub operands: (Ty, Ty),
     /// The kind of the binary operation
+    #[serde(with = "crate::persist::bin_op")]
     pub op: BinaryOp,
 }
 
@@ -1192,7 +1206,7 @@ impl TypeBinary {
 
 /// A conditional type
 /// `if t1 then t2 else t3`
-#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct IfTy {
     /// The condition
     pub cond: TyRef,
@@ -1388,6 +1402,29 @@ impl TypeVarBounds {
     }
 }
 
+/// The bounds are persisted frozen, as the type checking is done.
+impl Serialize for TypeVarBounds {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let weak = matches!(self.bounds, FlowVarKind::Weak(_));
+        let bounds = self.bounds.bounds().read().freeze();
+        (&self.var, weak, bounds).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TypeVarBounds {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (var, weak, bounds) =
+            <(Interned<TypeVar>, bool, TypeBounds)>::deserialize(deserializer)?;
+        let bounds = Arc::new(RwLock::new(DynTypeBounds::from(bounds)));
+        let bounds = if weak {
+            FlowVarKind::Weak(bounds)
+        } else {
+            FlowVarKind::Strong(bounds)
+        };
+        Ok(Self { var, bounds })
+    }
+}
+
 /// A type variable bounds
 #[derive(Clone)]
 pub enum FlowVarKind {
@@ -171,7 +171,7 @@ fn resolve_known(head: &str, base: &str) -> Option<String> {
     })
 }
 
-static LIBRARY: LazyLock<Library> = LazyLock::new(Library::default);
+pub(crate) static LIBRARY: LazyLock<Library> = LazyLock::new(Library::default);
 
 /// Extract a module from another module.
 #[track_caller]
@@ -1,12 +1,14 @@
 //! Tinymist coverage support for Typst.
 use core::fmt;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
 use std::sync::{Arc, LazyLock};
 
 use parking_lot::Mutex;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use tinymist_analysis::location::PositionEncoding;
 use tinymist_std::hash::FxHashMap;
+use tinymist_std::xml::escape_xml;
 use tinymist_world::debug_loc::LspRange;
 use tinymist_world::vfs::{FileId, WorkspaceResolver};
 use tinymist_world::{CompilerFeat, CompilerWorld};
@@ -74,6 +76,93 @@ impl CoverageResult {
         serde_json::to_value(result).unwrap()
     }
 
+    /// Converts the coverage result to the LCOV tracefile format, e.g. for
+    /// uploading to Codecov.
+    ///
+    /// The paths are relative to the root of the workspace, and the package
+    /// files are not included.
+    pub fn to_lcov<F: CompilerFeat>(&self, w: &CompilerWorld<F>) -> String {
+        write_lcov(&self.line_coverage(w))
+    }
+
+    /// Converts the coverage result to the Cobertura XML format, e.g. for
+    /// rendering in the merge requests of GitLab.
+    ///
+    /// The paths are relative to the root of the workspace, and the package
+    /// files are not included.
+    pub fn to_cobertura<F: CompilerFeat>(&self, w: &CompilerWorld<F>) -> String {
+        let root = w.entry_state().root();
+        let root = root.as_ref().and_then(|root| root.to_str()).unwrap_or(".");
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        write_cobertura(root, timestamp, &self.line_coverage(w))
+    }
+
+    /// Collects the hits of the lines in the files, keyed by the 0-based line
+    /// numbers, where the hits of a line are the maximum hits of the regions
+    /// starting at the line.
+    pub fn line_hits<F: CompilerFeat>(
+        &self,
+        w: &CompilerWorld<F>,
+    ) -> Vec<(FileId, BTreeMap<usize, u32>)> {
+        let mut files = vec![];
+        for (file_id, region) in &self.regions {
+            let (Some(meta), Ok(source)) = (self.meta.get(file_id), w.source(*file_id)) else {
+                continue;
+            };
+
+            let mut lines = BTreeMap::new();
+            let hits = region.hits.lock();
+            for (idx, (span, _kind)) in meta.meta.iter().enumerate() {
+                let Some(line) = source
+                    .range(*span)
+                    .and_then(|r| source.byte_to_line(r.start))
+                else {
+                    continue;
+                };
+                let hit = lines.entry(line).or_insert(0);
+                *hit = (*hit).max(hits[idx] as u32);
+            }
+
+            files.push((*file_id, lines));
+        }
+
+        files
+    }
+
+    /// Collects the hits of the lines in the workspace files, keyed by the
+    /// 1-based line numbers.
+    fn line_coverage<F: CompilerFeat>(&self, w: &CompilerWorld<F>) -> Vec<FileLineCoverage> {
+        let root = w.entry_state().root();
+
+        let mut files = vec![];
+        for (file_id, lines) in self.line_hits(w) {
+            if WorkspaceResolver::is_package_file(file_id) {
+                continue;
+            }
+            let Ok(path) = w.path_for_id(file_id) else {
+                continue;
+            };
+            let path = path.as_path();
+            let path = root
+                .as_ref()
+                .and_then(|root| path.strip_prefix(root).ok())
+                .unwrap_or(path);
+
+            files.push(FileLineCoverage {
+                path: path.to_string_lossy().replace('\\', "/"),
+                lines: lines
+                    .into_iter()
+                    .map(|(line, hits)| (line + 1, hits))
+                    .collect(),
+            });
+        }
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    }
+
     /// Summarizes the coverage result.
     pub fn summarize<'a>(&'a self, short: bool, prefix: &'a str) -> SummarizedCoverage<'a> {
         SummarizedCoverage {
@@ -152,6 +241,93 @@ impl fmt::Display for SummarizedCoverage<'_> {
     }
 }
 
+/// The hits of the lines in a file.
+struct FileLineCoverage {
+    /// The path of the file, relative to the root of the workspace.
+    path: String,
+    /// The hits of the lines, keyed by the 1-based line numbers.
+    lines: BTreeMap<usize, u32>,
+}
+
+impl FileLineCoverage {
+    fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits > 0).count()
+    }
+}
+
+fn write_lcov(files: &[FileLineCoverage]) -> String {
+    let mut out = String::new();
+    for file in files {
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", file.path);
+        for (line, hits) in &file.lines {
+            let _ = writeln!(out, "DA:{line},{hits}");
+        }
+        let _ = writeln!(out, "LF:{}", file.lines.len());
+        let _ = writeln!(out, "LH:{}", file.lines_hit());
+        let _ = writeln!(out, "end_of_record");
+    }
+    out
+}
+
+fn write_cobertura(root: &str, timestamp: u128, files: &[FileLineCoverage]) -> String {
+    fn rate(covered: usize, valid: usize) -> String {
+        let rate = if valid == 0 {
+            1.0
+        } else {
+            covered as f64 / valid as f64
+        };
+        format!("{rate:.4}")
+    }
+
+    let valid = files.iter().map(|file| file.lines.len()).sum::<usize>();
+    let covered = files.iter().map(FileLineCoverage::lines_hit).sum::<usize>();
+    let line_rate = rate(covered, valid);
+
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<?xml version="1.0" ?>"#);
+    let _ = writeln!(
+        out,
+        r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+    );
+    let _ = writeln!(
+        out,
+        r#"<coverage line-rate="{line_rate}" branch-rate="0" lines-covered="{covered}" lines-valid="{valid}" branches-covered="0" branches-valid="0" complexity="0" version="tinymist" timestamp="{timestamp}">"#
+    );
+    let _ = writeln!(out, "  <sources>");
+    let _ = writeln!(out, "    <source>{}</source>", escape_xml(root));
+    let _ = writeln!(out, "  </sources>");
+    let _ = writeln!(out, "  <packages>");
+    let _ = writeln!(
+        out,
+        r#"    <package name="." line-rate="{line_rate}" branch-rate="0" complexity="0">"#
+    );
+    let _ = writeln!(out, "      <classes>");
+    for file in files {
+        let path = escape_xml(&file.path);
+        let line_rate = rate(file.lines_hit(), file.lines.len());
+        let _ = writeln!(
+            out,
+            r#"        <class name="{path}" filename="{path}" line-rate="{line_rate}" branch-rate="0" complexity="0">"#
+        );
+        let _ = writeln!(out, "          <methods/>");
+        let _ = writeln!(out, "          <lines>");
+        for (line, hits) in &file.lines {
+            let _ = writeln!(
+                out,
+                r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+            );
+        }
+        let _ = writeln!(out, "          </lines>");
+        let _ = writeln!(out, "        </class>");
+    }
+    let _ = writeln!(out, "      </classes>");
+    let _ = writeln!(out, "    </package>");
+    let _ = writeln!(out, "  </packages>");
+    let _ = writeln!(out, "</coverage>");
+    out
+}
+
 /// The coverage result in the format of the VSCode coverage data.
 pub type VscodeCoverage = HashMap<String, Vec<VscodeFileCoverageDetail>>;
 
@@ -500,6 +676,37 @@ mod tests {
         insta::assert_snapshot!(instrumented, @"");
     }
 
+    fn line_coverage() -> Vec<FileLineCoverage> {
+        vec![FileLineCoverage {
+            path: "src/a&b.typ".into(),
+            lines: BTreeMap::from([(1, 2), (3, 0)]),
+        }]
+    }
+
+    #[test]
+    fn test_lcov() {
+        insta::assert_snapshot!(write_lcov(&line_coverage()), @r###"
+        TN:
+        SF:src/a&b.typ
+        DA:1,2
+        DA:3,0
+        LF:2
+        LH:1
+        end_of_record
+        "###);
+    }
+
+    #[test]
+    fn test_cobertura() {
+        let xml = write_cobertura("/root", 0, &line_coverage());
+        assert!(xml.contains(r#"lines-covered="1" lines-valid="2""#));
+        assert!(xml.contains("<source>/root</source>"));
+        assert!(xml.contains(
+            r#"<class name="src/a&amp;b.typ" filename="src/a&amp;b.typ" line-rate="0.5000""#
+        ));
+        assert!(xml.contains(r#"<line number="3" hits="0" branch="false"/>"#));
+    }
+
     #[test]
     fn test_instrument_coverage() {
         let source = Source::detached("#let a = 1;");
@@ -8,9 +8,11 @@ use comemo::Tracked;
 use parking_lot::RwLock;
 use tinymist_std::hash::{FxHashMap, FxHashSet};
 use tinymist_world::vfs::FileId;
-use typst::diag::FileResult;
+use typst::diag::{EcoString, FileResult, StrResult};
 use typst::engine::Engine;
-use typst::foundations::{func, Binding, Context, Dict, Scopes};
+use typst::foundations::{
+    func, scope, Binding, Context, Dict, IntoValue, NoneValue, Repr, Scopes, Value,
+};
 use typst::syntax::{Source, Span};
 use typst::World;
 
@@ -19,6 +21,17 @@ use crate::instrument::Instrumenter;
 #[derive(Default)]
 pub struct BreakpointInstr {}
 
+/// Keeps the sources intact, so that the spans of the exceptions point to the
+/// original sources.
+#[derive(Default)]
+pub struct ExceptionInstr {}
+
+impl Instrumenter for ExceptionInstr {
+    fn instrument(&self, source: Source) -> FileResult<Source> {
+        Ok(source)
+    }
+}
+
 /// The kind of breakpoint.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BreakpointKind {
@@ -76,6 +89,30 @@ impl BreakpointKind {
     }
 }
 
+/// The kind of an exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExceptionKind {
+    /// A call of `panic`.
+    Panic,
+    /// A failed call of `assert`, `assert.eq` or `assert.ne`.
+    Assertion,
+}
+
+/// An exception raised by the evaluation.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    /// The kind of the exception.
+    pub kind: ExceptionKind,
+    /// The name of the raising function, e.g. `assert.eq`.
+    pub name: &'static str,
+    /// The span of the call raising the exception.
+    pub span: Span,
+    /// The error message of the exception.
+    pub message: EcoString,
+    /// The arguments of the call.
+    pub args: Vec<(EcoString, Value)>,
+}
+
 #[derive(Default)]
 pub struct BreakpointInfo {
     pub meta: Vec<BreakpointItem>,
@@ -98,6 +135,10 @@ pub trait DebugSessionHandler: Send + Sync {
         span: Span,
         kind: BreakpointKind,
     );
+
+    /// Called when a panic or a failed assertion is raised, before the error
+    /// is propagated.
+    fn on_exception(&self, _exception: &Exception) {}
 }
 
 /// The debug session.
@@ -133,7 +174,7 @@ where
 pub fn set_debug_session(session: Option<DebugSession>) -> bool {
     let mut lock = DEBUG_SESSION.write();
 
-    if session.is_some() {
+    if session.is_some() && lock.is_some() {
         return false;
     }
 
@@ -323,3 +364,126 @@ pub mod breakpoints {
         AfterCompile
     );
 }
+
+pub mod exceptions {
+
+    use super::*;
+
+    /// Reports an exception to the debug session if the call fails.
+    fn report(
+        kind: ExceptionKind,
+        name: &'static str,
+        span: Span,
+        result: StrResult<NoneValue>,
+        args: impl FnOnce() -> Vec<(EcoString, Value)>,
+    ) -> StrResult<NoneValue> {
+        let Err(message) = &result else {
+            return result;
+        };
+
+        if let Some(handler) = with_debug_session(|session| session.handler.clone()) {
+            handler.on_exception(&Exception {
+                kind,
+                name,
+                span,
+                message: message.clone(),
+                args: args(),
+            });
+        }
+        result
+    }
+
+    /// Fails with an error, reporting the values to the debug session.
+    #[func(name = "panic", title = "Panic")]
+    pub fn __exception_panic(
+        span: Span,
+        /// The values to panic with and display to the user.
+        #[variadic]
+        values: Vec<Value>,
+    ) -> StrResult<NoneValue> {
+        let mut msg = EcoString::from("panicked");
+        if !values.is_empty() {
+            msg.push_str(" with: ");
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    msg.push_str(", ");
+                }
+                msg.push_str(&value.repr());
+            }
+        }
+
+        let args = || vec![("values".into(), Value::Array(values.into_iter().collect()))];
+        report(ExceptionKind::Panic, "panic", span, Err(msg), args)
+    }
+
+    /// Ensures that a condition is fulfilled, reporting the failures to the
+    /// debug session.
+    #[func(scope, name = "assert", title = "Assert")]
+    pub fn __exception_assert(
+        span: Span,
+        /// The condition that must be true for the assertion to pass.
+        condition: bool,
+        /// The error message when the assertion fails.
+        #[named]
+        message: Option<EcoString>,
+    ) -> StrResult<NoneValue> {
+        let result = typst::foundations::assert(condition, message.clone());
+        report(ExceptionKind::Assertion, "assert", span, result, || {
+            vec![
+                ("condition".into(), Value::Bool(condition)),
+                ("message".into(), message.into_value()),
+            ]
+        })
+    }
+
+    #[scope]
+    impl __exception_assert {
+        /// Ensures that two values are equal.
+        #[func(title = "Assert Equal")]
+        pub fn eq(
+            span: Span,
+            /// The first value to compare.
+            left: Value,
+            /// The second value to compare.
+            right: Value,
+            /// An optional message to display on error instead of the
+            /// representations of the compared values.
+            #[named]
+            message: Option<EcoString>,
+        ) -> StrResult<NoneValue> {
+            let result =
+                typst::foundations::assert::eq(left.clone(), right.clone(), message.clone());
+            report(ExceptionKind::Assertion, "assert.eq", span, result, || {
+                vec![
+                    ("left".into(), left),
+                    ("right".into(), right),
+                    ("message".into(), message.into_value()),
+                ]
+            })
+        }
+
+        /// Ensures that two values are not equal.
+        #[func(title = "Assert Not Equal")]
+        pub fn ne(
+            span: Span,
+            /// The first value to compare.
+            left: Value,
+            /// The second value to compare.
+            right: Value,
+            /// An optional message to display on error instead of the
+            /// representations of the compared values.
+            #[named]
+            message: Option<EcoString>,
+        ) -> StrResult<NoneValue> {
+            let result =
+                typst::foundations::assert::ne(left.clone(), right.clone(), message.clone());
+            report(ExceptionKind::Assertion, "assert.ne", span, result, || {
+                vec![
+                    ("left".into(), left),
+                    ("right".into(), right),
+                    ("message".into(), message.into_value()),
+                ]
+            })
+        }
+    }
+}
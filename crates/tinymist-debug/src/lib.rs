@@ -3,6 +3,7 @@
 pub use cov::CoverageResult;
 pub use debugger::{
     set_debug_session, with_debug_session, BreakpointKind, DebugSession, DebugSessionHandler,
+    Exception, ExceptionKind,
 };
 
 mod cov;
@@ -10,9 +11,10 @@ mod debugger;
 mod instrument;
 
 use std::ops::DerefMut;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use debugger::BreakpointInstr;
+use debugger::{BreakpointInstr, ExceptionInstr};
 use parking_lot::Mutex;
 use tinymist_std::{error::prelude::*, hash::FxHashMap};
 use tinymist_world::package::PackageSpec;
@@ -77,6 +79,40 @@ pub fn instr_breakpoints<F: CompilerFeat>(base: &CompilerWorld<F>) -> DebuggerWo
     }
 }
 
+/// The world for catching the exceptions.
+pub type ExceptionWorld<'a, F> = InstrumentWorld<'a, F, ExceptionInstr>;
+/// Creates a world reporting the panics and the failed assertions to the
+/// debug session.
+///
+/// Each world has a distinct library, so the evaluations memoized by the
+/// previous worlds are not reused and raise the exceptions again, while the
+/// evaluations memoized by the other worlds are kept.
+pub fn instr_exceptions<F: CompilerFeat>(base: &CompilerWorld<F>) -> ExceptionWorld<'_, F> {
+    static RUNS: AtomicU64 = AtomicU64::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+
+    InstrumentWorld {
+        base,
+        library: exception_library(&base.library, run),
+        instr: ExceptionInstr::default(),
+        instrumented: Mutex::new(FxHashMap::default()),
+    }
+}
+
+fn exception_library(library: &Arc<LazyHash<Library>>, run: u64) -> Arc<LazyHash<Library>> {
+    use debugger::exceptions::*;
+
+    let mut library = library.as_ref().clone();
+
+    let scope = library.global.scope_mut();
+    scope.define_func::<__exception_panic>();
+    scope.define_func::<__exception_assert>();
+    // Keys the memoized evaluations by the run.
+    scope.define("__exception_run", run as i64);
+
+    Arc::new(library)
+}
+
 #[comemo::memoize]
 fn instrument_library(library: &Arc<LazyHash<Library>>) -> Arc<LazyHash<Library>> {
     use debugger::breakpoints::*;
@@ -244,6 +244,34 @@ impl<'w> Linter<'w> {
 
         self.check_variable_font_str(expr)
     }
+
+    fn check_figure_alt(&mut self, args: ast::Args<'_>) -> Option<()> {
+        let body = args.items().find_map(|arg| match arg {
+            ast::Arg::Pos(body) => Some(body),
+            _ => None,
+        })?;
+
+        let mut images = vec![];
+        find_images(body.to_untyped(), &mut images);
+        for image in images {
+            let has_alt = image.args().items().any(|arg| match arg {
+                ast::Arg::Named(arg) => arg.name().as_str() == "alt",
+                _ => false,
+            });
+            if has_alt {
+                continue;
+            }
+
+            let diag =
+                SourceDiagnostic::warning(image.span(), "figure image has no alternative text");
+            let diag = diag.with_hint(
+                "describe the image with `alt: ..` for the screen readers and the tagged PDF",
+            );
+            self.diag.push(diag);
+        }
+
+        Some(())
+    }
     fn check_variable_font_str(&mut self, expr: &SyntaxNode) -> Option<()> {
         if !expr.cast::<ast::Str>()?.get().ends_with("VF") {
             return None;
@@ -260,6 +288,20 @@ impl<'w> Linter<'w> {
     }
 }
 
+/// Finds the calls to `image` in a figure body.
+fn find_images<'a>(node: &'a SyntaxNode, images: &mut Vec<ast::FuncCall<'a>>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        if call.callee().to_untyped().text() == "image" {
+            images.push(call);
+            return;
+        }
+    }
+
+    for child in node.children() {
+        find_images(child, images);
+    }
+}
+
 impl DataFlowVisitor for Linter<'_> {
     fn exprs<'a>(&mut self, exprs: impl DoubleEndedIterator<Item = ast::Expr<'a>>) -> Option<()> {
         for expr in exprs {
@@ -386,6 +428,10 @@ impl DataFlowVisitor for Linter<'_> {
         if expr.callee().to_untyped().text() == "text" {
             self.check_variable_font(expr.args().items());
         }
+        // warn if figure(image("..")) has no alternative text
+        if expr.callee().to_untyped().text() == "figure" {
+            self.check_figure_alt(expr.args());
+        }
         Some(())
     }
 }
@@ -4,7 +4,7 @@ use std::num::NonZeroUsize;
 use std::{path::Path, sync::Arc};
 
 use ecow::EcoString;
-pub use typst::diag::PackageError;
+pub use typst::diag::{PackageError, PackageResult};
 pub use typst::syntax::package::PackageSpec;
 
 mod dummy;
@@ -23,6 +23,11 @@ mod http;
 #[cfg(feature = "http-registry")]
 pub use http::*;
 
+#[cfg(feature = "http-registry")]
+mod integrity;
+#[cfg(feature = "http-registry")]
+pub use integrity::*;
+
 /// The default Typst registry.
 pub const DEFAULT_REGISTRY: &str = "https://packages.typst.org";
 
@@ -56,6 +61,9 @@ pub trait PackageRegistry {
 pub trait Notifier {
     /// Called when a package is being downloaded.
     fn downloading(&self, _spec: &PackageSpec) {}
+    /// Called when a package download is finished, either successfully or
+    /// not.
+    fn downloaded(&self, _spec: &PackageSpec, _result: &PackageResult<()>) {}
 }
 
 /// A dummy notifier that does nothing.
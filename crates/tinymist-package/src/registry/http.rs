@@ -1,9 +1,12 @@
 //! Http registry for tinymist.
 
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 
 use parking_lot::Mutex;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use reqwest::blocking::Response;
 use reqwest::Certificate;
 use tinymist_std::ImmutPath;
@@ -11,7 +14,9 @@ use typst::diag::{eco_format, EcoString, PackageResult, StrResult};
 use typst::syntax::package::{PackageVersion, VersionlessPackageSpec};
 
 use super::{
-    DummyNotifier, Notifier, PackageError, PackageRegistry, PackageSpec, DEFAULT_REGISTRY,
+    quarantine_package_dir, sha256_hex, tree_sha256, verify_package_dir, DummyNotifier, Notifier,
+    PackageError, PackageIntegrity, PackageProvenance, PackageRegistry, PackageSpec,
+    DEFAULT_REGISTRY,
 };
 
 /// The http package registry for typst.ts.
@@ -67,6 +72,12 @@ impl HttpRegistry {
         }
     }
 
+    /// Sets the notifier to use for download progress updates.
+    pub fn with_notifier(mut self, notifier: Arc<Mutex<dyn Notifier + Send>>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
     /// Get `typst-kit` implementing package storage
     pub fn storage(&self) -> &PackageStorage {
         self.storage.get_or_init(|| {
@@ -97,7 +108,12 @@ impl HttpRegistry {
 
     /// Set list of packages for testing.
     pub fn test_package_list(&self, f: impl FnOnce() -> Vec<(PackageSpec, Option<EcoString>)>) {
-        self.storage().index.get_or_init(f);
+        self.storage().index.get_or_init(|| {
+            Arc::new(PackageIndex {
+                packages: f(),
+                ..PackageIndex::default()
+            })
+        });
     }
 }
 
@@ -115,6 +131,24 @@ impl PackageRegistry for HttpRegistry {
 /// paths.
 pub const DEFAULT_PACKAGES_SUBDIR: &str = "typst/packages";
 
+/// The shared result of an in-flight package download.
+type DownloadSlot = Arc<OnceLock<PackageResult<()>>>;
+
+/// The package index of the preview namespace.
+#[derive(Default)]
+struct PackageIndex {
+    /// The packages and their descriptions.
+    packages: Vec<(PackageSpec, Option<EcoString>)>,
+    /// The archive checksums published by the index, if any.
+    checksums: HashMap<PackageSpec, EcoString>,
+}
+
+/// The package index fetched in this session. It is shared by all the package
+/// storages, as the registries are created again whenever their configuration
+/// changes. A failed fetch is not shared, so that it is retried by the next
+/// storage.
+static SESSION_INDEX: OnceLock<Arc<PackageIndex>> = OnceLock::new();
+
 /// Holds information about where packages should be stored and downloads them
 /// on demand, if possible.
 pub struct PackageStorage {
@@ -125,7 +159,12 @@ pub struct PackageStorage {
     /// The downloader used for fetching the index and packages.
     cert_path: Option<ImmutPath>,
     /// The cached index of the preview namespace.
-    index: OnceLock<Vec<(PackageSpec, Option<EcoString>)>>,
+    index: OnceLock<Arc<PackageIndex>>,
+    /// The cached packages that are verified in this session.
+    verified: Mutex<HashSet<PackageSpec>>,
+    /// The downloads in progress, which are shared by concurrent requests of
+    /// the same package.
+    downloads: Mutex<HashMap<PackageSpec, DownloadSlot>>,
     notifier: Arc<Mutex<dyn Notifier + Send>>,
 }
 
@@ -145,6 +184,8 @@ impl PackageStorage {
             cert_path,
             notifier,
             index: OnceLock::new(),
+            verified: Mutex::default(),
+            downloads: Mutex::default(),
         }
     }
 
@@ -173,12 +214,13 @@ impl PackageStorage {
         if let Some(cache_dir) = &self.package_cache_path {
             let dir = cache_dir.join(&subdir);
             if dir.exists() {
+                self.check_integrity(spec, &dir)?;
                 return Ok(dir.into());
             }
 
             // Download from network if it doesn't exist yet.
             if spec.namespace == "preview" {
-                self.download_package(spec, &dir)?;
+                self.download_package_once(spec, &dir)?;
                 if dir.exists() {
                     return Ok(dir.into());
                 }
@@ -188,6 +230,107 @@ impl PackageStorage {
         Err(PackageError::NotFound(spec.clone()))
     }
 
+    /// Makes several packages available in the on-disk cache, downloading the
+    /// missing ones in parallel.
+    pub fn prepare_packages(&self, specs: &[PackageSpec]) -> Vec<PackageResult<ImmutPath>> {
+        specs
+            .par_iter()
+            .map(|spec| self.prepare_package(spec))
+            .collect()
+    }
+
+    /// Checks that a cached package is not modified since it was downloaded.
+    /// Each package is only checked once per session.
+    fn check_integrity(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+        if self.verified.lock().contains(spec) {
+            return Ok(());
+        }
+
+        if let PackageIntegrity::Tampered { expected, actual } = verify_package_dir(dir)? {
+            return Err(PackageError::Other(Some(eco_format!(
+                "package {spec} is modified since it was downloaded \
+                 (expected tree hash {expected}, found {actual}), \
+                 run `tinymist package verify --quarantine` to remove it from the cache"
+            ))));
+        }
+
+        self.verified.lock().insert(spec.clone());
+        Ok(())
+    }
+
+    /// Verifies all the downloaded packages in the cache.
+    pub fn verify_cache(&self) -> Vec<(PackageSpec, PathBuf, PackageResult<PackageIntegrity>)> {
+        let Some(cache_dir) = &self.package_cache_path else {
+            return vec![];
+        };
+
+        let read_dir = |dir: &Path| {
+            std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .filter(|entry| entry.path().is_dir())
+                .collect::<Vec<_>>()
+        };
+
+        let mut packages = vec![];
+        for namespace in read_dir(cache_dir) {
+            for name in read_dir(&namespace.path()) {
+                for version in read_dir(&name.path()) {
+                    let Ok(parsed) = version.file_name().to_string_lossy().parse() else {
+                        continue;
+                    };
+                    let spec = PackageSpec {
+                        namespace: namespace.file_name().to_string_lossy().into(),
+                        name: name.file_name().to_string_lossy().into(),
+                        version: parsed,
+                    };
+                    packages.push((spec, version.path()));
+                }
+            }
+        }
+
+        packages
+            .into_par_iter()
+            .map(|(spec, dir)| {
+                let integrity = verify_package_dir(&dir);
+                (spec, dir, integrity)
+            })
+            .collect()
+    }
+
+    /// Moves a cached package into the quarantine directory of the cache.
+    pub fn quarantine_package(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<PathBuf> {
+        let cache_dir = self
+            .package_cache_path
+            .as_ref()
+            .ok_or_else(|| PackageError::Other(Some("no package cache directory".into())))?;
+        self.verified.lock().remove(spec);
+        quarantine_package_dir(cache_dir, spec, dir)
+    }
+
+    /// Downloads a package, or waits for the download of the same package
+    /// that is already in progress.
+    fn download_package_once(&self, spec: &PackageSpec, package_dir: &Path) -> PackageResult<()> {
+        let slot = self
+            .downloads
+            .lock()
+            .entry(spec.clone())
+            .or_default()
+            .clone();
+        let result = slot
+            .get_or_init(|| self.download_package(spec, package_dir))
+            .clone();
+
+        let mut downloads = self.downloads.lock();
+        if downloads.get(spec).is_some_and(|it| Arc::ptr_eq(it, &slot)) {
+            downloads.remove(spec);
+        }
+
+        result
+    }
+
     /// Try to determine the latest version of a package.
     pub fn determine_latest_version(
         &self,
@@ -221,55 +364,81 @@ impl PackageStorage {
 
     /// Get the cached package index without network access.
     pub fn cached_index(&self) -> Option<&[(PackageSpec, Option<EcoString>)]> {
-        self.index.get().map(Vec::as_slice)
+        let index = self.index.get().or_else(|| {
+            let index = SESSION_INDEX.get()?;
+            Some(self.index.get_or_init(|| index.clone()))
+        })?;
+        Some(index.packages.as_slice())
     }
 
-    /// Download the package index. The result of this is cached for efficiency.
+    /// Download the package index. The result of this is cached for the
+    /// session, so the index is fetched at most once unless the fetch fails.
     pub fn download_index(&self) -> &[(PackageSpec, Option<EcoString>)] {
+        &self.index().packages
+    }
+
+    /// Gets the package index, which is fetched at most once per session.
+    fn index(&self) -> &PackageIndex {
         self.index.get_or_init(|| {
-            let url = format!("{DEFAULT_REGISTRY}/preview/index.json");
-
-            threaded_http(&url, self.cert_path.as_deref(), |resp| {
-                let reader = match resp.and_then(|r| r.error_for_status()) {
-                    Ok(response) => response,
-                    Err(err) => {
-                        // todo: silent error
-                        log::error!("Failed to fetch package index: {err} from {url}");
-                        return vec![];
-                    }
-                };
+            if let Some(index) = SESSION_INDEX.get() {
+                return index.clone();
+            }
+
+            let index = Arc::new(self.fetch_index());
+            if !index.packages.is_empty() {
+                let _ = SESSION_INDEX.set(index.clone());
+            }
+            index
+        })
+    }
+
+    /// Fetches the package index, which is empty if the fetch fails.
+    fn fetch_index(&self) -> PackageIndex {
+        let url = format!("{DEFAULT_REGISTRY}/preview/index.json");
 
-                #[derive(serde::Deserialize)]
-                struct RemotePackageIndex {
-                    name: EcoString,
-                    version: PackageVersion,
-                    description: Option<EcoString>,
+        threaded_http(&url, self.cert_path.as_deref(), |resp| {
+            let reader = match resp.and_then(|r| r.error_for_status()) {
+                Ok(response) => response,
+                Err(err) => {
+                    // todo: silent error
+                    log::error!("Failed to fetch package index: {err} from {url}");
+                    return PackageIndex::default();
                 }
+            };
 
-                let indices: Vec<RemotePackageIndex> = match serde_json::from_reader(reader) {
-                    Ok(index) => index,
-                    Err(err) => {
-                        log::error!("Failed to parse package index: {err} from {url}");
-                        return vec![];
-                    }
-                };
+            #[derive(serde::Deserialize)]
+            struct RemotePackageIndex {
+                name: EcoString,
+                version: PackageVersion,
+                description: Option<EcoString>,
+                /// The SHA-256 hash of the package archive.
+                #[serde(default)]
+                sha256: Option<EcoString>,
+            }
 
-                indices
-                    .into_iter()
-                    .map(|index| {
-                        (
-                            PackageSpec {
-                                namespace: "preview".into(),
-                                name: index.name,
-                                version: index.version,
-                            },
-                            index.description,
-                        )
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
+            let indices: Vec<RemotePackageIndex> = match serde_json::from_reader(reader) {
+                Ok(index) => index,
+                Err(err) => {
+                    log::error!("Failed to parse package index: {err} from {url}");
+                    return PackageIndex::default();
+                }
+            };
+
+            let mut index = PackageIndex::default();
+            for remote in indices {
+                let spec = PackageSpec {
+                    namespace: "preview".into(),
+                    name: remote.name,
+                    version: remote.version,
+                };
+                if let Some(sha256) = remote.sha256 {
+                    index.checksums.insert(spec.clone(), sha256);
+                }
+                index.packages.push((spec, remote.description));
+            }
+            index
         })
+        .unwrap_or_default()
     }
 
     /// Download a package over the network.
@@ -284,9 +453,17 @@ impl PackageStorage {
             spec.name, spec.version
         );
 
+        // The archive is unverified if the index publishes no checksum of it.
+        let expected = self.index().checksums.get(spec).cloned();
+        if expected.is_none() {
+            log::warn!(
+                "the package index publishes no checksum of {spec}, its archive is unverified"
+            );
+        }
+
         self.notifier.lock().downloading(spec);
-        threaded_http(&url, self.cert_path.as_deref(), |resp| {
-            let reader = match resp.and_then(|r| r.error_for_status()) {
+        let result = threaded_http(&url, self.cert_path.as_deref(), |resp| {
+            let mut reader = match resp.and_then(|r| r.error_for_status()) {
                 Ok(response) => response,
                 Err(err) if matches!(err.status().map(|s| s.as_u16()), Some(404)) => {
                     return Err(PackageError::NotFound(spec.clone()))
@@ -294,15 +471,53 @@ impl PackageStorage {
                 Err(err) => return Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
             };
 
-            let decompressed = flate2::read::GzDecoder::new(reader);
+            let mut archive = vec![];
+            reader
+                .read_to_end(&mut archive)
+                .map_err(|err| PackageError::NetworkFailed(Some(eco_format!("{err}"))))?;
+
+            let archive_sha256 = sha256_hex(&archive);
+            if let Some(expected) = expected
+                .as_ref()
+                .filter(|it| !it.eq_ignore_ascii_case(&archive_sha256))
+            {
+                return Err(PackageError::MalformedArchive(Some(eco_format!(
+                    "checksum mismatch for {spec}: \
+                     expected {expected}, found {archive_sha256}"
+                ))));
+            }
+
+            let decompressed = flate2::read::GzDecoder::new(archive.as_slice());
             tar::Archive::new(decompressed)
                 .unpack(package_dir)
                 .map_err(|err| {
                     std::fs::remove_dir_all(package_dir).ok();
                     PackageError::MalformedArchive(Some(eco_format!("{err}")))
-                })
+                })?;
+
+            let downloaded_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|it| it.as_secs())
+                .unwrap_or_default();
+            let provenance = PackageProvenance {
+                source: url.as_str().into(),
+                archive_sha256,
+                tree_sha256: tree_sha256(package_dir)?,
+                archive_verified: expected.is_some(),
+                downloaded_at,
+            };
+            if let Err(err) = provenance.write(package_dir) {
+                log::warn!("failed to record provenance of {spec}: {err}");
+            }
+            self.verified.lock().insert(spec.clone());
+
+            Ok(())
         })
-        .ok_or_else(|| PackageError::Other(Some(eco_format!("cannot spawn http thread"))))?
+        .ok_or_else(|| PackageError::Other(Some(eco_format!("cannot spawn http thread"))))
+        .and_then(|result| result);
+
+        self.notifier.lock().downloaded(spec, &result);
+        result
     }
 }
 
@@ -0,0 +1,212 @@
+//! Integrity checks for downloaded packages.
+
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use typst::diag::PackageResult;
+
+use super::{PackageError, PackageSpec};
+
+/// The provenance of a downloaded package, stored next to the package
+/// directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageProvenance {
+    /// The url from which the package archive was downloaded.
+    pub source: EcoString,
+    /// The SHA-256 hash of the downloaded archive.
+    pub archive_sha256: EcoString,
+    /// The SHA-256 hash of the files extracted from the archive.
+    pub tree_sha256: EcoString,
+    /// Whether the archive matched the checksum published by the package
+    /// index. The index may publish no checksum of a package, in which case
+    /// the archive is unverified.
+    #[serde(default)]
+    pub archive_verified: bool,
+    /// The time the package was downloaded, in seconds since the unix epoch.
+    pub downloaded_at: u64,
+}
+
+/// The result of verifying a package in the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageIntegrity {
+    /// The package is unchanged since it was downloaded.
+    Verified,
+    /// The package is unchanged since it was downloaded, but its archive was
+    /// not checked against a checksum published by the package index.
+    Unverified,
+    /// The package has no provenance record, e.g. it was downloaded by
+    /// another tool.
+    Unrecorded,
+    /// The package is modified since it was downloaded.
+    Tampered {
+        /// The hash recorded when the package was downloaded.
+        expected: EcoString,
+        /// The hash of the package files now.
+        actual: EcoString,
+    },
+}
+
+impl PackageProvenance {
+    /// The path of the provenance record of a package directory, which is a
+    /// hidden sibling file so that it doesn't change the package contents.
+    pub fn path_of(package_dir: &Path) -> Option<PathBuf> {
+        let version = package_dir.file_name()?.to_str()?;
+        Some(package_dir.with_file_name(format!(".{version}.provenance.json")))
+    }
+
+    /// Reads the provenance record of a package directory.
+    pub fn read(package_dir: &Path) -> Option<Self> {
+        let content = std::fs::read(Self::path_of(package_dir)?).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Writes the provenance record of a package directory.
+    pub fn write(&self, package_dir: &Path) -> PackageResult<()> {
+        let path = Self::path_of(package_dir)
+            .ok_or_else(|| PackageError::Other(Some("invalid package directory".into())))?;
+        let content = serde_json::to_vec_pretty(self).map_err(other_err)?;
+        std::fs::write(path, content).map_err(other_err)
+    }
+
+    /// Checks whether the package directory still matches the record.
+    pub fn verify(&self, package_dir: &Path) -> PackageResult<PackageIntegrity> {
+        let actual = tree_sha256(package_dir)?;
+        if actual != self.tree_sha256 {
+            Ok(PackageIntegrity::Tampered {
+                expected: self.tree_sha256.clone(),
+                actual,
+            })
+        } else if self.archive_verified {
+            Ok(PackageIntegrity::Verified)
+        } else {
+            Ok(PackageIntegrity::Unverified)
+        }
+    }
+}
+
+/// Verifies a package directory against its provenance record.
+pub fn verify_package_dir(package_dir: &Path) -> PackageResult<PackageIntegrity> {
+    match PackageProvenance::read(package_dir) {
+        Some(provenance) => provenance.verify(package_dir),
+        None => Ok(PackageIntegrity::Unrecorded),
+    }
+}
+
+/// Moves a package directory out of the cache, together with its provenance
+/// record, and returns the new location.
+pub fn quarantine_package_dir(
+    cache_dir: &Path,
+    spec: &PackageSpec,
+    package_dir: &Path,
+) -> PackageResult<PathBuf> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or_default();
+    let target = cache_dir
+        .join(".quarantine")
+        .join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(format!("{}-{now}", spec.version));
+
+    std::fs::create_dir_all(&target).map_err(other_err)?;
+    std::fs::rename(package_dir, target.join("package")).map_err(other_err)?;
+    if let Some(record) = PackageProvenance::path_of(package_dir) {
+        if record.exists() {
+            std::fs::rename(record, target.join("provenance.json")).map_err(other_err)?;
+        }
+    }
+
+    Ok(target)
+}
+
+/// Computes the SHA-256 hash of some bytes as a hex string.
+pub fn sha256_hex(data: &[u8]) -> EcoString {
+    hex::encode(Sha256::digest(data)).into()
+}
+
+/// Computes the SHA-256 hash of all files in a directory, which is
+/// independent of the order the files are listed by the file system.
+pub fn tree_sha256(dir: &Path) -> PackageResult<EcoString> {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    collect(dir, &mut files).map_err(other_err)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let rel = file.strip_prefix(dir).unwrap_or(&file);
+        let content = std::fs::read(&file).map_err(other_err)?;
+        hasher.update(rel.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+
+    Ok(hex::encode(hasher.finalize()).into())
+}
+
+fn other_err(err: impl std::fmt::Display) -> PackageError {
+    PackageError::Other(Some(eco_format!("{err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_hash_detects_changes() {
+        let dir = std::env::temp_dir().join(format!("tinymist-integrity-{}", std::process::id()));
+        let package_dir = dir.join("0.1.0");
+        std::fs::create_dir_all(package_dir.join("src")).unwrap();
+        std::fs::write(package_dir.join("typst.toml"), "[package]").unwrap();
+        std::fs::write(package_dir.join("src/lib.typ"), "#let x = 1").unwrap();
+
+        let provenance = PackageProvenance {
+            source: "test".into(),
+            archive_sha256: sha256_hex(b""),
+            tree_sha256: tree_sha256(&package_dir).unwrap(),
+            archive_verified: true,
+            downloaded_at: 0,
+        };
+        provenance.write(&package_dir).unwrap();
+        assert_eq!(
+            verify_package_dir(&package_dir).unwrap(),
+            PackageIntegrity::Verified
+        );
+
+        // The archive without a published checksum is reported as unverified.
+        let unverified = PackageProvenance {
+            archive_verified: false,
+            ..provenance.clone()
+        };
+        unverified.write(&package_dir).unwrap();
+        assert_eq!(
+            verify_package_dir(&package_dir).unwrap(),
+            PackageIntegrity::Unverified
+        );
+        provenance.write(&package_dir).unwrap();
+
+        std::fs::write(package_dir.join("src/lib.typ"), "#let x = 2").unwrap();
+        assert!(matches!(
+            verify_package_dir(&package_dir).unwrap(),
+            PackageIntegrity::Tampered { .. }
+        ));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
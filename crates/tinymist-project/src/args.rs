@@ -129,8 +129,8 @@ pub struct TaskCompileArgs {
     #[arg(long = "when")]
     pub when: Option<TaskWhen>,
 
-    /// Path to output file (PDF, PNG, SVG, or HTML). Use `-` to write output to
-    /// stdout.
+    /// Path to output file (PDF, PNG, SVG, HTML, Markdown, DOCX, or plain
+    /// text). Use `-` to write output to stdout.
     ///
     /// For output formats emitting one file per page (PNG & SVG), a page number
     /// template must be present if the source document renders to multiple
@@ -165,6 +165,22 @@ pub struct TaskCompileArgs {
     #[clap(flatten)]
     pub png: PngExportArgs,
 
+    /// The argument to export to SVG.
+    #[clap(flatten)]
+    pub svg: SvgExportArgs,
+
+    /// The argument to export to HTML.
+    #[clap(flatten)]
+    pub html: HtmlExportArgs,
+
+    /// The argument to export to Markdown.
+    #[clap(flatten)]
+    pub md: MarkdownExportArgs,
+
+    /// The argument to export to DOCX.
+    #[clap(flatten)]
+    pub docx: DocxExportArgs,
+
     /// The output format.
     #[clap(skip)]
     pub output_format: OnceLock<Result<OutputFormat>>,
@@ -186,6 +202,9 @@ impl TaskCompileArgs {
                 Some(ext) if ext.eq_ignore_ascii_case("png") => OutputFormat::Png,
                 Some(ext) if ext.eq_ignore_ascii_case("svg") => OutputFormat::Svg,
                 Some(ext) if ext.eq_ignore_ascii_case("html") => OutputFormat::Html,
+                Some(ext) if ext.eq_ignore_ascii_case("md") => OutputFormat::Md,
+                Some(ext) if ext.eq_ignore_ascii_case("docx") => OutputFormat::Docx,
+                Some(ext) if ext.eq_ignore_ascii_case("txt") => OutputFormat::Txt,
                 _ => bail!(
                     "could not infer output format for path {output:?}.\n\
                          consider providing the format manually with `--format/-f`",
@@ -211,19 +230,54 @@ impl TaskCompileArgs {
             transform: transforms,
         };
 
+        // The file name of the output path is the page template if it has a page
+        // number.
+        let output_template = || {
+            let output = Path::new(self.output.as_deref()?);
+            let file_name = output.file_name()?.to_str()?;
+            has_page_number(file_name).then(|| file_name.to_owned())
+        };
+
         let config = match output_format {
-            OutputFormat::Pdf => ProjectTask::ExportPdf(ExportPdfTask {
-                export,
-                pdf_standards: self.pdf.pdf_standard.clone(),
-                creation_timestamp: None,
-            }),
+            OutputFormat::Pdf => {
+                let task = ExportPdfTask {
+                    export,
+                    pdf_standards: self.pdf.pdf_standard.clone(),
+                    creation_timestamp: None,
+                    image_max_dpi: self.pdf.image_max_dpi,
+                    tagged: self.pdf.tagged,
+                };
+                task.check_standards()?;
+                ProjectTask::ExportPdf(task)
+            }
             OutputFormat::Png => ProjectTask::ExportPng(ExportPngTask {
                 export,
                 ppi: self.png.ppi.try_into().unwrap(),
                 fill: None,
+                page_template: self.png.page_template.clone().or_else(output_template),
+            }),
+            OutputFormat::Svg => ProjectTask::ExportSvg(ExportSvgTask {
+                export,
+                page_template: self.svg.page_template.clone().or_else(output_template),
+            }),
+            OutputFormat::Html => ProjectTask::ExportHtml(ExportHtmlTask {
+                export,
+                post_export: self.html.post_export.clone(),
+                heading_anchors: self.html.heading_anchors,
+            }),
+            OutputFormat::Md => ProjectTask::ExportMd(ExportMarkdownTask {
+                processor: self.md.processor.clone(),
+                assets_path: self.md.assets_path.clone(),
+                math: self.md.math,
+                figure: self.md.figure,
+                raw: self.md.raw,
+                export,
+            }),
+            OutputFormat::Docx => ProjectTask::ExportDocx(ExportDocxTask {
+                processor: self.docx.processor.clone(),
+                export,
             }),
-            OutputFormat::Svg => ProjectTask::ExportSvg(ExportSvgTask { export }),
-            OutputFormat::Html => ProjectTask::ExportSvg(ExportSvgTask { export }),
+            OutputFormat::Txt => ProjectTask::ExportText(ExportTextTask { export }),
         };
 
         Ok(ApplyProjectTask {
@@ -241,12 +295,88 @@ pub struct PdfExportArgs {
     /// conformance with.
     #[arg(long = "pdf-standard", value_delimiter = ',')]
     pub pdf_standard: Vec<PdfStandard>,
+
+    /// Downscales and recompresses the raster images exceeding the resolution
+    /// (in dots per inch) before embedding them. The original files are kept.
+    #[arg(long = "image-max-dpi")]
+    pub image_max_dpi: Option<u32>,
+
+    /// Exports a tagged PDF for accessibility, checking that the document has
+    /// a title and that its images have alternative texts.
+    #[arg(long = "pdf-tagged")]
+    pub tagged: bool,
+}
+
+/// Declare arguments for exporting a document to HTML.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct HtmlExportArgs {
+    /// The shell command to run after the HTML is exported. It runs in the
+    /// directory of the output, whose path is passed by the `TINYMIST_OUTPUT`
+    /// environment variable.
+    #[arg(long = "post-export")]
+    pub post_export: Option<String>,
+
+    /// How the anchors of the headings are made.
+    #[arg(long = "heading-anchors", default_value = "github")]
+    pub heading_anchors: HeadingAnchors,
+}
+
+/// Declare arguments for exporting a document to Markdown.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct MarkdownExportArgs {
+    /// The package to process the markup, whose `article` function wraps the
+    /// content.
+    #[arg(long = "md-processor", value_name = "PACKAGE_SPEC")]
+    pub processor: Option<String>,
+
+    /// The directory to write the rendered assets to, e.g. equations, which
+    /// are embedded into the Markdown if not provided.
+    #[arg(long = "md-assets-path", value_name = "DIR")]
+    pub assets_path: Option<std::path::PathBuf>,
+
+    /// How the math equations are lowered.
+    #[arg(long = "md-math", default_value = "svg")]
+    pub math: MathLowering,
+
+    /// How the figures are lowered.
+    #[arg(long = "md-figure", default_value = "html")]
+    pub figure: FigureLowering,
+
+    /// How the raw blocks are lowered.
+    #[arg(long = "md-raw", default_value = "fenced")]
+    pub raw: RawLowering,
+}
+
+/// Declare arguments for exporting a document to DOCX.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct DocxExportArgs {
+    /// The package to process the markup, whose `article` function wraps the
+    /// content.
+    #[arg(long = "docx-processor", value_name = "PACKAGE_SPEC")]
+    pub processor: Option<String>,
 }
 
 /// Declare arguments for exporting a document to PNG.
 #[derive(Debug, Clone, clap::Parser)]
 pub struct PngExportArgs {
     /// The PPI (pixels per inch) to use for PNG export.
-    #[arg(long = "ppi", default_value_t = 144.0)]
+    #[arg(long = "ppi", visible_alias = "dpi", default_value_t = 144.0)]
     pub ppi: f32,
+
+    /// The template of the file names to export one PNG file per page, e.g.
+    /// `page-{0p}.png`. See the output path for the placeholders. Defaults to
+    /// the file name of the output path if it has a page number.
+    #[arg(long = "png-page-template", value_name = "TEMPLATE")]
+    pub page_template: Option<String>,
+}
+
+/// Declare arguments for exporting a document to SVG.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct SvgExportArgs {
+    /// The template of the file names to export one SVG file per page, e.g.
+    /// `figure-{p}.svg`. See the output path for the placeholders. Defaults to
+    /// the file name of the output path if it has a page number. Otherwise,
+    /// the selected pages are merged into a single SVG.
+    #[arg(long = "svg-page-template", value_name = "TEMPLATE")]
+    pub page_template: Option<String>,
 }
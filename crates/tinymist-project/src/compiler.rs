@@ -488,13 +488,14 @@ impl<F: CompilerFeat + Send + Sync + 'static, Ext: Default + 'static> ProjectCom
             Self::create_project(id.clone(), verse, self.export_target, self.handler.clone());
         proj.reason.see(reason_by_entry_change());
 
-        self.remove_dedicates(&id);
+        self.remove_dedicate(&id);
         self.dedicates.push(proj);
 
         Ok(id)
     }
 
-    fn remove_dedicates(&mut self, id: &ProjectInsId) {
+    /// Removes a dedicate project and its diagnostics.
+    pub fn remove_dedicate(&mut self, id: &ProjectInsId) {
         let proj = self.dedicates.iter().position(|e| e.id == *id);
         if let Some(idx) = proj {
             // Resets the handle state, e.g. notified revision
@@ -549,7 +550,7 @@ impl<F: CompilerFeat + Send + Sync + 'static, Ext: Default + 'static> ProjectCom
                 }
             }
             Interrupt::Settle(id) => {
-                self.remove_dedicates(&id);
+                self.remove_dedicate(&id);
             }
             Interrupt::ChangeTask(id, change) => {
                 let proj = Self::find_project(&mut self.primary, &mut self.dedicates, &id);
@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
@@ -33,7 +34,7 @@ pub struct EntryResolver {
     pub project_resolution: ProjectResolutionKind,
     /// Specifies the root path of the project manually.
     pub root_path: Option<ImmutPath>,
-    /// The workspace roots from initialization.
+    /// The workspace roots, i.e. the workspace folders of the client.
     pub roots: Vec<ImmutPath>,
     /// Default entry path from the configuration.
     pub entry: Option<ImmutPath>,
@@ -49,10 +50,8 @@ impl EntryResolver {
         }
 
         if let Some(entry) = entry {
-            for root in self.roots.iter() {
-                if entry.starts_with(root) {
-                    return Some(root.clone());
-                }
+            if let Some(root) = self.folder(entry) {
+                return Some(root);
             }
 
             if !self.roots.is_empty() {
@@ -103,6 +102,16 @@ impl EntryResolver {
         None
     }
 
+    /// Finds the workspace folder containing a path. The innermost folder is
+    /// selected if the folders are nested.
+    pub fn folder(&self, path: &Path) -> Option<ImmutPath> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .cloned()
+    }
+
     /// Resolves the entry state.
     pub fn resolve(&self, entry: Option<ImmutPath>) -> EntryState {
         let root_dir = self.root(entry.as_ref());
@@ -270,6 +279,31 @@ mod entry_tests {
         }
     }
 
+    #[test]
+    fn test_entry_resolution_nested_roots() {
+        let root_path = Path::new(ROOT);
+        let nested_path = root_path.join("nested");
+
+        let entry = EntryResolver {
+            roots: vec![
+                ImmutPath::from(root_path),
+                ImmutPath::from(nested_path.as_path()),
+            ],
+            ..Default::default()
+        };
+
+        let entry = entry.resolve(Some(nested_path.join("main.typ").into()));
+
+        assert_eq!(entry.root(), Some(ImmutPath::from(nested_path.as_path())));
+        assert_eq!(
+            entry.main(),
+            Some(WorkspaceResolver::workspace_file(
+                entry.root().as_ref(),
+                VirtualPath::new("main.typ")
+            ))
+        );
+    }
+
     #[test]
     fn test_entry_resolution_default_multi_root() {
         let root_path = Path::new(ROOT);
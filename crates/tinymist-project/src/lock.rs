@@ -2,17 +2,23 @@
 
 use std::cmp::Ordering;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
 use std::{path::Path, sync::Arc};
 
-use ecow::{eco_vec, EcoVec};
+use ecow::{eco_format, eco_vec, EcoVec};
 use tinymist_std::error::prelude::*;
 use tinymist_std::path::unix_slash;
 use tinymist_std::{bail, ImmutPath};
-use typst::diag::EcoString;
+use typst::diag::{EcoString, SourceDiagnostic};
+use typst::syntax::package::PackageSpec;
+use typst::syntax::{ast, SyntaxKind, SyntaxNode};
 use typst::World;
 
 use crate::model::{ApplyProjectTask, Id, ProjectInput, ProjectRoute, ResourcePath};
-use crate::{LockFile, LockFileCompat, LspWorld, ProjectPathMaterial, LOCK_VERSION};
+use crate::package::registry::{tree_sha256, PackageRegistry};
+use crate::{
+    LockFile, LockFileCompat, LspWorld, ProjectPackage, ProjectPathMaterial, LOCK_VERSION,
+};
 
 pub const LOCK_FILENAME: &str = "tinymist.lock";
 
@@ -52,8 +58,85 @@ impl LockFile {
         self.route.push(route);
     }
 
+    /// Returns the pin of a package at exactly the version of the specifier.
+    pub fn get_package(&self, spec: &PackageSpec) -> Option<&ProjectPackage> {
+        self.package
+            .iter()
+            .find(|p| p.parse().is_some_and(|pinned| pinned == *spec))
+    }
+
+    /// Returns the pins of a package at any version.
+    pub fn pinned_versions<'a>(
+        &'a self,
+        spec: &'a PackageSpec,
+    ) -> impl Iterator<Item = PackageSpec> + 'a {
+        self.package
+            .iter()
+            .filter_map(|p| p.parse())
+            .filter(|pinned| pinned.namespace == spec.namespace && pinned.name == spec.name)
+    }
+
+    pub fn replace_package(&mut self, package: ProjectPackage) {
+        let index = self.package.iter().position(|p| p.spec == package.spec);
+        if let Some(index) = index {
+            self.package[index] = package;
+        } else {
+            self.package.push(package);
+        }
+    }
+
+    /// Checks the package imports of the files reached by a compilation
+    /// against the pinned packages, reporting the imports whose versions are
+    /// not pinned while another version of the package is. The imports inside
+    /// packages are not checked, since they cannot be edited.
+    pub fn check_imports(&self, world: &LspWorld) -> EcoVec<SourceDiagnostic> {
+        let mut diagnostics = EcoVec::new();
+        if self.package.is_empty() {
+            return diagnostics;
+        }
+
+        for fid in world.depended_files() {
+            let path = fid.vpath().as_rooted_path();
+            let is_typ = path.extension().is_some_and(|ext| ext == "typ");
+            if fid.package().is_some() || !is_typ {
+                continue;
+            }
+            let Ok(source) = world.source(fid) else {
+                continue;
+            };
+
+            for import in package_imports(source.root()) {
+                let Ok(spec) = PackageSpec::from_str(&import.get()) else {
+                    continue;
+                };
+                if self.get_package(&spec).is_some() {
+                    continue;
+                }
+                let pinned = self.pinned_versions(&spec).collect::<Vec<_>>();
+                if pinned.is_empty() {
+                    continue;
+                }
+
+                let versions = pinned.iter().map(|p| p.version.to_string());
+                let versions = versions.collect::<Vec<_>>().join(", ");
+                diagnostics.push(
+                    SourceDiagnostic::warning(
+                        import.span(),
+                        eco_format!(
+                            "package {spec} disagrees with {LOCK_FILENAME}, which pins version {versions}"
+                        ),
+                    )
+                    .with_hint("run `tinymist package lock` to update the pinned packages"),
+                );
+            }
+        }
+
+        diagnostics
+    }
+
     pub fn sort(&mut self) {
         self.document.sort_by(|a, b| a.id.cmp(&b.id));
+        self.package.sort_by(|a, b| a.spec.cmp(&b.spec));
         self.task
             .sort_by(|a, b| a.doc_id().cmp(b.doc_id()).then_with(|| a.id().cmp(b.id())));
         // the route's order is important, so we don't sort them.
@@ -104,6 +187,15 @@ impl LockFile {
             }
         }
 
+        let package = content.get("package");
+        if let Some(package) = package {
+            for package in package.as_array().unwrap() {
+                out.push('\n');
+                out.push_str("[[package]]\n");
+                emit_package(package, &mut out);
+            }
+        }
+
         return out;
 
         fn emit_document(input: &toml::Value, out: &mut String) {
@@ -129,6 +221,11 @@ impl LockFile {
             let table = route.as_table().unwrap();
             out.push_str(&table.to_string());
         }
+
+        fn emit_package(package: &toml::Value, out: &mut String) {
+            let table = package.as_table().unwrap();
+            out.push_str(&table.to_string());
+        }
     }
 
     pub fn update(cwd: &Path, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
@@ -149,6 +246,7 @@ impl LockFile {
                 document: vec![],
                 task: vec![],
                 route: eco_vec![],
+                package: vec![],
             }
         } else {
             let old_state = toml::from_str::<LockFileCompat>(old_data)
@@ -217,6 +315,65 @@ impl LockFile {
     }
 }
 
+impl ProjectPackage {
+    /// Pins a package used by a world, hashing the files of the package.
+    pub fn pin(world: &LspWorld, spec: &PackageSpec) -> Self {
+        let checksum = world
+            .registry
+            .resolve(spec)
+            .ok()
+            .and_then(|dir| tree_sha256(&dir).ok());
+
+        Self {
+            spec: eco_format!("{spec}"),
+            checksum,
+        }
+    }
+
+    /// Parses the specifier of the pinned package.
+    pub fn parse(&self) -> Option<PackageSpec> {
+        PackageSpec::from_str(&self.spec).ok()
+    }
+}
+
+/// Collects the packages used by a compilation, including the ones imported
+/// by other packages.
+pub fn used_packages(world: &LspWorld) -> Vec<PackageSpec> {
+    let mut packages = world
+        .depended_files()
+        .into_iter()
+        .filter_map(|fid| fid.package().cloned())
+        .collect::<Vec<_>>();
+    packages.sort_by_cached_key(|spec| spec.to_string());
+    packages.dedup();
+    packages
+}
+
+/// Finds the string literals importing or including packages in a syntax
+/// tree.
+fn package_imports(root: &SyntaxNode) -> Vec<ast::Str<'_>> {
+    fn walk<'a>(node: &'a SyntaxNode, imports: &mut Vec<ast::Str<'a>>) {
+        let source = match node.kind() {
+            SyntaxKind::ModuleImport => node.cast::<ast::ModuleImport>().map(|i| i.source()),
+            SyntaxKind::ModuleInclude => node.cast::<ast::ModuleInclude>().map(|i| i.source()),
+            _ => None,
+        };
+        if let Some(ast::Expr::Str(s)) = source {
+            if s.get().starts_with('@') {
+                imports.push(s);
+            }
+        }
+
+        for child in node.children() {
+            walk(child, imports);
+        }
+    }
+
+    let mut imports = vec![];
+    walk(root, &mut imports);
+    imports
+}
+
 /// Make a new project lock updater.
 pub fn update_lock(root: ImmutPath) -> LockFileUpdate {
     LockFileUpdate {
@@ -227,6 +384,7 @@ pub fn update_lock(root: ImmutPath) -> LockFileUpdate {
 
 enum LockUpdate {
     Input(ProjectInput),
+    Packages(Vec<ProjectPackage>),
     Task(ApplyProjectTask),
     Material(ProjectPathMaterial),
     Route(ProjectRoute),
@@ -279,10 +437,35 @@ impl LockFileUpdate {
         };
 
         self.updates.push(LockUpdate::Input(input));
+        self.packages(world);
 
         Some(id)
     }
 
+    /// Pins the packages used by a world which are not pinned at any version
+    /// yet. The existing pins are kept, so that the imports disagreeing with
+    /// them are reported instead of silently updating the lock file.
+    pub fn packages(&mut self, world: &LspWorld) {
+        let packages = used_packages(world);
+        let pinned = LockFile::read(&self.root)
+            .map(|l| l.package)
+            .unwrap_or_default();
+        let packages = packages
+            .iter()
+            .filter(|spec| {
+                !pinned
+                    .iter()
+                    .filter_map(|p| p.parse())
+                    .any(|pinned| pinned.namespace == spec.namespace && pinned.name == spec.name)
+            })
+            .map(|spec| ProjectPackage::pin(world, spec))
+            .collect::<Vec<_>>();
+
+        if !packages.is_empty() {
+            self.updates.push(LockUpdate::Packages(packages));
+        }
+    }
+
     pub fn task(&mut self, task: ApplyProjectTask) {
         self.updates.push(LockUpdate::Task(task));
     }
@@ -313,6 +496,16 @@ impl LockFileUpdate {
                     LockUpdate::Task(task) => {
                         l.replace_task(task);
                     }
+                    LockUpdate::Packages(packages) => {
+                        for package in packages {
+                            let Some(spec) = package.parse() else {
+                                continue;
+                            };
+                            if l.pinned_versions(&spec).next().is_none() {
+                                l.replace_package(package);
+                            }
+                        }
+                    }
                     LockUpdate::Material(mut mat) => {
                         let root: EcoString = unix_slash(&self.root).into();
                         mat.root = root.clone();
@@ -64,6 +64,9 @@ pub struct LockFile {
     /// The project's task route.
     #[serde(skip_serializing_if = "EcoVec::is_empty", default)]
     pub route: EcoVec<ProjectRoute>,
+    /// The packages pinned by the project.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub package: Vec<ProjectPackage>,
 }
 
 /// A project input specifier.
@@ -93,6 +96,18 @@ pub struct ProjectInput {
     pub package_cache_path: Option<ResourcePath>,
 }
 
+/// A package pinned by the lock file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProjectPackage {
+    /// The package specifier, e.g. `@preview/example:0.1.0`.
+    pub spec: EcoString,
+    /// The SHA-256 hash of the package files, which is absent if the package
+    /// could not be read when it was pinned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<EcoString>,
+}
+
 /// A project route specifier.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -0,0 +1,12 @@
+//! Generates the version metadata of the analyzer.
+
+fn main() {
+    let metadata = cargo_metadata::MetadataCommand::new().exec().unwrap();
+    let typst = metadata
+        .packages
+        .iter()
+        .find(|package| package.name == "typst")
+        .expect("Typst should be a dependency");
+
+    println!("cargo:rustc-env=TYPST_VERSION={}", typst.version);
+}
@@ -15,6 +15,12 @@ pub mod doc_highlight;
 pub use doc_highlight::*;
 pub mod link_expr;
 pub use link_expr::*;
+pub mod disk_cache;
+pub use disk_cache::*;
+pub mod dump;
+pub use dump::*;
+pub mod fuzz;
+pub use fuzz::*;
 pub mod stats;
 pub use stats::*;
 pub mod definition;
@@ -23,7 +29,10 @@ pub mod signature;
 pub use signature::*;
 pub mod semantic_tokens;
 pub use semantic_tokens::*;
+mod split;
+pub(crate) use split::*;
 mod post_tyck;
+pub(crate) mod snippet;
 mod tyck;
 pub(crate) use crate::ty::*;
 pub(crate) use post_tyck::*;
@@ -33,10 +42,15 @@ mod prelude;
 mod global;
 pub use global::*;
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use ecow::eco_format;
-use lsp_types::Url;
+use lsp_types::{
+    DocumentChangeOperation, DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier,
+    TextDocumentEdit, Url, WorkspaceEdit,
+};
 use tinymist_project::LspComputeGraph;
 use tinymist_std::{bail, Result};
 use tinymist_world::{EntryReader, TaskInputs};
@@ -44,7 +58,9 @@ use typst::diag::{FileError, FileResult};
 use typst::foundations::{Func, Value};
 use typst::syntax::FileId;
 
-use crate::{path_res_to_url, CompilerQueryResponse, SemanticRequest, StatefulRequest};
+use crate::{
+    path_res_to_url, url_to_path, CompilerQueryResponse, SemanticRequest, StatefulRequest,
+};
 
 pub(crate) trait ToFunc {
     fn to_func(&self) -> Option<Func>;
@@ -75,10 +91,114 @@ impl LspWorldExt for tinymist_project::LspWorld {
     }
 }
 
+/// The versions of the documents opened in the editor, taken together with the
+/// snapshot of the sources.
+///
+/// The versions are maintained as the documents are opened, changed and
+/// closed, and are cheap to clone into the snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentVersions {
+    /// Whether the editor accepts the versioned `documentChanges` in the
+    /// workspace edits.
+    document_changes: bool,
+    versions: rpds::HashTrieMapSync<PathBuf, i32>,
+}
+
+impl DocumentVersions {
+    /// Creates the versions for an editor, which pins the workspace edits only
+    /// if it accepts the `documentChanges` in the workspace edits.
+    pub fn new(document_changes: bool) -> Self {
+        Self {
+            document_changes,
+            versions: rpds::HashTrieMapSync::new_sync(),
+        }
+    }
+
+    /// Gets the version of an opened document.
+    pub fn get(&self, path: &Path) -> Option<i32> {
+        self.versions.get(path).copied()
+    }
+
+    /// Updates the version of an opened document.
+    pub fn insert(&mut self, path: PathBuf, version: i32) {
+        self.versions.insert_mut(path, version);
+    }
+
+    /// Forgets the version of a closed document.
+    pub fn remove(&mut self, path: &Path) {
+        self.versions.remove_mut(path);
+    }
+
+    /// Pins the workspace edits in a response to the versions.
+    pub fn pin(&self, mut resp: CompilerQueryResponse) -> CompilerQueryResponse {
+        use CompilerQueryResponse::*;
+
+        match &mut resp {
+            Rename(Some(edit))
+            | WillRenameFiles(Some(edit))
+            | ProseReplace(Some(edit))
+            | MoveAsset(Some(edit)) => self.pin_edit(edit),
+            _ => {}
+        }
+        resp
+    }
+
+    /// Pins a workspace edit to the versions, so that the editor rejects the
+    /// edit instead of applying it to the documents changed since the
+    /// snapshot. The edits to the documents not opened are left unversioned.
+    ///
+    /// The edit is left as is if the editor doesn't accept the
+    /// `documentChanges`.
+    pub fn pin_edit(&self, edit: &mut WorkspaceEdit) {
+        if !self.document_changes {
+            return;
+        }
+
+        if let Some(changes) = edit.changes.take() {
+            let edits = changes.into_iter().map(|(uri, edits)| TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: edits.into_iter().map(OneOf::Left).collect(),
+            });
+            match &mut edit.document_changes {
+                Some(DocumentChanges::Edits(changes)) => changes.extend(edits),
+                Some(DocumentChanges::Operations(changes)) => {
+                    changes.extend(edits.map(DocumentChangeOperation::Edit));
+                }
+                None => edit.document_changes = Some(DocumentChanges::Edits(edits.collect())),
+            }
+        }
+
+        let pin = |doc: &mut OptionalVersionedTextDocumentIdentifier| {
+            if doc.version.is_none() {
+                doc.version = self.get(&url_to_path(doc.uri.clone()));
+            }
+        };
+        match &mut edit.document_changes {
+            Some(DocumentChanges::Edits(changes)) => {
+                changes.iter_mut().for_each(|e| pin(&mut e.text_document));
+            }
+            Some(DocumentChanges::Operations(changes)) => {
+                for change in changes {
+                    if let DocumentChangeOperation::Edit(e) = change {
+                        pin(&mut e.text_document);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
 /// A snapshot for LSP queries.
+///
+/// All the files are read from the same revision of the sources, and the
+/// workspace edits are pinned to the versions of the opened documents at the
+/// revision, even if the user keeps typing while the query is running.
 pub struct LspQuerySnapshot {
     /// The using snapshot.
     pub snap: LspComputeGraph,
+    /// The versions of the opened documents at the snapshot.
+    pub versions: DocumentVersions,
     /// The global shared analysis data.
     analysis: Arc<Analysis>,
     /// The revision lock for the analysis (cache).
@@ -100,6 +220,12 @@ impl LspQuerySnapshot {
         self
     }
 
+    /// Sets the versions of the opened documents at the snapshot.
+    pub fn with_versions(mut self, versions: DocumentVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
     /// Runs a stateful query.
     pub fn run_stateful<T: StatefulRequest>(
         self,
@@ -107,8 +233,9 @@ impl LspQuerySnapshot {
         wrapper: fn(Option<T::Response>) -> CompilerQueryResponse,
     ) -> Result<CompilerQueryResponse> {
         let graph = self.snap.clone();
+        let versions = self.versions.clone();
         self.run_analysis(|ctx| query.request(ctx, graph))
-            .map(wrapper)
+            .map(|res| versions.pin(wrapper(res)))
     }
 
     /// Runs a semantic query.
@@ -117,7 +244,9 @@ impl LspQuerySnapshot {
         query: T,
         wrapper: fn(Option<T::Response>) -> CompilerQueryResponse,
     ) -> Result<CompilerQueryResponse> {
-        self.run_analysis(|ctx| query.request(ctx)).map(wrapper)
+        let versions = self.versions.clone();
+        self.run_analysis(|ctx| query.request(ctx))
+            .map(|res| versions.pin(wrapper(res)))
     }
 
     /// Runs a query.
@@ -627,3 +756,73 @@ mod lint_tests {
         });
     }
 }
+
+#[cfg(test)]
+mod versions_tests {
+    use lsp_types::{Range, TextEdit};
+
+    use super::*;
+
+    fn edit_of(uris: &[&Url]) -> WorkspaceEdit {
+        let text_edit = TextEdit {
+            range: Range::default(),
+            new_text: "x".into(),
+        };
+        let changes = uris
+            .iter()
+            .map(|uri| ((*uri).clone(), vec![text_edit.clone()]));
+        WorkspaceEdit {
+            changes: Some(changes.collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pin_edit() {
+        let opened = Url::parse("file:///opened.typ").unwrap();
+        let closed = Url::parse("file:///closed.typ").unwrap();
+        let mut versions = DocumentVersions::new(true);
+        versions.insert(url_to_path(closed.clone()), 1);
+        versions.insert(url_to_path(opened.clone()), 2);
+        versions.insert(url_to_path(opened.clone()), 3);
+        versions.remove(&url_to_path(closed.clone()));
+
+        let mut edit = edit_of(&[&opened, &closed]);
+        versions.pin_edit(&mut edit);
+
+        assert!(edit.changes.is_none());
+        let Some(DocumentChanges::Edits(edits)) = edit.document_changes else {
+            panic!("expected document changes");
+        };
+        let version_of = |uri: &Url| {
+            let edit = edits.iter().find(|edit| edit.text_document.uri == *uri);
+            edit.unwrap().text_document.version
+        };
+        assert_eq!(version_of(&opened), Some(3));
+        assert_eq!(version_of(&closed), None);
+    }
+
+    #[test]
+    fn pin_edit_unsupported() {
+        let opened = Url::parse("file:///opened.typ").unwrap();
+        let mut versions = DocumentVersions::new(false);
+        versions.insert(url_to_path(opened.clone()), 3);
+
+        let mut edit = edit_of(&[&opened]);
+        versions.pin_edit(&mut edit);
+        assert!(edit.document_changes.is_none());
+        assert_eq!(edit.changes.map(|changes| changes.len()), Some(1));
+    }
+
+    #[test]
+    fn snapshot_versions() {
+        let path = PathBuf::from("/a.typ");
+        let mut versions = DocumentVersions::new(true);
+        versions.insert(path.clone(), 1);
+
+        let snapshot = versions.clone();
+        versions.insert(path.clone(), 2);
+        assert_eq!(snapshot.get(&path), Some(1));
+        assert_eq!(versions.get(&path), Some(2));
+    }
+}
@@ -1,31 +1,52 @@
 use indexmap::IndexMap;
+use parking_lot::Mutex;
 use typst::{foundations::Bytes, model::CslStyle};
 use yaml_rust2::{parser::Event, parser::MarkedEventReceiver, scanner::Marker};
 
 use super::prelude::*;
+use crate::bib::RenderedBibCitation;
 
+/// Merges the bibliography entries of all the bib/yaml files.
+///
+/// The result is memoized by the style and the file contents, so that it is
+/// reused across compilations as long as the bibliography is not changed.
+#[comemo::memoize]
 pub(crate) fn bib_info(
     csl_style: CslStyle,
-    files: impl Iterator<Item = (TypstFileId, Bytes)>,
+    files: EcoVec<(TypstFileId, Bytes)>,
 ) -> Option<Arc<BibInfo>> {
-    let mut worker = BibWorker {
-        info: BibInfo {
-            csl_style,
-            entries: IndexMap::new(),
-        },
-    };
+    let mut entries = IndexMap::new();
 
     // We might have multiple bib/yaml files
     for (file_id, content) in files {
-        worker.analyze_path(file_id, content);
+        if let Some(file_entries) = parse_bib_file(file_id, content) {
+            entries.extend(file_entries.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
     }
 
-    let info = Arc::new(worker.info);
+    let info = Arc::new(BibInfo {
+        csl_style,
+        entries,
+        rendered: Mutex::default(),
+    });
 
     crate::log_debug_ct!("bib analysis: {info:?}");
     Some(info)
 }
 
+/// Parses the entries of a single bib/yaml file.
+///
+/// The bytes are hashed when they are loaded, so an unchanged file is never
+/// parsed twice.
+#[comemo::memoize]
+fn parse_bib_file(file_id: TypstFileId, content: Bytes) -> Option<Arc<IndexMap<String, BibEntry>>> {
+    let mut worker = BibWorker {
+        entries: IndexMap::new(),
+    };
+    worker.analyze_path(file_id, content);
+    Some(Arc::new(worker.entries))
+}
+
 /// The bibliography information.
 #[derive(Debug)]
 pub struct BibInfo {
@@ -33,6 +54,34 @@ pub struct BibInfo {
     pub csl_style: CslStyle,
     /// The bibliography entries.
     pub entries: IndexMap<String, BibEntry>,
+    /// The rendered citations, keyed by the entry key and whether it is
+    /// rendered in HTML. Only the citations of the entries are cached, so the
+    /// cache never grows beyond twice the number of the entries.
+    rendered: Mutex<HashMap<(String, bool), Option<RenderedBibCitation>>>,
+}
+
+impl BibInfo {
+    /// Gets the rendered citation of the entry, or renders it if it is not
+    /// rendered yet.
+    pub(crate) fn render_citation(
+        &self,
+        key: &str,
+        support_html: bool,
+        render: impl FnOnce() -> Option<RenderedBibCitation>,
+    ) -> Option<RenderedBibCitation> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+
+        let cache_key = (key.to_owned(), support_html);
+        if let Some(rendered) = self.rendered.lock().get(&cache_key) {
+            return rendered.clone();
+        }
+
+        let rendered = render();
+        self.rendered.lock().insert(cache_key, rendered.clone());
+        rendered
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,7 +93,7 @@ pub struct BibEntry {
 }
 
 struct BibWorker {
-    info: BibInfo,
+    entries: IndexMap<String, BibEntry>,
 }
 
 impl BibWorker {
@@ -69,7 +118,7 @@ impl BibWorker {
         };
 
         for entry in bib {
-            if let Some(stored_entry) = self.info.entries.get_mut(entry.key()) {
+            if let Some(stored_entry) = self.entries.get_mut(entry.key()) {
                 stored_entry.raw_entry = Some(entry);
             }
         }
@@ -79,7 +128,7 @@ impl BibWorker {
 
     fn yaml_bib(&mut self, file_id: TypstFileId, content: &str) {
         let yaml = YamlBib::from_content(content, file_id);
-        self.info.entries.extend(yaml.entries);
+        self.entries.extend(yaml.entries);
     }
 
     fn tex_bib(&mut self, file_id: TypstFileId, bibliography: biblatex::RawBibliography) {
@@ -91,7 +140,7 @@ impl BibWorker {
                 range: entry.span,
                 raw_entry: None,
             };
-            self.info.entries.insert(name.v.to_owned(), entry);
+            self.entries.insert(name.v.to_owned(), entry);
         }
     }
 }
@@ -251,4 +300,36 @@ Euclid3
         let file_id = FileId::new_fake(VirtualPath::new(Path::new("test.yml")));
         super::YamlBib::from_content(content, file_id);
     }
+
+    #[test]
+    fn bib_file_parse_is_cached() {
+        let content = typst::foundations::Bytes::from_string(
+            "@article{euclid, title = {Elements}}\n@book{knuth, title = {TAOCP}}\n".to_owned(),
+        );
+        let file_id = FileId::new_fake(VirtualPath::new(Path::new("refs.bib")));
+
+        let first = super::parse_bib_file(file_id, content.clone()).unwrap();
+        let second = super::parse_bib_file(file_id, content).unwrap();
+        assert_eq!(first.len(), 2);
+        assert!(std::sync::Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn rendered_citations_are_bounded() {
+        use hayagriva::archive::ArchivedStyle;
+        use typst::model::CslStyle;
+
+        let content = typst::foundations::Bytes::from_string(
+            "@article{euclid, title = {Elements}}\n".to_owned(),
+        );
+        let file_id = FileId::new_fake(VirtualPath::new(Path::new("refs.bib")));
+        let style =
+            CslStyle::from_archived(ArchivedStyle::InstituteOfElectricalAndElectronicsEngineers);
+        let info = super::bib_info(style, ecow::eco_vec![(file_id, content)]).unwrap();
+
+        for key in ["euclid", "euclid", "missing", "unknown"] {
+            crate::bib::render_citation_string(&info, key, false);
+        }
+        assert_eq!(info.rendered.lock().len(), 1);
+    }
 }
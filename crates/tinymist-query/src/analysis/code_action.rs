@@ -1,7 +1,10 @@
 //! Provides code actions for the document.
 
 use ecow::eco_format;
-use lsp_types::{ChangeAnnotation, CreateFile, CreateFileOptions};
+use lsp_types::{
+    ChangeAnnotation, CreateFile, CreateFileOptions, DeleteFile, OneOf,
+    OptionalVersionedTextDocumentIdentifier,
+};
 use regex::Regex;
 use tinymist_analysis::syntax::{
     adjust_expr, node_ancestors, previous_items, PreviousItem, SyntaxClass,
@@ -9,10 +12,11 @@ use tinymist_analysis::syntax::{
 use tinymist_std::path::{diff, unix_slash};
 use typst::syntax::Side;
 
-use super::get_link_exprs_in;
+use super::{get_link_exprs, get_link_exprs_in, inline_include, split_document};
 use crate::analysis::LinkTarget;
 use crate::prelude::*;
 use crate::syntax::{interpret_mode_at, InterpretMode};
+use crate::{spelling_suggestions, url_to_path};
 
 /// Analyzes the document and provides code actions.
 pub struct CodeActionWorker<'a> {
@@ -84,6 +88,9 @@ impl<'a> CodeActionWorker<'a> {
                 Some(AutofixKind::FileNotFound) => {
                     self.autofix_file_not_found(root, range);
                 }
+                Some(AutofixKind::UnknownWord) => {
+                    self.autofix_unknown_word(diag);
+                }
                 _ => {}
             }
         }
@@ -236,6 +243,77 @@ impl<'a> CodeActionWorker<'a> {
         Some(())
     }
 
+    /// Automatically fixes unknown word warnings, by replacing the word with
+    /// the suggestions of the dictionary or adding it to the project word list.
+    pub fn autofix_unknown_word(&mut self, diag: &lsp_types::Diagnostic) -> Option<()> {
+        let range = self.ctx.to_typst_range(diag.range, &self.source)?;
+        let word = self.source.text().get(range.clone())?.to_owned();
+        let feat = self.ctx.analysis.spell.clone();
+        let root = self.ctx.world.entry_state().workspace_root();
+
+        for suggestion in spelling_suggestions(&self.source, &range, &feat, root.as_deref()) {
+            let edit = EcoSnippetTextEdit::new_plain(diag.range, suggestion.as_str().into());
+            let action = CodeAction {
+                title: format!("Replace with `{suggestion}`"),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diag.clone()]),
+                edit: Some(self.local_edit(edit)?),
+                ..CodeAction::default()
+            };
+            self.actions.push(action);
+        }
+
+        let path = feat.word_list_path(root.as_deref())?;
+        let uri = path_to_url(&path).ok()?;
+        // The word is appended to the list as it is on the disk.
+        let words = std::fs::read_to_string(&path).unwrap_or_default();
+        let last_line = words.rsplit('\n').next().unwrap_or_default();
+        let end = LspPosition::new(
+            words.matches('\n').count() as u32,
+            match self.ctx.analysis.position_encoding {
+                PositionEncoding::Utf8 => last_line.len(),
+                PositionEncoding::Utf16 => last_line.encode_utf16().count(),
+            } as u32,
+        );
+        let new_text = if last_line.is_empty() {
+            eco_format!("{word}\n")
+        } else {
+            eco_format!("\n{word}\n")
+        };
+
+        let ops = vec![
+            EcoDocumentChangeOperation::Op(lsp_types::ResourceOp::Create(CreateFile {
+                uri: uri.clone(),
+                options: Some(CreateFileOptions {
+                    overwrite: Some(false),
+                    ignore_if_exists: Some(true),
+                }),
+                annotation_id: None,
+            })),
+            EcoDocumentChangeOperation::Edit(EcoTextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: vec![OneOf::Left(EcoSnippetTextEdit::new_plain(
+                    LspRange::new(end, end),
+                    new_text,
+                ))],
+            }),
+        ];
+        let action = CodeAction {
+            title: format!("Add `{word}` to the project word list"),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diag.clone()]),
+            edit: Some(EcoWorkspaceEdit {
+                changes: None,
+                document_changes: Some(EcoDocumentChanges::Operations(ops)),
+                change_annotations: None,
+            }),
+            ..CodeAction::default()
+        };
+        self.actions.push(action);
+
+        Some(())
+    }
+
     /// Starts to work.
     pub fn scoped(&mut self, root: &LinkedNode, range: &Range<usize>) -> Option<()> {
         let cursor = (range.start + 1).min(self.source.text().len());
@@ -273,11 +351,14 @@ impl<'a> CodeActionWorker<'a> {
 
     fn path_actions(&mut self, node: &LinkedNode, cursor: usize) -> Option<()> {
         // We can only process the case where the import path is a string.
-        if let Some(SyntaxClass::IncludePath(path_node) | SyntaxClass::ImportPath(path_node)) =
+        if let Some(class @ (SyntaxClass::IncludePath(..) | SyntaxClass::ImportPath(..))) =
             classify_syntax(node.clone(), cursor)
         {
-            let str_node = adjust_expr(path_node)?;
+            let str_node = adjust_expr(class.node().clone())?;
             let str_ast = str_node.cast::<ast::Str>()?;
+            if matches!(class, SyntaxClass::IncludePath(..)) {
+                self.inline_include_action(&str_node, &str_ast.get());
+            }
             return self.path_rewrite(self.source.id(), &str_ast.get(), &str_node);
         }
 
@@ -434,6 +515,144 @@ impl<'a> CodeActionWorker<'a> {
         };
         self.actions.push(action);
 
+        if depth == 1 {
+            self.split_document_action();
+        }
+
+        Some(())
+    }
+
+    /// Splits the document into chapter files at its top-level headings.
+    fn split_document_action(&mut self) -> Option<()> {
+        let split = split_document(&self.source)?;
+        let main_url = self.local_url()?.clone();
+        let main_path = url_to_path(main_url.clone());
+        let dir = main_path.parent()?;
+
+        let mut ops = vec![];
+        let mut create_file = |path: &str, content: String| -> Option<()> {
+            let uri = path_to_url(&dir.join(path)).ok()?;
+            ops.push(EcoDocumentChangeOperation::Op(
+                lsp_types::ResourceOp::Create(CreateFile {
+                    uri: uri.clone(),
+                    options: Some(CreateFileOptions {
+                        overwrite: Some(false),
+                        ignore_if_exists: Some(false),
+                    }),
+                    annotation_id: None,
+                }),
+            ));
+            ops.push(EcoDocumentChangeOperation::Edit(EcoTextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: vec![OneOf::Left(EcoSnippetTextEdit::new_plain(
+                    LspRange::default(),
+                    content.into(),
+                ))],
+            }));
+            Some(())
+        };
+
+        if let Some(preamble) = split.preamble {
+            create_file(super::PREAMBLE_FILE, preamble)?;
+        }
+        for (path, content) in split.chapters {
+            create_file(&path, content)?;
+        }
+
+        let full_range = self
+            .ctx
+            .to_lsp_range(0..self.source.text().len(), &self.source);
+        ops.push(EcoDocumentChangeOperation::Edit(EcoTextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier {
+                uri: main_url,
+                version: None,
+            },
+            edits: vec![OneOf::Left(EcoSnippetTextEdit::new_plain(
+                full_range,
+                split.main.into(),
+            ))],
+        }));
+
+        let action = CodeAction {
+            title: "Split document into chapter files at top-level headings".to_string(),
+            kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+            edit: Some(EcoWorkspaceEdit {
+                changes: None,
+                document_changes: Some(EcoDocumentChanges::Operations(ops)),
+                change_annotations: None,
+            }),
+            ..CodeAction::default()
+        };
+        self.actions.push(action);
+
+        Some(())
+    }
+
+    /// Inlines the content of an included file, and optionally deletes the
+    /// file if nothing else refers to it.
+    fn inline_include_action(&mut self, path_node: &LinkedNode, path: &str) -> Option<()> {
+        let include =
+            node_ancestors(path_node).find(|node| node.kind() == SyntaxKind::ModuleInclude)?;
+        let included_id = self.source.id().join(path);
+        let included = self.ctx.source_by_id(included_id).ok()?;
+        let (range, content) = inline_include(&self.source, include, &included)?;
+
+        let edit = EcoSnippetTextEdit::new_plain(
+            self.ctx.to_lsp_range(range, &self.source),
+            content.into(),
+        );
+        let action = CodeAction {
+            title: "Inline included file".to_string(),
+            kind: Some(CodeActionKind::REFACTOR_INLINE),
+            edit: Some(self.local_edit(edit.clone())?),
+            ..CodeAction::default()
+        };
+        self.actions.push(action);
+
+        // The file is kept if it is included or imported elsewhere.
+        let path_range = path_node.range();
+        let referred_elsewhere = self.ctx.source_files().clone().into_iter().any(|id| {
+            let Ok(source) = self.ctx.source_by_id(id) else {
+                return false;
+            };
+            get_link_exprs(&source).objects.iter().any(|link| {
+                let LinkTarget::Path(id, path) = &link.target else {
+                    return false;
+                };
+                let is_this = *id == self.source.id() && path_range.contains(&link.range.start);
+                !is_this && id.join(path) == included_id
+            })
+        });
+        if referred_elsewhere {
+            return Some(());
+        }
+
+        let included_url = self.ctx.uri_for_id(included_id).ok()?;
+        let ops = vec![
+            EcoDocumentChangeOperation::Edit(EcoTextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: self.local_url()?.clone(),
+                    version: None,
+                },
+                edits: vec![OneOf::Left(edit)],
+            }),
+            EcoDocumentChangeOperation::Op(lsp_types::ResourceOp::Delete(DeleteFile {
+                uri: included_url,
+                options: None,
+            })),
+        ];
+        let action = CodeAction {
+            title: "Inline included file and delete it".to_string(),
+            kind: Some(CodeActionKind::REFACTOR_INLINE),
+            edit: Some(EcoWorkspaceEdit {
+                changes: None,
+                document_changes: Some(EcoDocumentChanges::Operations(ops)),
+                change_annotations: None,
+            }),
+            ..CodeAction::default()
+        };
+        self.actions.push(action);
+
         Some(())
     }
 
@@ -573,12 +792,14 @@ impl<'a> CodeActionWorker<'a> {
 enum AutofixKind {
     UnknownVariable,
     FileNotFound,
+    UnknownWord,
 }
 
 fn match_autofix_kind(msg: &str) -> Option<AutofixKind> {
     static PATTERNS: &[(&str, AutofixKind)] = &[
         ("unknown variable", AutofixKind::UnknownVariable),
         ("file not found", AutofixKind::FileNotFound),
+        ("unknown word", AutofixKind::UnknownWord),
     ];
 
     for (pattern, kind) in PATTERNS {
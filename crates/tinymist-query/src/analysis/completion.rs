@@ -56,11 +56,13 @@ mod param;
 mod path;
 mod scope;
 mod snippet;
+mod symbol;
 #[path = "completion/type.rs"]
 mod type_;
 mod typst_specific;
 use kind::*;
 use scope::*;
+pub use symbol::latex_command_symbol;
 use type_::*;
 
 type LspCompletion = CompletionItem;
@@ -129,6 +129,16 @@ impl CompletionPair<'_, '_, '_> {
 
     /// Complete in math mode.
     pub fn complete_math(&mut self) -> bool {
+        // Behind a LaTeX command: "$\alp|$".
+        if self.latex_symbol_completions() {
+            return true;
+        }
+
+        // Behind a shorthand: "$=>|$".
+        if self.worker.explicit && self.shorthand_symbol_completions() {
+            return true;
+        }
+
         // Behind existing atom or identifier: "$a|$" or "$abc|$".
         if !is_triggered_by_punc(self.worker.trigger_character)
             && matches!(
@@ -138,6 +148,7 @@ impl CompletionPair<'_, '_, '_> {
         {
             self.cursor.from = self.cursor.leaf.offset();
             self.scope_completions(true);
+            self.bare_latex_symbol_completions();
             self.snippet_completions(Some(InterpretMode::Math), None);
             return true;
         }
@@ -83,7 +83,16 @@ impl CompletionPair<'_, '_, '_> {
             };
             crate::log_debug_ct!("compl_label: {label:?}");
 
-            module_completions.push((label, CompletionKind::File));
+            // Describes the styles by their metadata.
+            let detail = match preference {
+                PathPreference::Csl => {
+                    let content = self.worker.world().file(*path).ok();
+                    content.and_then(|content| crate::bib::csl_file_docs(&content))
+                }
+                _ => None,
+            };
+
+            module_completions.push((label, CompletionKind::File, detail));
 
             // todo: looks like the folder completion is broken
             // if path.is_dir() {
@@ -138,7 +147,7 @@ impl CompletionPair<'_, '_, '_> {
                     LspCompletion {
                         label: typst_completion.0,
                         kind: typst_completion.1,
-                        detail: None,
+                        detail: typst_completion.2,
                         text_edit: Some(text_edit),
                         // don't sort me
                         sort_text: Some(sort_text),
@@ -0,0 +1,426 @@
+//! Completion of symbols by their LaTeX-ish aliases in math mode.
+
+use std::sync::LazyLock;
+
+use typst::Library;
+
+use super::*;
+
+/// The aliases of the symbols, i.e. the LaTeX commands and the shorthands
+/// typed for the symbols. The LaTeX commands named the same as the symbols,
+/// e.g. `\alpha`, are not listed.
+const SYMBOL_ALIASES: &[(&str, &str)] = &[
+    // Relations
+    ("\\leq", "lt.eq"),
+    ("\\le", "lt.eq"),
+    ("\\geq", "gt.eq"),
+    ("\\ge", "gt.eq"),
+    ("\\neq", "eq.not"),
+    ("\\ne", "eq.not"),
+    ("\\ll", "lt.double"),
+    ("\\gg", "gt.double"),
+    ("\\sim", "tilde.op"),
+    ("\\simeq", "tilde.eq"),
+    ("\\cong", "tilde.equiv"),
+    ("\\propto", "prop"),
+    ("\\mid", "divides"),
+    ("\\vdash", "tack.r"),
+    ("\\dashv", "tack.l"),
+    ("\\coloneqq", "colon.eq"),
+    // Sets and logic
+    ("\\notin", "in.not"),
+    ("\\ni", "in.rev"),
+    ("\\subseteq", "subset.eq"),
+    ("\\supseteq", "supset.eq"),
+    ("\\subsetneq", "subset.neq"),
+    ("\\supsetneq", "supset.neq"),
+    ("\\cup", "union"),
+    ("\\cap", "sect"),
+    ("\\bigcup", "union.big"),
+    ("\\bigcap", "sect.big"),
+    ("\\setminus", "without"),
+    ("\\emptyset", "nothing"),
+    ("\\varnothing", "nothing"),
+    ("\\neg", "not"),
+    ("\\lnot", "not"),
+    ("\\land", "and"),
+    ("\\wedge", "and"),
+    ("\\lor", "or"),
+    ("\\vee", "or"),
+    ("\\implies", "arrow.r.double.long"),
+    ("\\iff", "arrow.l.r.double.long"),
+    // Operators
+    ("\\pm", "plus.minus"),
+    ("\\mp", "minus.plus"),
+    ("\\cdot", "dot.op"),
+    ("\\circ", "compose"),
+    ("\\ast", "ast.op"),
+    ("\\star", "star.op"),
+    ("\\oplus", "plus.circle"),
+    ("\\otimes", "times.circle"),
+    ("\\odot", "dot.circle"),
+    ("\\bigoplus", "plus.circle.big"),
+    ("\\bigotimes", "times.circle.big"),
+    ("\\prod", "product"),
+    ("\\coprod", "product.co"),
+    ("\\int", "integral"),
+    ("\\iint", "integral.double"),
+    ("\\iiint", "integral.triple"),
+    ("\\oint", "integral.cont"),
+    ("\\partial", "diff"),
+    // Arrows
+    ("\\to", "arrow.r"),
+    ("\\rightarrow", "arrow.r"),
+    ("\\leftarrow", "arrow.l"),
+    ("\\gets", "arrow.l"),
+    ("\\uparrow", "arrow.t"),
+    ("\\downarrow", "arrow.b"),
+    ("\\leftrightarrow", "arrow.l.r"),
+    ("\\Rightarrow", "arrow.r.double"),
+    ("\\Leftarrow", "arrow.l.double"),
+    ("\\Leftrightarrow", "arrow.l.r.double"),
+    ("\\longrightarrow", "arrow.r.long"),
+    ("\\longleftarrow", "arrow.l.long"),
+    ("\\Longrightarrow", "arrow.r.double.long"),
+    ("\\Longleftarrow", "arrow.l.double.long"),
+    ("\\mapsto", "arrow.r.bar"),
+    ("\\hookrightarrow", "arrow.r.hook"),
+    ("\\hookleftarrow", "arrow.l.hook"),
+    ("\\rightharpoonup", "harpoon.rt"),
+    ("\\leftharpoonup", "harpoon.lt"),
+    // Delimiters and dots
+    ("\\langle", "angle.l"),
+    ("\\rangle", "angle.r"),
+    ("\\lfloor", "floor.l"),
+    ("\\rfloor", "floor.r"),
+    ("\\lceil", "ceil.l"),
+    ("\\rceil", "ceil.r"),
+    ("\\ldots", "dots.h"),
+    ("\\dots", "dots.h"),
+    ("\\cdots", "dots.h.c"),
+    ("\\vdots", "dots.v"),
+    ("\\ddots", "dots.down"),
+    // Letters and miscellaneous
+    ("\\varepsilon", "epsilon.alt"),
+    ("\\vartheta", "theta.alt"),
+    ("\\varphi", "phi.alt"),
+    ("\\varpi", "pi.alt"),
+    ("\\varrho", "rho.alt"),
+    ("\\varsigma", "sigma.alt"),
+    ("\\varkappa", "kappa.alt"),
+    ("\\infty", "infinity"),
+    ("\\hbar", "planck.reduce"),
+    ("\\triangle", "triangle.stroked.t"),
+    ("\\square", "square.stroked"),
+    // Shorthands
+    ("...", "dots.h"),
+    ("!=", "eq.not"),
+    (":=", "colon.eq"),
+    ("=:", "eq.colon"),
+    ("<<", "lt.double"),
+    (">>", "gt.double"),
+    ("<<<", "lt.triple"),
+    (">>>", "gt.triple"),
+    ("<=", "lt.eq"),
+    (">=", "gt.eq"),
+    ("->", "arrow.r"),
+    ("-->", "arrow.r.long"),
+    ("|->", "arrow.r.bar"),
+    (">->", "arrow.r.tail"),
+    ("->>", "arrow.r.twohead"),
+    ("<-", "arrow.l"),
+    ("<--", "arrow.l.long"),
+    ("<-<", "arrow.l.tail"),
+    ("<<-", "arrow.l.twohead"),
+    ("<->", "arrow.l.r"),
+    ("<-->", "arrow.l.r.long"),
+    ("~>", "arrow.r.squiggly"),
+    ("<~", "arrow.l.squiggly"),
+    ("=>", "arrow.r.double"),
+    ("|=>", "arrow.r.double.bar"),
+    ("==>", "arrow.r.double.long"),
+    ("<==", "arrow.l.double.long"),
+    ("<=>", "arrow.l.r.double"),
+    ("<==>", "arrow.l.r.double.long"),
+    ("[|", "bracket.l.double"),
+    ("|]", "bracket.r.double"),
+    ("||", "bar.v.double"),
+];
+
+/// A symbol in the [`SymbolIndex`].
+#[derive(Debug, Clone)]
+pub(crate) struct SymbolEntry {
+    /// The full name of the symbol, e.g. `arrow.r.double`.
+    pub name: EcoString,
+    /// The glyph of the symbol.
+    pub ch: char,
+    /// The aliases of the symbol, e.g. `\Rightarrow` and `=>`.
+    pub aliases: Vec<&'static str>,
+}
+
+impl SymbolEntry {
+    /// Gets the LaTeX commands of the symbol, including the command named the
+    /// same as the symbol.
+    fn commands(&self) -> impl Iterator<Item = EcoString> + '_ {
+        let own = (!self.name.contains('.')).then(|| eco_format!("\\{}", self.name));
+        own.into_iter().chain(
+            (self.aliases.iter())
+                .filter(|alias| alias.starts_with('\\'))
+                .map(|alias| EcoString::from(*alias)),
+        )
+    }
+
+    /// Gets the shorthands of the symbol.
+    fn shorthands(&self) -> impl Iterator<Item = &'static str> + '_ {
+        (self.aliases.iter().copied()).filter(|alias| !alias.starts_with('\\'))
+    }
+}
+
+/// The index of the symbols in the `sym` module, which is generated from the
+/// standard library.
+#[derive(Debug, Default)]
+pub(crate) struct SymbolIndex {
+    pub entries: Vec<SymbolEntry>,
+}
+
+impl SymbolIndex {
+    /// Gets the index of the symbols of the standard library.
+    pub fn get() -> &'static Self {
+        static INDEX: LazyLock<SymbolIndex> =
+            LazyLock::new(|| SymbolIndex::new(&Library::builder().build()));
+        &INDEX
+    }
+
+    /// Generates the index from the `sym` module of a library.
+    fn new(lib: &Library) -> Self {
+        let sym = lib.std.read().scope().and_then(|std| std.get("sym"));
+        let Some(scope) = sym.and_then(|sym| sym.read().scope()) else {
+            return Self::default();
+        };
+
+        let mut entries = Vec::new();
+        for (base, binding) in scope.iter() {
+            let Value::Symbol(sym) = binding.read() else {
+                continue;
+            };
+            for (modifiers, ch) in sym.variants() {
+                let name = if modifiers.is_empty() {
+                    eco_format!("{base}")
+                } else {
+                    eco_format!("{base}.{modifiers}")
+                };
+                entries.push(SymbolEntry {
+                    name,
+                    ch,
+                    aliases: vec![],
+                });
+            }
+        }
+
+        // The modifiers of the aliased names may be ordered differently from the
+        // variants, so the aliases are resolved by the glyphs.
+        for &(alias, name) in SYMBOL_ALIASES {
+            let Some(ch) = resolve_symbol(scope, name) else {
+                log::warn!("unknown symbol `{name}` aliased by `{alias}`");
+                continue;
+            };
+            let base = name.split('.').next();
+            let entry = entries
+                .iter_mut()
+                .find(|entry| entry.ch == ch && entry.name.split('.').next() == base);
+            if let Some(entry) = entry {
+                entry.aliases.push(alias);
+            }
+        }
+
+        Self { entries }
+    }
+}
+
+/// Gets the name of the symbol written by a LaTeX command, e.g. `lt.eq` for
+/// `leq` or `alpha` for `alpha`.
+pub fn latex_command_symbol(command: &str) -> Option<EcoString> {
+    let entries = &SymbolIndex::get().entries;
+    let aliased = entries.iter().find(|entry| {
+        (entry.aliases.iter()).any(|alias| alias.strip_prefix('\\') == Some(command))
+    });
+    let own = || entries.iter().find(|entry| entry.name.as_str() == command);
+    aliased.or_else(own).map(|entry| entry.name.clone())
+}
+
+/// Resolves the glyph of a symbol by its full name.
+fn resolve_symbol(scope: &Scope, name: &str) -> Option<char> {
+    let mut parts = name.split('.');
+    let Value::Symbol(sym) = scope.get(parts.next()?)?.read() else {
+        return None;
+    };
+    let mut sym = sym.clone();
+    for modifier in parts {
+        sym = sym.modified(modifier).ok()?;
+    }
+    Some(sym.get())
+}
+
+/// Checks whether the characters of the pattern occur in order in the text,
+/// starting with the same character. The letters are matched
+/// case-insensitively.
+fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    let mut pattern = pattern.chars();
+    let eq = |a: char, b: char| a.eq_ignore_ascii_case(&b);
+
+    if let Some(first) = pattern.next() {
+        if !text.next().is_some_and(|ch| eq(ch, first)) {
+            return false;
+        }
+    }
+    pattern.all(|pat| text.any(|ch| eq(ch, pat)))
+}
+
+impl CompletionPair<'_, '_, '_> {
+    /// Completes the symbols by the LaTeX command before the cursor, e.g.
+    /// `$\alp|$` or `$\Right|$`.
+    pub fn latex_symbol_completions(&mut self) -> bool {
+        let text = &self.cursor.source.text()[..self.cursor.cursor];
+        let name = text.trim_end_matches(|ch: char| ch.is_ascii_alphabetic());
+        let Some(before) = name.strip_suffix('\\') else {
+            return false;
+        };
+        // A line break `\\`.
+        if before.ends_with('\\') {
+            return false;
+        }
+        let query = EcoString::from(&text[before.len()..]);
+
+        self.cursor.from = before.len();
+        for entry in &SymbolIndex::get().entries {
+            let commands = entry.commands().collect::<Vec<_>>();
+            let matched = commands.iter().any(|it| fuzzy_match(&query, it));
+            if matched {
+                self.alias_symbol_completion(entry, commands.join(" ").into());
+            }
+        }
+
+        true
+    }
+
+    /// Completes the symbols by the shorthand under the cursor, e.g. `$=>|$`.
+    pub fn shorthand_symbol_completions(&mut self) -> bool {
+        let leaf = self.cursor.leaf.clone();
+        if leaf.kind() != SyntaxKind::MathShorthand || leaf.range().end != self.cursor.cursor {
+            return false;
+        }
+
+        self.cursor.from = leaf.offset();
+        for entry in &SymbolIndex::get().entries {
+            let shorthands = entry
+                .shorthands()
+                .filter(|it| fuzzy_match(leaf.text(), it))
+                .collect::<Vec<_>>();
+            if !shorthands.is_empty() {
+                self.alias_symbol_completion(entry, shorthands.join(" ").into());
+            }
+        }
+
+        true
+    }
+
+    /// Completes the symbols by the LaTeX commands written without the
+    /// backslashes, e.g. `$leq|$`, which are not named the same as the
+    /// symbols.
+    pub fn bare_latex_symbol_completions(&mut self) {
+        if self.cursor.leaf.kind() != SyntaxKind::MathIdent {
+            return;
+        }
+
+        let query = self.cursor.leaf.text().clone();
+        let index = SymbolIndex::get();
+        for entry in &index.entries {
+            let aliases = entry
+                .aliases
+                .iter()
+                .filter_map(|alias| alias.strip_prefix('\\'))
+                .filter(|alias| fuzzy_match(&query, alias))
+                .filter(|alias| !index.entries.iter().any(|it| it.name.as_str() == *alias))
+                .collect::<Vec<_>>();
+            if !aliases.is_empty() {
+                self.alias_symbol_completion(entry, aliases.join(" ").into());
+            }
+        }
+    }
+
+    /// Adds the completion of a symbol matched by its aliases, which are
+    /// replaced by the name of the symbol.
+    fn alias_symbol_completion(&mut self, entry: &SymbolEntry, filter_text: EcoString) {
+        let text = self.cursor.source.text();
+        let is_word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '.');
+        // Keeps the symbol from merging with the surrounding identifiers, e.g.
+        // `$a\leq b$` to `$a lt.eq b$`.
+        let mut apply = EcoString::new();
+        if is_word(text[..self.cursor.from].chars().next_back()) {
+            apply.push(' ');
+        }
+        apply.push_str(&entry.name);
+        if is_word(text[self.cursor.cursor..].chars().next()) {
+            apply.push(' ');
+        }
+
+        let mut detail = symbol_detail(entry.ch);
+        if !entry.aliases.is_empty() {
+            let aliases = entry.aliases.iter().map(|alias| eco_format!("`{alias}`"));
+            detail.push_str(&eco_format!(
+                ", aliases: {}",
+                aliases.collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        self.push_completion(Completion {
+            kind: CompletionKind::Symbol(entry.ch),
+            label: entry.name.clone(),
+            label_details: Some(symbol_label_detail(entry.ch)),
+            filter_text: Some(filter_text),
+            apply: Some(apply),
+            detail: Some(detail),
+            ..Completion::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_index() {
+        let index = SymbolIndex::get();
+        let find = |name: &str| index.entries.iter().find(|it| it.name == name);
+
+        let alpha = find("alpha").unwrap();
+        assert_eq!(alpha.ch, 'α');
+        assert_eq!(
+            alpha.commands().collect::<Vec<_>>(),
+            vec![EcoString::from("\\alpha")]
+        );
+
+        let implies = find("arrow.r.double").unwrap();
+        assert_eq!(implies.ch, '⇒');
+        assert!(implies.aliases.contains(&"\\Rightarrow"));
+        assert_eq!(implies.shorthands().collect::<Vec<_>>(), vec!["=>"]);
+
+        for (alias, name) in SYMBOL_ALIASES {
+            assert!(
+                index.entries.iter().any(|it| it.aliases.contains(alias)),
+                "`{alias}` is not resolved to `{name}`"
+            );
+        }
+    }
+
+    #[test]
+    fn symbol_fuzzy_match() {
+        assert!(fuzzy_match("\\rarr", "\\rightarrow"));
+        assert!(fuzzy_match("\\Right", "\\rightarrow"));
+        assert!(fuzzy_match("=>", "==>"));
+        assert!(!fuzzy_match("\\leq", "\\alpha"));
+        assert!(!fuzzy_match("eq", "leq"));
+    }
+}
@@ -155,6 +155,16 @@ impl TypeCompletionWorker<'_, '_, '_, '_> {
             BuiltinTy::Module(..) => return None,
 
             BuiltinTy::Path(preference) => {
+                // The built-in styles are completed along with the CSL files,
+                // before the same names from the cast info, which are only
+                // described by their display names.
+                if *preference == PathPreference::Csl {
+                    for (name, docs) in crate::bib::builtin_csl_styles() {
+                        let value = Value::Str((*name).into());
+                        self.base
+                            .value_completion(None, &value, true, Some(docs.as_str()));
+                    }
+                }
                 let items = self.base.complete_path(preference);
                 self.base
                     .worker
@@ -39,14 +39,29 @@ impl Definition {
         self.decl.file_id()
     }
 
-    /// Gets name range of the definition.
-    pub fn name_range(&self, ctx: &SharedContext) -> Option<Range<usize>> {
-        self.decl.name_range(ctx)
-    }
+    /// Locates the definition in the source files.
+    ///
+    /// A declaration with a detached span, e.g. a bibliography entry or a
+    /// variable created from a value, is located by the range stored in the
+    /// declaration or by the span of its term.
+    pub fn location(&self, ctx: &SharedContext) -> Option<DefLocation> {
+        if let Some(loc) = locate_decl(ctx, &self.decl) {
+            return Some(loc);
+        }
 
-    /// Gets full range of the definition.
-    pub fn full_range(&self) -> Option<Range<usize>> {
-        self.decl.full_range()
+        let term = self.term.as_ref()?;
+        let span = match term.value() {
+            Some(Value::Func(func)) => func.span(),
+            Some(Value::Content(content)) => content.span(),
+            _ => term.span(),
+        };
+        let fid = span.id()?;
+        let range = ctx.source_by_id(fid).ok()?.range(span)?;
+        Some(DefLocation {
+            fid,
+            name_range: range.clone(),
+            full_range: range,
+        })
     }
 
     pub(crate) fn value(&self) -> Option<Value> {
@@ -54,29 +69,46 @@ impl Definition {
     }
 }
 
-trait HasNameRange {
-    /// Gets name range of the item.
-    fn name_range(&self, ctx: &SharedContext) -> Option<Range<usize>>;
+/// The location of a definition in the source files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefLocation {
+    /// The file containing the definition.
+    pub fid: TypstFileId,
+    /// The range of the name of the definition.
+    pub name_range: Range<usize>,
+    /// The range of the whole definition, e.g. a bibliography entry, which is
+    /// the name range if unknown.
+    pub full_range: Range<usize>,
 }
 
-impl HasNameRange for Decl {
-    fn name_range(&self, ctx: &SharedContext) -> Option<Range<usize>> {
-        if let Self::BibEntry(decl) = self {
-            return Some(decl.at.1.clone());
-        }
-
-        if !self.is_def() {
-            return None;
+/// Locates a declaration by its span, or by the range stored in it if the
+/// span is detached.
+fn locate_decl(ctx: &SharedContext, decl: &Decl) -> Option<DefLocation> {
+    let (fid, name_range, full_range) = match decl {
+        Decl::BibEntry(decl) => {
+            let (fid, name_range, full_range) = decl.at.as_ref();
+            (*fid, Some(name_range.clone()), full_range.clone())
         }
-
-        let span = self.span();
-        if let Some(range) = span.range() {
-            return Some(range.clone());
+        Decl::Docs(decl) => return locate_decl(ctx, decl.base()),
+        Decl::Module(decl) => (decl.fid, None, None),
+        _ => {
+            let span = decl.span();
+            let fid = span.id()?;
+            let name_range = decl.is_def().then(|| {
+                span.range()
+                    .or_else(|| ctx.source_by_id(fid).ok()?.range(span))
+            });
+            (fid, name_range.flatten(), None)
         }
+    };
 
-        let src = ctx.source_by_id(self.file_id()?).ok()?;
-        src.range(span)
-    }
+    let name_range = name_range.unwrap_or_default();
+    let full_range = full_range.unwrap_or_else(|| name_range.clone());
+    Some(DefLocation {
+        fid,
+        name_range,
+        full_range,
+    })
 }
 
 // todo: field definition
@@ -0,0 +1,414 @@
+//! A persistent cache of the analysis results on disk.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use serde::{de::DeserializeOwned, Serialize};
+use tinymist_std::hash::hash128;
+use tinymist_std::ImmutPath;
+
+/// The version of the analyzer, which invalidates all the entries cached by the
+/// other versions.
+const ANALYZER_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The version of Typst, whose standard library the cached entries refer to.
+const TYPST_VERSION: &str = env!("TYPST_VERSION");
+/// The version of the format of the cached entries, which is bumped when the
+/// persisted analysis results change their shapes or meanings.
+const SCHEMA_VERSION: u32 = 1;
+
+/// The default maximum size of the cache in bytes.
+const DEFAULT_MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+/// A persistent cache of the serializable analysis results, which survives the
+/// restarts of the server.
+///
+/// The entries are keyed by the hash of their inputs and the versions of the
+/// analyzer, Typst, and the format of the entries, so a stale entry is never
+/// read. The least recently used entries are evicted once the cache exceeds
+/// its maximum size.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: ImmutPath,
+    max_size: u64,
+    /// The number of bytes written since the last eviction.
+    written: Arc<AtomicU64>,
+    /// The hashes of the contents of the package directories, which are
+    /// computed once per session.
+    package_hashes: Arc<Mutex<FxHashMap<PathBuf, Option<u128>>>>,
+}
+
+impl DiskCache {
+    /// Creates a cache in the directory.
+    pub fn new(dir: ImmutPath) -> Self {
+        Self::with_max_size(dir, DEFAULT_MAX_SIZE)
+    }
+
+    /// Creates a cache in the directory, which holds at most `max_size` bytes.
+    pub fn with_max_size(dir: ImmutPath, max_size: u64) -> Self {
+        Self {
+            dir,
+            max_size,
+            // Evicts on the first write, so the entries left by the previous
+            // processes are bounded as well.
+            written: Arc::new(AtomicU64::new(Self::eviction_interval(max_size))),
+            package_hashes: Arc::default(),
+        }
+    }
+
+    /// Creates a cache in the cache directory of the user.
+    pub fn user() -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("tinymist/analysis");
+        Some(Self::new(dir.into()))
+    }
+
+    /// Gets the entry of a kind by the hash of its inputs.
+    pub fn get<T: DeserializeOwned>(&self, kind: &str, key: u128) -> Option<T> {
+        let path = self.path(kind, key);
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(value) => {
+                // Marks the entry as recently used.
+                let file = std::fs::File::options().write(true).open(&path);
+                let _ = file.and_then(|file| file.set_modified(SystemTime::now()));
+                Some(value)
+            }
+            Err(err) => {
+                log::warn!("failed to read analysis cache at {path:?}: {err}");
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Puts the entry of a kind by the hash of its inputs.
+    ///
+    /// The entry is not put if it cannot be serialized, e.g. it refers to a
+    /// value that cannot be persisted, or if it is too large.
+    pub fn put<T: Serialize>(&self, kind: &str, key: u128, value: &T) {
+        let data = match serde_json::to_vec(value) {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("analysis result of kind {kind} is not cached: {err}");
+                return;
+            }
+        };
+        if data.len() as u64 > self.max_size / 16 {
+            log::debug!("analysis result of kind {kind} is too large to cache");
+            return;
+        }
+
+        let path = self.path(kind, key);
+        let len = data.len() as u64;
+        if let Err(err) = write_atomic(&path, &data) {
+            log::warn!("failed to write analysis cache at {path:?}: {err}");
+            return;
+        }
+
+        let interval = Self::eviction_interval(self.max_size);
+        if self.written.fetch_add(len, Ordering::Relaxed) + len >= interval {
+            self.written.store(0, Ordering::Relaxed);
+            if let Err(err) = self.evict() {
+                log::warn!("failed to evict analysis cache at {:?}: {err}", self.dir);
+            }
+        }
+    }
+
+    /// Removes the least recently used entries until the cache fits in its
+    /// maximum size. Returns the number of the removed entries.
+    pub fn evict(&self) -> std::io::Result<usize> {
+        let mut entries = vec![];
+        let mut size = 0;
+        for entry in walkdir::WalkDir::new(&self.dir).follow_links(false) {
+            // The entries may be removed by the other processes meanwhile.
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) if is_not_found(err.io_error()) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+
+            let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            size += meta.len();
+            entries.push((used, meta.len(), entry.into_path()));
+        }
+
+        entries.sort();
+        let mut removed = 0;
+        for (_, len, path) in entries {
+            if size <= self.max_size {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(err) if is_not_found(Some(&err)) => {}
+                Err(err) => return Err(err),
+            }
+            size -= len;
+        }
+
+        Ok(removed)
+    }
+
+    /// Gets the hash of the contents of a package directory, which keys the
+    /// entries derived from the package. A package is hashed only once per
+    /// session, and `None` is returned if it cannot be read.
+    pub fn package_hash(&self, root: &Path) -> Option<u128> {
+        if let Some(hash) = self.package_hashes.lock().get(root) {
+            return *hash;
+        }
+
+        let hash = match hash_dir_contents(root) {
+            Ok(hash) => Some(hash),
+            Err(err) => {
+                log::warn!("failed to hash package at {root:?}: {err}");
+                None
+            }
+        };
+        self.package_hashes.lock().insert(root.to_owned(), hash);
+        hash
+    }
+
+    /// The number of bytes to write between the evictions.
+    fn eviction_interval(max_size: u64) -> u64 {
+        max_size / 8
+    }
+
+    fn path(&self, kind: &str, key: u128) -> PathBuf {
+        let key = hash128(&(ANALYZER_VERSION, TYPST_VERSION, SCHEMA_VERSION, key));
+        self.dir.join(kind).join(format!("{key:032x}.json"))
+    }
+}
+
+fn is_not_found(err: Option<&std::io::Error>) -> bool {
+    err.is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound)
+}
+
+/// Writes the data to a temporary file and then renames it, so that a
+/// concurrent reader never sees a partially written entry.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Whether the packages in a namespace never change once published, i.e. the
+/// `preview` namespace, so that the analysis results of their files can be
+/// cached on disk as well. The entries are still keyed by the contents of the
+/// packages, as a package in the local cache may be modified by hand.
+pub fn is_immutable_namespace(namespace: &str) -> bool {
+    namespace == "preview"
+}
+
+/// Hashes the contents of the files in a directory, e.g. a package.
+pub fn hash_dir_contents(dir: &Path) -> std::io::Result<u128> {
+    let mut files = vec![];
+    for entry in walkdir::WalkDir::new(dir).follow_links(false) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let rel = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            files.push((rel.to_owned(), std::fs::read(entry.path())?));
+        }
+    }
+    files.sort();
+    Ok(hash128(&files))
+}
+
+#[cfg(test)]
+mod tests {
+    use tinymist_analysis::persist::{PersistedExprInfo, PersistedTypeInfo};
+
+    use super::*;
+    use crate::tests::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("tinymist-disk-cache-{}", std::process::id()));
+        let cache = DiskCache::new(dir.as_path().into());
+
+        assert_eq!(cache.get::<Vec<String>>("test", 1), None);
+        cache.put("test", 1, &vec!["a".to_owned()]);
+        assert_eq!(
+            cache.get::<Vec<String>>("test", 1),
+            Some(vec!["a".to_owned()])
+        );
+        assert_eq!(cache.get::<Vec<String>>("test", 2), None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn eviction() {
+        let dir =
+            std::env::temp_dir().join(format!("tinymist-disk-cache-lru-{}", std::process::id()));
+        let cache = DiskCache::with_max_size(dir.as_path().into(), 1600);
+        // Each entry takes 62 bytes.
+        let value = "x".repeat(60);
+
+        // Too large entries are not put.
+        cache.put("test", 0, &"x".repeat(200));
+        assert_eq!(cache.get::<String>("test", 0), None);
+
+        for key in 0..3 {
+            cache.put("test", key, &value);
+            let file = std::fs::File::options()
+                .write(true)
+                .open(cache.path("test", key));
+            let used = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(key as u64 + 1);
+            file.unwrap().set_modified(used).unwrap();
+        }
+        // Reading the oldest entry marks it as recently used.
+        assert_eq!(cache.get::<String>("test", 0).as_ref(), Some(&value));
+
+        let small = DiskCache::with_max_size(dir.as_path().into(), 2 * 62);
+        assert_eq!(small.evict().unwrap(), 1);
+        assert_eq!(small.get::<String>("test", 1), None);
+        assert!(small.get::<String>("test", 0).is_some());
+        assert!(small.get::<String>("test", 2).is_some());
+
+        // The writes evict the entries periodically.
+        for key in 0..100 {
+            cache.put("test", key, &value);
+        }
+        let size: u64 = walkdir::WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum();
+        assert!(size <= 1600 + 1600 / 8, "{size}");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn persisted_analysis() {
+        let source = "#let f(x) = text(red, x)\n#let g = calc.max(1, 2) + 2pt + 50%\n#f[a]";
+        run_with_sources(source, |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let source = ctx.source_by_path(path).unwrap();
+                let ei = ctx.expr_stage(&source);
+                let ti = ctx.type_check(&source);
+
+                let data = serde_json::to_vec(&PersistedExprInfo::new(&ei)).unwrap();
+                let restored: PersistedExprInfo = serde_json::from_slice(&data).unwrap();
+                let restored = restored.restore(source.clone(), ei.revision);
+                assert_eq!(restored.root, ei.root);
+                assert_eq!(restored.exports, ei.exports);
+                assert_eq!(restored.resolves, ei.resolves);
+                assert_eq!(restored.exprs, ei.exprs);
+
+                let data = serde_json::to_vec(&PersistedTypeInfo::new(&ti)).unwrap();
+                let restored: PersistedTypeInfo = serde_json::from_slice(&data).unwrap();
+                let restored = restored.restore(source.id(), ti.revision);
+                assert_eq!(restored.exports, ti.exports);
+                assert_eq!(restored.mapping, ti.mapping);
+                assert_eq!(restored.vars.len(), ti.vars.len());
+            })
+        });
+    }
+
+    #[test]
+    fn package_def_docs() {
+        use std::str::FromStr;
+
+        use tinymist_project::{
+            CompileFontArgs, CompilePackageArgs, EntryState, ExportTarget, LspUniverseBuilder,
+        };
+        use typst::syntax::package::PackageSpec;
+        use typst::syntax::{FileId, VirtualPath};
+
+        use crate::adt::interner::Interned;
+        use crate::analysis::{Analysis, Definition};
+        use crate::syntax::Expr;
+
+        let dir =
+            std::env::temp_dir().join(format!("tinymist-disk-cache-docs-{}", std::process::id()));
+        let packages = dir.join("packages");
+        let pkg = packages.join("preview/example/0.1.0");
+        std::fs::create_dir_all(&pkg).unwrap();
+        let manifest =
+            "[package]\nname = \"example\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n";
+        std::fs::write(pkg.join("typst.toml"), manifest).unwrap();
+
+        let spec = PackageSpec::from_str("@preview/example:0.1.0").unwrap();
+        let lib = FileId::new(Some(spec), VirtualPath::new("lib.typ"));
+        let fonts = Arc::new(
+            LspUniverseBuilder::resolve_fonts(CompileFontArgs {
+                ignore_system_fonts: true,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+
+        // Gets the docs of `x` in a new session, which reads the disk cache.
+        let docs_of_x = || {
+            let registry = LspUniverseBuilder::resolve_package(
+                None,
+                Some(&CompilePackageArgs {
+                    package_path: Some(packages.clone()),
+                    package_cache_path: Some(dir.join("package-cache")),
+                }),
+            );
+            let verse = LspUniverseBuilder::build(
+                EntryState::new_rooted(dir.as_path().into(), None),
+                ExportTarget::Paged,
+                Default::default(),
+                Default::default(),
+                registry,
+                fonts.clone(),
+            );
+            let mut ctx = Arc::new(Analysis {
+                disk_cache: Some(DiskCache::new(dir.join("cache").into())),
+                ..Analysis::default()
+            })
+            .enter(verse.snapshot());
+
+            let ei = ctx.expr_stage_by_id(lib).unwrap();
+            let decl = match ei.exports.get(&Interned::new_str("x")).unwrap() {
+                Expr::Decl(decl) => decl.clone(),
+                Expr::Ref(ref_expr) => ref_expr.decl.clone(),
+                expr => panic!("unexpected export {expr:?}"),
+            };
+            let docs = ctx.def_docs(&Definition::new(decl, None)).unwrap();
+            docs.docs().to_string()
+        };
+        let cached_entries = || {
+            walkdir::WalkDir::new(dir.join("cache/package-def-docs"))
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .collect::<Vec<_>>()
+        };
+
+        std::fs::write(pkg.join("lib.typ"), "/// The docs.\n#let x = 1\n").unwrap();
+        assert_eq!(docs_of_x().trim(), "The docs.");
+        let entries = cached_entries();
+        assert_eq!(entries.len(), 1);
+
+        // The entry is read by the next session.
+        let entry = std::fs::read_to_string(&entries[0]).unwrap();
+        std::fs::write(&entries[0], entry.replace("The docs.", "The cached docs.")).unwrap();
+        assert_eq!(docs_of_x().trim(), "The cached docs.");
+
+        // The package modified in place is not read from the stale entry.
+        std::fs::write(pkg.join("lib.typ"), "/// The new docs.\n#let x = 1\n").unwrap();
+        assert_eq!(docs_of_x().trim(), "The new docs.");
+        assert_eq!(cached_entries().len(), 2);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,78 @@
+//! Dumps the analysis results of a file for debugging.
+
+use std::fmt::{self, Write};
+
+use typst::diag::SourceDiagnostic;
+
+use crate::prelude::*;
+use crate::ty::Ty;
+
+/// Dumps the expressions, the types, and the static diagnostics of a file, so
+/// that the analysis of a revision reported by a user can be reproduced. The
+/// items are sorted so that the dumps are stable across runs.
+pub fn dump_analysis(ctx: &mut LocalContext, source: &Source) -> String {
+    let mut out = String::new();
+    let _ = write_dump(ctx, source, &mut out);
+    out
+}
+
+fn write_dump(ctx: &mut LocalContext, source: &Source, f: &mut String) -> fmt::Result {
+    let ei = ctx.expr_stage(source);
+    writeln!(f, "= expressions")?;
+    writeln!(f, "{}", ei.root)?;
+
+    writeln!(f, "= exports")?;
+    let mut exports = ei.exports.iter().collect::<Vec<_>>();
+    exports.sort_by(|x, y| x.0.cmp(y.0));
+    for (name, expr) in exports {
+        writeln!(f, "{name} -> {expr}")?;
+    }
+
+    writeln!(f, "= types")?;
+    let ti = ctx.type_check(source);
+    let mut vars = ti
+        .vars
+        .values()
+        .map(|bounds| (bounds.name(), bounds))
+        .collect::<Vec<_>>();
+    vars.sort_by(|x, y| x.1.var.strict_cmp(&y.1.var));
+    for (name, bounds) in vars {
+        writeln!(f, "{name:?} = {:?}", ti.simplify(bounds.as_type(), true))?;
+    }
+    let mut mapping = ti
+        .mapping
+        .iter()
+        .map(|pair| (source.range(*pair.0).unwrap_or_default(), pair.1))
+        .collect::<Vec<_>>();
+    mapping.sort_by(|x, y| {
+        x.0.start
+            .cmp(&y.0.start)
+            .then_with(|| x.0.end.cmp(&y.0.end))
+    });
+    for (range, value) in mapping {
+        let ty = Ty::from_types(value.clone().into_iter());
+        writeln!(f, "{range:?} -> {ty:?}")?;
+    }
+
+    writeln!(f, "= diagnostics")?;
+    let errors = source.root().errors().into_iter();
+    let errors = errors.map(SourceDiagnostic::from);
+    let mut diags = errors
+        .chain(ctx.lint(source))
+        .map(|diag| {
+            let range = source.range(diag.span).unwrap_or_default();
+            (
+                range.start,
+                range.end,
+                format!("{:?}", diag.severity),
+                diag.message,
+            )
+        })
+        .collect::<Vec<_>>();
+    diags.sort();
+    for (start, end, severity, message) in diags {
+        writeln!(f, "{start}..{end} {severity}: {message}")?;
+    }
+
+    Ok(())
+}
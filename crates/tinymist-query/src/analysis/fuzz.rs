@@ -0,0 +1,302 @@
+//! Fuzzes the analyses with random Typst documents, to catch the panics in the
+//! expression checker, the type checker and the interner before the users do.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use tinymist_project::LspWorld;
+use tinymist_world::{EntryReader, ShadowApi, TaskInputs};
+use typst::foundations::Bytes;
+
+use crate::analysis::prelude::*;
+use crate::analysis::{dump_analysis, Analysis};
+
+/// The virtual path of the main file to fuzz.
+const FUZZ_ENTRY: &str = "/__fuzz__.typ";
+
+/// The names of the bindings, which are few so that the references often
+/// resolve to the definitions.
+const NAMES: &[&str] = &["x", "y", "f", "it", "body", "text", "calc", "range"];
+/// The operators of the binary expressions.
+const BIN_OPS: &[&str] = &[
+    "+", "-", "*", "/", "==", "!=", "<", ">=", "and", "or", "in", "not in",
+];
+/// The tokens inserted by the mutations, which mostly break the nesting.
+const JUNK: &[&str] = &["(", ")", "[", "]", "{", "}", "\"", "$", "#", ",", ":", "=>"];
+
+/// Generates random documents following the grammar of Typst, then mutates a
+/// part of them to exercise the error recovery of the parser.
+pub struct TypstGenerator {
+    rng: fastrand::Rng,
+    depth: usize,
+}
+
+impl TypstGenerator {
+    /// Creates a generator, which generates the same documents for the same
+    /// seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: fastrand::Rng::with_seed(seed),
+            depth: 0,
+        }
+    }
+
+    /// Generates a document.
+    pub fn document(&mut self) -> String {
+        let mut out = String::new();
+        for _ in 0..self.rng.usize(1..16) {
+            self.markup(&mut out);
+        }
+        if self.rng.u8(..4) == 0 {
+            out = self.mutate(out);
+        }
+        out
+    }
+
+    fn markup(&mut self, out: &mut String) {
+        match self.rng.u8(..10) {
+            0 => out.push_str("Hello world "),
+            1 => out.push_str(&format!("\n= {} ", self.name())),
+            2 => out.push_str("\n- item\n"),
+            3 => {
+                out.push_str(" $ ");
+                out.push_str(self.name());
+                out.push_str("^2 + sum_(i=0)^n i $ ");
+            }
+            4 => out.push_str("*strong* _emph_ <lbl> @lbl "),
+            _ => {
+                out.push('#');
+                self.stmt(out);
+                out.push('\n');
+            }
+        }
+    }
+
+    fn stmt(&mut self, out: &mut String) {
+        match self.rng.u8(..12) {
+            0 => {
+                out.push_str(&format!("let {} = ", self.name()));
+                self.expr(out);
+            }
+            1 => {
+                out.push_str(&format!("let {}({}, ..args) = ", self.name(), self.name()));
+                self.expr(out);
+            }
+            2 => {
+                out.push_str(&format!("let ({}, {}) = ", self.name(), self.name()));
+                self.expr(out);
+            }
+            3 => {
+                out.push_str("if ");
+                self.expr(out);
+                self.block(out);
+                out.push_str(" else ");
+                self.block(out);
+            }
+            4 => {
+                out.push_str(&format!("for {} in ", self.name()));
+                self.expr(out);
+                self.block(out);
+            }
+            5 => {
+                out.push_str("set text(size: ");
+                self.expr(out);
+                out.push(')');
+            }
+            6 => {
+                out.push_str("show heading: ");
+                self.expr(out);
+            }
+            7 => out.push_str(&format!("import \"{FUZZ_ENTRY}\": {}", self.name())),
+            8 => {
+                out.push_str("context ");
+                self.expr(out);
+            }
+            _ => self.expr(out),
+        }
+    }
+
+    fn block(&mut self, out: &mut String) {
+        out.push_str(" {");
+        for _ in 0..self.rng.usize(..3) {
+            self.stmt(out);
+            out.push_str("; ");
+        }
+        out.push('}');
+    }
+
+    fn expr(&mut self, out: &mut String) {
+        // Only the leaves are generated deep in the tree to bound the size.
+        let choices = if self.depth > 4 { 6 } else { 16 };
+        self.depth += 1;
+        match self.rng.u8(..choices) {
+            0 => out.push_str(&self.rng.i32(-10..100).to_string()),
+            1 => out.push_str(["1pt", "2em", "50%", "1.5", "1fr"][self.rng.usize(..5)]),
+            2 => out.push_str(["\"s\"", "true", "none", "auto"][self.rng.usize(..4)]),
+            3..=5 => out.push_str(self.name()),
+            6 | 7 => {
+                out.push_str(self.name());
+                out.push('(');
+                self.args(out);
+                out.push(')');
+            }
+            8 => {
+                out.push('(');
+                self.args(out);
+                out.push_str(",)");
+            }
+            9 => {
+                out.push_str(&format!("(a: {}, b: ", self.name()));
+                self.expr(out);
+                out.push(')');
+            }
+            10 => {
+                out.push_str(&format!("({}) => ", self.name()));
+                self.expr(out);
+            }
+            11 => {
+                self.expr(out);
+                out.push_str(&format!(" {} ", BIN_OPS[self.rng.usize(..BIN_OPS.len())]));
+                self.expr(out);
+            }
+            12 => {
+                out.push_str(["-", "not "][self.rng.usize(..2)]);
+                self.expr(out);
+            }
+            13 => {
+                out.push_str(self.name());
+                out.push_str([".at(0)", ".len()", ".map(it => it)", ".field"][self.rng.usize(..4)]);
+            }
+            14 => {
+                out.push('[');
+                self.markup(out);
+                out.push(']');
+            }
+            _ => self.block(out),
+        }
+        self.depth -= 1;
+    }
+
+    fn args(&mut self, out: &mut String) {
+        for i in 0..self.rng.usize(..3) {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            match self.rng.u8(..4) {
+                0 => out.push_str(&format!("{}: ", self.name())),
+                1 => out.push_str(".."),
+                _ => {}
+            }
+            self.expr(out);
+        }
+    }
+
+    fn name(&mut self) -> &'static str {
+        NAMES[self.rng.usize(..NAMES.len())]
+    }
+
+    /// Deletes a slice of the document or inserts a junk token into it.
+    fn mutate(&mut self, text: String) -> String {
+        let boundaries = text.char_indices().map(|(idx, _)| idx);
+        let boundaries = boundaries.chain([text.len()]).collect::<Vec<_>>();
+        let at = boundaries[self.rng.usize(..boundaries.len())];
+        if self.rng.bool() {
+            let end = boundaries[self.rng.usize(..boundaries.len())].max(at);
+            format!("{}{}", &text[..at], &text[end..])
+        } else {
+            let junk = JUNK[self.rng.usize(..JUNK.len())];
+            format!("{}{junk}{}", &text[..at], &text[at..])
+        }
+    }
+}
+
+/// A document on which the analyses panic.
+#[derive(Debug, Clone)]
+pub struct FuzzFailure {
+    /// The seed generating the document.
+    pub seed: u64,
+    /// The content of the document.
+    pub source: String,
+    /// The message of the panic.
+    pub message: String,
+}
+
+/// Runs the analyses on a document, i.e. the target to fuzz.
+pub fn fuzz_target(ctx: &mut LocalContext, source: &Source) {
+    // Dumping checks the expressions and the types of the document, and
+    // resolves the interned names and types while printing them.
+    let _ = dump_analysis(ctx, source);
+}
+
+/// Fuzzes the analyses on the documents generated by the seeds in a range,
+/// each in a world forked from the given one. Returns the documents on which
+/// the analyses panic.
+pub fn fuzz_analysis(analysis: &Analysis, world: &LspWorld, seeds: Range<u64>) -> Vec<FuzzFailure> {
+    let mut failures = vec![];
+    for seed in seeds {
+        let text = TypstGenerator::new(seed).document();
+        let Some(world) = fuzz_world(world, &text) else {
+            continue;
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let main = world.main();
+            let mut ctx = analysis.enter(world);
+            if let Ok(source) = ctx.source_by_id(main) {
+                fuzz_target(&mut ctx, &source);
+            }
+        }));
+        if let Err(payload) = result {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => (*message).to_owned(),
+                    Err(_) => "unknown panic".to_owned(),
+                },
+            };
+            failures.push(FuzzFailure {
+                seed,
+                source: text,
+                message,
+            });
+        }
+    }
+
+    failures
+}
+
+/// Forks a world whose main file contains the document.
+fn fuzz_world(world: &LspWorld, text: &str) -> Option<LspWorld> {
+    let mut world = world.task(TaskInputs {
+        entry: Some(
+            world
+                .entry_state()
+                .select_in_workspace(Path::new(FUZZ_ENTRY)),
+        ),
+        ..TaskInputs::default()
+    });
+    world.take_db();
+    world
+        .map_shadow_by_id(world.main(), Bytes::from_string(text.to_owned()))
+        .ok()?;
+    Some(world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let docs = (0..8).map(|seed| TypstGenerator::new(seed).document());
+        let again = (0..8).map(|seed| TypstGenerator::new(seed).document());
+        assert!(docs.eq(again));
+    }
+
+    #[test]
+    fn no_panic() {
+        crate::tests::run_with_sources("", |verse, _path| {
+            let failures = fuzz_analysis(&Analysis::default(), &verse.snapshot(), 0..64);
+            assert!(failures.is_empty(), "{failures:#?}");
+        });
+    }
+}
@@ -7,9 +7,12 @@ use std::{collections::HashSet, ops::Deref};
 use comemo::{Track, Tracked};
 use lsp_types::Url;
 use parking_lot::Mutex;
+use rayon::prelude::*;
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use tinymist_analysis::docs::DocString;
-use tinymist_analysis::stats::AllocStats;
+use tinymist_analysis::persist::{PersistedExprInfo, PersistedTypeInfo};
+use tinymist_analysis::stats::{AllocStats, InternerStats};
 use tinymist_analysis::ty::term_value;
 use tinymist_analysis::{analyze_expr_, analyze_import_};
 use tinymist_lint::LintInfo;
@@ -17,6 +20,7 @@ use tinymist_project::{LspComputeGraph, LspWorld, TaskWhen};
 use tinymist_std::hash::{hash128, FxDashMap};
 use tinymist_std::typst::TypstDocument;
 use tinymist_world::debug_loc::DataSource;
+use tinymist_world::package::PackageRegistry;
 use tinymist_world::vfs::{PathResolution, WorkspaceResolver};
 use tinymist_world::{EntryReader, DETACHED_ENTRY};
 use typst::diag::{
@@ -30,23 +34,26 @@ use typst::syntax::package::{PackageManifest, PackageSpec};
 use typst::syntax::{Span, VirtualPath};
 use typst_shim::eval::{eval_compat, Eval};
 
-use super::{LspQuerySnapshot, TypeEnv};
+use super::{is_immutable_namespace, DiskCache, DocumentVersions, LspQuerySnapshot, TypeEnv};
+use crate::adt::interner;
 use crate::adt::revision::{RevisionLock, RevisionManager, RevisionManagerLike, RevisionSlot};
 use crate::analysis::prelude::*;
 use crate::analysis::{
     analyze_signature, bib_info, definition, post_type_check, AnalysisStats, BibInfo,
-    CompletionFeat, Definition, PathPreference, QueryStatGuard, SemanticTokenCache,
-    SemanticTokenContext, SemanticTokens, Signature, SignatureTarget, Ty, TypeInfo,
+    CompletionFeat, Definition, PathPreference, QueryCacheStats, QueryStatGuard,
+    SemanticTokenCache, SemanticTokenContext, SemanticTokens, Signature, SignatureTarget, Ty,
+    TypeInfo,
 };
 use crate::docs::{DefDocs, TidyModuleDocs};
 use crate::syntax::{
     classify_syntax, construct_module_dependencies, is_mark, resolve_id_by_path,
-    scan_workspace_files, Decl, DefKind, ExprInfo, ExprRoute, LexicalScope, ModuleDependency,
-    SyntaxClass,
+    scan_workspace_files, Decl, DefKind, ExprInfo, ExprRoute, ImportGraph, LexicalScope,
+    ModuleDependency, SyntaxClass,
 };
 use crate::upstream::{tooltip_, Tooltip};
 use crate::{
-    ColorTheme, CompilerQueryRequest, LspPosition, LspRange, LspWorldExt, PositionEncoding,
+    ColorTheme, CompilerQueryRequest, IndexFeat, LspPosition, LspRange, LspWorldExt,
+    PositionEncoding, ReachableLints, SpellFeat,
 };
 
 macro_rules! interned_str {
@@ -80,6 +87,14 @@ pub struct Analysis {
     pub color_theme: ColorTheme,
     /// When to trigger the lint.
     pub lint: TaskWhen,
+    /// Whether to check all the files reachable from the entry.
+    pub project_diagnostics: bool,
+    /// The markers and locale of the index terms.
+    pub index: IndexFeat,
+    /// The dictionaries and the word list of the spell checking.
+    pub spell: SpellFeat,
+    /// The lint results of the last check of the reachable files.
+    pub reachable_lints: Arc<Mutex<ReachableLints>>,
     /// The periscope provider.
     pub periscope: Option<Arc<dyn PeriscopeProvider + Send + Sync>>,
     /// The global worker resources for analysis.
@@ -88,12 +103,36 @@ pub struct Analysis {
     pub tokens_caches: Arc<Mutex<SemanticTokenCache>>,
     /// The global caches for analysis.
     pub caches: AnalysisGlobalCaches,
+    /// The persistent cache on disk, which is shared by the server instances.
+    pub disk_cache: Option<DiskCache>,
     /// The revision-managed cache for analysis.
     pub analysis_rev_cache: Arc<Mutex<AnalysisRevCache>>,
+    /// The limits of the memory used by the caches, which can be changed by
+    /// the user during the session.
+    pub cache_limits: Arc<Mutex<CacheLimits>>,
     /// The statistics about the analyzers.
     pub stats: Arc<AnalysisStats>,
 }
 
+/// The limits of the memory used by the analysis caches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheLimits {
+    /// The maximum estimated memory of the cached analysis results in MiB,
+    /// beyond which the least recently used results are evicted.
+    pub max_memory: Option<usize>,
+    /// The idle time in seconds after which the caches are evicted.
+    pub idle_eviction_interval: Option<u64>,
+}
+
+impl CacheLimits {
+    /// Gets the maximum estimated memory of the cached analysis results in
+    /// bytes.
+    pub fn max_memory_bytes(&self) -> Option<usize> {
+        self.max_memory.map(|mib| mib.saturating_mul(1024 * 1024))
+    }
+}
+
 impl Analysis {
     /// Enters the analysis context.
     pub fn enter(&self, world: LspWorld) -> LocalContextGuard {
@@ -132,6 +171,7 @@ impl Analysis {
         let rev_lock = self.lock_revision(req);
         LspQuerySnapshot {
             snap,
+            versions: DocumentVersions::default(),
             analysis: self,
             rev_lock,
         }
@@ -158,6 +198,7 @@ impl Analysis {
             },
             inner: grid.manager.lock_estimated(),
             grid: self.analysis_rev_cache.clone(),
+            limits: self.cache_limits.clone(),
         }
     }
 
@@ -168,10 +209,34 @@ impl Analysis {
         self.caches.def_signatures.clear();
         self.caches.static_signatures.clear();
         self.caches.terms.clear();
+        self.caches.snippets.clear();
         self.tokens_caches.lock().clear();
         self.analysis_rev_cache.lock().clear();
     }
 
+    /// Evicts the caches that are cheap to recompute, the rendered artifacts
+    /// and the least recently used results beyond the memory limit, e.g. when
+    /// the server is idle.
+    pub fn evict_idle(&self) {
+        self.caches.signatures.clear();
+        self.caches.docstrings.clear();
+        self.caches.def_signatures.clear();
+        self.caches.static_signatures.clear();
+        self.caches.terms.clear();
+        self.caches.snippets.clear();
+        self.tokens_caches.lock().evict();
+
+        let budget = self.cache_limits.lock().max_memory_bytes();
+        if let Some(budget) = budget {
+            self.analysis_rev_cache.lock().sweep(budget);
+        }
+    }
+
+    /// Releases the semantic tokens cached for the delta requests on a file.
+    pub fn release_tokens(&self, path: &Path) {
+        self.tokens_caches.lock().remove(path);
+    }
+
     /// Report the statistics of the analysis.
     pub fn report_query_stats(&self) -> String {
         self.stats.report()
@@ -182,6 +247,38 @@ impl Analysis {
         AllocStats::report()
     }
 
+    /// Collects the statistics of the interners.
+    pub fn interner_stats(&self) -> Vec<InternerStats> {
+        AllocStats::collect()
+    }
+
+    /// Collects the hit rates of the cached queries.
+    pub fn query_cache_stats(&self) -> Vec<QueryCacheStats> {
+        self.stats.query_cache_stats()
+    }
+
+    /// Gets the number of the entries in each cache of the analysis.
+    pub fn cache_sizes(&self) -> BTreeMap<String, usize> {
+        let caches = &self.caches;
+        let rev_cache = self.analysis_rev_cache.lock();
+        let slot = &rev_cache.default_slot;
+        let sizes = [
+            ("defSignatures", caches.def_signatures.m.len()),
+            ("staticSignatures", caches.static_signatures.m.len()),
+            ("signatures", caches.signatures.m.len()),
+            ("docstrings", caches.docstrings.m.len()),
+            ("terms", caches.terms.m.len()),
+            ("snippets", caches.snippets.m.len()),
+            ("exprStage", slot.expr_stage.global.lock().len()),
+            ("typeCheck", slot.type_check.global.lock().len()),
+            ("lint", slot.lint.global.lock().len()),
+        ];
+        sizes
+            .into_iter()
+            .map(|(name, size)| (name.to_owned(), size))
+            .collect()
+    }
+
     /// Get configured trigger suggest command.
     pub fn trigger_suggest(&self, context: bool) -> Option<Interned<str>> {
         interned_str!(INTERNED, "editor.action.triggerSuggest");
@@ -290,6 +387,7 @@ impl LocalContextGuard {
         caches.def_signatures.retain(|(l, _)| retainer(*l));
         caches.static_signatures.retain(|(l, _)| retainer(*l));
         caches.terms.retain(|(l, _)| retainer(*l));
+        caches.snippets.retain(|(l, _)| retainer(*l));
         caches.signatures.retain(|(l, _)| retainer(*l));
         caches.docstrings.retain(|(l, _)| retainer(*l));
     }
@@ -479,7 +577,8 @@ impl LocalContext {
         cache.get_or_init(|| self.shared.type_check(source)).clone()
     }
 
-    pub(crate) fn lint(&mut self, source: &Source) -> EcoVec<SourceDiagnostic> {
+    /// Lints a source file.
+    pub fn lint(&mut self, source: &Source) -> EcoVec<SourceDiagnostic> {
         self.shared.lint(source).diagnostics
     }
 
@@ -503,7 +602,46 @@ impl LocalContext {
         Some(scheme.simplify(ty, false))
     }
 
+    /// Gets the documentation of a definition.
+    ///
+    /// The documentation of the definitions in the immutable packages is
+    /// cached on disk, so that it is not computed again after restarts.
     pub(crate) fn def_docs(&mut self, def: &Definition) -> Option<DefDocs> {
+        let cache = self.analysis.disk_cache.clone();
+        let key = cache
+            .as_ref()
+            .and_then(|cache| self.package_def_key(cache, def));
+        let Some((cache, key)) = cache.zip(key) else {
+            return self.compute_def_docs(def);
+        };
+
+        if let Some(docs) = cache.get("package-def-docs", key) {
+            return Some(docs);
+        }
+        let docs = self.compute_def_docs(def)?;
+        cache.put("package-def-docs", key, &docs);
+        Some(docs)
+    }
+
+    /// Gets the key of a definition in the disk cache, if it is defined in an
+    /// immutable package. The key is stable across the server processes.
+    fn package_def_key(&self, cache: &DiskCache, def: &Definition) -> Option<u128> {
+        let fid = def.decl.file_id()?;
+        let spec = fid
+            .package()
+            .filter(|spec| is_immutable_namespace(&spec.namespace))?;
+        let contents = self.package_hash(cache, spec)?;
+        let range = self.source_by_id(fid).ok()?.range(def.decl.span())?;
+        Some(hash128(&(
+            spec.to_string(),
+            contents,
+            fid.vpath().as_rooted_path(),
+            def.decl.name().to_string(),
+            range,
+        )))
+    }
+
+    fn compute_def_docs(&mut self, def: &Definition) -> Option<DefDocs> {
         // let plain_docs = sym.head.docs.as_deref();
         // let plain_docs = plain_docs.or(sym.head.oneliner.as_deref());
         match def.decl.kind() {
@@ -746,10 +884,48 @@ impl SharedContext {
         use crate::syntax::expr_of;
         let guard = self.query_stat(source.id(), "expr_stage");
         self.slot.expr_stage.compute(hash128(&source), |prev| {
-            expr_of(self.clone(), source.clone(), route, guard, prev)
+            // The previous revision is reused incrementally if any.
+            let persisted = prev.is_none().then(|| self.persist_key(source)).flatten();
+            if let Some((cache, key)) = &persisted {
+                if let Some(ei) = cache.get::<PersistedExprInfo>("expr-info", *key) {
+                    return ei.restore(source.clone(), self.revision());
+                }
+            }
+
+            let ei = expr_of(self.clone(), source.clone(), route, guard, prev);
+            if let Some((cache, key)) = &persisted {
+                cache.put("expr-info", *key, &PersistedExprInfo::new(&ei));
+            }
+            ei
         })
     }
 
+    /// Gets the key of a file in the disk cache, if it is in an immutable
+    /// package. The key is stable across the server processes.
+    fn persist_key(&self, source: &Source) -> Option<(DiskCache, u128)> {
+        let cache = self.analysis.disk_cache.clone()?;
+        let fid = source.id();
+        let spec = fid
+            .package()
+            .filter(|spec| is_immutable_namespace(&spec.namespace))?;
+        // The files imported from the same package are hashed as well.
+        let contents = self.package_hash(&cache, spec)?;
+        let key = hash128(&(
+            spec.to_string(),
+            contents,
+            fid.vpath().as_rooted_path(),
+            source.text(),
+        ));
+        Some((cache, key))
+    }
+
+    /// Gets the hash of the contents of a package, so that a package modified
+    /// in place never reads the stale entries in the disk cache.
+    fn package_hash(&self, cache: &DiskCache, spec: &PackageSpec) -> Option<u128> {
+        let root = self.world.registry.resolve(spec).ok()?;
+        cache.package_hash(&root)
+    }
+
     pub(crate) fn exports_of(
         self: &Arc<Self>,
         source: &Source,
@@ -784,8 +960,19 @@ impl SharedContext {
                 return cache_hint;
             }
 
+            let persisted = self.persist_key(source);
+            if let Some((cache, key)) = &persisted {
+                if let Some(ti) = cache.get::<PersistedTypeInfo>("type-info", *key) {
+                    return Arc::new(ti.restore(ei.fid, ei.revision));
+                }
+            }
+
             guard.miss();
-            type_check(self.clone(), ei, route)
+            let ti = type_check(self.clone(), ei, route);
+            if let Some((cache, key)) = &persisted {
+                cache.put("type-info", *key, &PersistedTypeInfo::new(&ti));
+            }
+            ti
         })
     }
 
@@ -834,6 +1021,33 @@ impl SharedContext {
         res
     }
 
+    /// Gets the rendered image of a snippet by the compiled content, or renders
+    /// it if it is not rendered yet.
+    ///
+    /// The images are also keyed by the revision of the world, since the
+    /// imported files and the fonts affect the rendering as well.
+    pub(crate) fn rendered_snippet(
+        &self,
+        content: EcoString,
+        render: impl FnOnce() -> Option<Arc<str>>,
+    ) -> Option<Arc<str>> {
+        let content = (self.world().revision().get(), content);
+        let key = hash128(&content);
+        let cached = self.analysis.caches.snippets.m.get(&key);
+        if let Some(slot) = cached.filter(|slot| slot.1 .0 == content) {
+            return slot.1 .1.clone();
+        }
+
+        let res = render();
+        self.analysis
+            .caches
+            .snippets
+            .m
+            .entry(key)
+            .or_insert_with(|| (self.lifetime, (content, res.clone())));
+        res
+    }
+
     pub(crate) fn def_of_span(
         self: &Arc<Self>,
         source: &Source,
@@ -1003,7 +1217,7 @@ impl SharedContext {
             .into()
     }
 
-    fn query_stat(&self, id: TypstFileId, query: &'static str) -> QueryStatGuard {
+    pub(crate) fn query_stat(&self, id: TypstFileId, query: &'static str) -> QueryStatGuard {
         let stats = &self.analysis.stats.query_stats;
         let entry = stats.entry(id).or_default();
         let entry = entry.entry(query).or_default();
@@ -1027,36 +1241,28 @@ impl SharedContext {
         // });
     }
 
+    /// Analyzes the modules reachable from the entry point of a package, e.g.
+    /// to warm up the caches.
+    ///
+    /// The modules are analyzed on the rayon pool in waves of the import
+    /// graph, so a module is analyzed after the modules it imports and the
+    /// parallel analyses never wait for each other.
     pub(crate) fn preload_package(self: Arc<Self>, entry_point: TypstFileId) {
         crate::log_debug_ct!("preload package start {entry_point:?}");
 
-        #[derive(Clone)]
-        struct Preloader {
-            shared: Arc<SharedContext>,
-            analyzed: Arc<Mutex<HashSet<TypstFileId>>>,
-        }
-
-        impl Preloader {
-            fn work(&self, fid: TypstFileId) {
-                crate::log_debug_ct!("preload package {fid:?}");
-                let source = self.shared.source_by_id(fid).ok().unwrap();
-                let exprs = self.shared.expr_stage(&source);
-                self.shared.type_check(&source);
-                exprs.imports.iter().for_each(|(fid, _)| {
-                    if !self.analyzed.lock().insert(*fid) {
-                        return;
-                    }
-                    self.work(*fid);
-                })
-            }
+        let graph = ImportGraph::scan(&self.world, entry_point);
+        for wave in graph.waves() {
+            wave.into_par_iter().for_each(|group| {
+                for fid in group {
+                    crate::log_debug_ct!("preload package {fid:?}");
+                    let Ok(source) = self.source_by_id(fid) else {
+                        continue;
+                    };
+                    self.expr_stage(&source);
+                    self.type_check(&source);
+                }
+            });
         }
-
-        let preloader = Preloader {
-            shared: self,
-            analyzed: Arc::default(),
-        };
-
-        preloader.work(entry_point);
     }
 }
 
@@ -1130,6 +1336,66 @@ impl<K, V> IncrCacheMap<K, V> {
     }
 }
 
+/// The estimated memory of an entry in the maps of the analysis results.
+const MAP_ENTRY_SIZE: usize = 64;
+
+/// Estimates the memory of an analysis result in bytes, which is used to keep
+/// the caches under the limit.
+trait EstimateSize {
+    fn estimate_size(&self) -> usize;
+}
+
+impl EstimateSize for ExprInfo {
+    fn estimate_size(&self) -> usize {
+        let entries = self.resolves.len()
+            + self.docstrings.len()
+            + self.exprs.len()
+            + self.imports.len()
+            + self.closures.len();
+        self.source.text().len() + entries * MAP_ENTRY_SIZE
+    }
+}
+
+impl EstimateSize for Arc<TypeInfo> {
+    fn estimate_size(&self) -> usize {
+        let entries =
+            self.exports.len() + self.vars.len() + self.var_docs.len() + self.mapping.len();
+        entries * MAP_ENTRY_SIZE
+    }
+}
+
+impl EstimateSize for LintInfo {
+    fn estimate_size(&self) -> usize {
+        self.diagnostics.len() * MAP_ENTRY_SIZE
+    }
+}
+
+/// A cache whose least recently used entries can be swept.
+trait SweepCache {
+    /// Gets the keys, the last used revisions and the estimated sizes of the
+    /// entries.
+    fn usage(&self) -> Vec<(u128, usize, usize)>;
+    /// Evicts an entry.
+    fn evict(&self, key: u128);
+}
+
+impl<V: EstimateSize> SweepCache for IncrCacheMap<u128, V> {
+    fn usage(&self) -> Vec<(u128, usize, usize)> {
+        let global = self.global.lock();
+        let entries = global.iter();
+        entries
+            .map(|entry| {
+                let (revision, value) = entry.value();
+                (*entry.key(), *revision, value.estimate_size())
+            })
+            .collect()
+    }
+
+    fn evict(&self, key: u128) {
+        self.global.lock().remove(&key);
+    }
+}
+
 #[derive(Clone)]
 struct CacheMap<T> {
     m: Arc<FxDashMap<u128, (u64, T)>>,
@@ -1185,6 +1451,7 @@ pub struct AnalysisGlobalCaches {
     signatures: CacheMap<DeferredCompute<Option<Signature>>>,
     docstrings: CacheMap<DeferredCompute<Option<Arc<DocString>>>>,
     terms: CacheMap<(Value, Ty)>,
+    snippets: CacheMap<((usize, EcoString), Option<Arc<str>>)>,
 }
 
 /// A local (lsp request spanned) cache for all level of analysis results of a
@@ -1261,6 +1528,38 @@ impl AnalysisRevCache {
         self.default_slot = Default::default();
     }
 
+    /// Evicts the least recently used results until their estimated size fits
+    /// in the budget in bytes.
+    fn sweep(&mut self, budget: usize) {
+        let slot = &self.default_slot;
+        let maps: [&dyn SweepCache; 3] = [&slot.expr_stage, &slot.type_check, &slot.lint];
+
+        let mut entries = vec![];
+        for (idx, map) in maps.iter().enumerate() {
+            for (key, revision, size) in map.usage() {
+                entries.push((revision, size, idx, key));
+            }
+        }
+        let mut total = entries.iter().map(|entry| entry.1).sum::<usize>();
+        if total <= budget {
+            return;
+        }
+
+        // The results are touched in the revisions using them, so the oldest
+        // revisions are the least recently used.
+        entries.sort_by_key(|entry| entry.0);
+        let mut evicted = 0;
+        for (_, size, idx, key) in entries {
+            if total <= budget {
+                break;
+            }
+            maps[idx].evict(key);
+            total -= size;
+            evicted += 1;
+        }
+        log::info!("analysis cache: evicted {evicted} results to fit in {budget} bytes");
+    }
+
     /// Find the last revision slot by revision number.
     fn find_revision(
         &mut self,
@@ -1287,6 +1586,7 @@ pub struct AnalysisRevLock {
     inner: RevisionLock,
     tokens: Option<SemanticTokenContext>,
     grid: Arc<Mutex<AnalysisRevCache>>,
+    limits: Arc<Mutex<CacheLimits>>,
 }
 
 impl Drop for AnalysisRevLock {
@@ -1296,8 +1596,20 @@ impl Drop for AnalysisRevLock {
 
         if let Some(gc_revision) = gc_revision {
             let grid = self.grid.clone();
+            let budget = self.limits.lock().max_memory_bytes();
             rayon::spawn(move || {
-                grid.lock().gc(gc_revision);
+                {
+                    let mut grid = grid.lock();
+                    grid.gc(gc_revision);
+                    if let Some(budget) = budget {
+                        grid.sweep(budget);
+                    }
+                }
+
+                // Frees the interned objects that were only referenced by the
+                // stale revisions.
+                let collected = interner::collect_garbage();
+                log::debug!("collected {collected} interned objects at revision {gc_revision}");
             });
         }
     }
@@ -1348,7 +1660,8 @@ fn analyze_bib(
         .flat_map(|bib_path: EcoString| {
             let bib_fid = resolve_id_by_path(world.deref(), elem_fid, &bib_path)?;
             Some((bib_fid, world.file(bib_fid).ok()?))
-        });
+        })
+        .collect();
 
     bib_info(csl_style, files)
 }
@@ -1390,10 +1703,7 @@ fn find_loc(
     };
 
     let line = r as u32;
-    let character = match encoding {
-        PositionEncoding::Utf8 => column_prefix.chars().count(),
-        PositionEncoding::Utf16 => column_prefix.chars().map(|ch| ch.len_utf16()).sum(),
-    } as u32;
+    let character = encoding.len_of(column_prefix) as u32;
 
     Some(LspPosition { line, character })
 }
@@ -1443,3 +1753,62 @@ impl RateLimiter {
         f()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+
+    fn cached_size(grid: &AnalysisRevCache) -> (usize, usize) {
+        let slot = &grid.default_slot;
+        let maps: [&dyn SweepCache; 3] = [&slot.expr_stage, &slot.type_check, &slot.lint];
+        let usage = maps.iter().flat_map(|map| map.usage()).collect::<Vec<_>>();
+        (usage.len(), usage.iter().map(|entry| entry.2).sum())
+    }
+
+    #[test]
+    fn sweep() {
+        let source = "#let a = 1\n-----\n#let b = 2\n-----\n#import \"s0.typ\": a\n#import \"s1.typ\": b\n#(a + b)";
+        run_with_sources(source, |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let source = ctx.source_by_path(&path).unwrap();
+                let dep = ctx.source_by_path(&path.with_file_name("s0.typ")).unwrap();
+                ctx.type_check(&source);
+                ctx.type_check(&dep);
+
+                let mut grid = ctx.analysis.analysis_rev_cache.lock();
+                let (count, size) = cached_size(&grid);
+                assert!(count >= 4, "{count}");
+
+                // Nothing is evicted within the budget.
+                grid.sweep(size);
+                assert_eq!(cached_size(&grid), (count, size));
+
+                // The results of the oldest revision are evicted first.
+                let slot = &grid.default_slot;
+                for mut entry in slot.expr_stage.global.lock().iter_mut() {
+                    let is_dep = entry.value().1.fid == dep.id();
+                    entry.value_mut().0 = if is_dep { 1 } else { 2 };
+                }
+                for mut entry in slot.type_check.global.lock().iter_mut() {
+                    entry.value_mut().0 = 2;
+                }
+                for mut entry in slot.lint.global.lock().iter_mut() {
+                    entry.value_mut().0 = 2;
+                }
+                grid.sweep(size - 1);
+                let expr_stage = grid.default_slot.expr_stage.global.lock();
+                assert!(expr_stage
+                    .iter()
+                    .all(|entry| entry.value().1.fid != dep.id()));
+                assert!(!expr_stage.is_empty());
+                drop(expr_stage);
+                assert_eq!(cached_size(&grid).0, count - 1);
+
+                // Everything is evicted by the zero budget.
+                grid.sweep(0);
+                assert_eq!(cached_size(&grid), (0, 0));
+            })
+        });
+    }
+}
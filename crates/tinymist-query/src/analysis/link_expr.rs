@@ -4,6 +4,7 @@ use std::str::FromStr;
 
 use lsp_types::Url;
 use tinymist_world::package::PackageSpec;
+use typst::syntax::VirtualPath;
 
 use super::prelude::*;
 
@@ -56,7 +57,18 @@ pub enum LinkTarget {
 impl LinkTarget {
     pub(crate) fn resolve(&self, ctx: &mut LocalContext) -> Option<Url> {
         match self {
-            Self::Package(..) => None,
+            Self::Package(spec) => {
+                // Links to the entrypoint of the package, which is resolved to the package
+                // cache location.
+                let toml_id =
+                    TypstFileId::new(Some(spec.as_ref().clone()), VirtualPath::new("typst.toml"));
+                let id = match ctx.get_manifest(toml_id) {
+                    Ok(manifest) => toml_id.join(&manifest.package.entrypoint),
+                    Err(_) => toml_id,
+                };
+                let path = ctx.path_for_id(id).ok()?;
+                crate::path_res_to_url(path).ok()
+            }
             Self::Url(url) => Some(url.as_ref().clone()),
             Self::Path(id, path) => {
                 // Avoid creating new ids here.
@@ -83,11 +95,16 @@ impl LinkStrWorker {
                     return Some(());
                 }
             }
-            SyntaxKind::Include => {
+            SyntaxKind::ModuleInclude => {
                 let inc = node.cast::<ast::ModuleInclude>()?;
                 let path = inc.source();
                 self.analyze_path_expr(node, path);
             }
+            SyntaxKind::ModuleImport => {
+                let imp = node.cast::<ast::ModuleImport>()?;
+                let path = imp.source();
+                self.analyze_path_expr(node, path);
+            }
             // early exit
             kind if kind.is_trivia() || kind.is_keyword() || kind.is_error() => return Some(()),
             _ => {}
@@ -126,7 +143,8 @@ impl LinkStrWorker {
                         self.analyze_bibliography_style(node, call);
                         self.analyze_reader(node, call, "path", true);
                     }
-                    "cbor" | "csv" | "image" | "read" | "json" | "yaml" | "xml" => {
+                    "cbor" | "csv" | "image" | "plugin" | "read" | "json" | "toml" | "yaml"
+                    | "xml" => {
                         self.analyze_reader(node, call, "path", true);
                     }
                     _ => return None,
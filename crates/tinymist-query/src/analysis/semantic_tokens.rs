@@ -19,7 +19,7 @@ use crate::{
     adt::revision::{RevisionLock, RevisionManager, RevisionManagerLike, RevisionSlot},
     syntax::{Expr, ExprInfo},
     ty::Ty,
-    LocalContext, LspPosition, PositionEncoding,
+    LocalContext, LspPosition, LspPositionCursor, PositionEncoding,
 };
 
 /// A shared semantic tokens object.
@@ -42,7 +42,6 @@ pub(crate) fn get_semantic_tokens(ctx: &mut LocalContext, source: &Source) -> Se
 #[derive(Default)]
 pub struct SemanticTokenCache {
     next_id: usize,
-    // todo: clear cache after didClose
     manager: HashMap<ImmutPath, RevisionManager<OnceLock<SemanticTokens>>>,
 }
 
@@ -52,6 +51,19 @@ impl SemanticTokenCache {
         self.manager.clear();
     }
 
+    /// Removes the cached tokens of all files, so the next delta requests fall
+    /// back to the full tokens. Unlike [`Self::clear`], the ids of the next
+    /// results never collide with the ones already sent to the client.
+    pub(crate) fn evict(&mut self) {
+        self.manager.clear();
+    }
+
+    /// Removes the cached tokens of a file, e.g. after the file is closed, so
+    /// the next delta request falls back to the full tokens.
+    pub(crate) fn remove(&mut self, path: &Path) {
+        self.manager.remove(path);
+    }
+
     /// Lock the token cache with an optional previous id in *main thread*.
     pub(crate) fn acquire(
         cache: Arc<Mutex<Self>>,
@@ -308,6 +320,9 @@ impl std::ops::BitOr for ModifierSet {
 pub(crate) struct Tokenizer {
     curr_pos: LspPosition,
     pos_offset: usize,
+    /// Converts the ascending offsets of the tokens without counting from the
+    /// start of the lines again, which is slow on very long lines.
+    cursor: LspPositionCursor,
     output: Vec<SemanticToken>,
     source: Source,
     ei: ExprInfo,
@@ -328,6 +343,7 @@ impl Tokenizer {
         Self {
             curr_pos: LspPosition::new(0, 0),
             pos_offset: 0,
+            cursor: LspPositionCursor::new(encoding, &source),
             output: Vec::new(),
             source,
             ei,
@@ -390,7 +406,6 @@ impl Tokenizer {
             range,
         } = token;
 
-        use crate::lsp_typst_boundary;
         use lsp_types::Position;
         let utf8_start = range.start;
         if self.pos_offset > utf8_start {
@@ -405,20 +420,13 @@ impl Tokenizer {
             return;
         }
 
-        let position = lsp_typst_boundary::to_lsp_position(utf8_start, self.encoding, &self.source);
+        let position = self.cursor.to_lsp_position(utf8_start, &self.source);
 
         let delta = self.curr_pos.delta(&position);
 
-        let encode_length = |s, t| {
-            match self.encoding {
-                PositionEncoding::Utf8 => t - s,
-                PositionEncoding::Utf16 => {
-                    // todo: whether it is safe to unwrap
-                    let utf16_start = self.source.byte_to_utf16(s).unwrap();
-                    let utf16_end = self.source.byte_to_utf16(t).unwrap();
-                    utf16_end - utf16_start
-                }
-            }
+        let encode_length = |s, t| match self.source.text().get(s..t) {
+            Some(text) => self.encoding.len_of(text),
+            None => t - s,
         };
 
         if self.allow_multiline_token {
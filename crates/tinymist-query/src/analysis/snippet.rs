@@ -0,0 +1,52 @@
+//! Compiles and renders standalone snippets of Typst code.
+
+use base64::Engine;
+use tinymist_project::LspWorld;
+use tinymist_world::{EntryReader, ShadowApi, TaskInputs};
+use typst::diag::{SourceResult, Warned};
+use typst::foundations::Bytes;
+use typst::layout::{Abs, PagedDocument};
+
+use crate::analysis::prelude::*;
+
+/// Shrinks the page to fit the snippet.
+pub(crate) const PRELUDE: &str =
+    "#set page(width: auto, height: auto, margin: (y: 0.45em, rest: 0.2em), fill: none)\n";
+
+/// Forks a scratch world from the current one whose main file is at the given
+/// path in the workspace and contains the given content, then compiles it into
+/// a paged document. Returns the scratch world along with the compilation
+/// result, so that the diagnostics can be resolved against it.
+pub(crate) fn compile_scratch(
+    ctx: &LocalContext,
+    entry: &Path,
+    content: EcoString,
+) -> Option<(LspWorld, Warned<SourceResult<PagedDocument>>)> {
+    let world = ctx.world();
+    let mut world = world.task(TaskInputs {
+        entry: Some(world.entry_state().select_in_workspace(entry)),
+        ..TaskInputs::default()
+    });
+    // The source database is shared with the forked world, so it must not
+    // serve a stale snippet from a previous run.
+    world.take_db();
+
+    world
+        .map_shadow_by_id(world.main(), Bytes::from_string(content))
+        .ok()?;
+
+    let doc = typst::compile::<PagedDocument>(&world);
+    Some((world, doc))
+}
+
+/// Renders all pages of a document into a single SVG image.
+pub(crate) fn render_svg(doc: &PagedDocument) -> String {
+    typst_svg::svg_merged(doc, Abs::zero())
+}
+
+/// Renders an SVG image as an image with a data url in markdown, in the same
+/// way as the rendered blocks in the docs.
+pub(crate) fn svg_markdown(alt: &str, svg: &str) -> String {
+    let base64 = base64::engine::general_purpose::STANDARD.encode(svg);
+    format!(r#"<img alt="{alt}" src="data:image/svg+xml;base64,{base64}" />"#)
+}
@@ -0,0 +1,370 @@
+//! Splits a long document into chapter files at its top-level headings, and
+//! inlines the included files back.
+
+use tinymist_std::path::{diff, unix_slash};
+use tinymist_std::typst::HeadingSlugs;
+
+use super::prelude::*;
+use super::{get_link_exprs, LinkTarget};
+
+/// The directory of the chapter files, relative to the split document.
+pub(crate) const CHAPTER_DIR: &str = "chapters";
+/// The path of the preamble file, relative to the split document.
+pub(crate) const PREAMBLE_FILE: &str = "preamble.typ";
+/// The maximum number of the characters of the names of the chapter files.
+const MAX_SLUG_LEN: usize = 40;
+
+/// The files of a split document.
+#[derive(Debug)]
+pub(crate) struct DocumentSplit {
+    /// The new content of the split document.
+    pub main: String,
+    /// The content of the preamble file holding the shared imports,
+    /// definitions and rules, if there are any.
+    pub preamble: Option<String>,
+    /// The paths of the chapter files relative to the split document, along
+    /// with their contents.
+    pub chapters: Vec<(String, String)>,
+}
+
+/// Splits a document into chapter files at its top-level headings.
+///
+/// The leading imports and `let` bindings of the document are hoisted into
+/// the preamble file, which is imported by the document and the chapters. The
+/// leading set and show rules are hoisted into a `preamble` template, so they
+/// still style the included chapters. The relative paths in the chapters are
+/// adjusted to the chapter directory.
+///
+/// Returns `None` if the document has less than two top-level headings.
+pub(crate) fn split_document(source: &Source) -> Option<DocumentSplit> {
+    let text = source.text();
+    let root = LinkedNode::new(source.root());
+    let children = root.children().collect::<Vec<_>>();
+
+    let headings = children
+        .iter()
+        .filter(|child| {
+            let heading = child.cast::<ast::Heading>();
+            heading.is_some_and(|heading| heading.depth().get() == 1)
+        })
+        .collect::<Vec<_>>();
+    if headings.len() < 2 {
+        return None;
+    }
+    let first_heading = headings[0].offset();
+
+    // Collects the leading imports, bindings and rules, before any content.
+    let mut bindings = String::new();
+    let mut rules = String::new();
+    let mut comments = String::new();
+    let mut preamble_end = 0;
+    let mut iter = children.iter().peekable();
+    while let Some(child) = iter.next() {
+        if child.offset() >= first_heading {
+            break;
+        }
+        match child.kind() {
+            SyntaxKind::Space | SyntaxKind::Parbreak => continue,
+            // The comments are moved along with the following item.
+            kind if kind.is_trivia() => {
+                comments.push_str(child.text());
+                comments.push('\n');
+                continue;
+            }
+            SyntaxKind::Hash => {}
+            _ => break,
+        }
+
+        let Some(expr) = iter.next_if(|expr| {
+            matches!(
+                expr.kind(),
+                SyntaxKind::ModuleImport
+                    | SyntaxKind::LetBinding
+                    | SyntaxKind::SetRule
+                    | SyntaxKind::ShowRule
+            )
+        }) else {
+            break;
+        };
+        let mut end = expr.range().end;
+        if let Some(semi) = iter.next_if(|semi| semi.kind() == SyntaxKind::Semicolon) {
+            end = semi.range().end;
+        }
+
+        let code = &text[expr.offset()..end];
+        let comments = std::mem::take(&mut comments);
+        if matches!(expr.kind(), SyntaxKind::SetRule | SyntaxKind::ShowRule) {
+            for line in comments.lines() {
+                rules.push_str("  ");
+                rules.push_str(line);
+                rules.push('\n');
+            }
+            rules.push_str("  ");
+            rules.push_str(&code.replace('\n', "\n  "));
+            rules.push('\n');
+        } else {
+            bindings.push_str(&comments);
+            bindings.push('#');
+            bindings.push_str(code);
+            bindings.push('\n');
+        }
+        preamble_end = end;
+    }
+
+    let preamble = (!bindings.is_empty() || !rules.is_empty()).then(|| {
+        let mut preamble = bindings.clone();
+        if !rules.is_empty() {
+            if !preamble.is_empty() {
+                preamble.push('\n');
+            }
+            preamble.push_str("#let preamble(doc) = {\n");
+            preamble.push_str(&rules);
+            preamble.push_str("  doc\n}\n");
+        }
+        preamble
+    });
+
+    let mut main = String::new();
+    if preamble.is_some() {
+        main.push_str(&format!("#import {PREAMBLE_FILE:?}: *\n"));
+        if !rules.is_empty() {
+            main.push_str("#show: preamble\n");
+        }
+        main.push('\n');
+    }
+    let front = text[preamble_end..first_heading].trim();
+    if !front.is_empty() {
+        main.push_str(front);
+        main.push_str("\n\n");
+    }
+
+    // The starts of the relative paths, which are adjusted to the chapter
+    // directory.
+    let links = get_link_exprs(source);
+    let mut path_starts = links
+        .objects
+        .iter()
+        .filter(|link| match &link.target {
+            LinkTarget::Path(_, path) => !path.starts_with('/'),
+            _ => false,
+        })
+        .map(|link| link.range.start)
+        .collect::<Vec<_>>();
+    path_starts.sort();
+    path_starts.dedup();
+
+    let mut chapters = vec![];
+    let mut slugs = HeadingSlugs::default();
+    for (idx, heading) in headings.iter().enumerate() {
+        let start = heading.offset();
+        let end = headings
+            .get(idx + 1)
+            .map_or(text.len(), |next| next.offset());
+
+        let mut content = String::new();
+        if !bindings.is_empty() {
+            content.push_str(&format!("#import \"../{PREAMBLE_FILE}\": *\n\n"));
+        }
+        let mut cursor = start;
+        for &path_start in path_starts.iter().filter(|s| (start..end).contains(*s)) {
+            content.push_str(&text[cursor..path_start]);
+            content.push_str("../");
+            cursor = path_start;
+        }
+        content.push_str(text[cursor..end].trim_end());
+        content.push('\n');
+
+        let heading = heading.cast::<ast::Heading>()?;
+        let title = heading.body().to_untyped().clone().into_text();
+        // The chapter files are named by the anchors of their headings,
+        // truncated to keep the paths short.
+        let slug = slugs.slug(&title);
+        let slug = match slug.char_indices().nth(MAX_SLUG_LEN) {
+            Some((end, _)) => slug[..end].trim_end_matches('-'),
+            None => &slug,
+        };
+        let path = format!("{CHAPTER_DIR}/{:02}-{slug}.typ", idx + 1);
+        main.push_str(&format!("#include {path:?}\n"));
+        chapters.push((path, content));
+    }
+
+    Some(DocumentSplit {
+        main,
+        preamble,
+        chapters,
+    })
+}
+
+/// Inlines the content of an included file at its `include` expression.
+///
+/// The relative paths in the included file are adjusted to the directory of
+/// the including file, and the leading imports that the including file already
+/// has are dropped. The content is wrapped in a content block if the include
+/// is in code.
+///
+/// Returns the range to replace in the including file along with the new text.
+pub(crate) fn inline_include(
+    source: &Source,
+    include: &LinkedNode,
+    included: &Source,
+) -> Option<(Range<usize>, String)> {
+    let source_dir = source.id().vpath().as_rooted_path().parent()?;
+    let included_dir = included.id().vpath().as_rooted_path().parent()?;
+
+    // The path edits and the dropped imports, in the order of the included file.
+    let mut edits = vec![];
+    for link in get_link_exprs(included).objects.iter() {
+        let LinkTarget::Path(_, path) = &link.target else {
+            continue;
+        };
+        if path.starts_with('/') {
+            continue;
+        }
+        let new_path = unix_slash(&diff(&included_dir.join(path.as_str()), source_dir)?);
+        if new_path != path.as_str() {
+            edits.push((link.range.clone(), new_path));
+        }
+    }
+
+    let text = included.text();
+    let adjusted = |range: Range<usize>| {
+        let mut res = String::new();
+        let mut cursor = range.start;
+        for (edit, new_path) in edits.iter().filter(|(e, _)| range.contains(&e.start)) {
+            res.push_str(&text[cursor..edit.start]);
+            res.push_str(new_path);
+            cursor = edit.end;
+        }
+        res.push_str(&text[cursor..range.end]);
+        res
+    };
+
+    let root = LinkedNode::new(included.root());
+    let mut children = root.children().peekable();
+    let mut body_start = 0;
+    while let Some(child) = children.next() {
+        match child.kind() {
+            SyntaxKind::Space | SyntaxKind::Parbreak => continue,
+            SyntaxKind::Hash => {}
+            _ => break,
+        }
+        let Some(import) = children.next_if(|expr| expr.kind() == SyntaxKind::ModuleImport) else {
+            break;
+        };
+        let import = adjusted(import.range());
+        if !source.text().contains(&format!("#{import}")) {
+            break;
+        }
+        body_start = children.peek().map_or(text.len(), |next| next.offset());
+    }
+
+    let content = adjusted(body_start..text.len());
+    let content = content.trim();
+
+    let start = include.offset();
+    let in_markup = include.parent_kind() == Some(SyntaxKind::Markup);
+    let range = include.range();
+    if in_markup && source.text()[..start].ends_with('#') {
+        Some((start - 1..range.end, content.to_owned()))
+    } else {
+        Some((range, format!("[\n{content}\n]")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    #[test]
+    fn split() {
+        let source = Source::detached(
+            r#"#import "@preview/example:0.1.0": *
+#let name = "Typst"
+#set text(size: 11pt)
+
+#title-page()
+
+= Introduction
+#image("fig/intro.png")
+
+= Background, and More
+Hello #name.
+"#,
+        );
+
+        let split = split_document(&source).unwrap();
+        assert_eq!(
+            split.main,
+            r#"#import "preamble.typ": *
+#show: preamble
+
+#title-page()
+
+#include "chapters/01-introduction.typ"
+#include "chapters/02-background-and-more.typ"
+"#
+        );
+        assert_eq!(
+            split.preamble.as_deref(),
+            Some(
+                r#"#import "@preview/example:0.1.0": *
+#let name = "Typst"
+
+#let preamble(doc) = {
+  set text(size: 11pt)
+  doc
+}
+"#
+            )
+        );
+        assert_eq!(
+            split.chapters[0],
+            (
+                "chapters/01-introduction.typ".to_owned(),
+                "#import \"../preamble.typ\": *\n\n= Introduction\n#image(\"../fig/intro.png\")\n"
+                    .to_owned()
+            )
+        );
+        assert_eq!(split.chapters[1].0, "chapters/02-background-and-more.typ");
+    }
+
+    #[test]
+    fn inline() {
+        let main_id = TypstFileId::new(None, VirtualPath::new("main.typ"));
+        let chapter_id = TypstFileId::new(None, VirtualPath::new("chapters/intro.typ"));
+        let source = Source::new(
+            main_id,
+            "#import \"preamble.typ\": *\n\n#include \"chapters/intro.typ\"\n".to_owned(),
+        );
+        let included = Source::new(
+            chapter_id,
+            r#"#import "../preamble.typ": *
+
+= Introduction
+#image("../fig/intro.png")
+#image("fig/local.png")
+"#
+            .to_owned(),
+        );
+
+        let root = LinkedNode::new(source.root());
+        let include = root
+            .children()
+            .find(|child| child.kind() == SyntaxKind::ModuleInclude)
+            .unwrap();
+        let (range, content) = inline_include(&source, &include, &included).unwrap();
+        assert_eq!(&source.text()[range], "#include \"chapters/intro.typ\"");
+        assert_eq!(
+            content,
+            "= Introduction\n#image(\"fig/intro.png\")\n#image(\"chapters/fig/local.png\")"
+        );
+    }
+
+    #[test]
+    fn no_split() {
+        let source = Source::detached("= Introduction\nHello.\n");
+        assert!(split_document(&source).is_none());
+    }
+}
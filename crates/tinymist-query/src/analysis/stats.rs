@@ -3,6 +3,8 @@
 use std::{sync::Arc, time::Duration};
 
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+pub use tinymist_analysis::stats::InternerStats;
 use tinymist_std::hash::FxDashMap;
 use typst::syntax::FileId;
 
@@ -63,6 +65,31 @@ pub struct AnalysisStats {
 }
 
 impl AnalysisStats {
+    /// Collects the hit rates of the cached queries, summed over the files.
+    pub fn query_cache_stats(&self) -> Vec<QueryCacheStats> {
+        let mut data = std::collections::BTreeMap::<&'static str, (u64, u64)>::new();
+        for refs in self.query_stats.iter() {
+            for refs2 in refs.value().iter() {
+                let bucket = refs2.value().data.lock();
+                let entry = data.entry(refs2.key()).or_default();
+                entry.0 += bucket.query;
+                entry.1 += bucket.missing;
+            }
+        }
+
+        data.into_iter()
+            .map(|(query, (count, missing))| QueryCacheStats {
+                query: query.to_owned(),
+                count,
+                missing,
+                hit_rate: match count {
+                    0 => 0.,
+                    _ => count.saturating_sub(missing) as f64 / count as f64,
+                },
+            })
+            .collect()
+    }
+
     /// Report the statistics of the analysis.
     pub fn report(&self) -> String {
         let stats = &self.query_stats;
@@ -112,3 +139,17 @@ table.analysis-stats tr:nth-child(odd) { background-color: rgba(242, 242, 242, 0
         html
     }
 }
+
+/// Statistics about the cache of a query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryCacheStats {
+    /// The name of the query.
+    pub query: String,
+    /// The number of the queries.
+    pub count: u64,
+    /// The number of the queries missing the cache.
+    pub missing: u64,
+    /// The ratio of the queries hitting the cache.
+    pub hit_rate: f64,
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashSet};
+
+use lsp_types::{DocumentChangeOperation, DocumentChanges, RenameFile, ResourceOp};
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use tinymist_world::debug_loc::DataSource;
+use tinymist_world::EntryReader;
+use typst::layout::{Frame, FrameItem};
+use typst::text::Font;
+
+use crate::{
+    analysis::{get_link_exprs, LinkTarget},
+    do_rename_file, edits_to_document_changes,
+    prelude::*,
+};
+
+/// The kind of an asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetKind {
+    /// An image loaded by `image`.
+    Image,
+    /// A data file loaded by `read`, `json`, `csv`, etc.
+    Data,
+    /// A bibliography file or a citation style.
+    Bibliography,
+    /// A font file in the project.
+    Font,
+    /// A WebAssembly plugin.
+    Plugin,
+}
+
+impl AssetKind {
+    /// Determines the kind of an asset by its extension.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "gif" | "jpeg" | "jpg" | "png" | "svg" | "webp" => Self::Image,
+            "bib" | "csl" => Self::Bibliography,
+            "otf" | "otc" | "ttf" | "ttc" | "woff" | "woff2" => Self::Font,
+            "wasm" => Self::Plugin,
+            _ => Self::Data,
+        }
+    }
+}
+
+/// An asset referenced by the project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectAsset {
+    /// The path of the asset.
+    pub path: PathBuf,
+    /// The kind of the asset.
+    pub kind: AssetKind,
+    /// Whether the asset exists on the disk.
+    pub exists: bool,
+    /// The locations referencing the asset. Fonts are referenced by the
+    /// compiled document instead of the source files.
+    pub references: Vec<LspLocation>,
+}
+
+/// The assets of the project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectAssetsResponse {
+    /// The assets referenced by the project.
+    pub assets: Vec<ProjectAsset>,
+    /// The files in the assets directory that are not referenced by the
+    /// project.
+    pub unreferenced: Vec<PathBuf>,
+}
+
+/// A request to list the assets referenced by the project, i.e. the images,
+/// data, bibliography, plugin files referenced by the source files, and the
+/// font files in the project used by the compiled document.
+#[derive(Debug, Clone)]
+pub struct ProjectAssetsRequest {
+    /// The directory to look for the unreferenced assets.
+    pub assets_dir: Option<PathBuf>,
+}
+
+impl StatefulRequest for ProjectAssetsRequest {
+    type Response = ProjectAssetsResponse;
+
+    fn request(self, ctx: &mut LocalContext, graph: LspComputeGraph) -> Option<Self::Response> {
+        let mut assets = BTreeMap::<PathBuf, ProjectAsset>::new();
+
+        for fid in ctx.source_files().clone() {
+            if fid.package().is_some() {
+                continue;
+            }
+            let Ok(source) = ctx.source_by_id(fid) else {
+                continue;
+            };
+            let Ok(uri) = ctx.uri_for_id(fid) else {
+                continue;
+            };
+
+            for obj in &get_link_exprs(&source).objects {
+                let LinkTarget::Path(id, path) = &obj.target else {
+                    continue;
+                };
+                let Ok(root) = ctx.path_for_id(id.join("/")) else {
+                    continue;
+                };
+                let Some(path) = root.resolve_to(&id.vpath().join(Path::new(path.as_str()))) else {
+                    continue;
+                };
+                let Ok(path) = path.to_err() else {
+                    continue;
+                };
+                // Modules are not assets.
+                if path.extension().is_some_and(|ext| ext == "typ") {
+                    continue;
+                }
+
+                let asset = assets.entry(path.clone()).or_insert_with(|| ProjectAsset {
+                    kind: AssetKind::from_path(&path),
+                    exists: path.exists(),
+                    path,
+                    references: vec![],
+                });
+                asset.references.push(LspLocation {
+                    uri: uri.clone(),
+                    range: ctx.to_lsp_range(obj.range.clone(), &source),
+                });
+            }
+        }
+
+        let root = ctx.world().entry_state().root();
+        if let (Some(doc), Some(root)) = (graph.snap.success_doc.as_ref(), root) {
+            let mut fonts = HashSet::new();
+            if let TypstDocument::Paged(doc) = doc {
+                for page in &doc.pages {
+                    collect_fonts(&page.frame, &mut fonts);
+                }
+            }
+            for font in fonts {
+                let Some(DataSource::Fs(source)) = ctx.font_info(font).as_deref().cloned() else {
+                    continue;
+                };
+                let path = PathBuf::from(source.path);
+                if !path.starts_with(&root) {
+                    continue;
+                }
+                assets.entry(path.clone()).or_insert_with(|| ProjectAsset {
+                    kind: AssetKind::Font,
+                    exists: true,
+                    path,
+                    references: vec![],
+                });
+            }
+        }
+
+        let mut unreferenced = vec![];
+        if let Some(dir) = self.assets_dir {
+            collect_unreferenced(&dir, &assets, &mut unreferenced);
+            unreferenced.sort();
+        }
+
+        Some(ProjectAssetsResponse {
+            assets: assets.into_values().collect(),
+            unreferenced,
+        })
+    }
+}
+
+/// Collects the fonts used by a frame.
+fn collect_fonts(frame: &Frame, fonts: &mut HashSet<Font>) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Text(text) => {
+                fonts.insert(text.font.clone());
+            }
+            FrameItem::Group(group) => collect_fonts(&group.frame, fonts),
+            FrameItem::Shape(..)
+            | FrameItem::Image(..)
+            | FrameItem::Tag(..)
+            | FrameItem::Link(..) => {}
+        }
+    }
+}
+
+/// Collects the files in a directory that are neither assets nor modules.
+fn collect_unreferenced(
+    dir: &Path,
+    assets: &BTreeMap<PathBuf, ProjectAsset>,
+    unreferenced: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_unreferenced(&path, assets, unreferenced);
+        } else if !assets.contains_key(&path) && path.extension().is_none_or(|ext| ext != "typ") {
+            unreferenced.push(path);
+        }
+    }
+}
+
+/// A request to move an asset, returning the workspace edit that moves the
+/// file and rewrites the paths referencing it.
+#[derive(Debug, Clone)]
+pub struct MoveAssetRequest {
+    /// The current path of the asset.
+    pub from: PathBuf,
+    /// The new path of the asset.
+    pub to: PathBuf,
+}
+
+impl StatefulRequest for MoveAssetRequest {
+    type Response = WorkspaceEdit;
+
+    fn request(self, ctx: &mut LocalContext, _graph: LspComputeGraph) -> Option<Self::Response> {
+        let diff = tinymist_std::path::diff(&self.to, &self.from)?;
+        if diff.is_absolute() {
+            log::info!("bad asset move: from {:?} to {:?}", self.from, self.to);
+            return None;
+        }
+
+        let def_fid = ctx.file_id_by_path(&self.from).ok()?;
+        let mut edits: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        do_rename_file(ctx, def_fid, diff, &mut edits)?;
+
+        let mut document_changes = edits_to_document_changes(edits);
+        document_changes.push(DocumentChangeOperation::Op(ResourceOp::Rename(
+            RenameFile {
+                old_uri: path_to_url(&self.from).ok()?,
+                new_uri: path_to_url(&self.to).ok()?,
+                options: None,
+                annotation_id: None,
+            },
+        )));
+
+        Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(document_changes)),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asset_kind() {
+        assert_eq!(
+            AssetKind::from_path(Path::new("a/cat.PNG")),
+            AssetKind::Image
+        );
+        assert_eq!(
+            AssetKind::from_path(Path::new("refs.bib")),
+            AssetKind::Bibliography
+        );
+        assert_eq!(AssetKind::from_path(Path::new("data.yml")), AssetKind::Data);
+        assert_eq!(
+            AssetKind::from_path(Path::new("fonts/a.otf")),
+            AssetKind::Font
+        );
+        assert_eq!(
+            AssetKind::from_path(Path::new("plugin.wasm")),
+            AssetKind::Plugin
+        );
+    }
+}
@@ -1,20 +1,43 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use ecow::{eco_format, EcoString, EcoVec};
+use hayagriva::archive::ArchivedStyle;
+use hayagriva::citationberg::{CitationFormat, Style, StyleCategory};
 use hayagriva::{
     BibliographyDriver, BibliographyRequest, BufWriteFormat, CitationItem, CitationRequest,
     ElemChildren,
 };
+use typst::diag::SourceDiagnostic;
+use typst::syntax::{Source, Span};
 
 use crate::analysis::BibInfo;
 
+#[derive(Debug, Clone)]
 pub(crate) struct RenderedBibCitation {
     pub citation: String,
     pub bib_item: String,
 }
 
 /// Render the citation string in the bib with given CSL style.
+///
+/// The rendered citation is cached in the bib info, which is only
+/// recomputed when the bibliography files or the style change.
 pub(crate) fn render_citation_string(
     bib_info: &BibInfo,
     key: &str,
     support_html: bool,
+) -> Option<RenderedBibCitation> {
+    bib_info.render_citation(key, support_html, || {
+        render_citation_string_(bib_info, key, support_html)
+    })
+}
+
+fn render_citation_string_(
+    bib_info: &BibInfo,
+    key: &str,
+    support_html: bool,
 ) -> Option<RenderedBibCitation> {
     let entry = bib_info.entries.get(key)?;
     let raw_entry = entry.raw_entry.as_ref()?;
@@ -54,3 +77,215 @@ pub(crate) fn render_citation_string(
         bib_item: format_elem(&rendered_bib.items.first()?.content)?,
     })
 }
+
+/// Gets the fields of a bibliography entry shown along with the rendered
+/// reference, i.e. the title, the authors, the year and the venue.
+pub(crate) fn bib_entry_fields(entry: &hayagriva::Entry) -> Vec<(&'static str, String)> {
+    let mut fields = vec![];
+    if let Some(title) = entry.title() {
+        fields.push(("Title", title.to_string()));
+    }
+    if let Some(authors) = entry.authors().filter(|authors| !authors.is_empty()) {
+        let authors = authors.iter().map(|author| author.given_first(false));
+        fields.push(("Authors", authors.collect::<Vec<_>>().join(", ")));
+    }
+    if let Some(date) = entry.date_any() {
+        fields.push(("Year", date.year.to_string()));
+    }
+
+    // The venue is the journal or the proceedings containing the entry, or
+    // the publisher of it.
+    let venue = entry.parents().iter().find_map(|parent| parent.title());
+    let venue = venue
+        .or_else(|| entry.publisher().and_then(|publisher| publisher.name()))
+        .or_else(|| entry.organization());
+    if let Some(venue) = venue {
+        fields.push(("Venue", venue.to_string()));
+    }
+
+    fields
+}
+
+/// Describes a CSL style by its title, its citation format and the date when
+/// it was last updated.
+pub(crate) fn csl_style_docs(style: &Style) -> EcoString {
+    let info = style.info();
+    let mut docs = EcoString::from(info.title.value.as_str());
+
+    let format = info.category.iter().find_map(|category| match category {
+        StyleCategory::CitationFormat { format } => Some(*format),
+        StyleCategory::Field { .. } => None,
+    });
+    if let Some(format) = format {
+        let format = match format {
+            CitationFormat::AuthorDate => "author-date",
+            CitationFormat::Author => "author",
+            CitationFormat::Numeric => "numeric",
+            CitationFormat::Label => "label",
+            CitationFormat::Note => "note",
+        };
+        docs.push_str(&eco_format!("\n\nCitation format: {format}"));
+    }
+    // The built-in styles are stored without the date.
+    if let Some(updated) = &info.updated {
+        let date = updated.raw.split('T').next().unwrap_or_default();
+        docs.push_str(&eco_format!("\nUpdated: {date}"));
+    }
+
+    docs
+}
+
+/// Gets the names of the built-in CSL styles, along with their descriptions.
+pub(crate) fn builtin_csl_styles() -> &'static [(&'static str, EcoString)] {
+    // Decoding the styles takes a while, so they are only described once.
+    static STYLES: LazyLock<Vec<(&'static str, EcoString)>> = LazyLock::new(|| {
+        ArchivedStyle::all()
+            .iter()
+            .map(|style| (style.names()[0], csl_style_docs(&style.get())))
+            .collect()
+    });
+
+    &STYLES
+}
+
+/// Describes a CSL file, or returns `None` if it is not a valid style.
+pub(crate) fn csl_file_docs(content: &[u8]) -> Option<EcoString> {
+    let xml = std::str::from_utf8(content).ok()?;
+    Some(csl_style_docs(&Style::from_xml(xml).ok()?))
+}
+
+/// Checks a BibTeX or BibLaTeX file, reporting the malformed entries, the
+/// duplicate keys and the required fields missing for the entry types.
+pub(crate) fn check_bib_file(source: &Source) -> EcoVec<SourceDiagnostic> {
+    let mut diags = EcoVec::new();
+    let span = |range: Range<usize>| Span::from_range(source.id(), range);
+
+    let raw = match biblatex::RawBibliography::parse(source.text()) {
+        Ok(raw) => raw,
+        Err(err) => {
+            let msg = eco_format!("failed to parse bibliography: {}", err.kind);
+            diags.push(SourceDiagnostic::error(span(err.span), msg));
+            return diags;
+        }
+    };
+
+    // Only the first entry of a key is used, so the later ones are reported
+    // and then ignored when checking the fields.
+    let mut entries = HashMap::new();
+    let mut unique = raw.clone();
+    unique.entries.clear();
+    for entry in &raw.entries {
+        let key = &entry.v.key;
+        if entries.contains_key(key.v) {
+            let msg = eco_format!("duplicate bibliography key `{}`", key.v);
+            let hint = "rename or remove one of the entries";
+            diags.push(SourceDiagnostic::error(span(key.span.clone()), msg).with_hint(hint));
+            continue;
+        }
+        entries.insert(key.v, &entry.v);
+        unique.entries.push(entry.clone());
+    }
+
+    let bib = match biblatex::Bibliography::from_raw(unique) {
+        Ok(bib) => bib,
+        Err(err) => {
+            let msg = eco_format!("failed to parse bibliography: {}", err.kind);
+            diags.push(SourceDiagnostic::error(span(err.span), msg));
+            return diags;
+        }
+    };
+
+    for entry in bib.iter() {
+        let Some(raw_entry) = entries.get(entry.key.as_str()) else {
+            continue;
+        };
+        let report = entry.verify();
+
+        if !report.missing.is_empty() {
+            let fields = report.missing.iter().map(|field| eco_format!("`{field}`"));
+            let msg = eco_format!(
+                "entry `{}` of type `@{}` is missing the required fields {}",
+                entry.key,
+                raw_entry.kind.v,
+                fields.collect::<Vec<_>>().join(", ")
+            );
+            let hint = "add the fields or change the type of the entry";
+            let key_span = span(raw_entry.key.span.clone());
+            diags.push(SourceDiagnostic::warning(key_span, msg).with_hint(hint));
+        }
+
+        for (field, err) in report.malformed {
+            let range = raw_entry
+                .fields
+                .iter()
+                .find(|pair| pair.key.v.eq_ignore_ascii_case(&field))
+                .map_or_else(
+                    || raw_entry.key.span.clone(),
+                    |pair| pair.value.span.clone(),
+                );
+            let msg = eco_format!(
+                "malformed field `{field}` in entry `{}`: {}",
+                entry.key,
+                err.kind
+            );
+            diags.push(SourceDiagnostic::warning(span(range), msg));
+        }
+    }
+
+    diags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bib_diagnostics() {
+        let text = r#"@article{a,
+  author = {Doe, Jane},
+  title = {A Title},
+  year = {2020},
+}
+@book{a,
+  title = {Another Title},
+}
+@book{b,
+  author = {Doe, Jane},
+  title = {A Book},
+  publisher = {Press},
+  year = {2020},
+  volume = {first},
+}"#;
+        let source = Source::detached(text);
+        let diags = check_bib_file(&source);
+        let messages = diags.iter().map(|diag| diag.message.as_str());
+        assert_eq!(
+            messages.collect::<Vec<_>>(),
+            vec![
+                "duplicate bibliography key `a`",
+                "entry `a` of type `@article` is missing the required fields `journaltitle`",
+                "malformed field `volume` in entry `b`: invalid number",
+            ]
+        );
+        let duplicate = source.range(diags[0].span).unwrap();
+        assert_eq!(
+            duplicate.start,
+            text.find("@book{a").unwrap() + "@book{".len()
+        );
+
+        let source = Source::detached("@article{a, title = {A}");
+        let diags = check_bib_file(&source);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity, typst::diag::Severity::Error);
+    }
+
+    #[test]
+    fn csl_docs() {
+        let styles = builtin_csl_styles();
+        let (_, apa) = styles.iter().find(|(name, _)| *name == "apa").unwrap();
+        assert!(apa.starts_with("American Psychological Association"));
+        assert!(apa.contains("Citation format: author-date"));
+
+        assert!(csl_file_docs(b"<style>").is_none());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use tinymist_project::{Id, LockFile, LOCK_FILENAME};
+use tinymist_std::typst::TypstDocument;
+
+use crate::analysis::{get_link_exprs, LinkTarget};
+use crate::prelude::*;
+use crate::testing::{test_suites, TestCaseKind};
+
+/// An entrypoint impacted by a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactedDocument {
+    /// The id of the document in the lock file, if it is locked.
+    pub id: Option<Id>,
+    /// The main file of the document.
+    pub main: Url,
+}
+
+/// An export task impacted by a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactedTask {
+    /// The id of the task in the lock file.
+    pub id: Id,
+    /// The id of the exported document.
+    pub document: Id,
+    /// The extension of the exported artifact, e.g. `pdf`.
+    pub format: String,
+}
+
+/// A test case impacted by a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactedTest {
+    /// The name of the test function, or of the example file.
+    pub name: EcoString,
+    /// The kind of the test case, i.e. `test`, `bench`, `panic`, `example` or
+    /// `doc-example`.
+    pub kind: EcoString,
+    /// The file defining the test case.
+    pub location: Url,
+}
+
+/// The response to a [`ChangeImpactRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeImpactResponse {
+    /// The workspace files depending on the modified file, including the
+    /// modified file itself.
+    pub files: Vec<Url>,
+    /// The entrypoints depending on the modified file.
+    pub documents: Vec<ImpactedDocument>,
+    /// The export tasks of the impacted entrypoints in the lock file.
+    pub tasks: Vec<ImpactedTask>,
+    /// The test cases of the current entrypoint depending on the modified
+    /// file.
+    pub tests: Vec<ImpactedTest>,
+}
+
+/// A request to find the entrypoints, tests and export tasks affected by a
+/// modified file, following the imports, includes and file paths of the
+/// workspace.
+///
+/// This is not part of the LSP protocol.
+#[derive(Debug, Clone)]
+pub struct ChangeImpactRequest {
+    /// The path of the modified file.
+    pub path: PathBuf,
+}
+
+impl StatefulRequest for ChangeImpactRequest {
+    type Response = ChangeImpactResponse;
+
+    fn request(self, ctx: &mut LocalContext, graph: LspComputeGraph) -> Option<Self::Response> {
+        let changed = ctx.file_id_by_path(&self.path).ok()?;
+        let impacted = impacted_files(ctx, &HashSet::from([changed]));
+
+        let mut files = impacted
+            .iter()
+            .filter_map(|fid| ctx.uri_for_id(*fid).ok())
+            .collect::<Vec<_>>();
+        files.sort();
+
+        // The current entrypoint is also impacted by the files read by the
+        // compilation, e.g. the images and the data files.
+        let main = ctx.world.main();
+        let mut documents = vec![];
+        if impacted.contains(&main) || ctx.world.depended_files().contains(&changed) {
+            if let Ok(main) = ctx.uri_for_id(main) {
+                documents.push(ImpactedDocument { id: None, main });
+            }
+        }
+
+        let mut tasks = vec![];
+        let root = ctx.world.entry_state().workspace_root();
+        let lock = root
+            .as_ref()
+            .filter(|root| root.join(LOCK_FILENAME).exists())
+            .and_then(|root| LockFile::read(root).ok());
+        if let (Some(root), Some(lock)) = (root, lock) {
+            let main_impacted = !documents.is_empty();
+            let mut locked = HashSet::new();
+            for doc in &lock.document {
+                let Some(path) = doc.main.to_abs_path(&root) else {
+                    continue;
+                };
+                let Ok(fid) = ctx.file_id_by_path(&path) else {
+                    continue;
+                };
+                if !impacted.contains(&fid) && !(fid == main && main_impacted) {
+                    continue;
+                }
+                let Ok(uri) = ctx.uri_for_id(fid) else {
+                    continue;
+                };
+
+                locked.insert(doc.id.clone());
+                documents.retain(|it| it.main != uri);
+                documents.push(ImpactedDocument {
+                    id: Some(doc.id.clone()),
+                    main: uri,
+                });
+            }
+
+            tasks = lock
+                .task
+                .iter()
+                .filter(|task| locked.contains(task.doc_id()))
+                .map(|task| ImpactedTask {
+                    id: task.id().clone(),
+                    document: task.doc_id().clone(),
+                    format: task.task.extension().to_owned(),
+                })
+                .collect();
+        }
+
+        let tests = graph
+            .snap
+            .success_doc
+            .as_ref()
+            .map(|doc| impacted_tests(ctx, doc, &impacted))
+            .unwrap_or_default();
+
+        Some(ChangeImpactResponse {
+            files,
+            documents,
+            tasks,
+            tests,
+        })
+    }
+}
+
+/// Collects the test cases of a document defined in the impacted files.
+fn impacted_tests(
+    ctx: &mut LocalContext,
+    doc: &TypstDocument,
+    impacted: &HashSet<TypstFileId>,
+) -> Vec<ImpactedTest> {
+    let Ok(suites) = test_suites(ctx, doc) else {
+        return vec![];
+    };
+
+    let tests = suites.tests.iter().map(|test| {
+        let kind = match test.kind {
+            TestCaseKind::Test => "test",
+            TestCaseKind::Bench => "bench",
+            TestCaseKind::Panic => "panic",
+            TestCaseKind::Example => "example",
+        };
+        (test.name.clone(), kind, test.location)
+    });
+    let examples = suites.examples.iter().map(|example| {
+        let path = example.id().vpath().as_rooted_path().with_extension("");
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        (name.into(), "example", example.id())
+    });
+    let doc_examples = suites
+        .doc_examples
+        .iter()
+        .map(|example| (example.name.clone(), "doc-example", example.location));
+
+    tests
+        .chain(examples)
+        .chain(doc_examples)
+        .filter(|(_, _, fid)| impacted.contains(fid))
+        .filter_map(|(name, kind, fid)| {
+            let location = ctx.uri_for_id(fid).ok()?;
+            Some(ImpactedTest {
+                name,
+                kind: kind.into(),
+                location,
+            })
+        })
+        .collect()
+}
+
+/// Computes the workspace files impacted by changes to some files, which are
+/// the changed files along with the source files depending on them, by
+/// imports and includes, or by file paths for the other files, e.g. images.
+pub fn impacted_files(
+    ctx: &mut LocalContext,
+    changed: &HashSet<TypstFileId>,
+) -> HashSet<TypstFileId> {
+    let mut impacted = changed.clone();
+    let mut queue = changed.iter().copied().collect::<VecDeque<_>>();
+
+    // The files referenced by paths, which are mostly not source files.
+    let preference = PathPreference::Source {
+        allow_package: false,
+    };
+    let is_source = |fid: &TypstFileId| preference.is_match(fid.vpath().as_rooted_path());
+    if !changed.iter().all(is_source) {
+        for fid in ctx.source_files().clone() {
+            let Ok(source) = ctx.source_by_id(fid) else {
+                continue;
+            };
+            let refers_changed = get_link_exprs(&source).objects.iter().any(|link| {
+                matches!(&link.target, LinkTarget::Path(id, path)
+                    if changed.contains(&id.join(path.as_str())))
+            });
+            if refers_changed && impacted.insert(fid) {
+                queue.push_back(fid);
+            }
+        }
+    }
+
+    while let Some(fid) = queue.pop_front() {
+        let dependents = ctx
+            .module_dependencies()
+            .get(&fid)
+            .map(|dep| dep.dependents.clone())
+            .unwrap_or_default();
+        for dependent in dependents {
+            if impacted.insert(dependent) {
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    impacted
+}
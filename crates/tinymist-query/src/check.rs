@@ -7,6 +7,11 @@ use crate::{prelude::*, DiagWorker, DiagnosticsMap, SemanticRequest};
 pub struct CheckRequest {
     /// The compilation result of the document.
     pub snap: LspCompiledArtifact,
+    /// Whether to run the lint checks.
+    pub lint: bool,
+    /// Whether to check all the files reachable from the entry, including the
+    /// ones not evaluated by the compilation.
+    pub project_wide: bool,
 }
 
 impl SemanticRequest for CheckRequest {
@@ -14,6 +19,13 @@ impl SemanticRequest for CheckRequest {
 
     fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
         let worker = DiagWorker::new(ctx);
-        Some(worker.check().convert_all(self.snap.diagnostics()))
+        let worker = if self.project_wide {
+            worker.check_reachable(self.lint)
+        } else if self.lint {
+            worker.check()
+        } else {
+            worker
+        };
+        Some(worker.convert_all(self.snap.diagnostics()))
     }
 }
@@ -143,7 +143,7 @@ impl SemanticRequest for InteractCodeContextRequest {
 }
 
 impl InteractCodeContextRequest {
-    fn mode_at(source: &Source, pos: usize) -> Option<InterpretMode> {
+    pub(crate) fn mode_at(source: &Source, pos: usize) -> Option<InterpretMode> {
         // Smart special cases that is definitely at markup
         if pos == 0 || pos >= source.text().len() {
             return Some(InterpretMode::Markup);
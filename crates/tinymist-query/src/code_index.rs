@@ -0,0 +1,224 @@
+//! Collects the definitions, references and hover documentation of a project
+//! to build code-intelligence indexes, e.g. for code browsers like Sourcegraph.
+
+use tinymist_std::path::unix_slash;
+
+use crate::{
+    analysis::Definition,
+    prelude::*,
+    syntax::{DeclExpr, Expr},
+};
+
+/// The code-intelligence index of a project.
+#[derive(Debug, Clone, Default)]
+pub struct CodeIndex {
+    /// The indexed source files.
+    pub documents: Vec<IndexDocument>,
+    /// The symbols occurring in the source files.
+    pub symbols: Vec<IndexSymbol>,
+}
+
+/// An indexed source file.
+#[derive(Debug, Clone)]
+pub struct IndexDocument {
+    /// The path of the file relative to the project root.
+    pub path: String,
+    /// The uri of the file.
+    pub uri: Url,
+    /// The occurrences of the symbols in the file, sorted by their ranges.
+    pub occurrences: Vec<IndexOccurrence>,
+}
+
+/// An occurrence of a symbol.
+#[derive(Debug, Clone)]
+pub struct IndexOccurrence {
+    /// The range of the occurrence.
+    pub range: LspRange,
+    /// The index of the symbol in [`CodeIndex::symbols`].
+    pub symbol: usize,
+    /// Whether the occurrence defines the symbol.
+    pub is_definition: bool,
+}
+
+/// A symbol in the code index.
+#[derive(Debug, Clone)]
+pub struct IndexSymbol {
+    /// The unique identifier of the symbol in the [SCIP symbol syntax](https://github.com/sourcegraph/scip/blob/main/scip.proto).
+    pub id: String,
+    /// Whether the symbol is only visible in its file.
+    pub is_local: bool,
+    /// The name of the symbol.
+    pub name: String,
+    /// The kind of the symbol.
+    pub kind: DefKind,
+    /// The hover documentation of the symbol in markdown.
+    pub docs: Option<String>,
+    /// The location of the definition.
+    pub definition: Option<LspLocation>,
+}
+
+/// Builds the code-intelligence index of the source files in the workspace.
+pub fn code_index(ctx: &mut LocalContext) -> CodeIndex {
+    let mut builder = IndexBuilder::default();
+    for fid in ctx.source_files().clone() {
+        if let Some(doc) = builder.document(ctx, fid) {
+            builder.index.documents.push(doc);
+        }
+    }
+    builder.index
+}
+
+#[derive(Default)]
+struct IndexBuilder {
+    index: CodeIndex,
+    symbol_ids: HashMap<DeclExpr, usize>,
+}
+
+impl IndexBuilder {
+    fn document(&mut self, ctx: &mut LocalContext, fid: TypstFileId) -> Option<IndexDocument> {
+        let source = ctx.source_by_id(fid).ok()?;
+        let uri = ctx.uri_for_id(fid).ok()?;
+        let ei = ctx.expr_stage(&source);
+
+        let mut resolves = ei
+            .resolves
+            .iter()
+            .filter_map(|(span, ref_expr)| Some((source.range(*span)?, ref_expr)))
+            .collect::<Vec<_>>();
+        resolves.sort_by_key(|(range, _)| (range.start, range.end));
+
+        let mut occurrences = vec![];
+        for (range, ref_expr) in resolves {
+            // References to the builtin items are not indexed.
+            let Some(Expr::Decl(target)) = ref_expr.root.as_ref() else {
+                continue;
+            };
+            if target.file_id().is_none() {
+                continue;
+            }
+
+            let symbol = match self.symbol_ids.get(target) {
+                Some(symbol) => *symbol,
+                None => {
+                    let symbol = self.index.symbols.len();
+                    self.index.symbols.push(index_symbol(ctx, target, symbol));
+                    self.symbol_ids.insert(target.clone(), symbol);
+                    symbol
+                }
+            };
+            occurrences.push(IndexOccurrence {
+                range: ctx.to_lsp_range(range, &source),
+                symbol,
+                is_definition: ref_expr.decl == *target,
+            });
+        }
+
+        Some(IndexDocument {
+            path: unix_slash(fid.vpath().as_rootless_path()),
+            uri,
+            occurrences,
+        })
+    }
+}
+
+fn index_symbol(ctx: &mut LocalContext, decl: &DeclExpr, local_id: usize) -> IndexSymbol {
+    let fid = decl.file_id();
+    let is_module = matches!(decl.kind(), DefKind::Module);
+    let is_exported = is_module
+        || fid
+            .and_then(|fid| ctx.expr_stage_by_id(fid))
+            .is_some_and(|ei| ei.is_exported(decl));
+
+    let id = match fid {
+        Some(fid) if is_exported => global_symbol_id(fid, decl),
+        _ => format!("local {local_id}"),
+    };
+
+    let docs = ctx
+        .def_docs(&Definition::new(decl.clone(), None))
+        .map(|docs| docs.hover_docs().to_string())
+        .filter(|docs| !docs.trim().is_empty());
+
+    let definition = fid.and_then(|fid| {
+        let source = ctx.source_by_id(fid).ok()?;
+        let range = if is_module {
+            0..0
+        } else {
+            source.range(decl.span())?
+        };
+        Some(LspLocation {
+            uri: ctx.uri_for_id(fid).ok()?,
+            range: ctx.to_lsp_range(range, &source),
+        })
+    });
+
+    IndexSymbol {
+        id,
+        is_local: !is_exported,
+        name: decl.name().to_string(),
+        kind: decl.kind(),
+        docs,
+        definition,
+    }
+}
+
+/// Gets the identifier of a symbol visible to other files, e.g.
+/// ``tinymist typst . . `lib.typ`/add().`` or
+/// ``tinymist typst @preview/example 0.1.0 `lib.typ`/add().``.
+fn global_symbol_id(fid: TypstFileId, decl: &DeclExpr) -> String {
+    let package = match fid.package() {
+        Some(spec) => format!(
+            "{} {}",
+            escape_symbol_part(&format!("@{}/{}", spec.namespace, spec.name)),
+            spec.version
+        ),
+        None => ". .".to_owned(),
+    };
+
+    let mut descriptors = String::new();
+    let path = unix_slash(fid.vpath().as_rootless_path());
+    for segment in path.split('/') {
+        descriptors.push_str(&escape_descriptor(segment));
+        descriptors.push('/');
+    }
+    if !matches!(decl.kind(), DefKind::Module) {
+        descriptors.push_str(&escape_descriptor(decl.name()));
+        descriptors.push_str(match decl.kind() {
+            DefKind::Function => "().",
+            _ => ".",
+        });
+    }
+
+    format!("tinymist typst {package} {descriptors}")
+}
+
+/// Escapes a space-separated part of a symbol by doubling the spaces.
+fn escape_symbol_part(part: &str) -> String {
+    part.replace(' ', "  ")
+}
+
+/// Escapes a descriptor name with backticks if it is not a simple identifier.
+fn escape_descriptor(name: &str) -> String {
+    let is_simple = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '+' | '-' | '$'));
+    if is_simple {
+        name.to_owned()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptors() {
+        assert_eq!(escape_descriptor("add-one"), "add-one");
+        assert_eq!(escape_descriptor("lib.typ"), "`lib.typ`");
+        assert_eq!(escape_descriptor("a`b"), "`a``b`");
+        assert_eq!(escape_symbol_part("my pkg"), "my  pkg");
+    }
+}
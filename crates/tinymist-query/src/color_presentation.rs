@@ -1,6 +1,6 @@
 use typst::foundations::Repr;
 
-use crate::prelude::*;
+use crate::{prelude::*, SyntaxRequest};
 
 /// The [`textDocument/colorPresentation`] request is sent from the client to
 /// the server to obtain a list of presentations for a color value at a given
@@ -31,33 +31,97 @@ pub struct ColorPresentationRequest {
     pub range: LspRange,
 }
 
-impl ColorPresentationRequest {
-    /// Serve the request.
-    pub fn request(self) -> Option<Vec<ColorPresentation>> {
+impl SyntaxRequest for ColorPresentationRequest {
+    type Response = Vec<ColorPresentation>;
+
+    fn request(
+        self,
+        source: &Source,
+        position_encoding: PositionEncoding,
+    ) -> Option<Self::Response> {
         let color = typst::visualize::Color::Rgb(typst::visualize::Rgb::new(
             self.color.red,
             self.color.green,
             self.color.blue,
             self.color.alpha,
         ));
-        Some(vec![
-            simple(format!("{:?}", color.to_hex())),
-            simple(color.to_rgb().repr().to_string()),
-            simple(color.to_luma().repr().to_string()),
-            simple(color.to_oklab().repr().to_string()),
-            simple(color.to_oklch().repr().to_string()),
-            simple(color.to_rgb().repr().to_string()),
-            simple(color.to_linear_rgb().repr().to_string()),
-            simple(color.to_cmyk().repr().to_string()),
-            simple(color.to_hsl().repr().to_string()),
-            simple(color.to_hsv().repr().to_string()),
-        ])
+        let range = to_typst_range(self.range, position_encoding, source)?;
+        let text = source.text().get(range)?;
+
+        let hex = format!("{:?}", color.to_hex());
+        let mut presentations = vec![
+            ("str", hex.clone()),
+            ("rgb", color.to_rgb().repr().to_string()),
+            ("luma", color.to_luma().repr().to_string()),
+            ("oklab", color.to_oklab().repr().to_string()),
+            ("oklch", color.to_oklch().repr().to_string()),
+            ("linear-rgb", color.to_linear_rgb().repr().to_string()),
+            ("cmyk", color.to_cmyk().repr().to_string()),
+            ("hsl", color.to_hsl().repr().to_string()),
+            ("hsv", color.to_hsv().repr().to_string()),
+        ];
+
+        // Writes back with the constructor the user has chosen.
+        let kind = color_kind(text);
+        if kind == "rgb" && text.contains('"') {
+            presentations.retain(|(kind, _)| *kind != "rgb");
+            presentations.insert(0, ("rgb", format!("rgb({hex})")));
+        } else if let Some(idx) = presentations.iter().position(|(k, _)| *k == kind) {
+            let chosen = presentations.remove(idx);
+            presentations.insert(0, chosen);
+        }
+
+        Some(
+            presentations
+                .into_iter()
+                .map(|(_, label)| ColorPresentation {
+                    text_edit: Some(TextEdit {
+                        range: self.range,
+                        new_text: label.clone(),
+                    }),
+                    label,
+                    additional_text_edits: None,
+                })
+                .collect(),
+        )
     }
 }
 
-fn simple(label: String) -> ColorPresentation {
-    ColorPresentation {
-        label,
-        ..ColorPresentation::default()
+/// Gets the kind of a color expression, which is the name of its constructor.
+fn color_kind(text: &str) -> &str {
+    let text = text.trim();
+    let text = text.strip_prefix("color.").unwrap_or(text);
+    text.split('(').next().unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_constructor() {
+        let source = Source::detached("#let t = luma(50%);\n#let s = rgb(\"#aabbcc\");");
+        let request = |range: LspRange| {
+            let request = ColorPresentationRequest {
+                path: PathBuf::new(),
+                color: lsp_types::Color {
+                    red: 0.5,
+                    green: 0.5,
+                    blue: 0.5,
+                    alpha: 1.0,
+                },
+                range,
+            };
+            request.request(&source, PositionEncoding::Utf16).unwrap()
+        };
+        let range = |line, start, end| {
+            LspRange::new(LspPosition::new(line, start), LspPosition::new(line, end))
+        };
+
+        let luma = request(range(0, 9, 18));
+        assert!(luma[0].label.starts_with("luma("), "{:?}", luma[0].label);
+
+        let hex = request(range(1, 9, 23));
+        assert!(hex[0].label.starts_with("rgb(\""), "{:?}", hex[0].label);
     }
 }
@@ -0,0 +1,295 @@
+use base64::Engine;
+use ecow::eco_format;
+use serde::{Deserialize, Serialize};
+use typst::layout::{Abs, PagedDocument};
+
+use crate::analysis::snippet::{compile_scratch, render_svg, PRELUDE};
+use crate::{convert_diagnostics, prelude::*, LspWorldExt};
+
+/// The name of the scratch file to compile snippets, which is placed beside
+/// the document to resolve the relative paths in the same way.
+const SCRATCH_FILE: &str = "__compile_snippet__.typ";
+
+/// The default pixels per inch of the exported PNG images.
+const DEFAULT_PPI: f32 = 144.;
+
+/// Fills the background of the PNG images, which are otherwise unreadable on
+/// dark backgrounds.
+const PNG_PRELUDE: &str = "#set page(fill: white)\n";
+
+/// A request to compile a detached snippet of Typst markup in the context of
+/// a document.
+///
+/// The top-level imports and set and show rules of the document are prepended
+/// to the snippet, so the snippet is rendered with the definitions and styles
+/// of the document, e.g. to preview a selection or to render the examples in
+/// documentation.
+#[derive(Debug, Clone)]
+pub struct CompileSnippetRequest {
+    /// The path of the document providing the context.
+    pub path: PathBuf,
+    /// The snippet to compile.
+    pub text: String,
+}
+
+/// The result of compiling a snippet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileSnippetResponse {
+    /// The rendered pages as a single SVG image, or `None` if the compilation
+    /// failed.
+    pub svg: Option<String>,
+    /// The diagnostics of the compilation, whose ranges are relative to the
+    /// snippet. The diagnostics in the other files are reported at the start
+    /// of the snippet, with their locations in the related information.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl SemanticRequest for CompileSnippetRequest {
+    type Response = CompileSnippetResponse;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let (doc, diagnostics) = compile_in_context(ctx, &source, "", &self.text)?;
+
+        Some(CompileSnippetResponse {
+            svg: doc.map(|doc| render_svg(&doc)),
+            diagnostics,
+        })
+    }
+}
+
+/// The format of the images exported from a selection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionImageFormat {
+    /// A PNG image.
+    #[default]
+    Png,
+    /// An SVG image.
+    Svg,
+}
+
+/// A request to render the selected markup of a document as an image, which
+/// is compiled in the context of the document like [`CompileSnippetRequest`],
+/// e.g. to paste a fragment of the document into a chat or an issue.
+#[derive(Debug, Clone)]
+pub struct ExportSelectionRequest {
+    /// The path of the document.
+    pub path: PathBuf,
+    /// The selected range of the document.
+    pub range: LspRange,
+    /// The format of the image.
+    pub format: SelectionImageFormat,
+    /// The pixels per inch of the PNG image, defaulting to 144.
+    pub ppi: Option<f32>,
+}
+
+/// The image rendered from a selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSelectionResponse {
+    /// The format of the image.
+    pub format: SelectionImageFormat,
+    /// The base64 encoded PNG image or the SVG image, or `None` if the
+    /// compilation failed.
+    pub data: Option<String>,
+    /// The diagnostics of the compilation, whose ranges are relative to the
+    /// selection.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl SemanticRequest for ExportSelectionRequest {
+    type Response = ExportSelectionResponse;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let range = ctx.to_typst_range(self.range, &source)?;
+        let text = source.text().get(range)?;
+
+        let prelude = match self.format {
+            SelectionImageFormat::Png => PNG_PRELUDE,
+            SelectionImageFormat::Svg => "",
+        };
+        let (doc, diagnostics) = compile_in_context(ctx, &source, prelude, text)?;
+        let data = doc.and_then(|doc| match self.format {
+            SelectionImageFormat::Png => {
+                let ppi = self.ppi.filter(|ppi| *ppi > 1e-6).unwrap_or(DEFAULT_PPI);
+                let pixmap = typst_render::render_merged(&doc, ppi / 72., Abs::zero(), None);
+                let png = pixmap.encode_png().ok()?;
+                Some(base64::engine::general_purpose::STANDARD.encode(png))
+            }
+            SelectionImageFormat::Svg => Some(render_svg(&doc)),
+        });
+
+        Some(ExportSelectionResponse {
+            format: self.format,
+            data,
+            diagnostics,
+        })
+    }
+}
+
+/// Renders a snippet in the context of a document into an SVG image, like
+/// [`CompileSnippetRequest`]. The images are cached by the compiled content,
+/// since hovering the same equation would otherwise compile it again and
+/// again.
+pub(crate) fn render_in_context(
+    ctx: &LocalContext,
+    source: &Source,
+    prelude: &str,
+    text: &str,
+) -> Option<Arc<str>> {
+    let content = eco_format!("{}{PRELUDE}{prelude}{text}", preamble(source));
+    ctx.rendered_snippet(content, || {
+        let (doc, _) = compile_in_context(ctx, source, prelude, text)?;
+        Some(render_svg(&doc?).into())
+    })
+}
+
+/// Compiles a snippet after the top-level imports and rules of a document and
+/// the given prelude, returning the document along with the diagnostics
+/// relative to the snippet.
+fn compile_in_context(
+    ctx: &LocalContext,
+    source: &Source,
+    prelude: &str,
+    text: &str,
+) -> Option<(Option<PagedDocument>, Vec<Diagnostic>)> {
+    let entry = source
+        .id()
+        .vpath()
+        .as_rooted_path()
+        .with_file_name(SCRATCH_FILE);
+
+    let prefix = eco_format!("{}{PRELUDE}{prelude}", preamble(source));
+    let prefix_lines = prefix.matches('\n').count() as u32;
+    let content = eco_format!("{prefix}{text}");
+    let (world, doc) = compile_scratch(ctx, &entry, content)?;
+
+    let errors = doc.output.as_ref().err().into_iter().flatten();
+    let diags = convert_diagnostics(
+        &world,
+        doc.warnings.iter().chain(errors),
+        ctx.analysis.position_encoding,
+    );
+
+    let scratch_uri = world.uri_for_id(world.main()).ok();
+    let mut diagnostics = vec![];
+    for (uri, diags) in diags {
+        for mut diag in diags {
+            if scratch_uri.as_ref() == Some(&uri) {
+                diag.range = shift_range(diag.range, prefix_lines);
+            } else {
+                let related = DiagnosticRelatedInformation {
+                    location: LspLocation {
+                        uri: uri.clone(),
+                        range: diag.range,
+                    },
+                    message: diag.message.clone(),
+                };
+                diag.related_information
+                    .get_or_insert_with(Vec::new)
+                    .insert(0, related);
+                diag.range = LspRange::default();
+            }
+            diagnostics.push(diag);
+        }
+    }
+
+    Some((doc.output.ok(), diagnostics))
+}
+
+/// Collects the top-level imports and set and show rules of a document.
+fn preamble(source: &Source) -> EcoString {
+    let mut preamble = EcoString::new();
+    for node in source.root().children() {
+        if matches!(
+            node.kind(),
+            SyntaxKind::ModuleImport | SyntaxKind::SetRule | SyntaxKind::ShowRule
+        ) {
+            preamble.push('#');
+            preamble.push_str(&node.clone().into_text());
+            preamble.push('\n');
+        }
+    }
+    preamble
+}
+
+/// Shifts a range in the scratch file to be relative to the snippet, clamping
+/// the positions in the prefix to the start of the snippet.
+fn shift_range(range: LspRange, lines: u32) -> LspRange {
+    let shift = |pos: LspPosition| match pos.line.checked_sub(lines) {
+        Some(line) => LspPosition::new(line, pos.character),
+        None => LspPosition::new(0, 0),
+    };
+    LspRange::new(shift(range.start), shift(range.end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+
+    #[test]
+    fn collect_preamble() {
+        let source = Source::detached(
+            "#import \"lib.typ\": *\n#set text(size: 12pt)\n= Title\n#show: template.with(\n  title: [A],\n)\nBody",
+        );
+        assert_eq!(
+            preamble(&source),
+            "#import \"lib.typ\": *\n#set text(size: 12pt)\n#show: template.with(\n  title: [A],\n)\n"
+        );
+    }
+
+    #[test]
+    fn shift() {
+        let range = LspRange::new(LspPosition::new(3, 2), LspPosition::new(4, 1));
+        assert_eq!(
+            shift_range(range, 3),
+            LspRange::new(LspPosition::new(0, 2), LspPosition::new(1, 1))
+        );
+        assert_eq!(
+            shift_range(range, 4),
+            LspRange::new(LspPosition::new(0, 0), LspPosition::new(0, 1))
+        );
+    }
+
+    #[test]
+    fn render_memoized() {
+        run_with_sources("#set text(size: 14pt)\n$ x + y $", |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let source = ctx.source_by_path(&path).unwrap();
+                let ctx = &*ctx;
+                let render = |text: &str| render_in_context(ctx, &source, "", text).unwrap();
+
+                let first = render("$ x + y $");
+                assert!(first.starts_with("<svg"));
+                assert!(Arc::ptr_eq(&first, &render("$ x + y $")));
+                assert!(!Arc::ptr_eq(&first, &render("$ x - y $")));
+            })
+        });
+    }
+
+    #[test]
+    fn render_after_import_change() {
+        let sources = "// path: lib.typ\n#let x = $a$\n-----\n#import \"lib.typ\": x\n$ x $";
+        run_with_sources(sources, |verse, path| {
+            let analysis = Arc::new(crate::analysis::Analysis::default());
+            let render = |verse: &LspUniverse| {
+                let ctx = analysis.enter(verse.snapshot());
+                let source = ctx.source_by_path(&path).unwrap();
+                render_in_context(&ctx, &source, "", "$ x $").unwrap()
+            };
+
+            let first = render(verse);
+            assert!(Arc::ptr_eq(&first, &render(verse)));
+
+            // The image is rendered again after the imported definition changes.
+            let lib = path.with_file_name("lib.typ");
+            let content = typst::foundations::Bytes::from_string("#let x = $b$");
+            tinymist_world::ShadowApi::map_shadow(verse, &lib, content).unwrap();
+            assert_ne!(first, render(verse));
+        });
+    }
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use tinymist_std::path::unix_slash;
+use typst::syntax::Span;
+
+use crate::prelude::*;
+use crate::syntax::{Expr, RefExpr};
+
+/// A request to dump the syntax tree, the resolved expressions, the scopes,
+/// and the inferred types of a file, which are attached to bug reports.
+///
+/// The items are sorted by their ranges or names, so the dumps of the same
+/// file are stable across runs.
+///
+/// This is not part of the LSP protocol.
+#[derive(Debug, Clone)]
+pub struct DebugAstRequest {
+    /// The path of the file to dump.
+    pub path: PathBuf,
+}
+
+/// The dump of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugAstResponse {
+    /// The version of the server producing the dump.
+    pub version: String,
+    /// The path of the file, relative to its root.
+    pub path: String,
+    /// The syntax tree of the file.
+    pub syntax: DebugSyntaxNode,
+    /// The expression of the whole file.
+    pub root: String,
+    /// The expressions of the syntax nodes.
+    pub exprs: Vec<DebugRanged>,
+    /// The references and the declarations they are resolved to.
+    pub resolves: Vec<DebugResolve>,
+    /// The scopes of the file.
+    pub scopes: DebugScopes,
+    /// The inferred types.
+    pub types: DebugTypes,
+}
+
+/// A node in the syntax tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSyntaxNode {
+    /// The kind of the node.
+    pub kind: String,
+    /// The range of the node.
+    pub range: LspRange,
+    /// The text of the node if it is a leaf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The message of the node if it is an error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The children of the node.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DebugSyntaxNode>,
+}
+
+/// A representation at a range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugRanged {
+    /// The range of the syntax node.
+    pub range: LspRange,
+    /// The representation of the expression or the type.
+    pub repr: String,
+}
+
+/// A named representation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugNamed {
+    /// The name of the item.
+    pub name: String,
+    /// The representation of the expression or the type.
+    pub repr: String,
+}
+
+/// A resolved reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugResolve {
+    /// The range of the reference.
+    pub range: LspRange,
+    /// The referenced declaration.
+    pub decl: String,
+    /// The expression the reference is resolved to in one step.
+    pub step: Option<String>,
+    /// The expression the reference is finally resolved to.
+    pub root: Option<String>,
+    /// The type of the referenced term.
+    pub term: Option<String>,
+}
+
+/// The scopes of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugScopes {
+    /// The items exported by the file.
+    pub exports: Vec<DebugNamed>,
+    /// The names imported from the other files.
+    pub imports: Vec<DebugImport>,
+}
+
+/// The names imported from a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugImport {
+    /// The path of the imported file, relative to its root.
+    pub path: String,
+    /// The imported names.
+    pub names: Vec<String>,
+}
+
+/// The inferred types of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugTypes {
+    /// The simplified types of the variables.
+    pub vars: Vec<DebugNamed>,
+    /// The types of the syntax nodes.
+    pub mapping: Vec<DebugRanged>,
+}
+
+impl SemanticRequest for DebugAstRequest {
+    type Response = DebugAstResponse;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let ei = ctx.expr_stage(&source);
+        let ti = ctx.type_check(&source);
+
+        let syntax = syntax_node(ctx, &source, &LinkedNode::new(source.root()));
+
+        let ranged = |span: Span, repr: String| {
+            let range = source.range(span)?;
+            Some((range, repr))
+        };
+        let exprs = ei
+            .exprs
+            .iter()
+            .filter_map(|(span, expr)| ranged(*span, show_expr(&source, expr)));
+        let exprs = sort_ranged(ctx, &source, exprs);
+
+        let mut resolves = ei
+            .resolves
+            .iter()
+            .filter_map(|(span, expr)| Some((source.range(*span)?, expr)))
+            .collect::<Vec<_>>();
+        resolves.sort_by_key(|(range, _)| (range.start, range.end));
+        let resolves = resolves
+            .into_iter()
+            .map(|(range, expr)| {
+                let RefExpr {
+                    decl,
+                    step,
+                    root,
+                    term,
+                } = expr.as_ref();
+                DebugResolve {
+                    range: ctx.to_lsp_range(range, &source),
+                    decl: show_expr(&source, &Expr::Decl(decl.clone())),
+                    step: step.as_ref().map(|expr| show_expr(&source, expr)),
+                    root: root.as_ref().map(|expr| show_expr(&source, expr)),
+                    term: term.as_ref().map(|term| format!("{term:?}")),
+                }
+            })
+            .collect();
+
+        let exports = ei
+            .exports
+            .iter()
+            .map(|(name, expr)| DebugNamed {
+                name: name.to_string(),
+                repr: show_expr(&source, expr),
+            })
+            .sorted_by(|x, y| x.name.cmp(&y.name))
+            .collect();
+        let imports = ei
+            .imports
+            .iter()
+            .map(|(fid, scope)| DebugImport {
+                path: unix_slash(fid.vpath().as_rooted_path()),
+                names: scope.keys().map(|name| name.to_string()).collect(),
+            })
+            .sorted_by(|x, y| x.path.cmp(&y.path))
+            .collect();
+
+        let vars = ti
+            .vars
+            .values()
+            .sorted_by(|x, y| x.var.strict_cmp(&y.var))
+            .map(|bounds| DebugNamed {
+                name: bounds.name().to_string(),
+                repr: format!("{:?}", ti.simplify(bounds.as_type(), true)),
+            })
+            .collect();
+        let mapping = ti.mapping.iter().filter_map(|(span, types)| {
+            let ty = crate::ty::Ty::from_types(types.clone().into_iter());
+            ranged(*span, format!("{ty:?}"))
+        });
+        let mapping = sort_ranged(ctx, &source, mapping);
+
+        Some(DebugAstResponse {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            path: unix_slash(source.id().vpath().as_rooted_path()),
+            syntax,
+            root: ei.root.to_string(),
+            exprs,
+            resolves,
+            scopes: DebugScopes { exports, imports },
+            types: DebugTypes { vars, mapping },
+        })
+    }
+}
+
+fn syntax_node(ctx: &LocalContext, source: &Source, node: &LinkedNode) -> DebugSyntaxNode {
+    let is_leaf = node.get().children().len() == 0;
+    let error = (node.kind() == SyntaxKind::Error)
+        .then(|| node.errors().into_iter().next())
+        .flatten()
+        .map(|err| err.message.to_string());
+
+    DebugSyntaxNode {
+        kind: node.kind().name().to_owned(),
+        range: ctx.to_lsp_range(node.range(), source),
+        text: is_leaf.then(|| node.text().to_string()),
+        error,
+        children: node
+            .children()
+            .map(|child| syntax_node(ctx, source, &child))
+            .collect(),
+    }
+}
+
+fn sort_ranged(
+    ctx: &LocalContext,
+    source: &Source,
+    items: impl Iterator<Item = (Range<usize>, String)>,
+) -> Vec<DebugRanged> {
+    items
+        .sorted_by(|x, y| (x.0.start, x.0.end, &x.1).cmp(&(y.0.start, y.0.end, &y.1)))
+        .map(|(range, repr)| DebugRanged {
+            range: ctx.to_lsp_range(range, source),
+            repr,
+        })
+        .collect()
+}
+
+/// Shows an expression, where the declarations are shown with their ranges
+/// and files.
+fn show_expr(source: &Source, expr: &Expr) -> String {
+    let Expr::Decl(decl) = expr else {
+        return expr.to_string();
+    };
+
+    let fid = decl.file_id();
+    let range = fid
+        .filter(|fid| *fid == source.id())
+        .and_then(|_| source.range(decl.span()));
+    let loc = match (fid, range) {
+        (_, Some(range)) => format!("@{range:?}"),
+        (Some(fid), None) => format!(" in {}", unix_slash(fid.vpath().as_rooted_path())),
+        (None, None) => String::new(),
+    };
+    format!("{decl:?}{loc}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+
+    #[test]
+    fn dump() {
+        run_with_sources("#let f(x) = x\n#f(1)", |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let dump = || DebugAstRequest { path: path.clone() }.request(ctx).unwrap();
+                let res = dump();
+
+                assert_eq!(res.syntax.kind, "markup");
+                assert!(res
+                    .resolves
+                    .iter()
+                    .any(|r| r.decl.starts_with("IdentRef(f)@")));
+                let exports = res.scopes.exports.iter().map(|e| e.name.as_str());
+                assert_eq!(exports.collect::<Vec<_>>(), ["f"]);
+                assert!(!res.types.vars.is_empty());
+
+                let repr = serde_json::to_string(&res).unwrap();
+                assert_eq!(repr, serde_json::to_string(&dump()).unwrap());
+            })
+        });
+    }
+}
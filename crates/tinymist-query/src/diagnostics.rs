@@ -1,10 +1,15 @@
 use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
 
 use tinymist_project::LspWorld;
+use tinymist_std::hash::hash128;
 use tinymist_world::vfs::WorkspaceResolver;
 use typst::syntax::Span;
 
-use crate::{analysis::Analysis, prelude::*};
+use crate::{
+    analysis::Analysis, bib::check_bib_file, check_index_terms, check_spelling, impacted_files,
+    prelude::*, syntax::resolve_id_by_path, yaml_bib::check_yaml_bib,
+};
 
 use regex::RegexSet;
 
@@ -14,6 +19,18 @@ pub type DiagnosticsMap = HashMap<Url, EcoVec<Diagnostic>>;
 type TypstDiagnostic = typst::diag::SourceDiagnostic;
 type TypstSeverity = typst::diag::Severity;
 
+/// The lint results of the files reachable from an entry, which are reused
+/// by the next check if the files are not impacted by the changes.
+#[derive(Default)]
+pub struct ReachableLints {
+    /// The hash of the checked entry.
+    entry: u128,
+    /// The hashes of the texts of the linted files.
+    hashes: HashMap<TypstFileId, u128>,
+    /// The lint results of the files.
+    diagnostics: HashMap<TypstFileId, EcoVec<TypstDiagnostic>>,
+}
+
 /// Converts a list of Typst diagnostics to LSP diagnostics,
 /// with potential refinements on the error messages.
 pub fn convert_diagnostics<'a>(
@@ -35,6 +52,9 @@ pub(crate) struct DiagWorker<'a> {
     pub ctx: &'a mut LocalContext,
     /// Results
     pub results: DiagnosticsMap,
+    /// The import and include chains from the entry to the files, cached by
+    /// the files.
+    import_chains: HashMap<TypstFileId, EcoVec<DiagnosticRelatedInformation>>,
 }
 
 impl<'w> DiagWorker<'w> {
@@ -43,11 +63,13 @@ impl<'w> DiagWorker<'w> {
         Self {
             ctx,
             results: DiagnosticsMap::default(),
+            import_chains: HashMap::new(),
         }
     }
 
     /// Runs code check on the document.
     pub fn check(mut self) -> Self {
+        let mut sources = vec![];
         for dep in self.ctx.world.depended_files() {
             if WorkspaceResolver::is_package_file(dep) {
                 continue;
@@ -60,11 +82,178 @@ impl<'w> DiagWorker<'w> {
             for diag in self.ctx.lint(&source) {
                 self.handle(&diag);
             }
+            sources.push(source);
+        }
+
+        self.check_index(&sources);
+        self.check_spelling(&sources);
+        self.check_bib_files();
+        self
+    }
+
+    /// Runs code check on all the workspace files reachable from the entry by
+    /// imports and includes, including the ones not evaluated by the
+    /// compilation, e.g. the files imported after a failed import.
+    pub fn check_reachable(mut self, lint: bool) -> Self {
+        let evaluated = self.ctx.world.depended_files();
+        let mut sources = vec![];
+        for fid in self.reachable_files(&evaluated) {
+            let Ok(source) = self.ctx.source_by_id(fid) else {
+                continue;
+            };
+
+            // The syntax errors of the evaluated files are reported by the
+            // compilation.
+            if !evaluated.contains(&fid) {
+                for error in source.root().errors() {
+                    self.handle(&TypstDiagnostic::from(error));
+                }
+            }
+
+            if lint {
+                sources.push(source);
+            }
         }
 
+        self.lint_impacted(&sources);
+        self.check_index(&sources);
+        self.check_spelling(&sources);
+        self.check_bib_files();
         self
     }
 
+    /// Lints the files, reusing the lint results of the last check for the
+    /// files not impacted by the changes since then.
+    fn lint_impacted(&mut self, sources: &[Source]) {
+        let cache = self.ctx.analysis.reachable_lints.clone();
+        let entry = hash128(&(
+            self.ctx.world.entry_state().workspace_root(),
+            self.ctx.world.main(),
+        ));
+        let hashes = sources
+            .iter()
+            .map(|source| (source.id(), hash128(source.text())))
+            .collect::<HashMap<_, _>>();
+
+        let changed = {
+            let mut cache = cache.lock();
+            if cache.entry != entry {
+                *cache = ReachableLints {
+                    entry,
+                    ..ReachableLints::default()
+                };
+            }
+            cache.hashes.retain(|fid, _| hashes.contains_key(fid));
+            cache.diagnostics.retain(|fid, _| hashes.contains_key(fid));
+            hashes
+                .iter()
+                .filter(|(fid, hash)| cache.hashes.get(fid) != Some(hash))
+                .map(|(fid, _)| *fid)
+                .collect::<HashSet<_>>()
+        };
+        let impacted = if changed.is_empty() {
+            HashSet::new()
+        } else {
+            impacted_files(self.ctx, &changed)
+        };
+
+        for source in sources {
+            let fid = source.id();
+            let cached = if impacted.contains(&fid) {
+                None
+            } else {
+                cache.lock().diagnostics.get(&fid).cloned()
+            };
+            let diags = match cached {
+                Some(diags) => diags,
+                None => {
+                    let diags = self.ctx.lint(source);
+                    let mut cache = cache.lock();
+                    cache.hashes.insert(fid, hashes[&fid]);
+                    cache.diagnostics.insert(fid, diags.clone());
+                    diags
+                }
+            };
+            for diag in diags.iter() {
+                self.handle(diag);
+            }
+        }
+    }
+
+    /// Checks the marks of the index terms in the files.
+    fn check_index(&mut self, sources: &[Source]) {
+        let feat = self.ctx.analysis.index.clone();
+        for diag in check_index_terms(sources, &feat) {
+            self.handle(&diag);
+        }
+    }
+
+    /// Checks the spelling of the prose in the files.
+    fn check_spelling(&mut self, sources: &[Source]) {
+        let feat = self.ctx.analysis.spell.clone();
+        let root = self.ctx.world.entry_state().workspace_root();
+        for diag in check_spelling(sources, &feat, root.as_deref()) {
+            self.handle(&diag);
+        }
+    }
+
+    /// Checks the BibTeX and Hayagriva files loaded by the compilation.
+    fn check_bib_files(&mut self) {
+        for dep in self.ctx.world.depended_files() {
+            if WorkspaceResolver::is_package_file(dep) {
+                continue;
+            }
+            let path = dep.vpath().as_rooted_path();
+            let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+                continue;
+            };
+            let check = match ext.to_lowercase().as_str() {
+                "bib" => check_bib_file,
+                "yml" | "yaml" => check_yaml_bib,
+                _ => continue,
+            };
+
+            let Ok(source) = self.ctx.source_by_id(dep) else {
+                continue;
+            };
+            for diag in check(&source) {
+                self.handle(&diag);
+            }
+        }
+    }
+
+    /// Collects the workspace source files reachable from the entry and the
+    /// evaluated files.
+    fn reachable_files(&mut self, evaluated: &EcoVec<TypstFileId>) -> Vec<TypstFileId> {
+        let preference = PathPreference::Source {
+            allow_package: false,
+        };
+        let is_workspace_source = |fid: &TypstFileId| {
+            !WorkspaceResolver::is_package_file(*fid)
+                && preference.is_match(fid.vpath().as_rooted_path())
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue = iter::once(self.ctx.world.main())
+            .chain(evaluated.iter().copied())
+            .filter(is_workspace_source)
+            .collect::<Vec<_>>();
+        while let Some(fid) = queue.pop() {
+            if !visited.insert(fid) {
+                continue;
+            }
+            let Ok(source) = self.ctx.source_by_id(fid) else {
+                continue;
+            };
+            let ei = self.ctx.expr_stage(&source);
+            queue.extend(ei.imports.keys().copied().filter(is_workspace_source));
+        }
+
+        let mut files = visited.into_iter().collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
     /// Converts a list of Typst diagnostics to LSP diagnostics.
     pub fn convert_all<'a>(
         mut self,
@@ -90,7 +279,7 @@ impl<'w> DiagWorker<'w> {
     }
 
     fn convert_diagnostic(
-        &self,
+        &mut self,
         typst_diagnostic: &TypstDiagnostic,
     ) -> anyhow::Result<(Url, Diagnostic)> {
         let typst_diagnostic = {
@@ -117,18 +306,24 @@ impl<'w> DiagWorker<'w> {
         let lsp_severity = diagnostic_severity(typst_diagnostic.severity);
         let lsp_message = diagnostic_message(&typst_diagnostic);
 
+        let mut related_info = typst_diagnostic
+            .trace
+            .iter()
+            .flat_map(|tracepoint| self.to_related_info(tracepoint))
+            .collect::<Vec<_>>();
+        // Explains why the file is compiled at all.
+        for info in self.import_chain(id) {
+            if !related_info.iter().any(|it| it.location == info.location) {
+                related_info.push(info);
+            }
+        }
+
         let diagnostic = Diagnostic {
             range: lsp_range,
             severity: Some(lsp_severity),
             message: lsp_message,
             source: Some("typst".to_owned()),
-            related_information: (!typst_diagnostic.trace.is_empty()).then(|| {
-                typst_diagnostic
-                    .trace
-                    .iter()
-                    .flat_map(|tracepoint| self.to_related_info(tracepoint))
-                    .collect()
-            }),
+            related_information: (!related_info.is_empty()).then_some(related_info),
             ..Default::default()
         };
 
@@ -156,6 +351,106 @@ impl<'w> DiagWorker<'w> {
         })
     }
 
+    /// Gets the import and include chain from the entry to a file, starting
+    /// from the nearest import or include of the file.
+    fn import_chain(&mut self, target: TypstFileId) -> EcoVec<DiagnosticRelatedInformation> {
+        if let Some(chain) = self.import_chains.get(&target) {
+            return chain.clone();
+        }
+
+        let chain = self.find_import_chain(target).unwrap_or_default();
+        self.import_chains.insert(target, chain.clone());
+        chain
+    }
+
+    fn find_import_chain(
+        &mut self,
+        target: TypstFileId,
+    ) -> Option<EcoVec<DiagnosticRelatedInformation>> {
+        let main = self.ctx.world.main();
+        if target == main {
+            return None;
+        }
+
+        // Finds the shortest chain by a breadth-first search from the entry.
+        let mut parents = HashMap::from([(main, main)]);
+        let mut queue = VecDeque::from([main]);
+        while let Some(fid) = queue.pop_front() {
+            if fid == target {
+                break;
+            }
+            let Ok(source) = self.ctx.source_by_id(fid) else {
+                continue;
+            };
+            let ei = self.ctx.expr_stage(&source);
+            for &dep in ei.imports.keys() {
+                if !parents.contains_key(&dep) {
+                    parents.insert(dep, fid);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        parents.get(&target)?;
+
+        let mut chain = EcoVec::new();
+        let mut child = target;
+        while child != main {
+            let parent = parents[&child];
+            chain.extend(self.import_site(parent, child));
+            child = parent;
+        }
+        Some(chain)
+    }
+
+    /// Locates the import or include of a file in another file.
+    fn import_site(
+        &self,
+        importer: TypstFileId,
+        imported: TypstFileId,
+    ) -> Option<DiagnosticRelatedInformation> {
+        let source = self.ctx.source_by_id(importer).ok()?;
+        let root = LinkedNode::new(source.root());
+        let (range, is_include) = self.find_import_site(importer, &root, imported)?;
+
+        let path = imported.vpath().as_rooted_path().display();
+        let file = match imported.package() {
+            Some(spec) => format!("{spec}{path}"),
+            None => path.to_string(),
+        };
+        let verb = if is_include { "included" } else { "imported" };
+
+        Some(DiagnosticRelatedInformation {
+            location: LspLocation {
+                uri: self.ctx.uri_for_id(importer).ok()?,
+                range: self.ctx.to_lsp_range(range, &source),
+            },
+            message: format!("`{file}` is {verb} here"),
+        })
+    }
+
+    fn find_import_site(
+        &self,
+        importer: TypstFileId,
+        node: &LinkedNode,
+        imported: TypstFileId,
+    ) -> Option<(Range<usize>, bool)> {
+        let (path, is_include) = match node.kind() {
+            SyntaxKind::ModuleImport => (node.cast::<ast::ModuleImport>()?.source(), false),
+            SyntaxKind::ModuleInclude => (node.cast::<ast::ModuleInclude>()?.source(), true),
+            _ => {
+                return node
+                    .children()
+                    .find_map(|child| self.find_import_site(importer, &child, imported));
+            }
+        };
+
+        let ast::Expr::Str(path) = path else {
+            return None;
+        };
+        let id = resolve_id_by_path(&self.ctx.world, importer, path.get().as_str())?;
+        (id == imported).then(|| (node.range(), is_include))
+    }
+
     fn diagnostic_span_id(&self, typst_diagnostic: &TypstDiagnostic) -> (TypstFileId, Span) {
         iter::once(typst_diagnostic.span)
             .chain(typst_diagnostic.trace.iter().map(|trace| trace.span))
@@ -170,8 +465,8 @@ impl<'w> DiagWorker<'w> {
         //
         // This may have been fixed after Typst 0.7.0, but it's still nice to avoid
         // panics in case something similar reappears.
-        match source.find(typst_span) {
-            Some(node) => self.ctx.to_lsp_range(node.range(), source),
+        match source.range(typst_span) {
+            Some(range) => self.ctx.to_lsp_range(range, source),
             None => LspRange::new(LspPosition::new(0, 0), LspPosition::new(0, 0)),
         }
     }
@@ -9,7 +9,10 @@ use typst::diag::StrResult;
 use typst::syntax::package::PackageSpec;
 use typst::syntax::FileId;
 
+use tinymist_std::hash::hash128;
+
 use crate::adt::interner::Interned;
+use crate::analysis::DiskCache;
 use crate::docs::file_id_repr;
 use crate::package::{get_manifest_id, PackageInfo};
 use crate::syntax::{Decl, DefKind, Expr, ExprInfo};
@@ -18,12 +21,41 @@ use crate::LocalContext;
 use super::DefDocs;
 
 /// Get documentation of definitions in a package.
+///
+/// The documentation is cached on disk by the contents of the package.
 pub fn package_module_docs(ctx: &mut LocalContext, pkg: &PackageInfo) -> StrResult<PackageDefInfo> {
+    let cache = ctx.analysis.disk_cache.clone();
+    let key = cache
+        .as_ref()
+        .and_then(|cache| package_cache_key(cache, pkg));
+    if let Some((cache, key)) = cache.as_ref().zip(key) {
+        if let Some(docs) = cache.get("package-module-docs", key) {
+            return Ok(docs);
+        }
+    }
+
     let toml_id = get_manifest_id(pkg)?;
     let manifest = ctx.get_manifest(toml_id)?;
 
     let entry_point = toml_id.join(&manifest.package.entrypoint);
-    module_docs(ctx, entry_point)
+    let docs = module_docs(ctx, entry_point)?;
+
+    if let Some((cache, key)) = cache.as_ref().zip(key) {
+        cache.put("package-module-docs", key, &docs);
+    }
+    Ok(docs)
+}
+
+/// Gets the key of a package in the disk cache, which changes with the
+/// contents of the package.
+pub(crate) fn package_cache_key(cache: &DiskCache, pkg: &PackageInfo) -> Option<u128> {
+    let contents = cache.package_hash(&pkg.path)?;
+    Some(hash128(&(
+        &pkg.namespace,
+        &pkg.name,
+        &pkg.version,
+        contents,
+    )))
 }
 
 /// Get documentation of definitions in a module.
@@ -9,12 +9,40 @@ use typst::diag::{eco_format, StrResult};
 use typst::syntax::package::PackageManifest;
 use typst::syntax::{FileId, Span};
 
-use crate::docs::{file_id_repr, module_docs, DefDocs, PackageDefInfo};
+use crate::docs::{file_id_repr, module_docs, package_cache_key, DefDocs, PackageDefInfo};
 use crate::package::{get_manifest_id, PackageInfo};
 use crate::LocalContext;
 
 /// Generate full documents in markdown format
+///
+/// The documents are cached on disk by the contents of the package.
 pub fn package_docs(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<String> {
+    let cache = ctx.analysis.disk_cache.clone();
+    let key = cache
+        .as_ref()
+        .and_then(|cache| package_cache_key(cache, spec));
+    if let Some((cache, key)) = cache.as_ref().zip(key) {
+        if let Some(md) = cache.get("package-docs", key) {
+            return Ok(md);
+        }
+    }
+
+    let md = generate_package_docs(ctx, spec)?;
+
+    if let Some((cache, key)) = cache.as_ref().zip(key) {
+        cache.put("package-docs", key, &md);
+    }
+    Ok(md)
+}
+
+/// Generate full documents in a browsable markdown format, which has a table
+/// of contents and an anchor per module and symbol, but no annotations for
+/// the editors.
+pub fn package_markdown_docs(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<String> {
+    Ok(browsable_docs(&package_docs(ctx, spec)?))
+}
+
+fn generate_package_docs(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<String> {
     log::info!("generate_md_docs {spec:?}");
 
     let mut md = String::new();
@@ -300,6 +328,72 @@ pub fn package_docs(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<Str
     Ok(md)
 }
 
+/// Removes the annotations from the documents generated by [`package_docs`],
+/// and adds the anchors and the table of contents.
+fn browsable_docs(md: &str) -> String {
+    // The anchors are the same as the slugs of the headings on GitHub, which
+    // are used by the links to the modules and the external symbols.
+    fn anchor(heading: &str) -> String {
+        heading
+            .replace(": ", "-")
+            .replace(' ', "-")
+            .replace('.', "")
+            .to_lowercase()
+    }
+
+    let mut body = String::new();
+    let mut toc = String::new();
+    let mut errors = vec![];
+    let mut in_errors = false;
+    for line in md.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("<!--") && trimmed.ends_with("-->") {
+            if trimmed.starts_with("<!-- begin:errors") {
+                in_errors = true;
+            } else if trimmed.starts_with("<!-- end:errors") {
+                in_errors = false;
+            }
+            continue;
+        }
+        if in_errors {
+            if let Some(err) = line.strip_prefix("- ") {
+                errors.push(err);
+            }
+            continue;
+        }
+
+        if let Some(module) = line.strip_prefix("## Module: ") {
+            let id = anchor(&format!("module: {module}"));
+            let _ = writeln!(toc, "- [Module {module}](#{id})");
+            let _ = writeln!(body, "<a id=\"{id}\"></a>\n");
+        } else if let Some(symbol) = line.strip_prefix("### ") {
+            let id = anchor(symbol);
+            let name = symbol.split_once(" in ").map_or(symbol, |(name, _)| name);
+            let _ = writeln!(toc, "  - [{name}](#{id})");
+            let _ = writeln!(body, "<a id=\"{id}\"></a>\n");
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+
+    if !errors.is_empty() {
+        let _ = writeln!(body, "## Errors");
+        for err in errors {
+            let _ = writeln!(body, "- {err}");
+        }
+    }
+
+    // Inserts the table of contents before the first module.
+    let split = body.find("\n---\n").map_or(body.len(), |idx| idx + 1);
+    let mut out = String::with_capacity(body.len() + toc.len());
+    out.push_str(&body[..split]);
+    if !toc.is_empty() {
+        let _ = writeln!(out, "## Contents\n\n{toc}");
+    }
+    out.push_str(&body[split..]);
+    out
+}
+
 fn jbase64<T: Serialize>(s: &T) -> String {
     use base64::Engine;
     let content = serde_json::to_string(s).unwrap();
@@ -350,9 +444,46 @@ fn remove_list_annotations(s: &str) -> String {
 mod tests {
     use tinymist_world::package::{PackageRegistry, PackageSpec};
 
-    use super::{package_docs, PackageInfo};
+    use super::{browsable_docs, package_docs, PackageInfo};
     use crate::tests::*;
 
+    #[test]
+    fn browsable() {
+        let md = r#"# @preview/example:0.1.0
+
+<!-- begin:package e30= -->
+---
+## Module: example
+<!-- begin:module example e30= -->
+### function: draw in example
+<!-- begin:symbol symbol-function-example.draw e30= -->
+Draws a shape.
+<!-- end:symbol symbol-function-example.draw -->
+<!-- end:module example -->
+<!-- begin:errors e30= -->
+## Errors
+<!-- end:errors -->
+<!-- end:package e30= -->
+"#;
+        assert_snapshot!(browsable_docs(md), @r###"
+        # @preview/example:0.1.0
+
+        ## Contents
+
+        - [Module example](#module-example)
+          - [function: draw](#function-draw-in-example)
+
+        ---
+        <a id="module-example"></a>
+
+        ## Module: example
+        <a id="function-draw-in-example"></a>
+
+        ### function: draw in example
+        Draws a shape.
+        "###);
+    }
+
     fn test(pkg: PackageSpec) {
         run_with_sources("", |verse: &mut LspUniverse, path| {
             let pkg_root = verse.registry.resolve(&pkg).unwrap();
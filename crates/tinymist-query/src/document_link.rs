@@ -1,4 +1,10 @@
-use crate::{analysis::get_link_exprs, prelude::*, SemanticRequest};
+use std::collections::HashSet;
+
+use typst::syntax::Span;
+
+use crate::analysis::{get_link_exprs, LinkTarget};
+use crate::ty::{BuiltinTy, Ty};
+use crate::{prelude::*, SemanticRequest};
 
 /// The [`textDocument/documentLink`] request is sent from the client to the
 /// server to request the location of links in a document.
@@ -24,13 +30,34 @@ impl SemanticRequest for DocumentLinkRequest {
     fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
         let source = ctx.source_by_path(&self.path).ok()?;
         let links = get_link_exprs(&source);
-        if links.objects.is_empty() {
+
+        let mut targets = links
+            .objects
+            .iter()
+            .map(|obj| (obj.range.clone(), obj.target.resolve(ctx)))
+            .collect::<Vec<_>>();
+
+        // Path literals passed to user functions are only known by their types.
+        let linked = links.objects.iter().map(|obj| obj.span).collect();
+        let mut worker = TypedPathWorker {
+            ctx,
+            linked,
+            paths: vec![],
+        };
+        worker.work(LinkedNode::new(source.root()));
+        let typed = std::mem::take(&mut worker.paths);
+        for (range, target) in typed {
+            targets.push((range, target.resolve(ctx)));
+        }
+
+        if targets.is_empty() {
             return None;
         }
 
-        let links = links.objects.iter().map(|obj| DocumentLink {
-            range: ctx.to_lsp_range(obj.range.clone(), &source),
-            target: obj.target.resolve(ctx),
+        targets.sort_by_key(|(range, _)| range.start);
+        let links = targets.into_iter().map(|(range, target)| DocumentLink {
+            range: ctx.to_lsp_range(range, &source),
+            target,
             tooltip: None,
             data: None,
         });
@@ -38,6 +65,64 @@ impl SemanticRequest for DocumentLinkRequest {
     }
 }
 
+/// Collects the string literals in arguments that are typed as paths.
+struct TypedPathWorker<'a> {
+    ctx: &'a mut LocalContext,
+    /// The strings that are already linked syntactically.
+    linked: HashSet<Span>,
+    paths: Vec<(Range<usize>, LinkTarget)>,
+}
+
+impl TypedPathWorker<'_> {
+    fn work(&mut self, node: LinkedNode) {
+        if node.kind() == SyntaxKind::Str {
+            self.check_str(&node);
+            return;
+        }
+
+        for child in node.children() {
+            self.work(child);
+        }
+    }
+
+    fn check_str(&mut self, node: &LinkedNode) -> Option<()> {
+        if self.linked.contains(&node.span()) {
+            return None;
+        }
+        let is_arg = matches!(
+            node.parent_kind(),
+            Some(SyntaxKind::Args | SyntaxKind::Named)
+        );
+        if !is_arg {
+            return None;
+        }
+
+        let content = node.cast::<ast::Str>()?.get();
+        let range = node.range();
+        let range = range.start + 1..range.end - 1;
+        if range.is_empty() || content.starts_with('@') || content.contains("://") {
+            return None;
+        }
+
+        let ty = self.ctx.shared_().post_type_of_node(node.clone())?;
+        if !is_path_ty(&ty) {
+            return None;
+        }
+
+        let id = node.span().id()?;
+        self.paths.push((range, LinkTarget::Path(id, content)));
+        Some(())
+    }
+}
+
+fn is_path_ty(ty: &Ty) -> bool {
+    match ty {
+        Ty::Builtin(BuiltinTy::Path(..)) => true,
+        Ty::Union(types) => types.iter().any(is_path_ty),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
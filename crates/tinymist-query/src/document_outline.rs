@@ -0,0 +1,221 @@
+use comemo::Track;
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use typst::engine::{Engine, Route, Sink, Traced};
+use typst::foundations::{Content, Context, Element, NativeElement, Selector, StyleChain};
+use typst::introspection::Counter;
+use typst::math::EquationElem;
+use typst::model::{HeadingElem, Numbering};
+use typst::syntax::Span;
+
+use crate::prelude::*;
+
+/// The maximum length of the titles of the equations in the outline.
+const EQUATION_TITLE_LEN: usize = 40;
+
+/// A request to get the outline of the compiled document, whose headings and
+/// equations are numbered as they are in the exported document.
+///
+/// This is not part of the LSP protocol.
+#[derive(Debug, Clone)]
+pub struct DocumentOutlineRequest {
+    /// The path of the document to get the outline for.
+    pub path: PathBuf,
+}
+
+/// The kind of an item in the document outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DocumentOutlineKind {
+    /// A heading.
+    Heading,
+    /// A numbered block equation.
+    Equation,
+}
+
+/// An item in the document outline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentOutlineItem {
+    /// The kind of the item.
+    pub kind: DocumentOutlineKind,
+    /// The level of the heading, or the level of the equation's heading plus
+    /// one.
+    pub level: usize,
+    /// The resolved number of the item, e.g. `1.2` or `(3)`, if it is
+    /// numbered.
+    pub numbering: Option<String>,
+    /// The plain text of the heading, or the source of the equation.
+    pub title: String,
+    /// The location of the item in the source files.
+    pub location: Option<LspLocation>,
+    /// The items nested in the heading.
+    pub children: Vec<DocumentOutlineItem>,
+}
+
+impl StatefulRequest for DocumentOutlineRequest {
+    type Response = Vec<DocumentOutlineItem>;
+
+    fn request(self, ctx: &mut LocalContext, graph: LspComputeGraph) -> Option<Self::Response> {
+        let doc = graph.snap.success_doc.as_ref()?;
+        let numbering = ResolvedNumbering::new(ctx.world(), doc);
+
+        let introspector = doc.introspector();
+        let selector = Selector::Or(eco_vec![
+            HeadingElem::elem().select(),
+            EquationElem::elem().select(),
+        ]);
+
+        let mut tree: Vec<DocumentOutlineItem> = vec![];
+        let mut heading_level = 0;
+        for elem in introspector.query(&selector).iter() {
+            let item = if let Some(heading) = elem.to_packed::<HeadingElem>() {
+                heading_level = heading.resolve_level(StyleChain::default()).get();
+                DocumentOutlineItem {
+                    kind: DocumentOutlineKind::Heading,
+                    level: heading_level,
+                    numbering: numbering.get(elem.span()).map(ToString::to_string),
+                    title: heading.body.plain_text().trim().into(),
+                    location: span_location(ctx, elem.span()),
+                    children: vec![],
+                }
+            } else {
+                // Unnumbered equations are not in the outline, as they are
+                // not referable.
+                let Some(number) = numbering.get(elem.span()) else {
+                    continue;
+                };
+                DocumentOutlineItem {
+                    kind: DocumentOutlineKind::Equation,
+                    level: heading_level + 1,
+                    numbering: Some(number.to_string()),
+                    title: equation_title(ctx, elem.span()),
+                    location: span_location(ctx, elem.span()),
+                    children: vec![],
+                }
+            };
+
+            let mut children = &mut tree;
+            while children.last().is_some_and(|last| {
+                last.kind == DocumentOutlineKind::Heading && last.level < item.level
+            }) {
+                children = &mut children.last_mut().unwrap().children;
+            }
+            children.push(item);
+        }
+
+        Some(tree)
+    }
+}
+
+/// The numbers of the headings and block equations in a compiled document,
+/// resolved by their numbering patterns and counters.
+#[derive(Debug, Default)]
+pub(crate) struct ResolvedNumbering {
+    numbers: HashMap<Span, EcoString>,
+}
+
+impl ResolvedNumbering {
+    /// Resolves the numbers in the document.
+    pub(crate) fn new(world: &dyn World, doc: &TypstDocument) -> Self {
+        let introspector = doc.introspector();
+        let traced = Traced::default();
+        let mut sink = Sink::new();
+        let mut engine = Engine {
+            routines: &typst::ROUTINES,
+            world: world.track(),
+            route: Route::default(),
+            introspector: introspector.track(),
+            traced: traced.track(),
+            sink: sink.track_mut(),
+        };
+
+        let mut numbers = HashMap::new();
+        let headings = introspector.query(&HeadingElem::elem().select());
+        for elem in headings.iter() {
+            let heading = elem.to_packed::<HeadingElem>().unwrap();
+            let numbering = heading.numbering(StyleChain::default()).clone();
+            let number = numbering.and_then(|numbering| {
+                resolve_number(&mut engine, HeadingElem::elem(), elem, &numbering)
+            });
+            if let Some(number) = number {
+                numbers.insert(elem.span(), number);
+            }
+        }
+
+        let equations = introspector.query(&EquationElem::elem().select());
+        for elem in equations.iter() {
+            let equation = elem.to_packed::<EquationElem>().unwrap();
+            if !equation.block(StyleChain::default()) {
+                continue;
+            }
+            let numbering = equation.numbering(StyleChain::default()).clone();
+            let number = numbering.and_then(|numbering| {
+                resolve_number(&mut engine, EquationElem::elem(), elem, &numbering)
+            });
+            if let Some(number) = number {
+                numbers.insert(elem.span(), number);
+            }
+        }
+
+        Self { numbers }
+    }
+
+    /// Gets the number of the element at the span.
+    pub(crate) fn get(&self, span: Span) -> Option<&EcoString> {
+        self.numbers.get(&span)
+    }
+
+    /// Gets the numbers of the elements in the source, along with the ranges
+    /// of the elements.
+    pub(crate) fn in_source(&self, source: &Source) -> Vec<(Range<usize>, EcoString)> {
+        let id = source.id();
+        self.numbers
+            .iter()
+            .filter(|(span, _)| span.id() == Some(id))
+            .filter_map(|(span, number)| Some((source.range(*span)?, number.clone())))
+            .collect()
+    }
+}
+
+/// Displays the counter of the element at its location by the numbering.
+fn resolve_number(
+    engine: &mut Engine,
+    func: Element,
+    elem: &Content,
+    numbering: &Numbering,
+) -> Option<EcoString> {
+    let loc = elem.location()?;
+    let state = Counter::of(func).at_loc(engine, loc).ok()?;
+    let context = Context::new(Some(loc), Some(StyleChain::default()));
+    let number = numbering.apply(engine, context.track(), &state.0).ok()?;
+    let number = number.display().plain_text();
+    let number = number.trim();
+    (!number.is_empty()).then(|| number.into())
+}
+
+fn span_location(ctx: &LocalContext, span: Span) -> Option<LspLocation> {
+    let id = span.id()?;
+    let source = ctx.source_by_id(id).ok()?;
+    let range = source.range(span)?;
+    Some(LspLocation {
+        uri: ctx.uri_for_id(id).ok()?,
+        range: ctx.to_lsp_range(range, &source),
+    })
+}
+
+/// Gets the source of an equation, with the whitespace collapsed.
+fn equation_title(ctx: &LocalContext, span: Span) -> String {
+    let source = span.id().and_then(|id| ctx.source_by_id(id).ok());
+    let text = source
+        .as_ref()
+        .and_then(|source| source.text().get(source.range(span)?));
+    let text = text.unwrap_or_default().split_whitespace().join(" ");
+    if text.chars().count() <= EQUATION_TITLE_LEN {
+        return text;
+    }
+
+    let mut title: String = text.chars().take(EQUATION_TITLE_LEN - 1).collect();
+    title.push('…');
+    title
+}
@@ -1,9 +1,9 @@
 use lsp_types::SymbolKind;
 
 use crate::{
+    document_outline::ResolvedNumbering,
     prelude::*,
-    syntax::{get_lexical_hierarchy, LexicalHierarchy, LexicalScopeKind},
-    SyntaxRequest,
+    syntax::{get_lexical_hierarchy, LexicalHierarchy, LexicalKind, LexicalScopeKind},
 };
 
 /// The [`textDocument/documentSymbol`] request is sent from the client to the
@@ -18,22 +18,27 @@ use crate::{
 ///   symbol’s container name should be used to infer a hierarchy.
 /// * [`DocumentSymbolResponse::Nested`] which is a hierarchy of symbols found
 ///   in a given text document.
+///
+/// If the document is compiled, the names of the headings are prefixed with
+/// their numbers, as they are numbered in the exported document.
 #[derive(Debug, Clone)]
 pub struct DocumentSymbolRequest {
     /// The path of the document to retrieve symbols from.
     pub path: PathBuf,
 }
 
-impl SyntaxRequest for DocumentSymbolRequest {
+impl StatefulRequest for DocumentSymbolRequest {
     type Response = DocumentSymbolResponse;
 
-    fn request(
-        self,
-        source: &Source,
-        position_encoding: PositionEncoding,
-    ) -> Option<Self::Response> {
-        let hierarchy = get_lexical_hierarchy(source, LexicalScopeKind::Symbol)?;
-        let symbols = symbols_in_hierarchy(&hierarchy, source, position_encoding);
+    fn request(self, ctx: &mut LocalContext, graph: LspComputeGraph) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let hierarchy = get_lexical_hierarchy(&source, LexicalScopeKind::Symbol)?;
+        let numbers = match graph.snap.success_doc.as_ref() {
+            Some(doc) => ResolvedNumbering::new(ctx.world(), doc).in_source(&source),
+            None => vec![],
+        };
+
+        let symbols = symbols_in_hierarchy(&hierarchy, &source, ctx.position_encoding(), &numbers);
         Some(DocumentSymbolResponse::Nested(symbols))
     }
 }
@@ -43,15 +48,30 @@ fn symbols_in_hierarchy(
     hierarchy: &[LexicalHierarchy],
     source: &Source,
     position_encoding: PositionEncoding,
+    numbers: &[(Range<usize>, EcoString)],
 ) -> Vec<DocumentSymbol> {
     hierarchy
         .iter()
         .filter(|hierarchy| TryInto::<SymbolKind>::try_into(hierarchy.info.kind.clone()).is_ok())
         .map(|hierarchy| {
-            let range = to_lsp_range(hierarchy.info.range.clone(), source, position_encoding);
+            let info = &hierarchy.info;
+            let range = to_lsp_range(info.range.clone(), source, position_encoding);
+            // The range of a heading symbol is the range of its body, which is
+            // inside the heading element.
+            let number = matches!(info.kind, LexicalKind::Heading(..))
+                .then(|| {
+                    numbers.iter().find(|(elem_range, _)| {
+                        elem_range.start <= info.range.start && info.range.end <= elem_range.end
+                    })
+                })
+                .flatten();
+            let name = match number {
+                Some((_, number)) => format!("{number} {}", info.name),
+                None => info.name.to_string(),
+            };
 
             DocumentSymbol {
-                name: hierarchy.info.name.to_string(),
+                name,
                 detail: None,
                 kind: hierarchy.info.kind.clone().try_into().unwrap(),
                 tags: None,
@@ -61,7 +81,7 @@ fn symbols_in_hierarchy(
                 children: hierarchy
                     .children
                     .as_ref()
-                    .map(|ch| symbols_in_hierarchy(ch, source, position_encoding)),
+                    .map(|ch| symbols_in_hierarchy(ch, source, position_encoding, numbers)),
             }
         })
         .collect()
@@ -70,16 +90,20 @@ fn symbols_in_hierarchy(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::syntax::find_module_level_docs;
     use crate::tests::*;
 
     #[test]
     fn test() {
         snapshot_testing("document_symbols", &|ctx, path| {
-            let request = DocumentSymbolRequest { path: path.clone() };
-
             let source = ctx.source_by_path(&path).unwrap();
 
-            let result = request.request(&source, PositionEncoding::Utf16);
+            let docs = find_module_level_docs(&source).unwrap_or_default();
+            let properties = get_test_properties(&docs);
+            let graph = compile_doc_for_test(ctx, &properties);
+
+            let request = DocumentSymbolRequest { path: path.clone() };
+            let result = request.request(ctx, graph);
             assert_snapshot!(JsonRepr::new_redacted(result.unwrap(), &REDACT_LOC));
         });
     }
@@ -0,0 +1,122 @@
+use typst::foundations::{Repr, Str};
+
+use crate::{
+    prelude::*, syntax::InterpretMode, EcoSnippetTextEdit, InteractCodeContextRequest,
+    SemanticRequest,
+};
+
+/// The extensions of the images that can be loaded by `image`.
+const IMAGE_EXTENSIONS: &[&str] = &["gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+/// A request to get the snippet inserting files that are dropped or pasted
+/// into a document.
+///
+/// Images are inserted as figures with a tabstop at the caption, other files
+/// are inserted as calls to the functions loading them. The files are expected
+/// to be already copied into the project, e.g. under the assets directory.
+#[derive(Debug, Clone)]
+pub struct DropFilesRequest {
+    /// The path of the document.
+    pub path: PathBuf,
+    /// The position where the files are dropped.
+    pub position: LspPosition,
+    /// The paths of the dropped files.
+    pub files: Vec<PathBuf>,
+}
+
+impl SemanticRequest for DropFilesRequest {
+    type Response = EcoSnippetTextEdit;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let cursor = ctx.to_typst_pos(self.position, &source)?;
+        let mode = InteractCodeContextRequest::mode_at(&source, cursor)?;
+
+        let base = self.path.parent()?;
+        let mut snippets = vec![];
+        for (idx, file) in self.files.iter().enumerate() {
+            let rel = tinymist_std::path::diff(file, base)?;
+            let rel = tinymist_std::path::unix_slash(&rel);
+            snippets.push(drop_snippet(&rel, mode, idx + 1)?);
+        }
+        if snippets.is_empty() {
+            return None;
+        }
+
+        let sep = match mode {
+            InterpretMode::Code => "\n",
+            InterpretMode::String => ", ",
+            _ => "\n\n",
+        };
+        let range = ctx.to_lsp_range(cursor..cursor, &source);
+        Some(EcoSnippetTextEdit::new(range, snippets.join(sep).into()))
+    }
+}
+
+/// Creates the snippet inserting a file in the given mode.
+fn drop_snippet(rel: &str, mode: InterpretMode, tabstop: usize) -> Option<String> {
+    let path = escape_snippet(&Str::from(rel).repr());
+    let ext = Path::new(rel)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    let code = match ext.as_str() {
+        ext if IMAGE_EXTENSIONS.contains(&ext) => match mode {
+            // A figure doesn't fit in an equation.
+            InterpretMode::Math => format!("image({path})"),
+            _ => format!("figure(image({path}), caption: [${{{tabstop}:caption}}])"),
+        },
+        "typ" => format!("include {path}"),
+        "bib" => format!("bibliography({path})"),
+        "csv" | "json" | "toml" | "xml" | "yaml" => format!("{ext}({path})"),
+        "yml" => format!("yaml({path})"),
+        _ => format!("read({path})"),
+    };
+
+    Some(match mode {
+        InterpretMode::Markup | InterpretMode::Math => format!("#{code}"),
+        InterpretMode::Code => code,
+        InterpretMode::String => escape_snippet(rel),
+        InterpretMode::Comment | InterpretMode::Raw => return None,
+    })
+}
+
+/// Escapes the characters having special meanings in a snippet.
+fn escape_snippet(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '$' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_as_figure() {
+        assert_eq!(
+            drop_snippet("assets/cat.png", InterpretMode::Markup, 1).as_deref(),
+            Some("#figure(image(\"assets/cat.png\"), caption: [${1:caption}])")
+        );
+        assert_eq!(
+            drop_snippet("assets/cat.PNG", InterpretMode::Math, 1).as_deref(),
+            Some("#image(\"assets/cat.PNG\")")
+        );
+        assert_eq!(
+            drop_snippet("data.yml", InterpretMode::Code, 1).as_deref(),
+            Some("yaml(\"data.yml\")")
+        );
+        assert_eq!(
+            drop_snippet("$x.typ", InterpretMode::String, 1).as_deref(),
+            Some("\\$x.typ")
+        );
+        assert_eq!(drop_snippet("a.png", InterpretMode::Raw, 1), None);
+    }
+}
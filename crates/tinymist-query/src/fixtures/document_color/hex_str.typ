@@ -0,0 +1,3 @@
+#let palette = (primary: rgb("#aabbcc"), secondary: color.rgb("#ff0"))
+#let labels = (heading: "#aabbcc", tag: "#ff0", text: "#hello")
+#link("#abc")[anchor]
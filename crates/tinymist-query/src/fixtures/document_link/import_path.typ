@@ -0,0 +1,5 @@
+/// path: base.typ
+#let x = 1;
+-----
+#import "base.typ": *
+#include "base.typ"
@@ -0,0 +1,7 @@
+// compile: true
+
+#set heading(numbering: "1.1")
+= Intro
+== Scope
+#counter(heading).update(4)
+= Method
\ No newline at end of file
@@ -0,0 +1,15 @@
+/// path: references.bib
+@article{Russell:1908,
+Author = {Bertand Russell},
+Journal = {American Journal of Mathematics},
+Pages = {222--262},
+Title = {Mathematical logic based on the theory of types},
+Volume = 30,
+Year = 1908}
+
+-----
+/// compile: true
+
+#cite(/* position after */ <Russell:1908>)
+
+#bibliography("references.bib")
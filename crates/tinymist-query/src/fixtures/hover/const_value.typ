@@ -0,0 +1,2 @@
+#let gap = 6pt * 2
+#let f() = (/* ident after */ gap)
@@ -0,0 +1,2 @@
+#figure(image("fig.png"), caption: [A figure])
+#figure([#image("a.png") #image("b.png", alt: "B")])
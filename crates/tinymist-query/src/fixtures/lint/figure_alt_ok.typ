@@ -0,0 +1,2 @@
+#figure(image("fig.png", alt: "A figure"), caption: [A figure])
+#figure(table(columns: 2)[a][b])
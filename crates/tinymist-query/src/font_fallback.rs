@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use typst::layout::{Frame, FrameItem};
+use typst::syntax::Span;
+use typst::text::{Font, TextItem};
+
+use crate::prelude::*;
+
+/// The maximum number of candidate fonts listed for a cluster.
+const MAX_CANDIDATES: usize = 8;
+
+/// Why a font is used for a glyph cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FontChoiceReason {
+    /// The font is the primary font of the text.
+    Primary,
+    /// The primary font lacks some of the characters, so the font is selected
+    /// from the fallback chain.
+    Fallback,
+    /// The primary font covers the characters, but the font is requested for
+    /// them, e.g. by the `covers` of a font family or by a show rule.
+    Requested,
+    /// No font covers some of the characters, so they are rendered as the
+    /// missing glyph of the font.
+    Missing,
+}
+
+/// A run of glyphs in a text, which are shaped by the same font.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontCluster {
+    /// The range of the characters in the source.
+    pub range: LspRange,
+    /// The characters of the cluster.
+    pub text: String,
+    /// The family of the used font.
+    pub font: String,
+    /// The PostScript name of the used font.
+    pub postscript_name: Option<String>,
+    /// The family of the primary font of the text, which is the font shaping
+    /// most of the glyphs of the text.
+    pub primary: Option<String>,
+    /// Why the font is used.
+    pub reason: FontChoiceReason,
+    /// The characters of the cluster that the primary font lacks.
+    pub missing_in_primary: String,
+    /// The families of the available fonts covering all the characters of
+    /// the cluster.
+    pub candidates: Vec<String>,
+    /// A human-readable explanation of the reason.
+    pub explanation: String,
+}
+
+/// The response to a [`FontFallbackRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFallbackResponse {
+    /// The glyph clusters in the selected range, in the layout order.
+    pub clusters: Vec<FontCluster>,
+}
+
+/// A request to explain which fonts are used for the text in a range of a
+/// document, and why.
+///
+/// This is not part of the LSP protocol.
+#[derive(Debug, Clone)]
+pub struct FontFallbackRequest {
+    /// The path of the document.
+    pub path: PathBuf,
+    /// The selected range of the text.
+    pub range: LspRange,
+}
+
+impl StatefulRequest for FontFallbackRequest {
+    type Response = FontFallbackResponse;
+
+    fn request(self, ctx: &mut LocalContext, graph: LspComputeGraph) -> Option<Self::Response> {
+        let doc = graph.snap.success_doc.as_ref()?;
+        let TypstDocument::Paged(doc) = doc else {
+            return None;
+        };
+
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let selected = ctx.to_typst_range(self.range, &source)?;
+
+        let mut glyphs = vec![];
+        for page in &doc.pages {
+            collect_glyphs(&page.frame, &source, &mut glyphs);
+        }
+
+        // The primary font of a text is the one shaping most of its glyphs.
+        let mut uses = HashMap::<(Span, &Font), usize>::new();
+        for glyph in &glyphs {
+            *uses.entry((glyph.span, glyph.font)).or_default() += 1;
+        }
+        let mut primaries = HashMap::<Span, (&Font, usize)>::new();
+        for ((span, font), count) in uses {
+            let primary = primaries.entry(span).or_insert((font, count));
+            if count > primary.1 {
+                *primary = (font, count);
+            }
+        }
+
+        let mut clusters: Vec<(&SourceGlyph, Range<usize>, String)> = vec![];
+        for glyph in glyphs.iter().filter(|g| selected.contains(&g.offset)) {
+            let end = glyph.offset + glyph.text.len().max(1);
+            if let Some((first, range, text)) = clusters.last_mut() {
+                if first.span == glyph.span
+                    && first.font == glyph.font
+                    && first.missing == glyph.missing
+                {
+                    range.start = range.start.min(glyph.offset);
+                    range.end = range.end.max(end);
+                    text.push_str(glyph.text);
+                    continue;
+                }
+            }
+
+            clusters.push((glyph, glyph.offset..end, glyph.text.to_owned()));
+        }
+
+        let clusters = clusters
+            .into_iter()
+            .map(|(first, range, text)| {
+                let primary = primaries.get(&first.span).map(|(font, _)| *font);
+                let range = ctx.to_lsp_range(range, &source);
+                explain(ctx, first, primary, range, text)
+            })
+            .collect();
+
+        Some(FontFallbackResponse { clusters })
+    }
+}
+
+/// A glyph shaped from the selected source.
+struct SourceGlyph<'a> {
+    /// The span of the text node.
+    span: Span,
+    /// The offset of the glyph in the source.
+    offset: usize,
+    /// The characters of the glyph.
+    text: &'a str,
+    /// The font shaping the glyph.
+    font: &'a Font,
+    /// Whether the glyph is the missing glyph of the font.
+    missing: bool,
+}
+
+/// Collects the glyphs of a frame that are shaped from a source.
+fn collect_glyphs<'a>(frame: &'a Frame, source: &Source, glyphs: &mut Vec<SourceGlyph<'a>>) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => collect_glyphs(&group.frame, source, glyphs),
+            FrameItem::Text(text) => collect_text(text, source, glyphs),
+            _ => {}
+        }
+    }
+}
+
+fn collect_text<'a>(text: &'a TextItem, source: &Source, glyphs: &mut Vec<SourceGlyph<'a>>) {
+    for glyph in &text.glyphs {
+        let (span, span_offset) = glyph.span;
+        if span.id() != Some(source.id()) {
+            continue;
+        }
+        let Some(node) = source.range(span) else {
+            continue;
+        };
+
+        let offset = (node.start + usize::from(span_offset)).min(node.end.saturating_sub(1));
+        glyphs.push(SourceGlyph {
+            span,
+            offset,
+            text: text.text.get(glyph.range()).unwrap_or_default(),
+            font: &text.font,
+            missing: glyph.id == 0,
+        });
+    }
+}
+
+/// Explains why a font is used for a cluster.
+fn explain(
+    ctx: &LocalContext,
+    glyph: &SourceGlyph,
+    primary: Option<&Font>,
+    range: LspRange,
+    text: String,
+) -> FontCluster {
+    let (font, missing) = (glyph.font, glyph.missing);
+    let family = &font.info().family;
+    let missing_in_primary = primary
+        .map(|primary| {
+            let coverage = &primary.info().coverage;
+            let chars = text.chars().filter(|c| !coverage.contains(*c as u32));
+            chars.collect::<String>()
+        })
+        .unwrap_or_default();
+
+    let primary_family = primary.map(|primary| primary.info().family.clone());
+    let (reason, explanation) = match &primary_family {
+        _ if missing => (
+            FontChoiceReason::Missing,
+            format!("no available font covers {text:?}, so the missing glyph of {family} is rendered"),
+        ),
+        Some(_) if primary == Some(font) => (
+            FontChoiceReason::Primary,
+            format!("{family} is the primary font of the text"),
+        ),
+        Some(primary) if !missing_in_primary.is_empty() => (
+            FontChoiceReason::Fallback,
+            format!("{primary} lacks {missing_in_primary:?}, so {family} is selected from the fallback fonts"),
+        ),
+        Some(primary) => (
+            FontChoiceReason::Requested,
+            format!("{primary} covers the text, but {family} is requested for it, e.g. by the `covers` of a font family or by a show rule"),
+        ),
+        None => (
+            FontChoiceReason::Primary,
+            format!("{family} is the primary font of the text"),
+        ),
+    };
+
+    let mut candidates = vec![];
+    for (name, mut infos) in ctx.world().book().families() {
+        if candidates.len() >= MAX_CANDIDATES {
+            break;
+        }
+        let covers = |info: &typst::text::FontInfo| {
+            text.chars()
+                .filter(|c| !c.is_whitespace())
+                .all(|c| info.coverage.contains(c as u32))
+        };
+        if infos.any(covers) {
+            candidates.push(name.to_owned());
+        }
+    }
+
+    FontCluster {
+        range,
+        text,
+        font: family.clone(),
+        postscript_name: font.find_name(ttf_parser::name_id::POST_SCRIPT_NAME),
+        primary: primary_family,
+        reason,
+        missing_in_primary,
+        candidates,
+        explanation,
+    }
+}
@@ -34,9 +34,11 @@ impl StatefulRequest for GotoDefinitionRequest {
 
         let def = ctx.def_of_syntax(&source, doc, syntax)?;
 
-        let fid = def.file_id()?;
-        let name_range = def.name_range(ctx.shared()).unwrap_or_default();
-        let full_range = def.full_range().unwrap_or_else(|| name_range.clone());
+        let DefLocation {
+            fid,
+            name_range,
+            full_range,
+        } = def.location(ctx.shared())?;
 
         let res = Some(GotoDefinitionResponse::Link(vec![LocationLink {
             origin_selection_range: Some(origin_selection_range),
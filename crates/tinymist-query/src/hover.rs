@@ -2,13 +2,16 @@ use core::fmt::{self, Write};
 
 use tinymist_std::typst::TypstDocument;
 use typst::foundations::repr::separated_list;
+use typst::foundations::Scope;
 use typst_shim::syntax::LinkedNodeExt;
 
 use crate::analysis::get_link_exprs_in;
-use crate::bib::{render_citation_string, RenderedBibCitation};
-use crate::jump_from_cursor;
+use crate::analysis::snippet::svg_markdown;
+use crate::bib::{bib_entry_fields, render_citation_string};
+use crate::compile_snippet::render_in_context;
 use crate::prelude::*;
 use crate::upstream::{route_of_value, truncated_repr, Tooltip};
+use crate::{jump_from_cursor, ColorTheme};
 
 /// The [`textDocument/hover`] request asks the server for hover information at
 /// a given text document position.
@@ -92,6 +95,7 @@ impl HoverWorker<'_> {
     fn work(&mut self) {
         self.static_analysis();
         self.preview();
+        self.math();
         self.dynamic_analysis();
     }
 
@@ -134,7 +138,15 @@ impl HoverWorker<'_> {
                 }
             }
             BibEntry(..) => {
-                if let Some(details) = try_get_bib_details(&self.doc, self.ctx, def.name()) {
+                let bib_info = self.doc.as_ref().and_then(|doc| {
+                    let introspector = doc.introspector();
+                    self.ctx.analyze_bib(introspector)
+                });
+                let support_html = !self.ctx.shared.analysis.remove_html;
+                let details = bib_info.as_ref().and_then(|bib_info| {
+                    render_citation_string(bib_info, def.name(), support_html)
+                });
+                if let Some(details) = details {
                     self.def.push(format!(
                         "Bibliography: `{}` {}",
                         def.name(),
@@ -145,6 +157,19 @@ impl HoverWorker<'_> {
                     // fallback: no additional information
                     self.def.push(format!("Bibliography: `{}`", def.name()));
                 }
+
+                // The fields are shown even if the entry cannot be rendered by
+                // the style.
+                let entry = bib_info
+                    .as_ref()
+                    .and_then(|bib_info| bib_info.entries.get(def.name())?.raw_entry.clone());
+                let fields = entry.as_ref().map(bib_entry_fields).unwrap_or_default();
+                if !fields.is_empty() {
+                    let fields = fields
+                        .iter()
+                        .map(|(name, value)| format!("- **{name}**: {value}"));
+                    self.def.push(fields.join("\n"));
+                }
             }
             _ => {
                 let sym_docs = self.ctx.def_docs(&def);
@@ -160,11 +185,20 @@ impl HoverWorker<'_> {
                     type_doc.push_str("let ");
                     type_doc.push_str(def.name());
 
-                    match &sym_docs {
-                        Some(DefDocs::Variable(docs)) => {
+                    let const_value = match &sym_docs {
+                        Some(DefDocs::Variable(..)) => self.const_value(&def),
+                        _ => None,
+                    };
+
+                    match (&sym_docs, const_value) {
+                        (Some(DefDocs::Variable(..)), Some(val)) => {
+                            let ty = val.ty().short_name();
+                            let _ = write!(type_doc, ": {ty} = {}", truncated_repr(&val));
+                        }
+                        (Some(DefDocs::Variable(docs)), None) => {
                             push_result_ty(def.name(), docs.return_ty.as_ref(), &mut type_doc);
                         }
-                        Some(DefDocs::Function(docs)) => {
+                        (Some(DefDocs::Function(docs)), _) => {
                             let _ = docs.print(&mut type_doc);
                             push_result_ty(def.name(), docs.ret_ty.as_ref(), &mut type_doc);
                         }
@@ -191,6 +225,84 @@ impl HoverWorker<'_> {
         Some(())
     }
 
+    /// Evaluates the initializer of a `let` binding if it is a constant
+    /// expression, e.g. `#let gap = 6pt * 2`.
+    fn const_value(&self, def: &Definition) -> Option<Value> {
+        let source = self.ctx.source_by_id(def.file_id()?).ok()?;
+        let name = LinkedNode::new(source.root()).find(def.decl.span())?;
+        let binding = name.parent()?.cast::<ast::LetBinding>()?;
+        let init = binding.init()?;
+        if !self.is_const_expr(&source, init) {
+            return None;
+        }
+
+        self.ctx.mini_eval(init)
+    }
+
+    /// Checks whether the expression is composed of literals, arithmetic and
+    /// calls to constant color constructors.
+    fn is_const_expr(&self, source: &Source, expr: ast::Expr) -> bool {
+        match expr {
+            ast::Expr::None(_)
+            | ast::Expr::Auto(_)
+            | ast::Expr::Bool(_)
+            | ast::Expr::Int(_)
+            | ast::Expr::Float(_)
+            | ast::Expr::Numeric(_)
+            | ast::Expr::Str(_) => true,
+            ast::Expr::Parenthesized(expr) => self.is_const_expr(source, expr.expr()),
+            ast::Expr::Unary(unary) => self.is_const_expr(source, unary.expr()),
+            ast::Expr::Binary(binary) => {
+                let allowed = match binary.op() {
+                    ast::BinOp::Assign
+                    | ast::BinOp::AddAssign
+                    | ast::BinOp::SubAssign
+                    | ast::BinOp::MulAssign
+                    | ast::BinOp::DivAssign => false,
+                    // Repeating a string by an integer may allocate a string of
+                    // any size.
+                    ast::BinOp::Mul => !may_be_str(binary.lhs()) && !may_be_str(binary.rhs()),
+                    _ => true,
+                };
+                allowed
+                    && self.is_const_expr(source, binary.lhs())
+                    && self.is_const_expr(source, binary.rhs())
+            }
+            ast::Expr::FuncCall(call) => {
+                self.is_const_func(source, call.callee())
+                    && call.args().items().all(|arg| match arg {
+                        ast::Arg::Pos(expr) => self.is_const_expr(source, expr),
+                        ast::Arg::Named(named) => self.is_const_expr(source, named.expr()),
+                        ast::Arg::Spread(..) => false,
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks whether the callee resolves to one of the [`CONST_FUNCS`], so
+    /// that a user function shadowing a builtin is never called.
+    fn is_const_func(&self, source: &Source, callee: ast::Expr) -> bool {
+        let span = match callee {
+            ast::Expr::Ident(ident) => ident.span(),
+            ast::Expr::FieldAccess(access) => access.field().span(),
+            _ => return false,
+        };
+        let Some(Value::Func(func)) = self
+            .ctx
+            .def_of_span(source, None, span)
+            .and_then(|def| def.value())
+        else {
+            return false;
+        };
+
+        let global = self.ctx.world.library().global.scope();
+        CONST_FUNCS
+            .iter()
+            .filter_map(|path| builtin_by_path(global, path))
+            .any(|builtin| builtin == Value::Func(func.clone()))
+    }
+
     fn star(&mut self, mut node: &LinkedNode) -> Option<()> {
         if !matches!(node.kind(), SyntaxKind::Star) {
             return None;
@@ -293,17 +405,58 @@ impl HoverWorker<'_> {
         self.preview.push(preview_content);
         Some(())
     }
+
+    /// Renders the equation under the cursor in the context of the document.
+    fn math(&mut self) -> Option<()> {
+        let source = self.source.clone();
+        let leaf = LinkedNode::new(source.root()).leaf_at_compat(self.cursor)?;
+        let equation = std::iter::successors(Some(&leaf), |node| node.parent())
+            .find(|node| node.kind() == SyntaxKind::Equation)?;
+
+        let theme = match self.ctx.analysis.color_theme {
+            ColorTheme::Dark => "#set text(fill: rgb(\"#c0caf5\"))\n",
+            ColorTheme::Light => "",
+        };
+        let text = equation.get().clone().into_text();
+        let svg = render_in_context(self.ctx, &source, theme, &text)?;
+        self.preview.push(svg_markdown("Rendered Equation", &svg));
+        Some(())
+    }
 }
 
-fn try_get_bib_details(
-    doc: &Option<TypstDocument>,
-    ctx: &LocalContext,
-    name: &str,
-) -> Option<RenderedBibCitation> {
-    let doc = doc.as_ref()?;
-    let support_html = !ctx.shared.analysis.remove_html;
-    let bib_info = ctx.analyze_bib(doc.introspector())?;
-    render_citation_string(&bib_info, name, support_html)
+/// The builtin functions that are pure and cheap to call at analysis time.
+const CONST_FUNCS: &[&str] = &[
+    "rgb",
+    "luma",
+    "cmyk",
+    "oklab",
+    "oklch",
+    "color.hsl",
+    "color.hsv",
+];
+
+/// Gets a builtin value by its path in a scope, e.g. `color.hsl`.
+fn builtin_by_path(scope: &Scope, path: &str) -> Option<Value> {
+    let (head, rest) = match path.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (path, None),
+    };
+    let value = scope.get(head)?.read().clone();
+    match rest {
+        Some(rest) => builtin_by_path(value.scope()?, rest),
+        None => Some(value),
+    }
+}
+
+/// Checks whether a constant expression may evaluate to a string, which is
+/// only the case for string literals and their concatenations.
+fn may_be_str(expr: ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Str(_) => true,
+        ast::Expr::Parenthesized(expr) => may_be_str(expr.expr()),
+        ast::Expr::Binary(binary) => may_be_str(binary.lhs()) || may_be_str(binary.rhs()),
+        _ => false,
+    }
 }
 
 fn push_result_ty(
@@ -437,6 +590,36 @@ mod tests {
         });
     }
 
+    fn hover_of(source: &str) -> String {
+        run_with_sources(source, |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let source = ctx.source_by_path(&path).unwrap();
+                let graph = compile_doc_for_test(ctx, &HashMap::new());
+                let request = HoverRequest {
+                    path: path.clone(),
+                    position: find_test_position(&source),
+                };
+                HoverDisplay(request.request(ctx, graph).as_ref()).to_string()
+            })
+        })
+    }
+
+    #[test]
+    fn const_value() {
+        let hover = hover_of("#let c = rgb(0, 0, 0)\n#(/* ident after */ c)");
+        assert!(hover.contains("let c: color = "), "{hover}");
+
+        // The repetition of a string is not evaluated.
+        let hover = hover_of("#let s = \"ab\" * 3\n#(/* ident after */ s)");
+        assert!(!hover.contains("ababab"), "{hover}");
+
+        // The function shadowing a builtin is not called.
+        let hover = hover_of(
+            "#let rgb(..args) = \"shadowed\"\n#let c = rgb(0, 0, 0)\n#(/* ident after */ c)",
+        );
+        assert!(!hover.contains("let c: color"), "{hover}");
+    }
+
     struct HoverDisplay<'a>(Option<&'a Hover>);
 
     impl fmt::Display for HoverDisplay<'_> {
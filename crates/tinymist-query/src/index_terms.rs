@@ -0,0 +1,481 @@
+//! Collects the terms marked for a back-of-book index, sorts them by the
+//! conventions of a locale, and checks the marks for mistakes.
+
+use std::cmp::Ordering;
+
+use ecow::eco_format;
+use serde::{Deserialize, Serialize};
+use typst::diag::SourceDiagnostic;
+use typst::syntax::Span;
+
+use crate::prelude::*;
+
+/// Tinymist's index features.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IndexFeat {
+    /// The names of the functions marking the index terms, e.g. `index` in
+    /// `#index[Typst]`. The positional string or content arguments of a mark
+    /// are the term and its subterms.
+    pub markers: Vec<EcoString>,
+    /// The names of the functions displaying the index, e.g. `make-index`.
+    pub displays: Vec<EcoString>,
+    /// The locale to sort the terms by, e.g. `de` or `sv-SE`.
+    pub locale: Option<String>,
+}
+
+impl Default for IndexFeat {
+    fn default() -> Self {
+        Self {
+            markers: vec!["index".into()],
+            displays: vec!["make-index".into()],
+            locale: None,
+        }
+    }
+}
+
+/// A term of the index, which may be a subterm of another term.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexTerm {
+    /// The term along with its parent terms, starting from the main term.
+    pub path: Vec<EcoString>,
+    /// The marks of the term in the workspace.
+    pub locations: Vec<LspLocation>,
+}
+
+/// The terms of the index starting with the same letter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexGroup {
+    /// The letter of the group, or `#` for the terms not starting with a
+    /// letter.
+    pub initial: String,
+    /// The terms of the group, in the sorted order.
+    pub terms: Vec<IndexTerm>,
+}
+
+/// The response to an [`IndexTermsRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexTermsResponse {
+    /// The locale by which the terms are sorted.
+    pub locale: Option<String>,
+    /// The groups of the terms, in the sorted order.
+    pub groups: Vec<IndexGroup>,
+}
+
+/// A request to collect the index terms marked in the workspace, grouped by
+/// their initials and sorted by the conventions of a locale.
+///
+/// This is not part of the LSP protocol.
+#[derive(Debug, Clone)]
+pub struct IndexTermsRequest {
+    /// The locale to sort the terms by, overriding the configured one.
+    pub locale: Option<String>,
+}
+
+impl SemanticRequest for IndexTermsRequest {
+    type Response = IndexTermsResponse;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let feat = ctx.analysis.index.clone();
+        let locale = self.locale.or(feat.locale);
+
+        let mut terms = HashMap::<Vec<EcoString>, Vec<LspLocation>>::new();
+        for fid in ctx.source_files().clone() {
+            // Packages are not part of the workspace.
+            if fid.package().is_some() {
+                continue;
+            }
+            let Ok(source) = ctx.source_by_id(fid) else {
+                continue;
+            };
+            let Ok(uri) = ctx.uri_for_id(fid) else {
+                continue;
+            };
+
+            for mark in index_marks(&source, &feat.markers) {
+                let range = ctx.to_lsp_range(mark.range, &source);
+                let location = LspLocation {
+                    uri: uri.clone(),
+                    range,
+                };
+                terms.entry(mark.path).or_default().push(location);
+            }
+        }
+
+        let mut terms = terms
+            .into_iter()
+            .map(|(path, locations)| IndexTerm { path, locations })
+            .collect::<Vec<_>>();
+        terms.sort_by(|a, b| compare_paths(&a.path, &b.path, locale.as_deref()));
+
+        let mut groups: Vec<IndexGroup> = vec![];
+        for term in terms {
+            let initial = initial(&term.path[0], locale.as_deref());
+            match groups.last_mut() {
+                Some(group) if group.initial == initial => group.terms.push(term),
+                _ => groups.push(IndexGroup {
+                    initial,
+                    terms: vec![term],
+                }),
+            }
+        }
+
+        Some(IndexTermsResponse { locale, groups })
+    }
+}
+
+/// A mark of an index term in a source file.
+#[derive(Debug, Clone)]
+pub(crate) struct IndexMark {
+    /// The term along with its parent terms.
+    pub path: Vec<EcoString>,
+    /// The span of the mark.
+    pub span: Span,
+    /// The range of the mark in the source.
+    pub range: Range<usize>,
+}
+
+/// Collects the marks of the index terms in a source file.
+///
+/// The marks whose terms are not literal strings or content, e.g. variables,
+/// are skipped.
+pub(crate) fn index_marks(source: &Source, markers: &[EcoString]) -> Vec<IndexMark> {
+    let mut marks = vec![];
+    if !markers.is_empty() {
+        collect_marks(&LinkedNode::new(source.root()), markers, &mut marks);
+    }
+    marks
+}
+
+fn collect_marks(node: &LinkedNode, markers: &[EcoString], marks: &mut Vec<IndexMark>) {
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        if callee_name(call).is_some_and(|name| markers.contains(name)) {
+            if let Some(path) = term_path(call) {
+                marks.push(IndexMark {
+                    path,
+                    span: node.span(),
+                    range: node.range(),
+                });
+            }
+        }
+    }
+
+    for child in node.children() {
+        collect_marks(&child, markers, marks);
+    }
+}
+
+/// Checks whether a node or its descendants call any of the functions.
+fn calls_any(node: &LinkedNode, names: &[EcoString]) -> bool {
+    let called = node
+        .cast::<ast::FuncCall>()
+        .and_then(callee_name)
+        .is_some_and(|name| names.contains(name));
+    called || node.children().any(|child| calls_any(&child, names))
+}
+
+/// Gets the name of the called function, e.g. `index` in `index[..]` and
+/// `in-dexter.index[..]`.
+fn callee_name<'a>(call: ast::FuncCall<'a>) -> Option<&'a EcoString> {
+    match call.callee() {
+        ast::Expr::Ident(ident) => Some(ident.get()),
+        ast::Expr::FieldAccess(access) => Some(access.field().get()),
+        _ => None,
+    }
+}
+
+/// Gets the term and its parent terms from the positional arguments of a
+/// mark.
+fn term_path(call: ast::FuncCall) -> Option<Vec<EcoString>> {
+    let mut path = vec![];
+    for arg in call.args().items() {
+        let ast::Arg::Pos(expr) = arg else {
+            continue;
+        };
+        let term = match expr {
+            ast::Expr::Str(s) => s.get(),
+            ast::Expr::Content(content) => content.body().to_untyped().clone().into_text(),
+            _ => return None,
+        };
+        let term = term.split_whitespace().collect::<Vec<_>>().join(" ");
+        if term.is_empty() {
+            return None;
+        }
+        path.push(term.into());
+    }
+
+    (!path.is_empty()).then_some(path)
+}
+
+/// Checks the marks of the index terms in the source files, reporting
+///
+/// - the terms spelled differently but sorted as the same term, e.g. `Typst`
+///   and `typst`,
+/// - the terms marked more than once in a paragraph, and
+/// - the marks when no function displays the index.
+pub(crate) fn check_index_terms(sources: &[Source], feat: &IndexFeat) -> EcoVec<SourceDiagnostic> {
+    let mut diags = EcoVec::new();
+    let locale = feat.locale.as_deref();
+
+    let mut marks = vec![];
+    for source in sources {
+        let mut file_marks = index_marks(source, &feat.markers);
+
+        // The marks are in the order of the source.
+        let text = source.text();
+        for (idx, mark) in file_marks.iter().enumerate() {
+            let prev = file_marks[..idx]
+                .iter()
+                .rev()
+                .find(|prev| prev.path == mark.path);
+            if let Some(prev) = prev {
+                let between = text
+                    .get(prev.range.end..mark.range.start)
+                    .unwrap_or_default();
+                if !has_parbreak(between) {
+                    let term = mark.path.iter().join(", ");
+                    let msg = eco_format!("index term `{term}` is marked twice in a paragraph");
+                    let hint = "the index lists the page once, so the second mark can be removed";
+                    diags.push(SourceDiagnostic::warning(mark.span, msg).with_hint(hint));
+                }
+            }
+        }
+        marks.append(&mut file_marks);
+    }
+
+    // The first spelling of a term is the expected one.
+    let mut spellings = HashMap::<Vec<String>, &Vec<EcoString>>::new();
+    for mark in &marks {
+        let key = mark
+            .path
+            .iter()
+            .map(|term| sort_key(term, locale))
+            .collect();
+        let spelling = *spellings.entry(key).or_insert(&mark.path);
+        if spelling != &mark.path {
+            let (term, expected) = (mark.path.iter().join(", "), spelling.iter().join(", "));
+            let msg = eco_format!("index term `{term}` duplicates `{expected}`");
+            let hint = "the terms are sorted together but listed separately, use one spelling";
+            diags.push(SourceDiagnostic::warning(mark.span, msg).with_hint(hint));
+        }
+    }
+
+    let displayed = sources
+        .iter()
+        .any(|source| calls_any(&LinkedNode::new(source.root()), &feat.displays));
+    if let Some(first) = marks
+        .first()
+        .filter(|_| !displayed && !feat.displays.is_empty())
+    {
+        let displays = feat
+            .displays
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .join(", ");
+        let msg = "index terms are marked but the index is not displayed";
+        let hint = eco_format!("call {displays} to display the index");
+        diags.push(SourceDiagnostic::warning(first.span, msg).with_hint(hint));
+    }
+
+    diags
+}
+
+/// Checks whether a blank line separates the text.
+fn has_parbreak(text: &str) -> bool {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|line| line.trim().is_empty())
+}
+
+/// Compares the paths of two terms by the conventions of a locale.
+fn compare_paths(a: &[EcoString], b: &[EcoString], locale: Option<&str>) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let ordering = compare_terms(a, b, locale);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Compares two terms by the conventions of a locale, ignoring the case and
+/// the diacritics unless the terms are equal otherwise.
+fn compare_terms(a: &str, b: &str, locale: Option<&str>) -> Ordering {
+    let key = |term: &str| sort_key(term, locale);
+    key(a)
+        .cmp(&key(b))
+        .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        .then_with(|| a.cmp(b))
+}
+
+/// The rank of a letter sorted after a base letter, which is larger than any
+/// other character.
+fn after(base: char, rank: u32) -> [char; 2] {
+    [base, char::from_u32(0xF0000 + rank).unwrap()]
+}
+
+/// Gets the letters sorted as separate letters in a locale, instead of as
+/// variants of their base letters.
+fn tailored(c: char, locale: Option<&str>) -> Option<[char; 2]> {
+    let lang = locale?.split(['-', '_']).next()?.to_ascii_lowercase();
+    match (lang.as_str(), c) {
+        ("sv" | "fi", 'å') => Some(after('z', 1)),
+        ("sv" | "fi", 'ä' | 'æ') => Some(after('z', 2)),
+        ("sv" | "fi", 'ö' | 'ø') => Some(after('z', 3)),
+        ("da" | "nb" | "nn" | "no", 'æ' | 'ä') => Some(after('z', 1)),
+        ("da" | "nb" | "nn" | "no", 'ø' | 'ö') => Some(after('z', 2)),
+        ("da" | "nb" | "nn" | "no", 'å') => Some(after('z', 3)),
+        ("es", 'ñ') => Some(after('n', 1)),
+        _ => None,
+    }
+}
+
+/// Computes the key to sort a term by in a locale, which folds the case and
+/// the diacritics.
+pub(crate) fn sort_key(term: &str, locale: Option<&str>) -> String {
+    let mut key = String::new();
+    for c in term.chars().flat_map(char::to_lowercase) {
+        if let Some(tailored) = tailored(c, locale) {
+            key.extend(tailored);
+        } else if let Some(folded) = fold_diacritic(c) {
+            key.push_str(folded);
+        } else if c.is_alphanumeric() || c == ' ' {
+            key.push(c);
+        }
+    }
+    key
+}
+
+/// Gets the letter of the group of a term.
+fn initial(term: &str, locale: Option<&str>) -> String {
+    let Some(c) = term.chars().find(|c| c.is_alphanumeric()) else {
+        return "#".to_owned();
+    };
+    if !c.is_alphabetic() {
+        return "#".to_owned();
+    }
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    if tailored(lower, locale).is_some() {
+        return c.to_uppercase().collect();
+    }
+    match fold_diacritic(lower).and_then(|folded| folded.chars().next()) {
+        Some(base) => base.to_uppercase().collect(),
+        None => c.to_uppercase().collect(),
+    }
+}
+
+/// Folds a lowercase letter with diacritics to its base letters.
+fn fold_diacritic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ð' | 'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(terms: &[&str], locale: Option<&str>) -> Vec<String> {
+        let mut terms = terms.to_vec();
+        terms.sort_by(|a, b| compare_terms(a, b, locale));
+        terms.into_iter().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn sort_by_locale() {
+        let terms = ["zebra", "Äpfel", "apple", "Öl", "Ofen", "Åre"];
+        assert_eq!(
+            sorted(&terms, None),
+            ["Äpfel", "apple", "Åre", "Ofen", "Öl", "zebra"]
+        );
+        assert_eq!(
+            sorted(&terms, Some("de")),
+            ["Äpfel", "apple", "Åre", "Ofen", "Öl", "zebra"]
+        );
+        assert_eq!(
+            sorted(&terms, Some("sv-SE")),
+            ["apple", "Ofen", "zebra", "Åre", "Äpfel", "Öl"]
+        );
+        assert_eq!(
+            sorted(&["nube", "ñandú", "oso"], Some("es")),
+            ["nube", "ñandú", "oso"]
+        );
+        assert_eq!(initial("Ölfass", Some("sv")), "Ö");
+        assert_eq!(initial("Ölfass", Some("de")), "O");
+        assert_eq!(initial("3D", None), "#");
+    }
+
+    #[test]
+    fn marks() {
+        let source = Source::detached(
+            r#"#let index(..args) = none
+Typst#index[Typst] is a typesetting system#index("Typesetting", "system").
+#index(fmt: strong, "Markup") and #index(name)
+"#,
+        );
+        let marks = index_marks(&source, &["index".into()]);
+        let paths = marks
+            .iter()
+            .map(|mark| mark.path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                vec![EcoString::from("Typst")],
+                vec!["Typesetting".into(), "system".into()],
+                vec!["Markup".into()],
+            ]
+        );
+    }
+
+    #[test]
+    fn check_marks() {
+        let source = Source::detached(
+            r#"Typst#index[Typst] and typst#index[typst].
+
+Again Typst#index[Typst]#index[Typst].
+"#,
+        );
+        let diags = check_index_terms(&[source], &IndexFeat::default());
+        let messages = diags
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "index term `Typst` is marked twice in a paragraph",
+                "index term `typst` duplicates `Typst`",
+                "index terms are marked but the index is not displayed",
+            ]
+        );
+    }
+}
@@ -62,6 +62,12 @@ impl SemanticRequest for InlayHintRequest {
 
     fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
         let source = ctx.source_by_path(&self.path).ok()?;
+        // The hints would clutter the generated or minified sources, and are
+        // expensive to compute on their very long lines.
+        if crate::has_long_lines(&source) {
+            log::debug!("skip inlay hints for long lines in {:?}", self.path);
+            return None;
+        }
         let range = ctx.to_typst_range(self.range, &source)?;
 
         let root = LinkedNode::new(source.root());
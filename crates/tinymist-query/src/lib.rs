@@ -12,37 +12,51 @@ pub use completion::{CompletionRequest, PostfixSnippet};
 pub use typlite::ColorTheme;
 pub use upstream::with_vm;
 
+pub use assets::*;
+pub use change_impact::*;
 pub use check::*;
 pub use code_action::*;
 pub use code_context::*;
 pub use code_lens::*;
 pub use color_presentation::*;
+pub use compile_snippet::*;
+pub use debug_ast::*;
 pub use diagnostics::*;
 pub use document_color::*;
 pub use document_highlight::*;
 pub use document_link::*;
 pub use document_metrics::*;
+pub use document_outline::*;
 pub use document_symbol::*;
+pub use drop_files::*;
 pub use folding_range::*;
+pub use font_fallback::*;
 pub use goto_declaration::*;
 pub use goto_definition::*;
 pub use hover::*;
+pub use index_terms::*;
 pub use inlay_hint::*;
 pub use jump::*;
 pub use lsp_typst_boundary::*;
 pub use on_enter::*;
 pub use prepare_rename::*;
+pub use prose_replace::*;
 pub use references::*;
 pub use rename::*;
 pub use selection_range::*;
 pub use semantic_tokens_delta::*;
 pub use semantic_tokens_full::*;
 pub use signature_help::*;
+pub use smart_paste::*;
+pub use spell_check::*;
 pub use symbol::*;
 pub use will_rename_files::*;
+pub use workspace_edit::*;
 pub use workspace_label::*;
+pub use yaml_bib::yaml_bib_completion;
 
 pub mod analysis;
+pub mod code_index;
 pub mod docs;
 pub mod package;
 pub mod syntax;
@@ -56,36 +70,49 @@ mod adt;
 mod lsp_typst_boundary;
 mod prelude;
 
+mod assets;
 mod bib;
+mod change_impact;
 mod check;
 mod code_action;
 mod code_context;
 mod code_lens;
 mod color_presentation;
+mod compile_snippet;
 mod completion;
+mod debug_ast;
 mod diagnostics;
 mod document_color;
 mod document_highlight;
 mod document_link;
 mod document_metrics;
+mod document_outline;
 mod document_symbol;
+mod drop_files;
 mod folding_range;
+mod font_fallback;
 mod goto_declaration;
 mod goto_definition;
 mod hover;
+mod index_terms;
 mod inlay_hint;
 mod jump;
 mod on_enter;
 mod prepare_rename;
+mod prose_replace;
 mod references;
 mod rename;
 mod selection_range;
 mod semantic_tokens_delta;
 mod semantic_tokens_full;
 mod signature_help;
+mod smart_paste;
+mod spell_check;
 mod symbol;
 mod will_rename_files;
+mod workspace_edit;
 mod workspace_label;
+mod yaml_bib;
 
 use typst::syntax::Source;
 
@@ -205,7 +232,19 @@ mod polymorphic {
         OnEnter(OnEnterRequest),
 
         DocumentMetrics(DocumentMetricsRequest),
+        DocumentOutline(DocumentOutlineRequest),
+        DebugAst(DebugAstRequest),
+        FontFallback(FontFallbackRequest),
         WorkspaceLabel(WorkspaceLabelRequest),
+        ProseReplace(ProseReplaceRequest),
+        IndexTerms(IndexTermsRequest),
+        ChangeImpact(ChangeImpactRequest),
+        DropFiles(DropFilesRequest),
+        SmartPaste(SmartPasteRequest),
+        CompileSnippet(CompileSnippetRequest),
+        ExportSelection(ExportSelectionRequest),
+        ProjectAssets(ProjectAssetsRequest),
+        MoveAsset(MoveAssetRequest),
         ServerInfo(ServerInfoRequest),
     }
 
@@ -230,7 +269,7 @@ mod polymorphic {
                 Self::Rename(..) => Mergeable,
                 Self::WillRenameFiles(..) => Mergeable,
                 Self::PrepareRename(..) => Mergeable,
-                Self::DocumentSymbol(..) => ContextFreeUnique,
+                Self::DocumentSymbol(..) => PinnedFirst,
                 Self::WorkspaceLabel(..) => Mergeable,
                 Self::Symbol(..) => Mergeable,
                 Self::SemanticTokensFull(..) => PinnedFirst,
@@ -243,6 +282,18 @@ mod polymorphic {
                 Self::OnEnter(..) => ContextFreeUnique,
 
                 Self::DocumentMetrics(..) => PinnedFirst,
+                Self::DocumentOutline(..) => PinnedFirst,
+                Self::DebugAst(..) => PinnedFirst,
+                Self::FontFallback(..) => PinnedFirst,
+                Self::ProseReplace(..) => Mergeable,
+                Self::IndexTerms(..) => Mergeable,
+                Self::ChangeImpact(..) => PinnedFirst,
+                Self::DropFiles(..) => PinnedFirst,
+                Self::SmartPaste(..) => PinnedFirst,
+                Self::CompileSnippet(..) => PinnedFirst,
+                Self::ExportSelection(..) => PinnedFirst,
+                Self::ProjectAssets(..) => Mergeable,
+                Self::MoveAsset(..) => Mergeable,
                 Self::ServerInfo(..) => Mergeable,
             }
         }
@@ -279,6 +330,18 @@ mod polymorphic {
                 Self::OnEnter(req) => &req.path,
 
                 Self::DocumentMetrics(req) => &req.path,
+                Self::DocumentOutline(req) => &req.path,
+                Self::DebugAst(req) => &req.path,
+                Self::FontFallback(req) => &req.path,
+                Self::ProseReplace(..) => return None,
+                Self::IndexTerms(..) => return None,
+                Self::ChangeImpact(req) => &req.path,
+                Self::DropFiles(req) => &req.path,
+                Self::SmartPaste(req) => &req.path,
+                Self::CompileSnippet(req) => &req.path,
+                Self::ExportSelection(req) => &req.path,
+                Self::ProjectAssets(..) => return None,
+                Self::MoveAsset(..) => return None,
                 Self::ServerInfo(..) => return None,
             })
         }
@@ -317,6 +380,18 @@ mod polymorphic {
         OnEnter(Option<Vec<TextEdit>>),
 
         DocumentMetrics(Option<DocumentMetricsResponse>),
+        DocumentOutline(Option<Vec<DocumentOutlineItem>>),
+        DebugAst(Option<DebugAstResponse>),
+        FontFallback(Option<FontFallbackResponse>),
+        ProseReplace(Option<WorkspaceEdit>),
+        IndexTerms(Option<IndexTermsResponse>),
+        ChangeImpact(Option<ChangeImpactResponse>),
+        DropFiles(Option<EcoSnippetTextEdit>),
+        SmartPaste(Option<String>),
+        CompileSnippet(Option<CompileSnippetResponse>),
+        ExportSelection(Option<ExportSelectionResponse>),
+        ProjectAssets(Option<ProjectAssetsResponse>),
+        MoveAsset(Option<WorkspaceEdit>),
         ServerInfo(Option<HashMap<String, ServerInfoResponse>>),
     }
 }
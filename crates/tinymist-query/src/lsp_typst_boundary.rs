@@ -1,5 +1,6 @@
 //! Conversions between Typst and LSP types and representations
 
+use parking_lot::RwLock;
 use tinymist_std::path::PathClean;
 use tinymist_world::vfs::PathResolution;
 
@@ -14,6 +15,20 @@ pub use tinymist_analysis::location::*;
 
 const UNTITLED_ROOT: &str = "/untitled";
 static EMPTY_URL: LazyLock<Url> = LazyLock::new(|| Url::parse("file://").unwrap());
+/// The urls of the virtual files that are not representable by `file:` or
+/// `untitled:` urls, e.g. the cells of a notebook.
+static VIRTUAL_URLS: LazyLock<RwLock<HashMap<PathBuf, Url>>> = LazyLock::new(RwLock::default);
+
+/// Registers the url of a virtual file, which is mapped from and to its path
+/// by [`url_to_path`] and [`path_to_url`].
+pub fn register_virtual_url(path: PathBuf, url: Url) {
+    VIRTUAL_URLS.write().insert(path, url);
+}
+
+/// Unregisters the url of a virtual file.
+pub fn unregister_virtual_url(path: &Path) {
+    VIRTUAL_URLS.write().remove(path);
+}
 
 /// Convert a path to a URL.
 pub fn untitled_url(path: &Path) -> anyhow::Result<Url> {
@@ -22,6 +37,10 @@ pub fn untitled_url(path: &Path) -> anyhow::Result<Url> {
 
 /// Convert a path to a URL.
 pub fn path_to_url(path: &Path) -> anyhow::Result<Url> {
+    if let Some(url) = VIRTUAL_URLS.read().get(path) {
+        return Ok(url.clone());
+    }
+
     if let Ok(untitled) = path.strip_prefix(UNTITLED_ROOT) {
         // rust-url will panic on converting an empty path.
         if untitled == Path::new("nEoViM-BuG") {
@@ -48,6 +67,13 @@ pub fn path_res_to_url(path: PathResolution) -> anyhow::Result<Url> {
 
 /// Convert a URL to a path.
 pub fn url_to_path(uri: Url) -> PathBuf {
+    if !matches!(uri.scheme(), "file" | "untitled") {
+        let urls = VIRTUAL_URLS.read();
+        if let Some((path, _)) = urls.iter().find(|(_, url)| **url == uri) {
+            return path.clone();
+        }
+    }
+
     if uri.scheme() == "file" {
         // typst converts an empty path to `Path::new("/")`, which is undesirable.
         if !uri.has_host() && uri.path() == "/" {
@@ -101,4 +127,16 @@ mod test {
         let uri2 = path_to_url(&path).unwrap();
         assert_eq!(EMPTY_URL.clone(), uri2);
     }
+
+    #[test]
+    fn virtual_url() {
+        let uri = Url::parse("vscode-notebook-cell:/nb/a.ipynb#W0sZmlsZQ%3D%3D").unwrap();
+        let path = PathBuf::from("/nb/.a.ipynb.cell-0.typ");
+        register_virtual_url(path.clone(), uri.clone());
+        assert_eq!(url_to_path(uri.clone()), path);
+        assert_eq!(path_to_url(&path).unwrap(), uri);
+
+        unregister_virtual_url(&path);
+        assert_ne!(path_to_url(&path).unwrap(), uri);
+    }
 }
@@ -8,13 +8,23 @@ use ecow::{eco_format, eco_vec, EcoVec};
 use parking_lot::Mutex;
 // use reflexo_typst::typst::prelude::*;
 use serde::{Deserialize, Serialize};
+use tinymist_std::path::unix_slash;
+use tinymist_std::typst::TypstPagedDocument;
 use tinymist_world::package::registry::HttpRegistry;
 use tinymist_world::package::PackageSpec;
+use tinymist_world::with_main_source;
 use typst::diag::{EcoString, StrResult};
+use typst::foundations::{Content, Type, Value};
 use typst::syntax::package::PackageManifest;
-use typst::syntax::{FileId, VirtualPath};
+use typst::syntax::{ast, FileId, Source, VirtualPath};
 use typst::World;
 
+use crate::adt::interner::Interned;
+use crate::analysis::SharedContext;
+use crate::docs::{DefDocs, DefInfo};
+use crate::syntax::{import_paths, Decl};
+use crate::testing::DocExample;
+use crate::ty::{BuiltinTy, Ty};
 use crate::LocalContext;
 
 /// Information about a package.
@@ -66,15 +76,371 @@ pub fn get_manifest(world: &dyn World, toml_id: FileId) -> StrResult<PackageMani
         .map_err(|err| eco_format!("package manifest is malformed ({})", err.message()))
 }
 
-/// Check Package.
-pub fn check_package(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<()> {
+/// The severity of a [`PackageIssue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PackageIssueSeverity {
+    /// The package is broken, e.g. it cannot be bundled or an example fails.
+    Error,
+    /// The package works, but its documentation is likely to be wrong.
+    Warning,
+}
+
+/// An issue found in a package by [`check_package`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageIssue {
+    /// The kind of the issue, i.e. `manifest`, `signature` or `example`.
+    pub kind: EcoString,
+    /// The severity of the issue.
+    pub severity: PackageIssueSeverity,
+    /// The message of the issue.
+    pub message: EcoString,
+    /// The path of the file, relative to the root of the package.
+    pub path: Option<EcoString>,
+    /// The 1-based line in the file.
+    pub line: Option<usize>,
+}
+
+impl PackageIssue {
+    fn error(kind: &str, message: EcoString) -> Self {
+        Self {
+            kind: kind.into(),
+            severity: PackageIssueSeverity::Error,
+            message,
+            path: None,
+            line: None,
+        }
+    }
+
+    fn warning(kind: &str, message: EcoString) -> Self {
+        Self {
+            severity: PackageIssueSeverity::Warning,
+            ..Self::error(kind, message)
+        }
+    }
+
+    fn at(mut self, fid: FileId, line: Option<usize>) -> Self {
+        self.path = Some(unix_slash(fid.vpath().as_rootless_path()).into());
+        self.line = line;
+        self
+    }
+}
+
+/// Checks a package, i.e. validates the manifest, checks the signatures of
+/// the exported functions against their docstrings, and compiles the
+/// examples in the docstrings.
+pub fn check_package(ctx: &mut LocalContext, spec: &PackageInfo) -> StrResult<Vec<PackageIssue>> {
     let toml_id = get_manifest_id(spec)?;
     let manifest = ctx.get_manifest(toml_id)?;
 
     let entry_point = toml_id.join(&manifest.package.entrypoint);
 
     ctx.shared_().preload_package(entry_point);
-    Ok(())
+
+    let mut issues = vec![];
+    let files = check_manifest(spec, &manifest, &mut issues);
+    if issues
+        .iter()
+        .any(|it| it.severity == PackageIssueSeverity::Error)
+    {
+        // The exports cannot be checked without a valid entrypoint.
+        return Ok(issues);
+    }
+
+    match crate::docs::module_docs(ctx, entry_point) {
+        Ok(docs) => {
+            let mut checked = HashSet::new();
+            check_signatures(ctx, &docs.root, &mut checked, &mut issues);
+        }
+        Err(err) => issues.push(
+            PackageIssue::error(
+                "signature",
+                eco_format!("failed to scan the exports: {err}"),
+            )
+            .at(entry_point, None),
+        ),
+    }
+
+    for path in files {
+        let fid = toml_id.join(&path);
+        let Ok(source) = ctx.source_by_id(fid) else {
+            continue;
+        };
+        for example in crate::testing::doc_examples(&source) {
+            check_example(ctx, &example, &mut issues);
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Validates the name, the version, the entrypoint and the excluded files of
+/// the manifest, and returns the source files to bundle.
+fn check_manifest(
+    spec: &PackageInfo,
+    manifest: &PackageManifest,
+    issues: &mut Vec<PackageIssue>,
+) -> Vec<String> {
+    let info = &manifest.package;
+    let mut push = |issue: PackageIssue| {
+        issues.push(PackageIssue {
+            path: Some("typst.toml".into()),
+            ..issue
+        })
+    };
+
+    if info.name != spec.name {
+        push(PackageIssue::error(
+            "manifest",
+            eco_format!(
+                "the name `{}` doesn't match the package `{}`",
+                info.name,
+                spec.name
+            ),
+        ));
+    }
+    if info.version.to_string() != spec.version {
+        push(PackageIssue::error(
+            "manifest",
+            eco_format!(
+                "the version `{}` doesn't match the package version `{}`",
+                info.version,
+                spec.version
+            ),
+        ));
+    }
+
+    let mut patterns = vec![];
+    let mut globs = globset::GlobSetBuilder::new();
+    for pattern in &info.exclude {
+        let glob = pattern.trim_start_matches('/').trim_end_matches('/');
+        match globset::Glob::new(glob) {
+            Ok(glob) => {
+                globs.add(glob);
+                patterns.push(pattern);
+            }
+            Err(err) => push(PackageIssue::error(
+                "manifest",
+                eco_format!("the exclude pattern `{pattern}` is invalid: {err}"),
+            )),
+        }
+    }
+    let globs = globs.build().unwrap_or_default();
+
+    // A file is excluded if the file or any of its parent directories match.
+    let mut used = HashSet::new();
+    let mut is_excluded = |path: &str| {
+        let mut excluded = false;
+        let mut path = Some(std::path::Path::new(path));
+        while let Some(p) = path.filter(|p| !p.as_os_str().is_empty()) {
+            let matches = globs.matches(p);
+            excluded |= !matches.is_empty();
+            used.extend(matches);
+            path = p.parent();
+        }
+        excluded
+    };
+
+    let mut files = vec![];
+    let mut has_entrypoint = false;
+    let walker = walkdir::WalkDir::new(&spec.path)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        });
+    for entry in walker.flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(path) = entry.path().strip_prefix(&spec.path) else {
+            continue;
+        };
+        let path = unix_slash(path);
+        if path == info.entrypoint.as_str() {
+            has_entrypoint = true;
+        }
+        if is_excluded(&path) {
+            if path == info.entrypoint.as_str() {
+                push(PackageIssue::error(
+                    "manifest",
+                    eco_format!("the entrypoint `{}` is excluded", info.entrypoint),
+                ));
+            }
+            continue;
+        }
+        if path.ends_with(".typ") {
+            files.push(path);
+        }
+    }
+
+    if !has_entrypoint {
+        push(PackageIssue::error(
+            "manifest",
+            eco_format!("the entrypoint `{}` doesn't exist", info.entrypoint),
+        ));
+    }
+    for (idx, pattern) in patterns.iter().enumerate() {
+        if !used.contains(&idx) {
+            push(PackageIssue::warning(
+                "manifest",
+                eco_format!("the exclude pattern `{pattern}` matches no file"),
+            ));
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Checks the parameters documented in the docstrings of the exported
+/// functions against their signatures.
+fn check_signatures(
+    ctx: &mut LocalContext,
+    def: &DefInfo,
+    checked: &mut HashSet<Interned<Decl>>,
+    issues: &mut Vec<PackageIssue>,
+) {
+    for child in &def.children {
+        check_signatures(ctx, child, checked, issues);
+    }
+
+    let (Some(decl), Some(DefDocs::Function(sig))) = (&def.decl, &def.parsed_docs) else {
+        return;
+    };
+    if def.is_external || !checked.insert(decl.clone()) {
+        return;
+    }
+    let Some(fid) = decl.file_id() else {
+        return;
+    };
+    let Some(docstring) = ctx
+        .expr_stage_by_id(fid)
+        .and_then(|ei| ei.docstrings.get(decl).cloned())
+    else {
+        return;
+    };
+    let line = ctx.source_by_id(fid).ok().and_then(|source| {
+        let range = source.range(decl.span())?;
+        Some(source.byte_to_line(range.start)? + 1)
+    });
+
+    let name = &def.name;
+    let mut push = |issue: PackageIssue| issues.push(issue.at(fid, line));
+
+    let params = sig.pos.iter().chain(sig.named.values());
+    let params = params.chain(sig.rest.as_ref()).collect::<Vec<_>>();
+    for documented in docstring.vars.keys() {
+        let documented = documented.trim_start_matches("..");
+        if !params.iter().any(|param| param.name.as_ref() == documented) {
+            push(PackageIssue::error(
+                "signature",
+                eco_format!("`{name}` documents the parameter `{documented}`, which is not in its signature"),
+            ));
+        }
+    }
+
+    // Only the functions documenting their parameters are expected to
+    // document all of them.
+    if docstring.vars.is_empty() {
+        return;
+    }
+    for param in params {
+        if param.name.as_ref() == "_" {
+            continue;
+        }
+        let var = docstring
+            .vars
+            .iter()
+            .find(|(documented, _)| documented.trim_start_matches("..") == param.name.as_ref());
+        let Some((_, var)) = var else {
+            push(PackageIssue::warning(
+                "signature",
+                eco_format!("`{name}` doesn't document the parameter `{}`", param.name),
+            ));
+            continue;
+        };
+
+        let (Some(annotated), Some(default)) = (&var.ty, &param.default) else {
+            continue;
+        };
+        if default_matches(annotated, default) == Some(false) {
+            push(PackageIssue::warning(
+                "signature",
+                eco_format!(
+                    "the default value `{default}` of the parameter `{}` of `{name}` doesn't match its documented type",
+                    param.name
+                ),
+            ));
+        }
+    }
+}
+
+/// Checks whether the default value of a parameter matches the annotated
+/// type, or returns `None` if the value or the type cannot be checked.
+fn default_matches(annotated: &Ty, default: &str) -> Option<bool> {
+    let code = typst::syntax::parse_code(default);
+    let expr = code.cast::<ast::Code>()?.exprs().next()?;
+    let value = SharedContext::const_eval(expr)?;
+
+    let terms = match annotated {
+        Ty::Union(types) => types.as_slice(),
+        ty => std::slice::from_ref(ty),
+    };
+    let mut matched = false;
+    for term in terms {
+        matched |= match term {
+            Ty::Any => true,
+            Ty::Boolean(expected) => {
+                matches!(value, Value::Bool(v) if expected.is_none_or(|expected| expected == v))
+            }
+            Ty::Value(ins) => ins.val == value,
+            Ty::Builtin(BuiltinTy::Type(ty)) => {
+                // The values casted implicitly are accepted as well.
+                let is = |v: Value| *ty == v.ty();
+                *ty == value.ty()
+                    || (is(Value::Float(0.0)) && matches!(value, Value::Int(_)))
+                    || (is(Value::Relative(Default::default()))
+                        && matches!(value, Value::Length(_) | Value::Ratio(_)))
+                    || (*ty == Type::of::<Content>()
+                        && matches!(value, Value::Str(_) | Value::Symbol(_)))
+            }
+            _ => return None,
+        };
+    }
+    Some(matched)
+}
+
+/// Compiles an example in a docstring, which imports the file containing the
+/// docstring.
+fn check_example(ctx: &mut LocalContext, example: &DocExample, issues: &mut Vec<PackageIssue>) {
+    let fid = example.location;
+    let file_name = fid.vpath().as_rooted_path().file_name();
+    let file_name = file_name.and_then(|s| s.to_str()).unwrap_or_default();
+    let id = fid.join(&format!("{file_name}.doc-example-{}.typ", example.line + 1));
+    let text = format!("#import \"{file_name}\": *\n{}", example.code);
+    let source = Source::new(id, text);
+
+    let world = with_main_source(&ctx.world, source.clone());
+    let Err(errors) = typst::compile::<TypstPagedDocument>(&world).output else {
+        return;
+    };
+    for error in errors {
+        // The first line of the source is the import.
+        let line = source
+            .range(error.span)
+            .and_then(|range| source.byte_to_line(range.start))
+            .filter(|line| *line > 0)
+            .map_or(example.line + 1, |line| example.line + line + 1);
+        issues.push(
+            PackageIssue::error(
+                "example",
+                eco_format!("the example fails to compile: {}", error.message),
+            )
+            .at(fid, Some(line)),
+        );
+    }
 }
 
 /// Get the packages in namespaces and their descriptions.
@@ -149,6 +515,23 @@ pub fn list_package_by_namespace(
     packages
 }
 
+/// Collects the packages imported or included by the source, without
+/// duplicates.
+pub fn imported_packages(source: &Source) -> Vec<PackageSpec> {
+    let mut paths = vec![];
+    import_paths(source.root(), &mut paths);
+
+    let mut specs = vec![];
+    for path in paths.iter().filter(|path| path.starts_with('@')) {
+        if let Ok(spec) = path.parse::<PackageSpec>() {
+            if !specs.contains(&spec) {
+                specs.push(spec);
+            }
+        }
+    }
+    specs
+}
+
 trait IsDirFollowLinks {
     fn is_dir_follow_links(&self) -> bool;
 }
@@ -177,3 +560,59 @@ fn once_log<T, E: std::fmt::Display>(result: Result<T, E>, site: &'static str) -
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_value() {
+        let ty = |v: Value| Ty::Builtin(BuiltinTy::Type(v.ty()));
+        let float = ty(Value::Float(0.0));
+        assert_eq!(default_matches(&float, "1.5"), Some(true));
+        assert_eq!(default_matches(&float, "1"), Some(true));
+        assert_eq!(default_matches(&float, "\"a\""), Some(false));
+
+        let content_or_none = Ty::from_types(
+            [
+                Ty::Builtin(BuiltinTy::Type(Type::of::<Content>())),
+                ty(Value::None),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(default_matches(&content_or_none, "none"), Some(true));
+        assert_eq!(default_matches(&content_or_none, "\"a\""), Some(true));
+        assert_eq!(default_matches(&content_or_none, "auto"), Some(false));
+
+        assert_eq!(default_matches(&Ty::Boolean(None), "false"), Some(true));
+        assert_eq!(default_matches(&float, "x + 1"), None);
+    }
+
+    #[test]
+    fn imported_packages() {
+        let source = Source::detached(
+            r#"#import "@preview/cetz:0.3.4": canvas
+#import "@preview/cetz:0.3.4" as cetz
+#include "chapter.typ"
+#import "utils.typ": *
+#import "@preview/not-a-spec"
+#let f() = {
+  import "@local/mine:0.1.0"
+}
+#include "@preview/tablex:0.0.9"
+"#,
+        );
+        let specs = super::imported_packages(&source)
+            .into_iter()
+            .map(|spec| spec.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            specs,
+            [
+                "@preview/cetz:0.3.4",
+                "@local/mine:0.1.0",
+                "@preview/tablex:0.0.9"
+            ]
+        );
+    }
+}
@@ -28,7 +28,7 @@ pub use typst::syntax::{
 pub use typst::World;
 pub use typst_shim::syntax::LinkedNodeExt;
 
-pub use crate::analysis::{Definition, LocalContext};
+pub use crate::analysis::{DefLocation, Definition, LocalContext};
 pub use crate::code_action::proto::*;
 pub use crate::docs::DefDocs;
 pub use crate::lsp_typst_boundary::{
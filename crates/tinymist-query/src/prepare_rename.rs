@@ -47,7 +47,7 @@ impl StatefulRequest for PrepareRenameRequest {
         let origin_selection_range = ctx.to_lsp_range(syntax.node().range(), &source);
         let def = ctx.def_of_syntax(&source, doc, syntax.clone())?;
 
-        let (name, range) = prepare_renaming(&syntax, &def)?;
+        let (name, range) = prepare_renaming(ctx, &syntax, &def)?;
 
         Some(PrepareRenameResponse::RangeWithPlaceholder {
             range: range.unwrap_or(origin_selection_range),
@@ -57,11 +57,12 @@ impl StatefulRequest for PrepareRenameRequest {
 }
 
 pub(crate) fn prepare_renaming(
+    ctx: &LocalContext,
     deref_target: &SyntaxClass,
     def: &Definition,
 ) -> Option<(String, Option<LspRange>)> {
     let name = def.name().clone();
-    let def_fid = def.file_id()?;
+    let def_fid = def.location(ctx.shared())?.fid;
 
     if WorkspaceResolver::is_package_file(def_fid) {
         crate::log_debug_ct!(
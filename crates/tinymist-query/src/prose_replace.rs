@@ -0,0 +1,193 @@
+use lsp_types::{
+    AnnotatedTextEdit, ChangeAnnotation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, TextDocumentEdit,
+};
+use regex::{Regex, RegexBuilder};
+
+use crate::{prelude::*, SemanticRequest};
+
+/// The annotation attached to the edits, which asks the client to show them
+/// for confirmation before applying.
+const PROSE_REPLACE_ANNOTATION: &str = "tinymist.proseReplace";
+
+/// The `workspace/proseReplace` request replaces text in the prose regions of
+/// all the files in the workspace.
+///
+/// Code, strings, labels, raw blocks and equations are skipped according to
+/// the syntax tree, unlike a regex replacement in the editor. The edits are
+/// annotated to be confirmed so that the client can preview them.
+#[derive(Debug, Clone)]
+pub struct ProseReplaceRequest {
+    /// The text or regular expression to search for.
+    pub pattern: String,
+    /// The replacement. Capture groups such as `$1` are expanded if the
+    /// pattern is a regular expression.
+    pub replacement: String,
+    /// Whether the pattern is a regular expression.
+    pub is_regex: bool,
+    /// Whether the search is case sensitive.
+    pub match_case: bool,
+}
+
+impl SemanticRequest for ProseReplaceRequest {
+    type Response = WorkspaceEdit;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        let re = RegexBuilder::new(&pattern)
+            .case_insensitive(!self.match_case)
+            .build()
+            .ok()?;
+
+        let mut document_changes = vec![];
+        for fid in ctx.source_files().clone() {
+            // Packages are not part of the workspace.
+            if fid.package().is_some() {
+                continue;
+            }
+            let Ok(source) = ctx.source_by_id(fid) else {
+                continue;
+            };
+            let Ok(uri) = ctx.uri_for_id(fid) else {
+                continue;
+            };
+
+            let edits = prose_replacements(&source, &re, &self.replacement, self.is_regex);
+            if edits.is_empty() {
+                continue;
+            }
+
+            let edits = edits
+                .into_iter()
+                .map(|(range, new_text)| {
+                    OneOf::Right(AnnotatedTextEdit {
+                        text_edit: TextEdit {
+                            range: ctx.to_lsp_range(range, &source),
+                            new_text,
+                        },
+                        annotation_id: PROSE_REPLACE_ANNOTATION.to_owned(),
+                    })
+                })
+                .collect();
+            document_changes.push(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits,
+            });
+        }
+
+        let annotation = ChangeAnnotation {
+            label: format!("Replace {:?} with {:?}", self.pattern, self.replacement),
+            needs_confirmation: Some(true),
+            description: Some("Replacement in prose".to_owned()),
+        };
+        Some(WorkspaceEdit {
+            changes: None,
+            document_changes: Some(DocumentChanges::Edits(document_changes)),
+            change_annotations: Some(HashMap::from_iter([(
+                PROSE_REPLACE_ANNOTATION.to_owned(),
+                annotation,
+            )])),
+        })
+    }
+}
+
+/// Finds the matches in the prose regions of a source and computes their
+/// replacements.
+fn prose_replacements(
+    source: &Source,
+    re: &Regex,
+    replacement: &str,
+    expand: bool,
+) -> Vec<(Range<usize>, String)> {
+    let mut segments = vec![];
+    collect_prose(&LinkedNode::new(source.root()), &mut segments);
+
+    let mut edits = vec![];
+    for segment in segments {
+        let text = &source.text()[segment.clone()];
+        for caps in re.captures_iter(text) {
+            let Some(matched) = caps.get(0).filter(|it| !it.is_empty()) else {
+                continue;
+            };
+
+            let mut new_text = String::new();
+            if expand {
+                caps.expand(replacement, &mut new_text);
+            } else {
+                new_text.push_str(replacement);
+            }
+
+            let start = segment.start + matched.start();
+            edits.push((start..start + matched.len(), new_text));
+        }
+    }
+
+    edits
+}
+
+/// Collects the ranges of the runs of text and spaces in markup, so that a
+/// phrase can be matched across words.
+fn collect_prose(node: &LinkedNode, segments: &mut Vec<Range<usize>>) {
+    let mut run: Option<Range<usize>> = None;
+    for child in node.children() {
+        match child.kind() {
+            SyntaxKind::Text => {
+                let range = child.range();
+                run = Some(match run {
+                    Some(run) => run.start..range.end,
+                    None => range,
+                });
+            }
+            SyntaxKind::Space if run.is_some() => {}
+            kind => {
+                segments.extend(run.take());
+                if !matches!(kind, SyntaxKind::Raw | SyntaxKind::Equation) {
+                    collect_prose(&child, segments);
+                }
+            }
+        }
+    }
+    segments.extend(run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_code() {
+        let source = Source::detached(
+            "= Hello World\nHello #text(\"Hello\")[Hello] `Hello` $Hello$ <Hello> #let Hello = 1\n",
+        );
+        let re = Regex::new("Hello").unwrap();
+        let edits = prose_replacements(&source, &re, "Hi", false);
+        let ranges = edits
+            .iter()
+            .map(|(range, _)| &source.text()[range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec!["Hello", "Hello", "Hello"]);
+        assert_eq!(edits[0].0, 2..7);
+        assert_eq!(edits[1].0, 14..19);
+    }
+
+    #[test]
+    fn matches_across_words() {
+        let source = Source::detached("Hello World, hello world");
+        let re = RegexBuilder::new("hello (world)")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let edits = prose_replacements(&source, &re, "bye $1", true);
+        assert_eq!(
+            edits,
+            vec![
+                (0..11, "bye World".to_owned()),
+                (13..24, "bye world".to_owned())
+            ]
+        );
+    }
+}
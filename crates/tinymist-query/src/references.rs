@@ -96,7 +96,8 @@ impl ReferencesWorker<'_> {
     }
 
     fn ident_root(mut self) -> Option<Vec<LspLocation>> {
-        self.file(self.def.decl.file_id()?);
+        let def_fid = self.def.location(self.ctx.ctx.shared())?.fid;
+        self.file(def_fid);
         while let Some(ref_fid) = self.ctx.worklist.pop() {
             self.file(ref_fid);
         }
@@ -44,7 +44,7 @@ impl StatefulRequest for RenameRequest {
 
         let def = ctx.def_of_syntax(&source, doc, syntax.clone())?;
 
-        prepare_renaming(&syntax, &def)?;
+        prepare_renaming(ctx, &syntax, &def)?;
 
         match syntax {
             // todo: abs path
@@ -56,7 +56,7 @@ impl StatefulRequest for RenameRequest {
                     self.new_name
                 };
 
-                let def_fid = def.file_id()?;
+                let def_fid = def.location(ctx.shared())?.fid;
                 // todo: rename in untitled files
                 let old_path = ctx.path_for_id(def_fid).ok()?.to_err().ok()?;
 
@@ -213,7 +213,7 @@ impl RenameFileWorker<'_> {
         let edits = edits.entry(uri).or_default();
         for obj in &link_info.objects {
             if !matches!(&obj.target,
-                LinkTarget::Path(file_id, _) if *file_id == self.def_fid
+                LinkTarget::Path(file_id, path) if self.is_def_path(*file_id, path)
             ) {
                 continue;
             }
@@ -225,6 +225,12 @@ impl RenameFileWorker<'_> {
         Some(())
     }
 
+    /// Checks whether a path in a file refers to the renamed file.
+    fn is_def_path(&self, file_id: TypstFileId, path: &str) -> bool {
+        file_id.package() == self.def_fid.package()
+            && file_id.vpath().join(Path::new(path)) == *self.def_fid.vpath()
+    }
+
     fn rename_resource_path(
         &mut self,
         obj: &LinkObject,
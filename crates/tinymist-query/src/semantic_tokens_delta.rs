@@ -84,3 +84,31 @@ fn token_delta(from: &[SemanticToken], to: &[SemanticToken]) -> Vec<SemanticToke
         }]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(delta_line: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start: 0,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn delta() {
+        let from = [token(0), token(1), token(2), token(3)];
+        let to = [token(0), token(5), token(6), token(3)];
+        let edits = token_delta(&from, &to);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].start, 5);
+        assert_eq!(edits[0].delete_count, 10);
+        assert_eq!(edits[0].data.as_deref(), Some(&to[1..3]));
+
+        assert!(token_delta(&from, &from).is_empty());
+    }
+}
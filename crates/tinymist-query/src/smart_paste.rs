@@ -0,0 +1,141 @@
+use regex::Regex;
+use typst::foundations::{Repr, Str};
+
+use crate::{prelude::*, syntax::InterpretMode, InteractCodeContextRequest, SemanticRequest};
+
+/// Matches the absolute paths in a pasted text, either unix-style or
+/// windows-style.
+static ABS_PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:[A-Za-z]:[\\/]|/)[^\s"'()\[\]<>]+"#).unwrap());
+
+/// Matches the bare urls in a pasted text.
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s"'()\[\]<>]+"#).unwrap());
+
+/// A request to rewrite a text pasted into a document.
+///
+/// Absolute paths to local files are rewritten to be relative to the
+/// document, and bare urls are converted into `link` calls. Returns `None` if
+/// the text can be pasted as is.
+#[derive(Debug, Clone)]
+pub struct SmartPasteRequest {
+    /// The path of the document.
+    pub path: PathBuf,
+    /// The position where the text is pasted.
+    pub position: LspPosition,
+    /// The pasted text.
+    pub text: String,
+    /// The files that are copied into the project, mapping from the pasted
+    /// paths to the copies.
+    pub copied: HashMap<PathBuf, PathBuf>,
+}
+
+impl SemanticRequest for SmartPasteRequest {
+    type Response = String;
+
+    fn request(self, ctx: &mut LocalContext) -> Option<Self::Response> {
+        let source = ctx.source_by_path(&self.path).ok()?;
+        let cursor = ctx.to_typst_pos(self.position, &source)?;
+        let mode = InteractCodeContextRequest::mode_at(&source, cursor)?;
+        if matches!(mode, InterpretMode::Comment | InterpretMode::Raw) {
+            return None;
+        }
+
+        let base = self.path.parent()?;
+        let text = smart_paste(&self.text, mode, |path| {
+            let path = self.copied.get(path).map_or(path, |copy| copy.as_path());
+            let rel = tinymist_std::path::diff(path, base)?;
+            Some(tinymist_std::path::unix_slash(&rel))
+        });
+
+        (text != self.text).then_some(text)
+    }
+}
+
+/// Gets the absolute paths to the existing files in a pasted text.
+pub fn pasted_files(text: &str) -> Vec<PathBuf> {
+    ABS_PATH
+        .find_iter(text)
+        .map(|it| PathBuf::from(it.as_str()))
+        .filter(|path| path.is_absolute() && path.is_file())
+        .collect()
+}
+
+/// Rewrites the paths and urls in a pasted text.
+fn smart_paste(text: &str, mode: InterpretMode, rel: impl Fn(&Path) -> Option<String>) -> String {
+    let mut edits = vec![];
+    for path in ABS_PATH.find_iter(text) {
+        // The scheme separator of an url looks like a path.
+        if text[..path.start()].ends_with(':') {
+            continue;
+        }
+        let file = Path::new(path.as_str());
+        if !file.is_absolute() || !file.is_file() {
+            continue;
+        }
+        if let Some(rel) = rel(file) {
+            edits.push((path.range(), rel));
+        }
+    }
+
+    let link = match mode {
+        InterpretMode::Markup | InterpretMode::Math => Some("#link"),
+        InterpretMode::Code => Some("link"),
+        _ => None,
+    };
+    if let Some(link) = link {
+        for url in URL.find_iter(text) {
+            // Urls in strings or arguments are kept as is.
+            if text[..url.start()].ends_with(['"', '(', '<']) {
+                continue;
+            }
+            // The punctuation ending a sentence is not part of the url.
+            let url_str = url
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            let range = url.start()..url.start() + url_str.len();
+            edits.push((range, format!("{link}({})", Str::from(url_str).repr())));
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut res = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (range, replacement) in edits {
+        if range.start < cursor {
+            continue;
+        }
+        res.push_str(&text[cursor..range.start]);
+        res.push_str(&replacement);
+        cursor = range.end;
+    }
+    res.push_str(&text[cursor..]);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_paths_and_urls() {
+        let file = std::env::current_exe().unwrap();
+        let file_str = file.to_string_lossy();
+        let text = format!("see https://typst.app and #image(\"{file_str}\")");
+
+        let pasted = smart_paste(&text, InterpretMode::Markup, |path| {
+            assert_eq!(path, file);
+            Some("assets/image.png".to_owned())
+        });
+        assert_eq!(
+            pasted,
+            "see #link(\"https://typst.app\") and #image(\"assets/image.png\")"
+        );
+
+        let pasted = smart_paste("go to https://typst.app.", InterpretMode::Code, |_| None);
+        assert_eq!(pasted, "go to link(\"https://typst.app\").");
+        let pasted = smart_paste("<https://typst.app>", InterpretMode::Markup, |_| None);
+        assert_eq!(pasted, "<https://typst.app>");
+        let pasted = smart_paste("https://typst.app", InterpretMode::String, |_| None);
+        assert_eq!(pasted, "https://typst.app");
+    }
+}
@@ -0,0 +1,455 @@
+//! Checks the spelling of the prose in markup against the Hunspell
+//! dictionaries of the languages set by `text.lang`.
+
+use std::collections::HashSet;
+
+use ecow::eco_format;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use spellbook::Dictionary;
+use typst::diag::SourceDiagnostic;
+use typst::syntax::Span;
+
+use crate::prelude::*;
+
+/// The language of the text if neither the configuration nor `text.lang` sets
+/// it, as in Typst.
+const DEFAULT_LANG: &str = "en";
+/// The project word list, relative to the workspace root.
+const DEFAULT_WORD_LIST: &str = "project-words.txt";
+/// The maximum number of the suggestions for an unknown word.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Tinymist's spell checking features.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpellFeat {
+    /// Whether to check the spelling along with the lints.
+    pub enabled: bool,
+    /// The language of the text not set by `text.lang`, e.g. `en-GB`.
+    pub lang: Option<String>,
+    /// The directories containing the Hunspell dictionaries, e.g. `en_US.aff`
+    /// and `en_US.dic`, which are searched before the system directories.
+    pub dictionary_paths: Vec<PathBuf>,
+    /// The project word list, with one word per line. A relative path is
+    /// resolved against the workspace root.
+    pub word_list: Option<PathBuf>,
+}
+
+impl SpellFeat {
+    fn default_lang(&self) -> TextLang {
+        TextLang::parse(self.lang.as_deref().unwrap_or(DEFAULT_LANG))
+    }
+
+    /// Gets the path of the project word list.
+    pub(crate) fn word_list_path(&self, root: Option<&Path>) -> Option<PathBuf> {
+        let path = (self.word_list.clone()).unwrap_or_else(|| DEFAULT_WORD_LIST.into());
+        if path.is_absolute() {
+            Some(path)
+        } else {
+            Some(root?.join(path))
+        }
+    }
+}
+
+/// The language and the region of the text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TextLang {
+    lang: EcoString,
+    region: Option<EcoString>,
+}
+
+impl TextLang {
+    /// Parses a language tag, e.g. `en`, `en-GB` or `en_GB`.
+    fn parse(tag: &str) -> Self {
+        let mut parts = tag.split(['-', '_']);
+        let lang = parts.next().unwrap_or_default().to_lowercase().into();
+        let region = parts.next().map(|region| region.to_uppercase().into());
+        Self { lang, region }
+    }
+
+    /// Gets the names of the dictionaries for the language, in the order of
+    /// preference, e.g. `de_AT`, `de_DE` and `de`.
+    fn dictionary_names(&self) -> Vec<String> {
+        let lang = &self.lang;
+        let default_region = match lang.as_str() {
+            "en" => "US".to_owned(),
+            "pt" => "BR".to_owned(),
+            "sv" => "SE".to_owned(),
+            "da" => "DK".to_owned(),
+            "nb" | "nn" => "NO".to_owned(),
+            "cs" => "CZ".to_owned(),
+            "el" => "GR".to_owned(),
+            "uk" => "UA".to_owned(),
+            _ => lang.to_uppercase().to_string(),
+        };
+
+        let mut names = vec![];
+        if let Some(region) = &self.region {
+            names.push(format!("{lang}_{region}"));
+        }
+        names.push(format!("{lang}_{default_region}"));
+        names.push(lang.to_string());
+        names.dedup();
+        names
+    }
+}
+
+/// The dictionaries loaded by the paths of their `.dic` files, which are
+/// `None` if they cannot be loaded.
+static DICTIONARIES: LazyLock<Mutex<HashMap<PathBuf, Option<Arc<Dictionary>>>>> =
+    LazyLock::new(Mutex::default);
+
+fn load_dictionary(dic_path: &Path) -> Option<Arc<Dictionary>> {
+    let mut dictionaries = DICTIONARIES.lock();
+    let dictionary = dictionaries.entry(dic_path.to_owned()).or_insert_with(|| {
+        let aff = std::fs::read_to_string(dic_path.with_extension("aff")).ok()?;
+        let dic = std::fs::read_to_string(dic_path).ok()?;
+        match Dictionary::new(&aff, &dic) {
+            Ok(dictionary) => Some(Arc::new(dictionary)),
+            Err(err) => {
+                log::warn!("failed to load dictionary {dic_path:?}: {err}");
+                None
+            }
+        }
+    });
+    dictionary.clone()
+}
+
+/// Gets the directories to search the dictionaries in.
+fn dictionary_dirs(feat: &SpellFeat) -> Vec<PathBuf> {
+    let mut dirs = feat.dictionary_paths.clone();
+    if let Some(paths) = std::env::var_os("DICPATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    dirs.extend(
+        [
+            "/usr/share/hunspell",
+            "/usr/share/myspell",
+            "/usr/share/myspell/dicts",
+            "/Library/Spelling",
+        ]
+        .map(PathBuf::from),
+    );
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join("Library/Spelling"));
+    }
+    dirs
+}
+
+/// Finds the dictionary of a language, falling back to any dictionary of the
+/// language if there is none for the region.
+fn find_dictionary(dirs: &[PathBuf], lang: &TextLang) -> Option<PathBuf> {
+    let exists = |path: &Path| path.is_file() && path.with_extension("aff").is_file();
+    for name in lang.dictionary_names() {
+        for dir in dirs {
+            let path = dir.join(format!("{name}.dic"));
+            if exists(&path) {
+                return Some(path);
+            }
+        }
+    }
+
+    let prefix = format!("{}_", lang.lang);
+    dirs.iter().find_map(|dir| {
+        let entries = std::fs::read_dir(dir).ok()?;
+        let mut paths = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let stem = path.file_stem().and_then(|stem| stem.to_str());
+                let is_dic = path.extension().is_some_and(|ext| ext == "dic");
+                is_dic && stem.is_some_and(|stem| stem.starts_with(&prefix)) && exists(path)
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.into_iter().next()
+    })
+}
+
+/// Checks the words against the dictionaries and the project word list.
+struct SpellChecker {
+    dirs: Vec<PathBuf>,
+    words: HashSet<String>,
+    dictionaries: HashMap<TextLang, Option<Arc<Dictionary>>>,
+}
+
+impl SpellChecker {
+    fn new(feat: &SpellFeat, root: Option<&Path>) -> Self {
+        let words = feat
+            .word_list_path(root)
+            .and_then(|path| std::fs::read_to_string(path).ok());
+        let words = words.iter().flat_map(|words| words.lines());
+        let words = words
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && !word.starts_with('#'));
+
+        Self {
+            dirs: dictionary_dirs(feat),
+            words: words.map(str::to_owned).collect(),
+            dictionaries: HashMap::new(),
+        }
+    }
+
+    fn dictionary(&mut self, lang: &TextLang) -> Option<Arc<Dictionary>> {
+        let dirs = &self.dirs;
+        let dictionary = self.dictionaries.entry(lang.clone()).or_insert_with(|| {
+            let dictionary = find_dictionary(dirs, lang).and_then(|path| load_dictionary(&path));
+            if dictionary.is_none() {
+                log::info!("no dictionary is found for {lang:?}");
+            }
+            dictionary
+        });
+        dictionary.clone()
+    }
+
+    /// Checks whether the word is known, which is also the case if there is no
+    /// dictionary for the language.
+    fn check(&mut self, word: &str, lang: &TextLang) -> bool {
+        let word = word.replace('’', "'");
+        if self.words.contains(&word) || self.words.contains(&word.to_lowercase()) {
+            return true;
+        }
+        self.dictionary(lang)
+            .is_none_or(|dictionary| dictionary.check(&word))
+    }
+
+    fn suggest(&mut self, word: &str, lang: &TextLang) -> Vec<String> {
+        let Some(dictionary) = self.dictionary(lang) else {
+            return vec![];
+        };
+        let mut suggestions = vec![];
+        dictionary.suggest(&word.replace('’', "'"), &mut suggestions);
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+}
+
+/// A word of the prose.
+#[derive(Debug, Clone)]
+struct ProseWord {
+    range: Range<usize>,
+    lang: TextLang,
+}
+
+/// Collects the words of the prose in a source, skipping the code, the
+/// equations and the raw blocks.
+fn prose_words(source: &Source, lang: &TextLang) -> Vec<ProseWord> {
+    let mut runs = vec![];
+    collect_runs(&LinkedNode::new(source.root()), lang, &mut runs);
+
+    let text = source.text();
+    let mut words = vec![];
+    for (run, lang) in runs {
+        for word in split_words(&text[run.clone()]) {
+            let range = run.start + word.start..run.start + word.end;
+            if should_check(&text[range.clone()]) {
+                words.push(ProseWord {
+                    range,
+                    lang: lang.clone(),
+                });
+            }
+        }
+    }
+    words
+}
+
+/// Collects the runs of text along with their languages, which are changed by
+/// the set rules of `text` for the following siblings and by the calls of
+/// `text` for their arguments.
+fn collect_runs(node: &LinkedNode, lang: &TextLang, runs: &mut Vec<(Range<usize>, TextLang)>) {
+    let mut lang = lang.clone();
+    let mut run: Option<Range<usize>> = None;
+    for child in node.children() {
+        if matches!(child.kind(), SyntaxKind::Text | SyntaxKind::SmartQuote) {
+            let range = child.range();
+            match &mut run {
+                Some(run) if run.end == range.start => run.end = range.end,
+                _ => runs.extend(run.replace(range).map(|run| (run, lang.clone()))),
+            }
+            continue;
+        }
+        runs.extend(run.take().map(|run| (run, lang.clone())));
+
+        match child.kind() {
+            SyntaxKind::Raw | SyntaxKind::Equation => {}
+            SyntaxKind::SetRule => {
+                if let Some(set) = child.cast::<ast::SetRule>() {
+                    if let Some(set_lang) = text_lang(set.target(), set.args(), &lang) {
+                        lang = set_lang;
+                    }
+                }
+            }
+            SyntaxKind::FuncCall => {
+                let call = child.cast::<ast::FuncCall>();
+                let call_lang = call.and_then(|call| text_lang(call.callee(), call.args(), &lang));
+                collect_runs(&child, call_lang.as_ref().unwrap_or(&lang), runs);
+            }
+            _ => collect_runs(&child, &lang, runs),
+        }
+    }
+    runs.extend(run.map(|run| (run, lang)));
+}
+
+/// Gets the language set by the arguments of `text`, if any.
+fn text_lang(callee: ast::Expr, args: ast::Args, lang: &TextLang) -> Option<TextLang> {
+    let ast::Expr::Ident(callee) = callee else {
+        return None;
+    };
+    if callee.get() != "text" {
+        return None;
+    }
+
+    let (mut set_lang, mut set_region) = (None, None);
+    for arg in args.items() {
+        let ast::Arg::Named(named) = arg else {
+            continue;
+        };
+        let ast::Expr::Str(value) = named.expr() else {
+            continue;
+        };
+        match named.name().get().as_str() {
+            "lang" => set_lang = Some(value.get().to_lowercase()),
+            "region" => set_region = Some(value.get().to_uppercase()),
+            _ => {}
+        }
+    }
+
+    let mut result = match set_lang {
+        // The region of another language doesn't apply.
+        Some(set_lang) => TextLang {
+            lang: set_lang.into(),
+            region: None,
+        },
+        None if set_region.is_some() => lang.clone(),
+        None => return None,
+    };
+    if let Some(set_region) = set_region {
+        result.region = Some(set_region.into());
+    }
+    Some(result)
+}
+
+/// Splits the text into the words, which may contain the apostrophes, e.g.
+/// `don't`.
+fn split_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        let is_apostrophe = matches!(ch, '\'' | '’')
+            && start.is_some()
+            && chars.peek().is_some_and(|(_, next)| next.is_alphabetic());
+        if ch.is_alphanumeric() || is_apostrophe {
+            start.get_or_insert(idx);
+        } else if let Some(start) = start.take() {
+            words.push(start..idx);
+        }
+    }
+    if let Some(start) = start {
+        words.push(start..text.len());
+    }
+    words
+}
+
+/// Checks whether a word should be checked, skipping the single letters, the
+/// numbers, the acronyms and the identifiers in camel case.
+fn should_check(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next();
+    word.chars().count() > 1
+        && !word.chars().any(|ch| ch.is_numeric())
+        && !chars.any(|ch| ch.is_uppercase())
+}
+
+/// Checks the spelling of the prose in the files.
+pub(crate) fn check_spelling(
+    sources: &[Source],
+    feat: &SpellFeat,
+    root: Option<&Path>,
+) -> EcoVec<SourceDiagnostic> {
+    let mut diags = EcoVec::new();
+    if !feat.enabled {
+        return diags;
+    }
+
+    let mut checker = SpellChecker::new(feat, root);
+    let lang = feat.default_lang();
+    for source in sources {
+        for word in prose_words(source, &lang) {
+            let text = &source.text()[word.range.clone()];
+            if checker.check(text, &word.lang) {
+                continue;
+            }
+
+            let span = Span::from_range(source.id(), word.range);
+            let msg = eco_format!("unknown word `{text}`");
+            let hint = "add it to the project word list if it is spelled correctly";
+            diags.push(SourceDiagnostic::warning(span, msg).with_hint(hint));
+        }
+    }
+    diags
+}
+
+/// Gets the suggestions for the unknown word at the range.
+pub(crate) fn spelling_suggestions(
+    source: &Source,
+    range: &Range<usize>,
+    feat: &SpellFeat,
+    root: Option<&Path>,
+) -> Vec<String> {
+    let words = prose_words(source, &feat.default_lang());
+    let Some(word) = words.into_iter().find(|word| word.range == *range) else {
+        return vec![];
+    };
+
+    let mut checker = SpellChecker::new(feat, root);
+    checker.suggest(&source.text()[range.clone()], &word.lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<(String, String)> {
+        let source = Source::detached(text);
+        let words = prose_words(&source, &TextLang::parse("en"));
+        let words = words.into_iter().map(|word| {
+            let lang = match &word.lang.region {
+                Some(region) => format!("{}-{region}", word.lang.lang),
+                None => word.lang.lang.to_string(),
+            };
+            (text[word.range].to_owned(), lang)
+        });
+        words.collect()
+    }
+
+    #[test]
+    fn prose_words_by_lang() {
+        let text = "Don’t check `raw` or $x y$ or #foo(bar).\n\
+                    #text(lang: \"de\")[Hallo Welt] and NASA's mRNA.\n\
+                    #set text(region: \"gb\", lang: \"en\")\n\
+                    Colour a2b";
+        let expected = [
+            ("Don’t", "en"),
+            ("check", "en"),
+            ("or", "en"),
+            ("or", "en"),
+            ("Hallo", "de"),
+            ("Welt", "de"),
+            ("and", "en"),
+            ("Colour", "en-GB"),
+        ];
+        let expected = expected.map(|(word, lang)| (word.to_owned(), lang.to_owned()));
+        assert_eq!(words(text), expected);
+    }
+
+    #[test]
+    fn dictionary_names() {
+        assert_eq!(
+            TextLang::parse("de-AT").dictionary_names(),
+            ["de_AT", "de_DE", "de"]
+        );
+        assert_eq!(TextLang::parse("en").dictionary_names(), ["en_US", "en"]);
+    }
+}
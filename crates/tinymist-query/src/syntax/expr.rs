@@ -40,6 +40,10 @@ pub(crate) fn expr_of(
 
     route.insert(source.id(), None);
 
+    let prev_closures = prev
+        .as_ref()
+        .map(|prev| prev.closures.clone())
+        .unwrap_or_default();
     let cache_hit = prev.and_then(|prev| {
         if prev.source.len_bytes() != source.len_bytes()
             || hash128(&prev.source) != hash128(&source)
@@ -84,6 +88,9 @@ pub(crate) fn expr_of(
     let imports_base = Arc::new(Mutex::new(FxHashMap::default()));
     let imports = imports_base.clone();
 
+    let closures_base = Arc::new(Mutex::new(FxHashMap::default()));
+    let closures = closures_base.clone();
+
     let module_docstring = find_module_level_docs(&source)
         .and_then(|docs| ctx.compute_docstring(source.id(), docs, DefKind::Module))
         .unwrap_or_default();
@@ -102,6 +109,10 @@ pub(crate) fn expr_of(
             init_stage: true,
             comment_matcher: DocCommentMatcher::default(),
             route,
+            prev_closures,
+            closures,
+            closure_logs: vec![],
+            external_deps: 0,
         };
 
         let root = source.root().cast::<ast::Markup>().unwrap();
@@ -131,6 +142,7 @@ pub(crate) fn expr_of(
         exports,
         exprs: std::mem::take(exprs_base.lock().deref_mut()),
         root,
+        closures: std::mem::take(closures_base.lock().deref_mut()),
     };
     crate::log_debug_ct!("expr_of end {:?}", source.id());
 
@@ -173,6 +185,33 @@ pub(crate) struct ExprWorker<'a> {
 
     route: &'a mut ExprRoute,
     comment_matcher: DocCommentMatcher,
+
+    /// The closures analyzed in the previous revision of the file.
+    prev_closures: FxHashMap<Span, Arc<ClosureInfo>>,
+    closures: Arc<Mutex<FxHashMap<Span, Arc<ClosureInfo>>>>,
+    /// The logs of the closures being checked, innermost last.
+    closure_logs: Vec<ClosureLog>,
+    /// The number of the lookups depending on the other files or the
+    /// compilation, which prevent the closures from being reused.
+    external_deps: usize,
+}
+
+/// A lookup of a name outside of a closure, with the index of the lexical
+/// scope where the name is found, or `None` if it is not found in the file.
+type Capture = (Interned<str>, InterpretMode, Option<usize>, ConcolicExpr);
+
+/// The lookups and outputs while checking a closure.
+struct ClosureLog {
+    /// The number of the lexical scopes outside of the closure.
+    depth: usize,
+    /// The length of the resolve buffer when the closure is entered.
+    resolves_start: usize,
+    /// The number of external dependencies when the closure is entered.
+    external_deps: usize,
+    captures: Vec<Capture>,
+    exprs: Vec<(Span, Expr)>,
+    docstrings: Vec<(DeclExpr, Arc<DocString>)>,
+    closures: Vec<(Span, Arc<ClosureInfo>)>,
 }
 
 impl ExprWorker<'_> {
@@ -220,6 +259,9 @@ impl ExprWorker<'_> {
         if let Some(docs) = docs {
             let docstring = self.ctx.compute_docstring(self.fid, docs, kind);
             if let Some(docstring) = docstring {
+                if let Some(log) = self.closure_logs.last_mut() {
+                    log.docstrings.push((decl.clone(), docstring.clone()));
+                }
                 self.docstrings.lock().insert(decl.clone(), docstring);
             }
         }
@@ -236,6 +278,9 @@ impl ExprWorker<'_> {
     fn check(&mut self, m: ast::Expr) -> Expr {
         let s = m.span();
         let ret = self.do_check(m);
+        if let Some(log) = self.closure_logs.last_mut() {
+            log.exprs.push((s, ret.clone()));
+        }
         self.exprs.lock().insert(s, ret.clone());
         ret
     }
@@ -419,6 +464,14 @@ impl ExprWorker<'_> {
         let (params, body) = self.with_scope(|this| {
             this.scope_mut()
                 .insert_mut(decl.name().clone(), decl.clone().into());
+            if let Some(reused) = this.reuse_closure(typed) {
+                return (reused.params.clone(), reused.body.clone());
+            }
+            let record = !this.init_stage;
+            if record {
+                this.enter_closure();
+            }
+
             let mut inputs = eco_vec![];
             let mut names = eco_vec![];
             let mut spread_left = None;
@@ -468,7 +521,11 @@ impl ExprWorker<'_> {
                 spread_right,
             };
 
-            (pattern, this.defer(typed.body()))
+            let body = this.defer(typed.body());
+            if record {
+                this.exit_closure(typed, &pattern, &body);
+            }
+            (pattern, body)
         });
 
         self.scope_mut()
@@ -476,6 +533,99 @@ impl ExprWorker<'_> {
         Expr::Func(FuncExpr { decl, params, body }.into())
     }
 
+    /// Reuses the analysis of a closure in the previous revision, if neither its
+    /// syntax nor the names it captures change.
+    fn reuse_closure(&mut self, typed: ast::Closure) -> Option<Arc<ClosureInfo>> {
+        if self.init_stage {
+            return None;
+        }
+        let span = typed.span();
+        let prev = self.prev_closures.get(&span)?.clone();
+        if prev.node != *typed.to_untyped() {
+            return None;
+        }
+
+        let depth = self.lexical.scopes.len();
+        let mut captures = Vec::with_capacity(prev.captures.len());
+        for (name, mode, expr, ty) in &prev.captures {
+            let (level, res) = self.eval_outer_ident(name, *mode, depth);
+            if res.0 != *expr || res.1 != *ty {
+                return None;
+            }
+            captures.push((name.clone(), *mode, level, res));
+        }
+
+        self.buffer.extend(prev.resolves.iter().cloned());
+        self.exprs.lock().extend(prev.exprs.iter().cloned());
+        self.docstrings
+            .lock()
+            .extend(prev.docstrings.iter().cloned());
+        let mut closures = self.closures.lock();
+        closures.insert(span, prev.clone());
+        closures.extend(prev.closures.iter().cloned());
+        drop(closures);
+
+        if let Some(parent) = self.closure_logs.last_mut() {
+            let depth = parent.depth;
+            let outer = captures.into_iter();
+            let outer = outer.filter(|(.., level, _)| level.is_none_or(|level| level < depth));
+            parent.captures.extend(outer);
+            parent.exprs.extend(prev.exprs.iter().cloned());
+            parent.docstrings.extend(prev.docstrings.iter().cloned());
+            parent.closures.push((span, prev.clone()));
+            parent.closures.extend(prev.closures.iter().cloned());
+        }
+
+        Some(prev)
+    }
+
+    /// Starts recording the lookups and outputs of a closure.
+    fn enter_closure(&mut self) {
+        self.closure_logs.push(ClosureLog {
+            depth: self.lexical.scopes.len(),
+            resolves_start: self.buffer.len(),
+            external_deps: self.external_deps,
+            captures: vec![],
+            exprs: vec![],
+            docstrings: vec![],
+            closures: vec![],
+        });
+    }
+
+    /// Stops recording a closure and remembers its analysis for the next
+    /// revision, unless it depends on the other files.
+    fn exit_closure(&mut self, typed: ast::Closure, params: &PatternSig, body: &Expr) {
+        let log = self.closure_logs.pop().expect("closure log");
+        let span = typed.span();
+        let info = Arc::new(ClosureInfo {
+            node: typed.to_untyped().clone(),
+            captures: (log.captures.iter())
+                .map(|(name, mode, _, (expr, ty))| (name.clone(), *mode, expr.clone(), ty.clone()))
+                .collect(),
+            params: params.clone(),
+            body: body.clone(),
+            resolves: self.buffer[log.resolves_start..].to_vec(),
+            exprs: log.exprs,
+            docstrings: log.docstrings,
+            closures: log.closures,
+        });
+
+        if log.external_deps == self.external_deps {
+            self.closures.lock().insert(span, info.clone());
+        }
+
+        if let Some(parent) = self.closure_logs.last_mut() {
+            let depth = parent.depth;
+            let outer = log.captures.into_iter();
+            let outer = outer.filter(|(.., level, _)| level.is_none_or(|level| level < depth));
+            parent.captures.extend(outer);
+            parent.exprs.extend(info.exprs.iter().cloned());
+            parent.docstrings.extend(info.docstrings.iter().cloned());
+            parent.closures.push((span, info.clone()));
+            parent.closures.extend(info.closures.iter().cloned());
+        }
+    }
+
     fn check_pattern(&mut self, typed: ast::Pattern) -> Interned<Pattern> {
         match typed {
             ast::Pattern::Normal(expr) => self.check_pattern_expr(expr),
@@ -655,6 +805,7 @@ impl ExprWorker<'_> {
         is_import: bool,
         is_wildcard_import: bool,
     ) -> Option<Expr> {
+        self.external_deps += 1;
         let src = self.eval_expr(source, InterpretMode::Code);
         let src_expr = self.fold_expr_and_val(src).or_else(|| {
             self.ctx
@@ -1129,19 +1280,50 @@ impl ExprWorker<'_> {
         }
     }
 
-    fn eval_ident(&self, name: &Interned<str>, mode: InterpretMode) -> ConcolicExpr {
+    fn eval_ident(&mut self, name: &Interned<str>, mode: InterpretMode) -> ConcolicExpr {
         let res = self.lexical.last.get(name);
         if res.0.is_some() || res.1.is_some() {
             return res;
         }
 
-        for scope in self.lexical.scopes.iter().rev() {
+        // The scopes inside the closure being checked.
+        let depth = self.closure_logs.last().map_or(0, |log| log.depth);
+        let depth = depth.min(self.lexical.scopes.len());
+        for scope in self.lexical.scopes[depth..].iter().rev() {
             let res = scope.get(name);
             if res.0.is_some() || res.1.is_some() {
                 return res;
             }
         }
 
+        let (level, res) = self.eval_outer_ident(name, mode, depth);
+        if let Some(log) = self.closure_logs.last_mut() {
+            log.captures.push((name.clone(), mode, level, res.clone()));
+        }
+        res
+    }
+
+    /// Evaluates an identifier in the lexical scopes outside of a closure and
+    /// the global scope, returning the index of the lexical scope where the
+    /// identifier is found.
+    fn eval_outer_ident(
+        &self,
+        name: &Interned<str>,
+        mode: InterpretMode,
+        depth: usize,
+    ) -> (Option<usize>, ConcolicExpr) {
+        let scopes = &self.lexical.scopes[..depth.min(self.lexical.scopes.len())];
+        for (level, scope) in scopes.iter().enumerate().rev() {
+            let res = scope.get(name);
+            if res.0.is_some() || res.1.is_some() {
+                return (Some(level), res);
+            }
+        }
+
+        (None, self.eval_global_ident(name, mode))
+    }
+
+    fn eval_global_ident(&self, name: &Interned<str>, mode: InterpretMode) -> ConcolicExpr {
         let scope = match mode {
             InterpretMode::Math => self.ctx.world.library.math.scope(),
             InterpretMode::Markup | InterpretMode::Code => self.ctx.world.library.global.scope(),
@@ -1220,6 +1402,7 @@ impl ExprWorker<'_> {
     }
 
     fn exports_of(&mut self, fid: TypstFileId) -> LexicalScope {
+        self.external_deps += 1;
         let imported = self
             .ctx
             .source_by_id(fid)
@@ -1245,9 +1428,84 @@ fn none_expr() -> Expr {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::tests::*;
+
     #[test]
     fn test_expr_size() {
-        use super::*;
         assert!(size_of::<Expr>() <= size_of::<usize>() * 2);
     }
+
+    /// Analyzes the main file before and after replacing `from` by `to`, where
+    /// the analysis after the edit reuses the one before.
+    fn check_edit(files: &str, from: &str, to: &str, f: impl Fn(&ExprInfo, &ExprInfo)) {
+        run_with_sources(files, |verse, path| {
+            run_with_ctx(verse, path, &|ctx, path| {
+                let shared = ctx.shared_();
+                let expr = |source: &Source, prev| {
+                    let guard = shared.query_stat(source.id(), "expr_stage");
+                    let mut route = ExprRoute::default();
+                    expr_of(shared.clone(), source.clone(), &mut route, guard, prev)
+                };
+
+                let source = ctx.source_by_path(&path).unwrap();
+                let prev = expr(&source, None);
+                let mut edited = source.clone();
+                let start = source.text().find(from).unwrap();
+                edited.edit(start..start + from.len(), to);
+                let next = expr(&edited, Some(prev.clone()));
+                f(&prev, &next);
+            })
+        });
+    }
+
+    /// Gets the analyzed closure containing the text.
+    fn closure<'a>(ei: &'a ExprInfo, text: &str) -> &'a Arc<ClosureInfo> {
+        let mut closures = ei.closures.values();
+        let closure = closures.find(|info| info.node.clone().into_text().contains(text));
+        closure.unwrap_or_else(|| panic!("closure {text:?} is not analyzed"))
+    }
+
+    fn is_reused(prev: &ExprInfo, next: &ExprInfo, text: &str) -> bool {
+        Arc::ptr_eq(closure(prev, text), closure(next, text))
+    }
+
+    #[test]
+    fn reuse_unchanged_closure() {
+        let files = "#let x = 1\n#let f(a) = a + x\n#let g(b) = b\nend";
+        check_edit(files, "end", "fin", |prev, next| {
+            let captures = &closure(prev, "a + x").captures;
+            assert!(captures.iter().any(|(name, ..)| name.as_ref() == "x"));
+
+            assert!(is_reused(prev, next, "a + x"));
+            assert!(is_reused(prev, next, "= b"));
+        });
+    }
+
+    #[test]
+    fn invalidate_closure_by_capture() {
+        let files = "#let x = 1\n#let f(a) = a + x\n#let g(b) = b\nend";
+        check_edit(files, "let x = 1", "let y = 1", |prev, next| {
+            assert!(!is_reused(prev, next, "a + x"));
+            assert!(is_reused(prev, next, "= b"));
+        });
+    }
+
+    #[test]
+    fn invalidate_closure_by_import() {
+        let files = r#"// path: b.typ
+#let y = 1
+-----
+// path: c.typ
+#let y = 2
+-----
+#import "b.typ": y
+#let f(a) = a + y
+#let g(b) = b
+"#;
+        check_edit(files, "\"b.typ\"", "\"c.typ\"", |prev, next| {
+            assert!(!is_reused(prev, next, "a + y"));
+            assert!(is_reused(prev, next, "= b"));
+        });
+    }
 }
@@ -3,6 +3,7 @@ use std::sync::Once;
 use regex::RegexSet;
 
 use crate::prelude::*;
+use crate::syntax::resolve_id_by_path;
 
 /// The dependency information of a module (file).
 #[derive(Debug, Clone)]
@@ -63,6 +64,190 @@ pub fn construct_module_dependencies(
     dependencies
 }
 
+/// The import graph of the modules reachable from an entry point, which is
+/// scanned from the syntax of the modules without analyzing them.
+pub(crate) struct ImportGraph {
+    /// The modules in the order of discovery.
+    nodes: Vec<TypstFileId>,
+    /// The indices of the modules imported or included by each module.
+    edges: Vec<Vec<usize>>,
+}
+
+impl ImportGraph {
+    /// Scans the modules imported or included by string paths from an entry
+    /// point.
+    pub fn scan(world: &dyn World, entry_point: TypstFileId) -> Self {
+        let mut graph = Self {
+            nodes: vec![entry_point],
+            edges: vec![vec![]],
+        };
+        let mut indices = HashMap::from([(entry_point, 0)]);
+
+        let mut idx = 0;
+        while idx < graph.nodes.len() {
+            let fid = graph.nodes[idx];
+            let mut paths = vec![];
+            if let Ok(source) = world.source(fid) {
+                import_paths(source.root(), &mut paths);
+            }
+
+            for path in paths {
+                let Some(dep) = resolve_id_by_path(world, fid, &path) else {
+                    continue;
+                };
+                if world.source(dep).is_err() {
+                    continue;
+                }
+                let dep_idx = *indices.entry(dep).or_insert_with(|| {
+                    graph.nodes.push(dep);
+                    graph.edges.push(vec![]);
+                    graph.nodes.len() - 1
+                });
+                if !graph.edges[idx].contains(&dep_idx) {
+                    graph.edges[idx].push(dep_idx);
+                }
+            }
+            idx += 1;
+        }
+
+        graph
+    }
+
+    /// Groups the modules into waves, where the modules in a wave only depend
+    /// on the modules in the earlier waves, or on the modules in the same
+    /// group if they import each other. Hence, the groups in a wave can be
+    /// analyzed in parallel without waiting for each other.
+    pub fn waves(&self) -> Vec<Vec<Vec<TypstFileId>>> {
+        let components = self.strongly_connected_components();
+        let mut component_of = vec![0; self.nodes.len()];
+        for (comp, nodes) in components.iter().enumerate() {
+            for &node in nodes {
+                component_of[node] = comp;
+            }
+        }
+
+        // The components are found after all the components they depend on.
+        let mut levels = vec![0; components.len()];
+        let mut waves: Vec<Vec<Vec<TypstFileId>>> = vec![];
+        for (comp, nodes) in components.iter().enumerate() {
+            let deps = nodes.iter().flat_map(|&node| &self.edges[node]);
+            let deps = deps
+                .map(|&dep| component_of[dep])
+                .filter(|&dep| dep != comp);
+            let level = deps.map(|dep| levels[dep] + 1).max().unwrap_or(0);
+            levels[comp] = level;
+
+            if waves.len() <= level {
+                waves.resize_with(level + 1, Vec::new);
+            }
+            waves[level].push(nodes.iter().map(|&node| self.nodes[node]).collect());
+        }
+
+        waves
+    }
+
+    /// Finds the strongly connected components by Tarjan's algorithm, in the
+    /// reverse topological order.
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            edges: &'a [Vec<usize>],
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            next_index: usize,
+            components: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn enter(&mut self, node: usize) {
+                self.index[node] = Some(self.next_index);
+                self.low[node] = self.next_index;
+                self.next_index += 1;
+                self.stack.push(node);
+                self.on_stack[node] = true;
+            }
+
+            /// Visits the nodes reachable from a root with an explicit stack
+            /// of the nodes being visited and their next edges, so a long
+            /// chain of imports doesn't overflow the call stack.
+            fn visit(&mut self, root: usize) {
+                self.enter(root);
+                let mut visiting = vec![(root, 0)];
+
+                while let Some(&(node, edge)) = visiting.last() {
+                    if let Some(&dep) = self.edges[node].get(edge) {
+                        let top = visiting.len() - 1;
+                        visiting[top].1 += 1;
+                        match self.index[dep] {
+                            None => {
+                                self.enter(dep);
+                                visiting.push((dep, 0));
+                            }
+                            Some(dep_index) if self.on_stack[dep] => {
+                                self.low[node] = self.low[node].min(dep_index);
+                            }
+                            Some(_) => {}
+                        }
+                        continue;
+                    }
+
+                    visiting.pop();
+                    if let Some(&(parent, _)) = visiting.last() {
+                        self.low[parent] = self.low[parent].min(self.low[node]);
+                    }
+
+                    if Some(self.low[node]) == self.index[node] {
+                        let mut component = vec![];
+                        while let Some(member) = self.stack.pop() {
+                            self.on_stack[member] = false;
+                            component.push(member);
+                            if member == node {
+                                break;
+                            }
+                        }
+                        self.components.push(component);
+                    }
+                }
+            }
+        }
+
+        let len = self.nodes.len();
+        let mut tarjan = Tarjan {
+            edges: &self.edges,
+            index: vec![None; len],
+            low: vec![0; len],
+            on_stack: vec![false; len],
+            stack: vec![],
+            next_index: 0,
+            components: vec![],
+        };
+        for node in 0..len {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.components
+    }
+}
+
+/// Collects the string paths of the imports and includes in a syntax tree, in
+/// the order of their appearance.
+pub(crate) fn import_paths(node: &SyntaxNode, paths: &mut Vec<EcoString>) {
+    let source = match node.kind() {
+        SyntaxKind::ModuleImport => node.cast::<ast::ModuleImport>().map(|it| it.source()),
+        SyntaxKind::ModuleInclude => node.cast::<ast::ModuleInclude>().map(|it| it.source()),
+        _ => None,
+    };
+    if let Some(ast::Expr::Str(path)) = source {
+        paths.push(path.get());
+    }
+
+    for child in node.children() {
+        import_paths(child, paths);
+    }
+}
+
 fn is_hidden(entry: &walkdir::DirEntry) -> bool {
     entry
         .file_name()
@@ -148,3 +333,53 @@ pub(crate) fn scan_workspace_files<T>(
 
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    #[test]
+    fn import_waves() {
+        let fid = |name: &str| TypstFileId::new(None, VirtualPath::new(name));
+        // main -> a -> c, main -> b -> c, b <-> d
+        let graph = ImportGraph {
+            nodes: ["/main.typ", "/a.typ", "/b.typ", "/c.typ", "/d.typ"]
+                .map(fid)
+                .to_vec(),
+            edges: vec![vec![1, 2], vec![3], vec![3, 4], vec![], vec![2]],
+        };
+
+        let mut waves = graph.waves();
+        for wave in &mut waves {
+            for group in wave.iter_mut() {
+                group.sort_by_key(|fid| fid.vpath().as_rooted_path().to_owned());
+            }
+            wave.sort();
+        }
+        assert_eq!(
+            waves,
+            vec![
+                vec![vec![fid("/c.typ")]],
+                vec![vec![fid("/a.typ")], vec![fid("/b.typ"), fid("/d.typ")]],
+                vec![vec![fid("/main.typ")]],
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_import_chain() {
+        let len = 100_000;
+        let graph = ImportGraph {
+            nodes: vec![TypstFileId::new(None, VirtualPath::new("/main.typ")); len],
+            edges: (0..len)
+                .map(|idx| if idx + 1 < len { vec![idx + 1] } else { vec![] })
+                .collect(),
+        };
+
+        let components = graph.strongly_connected_components();
+        assert_eq!(components.len(), len);
+        assert_eq!(components[0], vec![len - 1]);
+    }
+}
@@ -1,6 +1,6 @@
 //! Extracts test suites from the document.
 
-use ecow::EcoString;
+use ecow::{eco_format, EcoString};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use tinymist_std::error::prelude::*;
 use tinymist_std::typst::TypstDocument;
@@ -16,6 +16,7 @@ use typst::{
 use crate::LocalContext;
 
 /// Test suites extracted from the document.
+#[derive(Default)]
 pub struct TestSuites {
     /// Files from the current workspace.
     pub origin_files: Vec<(Source, Module)>,
@@ -23,6 +24,8 @@ pub struct TestSuites {
     pub tests: Vec<TestCase>,
     /// Example documents in the current workspace.
     pub examples: Vec<Source>,
+    /// Examples in the docstrings of the current workspace.
+    pub doc_examples: Vec<DocExample>,
 }
 impl TestSuites {
     /// Rechecks the test suites.
@@ -56,6 +59,7 @@ impl TestSuites {
             origin_files: self.origin_files.clone(),
             tests,
             examples,
+            doc_examples: self.doc_examples.clone(),
         }
     }
 }
@@ -85,6 +89,66 @@ pub struct TestCase {
     pub kind: TestCaseKind,
 }
 
+/// A fenced `example` block in a docstring, which is compiled as a hidden
+/// test case.
+#[derive(Debug, Clone)]
+pub struct DocExample {
+    /// Name of the example, i.e. the path of the file and the line of the
+    /// block, e.g. `src/lib.typ:12`.
+    pub name: EcoString,
+    /// The file containing the docstring.
+    pub location: FileId,
+    /// The 0-based line of the opening fence of the block.
+    pub line: usize,
+    /// The code in the block.
+    pub code: EcoString,
+    /// The byte lengths of the comment markers before the lines of the code,
+    /// e.g. `/// `.
+    pub indents: Vec<usize>,
+}
+
+/// Extracts the fenced `example` blocks in the line docstrings of a source.
+pub fn doc_examples(source: &Source) -> Vec<DocExample> {
+    let fid = source.id();
+    let path = fid.vpath().as_rootless_path().to_string_lossy();
+
+    let mut examples = vec![];
+    let mut block: Option<DocExample> = None;
+    for (line, text) in source.text().lines().enumerate() {
+        let trimmed = text.trim_start();
+        let content = trimmed
+            .strip_prefix("///")
+            .map(|rest| rest.strip_prefix(' ').unwrap_or(rest));
+        let Some(content) = content else {
+            // An unclosed block ends with the docstring.
+            block = None;
+            continue;
+        };
+
+        if content.trim_start().starts_with("```") {
+            match block.take() {
+                Some(example) => examples.push(example),
+                None if content.trim() == "```example" => {
+                    block = Some(DocExample {
+                        name: eco_format!("{path}:{}", line + 1),
+                        location: fid,
+                        line,
+                        code: EcoString::new(),
+                        indents: vec![],
+                    });
+                }
+                None => {}
+            }
+        } else if let Some(example) = &mut block {
+            example.code.push_str(content);
+            example.code.push('\n');
+            example.indents.push(text.len() - content.len());
+        }
+    }
+
+    examples
+}
+
 /// Extracts the test suites in the document
 pub fn test_suites(ctx: &mut LocalContext, doc: &TypstDocument) -> Result<TestSuites> {
     let main_id = ctx.world.main();
@@ -119,9 +183,15 @@ pub fn test_suites(ctx: &mut LocalContext, doc: &TypstDocument) -> Result<TestSu
 
     worker.discover_tests()?;
 
+    let doc_examples = files
+        .iter()
+        .flat_map(|(source, _)| doc_examples(source))
+        .collect();
+
     Ok(TestSuites {
         tests: worker.tests,
         examples: worker.examples,
+        doc_examples,
         origin_files: files,
     })
 }
@@ -227,3 +297,35 @@ impl TestSuitesWorker<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    #[test]
+    fn test_doc_examples() {
+        let text = r#"/// Adds two numbers.
+///
+/// ```example
+/// #add(1, 2)
+/// ```
+///
+/// ```typ
+/// #add(3, 4)
+/// ```
+#let add(a, b) = a + b
+
+  /// ```example
+  ///#add(5, 6)
+"#;
+        let fid = FileId::new(None, VirtualPath::new("src/lib.typ"));
+        let examples = doc_examples(&Source::new(fid, text.to_owned()));
+        assert_eq!(examples.len(), 1);
+        assert_eq!(examples[0].name, "src/lib.typ:3");
+        assert_eq!(examples[0].line, 2);
+        assert_eq!(examples[0].code, "#add(1, 2)\n");
+        assert_eq!(examples[0].indents, vec![4]);
+    }
+}
@@ -0,0 +1,355 @@
+//! Previews and reverts the workspace edits of large refactors, e.g. renames
+//! across files.
+
+use std::fmt::Write;
+
+use lsp_types::{
+    DeleteFile, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, RenameFile, ResourceOp, TextDocumentEdit,
+};
+
+use crate::prelude::*;
+use crate::url_to_path;
+
+/// A change to the workspace in the order of application.
+enum Change {
+    Edit(Url, Vec<TextEdit>),
+    Op(ResourceOp),
+}
+
+/// Flattens a workspace edit into the changes in the order of application.
+fn changes_of(edit: &WorkspaceEdit) -> Vec<Change> {
+    let mut changes = vec![];
+    if let Some(edits) = &edit.changes {
+        let mut edits = edits.iter().collect::<Vec<_>>();
+        edits.sort_by(|x, y| x.0.cmp(y.0));
+        for (uri, edits) in edits {
+            changes.push(Change::Edit(uri.clone(), edits.clone()));
+        }
+    }
+
+    let text_edits = |edit: &TextDocumentEdit| {
+        let edits = edit.edits.iter().map(|edit| match edit {
+            OneOf::Left(edit) => edit.clone(),
+            OneOf::Right(edit) => edit.text_edit.clone(),
+        });
+        Change::Edit(edit.text_document.uri.clone(), edits.collect())
+    };
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => changes.extend(edits.iter().map(text_edits)),
+        Some(DocumentChanges::Operations(ops)) => {
+            for op in ops {
+                changes.push(match op {
+                    DocumentChangeOperation::Edit(edit) => text_edits(edit),
+                    DocumentChangeOperation::Op(op) => Change::Op(op.clone()),
+                });
+            }
+        }
+        None => {}
+    }
+
+    changes
+}
+
+/// Converts the text edits of a document into byte ranges sorted by their
+/// starts, or `None` if any range is invalid or the ranges overlap.
+fn byte_edits(
+    source: &Source,
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+) -> Option<Vec<(Range<usize>, String)>> {
+    let mut byte_edits = edits
+        .iter()
+        .map(|edit| {
+            let range = to_typst_range(edit.range, encoding, source)?;
+            Some((range, edit.new_text.clone()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    byte_edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+    let overlaps = byte_edits.windows(2).any(|w| w[0].0.end > w[1].0.start);
+    (!overlaps).then_some(byte_edits)
+}
+
+/// Applies the text edits to a document, or returns `None` if the edits are
+/// invalid.
+pub fn apply_text_edits(
+    source: &Source,
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+) -> Option<String> {
+    let text = source.text();
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (range, new_text) in byte_edits(source, edits, encoding)? {
+        out.push_str(&text[cursor..range.start]);
+        out.push_str(&new_text);
+        cursor = range.end;
+    }
+    out.push_str(&text[cursor..]);
+    Some(out)
+}
+
+/// Renders a workspace edit as a unified diff, to be reviewed before the edit
+/// is applied.
+///
+/// The `read` function reads the current content of a document.
+pub fn preview_workspace_edit(
+    edit: &WorkspaceEdit,
+    encoding: PositionEncoding,
+    read: &dyn Fn(&Url) -> Option<Source>,
+) -> String {
+    let mut out = String::new();
+    for change in changes_of(edit) {
+        let _ = match change {
+            Change::Edit(uri, edits) => match read(&uri) {
+                Some(source) => write_diff(&mut out, &uri, &source, &edits, encoding),
+                None => writeln!(out, "# cannot read {uri}"),
+            },
+            Change::Op(ResourceOp::Create(op)) => writeln!(out, "# create {}", op.uri),
+            Change::Op(ResourceOp::Rename(op)) => {
+                writeln!(out, "# rename {} -> {}", op.old_uri, op.new_uri)
+            }
+            Change::Op(ResourceOp::Delete(op)) => writeln!(out, "# delete {}", op.uri),
+        };
+    }
+    out
+}
+
+/// Writes the hunks of the text edits to a document.
+fn write_diff(
+    out: &mut String,
+    uri: &Url,
+    source: &Source,
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+) -> std::fmt::Result {
+    let Some(edits) = byte_edits(source, edits, encoding) else {
+        return writeln!(out, "# invalid edits to {uri}");
+    };
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    let text = source.text();
+    let line_of = |byte: usize| source.byte_to_line(byte).unwrap_or(source.len_lines() - 1);
+
+    // Groups the edits touching the same or adjacent lines into hunks.
+    let mut hunks: Vec<(usize, usize, Vec<(Range<usize>, String)>)> = vec![];
+    for (range, new_text) in edits {
+        let (start, end) = (line_of(range.start), line_of(range.end));
+        match hunks.last_mut() {
+            Some((_, last, group)) if start <= *last + 1 => {
+                *last = (*last).max(end);
+                group.push((range, new_text));
+            }
+            _ => hunks.push((start, end, vec![(range, new_text)])),
+        }
+    }
+
+    writeln!(out, "--- {uri}")?;
+    writeln!(out, "+++ {uri}")?;
+    let mut delta = 0isize;
+    for (start, end, group) in hunks {
+        let old_start = source.line_to_byte(start).unwrap_or(text.len());
+        let old_end = source.line_to_byte(end + 1).unwrap_or(text.len());
+        let old = &text[old_start..old_end];
+
+        let mut new = String::new();
+        let mut cursor = old_start;
+        for (range, new_text) in group {
+            new.push_str(&text[cursor..range.start]);
+            new.push_str(&new_text);
+            cursor = range.end;
+        }
+        new.push_str(&text[cursor..old_end]);
+
+        let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+        let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+        let new_start = (start as isize + delta).max(0) as usize;
+        writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            start + 1,
+            old_lines.len(),
+            new_start + 1,
+            new_lines.len()
+        )?;
+        for line in &old_lines {
+            writeln!(out, "-{}", line.trim_end_matches(['\r', '\n']))?;
+        }
+        for line in &new_lines {
+            writeln!(out, "+{}", line.trim_end_matches(['\r', '\n']))?;
+        }
+        delta += new_lines.len() as isize - old_lines.len() as isize;
+    }
+
+    Ok(())
+}
+
+/// Applies a workspace edit to the files on disk, e.g. when refactoring from
+/// the command line without an editor.
+pub fn apply_workspace_edit_on_disk(
+    edit: &WorkspaceEdit,
+    encoding: PositionEncoding,
+) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    for change in changes_of(edit) {
+        match change {
+            Change::Edit(uri, edits) => {
+                let path = url_to_path(uri);
+                let source = Source::detached(std::fs::read_to_string(&path)?);
+                let text = apply_text_edits(&source, &edits, encoding).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid edits to {path:?}"),
+                    )
+                })?;
+                std::fs::write(&path, text)?;
+            }
+            Change::Op(ResourceOp::Create(op)) => {
+                let path = url_to_path(op.uri);
+                let options = op.options.unwrap_or_default();
+                if path.exists() && !options.overwrite.unwrap_or(false) {
+                    if options.ignore_if_exists.unwrap_or(false) {
+                        continue;
+                    }
+                    return Err(Error::new(ErrorKind::AlreadyExists, format!("{path:?}")));
+                }
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, "")?;
+            }
+            Change::Op(ResourceOp::Rename(op)) => {
+                let (from, to) = (url_to_path(op.old_uri), url_to_path(op.new_uri));
+                if let Some(dir) = to.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::rename(from, to)?;
+            }
+            Change::Op(ResourceOp::Delete(op)) => {
+                let path = url_to_path(op.uri);
+                let recursive = op.options.and_then(|options| options.recursive);
+                if path.is_dir() && recursive.unwrap_or(false) {
+                    std::fs::remove_dir_all(path)?;
+                } else if path.is_dir() {
+                    std::fs::remove_dir(path)?;
+                } else {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the workspace edit reverting an edit, which is applied after the
+/// edit to undo it. Returns `None` if the edit cannot be reverted, e.g. it
+/// deletes files.
+///
+/// The `read` function reads the content of a document before the edit.
+pub fn revert_workspace_edit(
+    edit: &WorkspaceEdit,
+    encoding: PositionEncoding,
+    read: &dyn Fn(&Url) -> Option<Source>,
+) -> Option<WorkspaceEdit> {
+    let mut ops = vec![];
+    for change in changes_of(edit) {
+        ops.push(match change {
+            Change::Edit(uri, edits) => {
+                let source = read(&uri)?;
+                let new_text = apply_text_edits(&source, &edits, encoding)?;
+                let new_source = Source::detached(new_text);
+                let range = to_lsp_range(0..new_source.len_bytes(), &new_source, encoding);
+                DocumentChangeOperation::Edit(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                    edits: vec![OneOf::Left(TextEdit {
+                        range,
+                        new_text: source.text().to_owned(),
+                    })],
+                })
+            }
+            Change::Op(ResourceOp::Create(op)) => {
+                DocumentChangeOperation::Op(ResourceOp::Delete(DeleteFile {
+                    uri: op.uri,
+                    options: None,
+                }))
+            }
+            Change::Op(ResourceOp::Rename(op)) => {
+                DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
+                    old_uri: op.new_uri,
+                    new_uri: op.old_uri,
+                    options: None,
+                    annotation_id: None,
+                }))
+            }
+            Change::Op(ResourceOp::Delete(..)) => return None,
+        });
+    }
+    ops.reverse();
+
+    Some(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(ops)),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_at(range: (u32, u32, u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: LspRange::new(
+                LspPosition::new(range.0, range.1),
+                LspPosition::new(range.2, range.3),
+            ),
+            new_text: new_text.into(),
+        }
+    }
+
+    #[test]
+    fn preview_and_revert() {
+        let uri = Url::parse("file:///main.typ").unwrap();
+        let source = Source::detached("#let x = 1\n#x\n\n#x\n");
+        let edit = WorkspaceEdit {
+            changes: Some(HashMap::from_iter([(
+                uri.clone(),
+                vec![
+                    edit_at((0, 5, 0, 6), "y"),
+                    edit_at((1, 1, 1, 2), "y"),
+                    edit_at((3, 1, 3, 2), "y"),
+                ],
+            )])),
+            ..Default::default()
+        };
+        let read = |_: &Url| Some(source.clone());
+
+        let preview = preview_workspace_edit(&edit, PositionEncoding::Utf16, &read);
+        assert_eq!(
+            preview,
+            "--- file:///main.typ\n+++ file:///main.typ\n\
+             @@ -1,2 +1,2 @@\n-#let x = 1\n-#x\n+#let y = 1\n+#y\n\
+             @@ -4,1 +4,1 @@\n-#x\n+#y\n"
+        );
+
+        let revert = revert_workspace_edit(&edit, PositionEncoding::Utf16, &read).unwrap();
+        let edited = Source::detached("#let y = 1\n#y\n\n#y\n");
+        let Some(DocumentChanges::Operations(ops)) = &revert.document_changes else {
+            panic!("expected operations");
+        };
+        let DocumentChangeOperation::Edit(revert) = &ops[0] else {
+            panic!("expected an edit");
+        };
+        let edits = revert.edits.iter().map(|edit| match edit {
+            OneOf::Left(edit) => edit.clone(),
+            OneOf::Right(edit) => edit.text_edit.clone(),
+        });
+        let reverted =
+            apply_text_edits(&edited, &edits.collect::<Vec<_>>(), PositionEncoding::Utf16);
+        assert_eq!(reverted.as_deref(), Some(source.text()));
+    }
+}
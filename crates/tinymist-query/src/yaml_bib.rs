@@ -0,0 +1,532 @@
+//! Completion and validation of the Hayagriva YAML bibliographies.
+
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use ecow::{eco_format, EcoString, EcoVec};
+use hayagriva::types::{Date, EntryType};
+use regex::Regex;
+use typst::diag::SourceDiagnostic;
+use typst::syntax::{Source, Span};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use crate::completion::{CompletionItem, CompletionKind, CompletionList};
+
+/// The fields of an entry, along with their descriptions.
+const ENTRY_FIELDS: &[(&str, &str)] = &[
+    ("type", "The type of the item, e.g. `article` or `book`."),
+    ("title", "The title of the item."),
+    (
+        "author",
+        "The persons primarily responsible for creating the item.",
+    ),
+    (
+        "date",
+        "The date of publication, e.g. `2024`, `2024-03` or `2024-03-15`.",
+    ),
+    (
+        "parent",
+        "The items containing the item, e.g. a journal or proceedings.",
+    ),
+    (
+        "editor",
+        "The persons selecting and revising the content of the item.",
+    ),
+    (
+        "affiliated",
+        "The persons involved in other roles, e.g. translators.",
+    ),
+    ("publisher", "The publisher of the item."),
+    (
+        "location",
+        "The location at which the item was published or took place.",
+    ),
+    (
+        "organization",
+        "The organization at or for which the item was created.",
+    ),
+    (
+        "issue",
+        "The position of the item in the issues of its parent.",
+    ),
+    ("volume", "The volume of the item in its parent."),
+    ("volume-total", "The total number of volumes of the item."),
+    ("edition", "The published version of the item."),
+    (
+        "page-range",
+        "The range of pages within the parent, e.g. `12-34`.",
+    ),
+    ("page-total", "The total number of pages of the item."),
+    (
+        "time-range",
+        "The time range within the parent, e.g. `00:01-02:30`.",
+    ),
+    ("runtime", "The total runtime of the item."),
+    (
+        "url",
+        "The canonical public URL of the item, with an optional access date.",
+    ),
+    (
+        "serial-number",
+        "The serial numbers of the item, e.g. its `doi` or `isbn`.",
+    ),
+    ("language", "The language of the item, e.g. `en-US`."),
+    (
+        "archive",
+        "The institution or collection where the item is kept.",
+    ),
+    ("archive-location", "The location of the archive."),
+    ("call-number", "The call number of the item in the archive."),
+    (
+        "note",
+        "An additional description appended to the reference.",
+    ),
+    ("abstract", "The abstract of the item."),
+    (
+        "genre",
+        "The type, class or subtype of the item, e.g. `Doctoral dissertation`.",
+    ),
+];
+
+/// The aliases of the entry fields.
+const ENTRY_FIELD_ALIASES: &[&str] = &["serial"];
+
+/// The types of the entries.
+const ENTRY_TYPES: &[&str] = &[
+    "article",
+    "anthology",
+    "anthos",
+    "artwork",
+    "audio",
+    "blog",
+    "book",
+    "case",
+    "chapter",
+    "conference",
+    "entry",
+    "exhibition",
+    "legislation",
+    "manuscript",
+    "misc",
+    "newspaper",
+    "original",
+    "patent",
+    "performance",
+    "periodical",
+    "post",
+    "proceedings",
+    "reference",
+    "report",
+    "repository",
+    "scene",
+    "thesis",
+    "thread",
+    "video",
+    "web",
+];
+
+/// The fields of a person.
+const PERSON_FIELDS: &[(&str, &str)] = &[
+    ("name", "The family name."),
+    ("given-name", "The given name."),
+    (
+        "prefix",
+        "The prefix of the family name, e.g. `van` or `de`.",
+    ),
+    ("suffix", "The suffix of the family name, e.g. `Jr.`."),
+    ("alias", "Another name the person is known under."),
+];
+
+/// The fields of the persons in the `affiliated` field.
+const AFFILIATED_FIELDS: &[(&str, &str)] = &[
+    (
+        "role",
+        "The role the persons had in the creation of the item.",
+    ),
+    ("names", "The persons."),
+];
+
+/// The roles of the persons in the `affiliated` field.
+const PERSON_ROLES: &[&str] = &[
+    "afterword",
+    "annotator",
+    "cast-member",
+    "cinematography",
+    "collaborator",
+    "commentator",
+    "compiler",
+    "composer",
+    "director",
+    "executive-producer",
+    "foreword",
+    "founder",
+    "holder",
+    "illustrator",
+    "introduction",
+    "narrator",
+    "organizer",
+    "producer",
+    "translator",
+    "writer",
+];
+
+/// Matches a key being typed, e.g. `  - given-na`.
+static KEY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*(?:-\s+)?)([\w-]*)$").unwrap());
+/// Matches a value being typed, e.g. `  type: art`.
+static VALUE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:-\s+)?([\w-]+):\s*([\w-]*)$").unwrap());
+/// Matches a line with a key, e.g. `  - name: Doe`.
+static LINE_KEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*(?:-\s+)?)([^\s:#][^:#]*?)\s*:(?:\s|$)").unwrap());
+/// Matches the fields only written in the entries of a bibliography.
+static BIB_FIELD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]+(?:type|title|author|date|parent):").unwrap());
+
+/// Checks whether a YAML file looks like a Hayagriva bibliography, i.e. it
+/// has the fields only written in the entries of a bibliography.
+fn is_yaml_bib(text: &str) -> bool {
+    BIB_FIELD_RE.is_match(text)
+}
+
+/// Completes the field names and the values of the enumerated fields in a
+/// Hayagriva bibliography at the cursor, which is a byte offset.
+pub fn yaml_bib_completion(text: &str, cursor: usize) -> Option<CompletionList> {
+    if !is_yaml_bib(text) {
+        return None;
+    }
+
+    let line_start = text[..cursor].rfind('\n').map_or(0, |idx| idx + 1);
+    let line = &text[line_start..cursor];
+
+    let items = if let Some(caps) = VALUE_RE.captures(line) {
+        let values = match &caps[1] {
+            "type" => ENTRY_TYPES,
+            "role" => PERSON_ROLES,
+            _ => return None,
+        };
+        let kind = CompletionKind::Constant;
+        values.iter().map(|value| item(value, None, kind)).collect()
+    } else if let Some(caps) = KEY_RE.captures(line) {
+        let column = caps[1].chars().count();
+        // The keys at the top level are the keys of the entries.
+        if column == 0 {
+            return None;
+        }
+
+        let lines = text[..line_start].lines().rev();
+        let mut siblings = vec![];
+        let mut item_started = false;
+        let mut fields = None;
+        for prev in lines {
+            let Some(caps) = LINE_KEY_RE.captures(prev) else {
+                continue;
+            };
+            let prev_column = caps[1].chars().count();
+            let key = &caps[2];
+            if prev_column == column && !item_started {
+                siblings.push(key.to_owned());
+                // The current item of the sequence starts here.
+                item_started = caps[1].contains('-');
+            }
+            if prev_column < column {
+                fields = match key {
+                    _ if prev_column == 0 => Some(ENTRY_FIELDS),
+                    "parent" => Some(ENTRY_FIELDS),
+                    "author" | "editor" | "names" => Some(PERSON_FIELDS),
+                    "affiliated" => Some(AFFILIATED_FIELDS),
+                    _ => None,
+                };
+                break;
+            }
+        }
+
+        let kind = CompletionKind::Field;
+        let fields = fields?
+            .iter()
+            .filter(|(name, _)| !siblings.iter().any(|it| it == *name));
+        fields
+            .map(|(name, docs)| item(name, Some(docs), kind))
+            .collect()
+    } else {
+        return None;
+    };
+
+    Some(CompletionList {
+        is_incomplete: false,
+        items,
+    })
+}
+
+fn item(label: &str, detail: Option<&str>, kind: CompletionKind) -> CompletionItem {
+    let insert_text = (kind == CompletionKind::Field).then(|| eco_format!("{label}: "));
+    CompletionItem {
+        label: label.into(),
+        kind,
+        detail: detail.map(EcoString::from),
+        insert_text,
+        ..CompletionItem::default()
+    }
+}
+
+/// Checks a Hayagriva bibliography, reporting the unknown fields, the unknown
+/// entry types and the malformed dates.
+pub(crate) fn check_yaml_bib(source: &Source) -> EcoVec<SourceDiagnostic> {
+    let text = source.text();
+    let mut checker = YamlBibChecker::default();
+    let mut parser = Parser::new(text.chars());
+    let error = parser.load(&mut checker, false).err();
+
+    let mut diags = EcoVec::new();
+    if !checker.typed {
+        return diags;
+    }
+
+    // Resolves byte offsets because yaml2 only provides char indices.
+    let offsets = text
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(Some(text.len()))
+        .collect::<Vec<_>>();
+    let span = |range: Range<usize>| {
+        let start = offsets[range.start.min(offsets.len() - 1)];
+        let end = offsets[range.end.min(offsets.len() - 1)];
+        Span::from_range(source.id(), start..end)
+    };
+
+    for (range, msg, hint) in checker.diags {
+        let diag = SourceDiagnostic::warning(span(range), msg);
+        diags.push(match hint {
+            Some(hint) => diag.with_hint(hint),
+            None => diag,
+        });
+    }
+    if let Some(error) = error {
+        let idx = error.marker().index();
+        let msg = eco_format!("failed to parse bibliography: {}", error.info());
+        diags.push(SourceDiagnostic::error(span(idx..idx), msg));
+    }
+
+    diags
+}
+
+/// A mapping or a sequence containing the current event.
+enum YamlNode {
+    Map {
+        /// Whether the mapping is an entry.
+        entry: bool,
+        /// The current key and its range.
+        key: Option<(String, Range<usize>)>,
+        /// Whether the next scalar is a key.
+        expect_key: bool,
+        /// Whether the mapping has the `type` field.
+        typed: bool,
+    },
+    Seq {
+        /// Whether the items are entries, i.e. the parents of an entry.
+        entries: bool,
+    },
+}
+
+#[derive(Default)]
+struct YamlBibChecker {
+    stack: Vec<YamlNode>,
+    /// Whether any entry at the top level has a type.
+    typed: bool,
+    /// The diagnostics with char ranges.
+    diags: Vec<(Range<usize>, EcoString, Option<EcoString>)>,
+}
+
+impl YamlBibChecker {
+    /// Gets whether the values of the current mapping, or the items of the
+    /// current sequence, are entries.
+    fn contains_entries(&self) -> bool {
+        match self.stack.last() {
+            Some(YamlNode::Map { entry, key, .. }) => {
+                self.stack.len() == 1
+                    || (*entry && key.as_ref().is_some_and(|(key, _)| key == "parent"))
+            }
+            Some(YamlNode::Seq { entries }) => *entries,
+            None => false,
+        }
+    }
+
+    fn value_done(&mut self) {
+        if let Some(YamlNode::Map { expect_key, .. }) = self.stack.last_mut() {
+            *expect_key = true;
+        }
+    }
+
+    fn scalar(&mut self, value: String, range: Range<usize>) {
+        let top_level = self.stack.len() == 2;
+        let Some(YamlNode::Map {
+            entry,
+            key,
+            expect_key,
+            typed,
+        }) = self.stack.last_mut()
+        else {
+            return;
+        };
+
+        if *expect_key {
+            *expect_key = false;
+            if *entry {
+                let known = ENTRY_FIELDS.iter().any(|(name, _)| *name == value)
+                    || ENTRY_FIELD_ALIASES.contains(&value.as_str());
+                if !known {
+                    let msg = eco_format!("unknown field `{value}`");
+                    let hint = "the unknown fields are ignored by the bibliography";
+                    self.diags.push((range.clone(), msg, Some(hint.into())));
+                }
+                if value == "type" {
+                    *typed = true;
+                    self.typed |= top_level;
+                }
+            }
+            *key = Some((value, range));
+            return;
+        }
+
+        *expect_key = true;
+        if !*entry {
+            return;
+        }
+        match key.as_ref().map(|(key, _)| key.as_str()) {
+            Some("type") if EntryType::from_str(&value).is_err() => {
+                let msg = eco_format!("unknown entry type `{value}`");
+                let hint = eco_format!("the entry types are {}", ENTRY_TYPES.join(", "));
+                self.diags.push((range, msg, Some(hint)));
+            }
+            Some("date") => {
+                if let Err(err) = Date::from_str(&value) {
+                    let msg = eco_format!("malformed date `{value}`: {err}");
+                    let hint = "dates are written as `YYYY`, `YYYY-MM` or `YYYY-MM-DD`";
+                    self.diags.push((range, msg, Some(hint.into())));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for YamlBibChecker {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(..) => {
+                let entry = self.contains_entries();
+                self.stack.push(YamlNode::Map {
+                    entry,
+                    key: None,
+                    expect_key: true,
+                    typed: false,
+                });
+            }
+            Event::SequenceStart(..) => {
+                let entries = self.contains_entries() && self.stack.len() > 1;
+                self.stack.push(YamlNode::Seq { entries });
+            }
+            Event::MappingEnd => {
+                // The entries at the top level must have a type, while the
+                // parents inherit a default type from their children.
+                let top_level = self.stack.len() == 2;
+                if let Some(YamlNode::Map {
+                    entry: true,
+                    typed: false,
+                    ..
+                }) = self.stack.pop()
+                {
+                    if let Some(YamlNode::Map {
+                        key: Some((key, range)),
+                        ..
+                    }) = self.stack.last().filter(|_| top_level)
+                    {
+                        let msg = eco_format!("entry `{key}` has no type");
+                        let hint = "add a `type` field, e.g. `type: article`";
+                        self.diags.push((range.clone(), msg, Some(hint.into())));
+                    }
+                }
+                self.value_done();
+            }
+            Event::SequenceEnd => {
+                self.stack.pop();
+                self.value_done();
+            }
+            Event::Scalar(value, style, ..) => {
+                let quotes = match style {
+                    TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => 2,
+                    _ => 0,
+                };
+                let start = mark.index();
+                let range = start..start + value.chars().count() + quotes;
+                self.scalar(value, range);
+            }
+            Event::Alias(..) => self.value_done(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_bib_diagnostics() {
+        let text = r#"doe2020:
+  type: article
+  title: A Title
+  date: 2020-13
+  pages: 12-34
+  parent:
+    type: jornal
+    title: A Journal
+untyped:
+  title: Another Title
+"#;
+        let source = Source::detached(text);
+        let diags = check_yaml_bib(&source);
+        let diags = diags
+            .iter()
+            .map(|diag| {
+                let range = source.range(diag.span).unwrap();
+                (&text[range], diag.message.as_str())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(diags.len(), 4, "{diags:?}");
+        assert_eq!(diags[0].0, "2020-13");
+        assert!(diags[0].1.starts_with("malformed date `2020-13`"));
+        assert_eq!(diags[1], ("pages", "unknown field `pages`"));
+        assert_eq!(diags[2], ("jornal", "unknown entry type `jornal`"));
+        assert_eq!(diags[3], ("untyped", "entry `untyped` has no type"));
+
+        // Other YAML files are not checked.
+        let source = Source::detached("name: value\nitems:\n  - pages: 1\n");
+        assert!(check_yaml_bib(&source).is_empty());
+    }
+
+    #[test]
+    fn yaml_bib_completions() {
+        let labels = |text: &str| {
+            let items = yaml_bib_completion(text, text.len()).map(|list| list.items);
+            let items = items.into_iter().flatten();
+            items.map(|item| item.label.to_string()).collect::<Vec<_>>()
+        };
+
+        let entry = "doe2020:\n  type: article\n  title: A Title\n  ";
+        let fields = labels(entry);
+        assert!(fields.contains(&"page-range".to_owned()));
+        assert!(!fields.contains(&"title".to_owned()));
+        assert!(!fields.contains(&"given-name".to_owned()));
+
+        let person = "doe2020:\n  type: article\n  author:\n    - name: Doe\n      gi";
+        let fields = labels(person);
+        assert!(fields.contains(&"given-name".to_owned()));
+        assert!(!fields.contains(&"name".to_owned()));
+
+        let types = labels("doe2020:\n  type: art");
+        assert!(types.contains(&"article".to_owned()));
+        assert!(labels("name: value\nitems:\n  ").is_empty());
+    }
+}
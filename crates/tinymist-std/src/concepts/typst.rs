@@ -101,12 +101,75 @@ impl<'a> TryFrom<&'a TypstDocument> for &'a Arc<well_known::TypstHtmlDocument> {
     }
 }
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub use well_known::*;
 
+/// Makes unique anchors for the headings of a document from their plain-text
+/// titles, in the style of GitHub. For example, `Hello, World!` becomes
+/// `hello-world`, and a second `Hello, World!` becomes `hello-world-1`.
+#[derive(Debug, Default)]
+pub struct HeadingSlugs {
+    used: HashMap<String, usize>,
+}
+
+impl HeadingSlugs {
+    /// Makes the anchor of a heading from its title.
+    pub fn slug(&mut self, title: &str) -> String {
+        let mut slug = String::new();
+        for ch in title.trim().chars() {
+            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+                slug.extend(ch.to_lowercase());
+            } else if ch.is_whitespace() {
+                slug.push('-');
+            }
+        }
+        if slug.is_empty() {
+            slug.push_str("section");
+        }
+
+        self.unique(slug)
+    }
+
+    /// Makes an anchor unique by appending a number to it if it is used.
+    pub fn unique(&mut self, anchor: String) -> String {
+        let Some(mut count) = self.used.get(&anchor).copied() else {
+            self.used.insert(anchor.clone(), 1);
+            return anchor;
+        };
+
+        let unique = loop {
+            let candidate = format!("{anchor}-{count}");
+            count += 1;
+            if !self.used.contains_key(&candidate) {
+                break candidate;
+            }
+        };
+        self.used.insert(anchor, count);
+        self.used.insert(unique.clone(), 1);
+        unique
+    }
+}
+
 /// The prelude of the Typst module.
 pub mod prelude {
     pub use comemo::Prehashed;
     pub use ecow::{eco_format, eco_vec, EcoString, EcoVec};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HeadingSlugs;
+
+    #[test]
+    fn heading_slugs() {
+        let mut slugs = HeadingSlugs::default();
+        assert_eq!(slugs.slug("Hello, World!"), "hello-world");
+        assert_eq!(slugs.slug("Hello, World!"), "hello-world-1");
+        assert_eq!(slugs.slug("hello-world-2"), "hello-world-2");
+        assert_eq!(slugs.slug("Hello World"), "hello-world-3");
+        assert_eq!(slugs.slug("使用 Typst"), "使用-typst");
+        assert_eq!(slugs.slug("?!"), "section");
+    }
+}
@@ -6,6 +6,7 @@ pub mod fs;
 pub mod hash;
 pub mod path;
 pub mod time;
+pub mod xml;
 
 pub(crate) mod concepts;
 
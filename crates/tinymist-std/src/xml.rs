@@ -0,0 +1,9 @@
+//! XML utilities.
+
+/// Escapes a string to be used in the text or the quoted attributes of XML.
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -19,6 +19,12 @@ use crate::model::{ExportHtmlTask, ExportPngTask, ExportSvgTask};
 use crate::primitives::TaskWhen;
 use crate::{ExportTransform, Pages, QueryTask};
 
+pub mod html;
+pub use html::*;
+#[cfg(feature = "pdf")]
+pub mod image_opt;
+#[cfg(feature = "pdf")]
+pub use image_opt::*;
 #[cfg(feature = "pdf")]
 pub mod pdf;
 #[cfg(feature = "pdf")]
@@ -67,7 +73,7 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for SvgExport {
                 typst_svg::svg_merged(doc, merged_gap)
             }
         } else {
-            typst_svg::svg_merged(doc, merged_gap)
+            typst_svg::svg_merged(&select_pages(&config.export, doc), merged_gap)
         })
     }
 }
@@ -112,7 +118,8 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for PngExport {
                 typst_render::render_merged(doc, ppp, merged_gap, Some(fill))
             }
         } else {
-            typst_render::render_merged(doc, ppp, merged_gap, Some(fill))
+            let doc = select_pages(&config.export, doc);
+            typst_render::render_merged(&doc, ppp, merged_gap, Some(fill))
         };
 
         pixmap
@@ -139,9 +146,10 @@ impl<F: CompilerFeat> ExportComputation<F, TypstHtmlDocument> for HtmlExport {
     fn run(
         _graph: &Arc<WorldComputeGraph<F>>,
         doc: &Arc<TypstHtmlDocument>,
-        _config: &ExportHtmlTask,
+        config: &ExportHtmlTask,
     ) -> Result<String> {
-        Ok(typst_html::html(doc)?)
+        let (doc, _) = anchor_headings(doc, config.heading_anchors);
+        Ok(typst_html::html(&doc)?)
     }
 }
 
@@ -318,6 +326,50 @@ pub fn get_page_selection(task: &crate::ExportTask) -> Result<(bool, Abs)> {
     Ok((is_first, gap_res))
 }
 
+/// Gets the indices of the pages selected by the page ranges of a task, in the
+/// order of the document. All the pages are selected if there are no ranges.
+pub fn get_selected_pages(task: &crate::ExportTask, page_count: usize) -> Vec<usize> {
+    let ranges = task
+        .transform
+        .iter()
+        .filter_map(|t| match t {
+            ExportTransform::Pages { ranges } => Some(ranges),
+            _ => None,
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+
+    (0..page_count)
+        .filter(|idx| ranges.is_empty() || ranges.iter().any(|r| r.includes(idx + 1)))
+        .collect()
+}
+
+/// Keeps the pages of a document selected by the page ranges of a task.
+pub fn select_pages(task: &crate::ExportTask, doc: &TypstPagedDocument) -> TypstPagedDocument {
+    let mut selected = doc.clone();
+    selected.pages = get_selected_pages(task, doc.pages.len())
+        .into_iter()
+        .map(|idx| doc.pages[idx].clone())
+        .collect();
+    selected
+}
+
+/// Substitutes a page into a file name template, in which `{p}` is replaced by
+/// the one-indexed page number, `{0p}` by the page number zero-padded to the
+/// width of the page count, and `{t}` by the page count.
+pub fn page_file_name(template: &str, number: usize, page_count: usize) -> String {
+    let width = page_count.to_string().len();
+    template
+        .replace("{p}", &number.to_string())
+        .replace("{0p}", &format!("{number:0width$}"))
+        .replace("{t}", &page_count.to_string())
+}
+
+/// Whether a file name template contains a page number.
+pub fn has_page_number(template: &str) -> bool {
+    template.contains("{p}") || template.contains("{0p}")
+}
+
 fn parse_length(gap: &str) -> Result<Abs> {
     let length = typst::syntax::parse_code(gap);
     if length.erroneous() {
@@ -392,6 +444,28 @@ mod tests {
         assert!(parse_color("invalid".to_owned()).is_err());
     }
 
+    #[test]
+    fn test_page_file_name() {
+        assert_eq!(
+            page_file_name("page-{0p}-of-{t}.png", 2, 10),
+            "page-02-of-10.png"
+        );
+        assert_eq!(page_file_name("page-{p}.png", 2, 10), "page-2.png");
+        assert!(!has_page_number("page.png"));
+    }
+
+    #[test]
+    fn test_selected_pages() {
+        let task = crate::ExportTask {
+            transform: vec![ExportTransform::Pages {
+                ranges: vec!["2".parse().unwrap(), "5-7".parse().unwrap()],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(get_selected_pages(&task, 6), vec![1, 4, 5]);
+        assert_eq!(get_selected_pages(&Default::default(), 2), vec![0, 1]);
+    }
+
     #[test]
     fn test_parse_length() {
         assert_eq!(parse_length("1pt").unwrap(), Abs::pt(1.));
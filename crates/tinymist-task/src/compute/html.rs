@@ -0,0 +1,127 @@
+//! Adds anchors to the headings of the HTML documents, and checks that the
+//! internal links of the documents point to existing anchors.
+
+use std::collections::{HashMap, HashSet};
+
+use ecow::{eco_format, EcoVec};
+use tinymist_std::typst::{HeadingSlugs, TypstHtmlDocument};
+use typst::diag::SourceDiagnostic;
+use typst::foundations::NativeElement;
+use typst::html::{attr, tag, HtmlElement, HtmlNode};
+use typst::introspection::Introspector;
+use typst::model::HeadingElem;
+use typst::syntax::Span;
+
+use crate::model::HeadingAnchors;
+
+/// Computes the anchors of the headings in a document, in the order of the
+/// document.
+pub fn heading_anchors(introspector: &Introspector, scheme: HeadingAnchors) -> Vec<(Span, String)> {
+    if scheme == HeadingAnchors::None {
+        return vec![];
+    }
+
+    let mut slugs = HeadingSlugs::default();
+    let elements = introspector.query(&HeadingElem::elem().select());
+    elements
+        .iter()
+        .map(|elem| {
+            let label = elem.label().filter(|_| scheme == HeadingAnchors::Label);
+            let anchor = match label {
+                Some(label) => slugs.unique(label.resolve().to_string()),
+                None => {
+                    let heading = elem.to_packed::<HeadingElem>().unwrap();
+                    slugs.slug(&heading.body.plain_text())
+                }
+            };
+            (elem.span(), anchor)
+        })
+        .collect()
+}
+
+/// Adds the anchors to the headings of a document, and checks that the
+/// internal links of the document point to existing anchors.
+///
+/// Returns the document with the anchors, along with the warnings of the
+/// broken links.
+pub fn anchor_headings(
+    doc: &TypstHtmlDocument,
+    scheme: HeadingAnchors,
+) -> (TypstHtmlDocument, EcoVec<SourceDiagnostic>) {
+    let mut anchors = heading_anchors(&doc.introspector, scheme)
+        .into_iter()
+        .filter(|(span, _)| !span.is_detached())
+        .collect::<HashMap<_, _>>();
+
+    let mut doc = doc.clone();
+    if !anchors.is_empty() {
+        add_anchors(&mut doc.root, &mut anchors);
+    }
+
+    let mut ids = HashSet::new();
+    collect_ids(&doc.root, &mut ids);
+    let mut warnings = EcoVec::new();
+    check_links(&doc.root, &ids, &mut warnings);
+
+    (doc, warnings)
+}
+
+/// Adds the anchors to the outermost elements spanned by the headings.
+fn add_anchors(elem: &mut HtmlElement, anchors: &mut HashMap<Span, String>) {
+    let has_id = elem.attrs.0.iter().any(|(name, _)| *name == attr::id);
+    if !has_id {
+        if let Some(anchor) = anchors.remove(&elem.span) {
+            elem.attrs.push(attr::id, anchor);
+        }
+    }
+
+    for child in &mut elem.children {
+        if let HtmlNode::Element(child) = child {
+            add_anchors(child, anchors);
+        }
+    }
+}
+
+fn collect_ids(elem: &HtmlElement, ids: &mut HashSet<String>) {
+    for (name, value) in elem.attrs.0.iter() {
+        if *name == attr::id {
+            ids.insert(value.to_string());
+        }
+    }
+
+    for child in &elem.children {
+        if let HtmlNode::Element(child) = child {
+            collect_ids(child, ids);
+        }
+    }
+}
+
+/// Checks that the links to the fragments point to existing anchors.
+fn check_links(elem: &HtmlElement, ids: &HashSet<String>, warnings: &mut EcoVec<SourceDiagnostic>) {
+    if elem.tag == tag::a {
+        let href = elem.attrs.0.iter().find(|(name, _)| *name == attr::href);
+        match href.map(|(_, value)| value.as_str()) {
+            Some(href) => {
+                if let Some(fragment) = href.strip_prefix('#') {
+                    if !fragment.is_empty() && !ids.contains(fragment) {
+                        let msg = eco_format!("link to `{href}` has no matching anchor");
+                        warnings.push(SourceDiagnostic::warning(elem.span, msg));
+                    }
+                }
+            }
+            // Typst drops the internal destinations, e.g. `link(<label>)`.
+            None if !elem.span.is_detached() => {
+                let msg = "internal link is lost in the HTML export";
+                let hint = "link to the anchor of the heading instead, e.g. `link(\"#anchor\")`";
+                warnings.push(SourceDiagnostic::warning(elem.span, msg).with_hint(hint));
+            }
+            None => {}
+        }
+    }
+
+    for child in &elem.children {
+        if let HtmlNode::Element(child) = child {
+            check_links(child, ids, warnings);
+        }
+    }
+}
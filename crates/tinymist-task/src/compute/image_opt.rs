@@ -0,0 +1,193 @@
+//! Downscales the raster images that are rendered at a higher resolution than
+//! needed, e.g. photos taken by a camera, before embedding them into a PDF.
+//!
+//! The images are replaced in the laid out document only, so the original
+//! files are kept for editing.
+
+use std::collections::HashMap;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use tinymist_std::typst::TypstPagedDocument;
+use typst::foundations::{Bytes, Smart};
+use typst::layout::{Frame, FrameItem, Size, Transform};
+use typst::visualize::{ExchangeFormat, Image, ImageKind, ImageScaling, RasterFormat, RasterImage};
+
+/// The quality of the recompressed JPEG images.
+const JPEG_QUALITY: u8 = 90;
+
+/// The savings of downscaling an image.
+#[derive(Debug, Clone)]
+pub struct ImageSaving {
+    /// The one-indexed number of the first page showing the image.
+    pub page: usize,
+    /// The size of the original image in pixels.
+    pub original: (u32, u32),
+    /// The size of the downscaled image in pixels.
+    pub optimized: (u32, u32),
+    /// The resolution of the original image at its largest size in the
+    /// document, in dots per inch.
+    pub dpi: f64,
+    /// The length of the original image data in bytes.
+    pub original_len: usize,
+    /// The length of the downscaled image data in bytes.
+    pub optimized_len: usize,
+}
+
+/// Downscales the raster images whose resolution exceeds `max_dpi` at their
+/// largest size in the document, and returns the document with the images
+/// replaced, along with the savings per image.
+///
+/// The images are kept if recompressing them doesn't make them smaller.
+pub fn optimize_images(
+    doc: &TypstPagedDocument,
+    max_dpi: u32,
+) -> (TypstPagedDocument, Vec<ImageSaving>) {
+    // Finds the largest size of each image, since an image shown at several
+    // sizes is embedded once.
+    let mut uses = HashMap::<Image, (usize, Size)>::new();
+    for (idx, page) in doc.pages.iter().enumerate() {
+        collect_images(&page.frame, Transform::identity(), idx + 1, &mut uses);
+    }
+
+    let mut replaced = HashMap::new();
+    let mut savings = vec![];
+    for (image, (page, size)) in uses {
+        let ImageKind::Raster(raster) = image.kind() else {
+            continue;
+        };
+        if image.scaling() == Smart::Custom(ImageScaling::Pixelated) {
+            continue;
+        }
+
+        let (width, height) = (raster.width(), raster.height());
+        let (inch_x, inch_y) = (size.x.to_inches(), size.y.to_inches());
+        if inch_x <= 0. || inch_y <= 0. {
+            continue;
+        }
+        let dpi = (width as f64 / inch_x).max(height as f64 / inch_y);
+        if dpi <= max_dpi as f64 {
+            continue;
+        }
+
+        let target_width = ((inch_x * max_dpi as f64).ceil() as u32).clamp(1, width);
+        let target_height = ((inch_y * max_dpi as f64).ceil() as u32).clamp(1, height);
+        let Some(optimized) = downscale(&image, target_width, target_height) else {
+            continue;
+        };
+        let ImageKind::Raster(optimized_raster) = optimized.kind() else {
+            continue;
+        };
+
+        savings.push(ImageSaving {
+            page,
+            original: (width, height),
+            optimized: (target_width, target_height),
+            dpi,
+            original_len: raster.data().len(),
+            optimized_len: optimized_raster.data().len(),
+        });
+        replaced.insert(image, optimized);
+    }
+    savings.sort_by_key(|saving| saving.page);
+
+    let mut doc = doc.clone();
+    if !replaced.is_empty() {
+        for page in &mut doc.pages {
+            page.frame = replace_images(&page.frame, &replaced);
+        }
+    }
+
+    (doc, savings)
+}
+
+/// Collects the raster images of a frame along with their largest sizes.
+fn collect_images(
+    frame: &Frame,
+    ts: Transform,
+    page: usize,
+    uses: &mut HashMap<Image, (usize, Size)>,
+) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => {
+                let ts = ts.pre_concat(group.transform);
+                collect_images(&group.frame, ts, page, uses);
+            }
+            FrameItem::Image(image, size, _) if matches!(image.kind(), ImageKind::Raster(..)) => {
+                // The size of the image after the transformations, e.g. the
+                // image may be scaled or rotated.
+                let scale_x = ts.sx.get().hypot(ts.ky.get());
+                let scale_y = ts.kx.get().hypot(ts.sy.get());
+                let size = Size::new(size.x * scale_x, size.y * scale_y);
+
+                let used = uses.entry(image.clone()).or_insert((page, size));
+                used.1 = used.1.max(size);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the images in a frame.
+fn replace_images(frame: &Frame, replaced: &HashMap<Image, Image>) -> Frame {
+    let mut res = frame.clone();
+    res.clear();
+    for (pos, item) in frame.items() {
+        let item = match item {
+            FrameItem::Group(group) => {
+                let mut group = group.clone();
+                group.frame = replace_images(&group.frame, replaced);
+                FrameItem::Group(group)
+            }
+            FrameItem::Image(image, size, span) => match replaced.get(image) {
+                Some(optimized) => FrameItem::Image(optimized.clone(), *size, *span),
+                None => item.clone(),
+            },
+            _ => item.clone(),
+        };
+        res.push(*pos, item);
+    }
+    res
+}
+
+/// Downscales an image to the given size and recompresses it, returning
+/// `None` if the result is not smaller than the original data.
+///
+/// The JPEG images are kept as JPEG, and the other images are encoded as PNG
+/// to avoid compression artifacts in diagrams and screenshots.
+#[comemo::memoize]
+fn downscale(image: &Image, width: u32, height: u32) -> Option<Image> {
+    let ImageKind::Raster(raster) = image.kind() else {
+        return None;
+    };
+
+    let resized = raster
+        .dynamic()
+        .resize_exact(width, height, FilterType::Lanczos3);
+    let is_jpeg = raster.format() == RasterFormat::Exchange(ExchangeFormat::Jpg);
+
+    let mut data = vec![];
+    let format = if is_jpeg && !resized.color().has_alpha() {
+        let rgb = DynamicImage::ImageRgb8(resized.to_rgb8());
+        let encoder = JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY);
+        rgb.write_with_encoder(encoder).ok()?;
+        ExchangeFormat::Jpg
+    } else {
+        resized
+            .write_with_encoder(PngEncoder::new(&mut data))
+            .ok()?;
+        ExchangeFormat::Png
+    };
+
+    if data.len() >= raster.data().len() {
+        return None;
+    }
+
+    let icc = raster.icc().cloned().map_or(Smart::Auto, Smart::Custom);
+    let optimized = RasterImage::new(Bytes::new(data), format, icc).ok()?;
+    let alt = image.alt().map(From::from);
+    Some(Image::new(optimized, alt, image.scaling()))
+}
@@ -1,4 +1,7 @@
+use ecow::EcoVec;
 use tinymist_std::time::ToUtcDateTime;
+use typst::diag::SourceDiagnostic;
+use typst::layout::{Frame, FrameItem};
 pub use typst_pdf::pdf;
 pub use typst_pdf::PdfStandard as TypstPdfStandard;
 
@@ -27,6 +30,7 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for PdfExport {
         // timezone information.
         let timestamp = Timestamp::new_utc(tinymist_std::time::to_typst_time(creation_timestamp));
 
+        config.check_standards()?;
         let standards = PdfStandards::new(
             &config
                 .pdf_standards
@@ -40,6 +44,19 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for PdfExport {
         )
         .context_ut("prepare pdf standards")?;
 
+        let optimized;
+        let doc = match config.image_max_dpi {
+            Some(max_dpi) => {
+                let savings;
+                (optimized, savings) = optimize_images(doc, max_dpi);
+                for saving in savings {
+                    log::info!("PdfExport: downscaled image {saving:?}");
+                }
+                &optimized
+            }
+            None => doc.as_ref(),
+        };
+
         // todo: Some(pdf_uri.as_str())
         // todo: ident option
         Ok(Bytes::new(typst_pdf::pdf(
@@ -52,3 +69,37 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for PdfExport {
         )?))
     }
 }
+
+/// Checks the accessibility requirements of a tagged PDF, i.e. that the
+/// document has a title and that its images have alternative texts.
+pub fn check_tagged_pdf(doc: &TypstPagedDocument) -> EcoVec<SourceDiagnostic> {
+    let mut warnings = EcoVec::new();
+    if doc.info.title.is_none() {
+        let msg = "document has no title";
+        let hint = "set the title with `set document(title: ..)`";
+        warnings.push(SourceDiagnostic::warning(Span::detached(), msg).with_hint(hint));
+    }
+
+    let mut spans = vec![];
+    for page in &doc.pages {
+        images_without_alt(&page.frame, &mut spans);
+    }
+    spans.dedup();
+    for span in spans {
+        let msg = "image has no alternative text";
+        let hint = "describe the image with `image(.., alt: ..)`";
+        warnings.push(SourceDiagnostic::warning(span, msg).with_hint(hint));
+    }
+
+    warnings
+}
+
+fn images_without_alt(frame: &Frame, spans: &mut Vec<Span>) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => images_without_alt(&group.frame, spans),
+            FrameItem::Image(image, _, span) if image.alt().is_none() => spans.push(*span),
+            _ => {}
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use core::fmt;
+use std::fmt::Write;
 use std::sync::Arc;
+use typst::foundations::Packed;
 use typst::html::{tag, HtmlNode::*};
+use typst::introspection::Tag as IntroTag;
+use typst::layout::{Abs, Frame, FrameItem, Point, Transform};
+use typst::model::HeadingElem;
+use typst::text::TextItem;
 
 use crate::ExportTextTask;
 use tinymist_std::error::prelude::*;
@@ -13,6 +19,58 @@ impl TextExport {
     pub fn run_on_doc(doc: &TypstDocument) -> Result<String> {
         Ok(format!("{}", FullTextDigest(doc)))
     }
+
+    /// Extracts the text of a document as [`Self::run_on_doc`] does, but split
+    /// at the headings.
+    pub fn sections_of_doc(doc: &TypstDocument) -> Vec<TextSection> {
+        let mut sections = TextSections(vec![TextSection::default()]);
+        let _ = FullTextDigest(doc).export(&mut sections);
+        sections.0
+    }
+}
+
+/// The text of a document from a heading to the next heading.
+#[derive(Debug, Clone, Default)]
+pub struct TextSection {
+    /// The heading starting the section, or `None` for the text before the
+    /// first heading.
+    pub heading: Option<Packed<HeadingElem>>,
+    /// The text of the section, including the heading.
+    pub text: String,
+}
+
+/// A writer of the text of a document, which is also told the tags of the
+/// elements.
+trait TextSink: Write {
+    fn tag(&mut self, _tag: &IntroTag) {}
+}
+
+impl TextSink for fmt::Formatter<'_> {}
+
+/// Collects the text of a document into the sections starting at the
+/// headings.
+struct TextSections(Vec<TextSection>);
+
+impl Write for TextSections {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(section) = self.0.last_mut() {
+            section.text.push_str(s);
+        }
+        Ok(())
+    }
+}
+
+impl TextSink for TextSections {
+    fn tag(&mut self, tag: &IntroTag) {
+        if let IntroTag::Start(elem) = tag {
+            if let Some(heading) = elem.to_packed::<HeadingElem>() {
+                self.0.push(TextSection {
+                    heading: Some(heading.clone()),
+                    text: String::new(),
+                });
+            }
+        }
+    }
 }
 
 impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for TextExport {
@@ -32,13 +90,12 @@ impl<F: CompilerFeat> ExportComputation<F, TypstPagedDocument> for TextExport {
 struct FullTextDigest<'a>(&'a TypstDocument);
 
 impl FullTextDigest<'_> {
-    fn export_frame(f: &mut fmt::Formatter<'_>, doc: &typst::layout::Frame) -> fmt::Result {
+    fn export_frame(f: &mut impl TextSink, doc: &typst::layout::Frame) -> fmt::Result {
         for (_, item) in doc.items() {
             Self::export_item(f, item)?;
         }
         #[cfg(not(feature = "no-content-hint"))]
         {
-            use std::fmt::Write;
             let c = doc.content_hint();
             if c != '\0' {
                 f.write_char(c)?;
@@ -48,25 +105,32 @@ impl FullTextDigest<'_> {
         Ok(())
     }
 
-    fn export_item(f: &mut fmt::Formatter<'_>, item: &typst::layout::FrameItem) -> fmt::Result {
+    fn export_item(f: &mut impl TextSink, item: &typst::layout::FrameItem) -> fmt::Result {
         use typst::layout::FrameItem::*;
         match item {
             Group(g) => Self::export_frame(f, &g.frame),
             Text(t) => f.write_str(t.text.as_str()),
-            Link(..) | Tag(..) | Shape(..) | Image(..) => Ok(()),
+            Tag(tag) => {
+                f.tag(tag);
+                Ok(())
+            }
+            Link(..) | Shape(..) | Image(..) => Ok(()),
         }
     }
 
-    fn export_element(f: &mut fmt::Formatter<'_>, elem: &typst::html::HtmlElement) -> fmt::Result {
+    fn export_element(f: &mut impl TextSink, elem: &typst::html::HtmlElement) -> fmt::Result {
         for child in elem.children.iter() {
             Self::export_html_node(f, child)?;
         }
         Ok(())
     }
 
-    fn export_html_node(f: &mut fmt::Formatter<'_>, node: &typst::html::HtmlNode) -> fmt::Result {
+    fn export_html_node(f: &mut impl TextSink, node: &typst::html::HtmlNode) -> fmt::Result {
         match node {
-            Tag(_) => Ok(()),
+            Tag(tag) => {
+                f.tag(tag);
+                Ok(())
+            }
             Element(elem) => {
                 // Skips certain tags that do not contribute to text content.
                 if matches!(elem.tag, tag::style | tag::script) {
@@ -79,14 +143,15 @@ impl FullTextDigest<'_> {
             Frame(frame) => Self::export_frame(f, frame),
         }
     }
-}
 
-impl fmt::Display for FullTextDigest<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn export(&self, f: &mut impl TextSink) -> fmt::Result {
         match &self.0 {
             TypstDocument::Paged(paged_doc) => {
-                for page in paged_doc.pages.iter() {
-                    Self::export_frame(f, &page.frame)?;
+                for (idx, page) in paged_doc.pages.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str("\n\n")?;
+                    }
+                    LayoutText::default().export_frame(f, &page.frame, Transform::identity())?;
                 }
                 Ok(())
             }
@@ -97,3 +162,90 @@ impl fmt::Display for FullTextDigest<'_> {
         }
     }
 }
+
+impl fmt::Display for FullTextDigest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.export(f)
+    }
+}
+
+/// The last text run written by [`LayoutText`].
+struct LastRun {
+    /// The horizontal end of the run.
+    end: Abs,
+    /// The vertical position of the baseline of the run.
+    baseline: Abs,
+    /// The font size of the run.
+    size: Abs,
+    /// Whether the run ends with a whitespace.
+    space: bool,
+}
+
+/// Writes the text of a page in reading order, breaking the lines by the
+/// positions of the text runs.
+///
+/// The frames are visited in the order of layout, which already reads the
+/// columns one after another and the table cells row by row. The runs on the
+/// same baseline are joined, with a tab for wide gaps, e.g. between the cells
+/// of a table row, and the runs far below the last line start a new paragraph.
+#[derive(Default)]
+struct LayoutText {
+    last: Option<LastRun>,
+}
+
+impl LayoutText {
+    fn export_frame(&mut self, f: &mut impl TextSink, frame: &Frame, ts: Transform) -> fmt::Result {
+        for (pos, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => {
+                    let ts = ts
+                        .pre_concat(Transform::translate(pos.x, pos.y))
+                        .pre_concat(group.transform);
+                    self.export_frame(f, &group.frame, ts)?;
+                }
+                FrameItem::Text(text) => self.export_text(f, text, pos.transform(ts))?,
+                FrameItem::Tag(tag) => f.tag(tag),
+                FrameItem::Link(..) | FrameItem::Shape(..) | FrameItem::Image(..) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn export_text(&mut self, f: &mut impl TextSink, text: &TextItem, pos: Point) -> fmt::Result {
+        let size = text.size;
+        if let Some(last) = &self.last {
+            let em = last.size.max(size);
+            let sep = if (pos.y - last.baseline).abs() < em * 0.3 {
+                let gap = pos.x - last.end;
+                if gap > em * 2.0 {
+                    "\t"
+                } else if gap < -em {
+                    "\n"
+                } else if gap > em * 0.15
+                    && !last.space
+                    && !text.text.starts_with(char::is_whitespace)
+                {
+                    " "
+                } else {
+                    ""
+                }
+            } else if pos.y > last.baseline && pos.y - last.baseline < em * 1.8 {
+                "\n"
+            } else {
+                "\n\n"
+            };
+            f.write_str(sep)?;
+        }
+
+        f.write_str(text.text.as_str())?;
+        self.last = Some(LastRun {
+            end: pos.x + text.width(),
+            baseline: pos.y,
+            size,
+            space: text.text.ends_with(char::is_whitespace),
+        });
+
+        Ok(())
+    }
+}
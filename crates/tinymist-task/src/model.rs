@@ -3,6 +3,7 @@
 use std::{hash::Hash, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
+use tinymist_std::{bail, Result};
 
 use super::{Id, Pages, PathPattern, PdfStandard, Scalar, TaskWhen};
 
@@ -74,6 +75,8 @@ pub enum ProjectTask {
     ExportMd(ExportMarkdownTask),
     /// An export TeX task.
     ExportTeX(ExportTeXTask),
+    /// An export DOCX task.
+    ExportDocx(ExportDocxTask),
     /// An export Text task.
     ExportText(ExportTextTask),
     /// An query task.
@@ -95,6 +98,7 @@ impl ProjectTask {
             | Self::ExportSvgHtml(..)
             | Self::ExportMd(..)
             | Self::ExportTeX(..)
+            | Self::ExportDocx(..)
             | Self::ExportText(..)
             | Self::Query(..) => &self.as_export()?.when,
         })
@@ -111,11 +115,21 @@ impl ProjectTask {
             Self::ExportSvgHtml(task) => &task.export,
             Self::ExportTeX(task) => &task.export,
             Self::ExportMd(task) => &task.export,
+            Self::ExportDocx(task) => &task.export,
             Self::ExportText(task) => &task.export,
             Self::Query(task) => &task.export,
         })
     }
 
+    /// Returns the template of the file names to export one file per page.
+    pub fn page_template(&self) -> Option<&str> {
+        match self {
+            Self::ExportPng(task) => task.page_template.as_deref(),
+            Self::ExportSvg(task) => task.page_template.as_deref(),
+            _ => None,
+        }
+    }
+
     /// Returns extension of the artifact.
     pub fn extension(&self) -> &str {
         match self {
@@ -123,6 +137,7 @@ impl ProjectTask {
             Self::Preview(..) | Self::ExportSvgHtml { .. } | Self::ExportHtml { .. } => "html",
             Self::ExportMd { .. } => "md",
             Self::ExportTeX { .. } => "tex",
+            Self::ExportDocx { .. } => "docx",
             Self::ExportText { .. } => "txt",
             Self::ExportSvg { .. } => "svg",
             Self::ExportPng { .. } => "png",
@@ -235,6 +250,34 @@ pub struct ExportPdfTask {
     /// For more information, see <https://reproducible-builds.org/specs/source-date-epoch/>.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub creation_timestamp: Option<i64>,
+    /// The maximum resolution of the raster images in dots per inch. The
+    /// images exceeding it are downscaled and recompressed before embedding,
+    /// while the original files are kept.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_max_dpi: Option<u32>,
+    /// Whether to export a tagged PDF for accessibility, which checks that the
+    /// document has a title and that its images have alternative texts.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub tagged: bool,
+}
+
+impl ExportPdfTask {
+    /// Checks that the PDF standards of the task can be enforced together.
+    pub fn check_standards(&self) -> Result<()> {
+        let mut parts = self
+            .pdf_standards
+            .iter()
+            .filter(|standard| matches!(standard, PdfStandard::A_2b | PdfStandard::A_3b))
+            .collect::<Vec<_>>();
+        parts.dedup();
+        if let [first, second, ..] = parts.as_slice() {
+            bail!(
+                "cannot conform to both PDF standards `{first}` and `{second}`, choose one of them"
+            );
+        }
+
+        Ok(())
+    }
 }
 
 /// An export png task specifier.
@@ -253,6 +296,14 @@ pub struct ExportPngTask {
     /// will be used.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub fill: Option<String>,
+    /// The template of the file names to export one file per page, relative to
+    /// the output directory. `{p}` is replaced by the page number, `{0p}` by
+    /// the zero-padded page number and `{t}` by the page count, e.g.
+    /// `page-{0p}.png`.
+    ///
+    /// If not provided, the selected pages are merged into a single image.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub page_template: Option<String>,
 }
 
 /// An export svg task specifier.
@@ -262,6 +313,13 @@ pub struct ExportSvgTask {
     /// The shared export arguments.
     #[serde(flatten)]
     pub export: ExportTask,
+    /// The template of the file names to export one file per page, relative to
+    /// the output directory, e.g. `figure-{p}.svg`. See
+    /// [`ExportPngTask::page_template`] for the placeholders.
+    ///
+    /// If not provided, the selected pages are merged into a single SVG.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub page_template: Option<String>,
 }
 
 /// An export html task specifier.
@@ -271,6 +329,32 @@ pub struct ExportHtmlTask {
     /// The shared export arguments.
     #[serde(flatten)]
     pub export: ExportTask,
+    /// The shell command to run after the HTML is exported, e.g. to deploy
+    /// the site. It runs in the directory of the output, whose path is passed
+    /// by the `TINYMIST_OUTPUT` environment variable.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub post_export: Option<String>,
+    /// How the anchors of the headings are made.
+    #[serde(default)]
+    pub heading_anchors: HeadingAnchors,
+}
+
+/// How the anchors of the headings are made in the HTML export, which are
+/// the `id` attributes linked by the `#fragment` of a URL.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeadingAnchors {
+    /// Doesn't add anchors to the headings.
+    None,
+    /// Makes the anchors from the titles in the style of GitHub, e.g.
+    /// `hello-world` for `Hello, World!`.
+    #[default]
+    Github,
+    /// Uses the labels of the headings as the anchors, and makes the anchors
+    /// of the unlabeled headings in the style of GitHub.
+    Label,
 }
 
 /// An export markdown task specifier.
@@ -281,11 +365,60 @@ pub struct ExportMarkdownTask {
     pub processor: Option<String>,
     /// The path of external assets directory.
     pub assets_path: Option<PathBuf>,
+    /// How the math equations are lowered.
+    #[serde(default)]
+    pub math: MathLowering,
+    /// How the figures are lowered.
+    #[serde(default)]
+    pub figure: FigureLowering,
+    /// How the raw blocks are lowered.
+    #[serde(default)]
+    pub raw: RawLowering,
     /// The shared export arguments.
     #[serde(flatten)]
     pub export: ExportTask,
 }
 
+/// How the math equations are lowered in the Markdown export.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum MathLowering {
+    /// Renders the equations as SVG images.
+    #[default]
+    Svg,
+    /// Writes the plain text of the equations.
+    Text,
+}
+
+/// How the figures are lowered in the Markdown export.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FigureLowering {
+    /// Writes the figures as centered HTML `<figure>` elements with captions.
+    #[default]
+    Html,
+    /// Writes the bodies of the figures followed by their captions in
+    /// emphasis, without HTML.
+    Markdown,
+}
+
+/// How the raw blocks are lowered in the Markdown export.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum RawLowering {
+    /// Writes the raw blocks as fenced code blocks.
+    #[default]
+    Fenced,
+    /// Renders the raw blocks as SVG images, keeping the highlighting.
+    Svg,
+}
+
 /// An export TeX task specifier.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -299,6 +432,17 @@ pub struct ExportTeXTask {
     pub export: ExportTask,
 }
 
+/// An export DOCX task specifier.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportDocxTask {
+    /// The processor to use for the DOCX export.
+    pub processor: Option<String>,
+    /// The shared export arguments.
+    #[serde(flatten)]
+    pub export: ExportTask,
+}
+
 /// An export text task specifier.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -315,7 +459,7 @@ pub struct QueryTask {
     /// The shared export arguments.
     #[serde(flatten)]
     pub export: ExportTask,
-    /// The format to serialize in. Can be `json`, `yaml`, or `txt`,
+    /// The format to serialize in. Can be `json`, `yaml`, `csv`, or `txt`,
     pub format: String,
     /// Uses a different output extension from the one inferring from the
     /// [`Self::format`].
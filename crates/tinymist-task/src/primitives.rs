@@ -179,7 +179,14 @@ pub struct Pages(pub RangeInclusive<Option<NonZeroUsize>>);
 
 impl Pages {
     /// Selects the first page.
-    pub const FIRST: Self = Self(NonZeroUsize::new(1)..=None);
+    pub const FIRST: Self = Self(NonZeroUsize::new(1)..=NonZeroUsize::new(1));
+
+    /// Whether the range includes a one-indexed page number.
+    pub fn includes(&self, number: usize) -> bool {
+        let start = self.0.start().map_or(1, NonZeroUsize::get);
+        let end = self.0.end().map_or(usize::MAX, NonZeroUsize::get);
+        (start..=end).contains(&number)
+    }
 }
 
 impl FromStr for Pages {
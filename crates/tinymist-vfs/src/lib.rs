@@ -147,6 +147,23 @@ impl SourceCache {
             true
         });
     }
+
+    /// Returns the number of the cached sources and the total length of their
+    /// texts in bytes.
+    pub fn usage(&self) -> (usize, usize) {
+        let mut count = 0;
+        let mut bytes = 0;
+        for shard in self.cache_entries.iter() {
+            for entry in shard.sources.values() {
+                count += 1;
+                bytes += entry
+                    .source
+                    .as_ref()
+                    .map_or(0, |source| source.text().len());
+            }
+        }
+        (count, bytes)
+    }
 }
 
 /// Creates a new `Vfs` harnessing over the given `access_model` specific for
@@ -299,6 +316,12 @@ impl<M: PathAccessModel + Sized> Vfs<M> {
         self.source_cache.clone()
     }
 
+    /// Returns the number of the cached sources and the total length of their
+    /// texts in bytes.
+    pub fn source_cache_usage(&self) -> (usize, usize) {
+        self.source_cache.usage()
+    }
+
     /// Resolve the real path for a file id.
     pub fn file_path(&self, id: FileId) -> Result<PathResolution, FileError> {
         self.access_model.inner.resolver.path_for_id(id)
@@ -276,6 +276,12 @@ pub enum OutputFormat {
     Svg,
     /// Export to HTML.
     Html,
+    /// Export to Markdown.
+    Md,
+    /// Export to DOCX.
+    Docx,
+    /// Export to plain text.
+    Txt,
 }
 
 display_possible_values!(OutputFormat);
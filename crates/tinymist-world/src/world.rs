@@ -850,6 +850,53 @@ impl typst::World for WorldWithMain<'_> {
     }
 }
 
+/// Creates a world with a source not in the world as the main file, e.g. a
+/// snippet importing the files of the world.
+pub fn with_main_source(world: &dyn World, main: Source) -> WorldWithMainSource<'_> {
+    WorldWithMainSource { world, main }
+}
+
+pub struct WorldWithMainSource<'a> {
+    world: &'a dyn World,
+    main: Source,
+}
+
+impl typst::World for WorldWithMainSource<'_> {
+    fn main(&self) -> FileId {
+        self.main.id()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+        self.world.source(id)
+    }
+
+    fn library(&self) -> &LazyHash<Library> {
+        self.world.library()
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.world.book()
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        if id == self.main.id() {
+            return Ok(Bytes::from_string(self.main.text().to_owned()));
+        }
+        self.world.file(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.world.font(index)
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        self.world.today(offset)
+    }
+}
+
 pub trait SourceWorld: World {
     fn as_world(&self) -> &dyn World;
 
@@ -2,9 +2,18 @@
 //! compile status.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use lsp_types::notification::{Notification, PublishDiagnostics as PublishDiagnosticsBase};
-use lsp_types::{Diagnostic, Url};
+use lsp_types::request::WorkspaceDiagnosticRefresh;
+use lsp_types::{
+    Diagnostic, DocumentDiagnosticReport, FullDocumentDiagnosticReport, PreviousResultId,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport, Url, WorkspaceDiagnosticReport,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceUnchangedDocumentDiagnosticReport,
+};
+use parking_lot::Mutex;
 use reflexo::path::unix_slash;
 use reflexo_typst::typst::prelude::{eco_vec, EcoVec};
 use serde::{Deserialize, Serialize};
@@ -13,6 +22,7 @@ use tinymist_query::DiagnosticsMap;
 use tokio::sync::mpsc;
 use typst::utils::OptionExt;
 
+use crate::notebook::HiddenLines;
 use crate::project::ProjectInsId;
 use crate::{tool::word_count::WordsCount, LspClient};
 
@@ -31,6 +41,8 @@ pub enum EditorRequest {
     Status(CompileReport),
     /// Updastes words count status to the editor.
     WordCount(ProjectInsId, WordsCount),
+    /// Updates the lines hidden from the cell documents of the notebooks.
+    HiddenLines(HiddenLines),
 }
 
 /// The actor maintaining output to the editor, including diagnostics and
@@ -50,6 +62,12 @@ pub struct EditorActor {
     diagnostics: HashMap<Url, HashMap<ProjectInsId, EcoVec<Diagnostic>>>,
     /// The map from project ID to the affected files.
     affect_map: HashMap<ProjectInsId, Vec<Url>>,
+    /// The store of the diagnostics to pull, if the client pulls diagnostics
+    /// instead of receiving the published ones.
+    pull_store: Option<DiagnosticsStore>,
+    /// The lines hidden from the cell documents, by which the diagnostics are
+    /// shifted.
+    hidden: HiddenLines,
 }
 
 impl EditorActor {
@@ -58,12 +76,15 @@ impl EditorActor {
         client: LspClient,
         editor_rx: mpsc::UnboundedReceiver<EditorRequest>,
         notify_status: bool,
+        pull_store: Option<DiagnosticsStore>,
     ) -> Self {
         Self {
             client,
             editor_rx,
             diagnostics: HashMap::new(),
             affect_map: HashMap::new(),
+            pull_store,
+            hidden: HiddenLines::default(),
             config: EditorActorConfig { notify_status },
         }
     }
@@ -112,6 +133,10 @@ impl EditorActor {
                         self.client.send_notification::<StatusAll>(&status);
                     }
                 }
+                EditorRequest::HiddenLines(hidden) => {
+                    log::trace!("received hidden lines request");
+                    self.hidden = hidden;
+                }
                 EditorRequest::WordCount(id, count) => {
                     log::trace!("received word count request");
                     if self.config.notify_status && id == ProjectInsId::PRIMARY {
@@ -141,21 +166,42 @@ impl EditorActor {
         // removed, without an explicit update, so we need to send an empty `Vec` of
         // diagnostics to these sources.
 
+        let mut changed = false;
+
         // Gets sources that affected by this group in last round but not this time
         for uri in affected.into_iter().flatten() {
             if !next_diag.as_ref().is_some_and(|e| e.contains_key(&uri)) {
-                self.publish_file(&id, uri, None)
+                changed |= self.publish_file(&id, uri, None)
             }
         }
 
         // Gets touched updates
-        for (uri, next) in next_diag.into_iter().flatten() {
-            self.publish_file(&id, uri, Some(next))
+        for (uri, mut next) in next_diag.into_iter().flatten() {
+            if !self.hidden.is_empty() {
+                self.hidden.hide_diagnostics(&uri, next.make_mut());
+            }
+            changed |= self.publish_file(&id, uri, Some(next))
+        }
+
+        // Asks the client to pull the changed diagnostics.
+        if changed && self.pull_store.is_some() {
+            self.client
+                .send_lsp_request_::<WorkspaceDiagnosticRefresh>((), |_, resp| {
+                    if let Some(err) = resp.error {
+                        log::warn!("failed to refresh diagnostics: {err:?}");
+                    }
+                });
         }
     }
 
-    /// Publishes diagnostics of a file to the editor.
-    fn publish_file(&mut self, id: &ProjectInsId, uri: Url, next: Option<EcoVec<Diagnostic>>) {
+    /// Publishes diagnostics of a file to the editor. Returns whether the
+    /// diagnostics to pull are changed.
+    fn publish_file(
+        &mut self,
+        id: &ProjectInsId,
+        uri: Url,
+        next: Option<EcoVec<Diagnostic>>,
+    ) -> bool {
         let mut diagnostics = EcoVec::new();
 
         // Gets the diagnostics from other groups
@@ -177,6 +223,10 @@ impl EditorActor {
             None => path_diags.remove(id),
         };
 
+        if let Some(store) = &self.pull_store {
+            return store.update(uri, ScatterVec(diagnostics).flatten());
+        }
+
         // Publishes the diagnostics
         self.client
             .send_notification::<PublishDiagnostics>(&PublishDiagnosticsParams {
@@ -184,6 +234,147 @@ impl EditorActor {
                 diagnostics: ScatterVec(diagnostics),
                 version: None,
             });
+        false
+    }
+}
+
+/// The diagnostics of the files to pull by the `textDocument/diagnostic` and
+/// `workspace/diagnostic` requests.
+#[derive(Debug, Default, Clone)]
+pub struct DiagnosticsStore(Arc<Mutex<DiagnosticsStoreRepr>>);
+
+#[derive(Debug, Default)]
+struct DiagnosticsStoreRepr {
+    /// The diagnostics of the files. The files whose diagnostics are cleared
+    /// are kept until the clearance is reported.
+    files: HashMap<Url, PulledDiagnostics>,
+}
+
+#[derive(Debug)]
+struct PulledDiagnostics {
+    /// The hash of the diagnostics, so that the result ID of a file doesn't
+    /// change unless its diagnostics change.
+    result_id: String,
+    items: Vec<Diagnostic>,
+}
+
+impl PulledDiagnostics {
+    fn new(items: Vec<Diagnostic>) -> Self {
+        let content = serde_json::to_string(&items).unwrap_or_default();
+        let result_id = format!("{:032x}", tinymist_std::hash::hash128(&content));
+        Self { result_id, items }
+    }
+
+    /// Gets the report of the diagnostics, which is unchanged if the client
+    /// has the diagnostics of the previous result ID.
+    fn report(&self, previous_result_id: Option<&str>) -> PulledReport {
+        if previous_result_id == Some(self.result_id.as_str()) {
+            PulledReport::Unchanged(UnchangedDocumentDiagnosticReport {
+                result_id: self.result_id.clone(),
+            })
+        } else {
+            PulledReport::Full(FullDocumentDiagnosticReport {
+                result_id: Some(self.result_id.clone()),
+                items: self.items.clone(),
+            })
+        }
+    }
+}
+
+enum PulledReport {
+    Full(FullDocumentDiagnosticReport),
+    Unchanged(UnchangedDocumentDiagnosticReport),
+}
+
+impl DiagnosticsStore {
+    /// Updates the diagnostics of a file. Returns whether they are changed.
+    pub fn update(&self, uri: Url, items: Vec<Diagnostic>) -> bool {
+        let mut repr = self.0.lock();
+        match repr.files.get(&uri) {
+            Some(file) if file.items == items => return false,
+            None if items.is_empty() => return false,
+            _ => {}
+        }
+
+        repr.files.insert(uri, PulledDiagnostics::new(items));
+        true
+    }
+
+    /// Gets the diagnostics report of a file, which is unchanged if the client
+    /// has the diagnostics of the previous result ID.
+    pub fn document_report(
+        &self,
+        uri: &Url,
+        previous_result_id: Option<&str>,
+    ) -> DocumentDiagnosticReport {
+        let mut repr = self.0.lock();
+        let report = match repr.files.get(uri) {
+            Some(file) => file.report(previous_result_id),
+            None => PulledDiagnostics::new(vec![]).report(previous_result_id),
+        };
+        // The clearance is reported, so the file is not tracked anymore.
+        if repr
+            .files
+            .get(uri)
+            .is_some_and(|file| file.items.is_empty())
+        {
+            repr.files.remove(uri);
+        }
+
+        match report {
+            PulledReport::Full(report) => {
+                DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                    related_documents: None,
+                    full_document_diagnostic_report: report,
+                })
+            }
+            PulledReport::Unchanged(report) => {
+                DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report: report,
+                })
+            }
+        }
+    }
+
+    /// Gets the diagnostics reports of all the files, which are unchanged if
+    /// the client has the diagnostics of the previous result IDs.
+    pub fn workspace_report(&self, previous: &[PreviousResultId]) -> WorkspaceDiagnosticReport {
+        let previous = previous
+            .iter()
+            .map(|prev| (&prev.uri, prev.value.as_str()))
+            .collect::<HashMap<_, _>>();
+
+        let mut repr = self.0.lock();
+        let items = repr
+            .files
+            .iter()
+            .map(|(uri, file)| {
+                let uri = uri.clone();
+                match file.report(previous.get(&uri).copied()) {
+                    PulledReport::Full(report) => WorkspaceDocumentDiagnosticReport::Full(
+                        WorkspaceFullDocumentDiagnosticReport {
+                            uri,
+                            version: None,
+                            full_document_diagnostic_report: report,
+                        },
+                    ),
+                    PulledReport::Unchanged(report) => {
+                        WorkspaceDocumentDiagnosticReport::Unchanged(
+                            WorkspaceUnchangedDocumentDiagnosticReport {
+                                uri,
+                                version: None,
+                                unchanged_document_diagnostic_report: report,
+                            },
+                        )
+                    }
+                }
+            })
+            .collect();
+        // The clearances are reported, so the files are not tracked anymore.
+        repr.files.retain(|_, file| !file.items.is_empty());
+
+        WorkspaceDiagnosticReport { items }
     }
 }
 
@@ -266,6 +457,13 @@ impl Notification for PublishDiagnostics {
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct ScatterVec<T>(EcoVec<EcoVec<T>>);
 
+impl<T: Clone> ScatterVec<T> {
+    /// Flattens the scattered items into a vector.
+    pub fn flatten(self) -> Vec<T> {
+        self.0.iter().flat_map(|e| e.iter().cloned()).collect()
+    }
+}
+
 impl serde::Serialize for ScatterVec<Diagnostic> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut vec = Vec::new();
@@ -282,3 +480,133 @@ impl<'de> serde::Deserialize<'de> for ScatterVec<Diagnostic> {
         Ok(Self(eco_vec![vec]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(message: &str) -> Diagnostic {
+        Diagnostic {
+            message: message.to_owned(),
+            ..Diagnostic::default()
+        }
+    }
+
+    fn full_report(report: DocumentDiagnosticReport) -> FullDocumentDiagnosticReport {
+        match report {
+            DocumentDiagnosticReport::Full(report) => report.full_document_diagnostic_report,
+            DocumentDiagnosticReport::Unchanged(..) => panic!("expected a full report"),
+        }
+    }
+
+    fn is_unchanged(report: &DocumentDiagnosticReport) -> bool {
+        matches!(report, DocumentDiagnosticReport::Unchanged(..))
+    }
+
+    fn previous(uri: &Url, result_id: &str) -> PreviousResultId {
+        PreviousResultId {
+            uri: uri.clone(),
+            value: result_id.to_owned(),
+        }
+    }
+
+    #[test]
+    fn document_report() {
+        let store = DiagnosticsStore::default();
+        let uri = Url::parse("file:///main.typ").unwrap();
+
+        let empty = full_report(store.document_report(&uri, None));
+        assert!(empty.items.is_empty());
+        let empty_id = empty.result_id.unwrap();
+        assert!(is_unchanged(&store.document_report(&uri, Some(&empty_id))));
+
+        // Unchanged
+        assert!(store.update(uri.clone(), vec![diag("a")]));
+        let first = full_report(store.document_report(&uri, Some(&empty_id)));
+        assert_eq!(first.items, vec![diag("a")]);
+        let first_id = first.result_id.unwrap();
+        assert!(!store.update(uri.clone(), vec![diag("a")]));
+        assert!(is_unchanged(&store.document_report(&uri, Some(&first_id))));
+
+        // Changed
+        assert!(store.update(uri.clone(), vec![diag("b")]));
+        let second = full_report(store.document_report(&uri, Some(&first_id)));
+        assert_eq!(second.items, vec![diag("b")]);
+        assert_ne!(second.result_id.as_ref(), Some(&first_id));
+
+        // The result IDs are the hashes of the diagnostics.
+        assert!(store.update(uri.clone(), vec![diag("a")]));
+        assert!(is_unchanged(&store.document_report(&uri, Some(&first_id))));
+
+        // Cleared
+        assert!(store.update(uri.clone(), vec![]));
+        let cleared = full_report(store.document_report(&uri, Some(&first_id)));
+        assert!(cleared.items.is_empty());
+        assert_eq!(cleared.result_id, Some(empty_id.clone()));
+        assert!(store.0.lock().files.is_empty());
+        assert!(!store.update(uri.clone(), vec![]));
+        assert!(is_unchanged(&store.document_report(&uri, Some(&empty_id))));
+    }
+
+    #[test]
+    fn workspace_report() {
+        let store = DiagnosticsStore::default();
+        let main = Url::parse("file:///main.typ").unwrap();
+        let lib = Url::parse("file:///lib.typ").unwrap();
+        assert!(store.workspace_report(&[]).items.is_empty());
+
+        assert!(store.update(main.clone(), vec![diag("a")]));
+        assert!(store.update(lib.clone(), vec![diag("b")]));
+        let report = store.workspace_report(&[]);
+        let mut ids = HashMap::new();
+        for item in report.items {
+            let WorkspaceDocumentDiagnosticReport::Full(full) = item else {
+                panic!("expected a full report");
+            };
+            let report = full.full_document_diagnostic_report;
+            ids.insert(full.uri, report.result_id.unwrap());
+        }
+        assert_eq!(ids.len(), 2);
+
+        // Unchanged, changed and cleared
+        assert!(store.update(lib.clone(), vec![diag("c")]));
+        let report =
+            store.workspace_report(&[previous(&main, &ids[&main]), previous(&lib, &ids[&lib])]);
+        assert_eq!(report.items.len(), 2);
+        for item in report.items {
+            match item {
+                WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) => {
+                    assert_eq!(unchanged.uri, main);
+                }
+                WorkspaceDocumentDiagnosticReport::Full(full) => {
+                    assert_eq!(full.uri, lib);
+                    let report = full.full_document_diagnostic_report;
+                    assert_eq!(report.items, vec![diag("c")]);
+                    ids.insert(lib.clone(), report.result_id.unwrap());
+                }
+            }
+        }
+
+        assert!(store.update(main.clone(), vec![]));
+        let report =
+            store.workspace_report(&[previous(&main, &ids[&main]), previous(&lib, &ids[&lib])]);
+        assert_eq!(report.items.len(), 2);
+        for item in report.items {
+            match item {
+                WorkspaceDocumentDiagnosticReport::Full(full) => {
+                    assert_eq!(full.uri, main);
+                    assert!(full.full_document_diagnostic_report.items.is_empty());
+                }
+                WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) => {
+                    assert_eq!(unchanged.uri, lib);
+                }
+            }
+        }
+
+        // The clearance is reported once.
+        let report = store.workspace_report(&[previous(&lib, &ids[&lib])]);
+        assert_eq!(report.items.len(), 1);
+        assert!(store.0.lock().files.contains_key(&lib));
+        assert!(!store.0.lock().files.contains_key(&main));
+    }
+}
@@ -1,11 +1,13 @@
 use std::{collections::HashMap, sync::Arc};
 
 use lsp_types::notification::Notification;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use sync_ls::{internal_error, LspClient, LspResult};
 use tinymist_preview::{ControlPlaneMessage, Previewer};
 use tinymist_std::error::IgnoreLogging;
+use tinymist_std::hash::FxHashSet;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::project::ProjectPreviewState;
@@ -42,6 +44,8 @@ pub struct PreviewActor {
     pub preview_rx: mpsc::UnboundedReceiver<PreviewRequest>,
     /// the watchers for the preview
     pub(crate) watchers: ProjectPreviewState,
+    /// The task ids of the running previews.
+    pub(crate) running: Arc<Mutex<FxHashSet<String>>>,
 }
 
 impl PreviewActor {
@@ -88,6 +92,7 @@ impl PreviewActor {
             let _ = tx.send(Err(internal_error("task not found")));
             return;
         };
+        self.running.lock().remove(&task_id);
 
         // Unregister preview early
         let unregistered = self.watchers.unregister(&tab.compile_handler.project_id);
@@ -2,8 +2,21 @@ use std::path::Path;
 
 use sync_ls::transport::MirrorArgs;
 use tinymist::project::DocCommands;
+use tinymist::tool::analysis::AnalysisCommands;
+use tinymist::tool::ci::CiArgs;
+use tinymist::tool::convert::ConvertLatexArgs;
+use tinymist::tool::fonts::FontsArgs;
+use tinymist::tool::format::FormatArgs;
+use tinymist::tool::index::IndexArgs;
+use tinymist::tool::lint::LintArgs;
+use tinymist::tool::merge::MergeArgs;
+use tinymist::tool::package::PackageCommands;
+use tinymist::tool::probe::ProbeArgs;
 use tinymist::tool::project::{CompileArgs, GenerateScriptArgs, TaskCommands};
-use tinymist::tool::testing::TestArgs;
+use tinymist::tool::query::QuerySelectorArgs;
+use tinymist::tool::self_test::SelfTestArgs;
+use tinymist::tool::serve::ServeArgs;
+use tinymist::tool::testing::{CoverageArgs, TestArgs};
 use tinymist::{CompileFontArgs, CompileOnceArgs};
 use tinymist_core::LONG_VERSION;
 
@@ -18,8 +31,8 @@ pub struct CliArguments {
 #[derive(Debug, Clone, clap::Subcommand)]
 #[clap(rename_all = "kebab-case")]
 pub enum Commands {
-    /// Probes existence (Nop run)
-    Probe,
+    /// Probes existence (Nop run), or checks the installation with `--doctor`
+    Probe(ProbeArgs),
 
     /// Generates completion script to stdout
     Completion(ShellCompletionArgs),
@@ -30,20 +43,41 @@ pub enum Commands {
     /// Runs language server for tracing some typst program.
     #[clap(hide(true))]
     TraceLsp(TraceLspArgs),
+    /// Runs compile server exposing a JSON API over HTTP
+    Serve(ServeArgs),
     /// Runs preview server
     #[cfg(feature = "preview")]
     Preview(tinymist::tool::preview::PreviewCliArgs),
 
     /// Execute a document and collect coverage
     #[clap(hide(true))] // still in development
-    Cov(CompileOnceArgs),
+    Cov(CoverageArgs),
     /// Test a document and gives summary
     Test(TestArgs),
     /// Runs compile command like `typst-cli compile`
     Compile(CompileArgs),
+    /// Compiles a document once per row of a CSV or JSON dataset
+    Merge(MergeArgs),
+    /// Checks, lints, tests and compiles the documents in the lock file
+    Ci(CiArgs),
+    /// Formats Typst files with the formatter configured in `tinymist.toml`
+    Format(FormatArgs),
+    /// Lints Typst documents and the workspace files they depend on
+    Lint(LintArgs),
+    /// Converts LaTeX math into Typst math
+    ConvertLatex(ConvertLatexArgs),
+    /// Lists the available fonts, or explains why a font family is not used
+    Fonts(FontsArgs),
     /// Generates build script for compilation
     #[clap(hide(true))] // still in development
     GenerateScript(GenerateScriptArgs),
+    /// Exports the code-intelligence index of a project
+    Index(IndexArgs),
+    /// Tests the analyses on random documents
+    SelfTest(SelfTestArgs),
+    /// Runs analysis commands, e.g. snapshotting the analysis results
+    #[clap(subcommand)]
+    Analysis(AnalysisCommands),
     /// Runs language query
     #[clap(hide(true))] // still in development
     #[clap(subcommand)]
@@ -56,6 +90,9 @@ pub enum Commands {
     #[clap(hide(true))] // still in development
     #[clap(subcommand)]
     Task(TaskCommands),
+    /// Manages downloaded packages
+    #[clap(subcommand)]
+    Package(PackageCommands),
 }
 
 impl Default for Commands {
@@ -170,8 +207,14 @@ pub type DapArgs = LspArgs;
 pub enum QueryCommands {
     /// Get the documentation for a specific package.
     PackageDocs(PackageDocsArgs),
-    /// Check a specific package.
+    /// Check a specific package, i.e. validate the manifest, the signatures
+    /// of the exported functions against their docstrings, and the examples
+    /// in the docstrings. The issues are written to the output path as JSON.
     CheckPackage(PackageDocsArgs),
+    /// Rename a symbol across the workspace.
+    Rename(RenameArgs),
+    /// Query the elements of a document by a selector.
+    Selector(QuerySelectorArgs),
 }
 
 #[derive(Debug, Clone, clap::Parser)]
@@ -185,15 +228,37 @@ pub struct PackageDocsArgs {
     /// The output path for the requested docs.
     #[clap(short, long)]
     pub output: String,
-    // /// The format of requested docs.
-    // #[clap(long)]
-    // pub format: Option<QueryDocsFormat>,
+    /// The format of requested docs. If not specified, the docs are generated
+    /// in the annotated markdown format used by the editors.
+    #[clap(long, value_enum)]
+    pub format: Option<QueryDocsFormat>,
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub struct RenameArgs {
+    /// The path of the document containing the symbol.
+    #[clap(long)]
+    pub path: String,
+    /// The line of the symbol, starting from 1.
+    #[clap(long)]
+    pub line: u32,
+    /// The column of the symbol in UTF-16 code units, starting from 1.
+    #[clap(long)]
+    pub column: u32,
+    /// The new name of the symbol.
+    #[clap(long)]
+    pub new_name: String,
+    /// Print the changes as a unified diff instead of applying them.
+    #[clap(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone, Default, clap::ValueEnum)]
 #[clap(rename_all = "camelCase")]
 pub enum QueryDocsFormat {
+    /// The definitions of the package in JSON.
     #[default]
     Json,
+    /// A single browsable markdown file, with an anchor per symbol.
     Markdown,
 }
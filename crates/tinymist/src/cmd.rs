@@ -1,7 +1,8 @@
 //! Tinymist LSP commands
 
+use std::collections::HashMap;
 use std::ops::{Deref, Range};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use lsp_types::TextDocumentIdentifier;
 use serde::{Deserialize, Serialize};
@@ -10,13 +11,16 @@ use sync_ls::RequestId;
 use task::TraceParams;
 use tinymist_assets::TYPST_PREVIEW_HTML;
 use tinymist_project::{
-    ExportHtmlTask, ExportPdfTask, ExportPngTask, ExportSvgTask, ExportTask, ExportTeXTask,
-    ExportTextTask, ExportTransform, PageSelection, Pages, ProjectTask, QueryTask,
+    ExportDocxTask, ExportHtmlTask, ExportPdfTask, ExportPngTask, ExportSvgTask, ExportTask,
+    ExportTeXTask, ExportTextTask, ExportTransform, HeadingAnchors, PageSelection, Pages,
+    ProjectTask, QueryTask,
 };
-use tinymist_query::package::PackageInfo;
-use tinymist_query::{LocalContextGuard, LspRange};
+use tinymist_query::package::{PackageInfo, PackageIssue};
+use tinymist_query::{LocalContextGuard, LspRange, SelectionImageFormat};
 use tinymist_std::error::prelude::*;
-use tinymist_task::ExportMarkdownTask;
+use tinymist_std::typst::TypstPagedDocument;
+use tinymist_std::ImmutPath;
+use tinymist_task::{ExportMarkdownTask, FigureLowering, MathLowering, RawLowering};
 use typst::diag::{eco_format, EcoString, StrResult};
 use typst::syntax::package::{PackageSpec, VersionlessPackageSpec};
 use typst::syntax::{LinkedNode, Source};
@@ -34,6 +38,12 @@ struct ExportOpts {
     ppi: Option<f32>,
     #[serde(default)]
     page: PageSelection,
+    /// The page ranges to export, e.g. `["2", "5-7"]`, which replace the first
+    /// page selection, or are merged in the merged selection.
+    pages: Option<Vec<Pages>>,
+    /// The template of the file names to export one PNG or SVG file per page,
+    /// e.g. `page-{0p}.png`.
+    page_template: Option<String>,
     /// Whether to open the exported file(s) after the export is done.
     open: Option<bool>,
     // todo: we made a mistake that they will be snakecase, but they should be camelCase
@@ -41,6 +51,13 @@ struct ExportOpts {
     creation_timestamp: Option<String>,
     /// A PDF standard that Typst can enforce conformance with.
     pdf_standard: Option<Vec<PdfStandard>>,
+    /// The maximum resolution of the raster images embedded in a PDF, in dots
+    /// per inch.
+    image_max_dpi: Option<u32>,
+    /// Whether to export a tagged PDF for accessibility.
+    tagged: Option<bool>,
+    /// How the anchors of the headings are made in the HTML export.
+    heading_anchors: Option<HeadingAnchors>,
 }
 
 /// See [`ProjectTask`].
@@ -53,6 +70,12 @@ struct ExportTypliteOpts {
     processor: Option<String>,
     /// The path of external assets directory.
     assets_path: Option<PathBuf>,
+    /// How the math equations are lowered in the Markdown export.
+    math: Option<MathLowering>,
+    /// How the figures are lowered in the Markdown export.
+    figure: Option<FigureLowering>,
+    /// How the raw blocks are lowered in the Markdown export.
+    raw: Option<RawLowering>,
 }
 
 /// See [`ProjectTask`].
@@ -76,6 +99,14 @@ struct ExportSyntaxRangeOpts {
     range: Option<LspRange>,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportSelectionOpts {
+    #[serde(default)]
+    format: SelectionImageFormat,
+    ppi: Option<f32>,
+}
+
 /// Here are implemented the handlers for each command.
 impl ServerState {
     /// Export the current document as PDF file(s).
@@ -93,13 +124,18 @@ impl ServerState {
         let pdf_standards = opts.pdf_standard.or_else(|| self.config.pdf_standards());
 
         let export = self.config.export_task();
+        let task = ExportPdfTask {
+            export,
+            pdf_standards: pdf_standards.unwrap_or_default(),
+            creation_timestamp,
+            image_max_dpi: opts.image_max_dpi,
+            tagged: opts.tagged.unwrap_or_default(),
+        };
+        task.check_standards()
+            .map_err(|e| invalid_params(format!("Invalid PDF standards: {e}")))?;
         self.export(
             req_id,
-            ProjectTask::ExportPdf(ExportPdfTask {
-                export,
-                pdf_standards: pdf_standards.unwrap_or_default(),
-                creation_timestamp,
-            }),
+            ProjectTask::ExportPdf(task),
             opts.open.unwrap_or_default(),
             args,
         )
@@ -111,7 +147,11 @@ impl ServerState {
         let export = self.config.export_task();
         self.export(
             req_id,
-            ProjectTask::ExportHtml(ExportHtmlTask { export }),
+            ProjectTask::ExportHtml(ExportHtmlTask {
+                export,
+                post_export: None,
+                heading_anchors: opts.heading_anchors.unwrap_or_default(),
+            }),
             opts.open.unwrap_or_default(),
             args,
         )
@@ -130,6 +170,9 @@ impl ServerState {
             ProjectTask::ExportMd(ExportMarkdownTask {
                 processor: opts.processor,
                 assets_path: opts.assets_path,
+                math: opts.math.unwrap_or_default(),
+                figure: opts.figure.unwrap_or_default(),
+                raw: opts.raw.unwrap_or_default(),
                 export,
             }),
             opts.open.unwrap_or_default(),
@@ -153,6 +196,21 @@ impl ServerState {
         )
     }
 
+    /// Export the current document as a DOCX file.
+    pub fn export_docx(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
+        let opts = get_arg_or_default!(args[1] as ExportTypliteOpts);
+        let export = self.config.export_task();
+        self.export(
+            req_id,
+            ProjectTask::ExportDocx(ExportDocxTask {
+                processor: opts.processor,
+                export,
+            }),
+            opts.open.unwrap_or_default(),
+            args,
+        )
+    }
+
     /// Export the current document as Text file(s).
     pub fn export_text(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
         let opts = get_arg_or_default!(args[1] as ExportOpts);
@@ -191,16 +249,33 @@ impl ServerState {
         )
     }
 
+    /// Renders a range of the current document as an image, which is compiled
+    /// with the imports and rules of the document.
+    pub fn export_selection(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let path = get_arg!(args[0] as PathBuf);
+        let range = get_arg!(args[1] as LspRange);
+        let opts = get_arg_or_default!(args[2] as ExportSelectionOpts);
+        let (format, ppi) = (opts.format, opts.ppi);
+        run_query!(req_id, self.ExportSelection(path, range, format, ppi))
+    }
+
     /// Export the current document as Svg file(s).
     pub fn export_svg(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
         let opts = get_arg_or_default!(args[1] as ExportOpts);
 
         let mut export = self.config.export_task();
-        select_page(&mut export, opts.page).map_err(invalid_params)?;
+        select_page(&mut export, opts.page, opts.pages).map_err(invalid_params)?;
 
         self.export(
             req_id,
-            ProjectTask::ExportSvg(ExportSvgTask { export }),
+            ProjectTask::ExportSvg(ExportSvgTask {
+                export,
+                page_template: opts.page_template,
+            }),
             opts.open.unwrap_or_default(),
             args,
         )
@@ -217,13 +292,14 @@ impl ServerState {
             .map_err(invalid_params)?;
 
         let mut export = self.config.export_task();
-        select_page(&mut export, opts.page).map_err(invalid_params)?;
+        select_page(&mut export, opts.page, opts.pages).map_err(invalid_params)?;
 
         self.export(
             req_id,
             ProjectTask::ExportPng(ExportPngTask {
                 fill: opts.fill,
                 ppi,
+                page_template: opts.page_template,
                 export,
             }),
             opts.open.unwrap_or_default(),
@@ -242,7 +318,10 @@ impl ServerState {
     ) -> ScheduledResult {
         let path = get_arg!(args[0] as PathBuf);
 
-        run_query!(req_id, self.OnExport(path, open, task))
+        let progress = self.begin_progress("Exporting", None);
+        let progress = progress.step(&task.extension().to_uppercase());
+        progress.report(Some(&path.display().to_string()));
+        run_query!(req_id, self.OnExport(path, open, task), progress)
     }
 
     /// Export a range of the current document as Ansi highlighted text.
@@ -281,6 +360,48 @@ impl ServerState {
         just_ok(JsonValue::String(output))
     }
 
+    /// Converts the pasted HTML or Markdown into Typst markup.
+    pub fn paste_as_typst(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let format = get_arg!(args[0] as String);
+        let text = get_arg!(args[1] as String);
+
+        let output = match format.as_str() {
+            "html" => crate::tool::convert::html_to_typst(&text),
+            "markdown" => crate::tool::convert::markdown_to_typst(&text),
+            _ => {
+                return Err(invalid_params(format!(
+                    "unsupported paste format: {format}"
+                )))
+            }
+        };
+
+        just_ok(JsonValue::String(output))
+    }
+
+    /// Converts the LaTeX math, e.g. the selected or copied formulas, into
+    /// Typst math.
+    pub fn convert_latex_math(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let text = get_arg!(args[0] as String);
+
+        let output = crate::tool::convert::latex_math_to_typst(&text);
+        just_ok(JsonValue::String(output))
+    }
+
+    /// Completes the field names and the values in a Hayagriva bibliography,
+    /// whose text is passed since the YAML files are not opened in the server.
+    pub fn complete_yaml_bib(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let text = get_arg!(args[0] as String);
+        let position = get_arg!(args[1] as tinymist_query::LspPosition);
+
+        let source = Source::detached(text);
+        let encoding = self.const_config().position_encoding;
+        let cursor = tinymist_query::to_typst_position(position, encoding, &source)
+            .ok_or_else(|| invalid_params("invalid position"))?;
+        let list = tinymist_query::yaml_bib_completion(source.text(), cursor);
+
+        just_ok(serde_json::to_value(list).map_err(|e| internal_error(e.to_string()))?)
+    }
+
     fn select_range<T>(
         &mut self,
         path: PathBuf,
@@ -311,6 +432,32 @@ impl ServerState {
         just_ok(JsonValue::Null)
     }
 
+    /// Dumps the analysis of a file at a recent revision for debugging.
+    pub fn dump_analysis(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let path = get_arg!(args[0] as PathBuf);
+        let revision = get_arg_or_default!(args[1] as Option<usize>);
+
+        let world = self.analysis_history.get(revision).cloned();
+        let world = world.ok_or_else(|| {
+            let revisions = self.analysis_history.revisions();
+            invalid_params(format!(
+                "revision {revision:?} is not recorded, recent revisions: {revisions:?}"
+            ))
+        })?;
+        let analysis = self.project.analysis.clone();
+
+        just_future(async move {
+            let revision = world.revision().get();
+            let mut ctx = analysis.enter(world);
+            let source = ctx.source_by_path(&path).map_err(|err| {
+                invalid_params(format!("could not read {path:?} at {revision}: {err}"))
+            })?;
+            let dump = tinymist_query::analysis::dump_analysis(&mut ctx, &source);
+
+            Ok(serde_json::json!({ "revision": revision, "dump": dump }))
+        })
+    }
+
     /// Pin main file to some path.
     pub fn pin_document(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
         let entry = get_arg!(args[0] as Option<PathBuf>).map(From::from);
@@ -340,6 +487,40 @@ impl ServerState {
         just_ok(JsonValue::Null)
     }
 
+    /// Registers an entry file, which is compiled with its own diagnostics.
+    pub fn add_entry(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let entry = get_arg!(args[0] as PathBuf);
+
+        let res = self.register_entry(entry.as_path().into());
+        res.map_err(|err| internal_error(format!("could not add entry: {err}")))?;
+
+        log::info!("entry added: {entry:?}");
+        just_ok(JsonValue::Null)
+    }
+
+    /// Unregisters an entry file.
+    pub fn remove_entry(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let entry = get_arg!(args[0] as PathBuf);
+
+        self.unregister_entry(&entry.as_path().into());
+
+        log::info!("entry removed: {entry:?}");
+        just_ok(JsonValue::Null)
+    }
+
+    /// Focuses an editor tab on some entry file, or resets it to the implicit
+    /// entry.
+    pub fn focus_entry(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let tab = get_arg!(args[0] as PathBuf);
+        let entry = get_arg!(args[1] as Option<PathBuf>).map(From::from);
+
+        let res = self.focus_tab_entry(tab.as_path().into(), entry.clone());
+        res.map_err(|err| internal_error(format!("could not focus entry: {err}")))?;
+
+        log::info!("entry focused: {tab:?} -> {entry:?}");
+        just_ok(JsonValue::Null)
+    }
+
     /// Starts a preview instance.
     #[cfg(feature = "preview")]
     pub fn do_start_preview(
@@ -415,6 +596,18 @@ impl ServerState {
         self.preview.scroll(task_id, req)
     }
 
+    /// Exports the page viewed in a preview, or a region of it, as an image.
+    #[cfg(feature = "preview")]
+    pub fn export_preview_view(
+        &mut self,
+        mut args: Vec<JsonValue>,
+    ) -> SchedulableResponse<tinymist_preview::ExportViewResponse> {
+        let task_id = get_arg_or_default!(args[0] as Option<String>);
+        let req = get_arg_or_default!(args[1] as tinymist_preview::ExportViewRequest);
+
+        self.preview.export_view(task_id, req)
+    }
+
     /// Initialize a new template.
     pub fn init_template(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
         use crate::tool::package::{self, TemplateSource};
@@ -627,6 +820,178 @@ impl ServerState {
         run_query!(req_id, self.DocumentMetrics(path))
     }
 
+    /// Explains which fonts are used for the text in a range, and why.
+    pub fn explain_font_fallback(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let path = get_arg!(args[0] as PathBuf);
+        let range = get_arg!(args[1] as LspRange);
+        run_query!(req_id, self.FontFallback(path, range))
+    }
+
+    /// Gets the entrypoints, tests and export tasks affected by a modified
+    /// file.
+    pub fn get_change_impact(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let path = get_arg!(args[0] as PathBuf);
+        run_query!(req_id, self.ChangeImpact(path))
+    }
+
+    /// Collects the coverage of a document and publishes the hits of the lines
+    /// by the [`CoverageNotification`]s, one per covered file, so that the
+    /// editors can paint the covered and uncovered lines.
+    pub fn run_coverage(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let path = get_arg!(args[0] as PathBuf).into();
+        let entry = self.entry_resolver().resolve(Some(path));
+        let snap = self.snapshot().map_err(internal_error)?;
+        let client = self.client.clone();
+
+        just_future(async move {
+            let snap = snap.task(TaskInputs {
+                entry: Some(entry),
+                inputs: None,
+            });
+            let world = snap.world();
+            let cov = tinymist_debug::collect_coverage::<TypstPagedDocument, _>(world)
+                .map_err(|err| internal_error(format!("failed to collect coverage: {err}")))?;
+
+            let (mut files, mut lines, mut lines_hit) = (0, 0, 0);
+            for (fid, hits) in cov.line_hits(world) {
+                let Ok(path) = world.path_for_id(fid) else {
+                    continue;
+                };
+                let Ok(uri) = tinymist_query::path_to_url(path.as_path()) else {
+                    continue;
+                };
+
+                files += 1;
+                lines += hits.len();
+                lines_hit += hits.values().filter(|&&hits| hits > 0).count();
+                client.send_notification::<CoverageNotification>(&CoverageParams {
+                    uri,
+                    lines: hits
+                        .into_iter()
+                        .map(|(line, hits)| LineCoverage {
+                            line: line as u32,
+                            hits,
+                        })
+                        .collect(),
+                });
+            }
+
+            Ok(serde_json::json!({
+                "files": files,
+                "lines": lines,
+                "linesHit": lines_hit,
+            }))
+        })
+    }
+
+    /// Copy the files dropped into a document under the assets directory and
+    /// get the snippet inserting them.
+    pub fn drop_files(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
+        let path = get_arg!(args[0] as PathBuf);
+        let position = get_arg!(args[1] as lsp_types::Position);
+        let files = get_arg!(args[2] as Vec<PathBuf>);
+
+        let assets_dir = self.config.assets_path.as_deref().unwrap_or("assets");
+        let files = files
+            .into_iter()
+            .map(|file| self.copy_asset(&path, &file, assets_dir))
+            .collect::<LspResult<Vec<_>>>()?;
+        run_query!(req_id, self.DropFiles(path, position, files))
+    }
+
+    /// Rewrite the text pasted into a document, making the absolute paths
+    /// relative and converting the bare urls into links.
+    ///
+    /// The pasted files are copied under the assets directory only if it is
+    /// configured.
+    pub fn smart_paste(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
+        let path = get_arg!(args[0] as PathBuf);
+        let position = get_arg!(args[1] as lsp_types::Position);
+        let text = get_arg!(args[2] as String);
+
+        let mut copied = HashMap::new();
+        if let Some(assets_dir) = self.config.assets_path.clone() {
+            for file in tinymist_query::pasted_files(&text) {
+                if copied.contains_key(&file) {
+                    continue;
+                }
+                let copy = self.copy_asset(&path, &file, &assets_dir)?;
+                copied.insert(file, copy);
+            }
+        }
+        run_query!(req_id, self.SmartPaste(path, position, text, copied))
+    }
+
+    /// Copies a file into the assets directory of a document, unless it is
+    /// already in the project.
+    fn copy_asset(&self, doc: &Path, file: &Path, assets_dir: &str) -> LspResult<PathBuf> {
+        let doc_path: ImmutPath = doc.into();
+        if let Some(root) = self.entry_resolver().root(Some(&doc_path)) {
+            if file.starts_with(&root) {
+                return Ok(file.to_owned());
+            }
+        }
+
+        let dir = doc
+            .parent()
+            .ok_or_else(|| invalid_params("the document has no parent directory"))?
+            .join(assets_dir);
+        let name = file
+            .file_name()
+            .ok_or_else(|| invalid_params(format!("{file:?} is not a file")))?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|err| internal_error(format!("cannot create {dir:?}: {err}")))?;
+
+        let stem = Path::new(name)
+            .file_stem()
+            .unwrap_or(name)
+            .to_string_lossy();
+        let ext = Path::new(name).extension().map(|ext| ext.to_string_lossy());
+        let mut target = dir.join(name);
+        for idx in 1.. {
+            if !target.exists() {
+                break;
+            }
+            target = dir.join(match &ext {
+                Some(ext) => format!("{stem}-{idx}.{ext}"),
+                None => format!("{stem}-{idx}"),
+            });
+        }
+
+        std::fs::copy(file, &target)
+            .map_err(|err| internal_error(format!("cannot copy {file:?} to {target:?}: {err}")))?;
+        Ok(target)
+    }
+
+    /// Get the assets referenced by the project and the files in the assets
+    /// directory that are not referenced.
+    pub fn get_project_assets(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let assets_dir = get_arg_or_default!(args[0] as Option<PathBuf>).or_else(|| {
+            let assets_path = self.config.assets_path.as_deref().unwrap_or("assets");
+            Some(self.entry_resolver().root(None)?.join(assets_path))
+        });
+        run_query!(req_id, self.ProjectAssets(assets_dir))
+    }
+
+    /// Move an asset, rewriting the paths referencing it.
+    pub fn move_asset(&mut self, req_id: RequestId, mut args: Vec<JsonValue>) -> ScheduledResult {
+        let from = get_arg!(args[0] as PathBuf);
+        let to = get_arg!(args[1] as PathBuf);
+        run_query!(req_id, self.MoveAsset(from, to))
+    }
+
     /// Get all syntactic labels in workspace.
     pub fn get_workspace_labels(
         &mut self,
@@ -636,6 +1001,33 @@ impl ServerState {
         run_query!(req_id, self.WorkspaceLabel())
     }
 
+    /// Replace text in the prose of all files in workspace, returning the
+    /// edits to preview.
+    pub fn replace_in_prose(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let pattern = get_arg!(args[0] as String);
+        let replacement = get_arg!(args[1] as String);
+        let is_regex = get_arg_or_default!(args[2] as bool);
+        let match_case = get_arg_or_default!(args[3] as bool);
+        run_query!(
+            req_id,
+            self.ProseReplace(pattern, replacement, is_regex, match_case)
+        )
+    }
+
+    /// Get the index terms marked in workspace, sorted by a locale.
+    pub fn get_index_terms(
+        &mut self,
+        req_id: RequestId,
+        mut args: Vec<JsonValue>,
+    ) -> ScheduledResult {
+        let locale = get_arg_or_default!(args[0] as Option<String>);
+        run_query!(req_id, self.IndexTerms(locale))
+    }
+
     /// Get the server info.
     pub fn get_server_info(
         &mut self,
@@ -760,7 +1152,7 @@ impl ServerState {
     pub fn check_package(
         &mut self,
         info: PackageInfo,
-    ) -> LspResult<impl Future<Output = LspResult<()>>> {
+    ) -> LspResult<impl Future<Output = LspResult<Vec<PackageIssue>>>> {
         self.within_package(info.clone(), move |a| {
             tinymist_query::package::check_package(a, &info)
                 .map_err(map_string_err("failed to check package"))
@@ -803,13 +1195,55 @@ impl ServerState {
     }
 }
 
-/// Applies page selection to the export task.
-fn select_page(task: &mut ExportTask, selection: PageSelection) -> Result<()> {
-    match selection {
-        PageSelection::First => task.transform.push(ExportTransform::Pages {
+/// The coverage of the lines in a file, published by the
+/// `tinymist.runCoverage` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageParams {
+    /// The uri of the file.
+    pub uri: lsp_types::Url,
+    /// The covered lines in the file, where the lines not listed have no
+    /// executable code.
+    pub lines: Vec<LineCoverage>,
+}
+
+/// The hits of a line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineCoverage {
+    /// The 0-based line number.
+    pub line: u32,
+    /// The times the code starting at the line is executed, where `0` means
+    /// that the line is uncovered.
+    pub hits: u32,
+}
+
+/// A notification publishing the coverage of the lines in a file.
+pub(crate) enum CoverageNotification {}
+
+impl lsp_types::notification::Notification for CoverageNotification {
+    const METHOD: &'static str = "tinymist/coverage";
+    type Params = CoverageParams;
+}
+
+/// Applies page selection to the export task. The page ranges replace the
+/// first page, and the selected pages are merged in the merged selection.
+fn select_page(
+    task: &mut ExportTask,
+    selection: PageSelection,
+    pages: Option<Vec<Pages>>,
+) -> Result<()> {
+    match (selection, pages) {
+        (PageSelection::First, None) => task.transform.push(ExportTransform::Pages {
             ranges: vec![Pages::FIRST],
         }),
-        PageSelection::Merged { gap } => {
+        (PageSelection::First, Some(ranges)) => {
+            task.transform.push(ExportTransform::Pages { ranges });
+        }
+        (PageSelection::Merged { gap }, pages) => {
+            if let Some(ranges) = pages {
+                task.transform.push(ExportTransform::Pages { ranges });
+            }
             task.transform.push(ExportTransform::Merge { gap });
         }
     }
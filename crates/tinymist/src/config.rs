@@ -6,6 +6,7 @@ use clap::Parser;
 use itertools::Itertools;
 use lsp_types::*;
 use reflexo::error::IgnoreLogging;
+use reflexo::hash::FxHashMap;
 use reflexo::CowStr;
 use reflexo_typst::{ImmutPath, TypstDict};
 use serde::{Deserialize, Serialize};
@@ -13,8 +14,8 @@ use serde_json::{Map, Value as JsonValue};
 use strum::IntoEnumIterator;
 use task::{ExportUserConfig, FormatUserConfig, FormatterConfig};
 use tinymist_l10n::DebugL10n;
-use tinymist_query::analysis::{Modifier, TokenType};
-use tinymist_query::{CompletionFeat, PositionEncoding};
+use tinymist_query::analysis::{CacheLimits, Modifier, TokenType};
+use tinymist_query::{CompletionFeat, IndexFeat, PositionEncoding, SpellFeat};
 use tinymist_render::PeriscopeArgs;
 use tinymist_std::error::prelude::*;
 use tinymist_task::ExportTarget;
@@ -32,6 +33,7 @@ use crate::world::font::FontResolverImpl;
 // region Configuration Items
 const CONFIG_ITEMS: &[&str] = &[
     "tinymist",
+    "assetsPath",
     "colorTheme",
     "compileStatus",
     "completion",
@@ -44,16 +46,40 @@ const CONFIG_ITEMS: &[&str] = &[
     "formatterIndentSize",
     "formatterProseWrap",
     "hoverPeriscope",
+    "index",
     "outputPath",
     "preview",
+    "projectDiagnostics",
     "projectResolution",
     "rootPath",
     "semanticTokens",
+    "spell",
     "systemFonts",
     "typstExtraArgs",
 ];
 // endregion Configuration Items
 
+/// The name of the configuration file checked in the workspace, which is
+/// merged with the configuration from the editor.
+pub const WORKSPACE_CONFIG_FILE: &str = "tinymist.toml";
+
+/// Merges the configuration from the editor into the one checked in the
+/// workspace. The values from the editor take precedence unless they are
+/// unset, i.e. absent or null.
+fn merge_workspace_config(workspace: &mut Map<String, JsonValue>, editor: &Map<String, JsonValue>) {
+    for (key, value) in editor {
+        match (workspace.get_mut(key), value) {
+            (_, JsonValue::Null) => {}
+            (Some(JsonValue::Object(workspace)), JsonValue::Object(editor)) => {
+                merge_workspace_config(workspace, editor);
+            }
+            (_, value) => {
+                workspace.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
 /// The user configuration read from the editor.
 ///
 /// Note: `Config::default` is intentionally to be "pure" and not to be
@@ -84,6 +110,12 @@ pub struct Config {
     pub color_theme: Option<String>,
     /// The entry resolver.
     pub entry_resolver: EntryResolver,
+    /// The workspace folder of the focused document, whose configuration
+    /// file is merged. The first folder having one is used if not set.
+    pub active_folder: Option<ImmutPath>,
+    /// The configuration values last received from the editor, which are
+    /// applied again when the workspace folders change.
+    pub editor_values: Map<String, JsonValue>,
     /// The `sys.inputs` passed to the typst compiler.
     pub lsp_inputs: ImmutDict,
     /// The arguments about periscope rendering in hover window.
@@ -99,6 +131,15 @@ pub struct Config {
     pub preview: PreviewFeat,
     /// When to trigger the lint checks.
     pub lint: LintFeat,
+    /// Whether to report the diagnostics of all the files reachable from the
+    /// entry, including the ones not evaluated by the compilation.
+    pub project_diagnostics: bool,
+    /// The markers, displays and sorting locale of the index terms.
+    pub index: IndexFeat,
+    /// The language, dictionaries and word list of the spell checking.
+    pub spell: SpellFeat,
+    /// The limits of the memory used by the analysis caches.
+    pub cache: CacheLimits,
 
     /// Specifies the cli font options
     pub font_opts: CompileFontArgs,
@@ -115,6 +156,12 @@ pub struct Config {
     pub export_pdf: TaskWhen,
     /// The output directory for PDF export.
     pub output_path: PathPattern,
+    /// The entry files compiled besides the primary one, which are relative
+    /// to the root.
+    pub entries: Vec<PathBuf>,
+    /// The directory to copy the dropped or pasted files into, relative to
+    /// the document.
+    pub assets_path: Option<String>,
 
     /// Dynamic configuration for the experimental formatter.
     pub formatter_mode: FormatterMode,
@@ -255,6 +302,11 @@ impl Config {
             // Configurations in the tinymist namespace take precedence.
             if let Some(namespaced) = update.get("tinymist").and_then(JsonValue::as_object) {
                 self.update_by_map(namespaced)?;
+
+                // Both of the values are applied again when the scope changes.
+                let mut values = update.clone();
+                values.extend(namespaced.clone());
+                self.editor_values = values;
             }
 
             Ok(())
@@ -278,6 +330,19 @@ impl Config {
         );
 
         self.warnings.clear();
+        self.editor_values = update.clone();
+
+        // The configuration set in the editor takes precedence over the one checked
+        // in the workspace.
+        let merged;
+        let update = match self.workspace_config() {
+            Some(mut workspace) => {
+                merge_workspace_config(&mut workspace, update);
+                merged = workspace;
+                &merged
+            }
+            None => update,
+        };
 
         macro_rules! try_deserialize {
             ($ty:ty, $key:expr) => {
@@ -313,6 +378,7 @@ impl Config {
             };
         }
 
+        assign_config!(assets_path := "assetsPath"?: Option<String>);
         assign_config!(color_theme := "colorTheme"?: Option<String>);
         assign_config!(completion := "completion"?: CompletionFeat);
         assign_config!(completion.trigger_suggest := "triggerSuggest"?: bool);
@@ -328,8 +394,13 @@ impl Config {
         assign_config!(formatter_indent_size := "formatterIndentSize"?: Option<u32>);
         assign_config!(formatter_prose_wrap := "formatterProseWrap"?: Option<bool>);
         assign_config!(output_path := "outputPath"?: PathPattern);
+        assign_config!(entries := "entries"?: Vec<PathBuf>);
         assign_config!(preview := "preview"?: PreviewFeat);
         assign_config!(lint := "lint"?: LintFeat);
+        assign_config!(project_diagnostics := "projectDiagnostics"?: bool);
+        assign_config!(index := "index"?: IndexFeat);
+        assign_config!(spell := "spell"?: SpellFeat);
+        assign_config!(cache := "cache"?: CacheLimits);
         assign_config!(semantic_tokens := "semanticTokens"?: SemanticTokensMode);
         assign_config!(support_html_in_markdown := "supportHtmlInMarkdown"?: bool);
         assign_config!(extended_code_action := "supportExtendedCodeAction"?: bool);
@@ -472,6 +543,74 @@ impl Config {
         Ok(())
     }
 
+    /// Reads the configuration file checked in the active workspace folder,
+    /// or in the first workspace folder containing one.
+    fn workspace_config(&mut self) -> Option<Map<String, JsonValue>> {
+        let path = match &self.active_folder {
+            Some(folder) => Some(folder.join(WORKSPACE_CONFIG_FILE)).filter(|p| p.is_file()),
+            None => self
+                .entry_resolver
+                .roots
+                .iter()
+                .map(|root| root.join(WORKSPACE_CONFIG_FILE))
+                .find(|path| path.is_file()),
+        }?;
+
+        let config = std::fs::read_to_string(&path)
+            .context("read")
+            .and_then(|text| toml::from_str(&text).context_ut("parse"));
+        match config {
+            Ok(config) => Some(config),
+            Err(err) => {
+                self.warnings.push(tinymist_l10n::t!(
+                    "tinymist.config.badWorkspaceConfig",
+                    "failed to load {path}: {err}",
+                    path = path.debug_l10n(),
+                    err = err.debug_l10n(),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Scopes the configuration to a workspace folder, which merges the
+    /// configuration file of the folder, e.g. to resolve the entries and the
+    /// packages of the folder.
+    pub fn scoped(&self, folder: ImmutPath) -> Self {
+        let mut config = self.clone();
+        config.active_folder = Some(folder.clone());
+        config
+            .update_by_map(&self.editor_values)
+            .log_error("failed to scope the configuration to the workspace folder");
+
+        // The package directories set by the folder are relative to it.
+        if let Some(extras) = &mut config.typst_extra_args {
+            let package = &mut extras.package;
+            for path in [&mut package.package_path, &mut package.package_cache_path] {
+                if let Some(path) = path.as_mut().filter(|path| path.is_relative()) {
+                    *path = folder.join(&*path);
+                }
+            }
+        }
+
+        config
+    }
+
+    /// Gets the entry files compiled besides the primary one.
+    pub fn entries(&self) -> Vec<ImmutPath> {
+        let root = self
+            .active_folder
+            .clone()
+            .or_else(|| self.entry_resolver.root(None));
+        let entries = self.entries.iter().filter_map(|entry| {
+            if entry.is_absolute() {
+                return Some(entry.as_path().into());
+            }
+            Some(root.as_ref()?.join(entry).as_path().into())
+        });
+        entries.collect()
+    }
+
     /// Gets the formatter configuration.
     pub fn formatter(&self) -> FormatUserConfig {
         let formatter_print_width = self.formatter_print_width.unwrap_or(120) as usize;
@@ -527,6 +666,8 @@ impl Config {
                 export,
                 pdf_standards: self.pdf_standards().unwrap_or_default(),
                 creation_timestamp: self.creation_timestamp(),
+                image_max_dpi: None,
+                tagged: false,
             }),
             count_words: self.notify_status,
             development: self.development,
@@ -643,29 +784,134 @@ impl Config {
         Option<bool>,
         &Vec<PathBuf>,
         Option<&CompileFontArgs>,
+        Option<&CompilePackageArgs>,
         Option<Arc<Path>>,
     ) {
         (
             self.system_fonts,
             &self.font_paths,
             self.typst_extra_args.as_ref().map(|e| &e.font),
+            self.typst_extra_args.as_ref().map(|e| &e.package),
             self.entry_resolver
                 .root(self.entry_resolver.resolve_default().as_ref()),
         )
     }
 }
 
+/// The configurations scoped to the workspace folders.
+///
+/// The documents in a workspace folder are resolved and compiled by the
+/// configuration of the folder, i.e. the configuration from the editor merged
+/// with the configuration file of the folder, while the documents outside the
+/// folders are resolved by the configuration of the whole workspace.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct FolderConfigs {
+    /// The configuration of the whole workspace, whose roots are the folders.
+    pub workspace: Config,
+    /// The configurations scoped to the folders.
+    pub scoped: FxHashMap<ImmutPath, Config>,
+}
+
+impl FolderConfigs {
+    /// Scopes the configuration of the whole workspace to its folders.
+    pub fn new(workspace: Config) -> Self {
+        let mut folders = Self {
+            workspace,
+            scoped: FxHashMap::default(),
+        };
+        folders.rescope();
+        folders
+    }
+
+    /// Scopes the configuration of the whole workspace to its folders again,
+    /// e.g. after the configuration or the configuration files change.
+    pub fn rescope(&mut self) {
+        let roots = self.workspace.entry_resolver.roots.iter();
+        let scoped = roots.map(|folder| (folder.clone(), self.workspace.scoped(folder.clone())));
+        self.scoped = scoped.collect();
+    }
+
+    /// Finds the folder containing a path, the innermost one if the folders
+    /// are nested.
+    pub fn folder(&self, path: &Path) -> Option<ImmutPath> {
+        self.workspace.entry_resolver.folder(path)
+    }
+
+    /// Gets the configuration of a folder, or the one of the whole workspace
+    /// if the folder is `None`.
+    pub fn get(&self, folder: Option<&ImmutPath>) -> &Config {
+        folder
+            .and_then(|folder| self.scoped.get(folder))
+            .unwrap_or(&self.workspace)
+    }
+
+    /// Gets the configuration of the folder containing a path.
+    pub fn of_path(&self, path: &Path) -> &Config {
+        self.get(self.folder(path).as_ref())
+    }
+
+    /// Adds and removes the folders. Returns the removed folders.
+    pub fn change(&mut self, event: &WorkspaceFoldersChangeEvent) -> Vec<ImmutPath> {
+        let to_path =
+            |folder: &WorkspaceFolder| folder.uri.to_file_path().ok().map(ImmutPath::from);
+        let removed = event.removed.iter().filter_map(to_path).collect::<Vec<_>>();
+
+        let roots = &mut self.workspace.entry_resolver.roots;
+        roots.retain(|root| !removed.contains(root));
+        for folder in event.added.iter().filter_map(to_path) {
+            if !roots.contains(&folder) {
+                roots.push(folder);
+            }
+        }
+
+        // The configuration of the whole workspace merges the configuration
+        // file of its first folder having one.
+        let values = self.workspace.editor_values.clone();
+        self.workspace
+            .update_by_map(&values)
+            .log_error("failed to update the configuration of the workspace");
+        self.rescope();
+
+        removed
+    }
+
+    /// Gets the entry files of all the folders, which are compiled besides the
+    /// primary one.
+    pub fn entries(&self) -> Vec<ImmutPath> {
+        if self.scoped.is_empty() {
+            return self.workspace.entries();
+        }
+
+        let mut entries = vec![];
+        for root in &self.workspace.entry_resolver.roots {
+            let Some(config) = self.scoped.get(root) else {
+                continue;
+            };
+            for entry in config.entries() {
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    }
+}
+
 /// Configuration set at initialization that won't change within a single
 /// session.
 #[derive(Debug, Clone)]
 pub struct ConstConfig {
-    /// Determined position encoding, either UTF-8 or UTF-16.
+    /// Determined position encoding, either UTF-8, UTF-16 or UTF-32.
     /// Defaults to UTF-16 if not specified.
     pub position_encoding: PositionEncoding,
     /// Allow dynamic registration of configuration changes.
     pub cfg_change_registration: bool,
+    /// Allow dynamic registration of watched files.
+    pub watch_files_registration: bool,
     /// Allow notifying workspace/didRenameFiles
     pub notify_will_rename_files: bool,
+    /// Allow the versioned `documentChanges` in the workspace edits.
+    pub document_changes: bool,
     /// Allow dynamic registration of semantic tokens.
     pub tokens_dynamic_registration: bool,
     /// Allow overlapping tokens.
@@ -676,6 +922,11 @@ pub struct ConstConfig {
     pub doc_line_folding_only: bool,
     /// Allow dynamic registration of document formatting.
     pub doc_fmt_dynamic_registration: bool,
+    /// Allow server-initiated work done progress.
+    pub work_done_progress: bool,
+    /// Whether the client pulls diagnostics and is able to refresh them on
+    /// request of the server.
+    pub pull_diagnostics: bool,
     /// The locale of the editor.
     pub locale: Option<String>,
 }
@@ -695,8 +946,14 @@ impl From<&InitializeParams> for ConstConfig {
             let encodings = try_(|| Some(general?.position_encodings.as_ref()?.as_slice()));
             let encodings = encodings.unwrap_or(DEFAULT_ENCODING);
 
+            // Prefers UTF-8, which is native to Typst sources, and falls back to the
+            // mandatory UTF-16 unless the client only supports UTF-32.
             if encodings.contains(&PositionEncodingKind::UTF8) {
                 PositionEncoding::Utf8
+            } else if !encodings.contains(&PositionEncodingKind::UTF16)
+                && encodings.contains(&PositionEncodingKind::UTF32)
+            {
+                PositionEncoding::Utf32
             } else {
                 PositionEncoding::Utf16
             }
@@ -704,10 +961,15 @@ impl From<&InitializeParams> for ConstConfig {
 
         let workspace = params.capabilities.workspace.as_ref();
         let file_operations = try_(|| workspace?.file_operations.as_ref());
+        let workspace_edit = try_(|| workspace?.workspace_edit.as_ref());
+        let watched_files = try_(|| workspace?.did_change_watched_files.as_ref());
         let doc = params.capabilities.text_document.as_ref();
         let sema = try_(|| doc?.semantic_tokens.as_ref());
         let fold = try_(|| doc?.folding_range.as_ref());
         let format = try_(|| doc?.formatting.as_ref());
+        let pull_diag = try_(|| doc?.diagnostic.as_ref()).is_some();
+        let diag_refresh = try_or(|| workspace?.diagnostic.as_ref()?.refresh_support, false);
+        let window = params.capabilities.window.as_ref();
 
         let locale = params
             .initialization_options
@@ -718,12 +980,16 @@ impl From<&InitializeParams> for ConstConfig {
         Self {
             position_encoding,
             cfg_change_registration: try_or(|| workspace?.configuration, false),
+            watch_files_registration: try_or(|| watched_files?.dynamic_registration, false),
             notify_will_rename_files: try_or(|| file_operations?.will_rename, false),
+            document_changes: try_or(|| workspace_edit?.document_changes, false),
             tokens_dynamic_registration: try_or(|| sema?.dynamic_registration, false),
             tokens_overlapping_token_support: try_or(|| sema?.overlapping_token_support, false),
             tokens_multiline_token_support: try_or(|| sema?.multiline_token_support, false),
             doc_line_folding_only: try_or(|| fold?.line_folding_only, true),
             doc_fmt_dynamic_registration: try_or(|| format?.dynamic_registration, false),
+            work_done_progress: try_or(|| window?.work_done_progress, false),
+            pull_diagnostics: pull_diag && diag_refresh,
             locale: locale.map(ToOwned::to_owned),
         }
     }
@@ -907,6 +1173,35 @@ mod tests {
         assert_eq!(cc.position_encoding, PositionEncoding::Utf16);
     }
 
+    #[test]
+    fn test_negotiate_encoding() {
+        let negotiate = |encodings: &[PositionEncodingKind]| {
+            let params = InitializeParams {
+                capabilities: ClientCapabilities {
+                    general: Some(GeneralClientCapabilities {
+                        position_encodings: Some(encodings.to_vec()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            ConstConfig::from(&params).position_encoding
+        };
+
+        use PositionEncodingKind as Kind;
+        assert_eq!(
+            negotiate(&[Kind::UTF16, Kind::UTF8]),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(
+            negotiate(&[Kind::UTF32, Kind::UTF16]),
+            PositionEncoding::Utf16
+        );
+        assert_eq!(negotiate(&[Kind::UTF32]), PositionEncoding::Utf32);
+        assert_eq!(negotiate(&[]), PositionEncoding::Utf16);
+    }
+
     #[test]
     fn test_config_update() {
         let mut config = Config::default();
@@ -970,6 +1265,124 @@ mod tests {
         assert_eq!(config.export_pdf, TaskWhen::OnType);
     }
 
+    #[test]
+    fn test_merge_workspace_config() {
+        let merge = |workspace: JsonValue, editor: JsonValue| {
+            let mut workspace = workspace.as_object().unwrap().clone();
+            let editor = editor.as_object().unwrap();
+            merge_workspace_config(&mut workspace, editor);
+            JsonValue::Object(workspace)
+        };
+
+        let workspace = json!({
+            "exportPdf": "onSave",
+            "formatterPrintWidth": 80,
+            "lint": { "enabled": true, "when": "onType" },
+        });
+        // The absent and null values sent by the editor don't shadow the file.
+        let editor = json!({
+            "formatterPrintWidth": null,
+            "lint": { "when": null },
+            "outputPath": "",
+        });
+        assert_eq!(
+            merge(workspace.clone(), editor),
+            json!({
+                "exportPdf": "onSave",
+                "formatterPrintWidth": 80,
+                "lint": { "enabled": true, "when": "onType" },
+                "outputPath": "",
+            })
+        );
+
+        // The values set by the editor take precedence, even if they are the
+        // defaults of the editor.
+        let editor = json!({
+            "exportPdf": "never",
+            "formatterPrintWidth": 100,
+            "lint": { "enabled": false },
+            "rootPath": "/root",
+        });
+        assert_eq!(
+            merge(workspace, editor),
+            json!({
+                "exportPdf": "never",
+                "formatterPrintWidth": 100,
+                "lint": { "enabled": false, "when": "onType" },
+                "rootPath": "/root",
+            })
+        );
+    }
+
+    #[test]
+    fn test_workspace_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let file = "exportPdf = \"onSave\"\nformatterPrintWidth = 80\n";
+        std::fs::write(root.join(WORKSPACE_CONFIG_FILE), file).unwrap();
+
+        let mut config = Config::default();
+        config.entry_resolver.roots = vec![root.into()];
+
+        let update = json!({ "formatterPrintWidth": null });
+        good_config(&mut config, &update);
+        assert_eq!(config.export_pdf, TaskWhen::OnSave);
+        assert_eq!(config.formatter_print_width, Some(80));
+
+        let update = json!({ "exportPdf": "never", "formatterPrintWidth": null });
+        good_config(&mut config, &update);
+        assert_eq!(config.export_pdf, TaskWhen::Never);
+        assert_eq!(config.formatter_print_width, Some(80));
+    }
+
+    #[test]
+    fn test_folder_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let [a, b, c] = ["a", "b", "c"].map(|folder| root.join(folder));
+        for folder in [&a, &b, &c] {
+            std::fs::create_dir_all(folder).unwrap();
+        }
+        let file = "formatterPrintWidth = 80\nentries = [\"slides.typ\"]\ntypstExtraArgs = [\"--package-path\", \"packages\"]\n";
+        std::fs::write(a.join(WORKSPACE_CONFIG_FILE), file).unwrap();
+        std::fs::write(c.join(WORKSPACE_CONFIG_FILE), "formatterPrintWidth = 100\n").unwrap();
+
+        let mut workspace = Config::default();
+        workspace.entry_resolver.roots = vec![a.as_path().into(), b.as_path().into()];
+        good_config(&mut workspace, &json!({ "formatterPrintWidth": null }));
+        let mut folders = FolderConfigs::new(workspace);
+
+        let config = folders.of_path(&a.join("main.typ"));
+        assert_eq!(config.active_folder.as_deref(), Some(a.as_path()));
+        assert_eq!(config.formatter_print_width, Some(80));
+        assert_eq!(config.package_opts().package_path, Some(a.join("packages")));
+        let config = folders.of_path(&b.join("main.typ"));
+        assert_eq!(config.formatter_print_width, None);
+        assert_eq!(config.package_opts().package_path, None);
+        assert_eq!(folders.entries(), [ImmutPath::from(a.join("slides.typ"))]);
+
+        // Removes the folder `a` and adds the folder `c`.
+        let folder = |path: &Path| WorkspaceFolder {
+            uri: Url::from_file_path(path).unwrap(),
+            name: String::new(),
+        };
+        let removed = folders.change(&WorkspaceFoldersChangeEvent {
+            added: vec![folder(&c)],
+            removed: vec![folder(&a)],
+        });
+        assert_eq!(removed, [ImmutPath::from(a.as_path())]);
+        let roots = &folders.workspace.entry_resolver.roots;
+        assert_eq!(roots, &[ImmutPath::from(b.as_path()), c.as_path().into()]);
+
+        let config = folders.of_path(&c.join("main.typ"));
+        assert_eq!(config.formatter_print_width, Some(100));
+        // The files in the removed folder are outside the workspace folders.
+        let config = folders.of_path(&a.join("main.typ"));
+        assert_eq!(config.active_folder, None);
+        assert_eq!(config.package_opts().package_path, None);
+        assert!(folders.entries().is_empty());
+    }
+
     #[test]
     fn test_compile_status() {
         let mut config = Config::default();
@@ -1,8 +1,10 @@
 #![allow(unused)]
 
 mod event;
+mod exception;
 mod init;
 mod request;
+mod variables;
 
 pub use init::*;
 
@@ -11,14 +13,18 @@ use serde::{Deserialize, Serialize};
 use sync_ls::{invalid_request, LspResult};
 use tinymist_query::PositionEncoding;
 // use sync_lsp::RequestId;
+use typst::foundations::Scope;
 use typst::syntax::{FileId, Source};
 
 use crate::project::LspCompileSnapshot;
 use crate::{ConstDapConfig, ServerState};
+use variables::VariableStore;
 
 #[derive(Default)]
 pub(crate) struct DebugState {
     pub(crate) session: Option<DebugSession>,
+    /// The enabled exception filters, which may be set before the launch.
+    pub(crate) exception_filters: Vec<String>,
 }
 
 impl DebugState {
@@ -27,6 +33,12 @@ impl DebugState {
             .as_ref()
             .ok_or_else(|| invalid_request("No active debug session"))
     }
+
+    pub(crate) fn session_mut(&mut self) -> LspResult<&mut DebugSession> {
+        self.session
+            .as_mut()
+            .ok_or_else(|| invalid_request("No active debug session"))
+    }
 }
 
 pub(crate) struct DebugSession {
@@ -39,13 +51,43 @@ pub(crate) struct DebugSession {
     /// Whether the debugger should stop on entry.
     stop_on_entry: bool,
 
+    /// The frame where the debugger is stopped.
+    frame: DebugFrame,
+    /// The variables exposed to the client while the debugger is stopped.
+    variables: VariableStore,
+}
+
+/// The id of the only stack frame. We don't track the calls yet, so the
+/// debugger is always stopped in a single frame.
+const FRAME_ID: u64 = 1;
+
+/// A frame where the debugger is stopped.
+pub(crate) struct DebugFrame {
+    /// The name of the frame, e.g. the name of the called function.
+    name: String,
     /// The current source file.
     source: Source,
     /// The current position.
     position: usize,
+    /// The local bindings, e.g. the arguments of the called function.
+    locals: Scope,
+    /// The bindings captured by the closure of the frame.
+    captures: Scope,
+    /// The bindings at the top level of the module.
+    module: Scope,
+}
+
+impl DebugFrame {
+    /// Gets the bindings visible in the frame, where the locals shadow the
+    /// captures, and the captures shadow the module bindings.
+    fn scope(&self) -> Scope {
+        let mut scope = self.module.clone();
+        for (name, binding) in self.captures.iter().chain(self.locals.iter()) {
+            scope.bind(name.clone(), binding.clone());
+        }
+        scope
+    }
 }
-// private _variableHandles = new Handles<"locals" | "globals" |
-// RuntimeVariable>();
 
 //     private _valuesInHex = false;
 //     private _useInvalidatedEvent = false;
@@ -0,0 +1,174 @@
+//! Stops on the exceptions raised by the compilation, i.e. the errors, the
+//! panics and the failed assertions.
+
+use std::sync::Arc;
+
+use comemo::Tracked;
+use dapts::StoppedEventReason;
+use parking_lot::Mutex;
+use sync_ls::{just_ok, SchedulableResponse};
+use tinymist_debug::{set_debug_session, DebugSessionHandler, Exception, ExceptionKind};
+use tinymist_project::LspWorld;
+use tinymist_std::typst::TypstPagedDocument;
+use typst::engine::Engine;
+use typst::foundations::{Binding, Context, Scope, Scopes};
+use typst::syntax::Span;
+use typst::World;
+
+use super::*;
+
+/// The filter stopping on the errors of the compilation.
+pub(crate) const ERROR_FILTER: &str = "error";
+/// The filter stopping on the calls of `panic`.
+pub(crate) const PANIC_FILTER: &str = "panic";
+/// The filter stopping on the failed assertions.
+pub(crate) const ASSERTION_FILTER: &str = "assertion";
+
+/// Records the first exception raised by the compilation.
+#[derive(Default)]
+struct ExceptionRecorder {
+    exception: Mutex<Option<Exception>>,
+}
+
+impl DebugSessionHandler for ExceptionRecorder {
+    fn on_breakpoint(
+        &self,
+        _engine: &Engine,
+        _context: Tracked<Context>,
+        _scopes: Scopes,
+        _span: Span,
+        _kind: tinymist_debug::BreakpointKind,
+    ) {
+    }
+
+    fn on_exception(&self, exception: &Exception) {
+        self.exception
+            .lock()
+            .get_or_insert_with(|| exception.clone());
+    }
+}
+
+/// An exception where the debugger stops.
+struct StoppedException {
+    /// The frame raising the exception.
+    frame: DebugFrame,
+    /// The error message of the exception.
+    message: String,
+}
+
+/// Compiles the document and finds the first exception matching the filters.
+fn find_exception(world: &LspWorld, filters: &[String]) -> Option<StoppedException> {
+    let enabled = |filter: &str| filters.iter().any(|it| it == filter);
+    if !(enabled(ERROR_FILTER) || enabled(PANIC_FILTER) || enabled(ASSERTION_FILTER)) {
+        return None;
+    }
+
+    let recorder = Arc::new(ExceptionRecorder::default());
+    let session = tinymist_debug::DebugSession::new(recorder.clone());
+    if !set_debug_session(Some(session)) {
+        log::warn!("failed to catch the exceptions: another debug session is running");
+        return None;
+    }
+    let instr = tinymist_debug::instr_exceptions(world);
+    let result = typst::compile::<TypstPagedDocument>(&instr).output;
+    set_debug_session(None);
+    let errors = result.err()?;
+
+    if let Some(exception) = recorder.exception.lock().take() {
+        let filter = match exception.kind {
+            ExceptionKind::Panic => PANIC_FILTER,
+            ExceptionKind::Assertion => ASSERTION_FILTER,
+        };
+        if enabled(filter) {
+            let mut locals = Scope::new();
+            for (name, value) in exception.args {
+                locals.bind(name, Binding::detached(value));
+            }
+            return Some(StoppedException {
+                frame: exception_frame(world, exception.name.into(), exception.span, locals)?,
+                message: exception.message.into(),
+            });
+        }
+    }
+
+    if !enabled(ERROR_FILTER) {
+        return None;
+    }
+    let error = errors.iter().find(|error| error.span.id().is_some())?;
+    let name = error
+        .span
+        .id()?
+        .vpath()
+        .as_rootless_path()
+        .display()
+        .to_string();
+    Some(StoppedException {
+        frame: exception_frame(world, name, error.span, Scope::new())?,
+        message: error.message.to_string(),
+    })
+}
+
+/// Creates the frame at the span raising an exception.
+fn exception_frame(
+    world: &LspWorld,
+    name: String,
+    span: Span,
+    locals: Scope,
+) -> Option<DebugFrame> {
+    let source = world.source(span.id()?).ok()?;
+    let position = source.range(span)?.start;
+    // The bindings are unknown if the exception is raised while evaluating the
+    // top level of the module.
+    let module = typst_shim::eval::eval_compat(world, &source)
+        .map(|module| module.scope().clone())
+        .unwrap_or_else(|_| Scope::new());
+
+    Some(DebugFrame {
+        name,
+        source,
+        position,
+        locals,
+        captures: Scope::new(),
+        module,
+    })
+}
+
+impl ServerState {
+    pub(crate) fn set_exception_breakpoints(
+        &mut self,
+        args: dapts::SetExceptionBreakpointsArguments,
+    ) -> SchedulableResponse<dapts::SetExceptionBreakpointsResponse> {
+        self.debug.exception_filters = args.filters;
+        // The filters may be changed after the launch.
+        self.stop_on_exception();
+
+        just_ok(dapts::SetExceptionBreakpointsResponse::default())
+    }
+
+    /// Stops at the first exception matching the filters, if any.
+    pub(crate) fn stop_on_exception(&mut self) -> bool {
+        let filters = self.debug.exception_filters.clone();
+        let Ok(session) = self.debug.session_mut() else {
+            return false;
+        };
+        let Some(exception) = find_exception(&session.snapshot.world, &filters) else {
+            return false;
+        };
+
+        let description = format!("Paused on exception in {}", exception.frame.name);
+        session.frame = exception.frame;
+        session.variables = VariableStore::default();
+        self.client
+            .send_dap_event::<dapts::event::Stopped>(dapts::StoppedEvent {
+                all_threads_stopped: Some(true),
+                reason: StoppedEventReason::Exception,
+                description: Some(description),
+                thread_id: Some(session.thread_id),
+                hit_breakpoint_ids: None,
+                preserve_focus_hint: Some(false),
+                text: Some(exception.message),
+            });
+
+        true
+    }
+}
@@ -2,6 +2,7 @@ use dapts::InitializeRequestArguments;
 use sync_ls::*;
 use tinymist_project::CompileFontArgs;
 
+use super::exception::{ASSERTION_FILTER, ERROR_FILTER, PANIC_FILTER};
 use crate::{Config, ServerState};
 
 /// The regular initializer.
@@ -89,21 +90,28 @@ impl Initializer for SuperInit {
             supports_exception_info_request: Some(false),
             exception_breakpoint_filters: Some(vec![
                 dapts::ExceptionBreakpointsFilter {
-                    filter: "layoutIterationException".into(),
-                    label: "Layout Iteration Exception".into(),
-                    description: Some("Break on each layout iteration.".into()),
+                    filter: ERROR_FILTER.into(),
+                    label: "Errors".into(),
+                    description: Some("Break on the errors of the compilation.".into()),
                     default: Some(false),
-                    supports_condition: Some(true),
-                    condition_description: Some(
-                        "Enter a typst expression to stop on specific layout iterator.
-                        e.g. `iterate-step == 3 and sys.inputs.target == \"html\"`"
-                            .into(),
-                    ),
+                    supports_condition: Some(false),
+                    condition_description: None,
                 },
                 dapts::ExceptionBreakpointsFilter {
-                    filter: "otherExceptions".into(),
-                    label: "Other Exceptions".into(),
-                    description: Some("This is a other exception".into()),
+                    filter: PANIC_FILTER.into(),
+                    label: "Panics".into(),
+                    description: Some("Break on the calls of `panic`.".into()),
+                    default: Some(true),
+                    supports_condition: Some(false),
+                    condition_description: None,
+                },
+                dapts::ExceptionBreakpointsFilter {
+                    filter: ASSERTION_FILTER.into(),
+                    label: "Failed Assertions".into(),
+                    description: Some(
+                        "Break on the failed calls of `assert`, `assert.eq` and `assert.ne`."
+                            .into(),
+                    ),
                     default: Some(true),
                     supports_condition: Some(false),
                     condition_description: None,
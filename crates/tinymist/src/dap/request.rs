@@ -1,3 +1,4 @@
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
 use comemo::Track;
@@ -5,10 +6,15 @@ use dapts::{CompletionItem, ProcessEventStartMethod, StoppedEventReason, ThreadE
 use reflexo::ImmutPath;
 use reflexo_typst::{EntryReader, TaskInputs};
 use serde::Deserialize;
-use sync_ls::{internal_error, invalid_params, invalid_request, just_ok, SchedulableResponse};
+use sync_ls::{
+    internal_error, invalid_params, invalid_request, just_ok, ResponseError, SchedulableResponse,
+};
+use tinymist_project::LspWorld;
 use tinymist_std::error::prelude::*;
 use typst::{
-    foundations::Repr,
+    diag::{SourceDiagnostic, SourceResult},
+    ecow::EcoVec,
+    foundations::{Repr, Scope, Value},
     routines::EvalMode,
     syntax::{LinkedNode, Span},
     World,
@@ -117,6 +123,9 @@ impl ServerState {
         let main_source = world.source(main).map_err(invalid_request)?;
         let main_eof = main_source.text().len();
         let source = main_source.clone();
+        let module = typst_shim::eval::eval_compat(world, &source)
+            .map(|module| module.scope().clone())
+            .unwrap_or_else(|_| Scope::new());
 
         self.debug.session = Some(DebugSession {
             config: self.config.const_dap_config.clone(),
@@ -125,8 +134,15 @@ impl ServerState {
             thread_id: 1,
             // Since we haven't implemented breakpoints, we can only stop intermediately and
             // response completions in repl console.
-            source,
-            position: main_eof,
+            frame: DebugFrame {
+                name: main.vpath().as_rootless_path().display().to_string(),
+                source,
+                position: main_eof,
+                locals: Scope::new(),
+                captures: Scope::new(),
+                module,
+            },
+            variables: VariableStore::default(),
         });
 
         self.client
@@ -142,6 +158,10 @@ impl ServerState {
                 thread_id: self.debug.session()?.thread_id,
             });
 
+        if self.stop_on_exception() {
+            return just_ok(());
+        }
+
         // Since we haven't implemented breakpoints, we can only stop intermediately and
         // response completions in repl console.
         let _ = self.debug.session()?.stop_on_entry;
@@ -190,6 +210,27 @@ impl ServerState {
             }],
         })
     }
+
+    pub(crate) fn debug_stack_trace(
+        &mut self,
+        _args: dapts::StackTraceArguments,
+    ) -> SchedulableResponse<dapts::StackTraceResponse> {
+        let session = self.debug.session()?;
+        let frame = &session.frame;
+        let pos = session.to_dap_position(frame.position, &frame.source);
+
+        just_ok(dapts::StackTraceResponse {
+            stack_frames: vec![dapts::StackFrame {
+                id: FRAME_ID,
+                name: frame.name.clone(),
+                source: Some(session.to_dap_source(frame.source.id())),
+                line: pos.line,
+                column: pos.character,
+                ..dapts::StackFrame::default()
+            }],
+            total_frames: Some(1),
+        })
+    }
 }
 
 impl ServerState {
@@ -197,32 +238,56 @@ impl ServerState {
         &mut self,
         args: dapts::EvaluateArguments,
     ) -> SchedulableResponse<dapts::EvaluateResponse> {
-        let session = self.debug.session()?;
-        let world = &session.snapshot.world;
-        let library = &world.library;
-
-        let root = session.source.root();
-        let span = LinkedNode::new(root)
-            .leaf_at_compat(session.position)
-            .map(|node| node.span())
-            .unwrap_or_else(Span::detached);
-
-        let source = typst_shim::eval::eval_compat(&world, &session.source)
-            .map_err(|e| invalid_params(format!("{e:?}")))?;
+        // Without a debug session, the snippets are evaluated at the end of the
+        // focused document.
+        if self.debug.session.is_none() {
+            let graph = self.snapshot().map_err(internal_error)?;
+            let world = graph.world();
+            let main = world
+                .main_id()
+                .ok_or_else(|| invalid_request("No focused document found"))?;
+            let source = world.source(main).map_err(invalid_request)?;
+            let scope = typst_shim::eval::eval_compat(world, &source)
+                .map(|module| module.scope().clone())
+                .unwrap_or_else(|_| Scope::new());
+
+            let val = eval_snippet(world, &source, source.text().len(), &args.expression, scope)
+                .map_err(eval_error)?;
+            return just_ok(dapts::EvaluateResponse {
+                result: val.repr().to_string(),
+                ty: Some(val.ty().repr().to_string()),
+                ..dapts::EvaluateResponse::default()
+            });
+        }
 
-        let val = typst_shim::eval::eval_string(
-            &typst::ROUTINES,
-            (world as &dyn World).track(),
+        // The snippets are evaluated against the bindings of the stopped frame.
+        // The client evaluates the watch expressions again on every stop.
+        let session = self.debug.session_mut()?;
+        let frame = &session.frame;
+        let val = eval_snippet(
+            &session.snapshot.world,
+            &frame.source,
+            frame.position,
             &args.expression,
-            span,
-            EvalMode::Code,
-            source.scope().clone(),
+            frame.scope(),
         )
-        .map_err(|e| invalid_params(format!("{e:?}")))?;
+        .map_err(eval_error)?;
+
+        // The console prints the whole repr, while the watch view and the
+        // hovers show a truncated one.
+        let repr = val.repr().to_string();
+        let var = session.variables.variable(args.expression, val, false);
+        let result = match args.context.as_deref() {
+            Some("watch" | "hover") => var.value,
+            _ => repr,
+        };
 
         just_ok(dapts::EvaluateResponse {
-            result: format!("{}", val.repr()),
-            ty: Some(format!("{}", val.ty().repr())),
+            result,
+            ty: var.ty,
+            variables_reference: var.variables_reference,
+            named_variables: var.named_variables,
+            indexed_variables: var.indexed_variables,
             ..dapts::EvaluateResponse::default()
         })
     }
@@ -241,3 +306,38 @@ impl ServerState {
         just_ok(dapts::CompletionsResponse { targets: vec![] })
     }
 }
+
+/// Evaluates a code snippet at a position of a source file.
+fn eval_snippet(
+    world: &LspWorld,
+    source: &Source,
+    position: usize,
+    snippet: &str,
+    scope: Scope,
+) -> SourceResult<Value> {
+    let span = LinkedNode::new(source.root())
+        .leaf_at_compat(position)
+        .map(|node| node.span())
+        .unwrap_or_else(Span::detached);
+
+    typst_shim::eval::eval_string(
+        &typst::ROUTINES,
+        (world as &dyn World).track(),
+        snippet,
+        span,
+        EvalMode::Code,
+        scope,
+    )
+}
+
+/// Prints the errors of an evaluation along with their hints.
+fn eval_error(errors: EcoVec<SourceDiagnostic>) -> ResponseError {
+    let mut message = String::new();
+    for error in errors.iter() {
+        let _ = writeln!(message, "error: {}", error.message);
+        for hint in &error.hints {
+            let _ = writeln!(message, "  hint: {hint}");
+        }
+    }
+    invalid_params(message.trim_end().to_owned())
+}
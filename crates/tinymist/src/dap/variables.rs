@@ -0,0 +1,232 @@
+//! The variables of a stopped frame, which are expanded lazily by the client.
+
+use sync_ls::{invalid_params, just_ok, SchedulableResponse};
+use typst::foundations::{func::Repr as FuncRepr, Repr, Scope, Value};
+
+use super::*;
+
+/// The maximum length of the values shown in the variables view.
+const MAX_VALUE_LEN: usize = 200;
+
+/// A node that can be expanded into variables.
+enum VariableNode {
+    /// The bindings of a scope.
+    Scope(Scope),
+    /// The fields, items or bindings of a value.
+    Value(Value),
+}
+
+/// The variables handed out to the client, referenced by their index plus
+/// one, since the reference `0` means that a variable has no children.
+///
+/// The references are only valid while the debugger is stopped, so a new
+/// store is created on each stop.
+#[derive(Default)]
+pub(crate) struct VariableStore {
+    nodes: Vec<VariableNode>,
+}
+
+impl VariableStore {
+    fn alloc(&mut self, node: VariableNode) -> u64 {
+        self.nodes.push(node);
+        self.nodes.len() as u64
+    }
+
+    /// Expands the variable of a reference. Only the children in the range
+    /// are computed, and their own children are kept until they are expanded.
+    fn children(
+        &mut self,
+        reference: u64,
+        start: usize,
+        count: usize,
+    ) -> Option<Vec<dapts::Variable>> {
+        let node = self.nodes.get(reference.checked_sub(1)? as usize)?;
+
+        let children: Vec<(String, Value, bool)> = match node {
+            VariableNode::Scope(scope) => bindings(scope),
+            VariableNode::Value(value) => match value {
+                Value::Dict(dict) => dict
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.clone(), false))
+                    .collect(),
+                Value::Array(array) => array
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, value)| (idx.to_string(), value.clone(), false))
+                    .collect(),
+                Value::Content(content) => content
+                    .fields()
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value, false))
+                    .collect(),
+                Value::Args(args) => args
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, arg)| {
+                        let name = arg
+                            .name
+                            .as_ref()
+                            .map_or(idx.to_string(), |name| name.to_string());
+                        (name, arg.value.v.clone(), false)
+                    })
+                    .collect(),
+                Value::Module(module) => bindings(module.scope()),
+                Value::Func(func) => match func.inner() {
+                    FuncRepr::Closure(closure) => bindings(&closure.captured),
+                    _ => vec![],
+                },
+                _ => vec![],
+            },
+        };
+
+        let children = children.into_iter().skip(start).take(count);
+        let variables = children.map(|(name, value, bound)| self.variable(name, value, bound));
+        Some(variables.collect())
+    }
+
+    /// Creates a variable, whose children are expanded on demand.
+    pub fn variable(&mut self, name: String, value: Value, bound: bool) -> dapts::Variable {
+        let (named, indexed) = child_count(&value);
+        let variables_reference = if named + indexed > 0 {
+            self.alloc(VariableNode::Value(value.clone()))
+        } else {
+            0
+        };
+
+        dapts::Variable {
+            // Only the bindings can be evaluated by their names.
+            evaluate_name: bound.then(|| name.clone()),
+            name,
+            value: truncate(value.repr().to_string()),
+            ty: Some(value.ty().repr().to_string()),
+            variables_reference,
+            named_variables: (named > 0).then_some(named as u64),
+            indexed_variables: (indexed > 0).then_some(indexed as u64),
+            ..dapts::Variable::default()
+        }
+    }
+}
+
+/// Collects the bindings of a scope.
+fn bindings(scope: &Scope) -> Vec<(String, Value, bool)> {
+    scope
+        .iter()
+        .map(|(name, binding)| (name.to_string(), binding.read().clone(), true))
+        .collect()
+}
+
+/// Counts the named and indexed children of a value.
+fn child_count(value: &Value) -> (usize, usize) {
+    match value {
+        Value::Dict(dict) => (dict.len(), 0),
+        Value::Array(array) => (0, array.len()),
+        Value::Content(content) => (content.fields().len(), 0),
+        Value::Args(args) => (args.items.len(), 0),
+        Value::Module(module) => (module.scope().iter().count(), 0),
+        Value::Func(func) => match func.inner() {
+            FuncRepr::Closure(closure) => (closure.captured.iter().count(), 0),
+            _ => (0, 0),
+        },
+        _ => (0, 0),
+    }
+}
+
+/// Truncates the long values, e.g. the repr of a content tree.
+fn truncate(mut repr: String) -> String {
+    if repr.len() > MAX_VALUE_LEN {
+        let mut end = MAX_VALUE_LEN;
+        while !repr.is_char_boundary(end) {
+            end -= 1;
+        }
+        repr.truncate(end);
+        repr.push('…');
+    }
+    repr
+}
+
+impl ServerState {
+    pub(crate) fn debug_scopes(
+        &mut self,
+        args: dapts::ScopesArguments,
+    ) -> SchedulableResponse<dapts::ScopesResponse> {
+        let session = self.debug.session_mut()?;
+        if args.frame_id != FRAME_ID {
+            return Err(invalid_params(format!("unknown frame {}", args.frame_id)));
+        }
+
+        let frame = &session.frame;
+        let scopes = [
+            ("Locals", &frame.locals),
+            ("Closure Captures", &frame.captures),
+            ("Module", &frame.module),
+        ];
+        let scopes = scopes
+            .into_iter()
+            .map(|(name, scope)| {
+                let count = scope.iter().count();
+                dapts::Scope {
+                    name: name.into(),
+                    variables_reference: session
+                        .variables
+                        .alloc(VariableNode::Scope(scope.clone())),
+                    named_variables: Some(count as u64),
+                    ..dapts::Scope::default()
+                }
+            })
+            .collect();
+
+        just_ok(dapts::ScopesResponse { scopes })
+    }
+
+    pub(crate) fn debug_variables(
+        &mut self,
+        args: dapts::VariablesArguments,
+    ) -> SchedulableResponse<dapts::VariablesResponse> {
+        let session = self.debug.session_mut()?;
+        let start = args.start.unwrap_or_default() as usize;
+        // The count `0` means all the remaining children.
+        let count = match args.count.unwrap_or_default() {
+            0 => usize::MAX,
+            count => count as usize,
+        };
+
+        let variables = session
+            .variables
+            .children(args.variables_reference, start, count)
+            .ok_or_else(|| {
+                invalid_params(format!("unknown variables {}", args.variables_reference))
+            })?;
+
+        just_ok(dapts::VariablesResponse { variables })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typst::foundations::{array, dict, IntoValue};
+
+    #[test]
+    fn lazy_expansion() {
+        let mut store = VariableStore::default();
+        let inner = array![1, 2, 3].into_value();
+        let value = dict! { "a" => 1, "b" => inner }.into_value();
+        let root = store.alloc(VariableNode::Value(value));
+
+        let children = store.children(root, 0, usize::MAX).unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].variables_reference, 0);
+        assert_eq!(children[1].indexed_variables, Some(3));
+        // The items of the array are not expanded until they are requested.
+        assert_eq!(store.nodes.len(), 2);
+
+        let items = store
+            .children(children[1].variables_reference, 1, 1)
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "1");
+        assert_eq!(items[0].value, "2");
+        assert!(store.children(0, 0, usize::MAX).is_none());
+    }
+}
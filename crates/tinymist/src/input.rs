@@ -14,7 +14,7 @@ use crate::*;
 /// In memory source file management.
 impl ServerState {
     /// Updates a set of source files.
-    fn update_sources(&mut self, files: FileChangeSet) -> Result<()> {
+    pub(crate) fn update_sources(&mut self, files: FileChangeSet) -> Result<()> {
         log::trace!("update source: {files:?}");
 
         let intr = Interrupt::Memory(MemoryEvent::Update(files.clone()));
@@ -115,12 +115,18 @@ impl ServerState {
             return Err(error_once!("entry file must be absolute", path: path.unwrap().display()));
         }
 
+        // The configuration is scoped to the workspace folder of the entry.
+        if let Some(path) = path.as_deref() {
+            self.switch_folder(path);
+        }
+
         let task = self.resolve_task_or(path);
 
         log::info!("the task of the primary is changing to {task:?}");
 
         let id = self.project.primary_id().clone();
         self.project.interrupt(Interrupt::ChangeTask(id, task));
+        self.sync_entries();
 
         Ok(true)
     }
@@ -143,16 +149,107 @@ impl ServerState {
 
         self.focusing = new_entry.clone();
 
-        if self.pinning_by_user
-            || (self.pinning_by_preview && !self.pinning_by_browsing_preview)
-            || self.config.has_default_entry_path
-        {
+        // The primary project is kept on the document of a regular preview, while
+        // the other entries are compiled by their dedicate projects.
+        let pinned_by_preview = self.pinning_by_preview && !self.pinning_by_browsing_preview;
+
+        // The entry focused by the tab takes precedence over the implicit one.
+        let tab_entry = new_entry.as_ref().and_then(|p| self.entries.by_tab.get(p));
+        if let Some(entry) = tab_entry.filter(|_| !pinned_by_preview) {
+            let entry = entry.clone();
+            return self.change_main_file(Some(entry));
+        }
+
+        if self.pinning_by_user || pinned_by_preview || self.config.has_default_entry_path {
             return Ok(false);
         }
 
         self.change_main_file(new_entry)
     }
 
+    /// Registers an entry file, which is compiled independently of the others.
+    pub fn register_entry(&mut self, entry: ImmutPath) -> Result<()> {
+        if !entry.is_absolute() {
+            bail!("entry file must be absolute: {}", entry.display());
+        }
+
+        if !self.entries.entries.contains(&entry) {
+            self.entries.entries.push(entry);
+        }
+        self.sync_entries();
+        Ok(())
+    }
+
+    /// Unregisters an entry file and the tabs focusing on it.
+    pub fn unregister_entry(&mut self, entry: &ImmutPath) {
+        self.entries.entries.retain(|e| e != entry);
+        self.entries.by_tab.retain(|_, e| e != entry);
+        self.sync_entries();
+    }
+
+    /// Focuses the tab showing the file `tab` on an entry, or on the implicit
+    /// entry if `entry` is `None`.
+    pub fn focus_tab_entry(&mut self, tab: ImmutPath, entry: Option<ImmutPath>) -> Result<bool> {
+        match entry {
+            Some(entry) => {
+                self.register_entry(entry.clone())?;
+                self.entries.by_tab.insert(tab.clone(), entry);
+            }
+            None => {
+                self.entries.by_tab.remove(&tab);
+            }
+        }
+
+        if self.focusing.as_ref() != Some(&tab) {
+            return Ok(false);
+        }
+        self.focus_main_file(Some(tab))
+    }
+
+    /// Starts or stops the dedicate projects, so that each entry registered
+    /// or configured, except the one compiled by the primary project, is
+    /// compiled exactly once.
+    pub(crate) fn sync_entries(&mut self) {
+        let primary = self.project.compiler.primary.verse.entry_state();
+        let primary = primary.main().zip(primary.root());
+        let primary = primary.and_then(|(main, root)| main.vpath().resolve(&root));
+
+        let mut entries = self.entries.entries.clone();
+        for entry in self.folders.entries() {
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+
+        let stale = self.entries.running.iter().filter(|(entry, _)| {
+            !entries.contains(entry) || primary.as_deref() == Some(entry.as_ref())
+        });
+        let stale = stale.map(|(entry, _)| entry.clone()).collect::<Vec<_>>();
+        for entry in stale {
+            if let Some(dedicate) = self.entries.running.remove(&entry) {
+                log::info!("stopping entry {entry:?} as {dedicate}");
+                self.stop_dedicate(&dedicate);
+            }
+        }
+
+        for entry in entries {
+            if self.entries.running.contains_key(&entry)
+                || primary.as_deref() == Some(entry.as_ref())
+            {
+                continue;
+            }
+
+            let dedicate = format!("entry:{}", entry.display());
+            log::info!("starting entry {entry:?} as {dedicate}");
+            match self.restart_dedicate(&dedicate, Some(entry.clone())) {
+                Ok(_) => {
+                    self.entries.running.insert(entry, dedicate);
+                }
+                Err(err) => log::error!("could not start entry {entry:?}: {err}"),
+            }
+        }
+    }
+
     /// This is used for tracking activating document status if a client is not
     /// performing any focus command request.
     ///
@@ -201,8 +298,12 @@ impl ServerState {
 /// Task input resolution.
 impl ServerState {
     fn resolve_task_without_lock(&self, path: Option<ImmutPath>) -> TaskInputs {
+        let resolver = match &path {
+            Some(path) => self.entry_resolver_of(path),
+            None => self.entry_resolver(),
+        };
         TaskInputs {
-            entry: Some(self.entry_resolver().resolve(path)),
+            entry: Some(resolver.resolve(path)),
             ..TaskInputs::default()
         }
     }
@@ -215,7 +316,7 @@ impl ServerState {
 
     pub(crate) fn resolve_task(&mut self, path: ImmutPath) -> TaskInputs {
         let proj_input = matches!(
-            self.entry_resolver().project_resolution,
+            self.entry_resolver_of(&path).project_resolution,
             ProjectResolutionKind::LockDatabase
         )
         .then(|| {
@@ -239,7 +340,7 @@ impl ServerState {
                 .map(|path| path.as_path().into())
                 .unwrap_or_else(|| path.clone());
             let entry = self
-                .entry_resolver()
+                .entry_resolver_of(&path)
                 .resolve_with_root(Some(root), Some(main));
             log::info!("resolved task with state: {path:?} -> {project_id:?} -> {entry:?}");
 
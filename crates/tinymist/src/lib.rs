@@ -24,12 +24,14 @@ pub(crate) mod config;
 pub(crate) mod dap;
 pub(crate) mod input;
 pub(crate) mod lsp;
+mod notebook;
 pub mod project;
 mod resource;
 pub(crate) mod route;
 mod server;
 mod stats;
 mod task;
+mod timings;
 pub mod tool;
 mod utils;
 
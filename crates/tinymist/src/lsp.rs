@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::OnceLock;
 
 use lsp_types::request::WorkspaceConfiguration;
@@ -8,10 +9,16 @@ use serde_json::{Map, Value as JsonValue};
 use sync_ls::*;
 use tinymist_std::error::{prelude::*, IgnoreLogging};
 
+pub(crate) mod edit;
 pub mod init;
+pub(crate) mod progress;
 pub(crate) mod query;
 
 use crate::actor::editor::{EditorActorConfig, EditorRequest};
+use crate::lsp::progress::WorkProgress;
+use crate::notebook::{
+    DidChangeNotebookDocumentParams, DidCloseNotebookDocumentParams, DidOpenNotebookDocumentParams,
+};
 use crate::task::FormatterConfig;
 use crate::*;
 
@@ -60,6 +67,26 @@ impl ServerState {
             .log_error("could not register to watch config changes");
         }
 
+        if self.const_config().watch_files_registration {
+            log::trace!("setting up to watch the configuration files");
+
+            const WATCH_REGISTRATION_ID: &str = "watch_config_files";
+            const WATCH_METHOD_ID: &str = "workspace/didChangeWatchedFiles";
+
+            let options = DidChangeWatchedFilesRegistrationOptions {
+                watchers: vec![FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(format!("**/{WORKSPACE_CONFIG_FILE}")),
+                    kind: None,
+                }],
+            };
+            self.register_capability(vec![Registration {
+                id: WATCH_REGISTRATION_ID.to_owned(),
+                method: WATCH_METHOD_ID.to_owned(),
+                register_options: serde_json::to_value(options).ok(),
+            }])
+            .log_error("could not register to watch the configuration files");
+        }
+
         log::info!("server initialized");
         Ok(())
     }
@@ -89,8 +116,13 @@ impl ServerState {
         let path: ImmutPath = as_path_(params.text_document.uri).as_path().into();
         let text = params.text_document.text;
 
+        self.memory_versions
+            .insert(path.to_path_buf(), params.text_document.version);
         self.create_source(path.clone(), text)
             .map_err(invalid_params)?;
+        if let Some(source) = self.memory_changes.get(&path) {
+            self.prefetch_packages(source);
+        }
 
         // Focus after opening
         self.implicit_focus_entry(|| Some(path), 'o');
@@ -98,16 +130,20 @@ impl ServerState {
     }
 
     pub(crate) fn did_close(&mut self, params: DidCloseTextDocumentParams) -> LspResult<()> {
-        let path = as_path_(params.text_document.uri).as_path().into();
+        let path: ImmutPath = as_path_(params.text_document.uri).as_path().into();
 
+        self.project.analysis.release_tokens(&path);
+        self.memory_versions.remove(&path);
         self.remove_source(path).map_err(invalid_params)?;
         Ok(())
     }
 
     pub(crate) fn did_change(&mut self, params: DidChangeTextDocumentParams) -> LspResult<()> {
-        let path = as_path_(params.text_document.uri).as_path().into();
+        let path: ImmutPath = as_path_(params.text_document.uri).as_path().into();
         let changes = params.content_changes;
 
+        self.memory_versions
+            .insert(path.to_path_buf(), params.text_document.version);
         self.edit_source(path, changes, self.const_config().position_encoding)
             .map_err(invalid_params)?;
         Ok(())
@@ -118,23 +154,106 @@ impl ServerState {
     }
 }
 
+/// LSP Notebook Document Synchronization
+impl ServerState {
+    pub(crate) fn did_open_notebook(
+        &mut self,
+        params: DidOpenNotebookDocumentParams,
+    ) -> LspResult<()> {
+        log::info!("did open notebook {}", params.notebook_document.uri);
+        self.open_notebook(params.notebook_document, params.cell_text_documents)
+            .map_err(invalid_params)
+    }
+
+    pub(crate) fn did_change_notebook(
+        &mut self,
+        params: DidChangeNotebookDocumentParams,
+    ) -> LspResult<()> {
+        let uri = params.notebook_document.uri;
+        let encoding = self.const_config().position_encoding;
+        self.change_notebook(&uri, params.change, encoding)
+            .map_err(invalid_params)
+    }
+
+    pub(crate) fn did_close_notebook(
+        &mut self,
+        params: DidCloseNotebookDocumentParams,
+    ) -> LspResult<()> {
+        self.close_notebook(&params.notebook_document.uri)
+            .map_err(invalid_params)
+    }
+}
+
+/// LSP Work Done Progress
+impl ServerState {
+    /// Begins a progress of a long operation, using the token given by the
+    /// client if any.
+    pub(crate) fn begin_progress(&self, title: &str, token: Option<ProgressToken>) -> WorkProgress {
+        let supported = token.is_some() || self.const_config().work_done_progress;
+        let client = supported.then(|| self.client.clone().to_untyped());
+        WorkProgress::begin(client, &self.progress, title, token)
+    }
+
+    pub(crate) fn work_done_progress_cancel(
+        &mut self,
+        params: WorkDoneProgressCancelParams,
+    ) -> LspResult<()> {
+        self.progress.cancel(&params.token);
+        Ok(())
+    }
+}
+
+/// LSP Pull Diagnostics
+impl ServerState {
+    pub(crate) fn document_diagnostic(
+        &mut self,
+        params: DocumentDiagnosticParams,
+    ) -> SchedulableResponse<DocumentDiagnosticReportResult> {
+        let report = self.diagnostics.document_report(
+            &params.text_document.uri,
+            params.previous_result_id.as_deref(),
+        );
+        just_ok(DocumentDiagnosticReportResult::Report(report))
+    }
+
+    pub(crate) fn workspace_diagnostic(
+        &mut self,
+        params: WorkspaceDiagnosticParams,
+    ) -> SchedulableResponse<WorkspaceDiagnosticReportResult> {
+        let report = self
+            .diagnostics
+            .workspace_report(&params.previous_result_ids);
+        just_ok(WorkspaceDiagnosticReportResult::Report(report))
+    }
+}
+
 /// LSP Configuration Synchronization
 impl ServerState {
     pub(crate) fn on_changed_configuration(
         &mut self,
         values: Map<String, JsonValue>,
     ) -> LspResult<()> {
-        let old_config = self.config.clone();
-        match self.config.update_by_map(&values) {
-            Ok(()) => {}
-            Err(err) => {
-                self.config = old_config;
-                log::error!("error applying new settings: {err}");
-                return Err(invalid_params(format!(
-                    "error applying new settings: {err}"
-                )));
-            }
+        let old_folders = self.folders.clone();
+        if let Err(err) = self.folders.workspace.update_by_map(&values) {
+            self.folders = old_folders;
+            log::error!("error applying new settings: {err}");
+            return Err(invalid_params(format!(
+                "error applying new settings: {err}"
+            )));
         }
+        self.folders.rescope();
+
+        self.reconfigure(self.config.active_folder.clone());
+        log::info!("new settings applied");
+        Ok(())
+    }
+
+    /// Switches to the configuration of a workspace folder, or of the whole
+    /// workspace if the folder is `None`, and reloads the affected states by
+    /// comparing with the old configuration.
+    fn reconfigure(&mut self, folder: Option<ImmutPath>) {
+        let new_config = self.folders.get(folder.as_ref()).clone();
+        let old_config = std::mem::replace(&mut self.config, new_config);
 
         let new_export_config = self.config.export();
         if old_config.export() != new_export_config {
@@ -149,12 +268,25 @@ impl ServerState {
                 .log_error("could not change editor actor configuration");
         }
 
+        // The fonts are resolved again only if their options change.
+        if old_config.font_opts() == self.config.font_opts() {
+            self.config.fonts = old_config.fonts.clone();
+        } else {
+            self.config.fonts = OnceLock::new();
+        }
         if old_config.primary_opts() != self.config.primary_opts() {
-            self.config.fonts = OnceLock::new(); // todo: don't reload fonts if not changed
             self.reload_projects()
                 .log_error("could not restart primary");
         }
 
+        if old_config.entries() != self.config.entries() {
+            self.sync_entries();
+        }
+
+        if old_config.cache != self.config.cache {
+            *self.project.analysis.cache_limits.lock() = self.config.cache;
+        }
+
         if old_config.semantic_tokens != self.config.semantic_tokens {
             self.enable_sema_token_caps(self.config.semantic_tokens == SemanticTokensMode::Enable)
                 .log_error("could not change semantic tokens config");
@@ -168,8 +300,70 @@ impl ServerState {
 
             self.formatter.change_config(new_formatter_config);
         }
+    }
+
+    /// Switches to the configuration of the workspace folder containing a
+    /// document.
+    pub(crate) fn switch_folder(&mut self, path: &Path) {
+        let folder = self.folders.folder(path);
+        if folder.is_none() || folder == self.config.active_folder {
+            return;
+        }
+
+        log::info!("the active workspace folder is changing to {folder:?}");
+        self.reconfigure(folder);
+    }
+
+    pub(crate) fn did_change_workspace_folders(
+        &mut self,
+        params: DidChangeWorkspaceFoldersParams,
+    ) -> LspResult<()> {
+        let removed = self.folders.change(&params.event);
+        let roots = &self.folders.workspace.entry_resolver.roots;
+        log::info!("workspace folders changed: {roots:?}");
+
+        let in_removed = |path: &Path| removed.iter().any(|root| path.starts_with(root));
+        let stale_entries = self
+            .entries
+            .entries
+            .iter()
+            .filter(|entry| in_removed(entry));
+        for entry in stale_entries.cloned().collect::<Vec<_>>() {
+            self.unregister_entry(&entry);
+        }
+
+        // The documents in a removed folder fall back to the configuration of
+        // the whole workspace.
+        let folder = self.config.active_folder.clone();
+        self.reconfigure(folder.filter(|folder| !removed.contains(folder)));
+        if !self.config.warnings.is_empty() {
+            self.show_config_warnings();
+        }
+
+        Ok(())
+    }
+
+    /// Reloads the configuration when a configuration file checked in the
+    /// workspace changes.
+    pub(crate) fn did_change_watched_files(
+        &mut self,
+        params: DidChangeWatchedFilesParams,
+    ) -> LspResult<()> {
+        let is_config = |event: &FileEvent| {
+            let path = event.uri.to_file_path().ok();
+            path.is_some_and(|path| path.ends_with(WORKSPACE_CONFIG_FILE))
+        };
+        if !params.changes.iter().any(is_config) {
+            return Ok(());
+        }
+
+        log::info!("the configuration file changed");
+        let values = self.folders.workspace.editor_values.clone();
+        self.on_changed_configuration(values)?;
+        if !self.config.warnings.is_empty() {
+            self.show_config_warnings();
+        }
 
-        log::info!("new settings applied");
         Ok(())
     }
 
@@ -0,0 +1,138 @@
+//! Previews, applies and undoes the batch workspace edits of large refactors,
+//! e.g. renames across files and fix-alls.
+
+use std::collections::VecDeque;
+
+use lsp_types::request::ApplyWorkspaceEdit;
+use lsp_types::*;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use sync_ls::*;
+use tinymist_query::{preview_workspace_edit, revert_workspace_edit, url_to_path};
+use typst::syntax::Source;
+
+use crate::*;
+
+/// The maximum number of the edits that can be undone.
+const JOURNAL_SIZE: usize = 32;
+
+/// The journal of the workspace edits applied by the server, from the oldest
+/// to the newest, with the edits reverting them.
+#[derive(Default)]
+pub(crate) struct EditJournal(VecDeque<(String, WorkspaceEdit)>);
+
+impl EditJournal {
+    /// Records an applied edit with the edit reverting it.
+    fn push(&mut self, label: String, revert: WorkspaceEdit) {
+        if self.0.len() >= JOURNAL_SIZE {
+            self.0.pop_front();
+        }
+        self.0.push_back((label, revert));
+    }
+
+    /// Takes the edit reverting the newest applied edit.
+    fn pop(&mut self) -> Option<(String, WorkspaceEdit)> {
+        self.0.pop_back()
+    }
+}
+
+/// The options of applying a workspace edit.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApplyEditOpts {
+    /// Whether to return the preview of the edit without applying it.
+    #[serde(default)]
+    dry_run: bool,
+    /// The label of the edit, shown by the client and the undo journal.
+    label: Option<String>,
+}
+
+impl ServerState {
+    /// Previews a workspace edit as a unified diff.
+    pub fn preview_workspace_edit(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let edit = get_arg!(args[0] as WorkspaceEdit);
+
+        let encoding = self.const_config().position_encoding;
+        let preview = preview_workspace_edit(&edit, encoding, &|uri| self.read_document(uri));
+        just_ok(JsonValue::String(preview))
+    }
+
+    /// Applies a workspace edit and records it in the undo journal, or only
+    /// previews it in a dry run. Returns the preview of the edit.
+    pub fn apply_workspace_edit(&mut self, mut args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let edit = get_arg!(args[0] as WorkspaceEdit);
+        let opts = get_arg_or_default!(args[1] as ApplyEditOpts);
+
+        let encoding = self.const_config().position_encoding;
+        let read = |uri: &Url| self.read_document(uri);
+        let preview = preview_workspace_edit(&edit, encoding, &read);
+        if opts.dry_run {
+            return just_ok(JsonValue::String(preview));
+        }
+
+        let revert = revert_workspace_edit(&edit, encoding, &read);
+        let label = opts.label.unwrap_or_else(|| "Workspace edit".to_owned());
+        self.send_workspace_edit(label.clone(), edit, move |this, applied| {
+            match revert {
+                Some(revert) if applied => this.edit_journal.push(label, revert),
+                Some(_) => {}
+                // The older edits cannot be undone past an unrevertible edit.
+                None => this.edit_journal = EditJournal::default(),
+            }
+        });
+        just_ok(JsonValue::String(preview))
+    }
+
+    /// Undoes the newest workspace edit applied by the server. Returns the
+    /// label of the undone edit.
+    pub fn undo_workspace_edit(&mut self, _args: Vec<JsonValue>) -> AnySchedulableResponse {
+        let Some((label, revert)) = self.edit_journal.pop() else {
+            return Err(invalid_params("no workspace edit to undo"));
+        };
+
+        self.send_workspace_edit(format!("Undo {label}"), revert, |_, _| {});
+        just_ok(JsonValue::String(label))
+    }
+
+    /// Asks the client to apply a workspace edit, and calls back with whether
+    /// the edit is applied.
+    fn send_workspace_edit(
+        &self,
+        label: String,
+        edit: WorkspaceEdit,
+        callback: impl FnOnce(&mut Self, bool) + Send + Sync + 'static,
+    ) {
+        let params = ApplyWorkspaceEditParams {
+            label: Some(label),
+            edit,
+        };
+        self.client
+            .send_lsp_request::<ApplyWorkspaceEdit>(params, move |this, resp| {
+                let result = resp.result.map(serde_json::from_value);
+                let applied = match (resp.error, result) {
+                    (None, Some(Ok(ApplyWorkspaceEditResponse { applied, .. }))) => applied,
+                    (Some(err), _) => {
+                        log::error!("failed to apply workspace edit: {err:?}");
+                        false
+                    }
+                    (None, _) => {
+                        log::error!("invalid response of applying workspace edit");
+                        false
+                    }
+                };
+                callback(this, applied);
+            });
+    }
+
+    /// Reads the current content of a document, from the memory if it is
+    /// opened by the client, or from the disk otherwise.
+    fn read_document(&self, uri: &Url) -> Option<Source> {
+        let path = url_to_path(uri.clone());
+        if let Some(source) = self.memory_changes.get(path.as_path()) {
+            return Some(source.clone());
+        }
+
+        let text = std::fs::read_to_string(&path).ok()?;
+        Some(Source::detached(text))
+    }
+}
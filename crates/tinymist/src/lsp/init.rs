@@ -119,10 +119,20 @@ impl Initializer for SuperInit {
             }
         });
 
+        let diagnostic_provider = const_config.pull_diagnostics.then(|| {
+            DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                identifier: Some("tinymist".to_owned()),
+                inter_file_dependencies: true,
+                workspace_diagnostics: true,
+                work_done_progress_options: WorkDoneProgressOptions {
+                    work_done_progress: None,
+                },
+            })
+        });
+
         let res = InitializeResult {
             capabilities: ServerCapabilities {
-                // todo: respect position_encoding
-                // position_encoding: Some(cc.position_encoding.into()),
+                position_encoding: Some(const_config.position_encoding.into()),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 signature_help_provider: Some(SignatureHelpOptions {
                     trigger_characters: Some(vec![
@@ -162,6 +172,7 @@ impl Initializer for SuperInit {
                     },
                 )),
                 semantic_tokens_provider,
+                diagnostic_provider,
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: exec_cmds,
                     work_done_progress_options: WorkDoneProgressOptions {
@@ -171,12 +182,17 @@ impl Initializer for SuperInit {
                 color_provider: Some(ColorProviderCapability::Simple(true)),
                 document_highlight_provider: Some(OneOf::Left(true)),
                 document_symbol_provider: Some(OneOf::Left(true)),
-                workspace_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Right(WorkspaceSymbolOptions {
+                    resolve_provider: None,
+                    work_done_progress_options: WorkDoneProgressOptions {
+                        work_done_progress: Some(true),
+                    },
+                })),
                 selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
                 rename_provider: Some(OneOf::Right(RenameOptions {
                     prepare_provider: Some(true),
                     work_done_progress_options: WorkDoneProgressOptions {
-                        work_done_progress: None,
+                        work_done_progress: Some(true),
                     },
                 })),
                 document_link_provider: Some(DocumentLinkOptions {
@@ -208,7 +224,13 @@ impl Initializer for SuperInit {
             ..InitializeResult::default()
         };
 
-        let res = serde_json::to_value(res).map_err(|e| invalid_params(e.to_string()));
+        let res = serde_json::to_value(res).map(|mut res| {
+            // The Typst cells of any notebook are synchronized, which is not
+            // modeled by `lsp_types` yet.
+            res["capabilities"]["notebookDocumentSync"] = crate::notebook::notebook_document_sync();
+            res
+        });
+        let res = res.map_err(|e| invalid_params(e.to_string()));
         (service, just_result(res))
     }
 }
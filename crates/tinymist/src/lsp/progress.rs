@@ -0,0 +1,376 @@
+//! Reports the progress of long operations to the client, e.g. package
+//! downloads, exports and workspace renames.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use lsp_types::notification::Progress;
+use lsp_types::request::WorkDoneProgressCreate;
+use lsp_types::*;
+use parking_lot::Mutex;
+use sync_ls::LspClient;
+use tinymist_project::package::registry::{Notifier, PackageResult, PackageSpec};
+use tokio_util::sync::CancellationToken;
+
+/// The cancellation tokens of the running operations by their progress tokens.
+#[derive(Default, Clone)]
+pub struct ProgressRegistry(Arc<Mutex<HashMap<ProgressToken, CancellationToken>>>);
+
+impl ProgressRegistry {
+    /// Cancels the operation reporting to a progress, e.g. when the user clicks
+    /// the cancel button of the progress.
+    pub fn cancel(&self, token: &ProgressToken) {
+        if let Some(cancel) = self.0.lock().remove(token) {
+            cancel.cancel();
+        }
+    }
+}
+
+/// A cancellable work done progress of a long operation.
+///
+/// The LSP doesn't nest progresses, so the titles of the nested steps are
+/// joined by ` › ` and shown before the messages of the progress, e.g.
+/// `Exporting › PDF: main.typ`. The progress ends when all of its steps are
+/// dropped.
+#[derive(Clone)]
+pub struct WorkProgress {
+    inner: Arc<ProgressInner>,
+    /// The titles of the nested steps.
+    path: String,
+}
+
+struct ProgressInner {
+    /// The client to report to, or `None` if the client doesn't support work
+    /// done progresses.
+    client: Option<LspClient>,
+    channel: ProgressChannel,
+    cancel: CancellationToken,
+    registry: ProgressRegistry,
+}
+
+impl WorkProgress {
+    /// Begins a progress, using the token given by the client, or creating a
+    /// new one.
+    pub fn begin(
+        client: Option<LspClient>,
+        registry: &ProgressRegistry,
+        title: &str,
+        token: Option<ProgressToken>,
+    ) -> Self {
+        static TOKEN_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let channel = token.map(ProgressChannel::given).unwrap_or_else(|| {
+            let id = TOKEN_ID.fetch_add(1, Ordering::Relaxed);
+            let token = ProgressToken::String(format!("tinymist/work/{id}"));
+            match &client {
+                Some(client) => ProgressChannel::create(client, token),
+                None => ProgressChannel::given(token),
+            }
+        });
+        let cancel = CancellationToken::new();
+        registry
+            .0
+            .lock()
+            .insert(channel.token.clone(), cancel.clone());
+
+        let inner = ProgressInner {
+            client,
+            channel,
+            cancel,
+            registry: registry.clone(),
+        };
+        inner.notify(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_owned(),
+            cancellable: Some(true),
+            ..Default::default()
+        }));
+
+        Self {
+            inner: Arc::new(inner),
+            path: String::new(),
+        }
+    }
+
+    /// Enters a nested step of the operation.
+    pub fn step(&self, title: &str) -> Self {
+        let path = if self.path.is_empty() {
+            title.to_owned()
+        } else {
+            format!("{} › {title}", self.path)
+        };
+        let step = Self {
+            inner: self.inner.clone(),
+            path,
+        };
+        step.report(None);
+        step
+    }
+
+    /// Reports a message in the current step.
+    pub fn report(&self, message: Option<&str>) {
+        let message = match message {
+            Some(message) if self.path.is_empty() => message.to_owned(),
+            Some(message) => format!("{}: {message}", self.path),
+            None => self.path.clone(),
+        };
+        self.inner
+            .notify(WorkDoneProgress::Report(WorkDoneProgressReport {
+                message: Some(message),
+                ..Default::default()
+            }));
+    }
+
+    /// Gets the token cancelled when the user cancels the progress.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.inner.cancel.clone()
+    }
+}
+
+impl ProgressInner {
+    fn notify(&self, progress: WorkDoneProgress) {
+        if let Some(client) = &self.client {
+            self.channel.notify(client, progress);
+        }
+    }
+}
+
+impl Drop for ProgressInner {
+    fn drop(&mut self) {
+        self.registry.0.lock().remove(&self.channel.token);
+
+        let message = self.cancel.is_cancelled().then(|| "cancelled".to_owned());
+        self.notify(WorkDoneProgress::End(WorkDoneProgressEnd { message }));
+    }
+}
+
+/// A work done progress token. The notifications to a token created by the
+/// server are held until the client responds to the creation, and are dropped
+/// if the client fails to create it.
+#[derive(Clone)]
+struct ProgressChannel {
+    token: ProgressToken,
+    state: Arc<Mutex<ChannelState>>,
+}
+
+enum ChannelState {
+    /// Waiting for the client to create the token, with the begin and the end
+    /// of the progress to send once it is created. The reports in the meantime
+    /// are dropped, as they are superseded by the later ones.
+    Creating {
+        begin: Option<WorkDoneProgressBegin>,
+        end: Option<WorkDoneProgressEnd>,
+    },
+    Created,
+    Failed,
+}
+
+impl ProgressChannel {
+    /// Uses a token given by the client, which is already created.
+    fn given(token: ProgressToken) -> Self {
+        Self {
+            token,
+            state: Arc::new(Mutex::new(ChannelState::Created)),
+        }
+    }
+
+    /// Creates a token by `window/workDoneProgress/create`.
+    fn create(client: &LspClient, token: ProgressToken) -> Self {
+        let channel = Self {
+            token: token.clone(),
+            state: Arc::new(Mutex::new(ChannelState::Creating {
+                begin: None,
+                end: None,
+            })),
+        };
+
+        let this = channel.clone();
+        let resp_client = client.clone();
+        client.send_lsp_request_::<WorkDoneProgressCreate>(
+            WorkDoneProgressCreateParams { token },
+            move |_, resp| {
+                let created = resp.error.is_none();
+                if let Some(err) = resp.error {
+                    log::warn!("failed to create work done progress: {err:?}");
+                }
+                this.on_created(&resp_client, created);
+            },
+        );
+
+        channel
+    }
+
+    fn on_created(&self, client: &LspClient, created: bool) {
+        let mut state = self.state.lock();
+        let next = if created {
+            ChannelState::Created
+        } else {
+            ChannelState::Failed
+        };
+        let ChannelState::Creating { begin, end } = std::mem::replace(&mut *state, next) else {
+            return;
+        };
+        if created {
+            let begin = begin.map(WorkDoneProgress::Begin);
+            let end = end.map(WorkDoneProgress::End);
+            for progress in begin.into_iter().chain(end) {
+                self.send(client, progress);
+            }
+        }
+    }
+
+    fn notify(&self, client: &LspClient, progress: WorkDoneProgress) {
+        // The state is locked while sending to keep the notifications in order.
+        let mut state = self.state.lock();
+        match &mut *state {
+            ChannelState::Created => self.send(client, progress),
+            ChannelState::Failed => {}
+            ChannelState::Creating { begin, end } => match progress {
+                WorkDoneProgress::Begin(progress) => *begin = Some(progress),
+                WorkDoneProgress::End(progress) => *end = Some(progress),
+                WorkDoneProgress::Report(_) => {}
+            },
+        }
+    }
+
+    fn send(&self, client: &LspClient, progress: WorkDoneProgress) {
+        client.send_notification::<Progress>(&ProgressParams {
+            token: self.token.clone(),
+            value: ProgressParamsValue::WorkDone(progress),
+        });
+    }
+}
+
+/// Aggregates the concurrent package downloads into a single work done
+/// progress, reporting each package as a step of it.
+pub struct PackageProgress {
+    client: LspClient,
+    batch: Mutex<Option<DownloadBatch>>,
+}
+
+/// The packages downloaded under the same progress token.
+struct DownloadBatch {
+    channel: ProgressChannel,
+    total: usize,
+    done: usize,
+    /// The last reported percentage.
+    percentage: u32,
+}
+
+impl DownloadBatch {
+    /// Gets the percentage to report. The packages joining the batch lower the
+    /// ratio of the finished ones, but the reported percentage never goes
+    /// back.
+    fn percentage(&mut self) -> u32 {
+        let percentage = (self.done * 100 / self.total) as u32;
+        self.percentage = self.percentage.max(percentage);
+        self.percentage
+    }
+}
+
+impl PackageProgress {
+    /// Creates a notifier sending progress to the client.
+    pub fn new(client: LspClient) -> Self {
+        Self {
+            client,
+            batch: Mutex::default(),
+        }
+    }
+
+    fn notify(&self, channel: &ProgressChannel, progress: WorkDoneProgress) {
+        channel.notify(&self.client, progress);
+    }
+}
+
+impl Notifier for PackageProgress {
+    fn downloading(&self, spec: &PackageSpec) {
+        let mut batch = self.batch.lock();
+        let message = Some(format!("{spec}"));
+
+        if let Some(batch) = batch.as_mut() {
+            batch.total += 1;
+            let report = WorkDoneProgressReport {
+                message,
+                percentage: Some(batch.percentage()),
+                ..Default::default()
+            };
+            self.notify(&batch.channel, WorkDoneProgress::Report(report));
+            return;
+        }
+
+        static TOKEN_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = TOKEN_ID.fetch_add(1, Ordering::Relaxed);
+        let token = ProgressToken::String(format!("tinymist/package-download/{id}"));
+
+        let channel = ProgressChannel::create(&self.client, token);
+        let begin = WorkDoneProgressBegin {
+            title: "Downloading packages".to_owned(),
+            message,
+            percentage: Some(0),
+            ..Default::default()
+        };
+        self.notify(&channel, WorkDoneProgress::Begin(begin));
+
+        *batch = Some(DownloadBatch {
+            channel,
+            total: 1,
+            done: 0,
+            percentage: 0,
+        });
+    }
+
+    fn downloaded(&self, spec: &PackageSpec, result: &PackageResult<()>) {
+        let mut batch_slot = self.batch.lock();
+        let Some(batch) = batch_slot.as_mut() else {
+            return;
+        };
+
+        batch.done += 1;
+        if let Err(err) = result {
+            log::warn!("failed to download package {spec}: {err}");
+        }
+
+        if batch.done < batch.total {
+            let status = if result.is_ok() {
+                "downloaded"
+            } else {
+                "failed"
+            };
+            let report = WorkDoneProgressReport {
+                message: Some(format!("{spec} {status} ({}/{})", batch.done, batch.total)),
+                percentage: Some(batch.percentage()),
+                ..Default::default()
+            };
+            self.notify(&batch.channel, WorkDoneProgress::Report(report));
+            return;
+        }
+
+        let end = WorkDoneProgressEnd {
+            message: Some(format!("{} package(s) processed", batch.total)),
+        };
+        self.notify(&batch.channel, WorkDoneProgress::End(end));
+        *batch_slot = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_percentage() {
+        let mut batch = DownloadBatch {
+            channel: ProgressChannel::given(ProgressToken::Number(0)),
+            total: 2,
+            done: 1,
+            percentage: 0,
+        };
+        assert_eq!(batch.percentage(), 50);
+
+        // More packages join the batch.
+        batch.total = 4;
+        assert_eq!(batch.percentage(), 50);
+        batch.done = 3;
+        assert_eq!(batch.percentage(), 75);
+    }
+}
@@ -8,8 +8,10 @@ use sync_ls::*;
 use tinymist_query::{
     CompilerQueryRequest, CompilerQueryResponse, FoldRequestFeature, SyntaxRequest,
 };
-use tinymist_std::{ImmutPath, Result};
+use tinymist_std::error::prelude::*;
+use tinymist_std::ImmutPath;
 
+use crate::lsp::progress::WorkProgress;
 use crate::project::{EntryState, TaskInputs, DETACHED_ENTRY};
 use crate::{as_path, as_path_, as_path_pos, FormatterMode, ServerState};
 
@@ -17,20 +19,31 @@ use crate::{as_path, as_path_, as_path_pos, FormatterMode, ServerState};
 pub type QueryFuture = Result<ResponseFuture<Result<CompilerQueryResponse>>>;
 
 pub trait LspClientExt {
-    fn schedule_query(&self, req_id: RequestId, query_fut: QueryFuture) -> ScheduledResult;
+    fn schedule_query<T: Serialize + 'static>(
+        &self,
+        req_id: RequestId,
+        query_fut: QueryFuture,
+        map: impl FnOnce(CompilerQueryResponse) -> T + Send + 'static,
+    ) -> ScheduledResult;
 }
 
 impl LspClientExt for LspClient {
-    /// Schedules a query from the client.
-    fn schedule_query(&self, req_id: RequestId, query_fut: QueryFuture) -> ScheduledResult {
+    /// Schedules a query from the client, mapping its response before sending
+    /// it.
+    fn schedule_query<T: Serialize + 'static>(
+        &self,
+        req_id: RequestId,
+        query_fut: QueryFuture,
+        map: impl FnOnce(CompilerQueryResponse) -> T + Send + 'static,
+    ) -> ScheduledResult {
         let fut = query_fut.map_err(|e| internal_error(e.to_string()))?;
-        let fut: SchedulableResponse<CompilerQueryResponse> = Ok(match fut {
+        let fut: SchedulableResponse<T> = Ok(match fut {
             MaybeDone::Done(res) => {
-                MaybeDone::Done(res.map_err(|err| internal_error(err.to_string())))
+                MaybeDone::Done(res.map(map).map_err(|err| internal_error(err.to_string())))
             }
             MaybeDone::Future(fut) => MaybeDone::Future(Box::pin(async move {
                 let res = fut.await;
-                res.map_err(|err| internal_error(err.to_string()))
+                res.map(map).map_err(|err| internal_error(err.to_string()))
             })),
             MaybeDone::Gone => MaybeDone::Gone,
         });
@@ -42,12 +55,63 @@ macro_rules! run_query {
     ($req_id: ident, $self: ident.$query: ident ($($arg_key:ident),* $(,)?)) => {{
         use tinymist_query::*;
         let req = paste::paste! { [<$query Request>] { $($arg_key),* } };
-        let query_fut = $self.query(CompilerQueryRequest::$query(req.clone()));
-        $self.client.untyped().schedule_query($req_id, query_fut)
+        $self.run_query($req_id, CompilerQueryRequest::$query(req), None)
+    }};
+    ($req_id: ident, $self: ident.$query: ident ($($arg_key:ident),* $(,)?), $progress: expr) => {{
+        use tinymist_query::*;
+        let req = paste::paste! { [<$query Request>] { $($arg_key),* } };
+        $self.run_query($req_id, CompilerQueryRequest::$query(req), Some($progress))
     }};
 }
 pub(crate) use run_query;
 
+impl ServerState {
+    /// Runs a query from the client and schedules its response. The positions
+    /// in the response are shifted to the cell documents if any notebook is
+    /// opened.
+    pub(crate) fn run_query(
+        &mut self,
+        req_id: RequestId,
+        query: CompilerQueryRequest,
+        progress: Option<WorkProgress>,
+    ) -> ScheduledResult {
+        let hidden = self.notebooks.hidden.clone();
+        let lines = query
+            .associated_path()
+            .map_or(0, |path| hidden.get_path(path));
+
+        let mut query_fut = self.query(query);
+        if let Some(progress) = progress {
+            query_fut = with_progress(progress, query_fut);
+        }
+
+        let client = self.client.untyped();
+        if hidden.is_empty() {
+            return client.schedule_query(req_id, query_fut, |res| res);
+        }
+        client.schedule_query(req_id, query_fut, move |res| hidden.hide(lines, res))
+    }
+}
+
+/// Runs a query reporting to a progress, which fails as soon as the user
+/// cancels the progress. The cancelled query still runs to the end in the
+/// background, but its result is discarded.
+fn with_progress(progress: WorkProgress, query_fut: QueryFuture) -> QueryFuture {
+    let fut = match query_fut? {
+        MaybeDone::Future(fut) => fut,
+        done => return Ok(done),
+    };
+
+    let cancel = progress.cancel_token();
+    just_future(async move {
+        let _progress = progress;
+        tokio::select! {
+            res = tokio::spawn(fut) => res.map_err(|err| error_once!("query failed", err: err))?,
+            _ = cancel.cancelled() => Err(error_once!("cancelled by user")),
+        }
+    })
+}
+
 /// LSP Standard Language Features
 impl ServerState {
     pub(crate) fn goto_definition(
@@ -55,6 +119,7 @@ impl ServerState {
         req_id: RequestId,
         params: GotoDefinitionParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position_params);
         run_query!(req_id, self.GotoDefinition(path, position))
     }
@@ -64,6 +129,7 @@ impl ServerState {
         req_id: RequestId,
         params: GotoDeclarationParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position_params);
         run_query!(req_id, self.GotoDeclaration(path, position))
     }
@@ -73,11 +139,13 @@ impl ServerState {
         req_id: RequestId,
         params: ReferenceParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position);
         run_query!(req_id, self.References(path, position))
     }
 
     pub(crate) fn hover(&mut self, req_id: RequestId, params: HoverParams) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position_params);
         self.implicit_focus_entry(|| Some(path.as_path().into()), 'h');
 
@@ -101,6 +169,7 @@ impl ServerState {
         req_id: RequestId,
         params: SelectionRangeParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let path = as_path(params.text_document);
         let positions = params.positions;
         run_query!(req_id, self.SelectionRange(path, positions))
@@ -111,6 +180,7 @@ impl ServerState {
         req_id: RequestId,
         params: DocumentHighlightParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position_params);
         run_query!(req_id, self.DocumentHighlight(path, position))
     }
@@ -139,9 +209,15 @@ impl ServerState {
         req_id: RequestId,
         params: SemanticTokensDeltaParams,
     ) -> ScheduledResult {
+        // The deltas are computed from the tokens of the virtual files, so the
+        // full tokens of the cells are sent instead.
+        let hidden = self.notebooks.hidden.get(&params.text_document.uri) > 0;
         let path = as_path(params.text_document);
         let previous_result_id = params.previous_result_id;
         self.implicit_focus_entry(|| Some(path.as_path().into()), 't');
+        if hidden {
+            return run_query!(req_id, self.SemanticTokensFull(path));
+        }
         run_query!(req_id, self.SemanticTokensDelta(path, previous_result_id))
     }
 
@@ -166,6 +242,7 @@ impl ServerState {
         req_id: RequestId,
         params: InlayHintParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let path = as_path(params.text_document);
         let range = params.range;
         run_query!(req_id, self.InlayHint(path, range))
@@ -194,6 +271,7 @@ impl ServerState {
         req_id: RequestId,
         params: ColorPresentationParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let path = as_path(params.text_document);
         let color = params.color;
         let range = params.range;
@@ -205,6 +283,7 @@ impl ServerState {
         req_id: RequestId,
         params: CodeActionParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let path = as_path(params.text_document);
         let range = params.range;
         let context = params.context;
@@ -225,6 +304,7 @@ impl ServerState {
         req_id: RequestId,
         params: CompletionParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position);
         let context = params.context.as_ref();
         let explicit =
@@ -245,6 +325,7 @@ impl ServerState {
         req_id: RequestId,
         params: SignatureHelpParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position_params);
 
         self.implicit_position = Some(position);
@@ -252,9 +333,12 @@ impl ServerState {
     }
 
     pub(crate) fn rename(&mut self, req_id: RequestId, params: RenameParams) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params.text_document_position);
         let new_name = params.new_name;
-        run_query!(req_id, self.Rename(path, position, new_name))
+        let token = params.work_done_progress_params.work_done_token;
+        let progress = self.begin_progress("Renaming", token).step(&new_name);
+        run_query!(req_id, self.Rename(path, position, new_name), progress)
     }
 
     pub(crate) fn prepare_rename(
@@ -262,6 +346,7 @@ impl ServerState {
         req_id: RequestId,
         params: TextDocumentPositionParams,
     ) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let (path, position) = as_path_pos(params);
         run_query!(req_id, self.PrepareRename(path, position))
     }
@@ -272,15 +357,52 @@ impl ServerState {
         params: WorkspaceSymbolParams,
     ) -> ScheduledResult {
         let pattern = (!params.query.is_empty()).then_some(params.query);
-        run_query!(req_id, self.Symbol(pattern))
+        // Only reports the progress of indexing the workspace if asked by the
+        // client, which otherwise flickers while typing the query.
+        match params.work_done_progress_params.work_done_token {
+            Some(token) => {
+                let progress = self.begin_progress("Indexing workspace symbols", Some(token));
+                run_query!(req_id, self.Symbol(pattern), progress)
+            }
+            None => run_query!(req_id, self.Symbol(pattern)),
+        }
     }
 
     pub(crate) fn on_enter(&mut self, req_id: RequestId, params: OnEnterParams) -> ScheduledResult {
+        let params = self.notebooks.hidden.unhide(params);
         let path = as_path(params.text_document);
         let range = params.range;
         run_query!(req_id, self.OnEnter(path, range))
     }
 
+    pub(crate) fn compile_snippet(
+        &mut self,
+        req_id: RequestId,
+        params: CompileSnippetParams,
+    ) -> ScheduledResult {
+        let path = as_path(params.text_document);
+        let text = params.text;
+        run_query!(req_id, self.CompileSnippet(path, text))
+    }
+
+    pub(crate) fn resolved_outline(
+        &mut self,
+        req_id: RequestId,
+        params: ResolvedOutlineParams,
+    ) -> ScheduledResult {
+        let path = as_path(params.text_document);
+        run_query!(req_id, self.DocumentOutline(path))
+    }
+
+    pub(crate) fn debug_ast(
+        &mut self,
+        req_id: RequestId,
+        params: DebugAstParams,
+    ) -> ScheduledResult {
+        let path = as_path(params.text_document);
+        run_query!(req_id, self.DebugAst(path))
+    }
+
     pub(crate) fn will_rename_files(
         &mut self,
         req_id: RequestId,
@@ -299,7 +421,9 @@ impl ServerState {
             .collect::<Option<Vec<_>>>()
             .ok_or_else(|| invalid_params("invalid urls"))?;
 
-        run_query!(req_id, self.WillRenameFiles(paths))
+        let progress = self.begin_progress("Renaming files", None);
+        progress.report(Some("updating the paths in the workspace"));
+        run_query!(req_id, self.WillRenameFiles(paths), progress)
     }
 }
 
@@ -323,9 +447,8 @@ impl ServerState {
         just_ok(match query {
             FoldingRange(req) => query_source!(self, FoldingRange, req)?,
             SelectionRange(req) => query_source!(self, SelectionRange, req)?,
-            DocumentSymbol(req) => query_source!(self, DocumentSymbol, req)?,
             OnEnter(req) => query_source!(self, OnEnter, req)?,
-            ColorPresentation(req) => CompilerQueryResponse::ColorPresentation(req.request()),
+            ColorPresentation(req) => query_source!(self, ColorPresentation, req)?,
             OnExport(req) => return self.on_export(req),
             ServerInfo(_) => return self.collect_server_info(),
             // todo: query on dedicate projects
@@ -391,7 +514,20 @@ impl ServerState {
                 PrepareRename(req) => snap.run_stateful(req, R::PrepareRename),
                 Symbol(req) => snap.run_semantic(req, R::Symbol),
                 WorkspaceLabel(req) => snap.run_semantic(req, R::WorkspaceLabel),
+                ProseReplace(req) => snap.run_semantic(req, R::ProseReplace),
+                IndexTerms(req) => snap.run_semantic(req, R::IndexTerms),
+                DropFiles(req) => snap.run_semantic(req, R::DropFiles),
+                SmartPaste(req) => snap.run_semantic(req, R::SmartPaste),
+                CompileSnippet(req) => snap.run_semantic(req, R::CompileSnippet),
+                ExportSelection(req) => snap.run_semantic(req, R::ExportSelection),
+                ProjectAssets(req) => snap.run_stateful(req, R::ProjectAssets),
+                MoveAsset(req) => snap.run_stateful(req, R::MoveAsset),
+                DocumentSymbol(req) => snap.run_stateful(req, R::DocumentSymbol),
                 DocumentMetrics(req) => snap.run_stateful(req, R::DocumentMetrics),
+                DocumentOutline(req) => snap.run_stateful(req, R::DocumentOutline),
+                DebugAst(req) => snap.run_semantic(req, R::DebugAst),
+                FontFallback(req) => snap.run_stateful(req, R::FontFallback),
+                ChangeImpact(req) => snap.run_stateful(req, R::ChangeImpact),
                 _ => unreachable!(),
             }
         })
@@ -417,3 +553,51 @@ impl lsp_types::request::Request for OnEnter {
     type Result = Option<Vec<TextEdit>>;
     const METHOD: &'static str = "experimental/onEnter";
 }
+
+/// A parameter for the `tinymist/compileSnippet` request.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileSnippetParams {
+    /// The document providing the imports and styles to the snippet.
+    pub text_document: TextDocumentIdentifier,
+
+    /// The snippet of Typst markup to compile.
+    pub text: String,
+}
+
+pub struct CompileSnippet;
+impl lsp_types::request::Request for CompileSnippet {
+    type Params = CompileSnippetParams;
+    type Result = Option<tinymist_query::CompileSnippetResponse>;
+    const METHOD: &'static str = "tinymist/compileSnippet";
+}
+
+/// A parameter for the `tinymist/resolvedOutline` request.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedOutlineParams {
+    /// The text document.
+    pub text_document: TextDocumentIdentifier,
+}
+
+pub struct ResolvedOutline;
+impl lsp_types::request::Request for ResolvedOutline {
+    type Params = ResolvedOutlineParams;
+    type Result = Option<Vec<tinymist_query::DocumentOutlineItem>>;
+    const METHOD: &'static str = "tinymist/resolvedOutline";
+}
+
+/// A parameter for the `tinymist/debugAst` request.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugAstParams {
+    /// The text document.
+    pub text_document: TextDocumentIdentifier,
+}
+
+pub struct DebugAst;
+impl lsp_types::request::Request for DebugAst {
+    type Params = DebugAstParams;
+    type Result = Option<tinymist_query::DebugAstResponse>;
+    const METHOD: &'static str = "tinymist/debugAst";
+}
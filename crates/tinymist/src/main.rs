@@ -13,27 +13,46 @@ use clap::Parser;
 use clap_builder::CommandFactory;
 use clap_complete::generate;
 use futures::future::MaybeDone;
+use lsp_types::Url;
 use parking_lot::Mutex;
 use reflexo::ImmutPath;
 use reflexo_typst::package::PackageSpec;
 use sync_ls::transport::{with_stdio_transport, MirrorArgs};
 use sync_ls::{
-    internal_error, DapBuilder, DapMessage, GetMessageKind, LsHook, LspBuilder, LspClientRoot,
-    LspMessage, LspResult, Message, RequestId, TConnectionTx,
+    internal_error, invalid_params, DapBuilder, DapMessage, GetMessageKind, LsHook, LspBuilder,
+    LspClientRoot, LspMessage, LspResult, Message, RequestId, TConnectionTx,
 };
+use tinymist::tool::analysis::analysis_main;
+use tinymist::tool::ci::ci_main;
+use tinymist::tool::convert::convert_latex_main;
+use tinymist::tool::fonts::fonts_main;
+use tinymist::tool::format::format_main;
+use tinymist::tool::index::index_main;
+use tinymist::tool::lint::lint_main;
+use tinymist::tool::merge::merge_main;
+use tinymist::tool::package::package_main;
+use tinymist::tool::probe::probe_main;
 use tinymist::tool::project::{compile_main, generate_script_main, project_main, task_main};
+use tinymist::tool::query::query_selector_main;
+use tinymist::tool::self_test::self_test_main;
+use tinymist::tool::serve::serve_main;
 use tinymist::tool::testing::{coverage_main, test_main};
 use tinymist::world::TaskInputs;
 use tinymist::{Config, DapRegularInit, RegularInit, ServerState, SuperInit, UserActionTask};
 use tinymist_core::LONG_VERSION;
 use tinymist_project::EntryResolver;
-use tinymist_query::package::PackageInfo;
+use tinymist_query::package::{PackageInfo, PackageIssueSeverity};
+use tinymist_query::{
+    apply_workspace_edit_on_disk, preview_workspace_edit, url_to_path, CompilerQueryRequest,
+    CompilerQueryResponse, LspPosition, RenameRequest,
+};
 use tinymist_std::hash::{FxBuildHasher, FxHashMap};
 use tinymist_std::{bail, error::prelude::*};
 
 #[cfg(feature = "l10n")]
 use tinymist_l10n::{load_translations, set_translations};
 use typst::ecow::EcoString;
+use typst::syntax::Source;
 
 use crate::args::*;
 
@@ -69,7 +88,7 @@ fn main() -> Result<()> {
     let args = CliArguments::parse();
 
     // Probes soon to avoid other initializations causing errors
-    if matches!(args.command, Some(Commands::Probe)) {
+    if matches!(&args.command, Some(Commands::Probe(probe)) if !probe.doctor) {
         return Ok(());
     }
 
@@ -79,7 +98,19 @@ fn main() -> Result<()> {
 
     // Starts logging
     let _ = {
-        let is_transient_cmd = matches!(args.command, Some(Commands::Compile(..)));
+        let is_transient_cmd = matches!(
+            args.command,
+            Some(
+                Commands::Compile(..)
+                    | Commands::Merge(..)
+                    | Commands::Ci(..)
+                    | Commands::Format(..)
+                    | Commands::Lint(..)
+                    | Commands::ConvertLatex(..)
+                    | Commands::Fonts(..)
+                    | Commands::Probe(..)
+            )
+        );
         let is_test_no_verbose =
             matches!(&args.command, Some(Commands::Test(test)) if !test.verbose);
         use log::LevelFilter::*;
@@ -104,11 +135,22 @@ fn main() -> Result<()> {
         Commands::Cov(args) => coverage_main(args),
         Commands::Test(args) => RUNTIMES.tokio_runtime.block_on(test_main(args)),
         Commands::Compile(args) => RUNTIMES.tokio_runtime.block_on(compile_main(args)),
+        Commands::Merge(args) => merge_main(args),
+        Commands::Ci(args) => RUNTIMES.tokio_runtime.block_on(ci_main(args)),
+        Commands::Format(args) => format_main(args),
+        Commands::Lint(args) => lint_main(args),
+        Commands::ConvertLatex(args) => convert_latex_main(args),
+        Commands::Fonts(args) => fonts_main(args),
         Commands::GenerateScript(args) => generate_script_main(args),
+        Commands::Index(args) => index_main(args),
+        Commands::SelfTest(args) => self_test_main(args),
+        Commands::Analysis(args) => analysis_main(args),
+        Commands::Query(QueryCommands::Selector(args)) => query_selector_main(args),
         Commands::Query(query_cmds) => query_main(query_cmds),
         Commands::Lsp(args) => lsp_main(args),
         Commands::Dap(args) => dap_main(args),
         Commands::TraceLsp(args) => trace_lsp_main(args),
+        Commands::Serve(args) => RUNTIMES.tokio_runtime.block_on(serve_main(args)),
         #[cfg(feature = "preview")]
         Commands::Preview(args) => {
             #[cfg(feature = "preview")]
@@ -118,7 +160,8 @@ fn main() -> Result<()> {
         }
         Commands::Doc(args) => project_main(args),
         Commands::Task(args) => task_main(args),
-        Commands::Probe => Ok(()),
+        Commands::Package(args) => package_main(args),
+        Commands::Probe(args) => probe_main(args),
     }
 }
 
@@ -304,14 +347,32 @@ pub fn query_main(cmds: QueryCommands) -> Result<()> {
                     let path = path
                         .unwrap_or_else(|| snap.registry().resolve(&pkg).unwrap().as_ref().into());
 
-                    let res = state
-                        .resource_package_docs_(PackageInfo {
-                            path,
-                            namespace: pkg.namespace,
-                            name: pkg.name,
-                            version: pkg.version.to_string(),
-                        })?
-                        .await?;
+                    let info = PackageInfo {
+                        path,
+                        namespace: pkg.namespace,
+                        name: pkg.name,
+                        version: pkg.version.to_string(),
+                    };
+                    let res = match args.format {
+                        None => state.resource_package_docs_(info)?.await?,
+                        Some(QueryDocsFormat::Markdown) => {
+                            state
+                                .within_package(info.clone(), move |a| {
+                                    tinymist_query::docs::package_markdown_docs(a, &info)
+                                        .map_err(internal_error)
+                                })?
+                                .await?
+                        }
+                        Some(QueryDocsFormat::Json) => {
+                            state
+                                .within_package(info.clone(), move |a| {
+                                    let docs = tinymist_query::docs::package_module_docs(a, &info)
+                                        .map_err(internal_error)?;
+                                    serde_json::to_string_pretty(&docs).map_err(internal_error)
+                                })?
+                                .await?
+                        }
+                    };
 
                     let output_path = Path::new(&args.output);
                     std::fs::write(output_path, res).map_err(internal_error)?;
@@ -322,7 +383,7 @@ pub fn query_main(cmds: QueryCommands) -> Result<()> {
                     let path = path
                         .unwrap_or_else(|| snap.registry().resolve(&pkg).unwrap().as_ref().into());
 
-                    state
+                    let issues = state
                         .check_package(PackageInfo {
                             path,
                             namespace: pkg.namespace,
@@ -330,6 +391,67 @@ pub fn query_main(cmds: QueryCommands) -> Result<()> {
                             version: pkg.version.to_string(),
                         })?
                         .await?;
+
+                    let json = serde_json::to_string_pretty(&issues).map_err(internal_error)?;
+                    std::fs::write(&args.output, json).map_err(internal_error)?;
+
+                    let mut errors = 0;
+                    for issue in &issues {
+                        let severity = match issue.severity {
+                            PackageIssueSeverity::Error => {
+                                errors += 1;
+                                "error"
+                            }
+                            PackageIssueSeverity::Warning => "warning",
+                        };
+                        let path = issue.path.as_deref().unwrap_or_default();
+                        let line = issue
+                            .line
+                            .map(|line| format!(":{line}"))
+                            .unwrap_or_default();
+                        eprintln!("{severity}: {path}{line}: {}", issue.message);
+                    }
+                    if errors > 0 {
+                        return Err(internal_error(format!(
+                            "{errors} error(s) found in the package"
+                        )));
+                    }
+                }
+                QueryCommands::Selector(..) => unreachable!("queried without the server"),
+                QueryCommands::Rename(args) => {
+                    let path = std::path::absolute(&args.path).map_err(internal_error)?;
+                    let position = LspPosition::new(
+                        args.line.saturating_sub(1),
+                        args.column.saturating_sub(1),
+                    );
+                    let req = RenameRequest {
+                        path,
+                        position,
+                        new_name: args.new_name,
+                    };
+                    let resp = match state.query(CompilerQueryRequest::Rename(req)) {
+                        Ok(MaybeDone::Done(resp)) => resp,
+                        Ok(MaybeDone::Future(fut)) => fut.await,
+                        Ok(MaybeDone::Gone) => unreachable!(),
+                        Err(err) => Err(err),
+                    };
+                    let Some(edit) = (match resp.map_err(internal_error)? {
+                        CompilerQueryResponse::Rename(edit) => edit,
+                        _ => unreachable!(),
+                    }) else {
+                        return Err(invalid_params("no symbol to rename at the position"));
+                    };
+
+                    let encoding = state.const_config().position_encoding;
+                    if args.dry_run {
+                        let read = |uri: &Url| {
+                            let text = std::fs::read_to_string(url_to_path(uri.clone())).ok()?;
+                            Some(Source::detached(text))
+                        };
+                        print!("{}", preview_workspace_edit(&edit, encoding, &read));
+                    } else {
+                        apply_workspace_edit_on_disk(&edit, encoding).map_err(internal_error)?;
+                    }
                 }
             };
 
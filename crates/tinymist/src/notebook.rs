@@ -0,0 +1,866 @@
+//! Synchronizes the notebook documents, e.g. the Typst cells in a Jupyter
+//! notebook.
+//!
+//! Each Typst cell is backed by a virtual file beside the notebook. A cell
+//! shares the scope of the cells executed before it, by importing them in a
+//! first line, which is hidden from the cell document, so the positions are
+//! shifted by [`HiddenLines`] in the requests and the responses. The cells
+//! that are never executed follow the executed ones in document order.
+
+use std::sync::Arc;
+
+use lsp_types::notification::Notification;
+use lsp_types::*;
+use reflexo_typst::Bytes;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tinymist_query::{
+    path_to_url, register_virtual_url, to_typst_range, unregister_virtual_url, url_to_path,
+    CompilerQueryResponse, PositionEncoding,
+};
+use tinymist_std::error::{prelude::*, IgnoreLogging};
+use tinymist_std::ImmutPath;
+use typst::{diag::FileResult, syntax::Source};
+
+use crate::actor::editor::EditorRequest;
+use crate::world::vfs::FileChangeSet;
+use crate::*;
+
+/// The capability to synchronize the Typst cells of any notebook.
+pub(crate) fn notebook_document_sync() -> serde_json::Value {
+    serde_json::json!({
+        "notebookSelector": [{ "cells": [{ "language": "typst" }] }],
+    })
+}
+
+/// A notebook document, of which only the used fields are deserialized.
+///
+/// The notebook protocol is not supported by `lsp_types` 0.95 yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocument {
+    /// The uri of the notebook.
+    pub uri: Url,
+    /// The cells of the notebook.
+    pub cells: Vec<NotebookCell>,
+}
+
+/// A cell of a notebook document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCell {
+    /// The uri of the cell's text document.
+    pub document: Url,
+    /// The summary of the last execution of the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_summary: Option<ExecutionSummary>,
+}
+
+/// The summary of the execution of a cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionSummary {
+    /// The strictly monotonically increasing execution order.
+    pub execution_order: u32,
+}
+
+/// The identifier of a notebook document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentIdentifier {
+    /// The uri of the notebook.
+    pub uri: Url,
+}
+
+/// The parameters of the `notebookDocument/didOpen` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenNotebookDocumentParams {
+    /// The opened notebook.
+    pub notebook_document: NotebookDocument,
+    /// The text documents of the cells selected by the server.
+    pub cell_text_documents: Vec<TextDocumentItem>,
+}
+
+/// The parameters of the `notebookDocument/didChange` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeNotebookDocumentParams {
+    /// The changed notebook.
+    pub notebook_document: NotebookDocumentIdentifier,
+    /// The changes of the notebook.
+    pub change: NotebookDocumentChangeEvent,
+}
+
+/// The parameters of the `notebookDocument/didClose` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCloseNotebookDocumentParams {
+    /// The closed notebook.
+    pub notebook_document: NotebookDocumentIdentifier,
+}
+
+/// The changes of a notebook document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentChangeEvent {
+    /// The changes of the cells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells: Option<NotebookDocumentCellChange>,
+}
+
+/// The changes of the cells of a notebook document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentCellChange {
+    /// The changes to the cell structure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure: Option<NotebookDocumentCellChangeStructure>,
+    /// The changed cells, e.g. the executed ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<NotebookCell>>,
+    /// The changes to the text contents of the cells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_content: Option<Vec<NotebookDocumentChangeTextContent>>,
+}
+
+/// The changes to the cell structure of a notebook document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentCellChangeStructure {
+    /// The change to the cell array.
+    pub array: NotebookCellArrayChange,
+    /// The opened text documents of the added cells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did_open: Option<Vec<TextDocumentItem>>,
+    /// The closed text documents of the removed cells.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did_close: Option<Vec<TextDocumentIdentifier>>,
+}
+
+/// A change to the cell array of a notebook document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCellArrayChange {
+    /// The start offset of the cell that changed.
+    pub start: u32,
+    /// The number of the deleted cells.
+    pub delete_count: u32,
+    /// The new cells, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells: Option<Vec<NotebookCell>>,
+}
+
+/// The changes to the text content of a cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentChangeTextContent {
+    /// The text document of the cell.
+    pub document: VersionedTextDocumentIdentifier,
+    /// The changes to the text document.
+    pub changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+/// The notification sent when a notebook is opened.
+#[derive(Debug)]
+pub enum DidOpenNotebookDocument {}
+
+impl Notification for DidOpenNotebookDocument {
+    type Params = DidOpenNotebookDocumentParams;
+    const METHOD: &'static str = "notebookDocument/didOpen";
+}
+
+/// The notification sent when a notebook is changed.
+#[derive(Debug)]
+pub enum DidChangeNotebookDocument {}
+
+impl Notification for DidChangeNotebookDocument {
+    type Params = DidChangeNotebookDocumentParams;
+    const METHOD: &'static str = "notebookDocument/didChange";
+}
+
+/// The notification sent when a notebook is closed.
+#[derive(Debug)]
+pub enum DidCloseNotebookDocument {}
+
+impl Notification for DidCloseNotebookDocument {
+    type Params = DidCloseNotebookDocumentParams;
+    const METHOD: &'static str = "notebookDocument/didClose";
+}
+
+/// The opened notebooks.
+#[derive(Default)]
+pub(crate) struct NotebookState {
+    opened: HashMap<Url, Notebook>,
+    /// The lines hidden from the cell documents of the opened notebooks.
+    pub hidden: HiddenLines,
+}
+
+impl NotebookState {
+    /// Updates the lines hidden from the cell documents, returning whether
+    /// they are changed.
+    fn update_hidden(&mut self) -> bool {
+        let cells = self.opened.values().flat_map(Notebook::hidden_lines);
+        let hidden = cells.collect::<HashMap<_, _>>();
+        if *self.hidden.0 == hidden {
+            return false;
+        }
+        self.hidden = HiddenLines(Arc::new(hidden));
+        true
+    }
+}
+
+/// The numbers of the lines hidden from the cell documents, i.e. the first
+/// lines of the virtual files importing the cells executed before.
+///
+/// The server analyzes the virtual files, so the positions in the requests on
+/// a cell are shifted down by its hidden lines, and the ones in the responses
+/// are shifted back up.
+#[derive(Debug, Default, Clone)]
+pub struct HiddenLines(Arc<HashMap<Url, u32>>);
+
+impl HiddenLines {
+    /// Whether no line is hidden.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Gets the number of the lines hidden from a document.
+    pub fn get(&self, uri: &Url) -> u32 {
+        self.0.get(uri).copied().unwrap_or_default()
+    }
+
+    /// Gets the number of the lines hidden from the document of a file.
+    pub fn get_path(&self, path: &Path) -> u32 {
+        if self.is_empty() {
+            return 0;
+        }
+        path_to_url(path).map_or(0, |uri| self.get(&uri))
+    }
+
+    /// Shifts the positions in the parameters of a request to the positions in
+    /// the virtual files.
+    pub fn unhide<T: Serialize + DeserializeOwned>(&self, params: T) -> T {
+        if self.is_empty() {
+            return params;
+        }
+        let Ok(mut value) = serde_json::to_value(&params) else {
+            return params;
+        };
+        self.shift(&mut value, 0, false);
+        serde_json::from_value(value).unwrap_or(params)
+    }
+
+    /// Shifts the positions in the response to a request on a file, which has
+    /// the given number of hidden lines, to the positions in the cell
+    /// documents. The positions in the hidden lines are clamped to the start of
+    /// the documents.
+    pub fn hide(&self, lines: u32, mut resp: CompilerQueryResponse) -> JsonValue {
+        use CompilerQueryResponse::SemanticTokensFull;
+        if let SemanticTokensFull(Some(SemanticTokensResult::Tokens(tokens))) = &mut resp {
+            hide_tokens(&mut tokens.data, lines);
+        }
+
+        let mut value = serde_json::to_value(resp).unwrap_or_default();
+        self.shift(&mut value, lines, true);
+        value
+    }
+
+    /// Shifts the positions in the diagnostics of a document to the positions
+    /// in the cell documents.
+    pub fn hide_diagnostics(&self, uri: &Url, diags: &mut [Diagnostic]) {
+        let lines = self.get(uri);
+        for diag in diags {
+            diag.range = hide_range(diag.range, lines);
+            for info in diag.related_information.iter_mut().flatten() {
+                let lines = self.get(&info.location.uri);
+                info.location.range = hide_range(info.location.range, lines);
+            }
+        }
+    }
+
+    /// Shifts the positions in a value, where the positions are in a document
+    /// with the given number of hidden lines unless they are nested in a
+    /// location or an edit of another document.
+    fn shift(&self, value: &mut JsonValue, lines: u32, hide: bool) {
+        let obj = match value {
+            JsonValue::Array(items) => {
+                for item in items {
+                    self.shift(item, lines, hide);
+                }
+                return;
+            }
+            JsonValue::Object(obj) => obj,
+            _ => return,
+        };
+
+        // A position.
+        if let (Some(line), Some(_)) = (obj.get("line"), obj.get("character")) {
+            let line = line.as_u64().unwrap_or_default() as u32;
+            match (hide, line.checked_sub(lines)) {
+                (false, _) => obj["line"] = (line + lines).into(),
+                (true, Some(line)) => obj["line"] = line.into(),
+                (true, None) => {
+                    obj["line"] = 0.into();
+                    obj["character"] = 0.into();
+                }
+            }
+            return;
+        }
+        // A folding range.
+        if hide {
+            for key in ["startLine", "endLine"] {
+                if let Some(line) = obj.get(key).and_then(JsonValue::as_u64) {
+                    obj[key] = (line as u32).saturating_sub(lines).into();
+                }
+            }
+        }
+
+        let outer = lines;
+        let uri = obj.get("uri").or_else(|| obj.get("targetUri"));
+        let uri = uri.or_else(|| obj.get("textDocument").and_then(|doc| doc.get("uri")));
+        let uri = uri
+            .and_then(JsonValue::as_str)
+            .and_then(|uri| Url::parse(uri).ok());
+        let lines = uri.map_or(lines, |uri| self.get(&uri));
+        for (key, value) in obj.iter_mut() {
+            match (key.as_str(), value) {
+                // The origin of a location link is in the document of the request.
+                ("originSelectionRange", value) => self.shift(value, outer, hide),
+                // The edits of a workspace edit, by the uris of the documents.
+                ("changes", JsonValue::Object(changes)) => {
+                    for (uri, edits) in changes {
+                        let lines = Url::parse(uri).map_or(0, |uri| self.get(&uri));
+                        self.shift(edits, lines, hide);
+                    }
+                }
+                (_, value) => self.shift(value, lines, hide),
+            }
+        }
+    }
+}
+
+/// Shifts a range in a document with the given number of hidden lines to the
+/// range in the cell document.
+fn hide_range(range: lsp_types::Range, lines: u32) -> lsp_types::Range {
+    let hide = |pos: Position| match pos.line.checked_sub(lines) {
+        Some(line) => Position::new(line, pos.character),
+        None => Position::new(0, 0),
+    };
+    lsp_types::Range::new(hide(range.start), hide(range.end))
+}
+
+/// Drops the semantic tokens in the hidden lines, and makes the first visible
+/// token relative to the start of the cell document.
+fn hide_tokens(tokens: &mut Vec<SemanticToken>, lines: u32) {
+    if lines == 0 {
+        return;
+    }
+    let mut line = 0;
+    let hidden = tokens
+        .iter()
+        .take_while(|token| {
+            line += token.delta_line;
+            line < lines
+        })
+        .count();
+    tokens.drain(..hidden);
+    if let Some(first) = tokens.first_mut() {
+        first.delta_line = line - lines;
+    }
+}
+
+/// An opened notebook.
+struct Notebook {
+    /// The directory of the notebook.
+    dir: PathBuf,
+    /// The file name of the notebook.
+    name: String,
+    /// The id of the next virtual file.
+    next_id: usize,
+    /// The cells in document order.
+    cells: Vec<Cell>,
+}
+
+/// A cell of a notebook.
+struct Cell {
+    /// The uri of the cell document.
+    uri: Url,
+    /// The path of the virtual file backing the cell.
+    path: ImmutPath,
+    /// The execution order of the cell, or `None` if it is never executed.
+    execution_order: Option<u32>,
+    /// The source of the cell, or `None` if it is not a synchronized Typst
+    /// cell.
+    source: Option<Source>,
+}
+
+/// The content of the virtual file backing a cell.
+#[derive(Debug, PartialEq)]
+struct CellContent {
+    /// The path of the virtual file.
+    path: ImmutPath,
+    /// The text of the virtual file.
+    text: String,
+}
+
+impl Notebook {
+    fn new(doc: NotebookDocument, cell_docs: Vec<TextDocumentItem>) -> Self {
+        let path = url_to_path(doc.uri);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "notebook".to_owned());
+        let dir = path.parent().map(Path::to_owned).unwrap_or_default();
+
+        let mut notebook = Self {
+            dir,
+            name,
+            next_id: 0,
+            cells: vec![],
+        };
+        for cell in doc.cells {
+            let cell = notebook.new_cell(cell);
+            notebook.cells.push(cell);
+        }
+        for cell_doc in cell_docs {
+            notebook.open_cell(cell_doc);
+        }
+        notebook
+    }
+
+    fn new_cell(&mut self, cell: NotebookCell) -> Cell {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let path: ImmutPath = self
+            .dir
+            .join(format!(".{}.cell-{id}.typ", self.name))
+            .as_path()
+            .into();
+        register_virtual_url(path.to_path_buf(), cell.document.clone());
+
+        Cell {
+            uri: cell.document,
+            path,
+            execution_order: cell
+                .execution_summary
+                .map(|summary| summary.execution_order),
+            source: None,
+        }
+    }
+
+    fn cell_mut(&mut self, uri: &Url) -> Option<&mut Cell> {
+        self.cells.iter_mut().find(|cell| cell.uri == *uri)
+    }
+
+    fn open_cell(&mut self, doc: TextDocumentItem) {
+        if let Some(cell) = self.cell_mut(&doc.uri) {
+            cell.source = Some(Source::detached(doc.text));
+        }
+    }
+
+    /// Applies the changes to the cells, returning the paths of the virtual
+    /// files that are removed.
+    fn apply_change(
+        &mut self,
+        change: NotebookDocumentChangeEvent,
+        position_encoding: PositionEncoding,
+    ) -> Result<Vec<ImmutPath>> {
+        let Some(cells) = change.cells else {
+            return Ok(vec![]);
+        };
+
+        let mut removes = vec![];
+        if let Some(structure) = cells.structure {
+            let array = structure.array;
+            let start = (array.start as usize).min(self.cells.len());
+            let end = (start + array.delete_count as usize).min(self.cells.len());
+            let mut deleted = self.cells.drain(start..end).collect::<Vec<_>>();
+            // A moved cell is deleted and inserted again without reopening its
+            // document, so it keeps its source.
+            let inserted = array.cells.unwrap_or_default();
+            let inserted = inserted
+                .into_iter()
+                .map(
+                    |cell| match deleted.iter().position(|c| c.uri == cell.document) {
+                        Some(idx) => {
+                            let mut moved = deleted.swap_remove(idx);
+                            moved.execution_order =
+                                cell.execution_summary.map(|s| s.execution_order);
+                            moved
+                        }
+                        None => self.new_cell(cell),
+                    },
+                )
+                .collect::<Vec<_>>();
+            self.cells.splice(start..start, inserted);
+            for cell in deleted {
+                unregister_virtual_url(&cell.path);
+                removes.push(cell.path);
+            }
+
+            for cell_doc in structure.did_close.unwrap_or_default() {
+                if let Some(cell) = self.cell_mut(&cell_doc.uri) {
+                    cell.source = None;
+                    removes.push(cell.path.clone());
+                }
+            }
+            for cell_doc in structure.did_open.unwrap_or_default() {
+                self.open_cell(cell_doc);
+            }
+        }
+
+        for data in cells.data.unwrap_or_default() {
+            if let Some(cell) = self.cell_mut(&data.document) {
+                cell.execution_order = data.execution_summary.map(|s| s.execution_order);
+            }
+        }
+
+        for content in cells.text_content.unwrap_or_default() {
+            let cell = self.cell_mut(&content.document.uri);
+            let Some(source) = cell.and_then(|cell| cell.source.as_mut()) else {
+                continue;
+            };
+            for change in content.changes {
+                match change.range {
+                    Some(lsp_range) => {
+                        let range = to_typst_range(lsp_range, position_encoding, source)
+                            .context("invalid range")?;
+                        source.edit(range, &change.text);
+                    }
+                    None => source.replace(&change.text),
+                }
+            }
+        }
+
+        Ok(removes)
+    }
+
+    /// Gets the Typst cells in execution order.
+    fn executed_cells(&self) -> Vec<&Cell> {
+        let mut cells = self
+            .cells
+            .iter()
+            .filter(|cell| cell.source.is_some())
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|cell| cell.execution_order.unwrap_or(u32::MAX));
+        cells
+    }
+
+    /// Gets the contents of the virtual files, where each cell imports the
+    /// cells executed before it.
+    fn contents(&self) -> Vec<CellContent> {
+        let mut imports = vec![];
+        let mut contents = vec![];
+        for cell in self.executed_cells() {
+            let text = cell.source.as_ref().map(Source::text).unwrap_or_default();
+            let text = if !imports.is_empty() {
+                format!("{}\n{text}", imports.join(" "))
+            } else {
+                text.to_owned()
+            };
+            contents.push(CellContent {
+                path: cell.path.clone(),
+                text,
+            });
+
+            let name = cell.path.file_name().unwrap_or_default().to_string_lossy();
+            imports.push(format!("#import {name:?}: *;"));
+        }
+        contents
+    }
+
+    /// Gets the numbers of the lines hidden from the cell documents, which are
+    /// the first lines of the cells executed after the first one.
+    fn hidden_lines(&self) -> impl Iterator<Item = (Url, u32)> + '_ {
+        let cells = self.executed_cells().into_iter().skip(1);
+        cells.map(|cell| (cell.uri.clone(), 1))
+    }
+
+    /// Gets the cell executed last, which imports all the other cells.
+    fn entry(&self) -> Option<ImmutPath> {
+        let cells = self.executed_cells();
+        cells.last().map(|cell| cell.path.clone())
+    }
+}
+
+impl Drop for Notebook {
+    fn drop(&mut self) {
+        for cell in &self.cells {
+            unregister_virtual_url(&cell.path);
+        }
+    }
+}
+
+/// In memory notebook management.
+impl ServerState {
+    /// Opens a notebook.
+    pub fn open_notebook(
+        &mut self,
+        doc: NotebookDocument,
+        cell_docs: Vec<TextDocumentItem>,
+    ) -> Result<()> {
+        for cell_doc in &cell_docs {
+            self.prefetch_packages(&Source::detached(cell_doc.text.as_str()));
+        }
+
+        let uri = doc.uri.clone();
+        let notebook = Notebook::new(doc, cell_docs);
+        self.notebooks.opened.insert(uri.clone(), notebook);
+        self.sync_notebook(&uri, vec![])
+    }
+
+    /// Changes the structure, the execution and the content of the cells in a
+    /// notebook.
+    pub fn change_notebook(
+        &mut self,
+        uri: &Url,
+        change: NotebookDocumentChangeEvent,
+        position_encoding: PositionEncoding,
+    ) -> Result<()> {
+        let notebook = self.notebooks.opened.get_mut(uri);
+        let notebook = notebook.ok_or_else(|| error_once!("notebook missing", uri: uri))?;
+        let removes = notebook.apply_change(change, position_encoding)?;
+        self.sync_notebook(uri, removes)
+    }
+
+    /// Closes a notebook.
+    pub fn close_notebook(&mut self, uri: &Url) -> Result<()> {
+        let Some(notebook) = self.notebooks.opened.remove(uri) else {
+            return Ok(());
+        };
+
+        let removes = notebook.cells.iter().map(|cell| cell.path.clone());
+        let removes = removes.collect::<Vec<_>>();
+        for path in &removes {
+            self.memory_changes.remove(path);
+        }
+        self.sync_hidden_lines();
+        self.update_sources(FileChangeSet::new_removes(removes))
+    }
+
+    /// Synchronizes the lines hidden from the cell documents to the editor
+    /// actor, which shifts the positions of the diagnostics.
+    fn sync_hidden_lines(&mut self) {
+        // The lines are sent before the sources are updated, so the
+        // diagnostics of the new sources are always shifted correctly.
+        if self.notebooks.update_hidden() {
+            let hidden = self.notebooks.hidden.clone();
+            self.editor_tx
+                .send(EditorRequest::HiddenLines(hidden))
+                .log_error("could not send hidden lines to the editor actor");
+        }
+    }
+
+    /// Synchronizes the virtual files of a notebook to the memory.
+    fn sync_notebook(&mut self, uri: &Url, removes: Vec<ImmutPath>) -> Result<()> {
+        let Some(notebook) = self.notebooks.opened.get(uri) else {
+            return Ok(());
+        };
+        let entry = notebook.entry();
+        let contents = notebook.contents();
+
+        self.sync_hidden_lines();
+
+        let mut files = FileChangeSet::new_removes(removes);
+        for path in &files.removes {
+            self.memory_changes.remove(path);
+        }
+        for CellContent { path, text } in contents {
+            let source = self.memory_changes.get(&path);
+            if source.is_some_and(|source| source.text() == text) {
+                continue;
+            }
+
+            let snapshot = FileResult::Ok(Bytes::from_string(text.clone())).into();
+            files.inserts.push((path.clone(), snapshot));
+            self.memory_changes.insert(path, Source::detached(text));
+        }
+
+        if !files.is_empty() {
+            self.update_sources(files)?;
+        }
+
+        self.implicit_focus_entry(|| entry, 'o');
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The notebooks of the tests are named differently, as the urls of the
+    // cells are registered globally.
+    fn cell(nb: &str, id: usize, order: Option<u32>) -> NotebookCell {
+        NotebookCell {
+            document: cell_uri(nb, id),
+            execution_summary: order.map(|execution_order| ExecutionSummary { execution_order }),
+        }
+    }
+
+    fn cell_uri(nb: &str, id: usize) -> Url {
+        Url::parse(&format!("vscode-notebook-cell:/nb/{nb}.ipynb#cell{id}")).unwrap()
+    }
+
+    fn cell_doc(nb: &str, id: usize, text: &str) -> TextDocumentItem {
+        TextDocumentItem::new(cell_uri(nb, id), "typst".to_owned(), 0, text.to_owned())
+    }
+
+    fn open(nb: &str) -> Notebook {
+        let doc = NotebookDocument {
+            uri: Url::parse(&format!("file:///nb/{nb}.ipynb")).unwrap(),
+            cells: vec![
+                cell(nb, 0, Some(2)),
+                cell(nb, 1, None),
+                cell(nb, 2, Some(1)),
+            ],
+        };
+        let cell_docs = vec![cell_doc(nb, 0, "#let a = b"), cell_doc(nb, 2, "#let b = 1")];
+        Notebook::new(doc, cell_docs)
+    }
+
+    fn texts(notebook: &Notebook) -> Vec<(String, String)> {
+        let contents = notebook.contents().into_iter();
+        contents
+            .map(|content| {
+                let name = content.path.file_name().unwrap().to_string_lossy();
+                (name.into_owned(), content.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn open_in_execution_order() {
+        let notebook = open("open");
+        assert_eq!(
+            texts(&notebook),
+            [
+                (".open.ipynb.cell-2.typ".into(), "#let b = 1".into()),
+                (
+                    ".open.ipynb.cell-0.typ".into(),
+                    "#import \".open.ipynb.cell-2.typ\": *;\n#let a = b".into(),
+                ),
+            ]
+        );
+        let hidden = notebook.hidden_lines().collect::<Vec<_>>();
+        assert_eq!(hidden, [(cell_uri("open", 0), 1)]);
+        let entry = notebook.entry().unwrap();
+        assert!(entry.ends_with(".open.ipynb.cell-0.typ"));
+        assert_eq!(url_to_path(cell_uri("open", 0)), entry.to_path_buf());
+    }
+
+    #[test]
+    fn change_text_and_structure() {
+        let mut notebook = open("change");
+
+        // Edits the cell at the positions of the cell document.
+        let text_content = NotebookDocumentChangeTextContent {
+            document: VersionedTextDocumentIdentifier::new(cell_uri("change", 0), 1),
+            changes: vec![TextDocumentContentChangeEvent {
+                range: Some(lsp_types::Range::new(
+                    Position::new(0, 9),
+                    Position::new(0, 10),
+                )),
+                range_length: None,
+                text: "b + 1".to_owned(),
+            }],
+        };
+        let change = NotebookDocumentChangeEvent {
+            cells: Some(NotebookDocumentCellChange {
+                text_content: Some(vec![text_content]),
+                ..Default::default()
+            }),
+        };
+        let removes = notebook.apply_change(change, PositionEncoding::Utf16);
+        assert!(removes.unwrap().is_empty());
+        assert!(texts(&notebook)[1].1.ends_with("\n#let a = b + 1"));
+
+        // Moves the first cell to the end, and deletes the second cell.
+        let structure = NotebookDocumentCellChangeStructure {
+            array: NotebookCellArrayChange {
+                start: 0,
+                delete_count: 2,
+                cells: Some(vec![cell("change", 0, Some(3))]),
+            },
+            did_open: None,
+            did_close: None,
+        };
+        let change = NotebookDocumentChangeEvent {
+            cells: Some(NotebookDocumentCellChange {
+                structure: Some(structure),
+                ..Default::default()
+            }),
+        };
+        let removes = notebook.apply_change(change, PositionEncoding::Utf16);
+        let removes = removes.unwrap();
+        assert_eq!(removes.len(), 1);
+        assert!(removes[0].ends_with(".change.ipynb.cell-1.typ"));
+        assert_eq!(notebook.cells.len(), 2);
+        assert_eq!(texts(&notebook)[1].0, ".change.ipynb.cell-0.typ");
+    }
+
+    #[test]
+    fn close_unregisters_cells() {
+        let notebook = open("close");
+        let path = notebook.entry().unwrap();
+        assert_eq!(path_to_url(&path).unwrap(), cell_uri("close", 0));
+        drop(notebook);
+        assert_ne!(path_to_url(&path).unwrap(), cell_uri("close", 0));
+    }
+
+    #[test]
+    fn shift_hidden_lines() {
+        let uri = cell_uri("shift", 0);
+        let other = Url::parse("file:///nb/other.typ").unwrap();
+        let hidden = HiddenLines(Arc::new(HashMap::from_iter([(uri.clone(), 1)])));
+
+        let params = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            Position::new(0, 3),
+        );
+        let params = hidden.unhide(params);
+        assert_eq!(params.position, Position::new(1, 3));
+
+        let range = |line, character| {
+            lsp_types::Range::new(Position::new(line, character), Position::new(line, 4))
+        };
+        let link = |target_uri: &Url| LocationLink {
+            origin_selection_range: Some(range(1, 3)),
+            target_uri: target_uri.clone(),
+            target_range: range(2, 1),
+            target_selection_range: range(2, 1),
+        };
+        let resp = GotoDefinitionResponse::Link(vec![link(&uri), link(&other)]);
+        let resp = hidden.hide(1, CompilerQueryResponse::GotoDefinition(Some(resp)));
+        let resp: GotoDefinitionResponse = serde_json::from_value(resp).unwrap();
+        let GotoDefinitionResponse::Link(links) = resp else {
+            panic!("expected location links");
+        };
+        assert_eq!(links[0].origin_selection_range, Some(range(0, 3)));
+        assert_eq!(links[0].target_range, range(1, 1));
+        assert_eq!(links[1].origin_selection_range, Some(range(0, 3)));
+        assert_eq!(links[1].target_range, range(2, 1));
+
+        // The positions in the hidden lines are clamped to the start.
+        let mut diags = [Diagnostic::new_simple(
+            lsp_types::Range::new(Position::new(0, 5), Position::new(1, 2)),
+            "error".to_owned(),
+        )];
+        hidden.hide_diagnostics(&uri, &mut diags);
+        let expected = lsp_types::Range::new(Position::new(0, 0), Position::new(0, 2));
+        assert_eq!(diags[0].range, expected);
+
+        let token = |delta_line, delta_start| SemanticToken {
+            delta_line,
+            delta_start,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        };
+        let mut tokens = vec![token(0, 1), token(0, 2), token(2, 3), token(0, 2)];
+        hide_tokens(&mut tokens, 1);
+        assert_eq!(tokens, [token(1, 3), token(0, 2)]);
+    }
+}
@@ -23,23 +23,29 @@ use reflexo_typst::{diag::print_diagnostics, TypstDocument};
 use serde::{Deserialize, Serialize};
 pub use tinymist_project::*;
 
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{collections::VecDeque, num::NonZeroUsize, path::PathBuf, sync::Arc, time::SystemTime};
 
 use parking_lot::Mutex;
 use reflexo::hash::FxHashMap;
 use sync_ls::{LspClient, TypedLspClient};
 use tinymist_project::vfs::{FileChangeSet, MemoryEvent};
-use tinymist_query::analysis::{Analysis, LspQuerySnapshot, PeriscopeProvider};
+use tinymist_query::analysis::{
+    Analysis, DiskCache, DocumentVersions, LspQuerySnapshot, PeriscopeProvider,
+};
 use tinymist_query::{
     CheckRequest, CompilerQueryRequest, DiagnosticsMap, LocalContext, SemanticRequest,
 };
 use tinymist_render::PeriscopeRenderer;
 use tinymist_std::{error::prelude::*, ImmutPath};
 use tokio::sync::mpsc;
+use typst::diag::SourceDiagnostic;
+use typst::ecow::EcoVec;
+use typst::syntax::Source;
 use typst::{diag::FileResult, foundations::Bytes, layout::Position as TypstPosition};
 
 use super::ServerState;
 use crate::actor::editor::{EditorRequest, ProjVersion};
+use crate::lsp::progress::PackageProgress;
 use crate::stats::{CompilerQueryStats, QueryStatGuard};
 use crate::task::ExportUserConfig;
 use crate::{Config, ServerEvent};
@@ -56,6 +62,26 @@ impl ServerState {
         self.project.export.change_config(config);
     }
 
+    /// Downloads the uncached packages imported by the source in parallel,
+    /// before the compiler requests them one by one.
+    pub fn prefetch_packages(&self, source: &Source) {
+        let specs = tinymist_query::package::imported_packages(source);
+        if specs.is_empty() {
+            return;
+        }
+
+        let registry = self.project.compiler.primary.verse.registry.clone();
+        self.client.handle.spawn_blocking(move || {
+            registry.prepare_packages(&specs);
+        });
+    }
+
+    /// Gets the versions of the opened documents, which are consistent with
+    /// the sources of the next snapshot.
+    pub fn document_versions(&self) -> DocumentVersions {
+        self.memory_versions.clone()
+    }
+
     /// Snapshots the project for tasks
     pub fn snapshot(&mut self) -> Result<LspComputeGraph> {
         self.project.snapshot()
@@ -63,7 +89,9 @@ impl ServerState {
 
     /// Snapshots the project for language queries
     pub fn query_snapshot(&mut self) -> Result<LspQuerySnapshot> {
-        self.project.query_snapshot(None)
+        self.last_query = Some(std::time::Instant::now());
+        let versions = self.document_versions();
+        Ok(self.project.query_snapshot(None)?.with_versions(versions))
     }
 
     /// Snapshots the project for language queries
@@ -74,7 +102,13 @@ impl ServerState {
         let name: &'static str = q.into();
         let path = q.associated_path();
         let stat = self.project.stats.query_stat(path, name);
-        let snap = self.project.query_snapshot(Some(q))?;
+        self.last_query = Some(std::time::Instant::now());
+        let versions = self.document_versions();
+        let snap = self
+            .project
+            .query_snapshot(Some(q))?
+            .with_versions(versions);
+        self.analysis_history.record(snap.world());
         Ok((snap, stat))
     }
 
@@ -96,6 +130,7 @@ impl ServerState {
         let mut old_project = std::mem::replace(&mut self.project, new_project);
 
         // todo: the old dedicate projects should be transferred.
+        self.entries.running.clear();
 
         let snapshot = FileChangeSet::new_inserts(
             self.memory_changes
@@ -114,6 +149,7 @@ impl ServerState {
             old_project.stop();
         });
 
+        self.sync_entries();
         Ok(())
     }
 
@@ -123,10 +159,21 @@ impl ServerState {
         dedicate: &str,
         entry: Option<ImmutPath>,
     ) -> Result<ProjectInsId> {
-        let entry = self.config.entry_resolver.resolve(entry);
+        let resolver = match &entry {
+            Some(entry) => self.entry_resolver_of(entry),
+            None => self.entry_resolver(),
+        };
+        let entry = resolver.resolve(entry);
         self.project.restart_dedicate(dedicate, entry)
     }
 
+    /// Stops a dedicate project.
+    pub fn stop_dedicate(&mut self, dedicate: &str) {
+        self.project
+            .compiler
+            .remove_dedicate(&ProjectInsId(dedicate.into()));
+    }
+
     /// Create a fresh [`ProjectState`].
     pub fn project(
         config: &Config,
@@ -164,6 +211,10 @@ impl ServerState {
                     _ => tinymist_query::ColorTheme::Light,
                 },
                 lint: config.lint.when().clone(),
+                project_diagnostics: config.project_diagnostics,
+                index: config.index.clone(),
+                spell: config.spell.clone(),
+                reachable_lints: Arc::default(),
                 periscope: periscope_args.map(|args| {
                     let r = TypstPeriscopeProvider(PeriscopeRenderer::new(args));
                     Arc::new(r) as Arc<dyn PeriscopeProvider + Send + Sync>
@@ -171,12 +222,15 @@ impl ServerState {
                 tokens_caches: Arc::default(),
                 workers: Default::default(),
                 caches: Default::default(),
+                disk_cache: DiskCache::user(),
                 analysis_rev_cache: Arc::default(),
+                cache_limits: Arc::new(Mutex::new(config.cache)),
                 stats: Arc::default(),
             }),
 
             status_revision: Mutex::default(),
             notified_revision: Mutex::default(),
+            lock_file: Mutex::default(),
         });
 
         let export_target = config.export_target;
@@ -190,7 +244,11 @@ impl ServerState {
         log::info!("ServerState: creating ProjectState, entry: {entry:?}, inputs: {inputs:?}");
 
         let fonts = config.fonts();
-        let packages = LspUniverseBuilder::resolve_package(cert_path.clone(), Some(&package));
+        let mut packages = LspUniverseBuilder::resolve_package(cert_path.clone(), Some(&package));
+        if const_config.work_done_progress {
+            let progress = PackageProgress::new(client.clone().to_untyped());
+            packages = packages.with_notifier(Arc::new(Mutex::new(progress)));
+        }
         let verse =
             LspUniverseBuilder::build(entry, export_target, features, inputs, packages, fonts);
 
@@ -351,6 +409,21 @@ impl PeriscopeProvider for TypstPeriscopeProvider {
     }
 }
 
+/// The entry files registered to the server, each compiled independently.
+///
+/// The focused entry is compiled by the primary project, which serves the
+/// queries, and the others are compiled by the dedicate projects, which own
+/// their diagnostics.
+#[derive(Default)]
+pub(crate) struct ProjectEntries {
+    /// The registered entry files.
+    pub entries: Vec<ImmutPath>,
+    /// The dedicate projects compiling the unfocused entries.
+    pub running: FxHashMap<ImmutPath, String>,
+    /// The entries focused by the editor tabs, keyed by the files in the tabs.
+    pub by_tab: FxHashMap<ImmutPath, ImmutPath>,
+}
+
 #[derive(Default, Clone)]
 pub struct ProjectPreviewState {
     #[cfg(feature = "preview")]
@@ -402,6 +475,16 @@ pub struct CompileHandlerImpl {
 
     pub(crate) status_revision: Mutex<FxHashMap<ProjectInsId, usize>>,
     pub(crate) notified_revision: Mutex<FxHashMap<ProjectInsId, usize>>,
+    /// The lock file last read from the workspace root.
+    pub(crate) lock_file: Mutex<Option<CachedLockFile>>,
+}
+
+/// A lock file read from a workspace root, which is read again only if its
+/// modification time changes.
+pub(crate) struct CachedLockFile {
+    path: PathBuf,
+    mtime: SystemTime,
+    lock: Option<Arc<LockFile>>,
 }
 
 pub(crate) trait ProjectClient: Send + Sync + 'static {
@@ -469,10 +552,13 @@ impl CompileHandlerImpl {
             art.snap.signal
         );
 
-        if !should_lint {
-            let enc = self.analysis.position_encoding;
-            let diagnostics =
-                tinymist_query::convert_diagnostics(art.world(), art.diagnostics(), enc);
+        let enc = self.analysis.position_encoding;
+        let lock_diags = self.lock_diagnostics(art.world());
+
+        let project_wide = self.analysis.project_diagnostics;
+        if !should_lint && !project_wide {
+            let diags = art.diagnostics().chain(lock_diags.iter());
+            let diagnostics = tinymist_query::convert_diagnostics(art.world(), diags, enc);
 
             log::trace!("notify diagnostics({dv:?}): {diagnostics:#?}");
 
@@ -480,6 +566,7 @@ impl CompileHandlerImpl {
                 .send(EditorRequest::Diag(dv, Some(diagnostics)))
                 .log_error("failed to send diagnostics");
         } else {
+            let lock_diags = tinymist_query::convert_diagnostics(art.world(), &lock_diags, enc);
             let snap = art.clone();
             let editor_tx = self.editor_tx.clone();
             let analysis = self.analysis.clone();
@@ -488,9 +575,17 @@ impl CompileHandlerImpl {
                 let mut ctx = analysis.enter(world);
 
                 // todo: check all errors in this file
-                let Some(diagnostics) = CheckRequest { snap }.request(&mut ctx) else {
+                let req = CheckRequest {
+                    snap,
+                    lint: should_lint,
+                    project_wide,
+                };
+                let Some(mut diagnostics) = req.request(&mut ctx) else {
                     return;
                 };
+                for (url, diags) in lock_diags {
+                    diagnostics.entry(url).or_default().extend(diags);
+                }
 
                 log::trace!("notify diagnostics({dv:?}): {diagnostics:#?}");
 
@@ -500,6 +595,36 @@ impl CompileHandlerImpl {
             });
         }
     }
+
+    /// Checks the package imports against the lock file in the workspace
+    /// root, if any.
+    fn lock_diagnostics(&self, world: &LspWorld) -> EcoVec<SourceDiagnostic> {
+        let Some(root) = world.entry_state().workspace_root() else {
+            return EcoVec::new();
+        };
+        let path = root.join(LOCK_FILENAME);
+        let Ok(mtime) = std::fs::metadata(&path).and_then(|meta| meta.modified()) else {
+            return EcoVec::new();
+        };
+
+        let lock = {
+            let mut cached = self.lock_file.lock();
+            let fresh = cached
+                .as_ref()
+                .is_some_and(|cached| cached.path == path && cached.mtime == mtime);
+            if !fresh {
+                let lock = LockFile::read(&root)
+                    .inspect_err(|err| log::debug!("failed to read lock at {root:?}: {err:?}"))
+                    .ok()
+                    .map(Arc::new);
+                *cached = Some(CachedLockFile { path, mtime, lock });
+            }
+            cached.as_ref().and_then(|cached| cached.lock.clone())
+        };
+
+        lock.map(|lock| lock.check_imports(world))
+            .unwrap_or_default()
+    }
 }
 
 impl CompileHandler<LspCompilerFeat, ProjectInsStateExt> for CompileHandlerImpl {
@@ -653,6 +778,41 @@ impl CompileHandler<LspCompilerFeat, ProjectInsStateExt> for CompileHandlerImpl
 
 pub type QuerySnapWithStat = (LspQuerySnapshot, QueryStatGuard);
 
+/// The number of the recent query snapshots kept for debugging.
+const ANALYSIS_HISTORY_SIZE: usize = 8;
+
+/// The worlds of the recent query snapshots, which allow dumping the analysis
+/// of a past revision to reproduce a bug reported by a user.
+#[derive(Default)]
+pub(crate) struct AnalysisHistory(VecDeque<LspWorld>);
+
+impl AnalysisHistory {
+    /// Records the world of a query snapshot if its revision is new.
+    pub fn record(&mut self, world: &LspWorld) {
+        if (self.0.back()).is_some_and(|last| last.revision() == world.revision()) {
+            return;
+        }
+        if self.0.len() == ANALYSIS_HISTORY_SIZE {
+            self.0.pop_front();
+        }
+        self.0.push_back(world.clone());
+    }
+
+    /// Gets the world of a revision, or the latest world if the revision is
+    /// not specified.
+    pub fn get(&self, revision: Option<usize>) -> Option<&LspWorld> {
+        match revision {
+            Some(revision) => self.0.iter().find(|w| w.revision().get() == revision),
+            None => self.0.back(),
+        }
+    }
+
+    /// Gets the recorded revisions, from the oldest to the latest.
+    pub fn revisions(&self) -> Vec<usize> {
+        self.0.iter().map(|w| w.revision().get()).collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct DevExportEvent {
@@ -7,6 +7,7 @@ use lsp_types::request::ShowMessageRequest;
 use lsp_types::*;
 use reflexo::debug_loc::LspPosition;
 use sync_ls::*;
+use tinymist_query::analysis::DocumentVersions;
 use tinymist_query::{OnExportRequest, ServerInfoResponse};
 use tinymist_std::error::prelude::*;
 use tinymist_std::ImmutPath;
@@ -14,14 +15,20 @@ use tinymist_task::ProjectTask;
 use tokio::sync::mpsc;
 use typst::syntax::Source;
 
-use crate::actor::editor::{EditorActor, EditorRequest};
-use crate::lsp::query::OnEnter;
+use crate::actor::editor::{DiagnosticsStore, EditorActor, EditorRequest};
+use crate::lsp::query::{CompileSnippet, DebugAst, OnEnter, ResolvedOutline};
+use crate::notebook::{
+    DidChangeNotebookDocument, DidCloseNotebookDocument, DidOpenNotebookDocument,
+};
 use crate::project::{
     update_lock, CompiledArtifact, EntryResolver, LspComputeGraph, LspInterrupt, ProjectInsId,
     ProjectState, PROJECT_ROUTE_USER_ACTION_PRIORITY,
 };
 use crate::route::ProjectRouteState;
+use crate::stats::ServerStats;
 use crate::task::{ExportTask, FormatTask, ServerTraceTask, UserActionTask};
+use crate::timings::CompileTimings;
+use crate::tool::word_count::DocumentStatisticsRequest;
 use crate::world::TaskInputs;
 use crate::{lsp::init::*, *};
 
@@ -76,6 +83,8 @@ pub struct ServerState {
     pub pinning_by_browsing_preview: bool,
     /// The client focusing file.
     pub focusing: Option<ImmutPath>,
+    /// The entry files compiled besides the primary one.
+    pub(crate) entries: crate::project::ProjectEntries,
     /// The client focusing position.
     pub implicit_position: Option<LspPosition>,
     /// The client ever focused implicitly by activities.
@@ -86,12 +95,30 @@ pub struct ServerState {
     pub server_trace: Option<ServerTraceTask>,
 
     // Configurations
-    /// User configuration from the editor.
+    /// User configuration from the editor, scoped to the workspace folder of
+    /// the primary project.
     pub config: Config,
+    /// The configurations scoped to the workspace folders.
+    pub(crate) folders: crate::config::FolderConfigs,
     /// Source synchronized with client
     pub memory_changes: HashMap<Arc<Path>, Source>,
+    /// The versions of the sources synchronized with client.
+    pub memory_versions: DocumentVersions,
+    /// The notebooks synchronized with client.
+    pub(crate) notebooks: crate::notebook::NotebookState,
+    /// The worlds of the recent query snapshots.
+    pub(crate) analysis_history: crate::project::AnalysisHistory,
+    /// The workspace edits applied by the server, to be undone.
+    pub(crate) edit_journal: crate::lsp::edit::EditJournal,
+    /// The progresses of the running long operations.
+    pub(crate) progress: crate::lsp::progress::ProgressRegistry,
+    /// The time of the last language query, or `None` if the caches are
+    /// evicted since then.
+    pub(crate) last_query: Option<std::time::Instant>,
     /// The diagnostics sender to send diagnostics to `crate::actor::cluster`.
     pub editor_tx: mpsc::UnboundedSender<EditorRequest>,
+    /// The diagnostics to pull by the client.
+    pub diagnostics: DiagnosticsStore,
 }
 
 /// Getters and the main loop.
@@ -119,7 +146,14 @@ impl ServerState {
             route: ProjectRouteState::default(),
             project: handle,
             editor_tx,
+            diagnostics: DiagnosticsStore::default(),
             memory_changes: HashMap::new(),
+            memory_versions: DocumentVersions::new(config.const_config.document_changes),
+            notebooks: Default::default(),
+            analysis_history: Default::default(),
+            edit_journal: Default::default(),
+            progress: Default::default(),
+            last_query: None,
             #[cfg(feature = "preview")]
             preview: tool::preview::PreviewState::new(
                 &config,
@@ -133,12 +167,14 @@ impl ServerState {
             sema_tokens_registered: false,
             formatter_registered: false,
             server_trace: None,
+            folders: crate::config::FolderConfigs::new(config.clone()),
             config,
 
             pinning_by_user: false,
             pinning_by_preview: false,
             pinning_by_browsing_preview: false,
             focusing: None,
+            entries: Default::default(),
             implicit_position: None,
             formatter,
             user_action: UserActionTask,
@@ -155,6 +191,11 @@ impl ServerState {
         &self.config.entry_resolver
     }
 
+    /// Gets the entry resolver of the workspace folder containing a path.
+    pub fn entry_resolver_of(&self, path: &Path) -> &EntryResolver {
+        &self.folders.of_path(path).entry_resolver
+    }
+
     /// Whether the main file is pinning.
     pub fn is_pinning(&self) -> bool {
         self.pinning_by_user
@@ -182,6 +223,10 @@ impl ServerState {
                 client.clone().to_untyped(),
                 editor_rx,
                 server.config.notify_status,
+                server
+                    .const_config()
+                    .pull_diagnostics
+                    .then(|| server.diagnostics.clone()),
             );
 
             server
@@ -193,6 +238,19 @@ impl ServerState {
 
             // Run the cluster in the background after we referencing it
             client.handle.spawn(editor_actor.run());
+
+            // Check periodically whether the server is idle to evict the caches
+            let idle_client = client.clone().to_untyped();
+            client.handle.spawn(async move {
+                let mut ticks = tokio::time::interval(IDLE_CHECK_INTERVAL);
+                loop {
+                    ticks.tick().await;
+                    if idle_client.is_closed() {
+                        break;
+                    }
+                    idle_client.send_event(ServerEvent::EvictIdleCaches);
+                }
+            });
         }
 
         server
@@ -211,6 +269,7 @@ impl ServerState {
             // User commands
             .with_command("tinymist.startDefaultPreview", Self::default_preview)
             .with_command("tinymist.scrollPreview", Self::scroll_preview)
+            .with_command("tinymist.exportPreviewView", Self::export_preview_view)
             // Internal commands
             .with_command("tinymist.doStartPreview", Self::do_start_preview)
             .with_command("tinymist.doStartBrowsingPreview", Self::browse_preview)
@@ -251,29 +310,52 @@ impl ServerState {
             .with_request_::<References>(Self::references)
             .with_request_::<WorkspaceSymbolRequest>(Self::symbol)
             .with_request_::<OnEnter>(Self::on_enter)
+            .with_request_::<CompileSnippet>(Self::compile_snippet)
+            .with_request_::<ResolvedOutline>(Self::resolved_outline)
+            .with_request_::<DebugAst>(Self::debug_ast)
+            .with_request::<ServerStats>(Self::server_stats)
+            .with_request::<CompileTimings>(Self::compile_timings)
+            .with_request::<DocumentStatisticsRequest>(Self::document_statistics)
             .with_request_::<WillRenameFiles>(Self::will_rename_files)
+            .with_request::<DocumentDiagnosticRequest>(Self::document_diagnostic)
+            .with_request::<WorkspaceDiagnosticRequest>(Self::workspace_diagnostic)
             // notifications
             .with_notification::<Initialized>(Self::initialized)
             .with_notification::<DidOpenTextDocument>(Self::did_open)
             .with_notification::<DidCloseTextDocument>(Self::did_close)
             .with_notification::<DidChangeTextDocument>(Self::did_change)
             .with_notification::<DidSaveTextDocument>(Self::did_save)
+            .with_notification::<DidOpenNotebookDocument>(Self::did_open_notebook)
+            .with_notification::<DidChangeNotebookDocument>(Self::did_change_notebook)
+            .with_notification::<DidCloseNotebookDocument>(Self::did_close_notebook)
             .with_notification::<DidChangeConfiguration>(Self::did_change_configuration)
+            .with_notification::<DidChangeWorkspaceFolders>(Self::did_change_workspace_folders)
+            .with_notification::<DidChangeWatchedFiles>(Self::did_change_watched_files)
+            .with_notification::<WorkDoneProgressCancel>(Self::work_done_progress_cancel)
             // commands
             .with_command_("tinymist.exportPdf", Self::export_pdf)
             .with_command_("tinymist.exportSvg", Self::export_svg)
             // .with_command_("tinymist.exportSvgHtml", State::export_html)
             .with_command_("tinymist.exportPng", Self::export_png)
+            .with_command_("tinymist.exportDocx", Self::export_docx)
             .with_command_("tinymist.exportText", Self::export_text)
             .with_command_("tinymist.exportHtml", Self::export_html)
             .with_command_("tinymist.exportMarkdown", Self::export_markdown)
             .with_command_("tinymist.exportTeX", Self::export_tex)
             .with_command_("tinymist.exportQuery", Self::export_query)
+            .with_command_("tinymist.exportSelection", Self::export_selection)
             .with_command("tinymist.exportAnsiHighlight", Self::export_ansi_hl)
             .with_command("tinymist.exportAst", Self::export_ast)
+            .with_command("tinymist.pasteAsTypst", Self::paste_as_typst)
+            .with_command("tinymist.convertLatexMath", Self::convert_latex_math)
+            .with_command("tinymist.completeYamlBib", Self::complete_yaml_bib)
             .with_command("tinymist.doClearCache", Self::clear_cache)
+            .with_command("tinymist.doDumpAnalysis", Self::dump_analysis)
             .with_command("tinymist.pinMain", Self::pin_document)
             .with_command("tinymist.focusMain", Self::focus_document)
+            .with_command("tinymist.addEntry", Self::add_entry)
+            .with_command("tinymist.removeEntry", Self::remove_entry)
+            .with_command("tinymist.focusEntry", Self::focus_entry)
             .with_command("tinymist.doInitTemplate", Self::init_template)
             .with_command("tinymist.doGetTemplateEntry", Self::get_template_entry)
             .with_command_("tinymist.interactCodeContext", Self::interact_code_context)
@@ -281,7 +363,22 @@ impl ServerState {
             .with_command("tinymist.startServerProfiling", Self::start_server_trace)
             .with_command("tinymist.stopServerProfiling", Self::stop_server_trace)
             .with_command_("tinymist.getDocumentMetrics", Self::get_document_metrics)
+            .with_command_("tinymist.explainFontFallback", Self::explain_font_fallback)
             .with_command_("tinymist.getWorkspaceLabels", Self::get_workspace_labels)
+            .with_command_("tinymist.replaceInProse", Self::replace_in_prose)
+            .with_command_("tinymist.getIndexTerms", Self::get_index_terms)
+            .with_command_("tinymist.getChangeImpact", Self::get_change_impact)
+            .with_command("tinymist.runCoverage", Self::run_coverage)
+            .with_command_("tinymist.dropFiles", Self::drop_files)
+            .with_command_("tinymist.smartPaste", Self::smart_paste)
+            .with_command_("tinymist.getProjectAssets", Self::get_project_assets)
+            .with_command_("tinymist.moveAsset", Self::move_asset)
+            .with_command(
+                "tinymist.previewWorkspaceEdit",
+                Self::preview_workspace_edit,
+            )
+            .with_command("tinymist.applyWorkspaceEdit", Self::apply_workspace_edit)
+            .with_command("tinymist.undoWorkspaceEdit", Self::undo_workspace_edit)
             .with_command_("tinymist.getServerInfo", Self::get_server_info)
             // resources
             .with_resource("/fonts", Self::resource_fonts)
@@ -323,6 +420,10 @@ impl ServerState {
             .with_request::<request::Evaluate>(Self::evaluate_repl)
             .with_request::<request::Completions>(Self::complete_repl)
             .with_request::<request::Threads>(Self::debug_threads)
+            .with_request::<request::StackTrace>(Self::debug_stack_trace)
+            .with_request::<request::Scopes>(Self::debug_scopes)
+            .with_request::<request::Variables>(Self::debug_variables)
+            .with_request::<request::SetExceptionBreakpoints>(Self::set_exception_breakpoints)
     }
 
     /// Handles the project interrupts.
@@ -358,6 +459,9 @@ impl ServerState {
             ServerEvent::UnpinPrimaryByPreview => {
                 ready.set_pin_by_preview(false, false);
             }
+            ServerEvent::EvictIdleCaches => {
+                ready.evict_idle_caches();
+            }
         }
 
         Ok(())
@@ -388,6 +492,35 @@ impl ServerState {
 pub enum ServerEvent {
     /// Updates the `pinning_by_preview` status to false.
     UnpinPrimaryByPreview,
+    /// Evicts the caches if the server is idle for the configured interval.
+    EvictIdleCaches,
+}
+
+/// The interval to check whether the server is idle.
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// The maximum age of the memoized results kept by an idle eviction. It is the
+/// age kept after compiling the primary project, so the results still used by
+/// the live projects survive.
+const IDLE_MEMO_MAX_AGE: usize = 10;
+
+impl ServerState {
+    /// Evicts the stale memoized results, the rendered artifacts and the
+    /// analysis caches if no language query is made within the idle eviction
+    /// interval.
+    fn evict_idle_caches(&mut self) {
+        let Some(interval) = self.config.cache.idle_eviction_interval else {
+            return;
+        };
+        let interval = std::time::Duration::from_secs(interval);
+        if !self.last_query.is_some_and(|at| at.elapsed() >= interval) {
+            return;
+        }
+
+        log::info!("evicting caches after idle for {interval:?}");
+        self.last_query = None;
+        comemo::evict(IDLE_MEMO_MAX_AGE);
+        self.project.analysis.evict_idle();
+    }
 }
 
 impl ServerState {
@@ -1,11 +1,17 @@
 //! Statistics about the analyzers
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use parking_lot::Mutex;
 use reflexo::{hash::FxDashMap, path::unix_slash};
+use serde::{Deserialize, Serialize};
+use sync_ls::{internal_error, just_ok, SchedulableResponse};
+use tinymist_query::analysis::{InternerStats, QueryCacheStats};
+
+use crate::ServerState;
 
 #[derive(Clone)]
 pub(crate) struct QueryStatBucketData {
@@ -130,3 +136,61 @@ table.query-stats tr:nth-child(odd) { background-color: rgba(242, 242, 242, 0.8)
         html
     }
 }
+
+/// The `tinymist/serverStats` request, which reports the memory and cache
+/// usage of the server to diagnose runaway memory in long sessions.
+pub struct ServerStats;
+impl lsp_types::request::Request for ServerStats {
+    type Params = ();
+    type Result = ServerStatsResponse;
+    const METHOD: &'static str = "tinymist/serverStats";
+}
+
+/// The memory and cache usage of the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatsResponse {
+    /// The statistics of the interners.
+    pub interners: Vec<InternerStats>,
+    /// The number of the entries in each cache of the analysis.
+    pub analysis_caches: BTreeMap<String, usize>,
+    /// The hit rates of the cached queries.
+    pub query_caches: Vec<QueryCacheStats>,
+    /// The number of the documents synchronized with the client.
+    pub opened_documents: usize,
+    /// The number of the parsed sources in the cache.
+    pub cached_sources: usize,
+    /// The total length of the cached sources in bytes.
+    pub cached_source_bytes: usize,
+    /// The number of the available fonts.
+    pub fonts: usize,
+    /// The number of the fonts loaded into memory.
+    pub loaded_fonts: usize,
+    /// The total size of the loaded fonts in bytes.
+    pub loaded_font_bytes: usize,
+}
+
+impl ServerState {
+    /// Reports the memory and cache usage of the server.
+    pub(crate) fn server_stats(&mut self, _params: ()) -> SchedulableResponse<ServerStatsResponse> {
+        let snap = self.snapshot().map_err(internal_error)?;
+        let world = snap.world();
+        let analysis = &self.project.analysis;
+
+        let (cached_sources, cached_source_bytes) = world.vfs().source_cache_usage();
+        let loaded_fonts = world.font_resolver.loaded_fonts().collect::<Vec<_>>();
+        let loaded_font_bytes = loaded_fonts.iter().map(|(_, font)| font.data().len());
+
+        just_ok(ServerStatsResponse {
+            interners: analysis.interner_stats(),
+            analysis_caches: analysis.cache_sizes(),
+            query_caches: analysis.query_cache_stats(),
+            opened_documents: self.memory_changes.len(),
+            cached_sources,
+            cached_source_bytes,
+            fonts: world.font_resolver.len(),
+            loaded_font_bytes: loaded_font_bytes.sum(),
+            loaded_fonts: loaded_fonts.len(),
+        })
+    }
+}
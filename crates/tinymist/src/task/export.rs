@@ -1,27 +1,33 @@
 //! The actor that handles various document export, like PDF and SVG export.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, OnceLock};
 
 use reflexo::ImmutPath;
 use reflexo_typst::{Bytes, CompilationTask, ExportComputation};
-use tinymist_project::LspWorld;
+use tinymist_project::system::print_diagnostics_to_string;
+use tinymist_project::{DiagnosticFormat, LspWorld};
 use tinymist_std::error::prelude::*;
 use tinymist_std::fs::paths::write_atomic;
-use tinymist_std::typst::TypstDocument;
-use tinymist_task::{get_page_selection, ExportMarkdownTask, ExportTarget, PdfExport, TextExport};
+use tinymist_std::typst::{TypstDocument, TypstPagedDocument};
+use tinymist_task::{
+    anchor_headings, check_tagged_pdf, get_page_selection, get_selected_pages, has_page_number,
+    page_file_name, select_pages, ExportMarkdownTask, ExportTarget, PdfExport, TextExport,
+};
 use tokio::sync::mpsc;
 use typlite::{Format, Typlite};
 use typst::foundations::IntoValue;
+use typst::layout::Page;
 use typst::visualize::Color;
 
 use super::{FutureFolder, SyncTaskFactory};
 use crate::project::{
-    ApplyProjectTask, CompiledArtifact, DevEvent, DevExportEvent, EntryReader, ExportHtmlTask,
-    ExportPdfTask, ExportPngTask, ExportSvgTask, ExportTask as ProjectExportTask, ExportTeXTask,
-    ExportTextTask, LspCompiledArtifact, ProjectClient, ProjectTask, QueryTask, TaskWhen,
+    ApplyProjectTask, CompiledArtifact, DevEvent, DevExportEvent, EntryReader, ExportDocxTask,
+    ExportHtmlTask, ExportPdfTask, ExportPngTask, ExportSvgTask, ExportTask as ProjectExportTask,
+    ExportTeXTask, ExportTextTask, ExportTransform, LspCompiledArtifact, LspComputeGraph,
+    ProjectClient, ProjectTask, QueryTask, TaskWhen,
 };
 use crate::{actor::editor::EditorRequest, tool::word_count};
 
@@ -166,9 +172,6 @@ impl ExportTask {
         artifact: LspCompiledArtifact,
         lock_dir: Option<ImmutPath>,
     ) -> Result<Option<PathBuf>> {
-        use reflexo_vec2svg::DefaultExportFeature;
-        use ProjectTask::*;
-
         let CompiledArtifact { graph, doc, .. } = artifact;
 
         // Prepare the output path.
@@ -213,173 +216,356 @@ impl ExportTask {
 
         // Prepare the document.
         let doc = doc.context("cannot export with compilation errors")?;
+        let main_dir = entry
+            .main()
+            .and_then(|main| graph.world().path_for_id(main).ok())
+            .and_then(|path| path.as_path().parent().map(Path::to_owned));
+
+        // Exports one file per page if the task has a page template.
+        if let Some(template) = task.page_template().map(str::to_owned) {
+            let (kind2, tmpl) = (task.clone(), template.clone());
+            let (page_count, pages) =
+                FutureFolder::compute(move |_| Self::export_pages(&kind2, &tmpl, &graph, &doc))
+                    .await??;
+
+            let dir = write_to.parent().context("no parent")?.to_owned();
+            let paths = pages
+                .iter()
+                .map(|(number, _)| dir.join(page_file_name(&template, *number, page_count)))
+                .collect::<Vec<_>>();
+            let first = paths.first().cloned();
+            tokio::task::spawn_blocking(move || -> Result<()> {
+                for (path, (_, data)) in paths.iter().zip(pages) {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent).context("failed to create directory")?;
+                    }
+                    write_atomic(path, &data)?;
+                }
+                Ok(())
+            })
+            .await
+            .context_ut("failed to export")??;
+
+            log::debug!("ExportTask({export_id}): export complete");
+            return Ok(first);
+        }
 
         // Prepare data.
         let kind2 = task.clone();
-        let data = FutureFolder::compute(move |_| -> Result<Bytes> {
-            let doc = &doc;
-
-            // static BLANK: Lazy<Page> = Lazy::new(Page::default);
-            // todo: check warnings and errors inside
-            let html_once = OnceLock::new();
-            let html_doc = || -> Result<_> {
-                html_once
-                    .get_or_init(|| -> Result<_> {
-                        Ok(match &doc {
-                            TypstDocument::Html(html_doc) => html_doc.clone(),
-                            TypstDocument::Paged(_) => extra_compile_for_export(graph.world())?,
-                        })
-                    })
-                    .as_ref()
-                    .map_err(|e| e.clone())
-            };
-            let page_once = OnceLock::new();
-            let paged_doc = || {
-                page_once
-                    .get_or_init(|| -> Result<_> {
-                        Ok(match &doc {
-                            TypstDocument::Paged(paged_doc) => paged_doc.clone(),
-                            TypstDocument::Html(_) => extra_compile_for_export(graph.world())?,
-                        })
-                    })
-                    .as_ref()
-                    .map_err(|e| e.clone())
-            };
-            let first_page = || {
-                paged_doc()?
-                    .pages
-                    .first()
-                    .context("no first page to export")
-            };
-            Ok(match kind2 {
-                Preview(..) => Bytes::new([]),
-                // todo: more pdf flags
-                ExportPdf(config) => PdfExport::run(&graph, paged_doc()?, &config)?,
-                Query(QueryTask {
-                    export: _,
-                    output_extension: _,
-                    format,
-                    selector,
-                    field,
-                    one,
-                }) => {
-                    let pretty = false;
-                    let elements = reflexo_typst::query::retrieve(&graph.world(), &selector, doc)
-                        .map_err(|e| anyhow::anyhow!("failed to retrieve: {e}"))?;
-                    if one && elements.len() != 1 {
-                        bail!("expected exactly one element, found {}", elements.len());
-                    }
+        let data =
+            FutureFolder::compute(move |_| Self::export_bytes(kind2, &graph, &doc)).await??;
 
-                    let mapped: Vec<_> = elements
-                        .into_iter()
-                        .filter_map(|c| match &field {
-                            Some(field) => c.get_by_name(field).ok(),
-                            _ => Some(c.into_value()),
-                        })
-                        .collect();
-
-                    if one {
-                        let Some(value) = mapped.first() else {
-                            bail!("no such field found for element");
-                        };
-                        serialize(value, &format, pretty).map(Bytes::from_string)?
-                    } else {
-                        serialize(&mapped, &format, pretty).map(Bytes::from_string)?
-                    }
-                }
-                ExportHtml(ExportHtmlTask { export: _ }) => Bytes::from_string(
-                    typst_html::html(html_doc()?)
-                        .map_err(|e| format!("export error: {e:?}"))
-                        .context_ut("failed to export to html")?,
-                ),
-                ExportSvgHtml(ExportHtmlTask { export: _ }) => Bytes::from_string(
-                    reflexo_vec2svg::render_svg_html::<DefaultExportFeature>(paged_doc()?),
-                ),
-                ExportText(ExportTextTask { export: _ }) => {
-                    Bytes::from_string(TextExport::run_on_doc(doc)?)
-                }
-                ExportMd(ExportMarkdownTask {
-                    processor,
-                    assets_path,
-                    export: _,
-                }) => {
-                    let conv = Typlite::new(Arc::new(graph.world().clone()))
-                        .with_format(Format::Md)
-                        .with_feature(typlite::TypliteFeat {
-                            processor,
-                            assets_path,
-                            ..Default::default()
-                        })
-                        .convert()
-                        .map_err(|e| anyhow::anyhow!("failed to convert to markdown: {e}"))?;
-
-                    Bytes::from_string(conv)
+        let to = write_to.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            write_atomic(&to, &data)?;
+
+            if let ProjectTask::ExportHtml(html) = &task {
+                if let (Some(main_dir), Some(out_dir)) = (main_dir, to.parent()) {
+                    let html = std::str::from_utf8(&data).context_ut("html is not utf-8")?;
+                    copy_html_assets(html, &main_dir, out_dir)?;
                 }
-                // todo: duplicated code with ExportMd
-                ExportTeX(ExportTeXTask {
-                    processor,
-                    assets_path,
-                    export: _,
-                }) => {
-                    log::info!("ExportTask({export_id}): exporting to TeX with processor {processor:?} and assets path {assets_path:?}");
-                    let conv = Typlite::new(Arc::new(graph.world().clone()))
-                        .with_format(Format::LaTeX)
-                        .with_feature(typlite::TypliteFeat {
-                            processor,
-                            assets_path,
-                            ..Default::default()
-                        })
-                        .convert()
-                        .map_err(|e| anyhow::anyhow!("failed to convert to latex: {e}"))?;
-
-                    Bytes::from_string(conv)
+                if let Some(hook) = &html.post_export {
+                    run_post_export(hook, &to)?;
                 }
-                ExportSvg(ExportSvgTask { export }) => {
-                    let (is_first, merged_gap) = get_page_selection(&export)?;
+            }
 
-                    Bytes::from_string(if is_first {
-                        typst_svg::svg(first_page()?)
-                    } else {
-                        typst_svg::svg_merged(paged_doc()?, merged_gap)
-                    })
-                }
-                ExportPng(ExportPngTask { export, ppi, fill }) => {
+            Ok(())
+        })
+        .await
+        .context_ut("failed to export")??;
+
+        log::debug!("ExportTask({export_id}): export complete");
+        Ok(Some(write_to))
+    }
+
+    /// Renders the pages selected by a PNG or SVG task to one file per page.
+    ///
+    /// Returns the page count of the document, along with the one-indexed
+    /// numbers of the selected pages and their data.
+    fn export_pages(
+        task: &ProjectTask,
+        template: &str,
+        graph: &LspComputeGraph,
+        doc: &TypstDocument,
+    ) -> Result<(usize, Vec<(usize, Bytes)>)> {
+        let render = |page: &Page| -> Result<Bytes> {
+            match task {
+                ProjectTask::ExportPng(ExportPngTask { ppi, .. }) => {
                     let ppi = ppi.to_f32();
                     if ppi <= 1e-6 {
                         bail!("invalid ppi: {ppi}");
                     }
+                    let data = typst_render::render(page, ppi / 72.)
+                        .encode_png()
+                        .map_err(|err| anyhow::anyhow!("failed to encode PNG ({err})"))?;
+                    Ok(Bytes::new(data))
+                }
+                ProjectTask::ExportSvg(..) => Ok(Bytes::from_string(typst_svg::svg(page))),
+                _ => bail!("cannot export {} one file per page", task.extension()),
+            }
+        };
 
-                    let fill = if let Some(fill) = fill {
-                        parse_color(fill).map_err(|err| anyhow::anyhow!("invalid fill ({err})"))?
-                    } else {
-                        Color::WHITE
-                    };
+        let doc = match doc {
+            TypstDocument::Paged(paged_doc) => paged_doc.clone(),
+            TypstDocument::Html(_) => extra_compile_for_export(graph.world())?,
+        };
+        let page_count = doc.pages.len();
+        let export = task.as_export().context("no export configuration")?;
+        let selected = get_selected_pages(export, page_count);
+        if selected.len() > 1 && !has_page_number(template) {
+            bail!(
+                "cannot export multiple pages to {template:?} without a page number template \
+                 ({{p}} or {{0p}})"
+            );
+        }
 
-                    let (is_first, merged_gap) = get_page_selection(&export)?;
+        let pages = selected
+            .into_iter()
+            .map(|idx| Ok((idx + 1, render(&doc.pages[idx])?)))
+            .collect::<Result<Vec<_>>>()?;
 
-                    let pixmap = if is_first {
-                        typst_render::render(first_page()?, ppi / 72.)
-                    } else {
-                        typst_render::render_merged(paged_doc()?, ppi / 72., merged_gap, Some(fill))
-                    };
+        Ok((page_count, pages))
+    }
+
+    /// Exports a document to PDF, reporting the features disallowed by the PDF
+    /// standards, e.g. the attachments in PDF/A-2b, at their locations.
+    fn export_pdf(
+        graph: &LspComputeGraph,
+        doc: &Arc<TypstPagedDocument>,
+        config: &ExportPdfTask,
+    ) -> Result<Bytes> {
+        let err = match PdfExport::run(graph, doc, config) {
+            Err(err) if !config.pdf_standards.is_empty() => err,
+            res => return res,
+        };
 
-                    Bytes::new(
-                        pixmap
-                            .encode_png()
-                            .map_err(|err| anyhow::anyhow!("failed to encode PNG ({err})"))?,
-                    )
+        let diags = err.diagnostics().unwrap_or_default();
+        let format = DiagnosticFormat::Human;
+        match print_diagnostics_to_string(graph.world(), diags.iter(), format) {
+            Ok(msg) if !diags.is_empty() => {
+                let standards = config.pdf_standards.iter().map(ToString::to_string);
+                let standards = standards.collect::<Vec<_>>().join(", ");
+                bail!("document does not conform to the PDF standards {standards}\n{msg}")
+            }
+            _ => Err(err),
+        }
+    }
+
+    /// Exports a compiled document to the data of a task in memory.
+    pub fn export_bytes(
+        task: ProjectTask,
+        graph: &LspComputeGraph,
+        doc: &TypstDocument,
+    ) -> Result<Bytes> {
+        use reflexo_vec2svg::DefaultExportFeature;
+        use ProjectTask::*;
+
+        // static BLANK: Lazy<Page> = Lazy::new(Page::default);
+        // todo: check warnings and errors inside
+        let html_once = OnceLock::new();
+        let html_doc = || -> Result<_> {
+            html_once
+                .get_or_init(|| -> Result<_> {
+                    Ok(match &doc {
+                        TypstDocument::Html(html_doc) => html_doc.clone(),
+                        TypstDocument::Paged(_) => extra_compile_for_export(graph.world())?,
+                    })
+                })
+                .as_ref()
+                .map_err(|e| e.clone())
+        };
+        let page_once = OnceLock::new();
+        let paged_doc = || {
+            page_once
+                .get_or_init(|| -> Result<_> {
+                    Ok(match &doc {
+                        TypstDocument::Paged(paged_doc) => paged_doc.clone(),
+                        TypstDocument::Html(_) => extra_compile_for_export(graph.world())?,
+                    })
+                })
+                .as_ref()
+                .map_err(|e| e.clone())
+        };
+        let first_page = || {
+            paged_doc()?
+                .pages
+                .first()
+                .context("no first page to export")
+        };
+        Ok(match task {
+            Preview(..) => Bytes::new([]),
+            // todo: more pdf flags
+            ExportPdf(config) => {
+                let doc = paged_doc()?;
+                if config.tagged {
+                    let warnings = check_tagged_pdf(doc);
+                    if !warnings.is_empty() {
+                        let world = graph.world();
+                        let format = DiagnosticFormat::Human;
+                        match print_diagnostics_to_string(world, warnings.iter(), format) {
+                            Ok(msg) => log::warn!("ExportTask: inaccessible tagged PDF\n{msg}"),
+                            Err(err) => log::warn!("ExportTask: {err}"),
+                        }
+                    }
+                }
+                Self::export_pdf(graph, doc, &config)?
+            }
+            Query(QueryTask {
+                export,
+                output_extension: _,
+                format,
+                selector,
+                field,
+                one,
+            }) => {
+                let pretty = export
+                    .transform
+                    .iter()
+                    .any(|t| matches!(t, ExportTransform::Pretty { .. }));
+                let elements = reflexo_typst::query::retrieve(&graph.world(), &selector, doc)
+                    .map_err(|e| anyhow::anyhow!("failed to retrieve: {e}"))?;
+                if one && elements.len() != 1 {
+                    bail!("expected exactly one element, found {}", elements.len());
                 }
-            })
-        })
-        .await??;
 
-        let to = write_to.clone();
-        tokio::task::spawn_blocking(move || write_atomic(to, data))
-            .await
-            .context_ut("failed to export")??;
+                let mapped: Vec<_> = elements
+                    .into_iter()
+                    .filter_map(|c| match &field {
+                        Some(field) => c.get_by_name(field).ok(),
+                        _ => Some(c.into_value()),
+                    })
+                    .collect();
 
-        log::debug!("ExportTask({export_id}): export complete");
-        Ok(Some(write_to))
+                if one {
+                    let Some(value) = mapped.first() else {
+                        bail!("no such field found for element");
+                    };
+                    serialize(value, &format, pretty).map(Bytes::from_string)?
+                } else {
+                    serialize(&mapped, &format, pretty).map(Bytes::from_string)?
+                }
+            }
+            ExportHtml(ExportHtmlTask {
+                heading_anchors, ..
+            }) => {
+                let (html, warnings) = anchor_headings(html_doc()?, heading_anchors);
+                if !warnings.is_empty() {
+                    let world = graph.world();
+                    let format = DiagnosticFormat::Human;
+                    match print_diagnostics_to_string(world, warnings.iter(), format) {
+                        Ok(msg) => log::warn!("ExportTask: broken links in HTML\n{msg}"),
+                        Err(err) => log::warn!("ExportTask: {err}"),
+                    }
+                }
+
+                Bytes::from_string(
+                    typst_html::html(&html)
+                        .map_err(|e| format!("export error: {e:?}"))
+                        .context_ut("failed to export to html")?,
+                )
+            }
+            ExportSvgHtml(ExportHtmlTask { .. }) => Bytes::from_string(
+                reflexo_vec2svg::render_svg_html::<DefaultExportFeature>(paged_doc()?),
+            ),
+            ExportText(ExportTextTask { export: _ }) => {
+                Bytes::from_string(TextExport::run_on_doc(doc)?)
+            }
+            ExportMd(ExportMarkdownTask {
+                processor,
+                assets_path,
+                math,
+                figure,
+                raw,
+                export: _,
+            }) => {
+                let conv = Typlite::new(Arc::new(graph.world().clone()))
+                    .with_format(Format::Md)
+                    .with_feature(typlite::TypliteFeat {
+                        processor,
+                        assets_path,
+                        math,
+                        figure,
+                        raw,
+                        ..Default::default()
+                    })
+                    .convert()
+                    .map_err(|e| anyhow::anyhow!("failed to convert to markdown: {e}"))?;
+
+                Bytes::from_string(conv)
+            }
+            // todo: duplicated code with ExportMd
+            ExportTeX(ExportTeXTask {
+                processor,
+                assets_path,
+                export: _,
+            }) => {
+                log::info!("ExportTask: exporting to TeX with processor {processor:?} and assets path {assets_path:?}");
+                let conv = Typlite::new(Arc::new(graph.world().clone()))
+                    .with_format(Format::LaTeX)
+                    .with_feature(typlite::TypliteFeat {
+                        processor,
+                        assets_path,
+                        ..Default::default()
+                    })
+                    .convert()
+                    .map_err(|e| anyhow::anyhow!("failed to convert to latex: {e}"))?;
+
+                Bytes::from_string(conv)
+            }
+            ExportDocx(ExportDocxTask {
+                processor,
+                export: _,
+            }) => {
+                let conv = Typlite::new(Arc::new(graph.world().clone()))
+                    .with_format(Format::Docx)
+                    .with_feature(typlite::TypliteFeat {
+                        processor,
+                        ..Default::default()
+                    })
+                    .to_docx()
+                    .map_err(|e| anyhow::anyhow!("failed to convert to docx: {e}"))?;
+
+                Bytes::new(conv)
+            }
+            ExportSvg(ExportSvgTask { export, .. }) => {
+                let (is_first, merged_gap) = get_page_selection(&export)?;
+
+                Bytes::from_string(if is_first {
+                    typst_svg::svg(first_page()?)
+                } else {
+                    typst_svg::svg_merged(&select_pages(&export, paged_doc()?), merged_gap)
+                })
+            }
+            ExportPng(ExportPngTask {
+                export, ppi, fill, ..
+            }) => {
+                let ppi = ppi.to_f32();
+                if ppi <= 1e-6 {
+                    bail!("invalid ppi: {ppi}");
+                }
+
+                let fill = if let Some(fill) = fill {
+                    parse_color(fill).map_err(|err| anyhow::anyhow!("invalid fill ({err})"))?
+                } else {
+                    Color::WHITE
+                };
+
+                let (is_first, merged_gap) = get_page_selection(&export)?;
+
+                let pixmap = if is_first {
+                    typst_render::render(first_page()?, ppi / 72.)
+                } else {
+                    let doc = select_pages(&export, paged_doc()?);
+                    typst_render::render_merged(&doc, ppi / 72., merged_gap, Some(fill))
+                };
+
+                Bytes::new(
+                    pixmap
+                        .encode_png()
+                        .map_err(|err| anyhow::anyhow!("failed to encode PNG ({err})"))?,
+                )
+            }
+        })
     }
 }
 
@@ -406,6 +592,8 @@ impl Default for ExportUserConfig {
                 },
                 pdf_standards: vec![],
                 creation_timestamp: None,
+                image_max_dpi: None,
+                tagged: false,
             }),
             count_words: false,
             development: false,
@@ -427,6 +615,95 @@ fn parse_color(fill: String) -> Result<Color> {
     }
 }
 
+/// Copies the local files referenced by the `src` and `href` attributes of an
+/// HTML document from the directory of the main file to the output directory,
+/// so that the exported site is self-contained.
+fn copy_html_assets(html: &str, main_dir: &Path, out_dir: &Path) -> Result<()> {
+    let out_dir = out_dir.canonicalize().context("output directory")?;
+    let Ok(main_dir) = main_dir.canonicalize() else {
+        return Ok(());
+    };
+    if main_dir == out_dir {
+        return Ok(());
+    }
+
+    for link in html_asset_links(html) {
+        let src = main_dir.join(link);
+        if !src.is_file() {
+            continue;
+        }
+
+        let dst = out_dir.join(link);
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent).context("failed to create asset directory")?;
+        }
+        std::fs::copy(&src, &dst).context("failed to copy asset")?;
+        log::debug!("ExportTask: copied asset {src:?} to {dst:?}");
+    }
+
+    Ok(())
+}
+
+/// Collects the relative links in the `src` and `href` attributes of an HTML
+/// document, without the queries and fragments. The links escaping the
+/// directory are skipped.
+fn html_asset_links(html: &str) -> Vec<&str> {
+    let mut links = vec![];
+    for attr in [" src=\"", " href=\""] {
+        let mut rest = html;
+        while let Some(start) = rest.find(attr) {
+            rest = &rest[start + attr.len()..];
+            let Some(end) = rest.find('"') else {
+                break;
+            };
+            let link = rest[..end].split(['?', '#']).next().unwrap_or_default();
+
+            let is_relative = !link.is_empty() && !link.starts_with('/') && !link.contains(':');
+            if is_relative && !link.split(['/', '\\']).any(|seg| seg == "..") {
+                links.push(link);
+            }
+        }
+    }
+
+    links.sort();
+    links.dedup();
+    links
+}
+
+/// Runs the shell command after exporting a document.
+fn run_post_export(hook: &str, output: &Path) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(hook).env("TINYMIST_OUTPUT", output);
+    if let Some(dir) = output.parent() {
+        cmd.current_dir(dir);
+    }
+
+    // The output is captured, since the standard output may be used by the
+    // language server.
+    let res = cmd.output().context("failed to run post-export hook")?;
+    log::info!(
+        "ExportTask: post-export hook {hook:?} printed: {}",
+        String::from_utf8_lossy(&res.stdout)
+    );
+    if !res.status.success() {
+        bail!(
+            "post-export hook {hook:?} failed with {}: {}",
+            res.status,
+            String::from_utf8_lossy(&res.stderr)
+        );
+    }
+
+    Ok(())
+}
+
 fn log_err<T>(artifact: Result<T>) -> Option<T> {
     match artifact {
         Ok(v) => Some(v),
@@ -449,12 +726,66 @@ fn extra_compile_for_export<D: typst::Document + Send + Sync + 'static>(
     }
 }
 
+/// Serializes the values to CSV, with one row per value. The columns are the
+/// fields of the objects, along with a `value` column for the other values.
+fn to_csv(value: serde_json::Value) -> String {
+    use serde_json::Value;
+
+    let rows = match value {
+        Value::Array(rows) => rows,
+        value => vec![value],
+    };
+    let mut columns = vec![];
+    for row in &rows {
+        if let Value::Object(fields) = row {
+            for key in fields.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+    }
+    if rows.iter().any(|row| !row.is_object()) && !columns.iter().any(|c| c == "value") {
+        columns.push("value".to_owned());
+    }
+
+    let cell = |value: Option<&Value>| {
+        let text = match value {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+        };
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text
+        }
+    };
+
+    let header = columns
+        .iter()
+        .map(|c| cell(Some(&Value::String(c.clone()))));
+    let mut out = header.collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for row in &rows {
+        let cells = columns.iter().map(|column| match row {
+            Value::Object(fields) => cell(fields.get(column)),
+            value if column == "value" => cell(Some(value)),
+            _ => String::new(),
+        });
+        out.push_str(&cells.collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
 /// Serialize data to the output format.
 fn serialize(data: &impl serde::Serialize, format: &str, pretty: bool) -> Result<String> {
     Ok(match format {
         "json" if pretty => serde_json::to_string_pretty(data).context("serialize to json")?,
         "json" => serde_json::to_string(data).context("serialize to json")?,
         "yaml" => serde_yaml::to_string(&data).context_ut("serialize to yaml")?,
+        "csv" => to_csv(serde_json::to_value(data).context("serialize to json value")?),
         "txt" => {
             use serde_json::Value::*;
             let value = serde_json::to_value(data).context("serialize to json value")?;
@@ -493,6 +824,32 @@ mod tests {
         assert_eq!(conf.task.when(), Some(&TaskWhen::Never));
     }
 
+    #[test]
+    fn test_html_asset_links() {
+        let html = concat!(
+            r#"<img src="img/a.png"><link href="style.css?v=1">"#,
+            r##"<a href="https://typst.app">typst</a><a href="#top">top</a>"##,
+            r#"<img src="../b.png"><img src="img/a.png?v=2">"#,
+        );
+        assert_eq!(html_asset_links(html), vec!["img/a.png", "style.css"]);
+    }
+
+    #[test]
+    fn test_query_csv() {
+        let value = serde_json::json!([
+            { "func": "metadata", "value": "a, b" },
+            { "func": "metadata", "value": 1, "label": "<x>" },
+        ]);
+        assert_eq!(
+            to_csv(value),
+            "func,value,label\nmetadata,\"a, b\",\nmetadata,1,<x>\n"
+        );
+        assert_eq!(
+            to_csv(serde_json::json!("say \"hi\"")),
+            "value\n\"say \"\"hi\"\"\"\n"
+        );
+    }
+
     #[test]
     fn test_parse_color() {
         assert_eq!(parse_color("black".to_owned()).unwrap(), Color::BLACK);
@@ -172,6 +172,21 @@ impl WorldComputable<LspCompilerFeat> for ProjectExport {
 
                     Ok(TypliteTeXExport::run(graph)?.map(Bytes::from_string))
                 }
+                ExportDocx(..) => {
+                    let doc = graph.compute::<OptionDocumentTask<TypstPagedDocument>>()?;
+                    let doc = doc.as_ref();
+                    let n =
+                        ExportTimings::needs_run(&graph.snap, when, doc.as_deref()).unwrap_or(true);
+                    if !n {
+                        return Ok(None);
+                    }
+
+                    let conv = Typlite::new(Arc::new(graph.snap.world.clone()))
+                        .with_format(Format::Docx)
+                        .to_docx()
+                        .map_err(|e| anyhow::anyhow!("failed to convert to DOCX: {e}"))?;
+                    Ok(Some(Bytes::new(conv)))
+                }
                 ExportText(config) => Self::export_string::<_, TextExport>(graph, when, config),
                 Query(..) => todo!(),
             }
@@ -16,6 +16,23 @@ pub enum FormatterConfig {
     Disable,
 }
 
+impl FormatterConfig {
+    /// Formats a source file, returning `None` if the formatter is disabled or
+    /// fails to format the file, e.g. because of syntax errors.
+    pub fn format(&self, src: Source) -> Option<String> {
+        match self {
+            FormatterConfig::Typstyle(config) => {
+                typstyle_core::Typstyle::new(config.as_ref().clone())
+                    .format_source(src)
+                    .render()
+                    .ok()
+            }
+            FormatterConfig::Typstfmt(config) => Some(typstfmt::format(src.text(), **config)),
+            FormatterConfig::Disable => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FormatUserConfig {
     pub config: FormatterConfig,
@@ -41,17 +58,7 @@ impl FormatTask {
     pub fn run(&self, src: Source) -> SchedulableResponse<Option<Vec<TextEdit>>> {
         let c = self.factory.task();
         just_future(async move {
-            let formatted = match &c.config {
-                FormatterConfig::Typstyle(config) => {
-                    typstyle_core::Typstyle::new(config.as_ref().clone())
-                        .format_source(src.clone())
-                        .render()
-                        .ok()
-                }
-                FormatterConfig::Typstfmt(config) => Some(typstfmt::format(src.text(), **config)),
-                FormatterConfig::Disable => None,
-            };
-
+            let formatted = c.config.format(src.clone());
             Ok(formatted.and_then(|formatted| calc_diff(src, formatted, c.position_encoding)))
         })
     }
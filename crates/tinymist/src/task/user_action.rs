@@ -91,7 +91,7 @@ impl UserActionTask {
         let resp = just_future(async move {
             let static_file_addr = "127.0.0.1:0".to_owned();
             tokio::spawn(async move {
-                make_http_server(timings, static_file_addr, addr_tx).await;
+                make_http_server(timings, static_file_addr, None, addr_tx).await;
                 stop_tx2.send(()).ok();
             });
 
@@ -269,7 +269,7 @@ async fn trace_main(
             let t = tokio::spawn(async move {
                 let static_file_addr = "127.0.0.1:0".to_owned();
                 let timings = async { Bytes::from_owner(timings) };
-                make_http_server(timings, static_file_addr, addr_tx).await;
+                make_http_server(timings, static_file_addr, None, addr_tx).await;
                 std::process::exit(0);
             });
 
@@ -468,7 +468,7 @@ async fn make_http_server(
 }
 
 /// Turns a span into a (file, line) pair.
-fn resolve_span(world: &LspWorld, span: Span) -> Option<(String, u32)> {
+pub(crate) fn resolve_span(world: &LspWorld, span: Span) -> Option<(String, u32)> {
     let id = span.id()?;
     let source = world.source(id).ok()?;
     let range = source.range(span)?;
@@ -0,0 +1,353 @@
+//! Timing reports of the compilations
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sync_ls::{internal_error, just_future, SchedulableResponse};
+use tinymist_project::LspWorld;
+use tinymist_std::error::prelude::*;
+use tinymist_std::typst::TypstPagedDocument;
+use typst::syntax::Span;
+use typst::World;
+
+use crate::task::resolve_span;
+use crate::world::TaskInputs;
+use crate::ServerState;
+
+/// The name of the scope enclosing a timed compilation, which tells the
+/// events of the compilation apart from the events recorded before.
+const ROOT_SCOPE: &str = "compile_timings";
+
+/// The `tinymist/compileTimings` request, which compiles a document with the
+/// timings enabled and reports where the time is spent.
+pub struct CompileTimings;
+impl lsp_types::request::Request for CompileTimings {
+    type Params = CompileTimingsParams;
+    type Result = TimingReport;
+    const METHOD: &'static str = "tinymist/compileTimings";
+}
+
+/// The parameters of the `tinymist/compileTimings` request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileTimingsParams {
+    /// The path of the document to compile, defaulting to the main document.
+    pub path: Option<PathBuf>,
+    /// The path to write the Chrome trace of the compilation to, which can be
+    /// viewed as a flamegraph in `chrome://tracing` or Perfetto.
+    pub trace_output: Option<PathBuf>,
+}
+
+/// The timings of a compilation, in milliseconds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingReport {
+    /// The time spent on parsing the source files read by the compilation.
+    pub parse: f64,
+    /// The time spent on evaluating the modules.
+    pub eval: f64,
+    /// The time spent on the rest of the compilation, i.e. the realization,
+    /// the layout and the introspection iterations.
+    pub layout: f64,
+    /// The time spent on exporting the document.
+    pub export: Option<f64>,
+    /// The time spent on the compilation and the export.
+    pub total: f64,
+    /// The layout timings of the pages, in the order of layout.
+    pub pages: Vec<f64>,
+    /// The total timings of the scopes recorded by the compiler, from the
+    /// slowest to the fastest.
+    pub scopes: Vec<ScopeTiming>,
+}
+
+/// The total timing of a scope recorded by the compiler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeTiming {
+    /// The name of the scope, e.g. `eval`.
+    pub name: String,
+    /// The number of the outermost occurrences of the scope.
+    pub count: usize,
+    /// The total time spent in the outermost occurrences of the scope.
+    pub time: f64,
+}
+
+/// Records the timings of a compilation and its export.
+pub struct CompileTimer {
+    start: Instant,
+    compile: Duration,
+    export: Option<Duration>,
+    scope: Option<typst_timing::TimingScope>,
+}
+
+impl CompileTimer {
+    /// Enables the timings and starts timing the compilation.
+    pub fn start() -> Self {
+        typst_timing::enable();
+        Self {
+            start: Instant::now(),
+            compile: Duration::ZERO,
+            export: None,
+            scope: typst_timing::TimingScope::new(ROOT_SCOPE),
+        }
+    }
+
+    /// Marks the end of the compilation.
+    pub fn compiled(&mut self) {
+        self.compile = self.start.elapsed();
+    }
+
+    /// Records the time spent on the export.
+    pub fn exported(&mut self, duration: Duration) {
+        self.export = Some(duration);
+    }
+
+    /// Disables the timings and reports them, writing the Chrome trace of the
+    /// compilation to the path if given.
+    pub fn finish(mut self, world: &LspWorld, trace_output: Option<&Path>) -> Result<TimingReport> {
+        drop(self.scope.take());
+        typst_timing::disable();
+
+        let mut writer = std::io::BufWriter::new(Vec::new());
+        let res = typst_timing::export_json(&mut writer, |span| {
+            resolve_span(world, Span::from_raw(span)).unwrap_or_else(|| ("unknown".to_string(), 0))
+        });
+        if let Err(err) = res {
+            bail!("failed to export the timings: {err:?}");
+        }
+        let trace = writer.into_inner().context("export the timings")?;
+        let trace = serde_json::from_slice::<JsonValue>(&trace).context("parse the timings")?;
+        let events = compilation_events(trace_events(&trace));
+
+        if let Some(path) = trace_output {
+            let trace = serde_json::to_vec(&events).context("serialize the timings")?;
+            std::fs::write(path, trace).context("write the timings")?;
+        }
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        let (scopes, pages) = aggregate_events(&events);
+        let eval = scopes.get("eval").map_or(0., |(_, time)| *time);
+        let mut scopes = scopes
+            .into_iter()
+            .filter(|(name, _)| name != ROOT_SCOPE)
+            .map(|(name, (count, time))| ScopeTiming { name, count, time })
+            .collect::<Vec<_>>();
+        scopes.sort_by(|a, b| b.time.total_cmp(&a.time));
+
+        Ok(TimingReport {
+            parse: ms(parse_time(world)),
+            eval,
+            layout: (ms(self.compile) - eval).max(0.),
+            export: self.export.map(ms),
+            total: ms(self.compile + self.export.unwrap_or_default()),
+            pages,
+            scopes,
+        })
+    }
+}
+
+impl TimingReport {
+    /// Prints the report to the stderr.
+    pub fn print(&self) {
+        eprintln!("timings:");
+        eprintln!("  parse   {:>10.2} ms", self.parse);
+        eprintln!("  eval    {:>10.2} ms", self.eval);
+        eprintln!("  layout  {:>10.2} ms", self.layout);
+        for (idx, time) in self.pages.iter().enumerate() {
+            eprintln!("    page {:<4}{time:>8.2} ms", idx + 1);
+        }
+        if let Some(export) = self.export {
+            eprintln!("  export  {export:>10.2} ms");
+        }
+        eprintln!("  total   {:>10.2} ms", self.total);
+
+        eprintln!("slowest scopes:");
+        for scope in self.scopes.iter().take(10) {
+            let count = format!("({}x)", scope.count);
+            eprintln!("  {:<32} {count:>8} {:>10.2} ms", scope.name, scope.time);
+        }
+    }
+}
+
+/// Measures the time to parse the source files read by the compilation, which
+/// are parsed once by the compilation.
+fn parse_time(world: &LspWorld) -> Duration {
+    let mut total = Duration::ZERO;
+    for fid in world.depended_files() {
+        let is_source = fid.vpath().as_rooted_path().extension() == Some("typ".as_ref());
+        let Some(source) = is_source.then(|| world.source(fid).ok()).flatten() else {
+            continue;
+        };
+
+        let start = Instant::now();
+        std::hint::black_box(typst::syntax::parse(source.text()));
+        total += start.elapsed();
+    }
+    total
+}
+
+/// Gets the events of a Chrome trace, which is either an array of events or
+/// an object with the `traceEvents` field.
+fn trace_events(trace: &JsonValue) -> &[JsonValue] {
+    let events = match trace {
+        JsonValue::Object(trace) => trace.get("traceEvents"),
+        trace => Some(trace),
+    };
+    events
+        .and_then(JsonValue::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn event_ts(event: &JsonValue) -> f64 {
+    event
+        .get("ts")
+        .and_then(JsonValue::as_f64)
+        .unwrap_or_default()
+}
+
+/// Keeps the events within the last root scope, which may be recorded on the
+/// other threads.
+fn compilation_events(events: &[JsonValue]) -> Vec<JsonValue> {
+    let is_root = |event: &JsonValue, ph: &str| {
+        event.get("name").and_then(JsonValue::as_str) == Some(ROOT_SCOPE)
+            && event.get("ph").and_then(JsonValue::as_str) == Some(ph)
+    };
+    let Some(start) = events.iter().rposition(|event| is_root(event, "B")) else {
+        return events.to_vec();
+    };
+    let end = events
+        .iter()
+        .skip(start)
+        .find(|event| is_root(event, "E"))
+        .map_or(f64::INFINITY, event_ts);
+    let start = event_ts(&events[start]);
+
+    events
+        .iter()
+        .filter(|event| (start..=end).contains(&event_ts(event)))
+        .cloned()
+        .collect()
+}
+
+type ScopeTimings = BTreeMap<String, (usize, f64)>;
+
+/// Sums the durations of the outermost occurrences of the scopes, and gets
+/// the durations of the outermost page scopes, in milliseconds.
+fn aggregate_events(events: &[JsonValue]) -> (ScopeTimings, Vec<f64>) {
+    let is_page = |name: &str| name.contains("page") && !name.contains("document");
+
+    let mut scopes = ScopeTimings::new();
+    let mut pages = vec![];
+    let mut stacks = BTreeMap::<String, Vec<(&str, f64)>>::new();
+    let mut record = |stack: &[(&str, f64)], name: &str, start: f64, duration: f64| {
+        let ms = duration / 1000.;
+        if stack.iter().all(|(outer, _)| *outer != name) {
+            let scope = scopes.entry(name.to_owned()).or_default();
+            scope.0 += 1;
+            scope.1 += ms;
+        }
+        if is_page(name) && !stack.iter().any(|(outer, _)| is_page(outer)) {
+            pages.push((start, ms));
+        }
+    };
+
+    for event in events {
+        let name = event
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+        let thread = event
+            .get("tid")
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        let ts = event_ts(event);
+        let stack = stacks.entry(thread).or_default();
+        match event.get("ph").and_then(JsonValue::as_str) {
+            Some("B") => stack.push((name, ts)),
+            Some("E") => {
+                let Some((name, start)) = stack.pop() else {
+                    continue;
+                };
+                record(stack, name, start, ts - start);
+            }
+            Some("X") => {
+                let duration = event.get("dur").and_then(JsonValue::as_f64);
+                record(stack, name, ts, duration.unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+
+    pages.sort_by(|a, b| a.0.total_cmp(&b.0));
+    (scopes, pages.into_iter().map(|(_, ms)| ms).collect())
+}
+
+impl ServerState {
+    /// Compiles a document with the timings enabled and reports where the
+    /// time is spent. The memoized results of the previous compilations are
+    /// reused, so it reports the time of an incremental compilation.
+    pub(crate) fn compile_timings(
+        &mut self,
+        params: CompileTimingsParams,
+    ) -> SchedulableResponse<TimingReport> {
+        let entry = params
+            .path
+            .map(|path| self.entry_resolver().resolve(Some(path.into())));
+        let snap = self.snapshot().map_err(internal_error)?;
+
+        just_future(async move {
+            let snap = snap.task(TaskInputs {
+                entry,
+                inputs: None,
+            });
+            let world = snap.world();
+
+            let mut timer = CompileTimer::start();
+            let doc = typst::compile::<TypstPagedDocument>(world).output;
+            timer.compiled();
+            if let Ok(doc) = &doc {
+                let start = Instant::now();
+                let _ = typst_pdf::pdf(doc, &typst_pdf::PdfOptions::default());
+                timer.exported(start.elapsed());
+            }
+
+            let trace_output = params.trace_output.as_deref();
+            timer.finish(world, trace_output).map_err(internal_error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate() {
+        let event = |name: &str, ph: &str, ts: f64, tid: u64| serde_json::json!({ "name": name, "ph": ph, "ts": ts, "tid": tid });
+        let events = [
+            event("old", "B", 0., 1),
+            event("old", "E", 10., 1),
+            event(ROOT_SCOPE, "B", 100., 1),
+            event("eval", "B", 100., 1),
+            event("eval", "B", 200., 1),
+            event("eval", "E", 300., 1),
+            event("eval", "E", 1100., 1),
+            event("layout page", "B", 3100., 2),
+            event("layout page", "E", 4100., 2),
+            event("layout page", "B", 1100., 1),
+            event("layout page", "E", 3100., 1),
+            event(ROOT_SCOPE, "E", 5100., 1),
+        ];
+
+        let events = compilation_events(&events);
+        assert_eq!(events.len(), 10);
+        let (scopes, pages) = aggregate_events(&events);
+        assert_eq!(scopes["eval"], (1, 1.));
+        assert_eq!(scopes["layout page"], (2, 3.));
+        assert!(!scopes.contains_key("old"));
+        assert_eq!(pages, [2., 1.]);
+    }
+}
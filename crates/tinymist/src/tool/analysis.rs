@@ -0,0 +1,146 @@
+//! Snapshots the analysis results of a corpus, so that the behavior of the
+//! analyses can be verified across upgrades of Typst.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use reflexo_typst::{EntryReader, TaskInputs};
+use tinymist_query::analysis::{dump_analysis, Analysis};
+use tinymist_std::error::prelude::*;
+
+use crate::project::*;
+use crate::{CompileFontArgs, CompilePackageArgs};
+
+/// The extension of the snapshot files.
+const SNAPSHOT_EXT: &str = "snap";
+
+/// Analysis related commands.
+#[derive(Debug, Clone, clap::Subcommand)]
+#[clap(rename_all = "kebab-case")]
+pub enum AnalysisCommands {
+    /// Dumps the expressions, the types, and the diagnostics of the Typst
+    /// files in a directory.
+    Snapshot(AnalysisSnapshotArgs),
+}
+
+/// Arguments for snapshotting the analysis results of a corpus.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AnalysisSnapshotArgs {
+    /// The directory containing the Typst files to analyze, which is also the
+    /// root of the files.
+    pub dir: PathBuf,
+
+    /// The directory to write the snapshots to, one `<file>.snap` per Typst
+    /// file. Prints the snapshots to stdout if not provided.
+    #[clap(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Compares the snapshots with the ones in the output directory instead
+    /// of writing them, and fails if any of them differs.
+    #[clap(long, requires = "output")]
+    pub check: bool,
+
+    /// The arguments to resolve fonts.
+    #[clap(flatten)]
+    pub font: CompileFontArgs,
+
+    /// The arguments to resolve packages.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+}
+
+/// The main function for the analysis commands.
+pub fn analysis_main(args: AnalysisCommands) -> Result<()> {
+    match args {
+        AnalysisCommands::Snapshot(args) => snapshot_main(args),
+    }
+}
+
+/// Snapshots the analysis results of the Typst files in a directory.
+fn snapshot_main(args: AnalysisSnapshotArgs) -> Result<()> {
+    let root = args
+        .dir
+        .canonicalize()
+        .context("resolve corpus directory")?;
+    let files = corpus_files(&root);
+
+    let entry = EntryState::new_rooted(root.as_path().into(), None);
+    let fonts = Arc::new(LspUniverseBuilder::resolve_fonts(args.font)?);
+    let packages = LspUniverseBuilder::resolve_package(None, Some(&args.package));
+    let verse = LspUniverseBuilder::build(
+        entry,
+        ExportTarget::Paged,
+        Default::default(),
+        Default::default(),
+        packages,
+        fonts,
+    );
+    let world = verse.snapshot();
+
+    let mut mismatches = vec![];
+    for rel in &files {
+        let snap = snapshot_file(&world, rel)?;
+        let Some(output) = &args.output else {
+            println!("== {}\n{snap}", rel.display());
+            continue;
+        };
+
+        let snap_path = output
+            .join(rel)
+            .with_extension(format!("typ.{SNAPSHOT_EXT}"));
+        if args.check {
+            let expected = std::fs::read_to_string(&snap_path).ok();
+            if expected.as_deref() != Some(snap.as_str()) {
+                eprintln!("snapshot mismatched: {}", snap_path.display());
+                mismatches.push(rel);
+            }
+        } else {
+            if let Some(parent) = snap_path.parent() {
+                std::fs::create_dir_all(parent).context("create snapshot directory")?;
+            }
+            std::fs::write(&snap_path, snap).context("write snapshot")?;
+        }
+    }
+
+    if !mismatches.is_empty() {
+        bail!(
+            "{} of {} snapshots mismatched",
+            mismatches.len(),
+            files.len()
+        );
+    }
+
+    eprintln!("snapshotted {} files", files.len());
+    Ok(())
+}
+
+/// Dumps the analysis results of a file, in a fresh analysis so that the
+/// dump doesn't depend on the files analyzed before.
+fn snapshot_file(world: &LspWorld, rel: &Path) -> Result<String> {
+    let vpath = Path::new("/").join(rel);
+    let world = world.task(TaskInputs {
+        entry: Some(world.entry_state().select_in_workspace(&vpath)),
+        ..TaskInputs::default()
+    });
+    let main = world.main();
+
+    let analysis = Analysis::default();
+    let mut ctx = analysis.enter(world);
+    let source = ctx.source_by_id(main).context_ut("read source")?;
+    Ok(dump_analysis(&mut ctx, &source))
+}
+
+/// Collects the Typst files in a directory, relative to the directory and
+/// sorted to keep the output stable.
+fn corpus_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "typ"))
+        .filter_map(|entry| Some(entry.path().strip_prefix(root).ok()?.to_owned()))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
@@ -0,0 +1,482 @@
+//! Runs the canonical pipeline of the documents in the lock file, i.e. check,
+//! lint, test and compile, for continuous integration.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::ValueHint;
+use reflexo::{path::unix_slash, ImmutPath};
+use reflexo_typst::vfs::WorkspaceResolver;
+use reflexo_typst::WorldComputeGraph;
+use serde::Serialize;
+use tinymist_query::analysis::Analysis;
+use tinymist_std::{bail, error::prelude::*};
+use typst::diag::{Severity, SourceDiagnostic};
+use typst::World;
+
+use super::testing::{run_tests, TestConfigArgs};
+use crate::project::*;
+use crate::task::ExportTask;
+use crate::world::system::print_diagnostics;
+
+/// A step of the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CiStep {
+    /// Compiles the documents and reports the diagnostics, along with the
+    /// imports disagreeing with the pinned packages.
+    Check,
+    /// Lints the workspace files of the documents.
+    Lint,
+    /// Runs the tests of the documents.
+    Test,
+    /// Runs the export tasks of the documents.
+    Compile,
+}
+
+impl CiStep {
+    /// The steps in the order of the pipeline.
+    const ALL: [CiStep; 4] = [Self::Check, Self::Lint, Self::Test, Self::Compile];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Lint => "lint",
+            Self::Test => "test",
+            Self::Compile => "compile",
+        }
+    }
+}
+
+/// How to annotate the diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CiAnnotations {
+    /// Uses the GitHub Actions workflow commands if the `GITHUB_ACTIONS`
+    /// environment variable is set.
+    #[default]
+    Auto,
+    /// Uses the GitHub Actions workflow commands.
+    Github,
+    /// Doesn't annotate the diagnostics.
+    None,
+}
+
+/// Arguments for running the pipeline.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct CiArgs {
+    /// Specifies the path to the lock file listing the documents and their
+    /// export tasks. Defaults to the lock file in the current directory.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub lockfile: Option<PathBuf>,
+
+    /// Skips the steps of the pipeline, e.g. `--skip lint,test`.
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub skip: Vec<CiStep>,
+
+    /// Stops the pipeline at the first failure.
+    #[clap(long)]
+    pub fail_fast: bool,
+
+    /// Fails the check and lint steps on warnings.
+    #[clap(long)]
+    pub deny_warnings: bool,
+
+    /// Path to write the summary of the pipeline to, in JSON. Prints the
+    /// summary to stdout if the path is `-`.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub summary: Option<PathBuf>,
+
+    /// How to annotate the diagnostics and group the output of the steps.
+    #[clap(long, value_enum, default_value = "auto")]
+    pub annotations: CiAnnotations,
+
+    /// Configuration for the test step.
+    #[clap(flatten)]
+    pub test: TestConfigArgs,
+}
+
+/// The status of a step, or of a step for a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum CiStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// The summary of the pipeline.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CiSummary {
+    /// Whether all the steps passed.
+    passed: bool,
+    /// The steps in the order of the pipeline.
+    steps: Vec<StepSummary>,
+}
+
+/// The summary of a step.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StepSummary {
+    step: CiStep,
+    status: CiStatus,
+    /// The time spent on the step, in milliseconds.
+    duration_ms: u128,
+    /// The results of the step per document.
+    documents: Vec<DocumentSummary>,
+}
+
+/// The result of a step for a document.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentSummary {
+    /// The id of the document in the lock file.
+    document: String,
+    status: CiStatus,
+    errors: usize,
+    warnings: usize,
+    /// The paths of the exported artifacts.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<PathBuf>,
+    /// The reason of the failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl DocumentSummary {
+    fn new(document: &ProjectInput) -> Self {
+        Self {
+            document: document.id.to_string(),
+            status: CiStatus::Passed,
+            errors: 0,
+            warnings: 0,
+            outputs: vec![],
+            error: None,
+        }
+    }
+
+    fn fail(&mut self, error: impl ToString) {
+        self.status = CiStatus::Failed;
+        self.error = Some(error.to_string());
+    }
+}
+
+/// Runs the pipeline on the documents in the lock file.
+pub async fn ci_main(args: CiArgs) -> Result<()> {
+    let lock_dir: ImmutPath = if let Some(lockfile) = &args.lockfile {
+        lockfile.parent().context("no parent")?.into()
+    } else {
+        std::env::current_dir().context("lock directory")?.into()
+    };
+    let lock = LockFile::read(&lock_dir)?;
+    if lock.document.is_empty() {
+        bail!("no documents in {LOCK_FILENAME}, save them by `tinymist compile --save-lock`");
+    }
+
+    let github = match args.annotations {
+        CiAnnotations::Auto => std::env::var_os("GITHUB_ACTIONS").is_some(),
+        CiAnnotations::Github => true,
+        CiAnnotations::None => false,
+    };
+    let mut runner = CiRunner {
+        args,
+        lock,
+        lock_dir,
+        github,
+        analysis: Analysis::default(),
+        compiled: HashMap::new(),
+    };
+    let summary = runner.run().await;
+
+    for step in &summary.steps {
+        let failed = step
+            .documents
+            .iter()
+            .filter(|doc| doc.status == CiStatus::Failed);
+        eprintln!(
+            "{:<8} {:<8} {} failed, {:.2}s",
+            step.step.name(),
+            format!("{:?}", step.status).to_lowercase(),
+            failed.count(),
+            step.duration_ms as f64 / 1000.,
+        );
+    }
+
+    let json = serde_json::to_string_pretty(&summary).context("serialize summary")?;
+    match runner.args.summary.as_deref() {
+        Some(path) if path.as_os_str() == "-" => println!("{json}"),
+        Some(path) => std::fs::write(path, json).context("write summary")?,
+        None => {}
+    }
+
+    if !summary.passed {
+        let failed = summary
+            .steps
+            .iter()
+            .filter(|s| s.status == CiStatus::Failed);
+        let failed = failed.map(|s| s.step.name()).collect::<Vec<_>>();
+        bail!("pipeline failed at {}", failed.join(", "));
+    }
+
+    Ok(())
+}
+
+/// The state of the pipeline.
+struct CiRunner {
+    args: CiArgs,
+    lock: LockFile,
+    lock_dir: ImmutPath,
+    /// Whether to emit the GitHub Actions workflow commands.
+    github: bool,
+    analysis: Analysis,
+    /// The compiled documents, keyed by the id of the document and whether it
+    /// is compiled to HTML.
+    compiled: HashMap<(Id, bool), LspCompiledArtifact>,
+}
+
+impl CiRunner {
+    async fn run(&mut self) -> CiSummary {
+        let mut passed = true;
+        let mut steps = vec![];
+        for step in CiStep::ALL {
+            let skipped = self.args.skip.contains(&step) || (!passed && self.args.fail_fast);
+            if skipped {
+                steps.push(StepSummary {
+                    step,
+                    status: CiStatus::Skipped,
+                    duration_ms: 0,
+                    documents: vec![],
+                });
+                continue;
+            }
+
+            self.group_start(step.name());
+            let instant = Instant::now();
+            let mut documents = vec![];
+            for doc in self.lock.document.clone() {
+                let mut summary = DocumentSummary::new(&doc);
+                if let Err(err) = self.run_step(step, &doc, &mut summary).await {
+                    self.annotate_error(&doc, &err.to_string());
+                    summary.fail(err);
+                }
+
+                let failed = summary.status == CiStatus::Failed;
+                documents.push(summary);
+                if failed && self.args.fail_fast {
+                    break;
+                }
+            }
+            self.group_end();
+
+            let failed = documents.iter().any(|doc| doc.status == CiStatus::Failed);
+            passed &= !failed;
+            steps.push(StepSummary {
+                step,
+                status: if failed {
+                    CiStatus::Failed
+                } else {
+                    CiStatus::Passed
+                },
+                duration_ms: instant.elapsed().as_millis(),
+                documents,
+            });
+        }
+
+        CiSummary { passed, steps }
+    }
+
+    async fn run_step(
+        &mut self,
+        step: CiStep,
+        doc: &ProjectInput,
+        summary: &mut DocumentSummary,
+    ) -> Result<()> {
+        match step {
+            CiStep::Check => {
+                let compiled = self.compile(doc, self.is_html(doc))?;
+                let world = compiled.world();
+                let lock_diags = self.lock.check_imports(world);
+                let diags = compiled.diagnostics().chain(lock_diags.iter());
+                self.report(world, diags.collect(), summary)?;
+            }
+            CiStep::Lint => {
+                let compiled = self.compile(doc, self.is_html(doc))?;
+                let world = compiled.world();
+                let mut ctx = self.analysis.enter(world.clone());
+                let mut diags = vec![];
+                for dep in compiled.depended_files() {
+                    if WorkspaceResolver::is_package_file(*dep) {
+                        continue;
+                    }
+                    if let Ok(source) = world.source(*dep) {
+                        diags.extend(ctx.lint(&source));
+                    }
+                }
+                self.report(world, diags.iter().collect(), summary)?;
+            }
+            CiStep::Test => {
+                let compiled = self.compile(doc, false)?;
+                if !run_tests(compiled.world(), self.args.test.clone())? {
+                    bail!("some test cases failed");
+                }
+            }
+            CiStep::Compile => {
+                let tasks = self.lock.task.iter().filter(|task| task.document == doc.id);
+                for task in tasks.cloned().collect::<Vec<_>>() {
+                    let is_html = matches!(task.task, ProjectTask::ExportHtml(..));
+                    let compiled = self.compile(doc, is_html)?;
+                    if compiled.has_errors() {
+                        bail!("compilation failed");
+                    }
+                    let output = ExportTask::do_export(task.task, compiled, None).await?;
+                    summary.outputs.extend(output);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the document is exported to HTML by its tasks.
+    fn is_html(&self, doc: &ProjectInput) -> bool {
+        self.lock
+            .task
+            .iter()
+            .any(|task| task.document == doc.id && matches!(task.task, ProjectTask::ExportHtml(..)))
+    }
+
+    /// Compiles a document, sharing the compilation among the steps.
+    fn compile(&mut self, doc: &ProjectInput, is_html: bool) -> Result<LspCompiledArtifact> {
+        let key = (doc.id.clone(), is_html);
+        if let Some(compiled) = self.compiled.get(&key) {
+            return Ok(compiled.clone());
+        }
+
+        let verse = (doc.clone(), self.lock_dir.clone()).resolve()?;
+        let graph = WorldComputeGraph::from_world(verse.snapshot());
+        let compiled = CompiledArtifact::from_graph(graph, is_html);
+        self.compiled.insert(key, compiled.clone());
+        Ok(compiled)
+    }
+
+    /// Prints and annotates the diagnostics of a document, failing the
+    /// document on errors, or on warnings if they are denied.
+    fn report(
+        &self,
+        world: &LspWorld,
+        diags: Vec<&SourceDiagnostic>,
+        summary: &mut DocumentSummary,
+    ) -> Result<()> {
+        print_diagnostics(world, diags.iter().copied(), DiagnosticFormat::Human)
+            .context_ut("print diagnostics")?;
+        for diag in &diags {
+            self.annotate(world, diag);
+        }
+
+        let errors = diags.iter().filter(|d| d.severity == Severity::Error);
+        summary.errors = errors.count();
+        summary.warnings = diags.len() - summary.errors;
+        if summary.errors > 0 {
+            summary.fail(format!("{} error(s)", summary.errors));
+        } else if summary.warnings > 0 && self.args.deny_warnings {
+            summary.fail(format!("{} warning(s) are denied", summary.warnings));
+        }
+
+        Ok(())
+    }
+
+    fn group_start(&self, name: &str) {
+        if self.github {
+            println!("::group::{name}");
+        } else {
+            eprintln!("==> {name}");
+        }
+    }
+
+    fn group_end(&self) {
+        if self.github {
+            println!("::endgroup::");
+        }
+        let _ = std::io::stdout().flush();
+    }
+
+    /// Annotates a diagnostic at its location.
+    fn annotate(&self, world: &LspWorld, diag: &SourceDiagnostic) {
+        if !self.github {
+            return;
+        }
+
+        let level = match diag.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let location = (|| {
+            let id = diag.span.id()?;
+            let path = world.path_for_id(id).ok()?;
+            let path = path.as_path();
+            let cwd = std::env::current_dir().ok();
+            let path = cwd
+                .and_then(|cwd| path.strip_prefix(cwd).ok())
+                .unwrap_or(path);
+
+            let mut location = format!(" file={}", escape_property(&unix_slash(path)));
+            let source = world.source(id).ok()?;
+            if let Some(range) = source.range(diag.span) {
+                let position = |offset| {
+                    let line = source.byte_to_line(offset)?;
+                    Some((line + 1, source.byte_to_column(offset)? + 1))
+                };
+                if let (Some(start), Some(end)) = (position(range.start), position(range.end)) {
+                    location.push_str(&format!(
+                        ",line={},col={},endLine={},endColumn={}",
+                        start.0, start.1, end.0, end.1
+                    ));
+                }
+            }
+            Some(location)
+        })();
+
+        let mut message = diag.message.to_string();
+        for hint in &diag.hints {
+            message.push_str("\nhint: ");
+            message.push_str(hint);
+        }
+        let location = location.unwrap_or_default();
+        println!("::{level}{location}::{}", escape_data(&message));
+    }
+
+    /// Annotates an error of a step that is not located in the sources.
+    fn annotate_error(&self, doc: &ProjectInput, message: &str) {
+        if self.github {
+            let title = escape_property(&format!("tinymist ci ({})", doc.id));
+            println!("::error title={title}::{}", escape_data(message));
+        } else {
+            eprintln!("{}: {message}", doc.id);
+        }
+    }
+}
+
+/// Escapes the data of a workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command.
+fn escape_property(prop: &str) -> String {
+    escape_data(prop).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workflow_escape() {
+        assert_eq!(escape_data("50%\nhint: x"), "50%25%0Ahint: x");
+        assert_eq!(escape_property("C:\\a,b.typ"), "C%3A\\a%2Cb.typ");
+    }
+}
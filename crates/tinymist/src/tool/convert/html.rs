@@ -0,0 +1,555 @@
+//! Converts HTML into Typst markup.
+
+use super::{collapse_whitespace, trim_inlines, write_typst, Block, Inline};
+
+/// Converts an HTML fragment or document into Typst markup.
+pub fn html_to_typst(html: &str) -> String {
+    let root = parse_html(html);
+    let mut blocks = vec![];
+    convert_blocks(&root.children, &mut blocks);
+    write_typst(&blocks)
+}
+
+/// The elements that have no content.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// The elements whose content is not a part of the document.
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "template", "title"];
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// Parses HTML into a tree, tolerating unclosed and mismatched tags.
+fn parse_html(html: &str) -> Element {
+    let mut stack = vec![Element::default()];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut stack, rest);
+            break;
+        };
+        push_text(&mut stack, &rest[..lt]);
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(tag) = rest.strip_prefix("</") {
+            let end = tag.find('>').unwrap_or(tag.len());
+            let name = tag[..end].trim().to_lowercase();
+            rest = tag.get(end + 1..).unwrap_or_default();
+            // Ignores the closing tags without an opening one.
+            let pos = stack.iter().rposition(|elem| elem.name == name);
+            if let Some(pos) = pos.filter(|&pos| pos > 0) {
+                while stack.len() > pos {
+                    close(&mut stack);
+                }
+            }
+        } else if rest[1..].starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+            let (elem, self_closing, len) = parse_tag(rest);
+            rest = &rest[len..];
+            auto_close(&mut stack, &elem.name);
+
+            let is_raw_text = matches!(elem.name.as_str(), "script" | "style");
+            if self_closing || VOID_ELEMENTS.contains(&elem.name.as_str()) {
+                push_node(&mut stack, Node::Element(elem));
+            } else if is_raw_text {
+                let close_tag = format!("</{}", elem.name);
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&close_tag)
+                    .unwrap_or(rest.len());
+                rest = rest[end..].find('>').map_or("", |gt| &rest[end + gt + 1..]);
+                push_node(&mut stack, Node::Element(elem));
+            } else {
+                stack.push(elem);
+            }
+        } else {
+            push_text(&mut stack, "<");
+            rest = &rest[1..];
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().unwrap_or_default()
+}
+
+/// Parses a start tag, returning the element, whether it is self-closing and
+/// the length of the tag.
+fn parse_tag(text: &str) -> (Element, bool, usize) {
+    let bytes = text.as_bytes();
+    let mut i = 1;
+    let name_end = text[i..]
+        .find(|ch: char| ch.is_whitespace() || ch == '>' || ch == '/')
+        .map_or(text.len(), |end| i + end);
+    let name = text[i..name_end].to_lowercase();
+    i = name_end;
+
+    let mut attrs = vec![];
+    let mut self_closing = false;
+    while i < text.len() {
+        match bytes[i] {
+            b'>' => {
+                i += 1;
+                break;
+            }
+            b'/' => {
+                self_closing = true;
+                i += 1;
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                let key_end = text[i..]
+                    .find(|ch: char| ch.is_whitespace() || matches!(ch, '=' | '>' | '/'))
+                    .map_or(text.len(), |end| i + end);
+                let key = text[i..key_end].to_lowercase();
+                i = key_end;
+                let mut value = String::new();
+                if text[i..].trim_start().starts_with('=') {
+                    i = text.len() - text[i..].trim_start().len() + 1;
+                    i = text.len() - text[i..].trim_start().len();
+                    let value_end = match bytes.get(i) {
+                        Some(&quote @ (b'"' | b'\'')) => {
+                            i += 1;
+                            let end = text[i..]
+                                .find(quote as char)
+                                .map_or(text.len(), |end| i + end);
+                            value = decode_entities(&text[i..end]);
+                            (end + 1).min(text.len())
+                        }
+                        _ => {
+                            let end = text[i..]
+                                .find(|ch: char| ch.is_whitespace() || ch == '>')
+                                .map_or(text.len(), |end| i + end);
+                            value = decode_entities(&text[i..end]);
+                            end
+                        }
+                    };
+                    i = value_end;
+                }
+                attrs.push((key, value));
+                self_closing = false;
+            }
+        }
+    }
+
+    let elem = Element {
+        name,
+        attrs,
+        children: vec![],
+    };
+    (elem, self_closing, i)
+}
+
+/// Closes the elements that are implicitly ended by a start tag, e.g. a list
+/// item by the next one.
+fn auto_close(stack: &mut Vec<Element>, name: &str) {
+    let (closed, bounds): (&[&str], &[&str]) = match name {
+        "li" => (&["li"], &["ul", "ol"]),
+        "dt" | "dd" => (&["dt", "dd"], &["dl"]),
+        "tr" => (&["tr", "td", "th"], &["table", "thead", "tbody", "tfoot"]),
+        "td" | "th" => (&["td", "th"], &["tr", "table"]),
+        "thead" | "tbody" | "tfoot" => (&["thead", "tbody", "tfoot", "tr", "td", "th"], &["table"]),
+        "p" | "div" | "ul" | "ol" | "table" | "pre" | "blockquote" | "hr" | "h1" | "h2" | "h3"
+        | "h4" | "h5" | "h6" => (&["p"], &[]),
+        _ => return,
+    };
+
+    let Some(pos) = stack.iter().rposition(|elem| {
+        closed.contains(&elem.name.as_str()) || bounds.contains(&elem.name.as_str())
+    }) else {
+        return;
+    };
+    if pos > 0 && closed.contains(&stack[pos].name.as_str()) {
+        while stack.len() > pos {
+            close(stack);
+        }
+    }
+}
+
+fn close(stack: &mut Vec<Element>) {
+    if let Some(elem) = stack.pop() {
+        push_node(stack, Node::Element(elem));
+    }
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if !text.is_empty() {
+        push_node(stack, Node::Text(decode_entities(text)));
+    }
+}
+
+/// Decodes the common character references.
+fn decode_entities(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        res.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                _ => {
+                    let num = entity.strip_prefix('#')?;
+                    let code = match num.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => num.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((ch, end + 1))
+        });
+
+        match decoded {
+            Some((ch, len)) => {
+                res.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+/// Converts the nodes into blocks, gathering the inline content between the
+/// block elements into paragraphs.
+fn convert_blocks(nodes: &[Node], blocks: &mut Vec<Block>) {
+    let mut para = vec![];
+
+    macro_rules! flush {
+        () => {
+            let content = trim_inlines(std::mem::take(&mut para));
+            if !content.is_empty() {
+                blocks.push(Block::Paragraph(content));
+            }
+        };
+    }
+
+    for node in nodes {
+        let elem = match node {
+            Node::Element(elem) if is_block(elem) => elem,
+            _ => {
+                convert_inline(node, &mut para);
+                continue;
+            }
+        };
+
+        flush!();
+        match elem.name.as_str() {
+            name if SKIPPED_ELEMENTS.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = usize::from(elem.name.as_bytes()[1] - b'0');
+                let content = trim_inlines(inline_content(&elem.children));
+                if !content.is_empty() {
+                    blocks.push(Block::Heading(level, content));
+                }
+            }
+            "ul" | "ol" => {
+                let items = elem
+                    .children
+                    .iter()
+                    .filter_map(|node| match node {
+                        Node::Element(item) if item.name == "li" => {
+                            let mut item_blocks = vec![];
+                            convert_blocks(&item.children, &mut item_blocks);
+                            Some(item_blocks)
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if !items.is_empty() {
+                    blocks.push(Block::List {
+                        ordered: elem.name == "ol",
+                        items,
+                    });
+                }
+            }
+            "table" => blocks.extend(convert_table(elem)),
+            "pre" => blocks.push(convert_pre(elem)),
+            "blockquote" => {
+                let mut content = vec![];
+                convert_blocks(&elem.children, &mut content);
+                blocks.push(Block::Quote(content));
+            }
+            "hr" => blocks.push(Block::Rule),
+            _ => convert_blocks(&elem.children, blocks),
+        }
+    }
+    flush!();
+}
+
+fn is_block(elem: &Element) -> bool {
+    matches!(
+        elem.name.as_str(),
+        "address"
+            | "article"
+            | "aside"
+            | "blockquote"
+            | "body"
+            | "dd"
+            | "details"
+            | "div"
+            | "dl"
+            | "dt"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "html"
+            | "li"
+            | "main"
+            | "nav"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "summary"
+            | "table"
+            | "ul"
+    ) || SKIPPED_ELEMENTS.contains(&elem.name.as_str())
+}
+
+fn convert_table(table: &Element) -> Option<Block> {
+    let mut header = None;
+    let mut rows = vec![];
+
+    let mut row_elems = vec![];
+    collect_rows(table, &mut row_elems);
+    for row in row_elems {
+        let cells = row
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(cell) if matches!(cell.name.as_str(), "td" | "th") => Some(cell),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            continue;
+        }
+
+        let is_header = cells.iter().all(|cell| cell.name == "th");
+        let cells = cells
+            .into_iter()
+            .map(|cell| trim_inlines(inline_content(&cell.children)))
+            .collect::<Vec<_>>();
+        if is_header && header.is_none() && rows.is_empty() {
+            header = Some(cells);
+        } else {
+            rows.push(cells);
+        }
+    }
+
+    (header.is_some() || !rows.is_empty()).then_some(Block::Table { header, rows })
+}
+
+/// Collects the rows of a table, skipping the nested tables.
+fn collect_rows<'a>(elem: &'a Element, rows: &mut Vec<&'a Element>) {
+    for node in &elem.children {
+        let Node::Element(child) = node else {
+            continue;
+        };
+        match child.name.as_str() {
+            "tr" => rows.push(child),
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+fn convert_pre(pre: &Element) -> Block {
+    let code_elem = match pre.children.as_slice() {
+        [Node::Element(code)] if code.name == "code" => code,
+        _ => pre,
+    };
+
+    let lang = [code_elem, pre].into_iter().find_map(|elem| {
+        elem.attr("class")?
+            .split_whitespace()
+            .find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+            })
+            .map(str::to_owned)
+    });
+
+    let mut code = String::new();
+    raw_text(&code_elem.children, &mut code);
+    let code = code.strip_prefix('\n').unwrap_or(&code).to_owned();
+    Block::Code { lang, code }
+}
+
+/// Gets the text content of the nodes without collapsing the whitespace.
+fn raw_text(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(elem) if elem.name == "br" => out.push('\n'),
+            Node::Element(elem) => raw_text(&elem.children, out),
+        }
+    }
+}
+
+fn inline_content(nodes: &[Node]) -> Vec<Inline> {
+    let mut inlines = vec![];
+    for node in nodes {
+        convert_inline(node, &mut inlines);
+    }
+    inlines
+}
+
+fn convert_inline(node: &Node, inlines: &mut Vec<Inline>) {
+    let elem = match node {
+        Node::Text(text) => {
+            let text = collapse_whitespace(text);
+            // Merges adjacent texts to collapse the whitespace between them.
+            if let Some(Inline::Text(prev)) = inlines.last_mut() {
+                if prev.ends_with(' ') {
+                    prev.push_str(text.trim_start());
+                } else {
+                    prev.push_str(&text);
+                }
+            } else if !text.is_empty() {
+                inlines.push(Inline::Text(text));
+            }
+            return;
+        }
+        Node::Element(elem) => elem,
+    };
+
+    match elem.name.as_str() {
+        name if SKIPPED_ELEMENTS.contains(&name) => {}
+        "em" | "i" | "cite" | "dfn" | "var" => {
+            let content = inline_content(&elem.children);
+            if !content.is_empty() {
+                inlines.push(Inline::Emph(content));
+            }
+        }
+        "strong" | "b" => {
+            let content = inline_content(&elem.children);
+            if !content.is_empty() {
+                inlines.push(Inline::Strong(content));
+            }
+        }
+        "code" | "kbd" | "samp" | "tt" => {
+            let mut code = String::new();
+            raw_text(&elem.children, &mut code);
+            inlines.push(Inline::Code(collapse_whitespace(&code)));
+        }
+        "a" => {
+            let content = inline_content(&elem.children);
+            match elem.attr("href").filter(|href| !href.is_empty()) {
+                Some(href) if !href.starts_with('#') && !href.starts_with("javascript:") => {
+                    inlines.push(Inline::Link {
+                        url: href.to_owned(),
+                        content: trim_inlines(content),
+                    });
+                }
+                _ => inlines.extend(content),
+            }
+        }
+        "img" => {
+            if let Some(src) = elem.attr("src").filter(|src| !src.is_empty()) {
+                inlines.push(Inline::Image {
+                    src: src.to_owned(),
+                    alt: elem.attr("alt").unwrap_or_default().to_owned(),
+                });
+            }
+        }
+        "br" => inlines.push(Inline::LineBreak),
+        // Flattens the other elements, e.g. `span`, into their content.
+        _ => {
+            for child in &elem.children {
+                convert_inline(child, inlines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_html() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Ignored</title><style>p { color: red; }</style></head>
+<body>
+<h1>Title</h1>
+<p>Some <em>emphasis</em>, <b>strong</b> and <code>code</code>
+  with <a href="https://typst.app">a&nbsp;link</a> &amp; <span>more</span>.
+<ul><li>one<li>two<ol><li>nested</ol></ul>
+<table>
+  <tr><th>a</th><th>b</th></tr>
+  <tr><td>1</td><td>2</td></tr>
+</table>
+<pre><code class="language-rust">fn main() {}
+</code></pre>
+</body></html>"#;
+        assert_eq!(
+            html_to_typst(html),
+            "= Title\n\nSome _emphasis_, *strong* and `code` with #link(\"https://typst.app\")[a\u{a0}link] & more.\n\n- one\n- two\n  + nested\n\n#table(\n  columns: 2,\n  table.header([a], [b]),\n  [1], [2],\n)\n\n```rust\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            decode_entities("&lt;a&gt; &#65;&#x42; &unknown; &"),
+            "<a> AB &unknown; &"
+        );
+    }
+}
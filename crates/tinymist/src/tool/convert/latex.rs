@@ -0,0 +1,736 @@
+//! Converts LaTeX math into Typst math, e.g. when migrating the formulas of a
+//! LaTeX document.
+//!
+//! The symbols, fractions, roots, fonts, accents, operators, spaces,
+//! delimiters and the matrix, cases and alignment environments are converted.
+//! The other commands are kept by their names.
+
+use std::io::Read;
+
+use tinymist_query::analysis::latex_command_symbol;
+use tinymist_std::error::prelude::*;
+use typst::foundations::{Repr, Str};
+
+/// Arguments for converting LaTeX math into Typst math.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ConvertLatexArgs {
+    /// The LaTeX math to convert. Reads the stdin if not given.
+    pub text: Option<String>,
+}
+
+/// Converts the LaTeX math in the arguments or the stdin, printing the Typst
+/// math to the stdout.
+pub fn convert_latex_main(args: ConvertLatexArgs) -> Result<()> {
+    let text = match args.text {
+        Some(text) => text,
+        None => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .context("read stdin")?;
+            text
+        }
+    };
+
+    println!("{}", latex_math_to_typst(&text));
+    Ok(())
+}
+
+/// Converts a LaTeX math snippet into Typst math. The snippets delimited by
+/// `$`, `$$`, `\(` or `\[`, or written in an equation environment, are
+/// converted into equations.
+pub fn latex_math_to_typst(text: &str) -> String {
+    let (body, delim) = strip_delimiters(text.trim());
+
+    let mut parser = Parser {
+        text: body,
+        cursor: 0,
+        args: 0,
+    };
+    let mut out = MathWriter::default();
+    loop {
+        out.push(&parser.seq(None, false));
+        // Skips the unbalanced closing tokens.
+        if parser.eat_command("right") {
+            parser.delimiter();
+        } else if parser.eat_command("end") {
+            parser.braced_raw();
+        } else if parser.bump().is_none() {
+            break;
+        }
+    }
+
+    let math = out.out.trim();
+    match delim {
+        Delim::None => math.to_owned(),
+        Delim::Inline => format!("${math}$"),
+        Delim::Display => format!("$ {math} $"),
+    }
+}
+
+/// The delimiters of a LaTeX math snippet.
+#[derive(Debug, Clone, Copy)]
+enum Delim {
+    None,
+    Inline,
+    Display,
+}
+
+/// The environments written as display equations.
+const DISPLAY_ENVS: &[&str] = &[
+    "equation",
+    "align",
+    "gather",
+    "multline",
+    "eqnarray",
+    "displaymath",
+];
+
+fn strip_delimiters(text: &str) -> (&str, Delim) {
+    const PAIRS: &[(&str, &str, Delim)] = &[
+        ("$$", "$$", Delim::Display),
+        ("\\[", "\\]", Delim::Display),
+        ("\\(", "\\)", Delim::Inline),
+        ("$", "$", Delim::Inline),
+    ];
+    for (open, close, delim) in PAIRS {
+        let body = text
+            .strip_prefix(open)
+            .and_then(|it| it.strip_suffix(close));
+        if let Some(body) = body {
+            return (body, *delim);
+        }
+    }
+
+    let env = text.strip_prefix("\\begin{").unwrap_or_default();
+    if DISPLAY_ENVS.iter().any(|it| env.starts_with(it)) {
+        return (text, Delim::Display);
+    }
+
+    (text, Delim::None)
+}
+
+/// The Typst math being written.
+#[derive(Default)]
+struct MathWriter {
+    out: String,
+}
+
+impl MathWriter {
+    /// Pushes a piece of math, separating it from the previous one if they
+    /// would be merged, e.g. the variables `a` and `b` into `ab`, or the
+    /// operators `<` and `=` into the shorthand `<=`.
+    fn push(&mut self, piece: &str) {
+        let last = self.out.chars().next_back();
+        let next = piece.chars().next();
+        if let (Some(last), Some(next)) = (last, next) {
+            let is_word = |ch: char| ch.is_alphanumeric();
+            let is_op = |ch: char| "<>-=!:|~.[]".contains(ch);
+            if (is_word(last) && (is_word(next) || next == '.')) || (is_op(last) && is_op(next)) {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(piece);
+    }
+
+    /// Whether an attachment has no base, e.g. at the start of a row.
+    fn lacks_base(&self) -> bool {
+        let out = self.out.trim_end();
+        out.is_empty() || out.ends_with(['&', '\\', '(', ',', ';'])
+    }
+}
+
+/// Maps the font commands to the Typst functions.
+const FONTS: &[(&str, &str)] = &[
+    ("mathrm", "upright"),
+    ("mathbf", "bold"),
+    ("boldsymbol", "bold"),
+    ("bm", "bold"),
+    ("mathit", "italic"),
+    ("mathsf", "sans"),
+    ("mathtt", "mono"),
+    ("mathcal", "cal"),
+    ("mathscr", "scr"),
+    ("mathfrak", "frak"),
+    ("mathbb", "bb"),
+];
+
+/// Maps the accent commands to the Typst functions.
+const ACCENTS: &[(&str, &str)] = &[
+    ("hat", "hat"),
+    ("widehat", "hat"),
+    ("tilde", "tilde"),
+    ("widetilde", "tilde"),
+    ("bar", "macron"),
+    ("vec", "arrow"),
+    ("overrightarrow", "arrow"),
+    ("dot", "dot"),
+    ("ddot", "dot.double"),
+    ("dddot", "dot.triple"),
+    ("breve", "breve"),
+    ("acute", "acute"),
+    ("grave", "grave"),
+    ("check", "caron"),
+    ("overline", "overline"),
+    ("underline", "underline"),
+    ("overbrace", "overbrace"),
+    ("underbrace", "underbrace"),
+    ("cancel", "cancel"),
+];
+
+/// The operators named the same in LaTeX and Typst.
+const OPERATORS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det", "dim",
+    "exp", "gcd", "hom", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max", "min",
+    "Pr", "sec", "sin", "sinh", "sup", "tan", "tanh",
+];
+
+/// Maps the spacing commands to the Typst spaces.
+const SPACES: &[(&str, &str)] = &[
+    ("quad", "quad"),
+    ("qquad", "wide"),
+    ("enspace", "space.en"),
+    ("thinspace", "thin"),
+    ("medspace", "med"),
+    ("thickspace", "thick"),
+];
+
+/// The commands having no effect in Typst, e.g. the explicit sizes of the
+/// delimiters, which are scaled automatically.
+const IGNORED: &[&str] = &[
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "scriptscriptstyle",
+    "limits",
+    "nolimits",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "bigr",
+    "Bigl",
+    "Bigr",
+    "biggl",
+    "biggr",
+    "Biggl",
+    "Biggr",
+    "nonumber",
+    "notag",
+    "hline",
+    "negthinspace",
+];
+
+/// Maps the matrix environments to their delimiters.
+const MATRICES: &[(&str, &str)] = &[
+    ("matrix", "#none"),
+    ("smallmatrix", "#none"),
+    ("pmatrix", ""),
+    ("bmatrix", "\"[\""),
+    ("Bmatrix", "\"{\""),
+    ("vmatrix", "\"|\""),
+    ("Vmatrix", "\"||\""),
+];
+
+/// A parser converting LaTeX math.
+struct Parser<'a> {
+    text: &'a str,
+    cursor: usize,
+    /// The nesting level of the arguments of the Typst functions, in which the
+    /// commas, semicolons and colons are escaped.
+    args: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.cursor..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.cursor += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        let matched = self.peek() == Some(ch);
+        if matched {
+            self.cursor += ch.len_utf8();
+        }
+        matched
+    }
+
+    fn at_command(&self, name: &str) -> bool {
+        let rest = self.text[self.cursor..].strip_prefix('\\');
+        let rest = rest.and_then(|rest| rest.strip_prefix(name));
+        rest.is_some_and(|rest| !rest.starts_with(|ch: char| ch.is_ascii_alphabetic()))
+    }
+
+    fn eat_command(&mut self, name: &str) -> bool {
+        let matched = self.at_command(name);
+        if matched {
+            self.cursor += name.len() + 1;
+        }
+        matched
+    }
+
+    /// Skips the whitespace and the comments, returning whether any is
+    /// skipped.
+    fn skip_trivia(&mut self) -> bool {
+        let start = self.cursor;
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_whitespace() => {
+                    self.bump();
+                }
+                Some('%') => while self.bump().is_some_and(|ch| ch != '\n') {},
+                _ => break,
+            }
+        }
+        self.cursor > start
+    }
+
+    /// Reads the text of a braced group as is, e.g. the name of an
+    /// environment.
+    fn braced_raw(&mut self) -> &'a str {
+        let text = self.text;
+        self.skip_trivia();
+        if !self.eat('{') {
+            return "";
+        }
+
+        let start = self.cursor;
+        let mut depth = 0;
+        while let Some(ch) = self.bump() {
+            match ch {
+                '\\' => {
+                    self.bump();
+                }
+                '{' => depth += 1,
+                '}' if depth == 0 => return &text[start..self.cursor - 1],
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        &text[start..]
+    }
+
+    /// Skips an optional argument, e.g. the spacing of a line break.
+    fn skip_optional(&mut self) {
+        let start = self.cursor;
+        self.skip_trivia();
+        if !self.eat('[') {
+            self.cursor = start;
+            return;
+        }
+        while self.bump().is_some_and(|ch| ch != ']') {}
+    }
+
+    /// Converts the math until a closing brace, the closer, `\right`, `\end`
+    /// or the end of the input. In the tables, the math is also stopped by
+    /// `&` and `\\`.
+    fn seq(&mut self, closer: Option<char>, table: bool) -> String {
+        let mut out = MathWriter::default();
+        loop {
+            // Keeps the spaces between the atoms for readability.
+            let spaced = self.skip_trivia();
+            let Some(ch) = self.peek() else {
+                break;
+            };
+            if spaced && !out.out.is_empty() && !out.out.ends_with([' ', '\n']) {
+                out.out.push(' ');
+            }
+            if ch == '}' || Some(ch) == closer || self.at_command("right") || self.at_command("end")
+            {
+                break;
+            }
+            if table && (ch == '&' || self.text[self.cursor..].starts_with("\\\\")) {
+                break;
+            }
+
+            if matches!(ch, '^' | '_') {
+                self.bump();
+                if out.lacks_base() {
+                    out.push("\"\"");
+                }
+                let arg = self.arg();
+                out.out.truncate(out.out.trim_end().len());
+                out.out.push(ch);
+                out.out.push_str(&script_arg(&arg));
+                continue;
+            }
+
+            let atom = self.atom();
+            out.push(&atom);
+        }
+        out.out.truncate(out.out.trim_end().len());
+        out.out
+    }
+
+    /// Converts an argument of a command, i.e. a braced group or a single
+    /// token.
+    fn arg(&mut self) -> String {
+        self.skip_trivia();
+        if self.eat('{') {
+            let arg = self.seq(None, false);
+            self.eat('}');
+            arg
+        } else if self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            // Only the first digit is the argument, e.g. `x^23`.
+            self.bump().map(String::from).unwrap_or_default()
+        } else {
+            self.atom()
+        }
+    }
+
+    /// Converts the arguments of a command into a call of a Typst function.
+    fn call(&mut self, func: &str, count: usize) -> String {
+        self.args += 1;
+        let args = (0..count)
+            .map(|_| non_empty(self.arg()))
+            .collect::<Vec<_>>();
+        self.args -= 1;
+        format!("{func}({})", args.join(", "))
+    }
+
+    /// Converts a single atom, e.g. a letter, a number, a group or a command.
+    fn atom(&mut self) -> String {
+        let Some(ch) = self.bump() else {
+            return String::new();
+        };
+
+        match ch {
+            '{' => {
+                let group = self.seq(None, false);
+                self.eat('}');
+                // The group is kept as a whole by the following attachments,
+                // e.g. `{a+b}^2`.
+                let start = self.cursor;
+                self.skip_trivia();
+                let attached = matches!(self.peek(), Some('^' | '_'));
+                self.cursor = start;
+                if attached && !group.is_empty() && !is_atomic(&group) {
+                    format!("({group})")
+                } else {
+                    group
+                }
+            }
+            '\\' => self.command(),
+            '0'..='9' => {
+                let start = self.cursor - 1;
+                while let Some(ch) = self.peek() {
+                    let is_decimal = ch == '.'
+                        && (self.text[self.cursor + 1..]
+                            .starts_with(|ch: char| ch.is_ascii_digit()));
+                    if !(ch.is_ascii_digit() || is_decimal) {
+                        break;
+                    }
+                    self.bump();
+                }
+                self.text[start..self.cursor].to_owned()
+            }
+            '/' | '"' | '#' | '$' | '@' | '^' | '_' => format!("\\{ch}"),
+            ',' | ';' | ':' if self.args > 0 => format!("\\{ch}"),
+            '~' => String::new(),
+            _ => ch.to_string(),
+        }
+    }
+
+    /// Converts a command after the backslash.
+    fn command(&mut self) -> String {
+        let text = self.text;
+        let start = self.cursor;
+        while self.peek().is_some_and(|ch| ch.is_ascii_alphabetic()) {
+            self.bump();
+        }
+        let name = &text[start..self.cursor];
+        if name.is_empty() {
+            return self.control_symbol();
+        }
+
+        let lookup = |table: &[(&str, &'static str)]| {
+            table.iter().find(|(it, _)| *it == name).map(|(_, it)| *it)
+        };
+        if let Some(func) = lookup(FONTS) {
+            return self.font(name, func);
+        }
+        if let Some(func) = lookup(ACCENTS) {
+            return self.call(func, 1);
+        }
+        if let Some(space) = lookup(SPACES) {
+            return space.to_owned();
+        }
+        if IGNORED.contains(&name) {
+            return String::new();
+        }
+        if OPERATORS.contains(&name) {
+            return name.to_owned();
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => self.call("frac", 2),
+            "binom" | "dbinom" | "tbinom" => self.call("binom", 2),
+            "sqrt" => {
+                self.skip_trivia();
+                if self.eat('[') {
+                    self.args += 1;
+                    let index = non_empty(self.seq(Some(']'), false));
+                    self.eat(']');
+                    let radicand = non_empty(self.arg());
+                    self.args -= 1;
+                    format!("root({index}, {radicand})")
+                } else {
+                    self.call("sqrt", 1)
+                }
+            }
+            "text" | "textrm" | "textnormal" | "mbox" | "textup" => self.text_arg(),
+            "textbf" => format!("bold({})", self.text_arg()),
+            "textit" => format!("italic({})", self.text_arg()),
+            "operatorname" => {
+                let limits = self.eat('*');
+                let op = self.text_arg();
+                if limits {
+                    format!("op({op}, limits: #true)")
+                } else {
+                    format!("op({op})")
+                }
+            }
+            "bmod" => "mod".to_owned(),
+            "pmod" => format!("(mod {})", self.arg()),
+            "left" => self.left_right(),
+            "middle" => format!("mid({})", self.delimiter()),
+            "begin" => self.env(),
+            "not" => self.not(),
+            "label" | "tag" => {
+                self.braced_raw();
+                String::new()
+            }
+            "lbrace" => "\\{".to_owned(),
+            "rbrace" => "\\}".to_owned(),
+            "vert" | "lvert" | "rvert" => "|".to_owned(),
+            "Vert" | "lVert" | "rVert" => "bar.v.double".to_owned(),
+            _ => match latex_command_symbol(name) {
+                Some(symbol) => symbol.to_string(),
+                None => name.to_owned(),
+            },
+        }
+    }
+
+    /// Converts a command named by a single non-letter character.
+    fn control_symbol(&mut self) -> String {
+        let Some(ch) = self.bump() else {
+            return String::new();
+        };
+
+        match ch {
+            ',' => "thin".to_owned(),
+            ':' | '>' => "med".to_owned(),
+            ';' => "thick".to_owned(),
+            '!' => String::new(),
+            ' ' => "space".to_owned(),
+            '|' => "bar.v.double".to_owned(),
+            '%' => "%".to_owned(),
+            '\\' => {
+                self.skip_optional();
+                "\\\n".to_owned()
+            }
+            _ => format!("\\{ch}"),
+        }
+    }
+
+    /// Converts the argument of a text command into a string.
+    fn text_arg(&mut self) -> String {
+        Str::from(self.braced_raw()).repr().to_string()
+    }
+
+    /// Converts the argument of a font command.
+    fn font(&mut self, name: &str, func: &str) -> String {
+        let arg = self.call(func, 1);
+        // The blackboard bold letters are named, e.g. `RR` for `\mathbb{R}`.
+        let letter = arg.strip_prefix("bb(").and_then(|it| it.strip_suffix(')'));
+        match letter {
+            Some(letter @ ("N" | "Z" | "Q" | "R" | "C")) if name == "mathbb" => letter.repeat(2),
+            _ => arg,
+        }
+    }
+
+    /// Converts a delimiter of `\left`, `\middle` or `\right`.
+    fn delimiter(&mut self) -> String {
+        self.skip_trivia();
+        if self.eat('.') {
+            return String::new();
+        }
+        self.atom()
+    }
+
+    /// Converts a pair of `\left` and `\right` into a call of `lr`.
+    fn left_right(&mut self) -> String {
+        let mut out = MathWriter::default();
+        self.args += 1;
+        out.push(&self.delimiter());
+        out.push(&self.seq(None, false));
+        if self.eat_command("right") {
+            out.push(&self.delimiter());
+        }
+        self.args -= 1;
+        format!("lr({})", out.out)
+    }
+
+    /// Converts a negated relation.
+    fn not(&mut self) -> String {
+        self.skip_trivia();
+        match self.peek() {
+            Some('=') => {
+                self.bump();
+                "eq.not".to_owned()
+            }
+            Some('<') => {
+                self.bump();
+                "lt.not".to_owned()
+            }
+            Some('>') => {
+                self.bump();
+                "gt.not".to_owned()
+            }
+            _ if self.eat_command("in") => "in.not".to_owned(),
+            _ => self.call("cancel", 1),
+        }
+    }
+
+    /// Converts an environment after `\begin`.
+    fn env(&mut self) -> String {
+        let name = self.braced_raw();
+        let matrix = MATRICES.iter().find(|(it, _)| *it == name);
+        let out = match name {
+            _ if matrix.is_some() => {
+                let delim = matrix.map_or("", |(_, delim)| delim);
+                mat(delim, &self.table())
+            }
+            "array" | "subarray" => {
+                self.braced_raw();
+                mat("#none", &self.table())
+            }
+            "cases" | "dcases" | "rcases" => {
+                let rows = self.table();
+                let rows = rows.iter().map(|row| row.join(" & ")).collect::<Vec<_>>();
+                let reverse = if name == "rcases" {
+                    "reverse: #true, "
+                } else {
+                    ""
+                };
+                format!("cases({reverse}{})", rows.join(", "))
+            }
+            _ => self.seq(None, false),
+        };
+
+        if self.eat_command("end") {
+            self.braced_raw();
+        }
+        out
+    }
+
+    /// Converts the rows and the cells of a table.
+    fn table(&mut self) -> Vec<Vec<String>> {
+        self.args += 1;
+        let mut rows = vec![];
+        let mut row = vec![];
+        loop {
+            row.push(non_empty(self.seq(None, true)));
+            if self.eat('&') {
+                continue;
+            }
+            let newline = self.text[self.cursor..].starts_with("\\\\");
+            rows.push(std::mem::take(&mut row));
+            if !newline {
+                break;
+            }
+            self.cursor += 2;
+            self.skip_optional();
+        }
+        self.args -= 1;
+
+        // The last row is usually ended by a line break.
+        if rows
+            .last()
+            .is_some_and(|row| row.iter().all(|cell| cell == "\"\""))
+        {
+            rows.pop();
+        }
+        rows
+    }
+}
+
+/// Writes a matrix with the delimiter.
+fn mat(delim: &str, rows: &[Vec<String>]) -> String {
+    let rows = rows.iter().map(|row| row.join(", ")).collect::<Vec<_>>();
+    if delim.is_empty() {
+        format!("mat({})", rows.join("; "))
+    } else {
+        format!("mat(delim: {delim}, {})", rows.join("; "))
+    }
+}
+
+/// Whether the math is a single atom, which is not parenthesized in the
+/// attachments.
+fn is_atomic(math: &str) -> bool {
+    math.chars().count() == 1
+        || (!math.is_empty() && math.chars().all(|ch| ch.is_alphanumeric() || ch == '.'))
+}
+
+/// Writes an argument of an attachment.
+fn script_arg(arg: &str) -> String {
+    if is_atomic(arg) {
+        arg.to_owned()
+    } else {
+        format!("({arg})")
+    }
+}
+
+/// Writes an empty argument of a function as an empty string.
+fn non_empty(arg: String) -> String {
+    if arg.trim().is_empty() {
+        "\"\"".to_owned()
+    } else {
+        arg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_latex() {
+        let convert = latex_math_to_typst;
+        assert_eq!(
+            convert(r"\alpha + \beta \leq \gamma"),
+            "alpha + beta lt.eq gamma"
+        );
+        assert_eq!(convert(r"$\frac{a+b}{2}$"), "$frac(a+b, 2)$");
+        assert_eq!(convert(r"\[ x^{2n} + y_1^2 \]"), "$ x^(2 n) + y_1^2 $");
+        assert_eq!(
+            convert(r"\sqrt[3]{x} \cdot \sqrt{y}"),
+            "root(3, x) dot.op sqrt(y)"
+        );
+        assert_eq!(convert(r"\mathbb{R} \to \mathbf{v}"), "RR arrow.r bold(v)");
+        assert_eq!(convert(r"\sin x \text{ if } a/b"), "sin x \" if \" a\\/b");
+        assert_eq!(
+            convert(r"\left( \frac{1}{2}, x \right]"),
+            "lr((frac(1, 2)\\, x])"
+        );
+        assert_eq!(
+            convert(r"\begin{pmatrix} 1 & 2 \\ 3 & 4 \\ \end{pmatrix}"),
+            "mat(1, 2; 3, 4)"
+        );
+        assert_eq!(
+            convert(r"|x| = \begin{cases} x & x \geq 0 \\ -x & \text{otherwise} \end{cases}"),
+            "|x| = cases(x & x gt.eq 0, -x & \"otherwise\")"
+        );
+        assert_eq!(
+            convert("\\begin{align}\n  a &= b \\\\\n  &= c % comment\n\\end{align}"),
+            "$ a &= b \\\n&= c $"
+        );
+        assert_eq!(convert(r"{}^{14}C \not= \{x\}"), "\"\"^14 C eq.not \\{x\\}");
+    }
+}
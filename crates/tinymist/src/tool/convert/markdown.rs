@@ -0,0 +1,520 @@
+//! Converts Markdown into Typst markup.
+
+use super::{trim_inlines, write_typst, Block, Inline};
+
+/// Converts a Markdown text into Typst markup.
+pub fn markdown_to_typst(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    write_typst(&parse_blocks(&lines))
+}
+
+fn parse_blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        if let Some(fence) = code_fence(trimmed) {
+            let indent = indent_of(line);
+            let lang = trimmed[fence.len()..].split_whitespace().next();
+            let mut code = vec![];
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                code.push(strip_indent(lines[i], indent));
+                i += 1;
+            }
+            i += 1;
+            blocks.push(Block::Code {
+                lang: lang.map(str::to_owned),
+                code: code.join("\n"),
+            });
+            continue;
+        }
+
+        if let Some((level, content)) = atx_heading(trimmed) {
+            blocks.push(Block::Heading(level, parse_inlines(content)));
+            i += 1;
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            blocks.push(Block::Rule);
+            i += 1;
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            let mut quoted = vec![];
+            while i < lines.len() {
+                let Some(rest) = lines[i].trim_start().strip_prefix('>') else {
+                    break;
+                };
+                quoted.push(rest.strip_prefix(' ').unwrap_or(rest));
+                i += 1;
+            }
+            blocks.push(Block::Quote(parse_blocks(&quoted)));
+            continue;
+        }
+
+        if list_marker(trimmed).is_some() {
+            let (list, next) = parse_list(lines, i);
+            blocks.push(list);
+            i = next;
+            continue;
+        }
+
+        if trimmed.contains('|') && lines.get(i + 1).is_some_and(|line| is_table_sep(line)) {
+            let header = split_row(trimmed);
+            let mut rows = vec![];
+            i += 2;
+            while i < lines.len() && lines[i].contains('|') && !lines[i].trim().is_empty() {
+                rows.push(split_row(lines[i].trim()));
+                i += 1;
+            }
+            blocks.push(Block::Table {
+                header: Some(header),
+                rows,
+            });
+            continue;
+        }
+
+        let mut para = vec![trimmed];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i].trim_start();
+            if let Some(level) = setext_level(line) {
+                blocks.push(Block::Heading(level, parse_inlines(&para.join("\n"))));
+                para.clear();
+                i += 1;
+                break;
+            }
+            if line.is_empty() || starts_block(line) {
+                break;
+            }
+            para.push(line);
+            i += 1;
+        }
+        if !para.is_empty() {
+            let content = trim_inlines(parse_inlines(&para.join("\n")));
+            blocks.push(Block::Paragraph(content));
+        }
+    }
+
+    blocks
+}
+
+/// Parses a list starting at the given line, returning the list and the
+/// index of the line after it.
+fn parse_list(lines: &[&str], start: usize) -> (Block, usize) {
+    let base = indent_of(lines[start]);
+    let (ordered, _) = list_marker(lines[start].trim_start()).unwrap_or_default();
+
+    let mut items = vec![];
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        let Some((item_ordered, marker_len)) = list_marker(trimmed) else {
+            break;
+        };
+        if item_ordered != ordered || indent_of(line).abs_diff(base) >= 2 {
+            break;
+        }
+        let content_offset = indent_of(line) + marker_len;
+
+        let mut item = vec![&trimmed[marker_len..]];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.trim().is_empty() {
+                let continued = lines[i + 1..]
+                    .iter()
+                    .find(|line| !line.trim().is_empty())
+                    .is_some_and(|line| indent_of(line) >= content_offset);
+                if !continued {
+                    break;
+                }
+                item.push("");
+            } else if indent_of(line) >= content_offset {
+                item.push(strip_indent(line, content_offset));
+            } else if list_marker(line.trim_start()).is_some() || starts_block(line.trim_start()) {
+                break;
+            } else {
+                // A lazy continuation line of the paragraph.
+                item.push(line.trim_start());
+            }
+            i += 1;
+        }
+        items.push(parse_blocks(&item));
+    }
+
+    (Block::List { ordered, items }, i)
+}
+
+/// Gets whether a list item is ordered and the length of its marker,
+/// including the following spaces.
+fn list_marker(line: &str) -> Option<(bool, usize)> {
+    let (ordered, marker) = match line.as_bytes().first()? {
+        b'-' | b'*' | b'+' => (false, 1),
+        b'0'..=b'9' => {
+            let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+            if !matches!(line.as_bytes().get(digits), Some(b'.' | b')')) {
+                return None;
+            }
+            (true, digits + 1)
+        }
+        _ => return None,
+    };
+
+    let rest = &line[marker..];
+    if rest.is_empty() {
+        return Some((ordered, marker));
+    }
+    let spaces = rest.len() - rest.trim_start_matches(' ').len();
+    (spaces > 0).then_some((ordered, marker + spaces.min(4)))
+}
+
+fn code_fence(line: &str) -> Option<&'static str> {
+    if line.starts_with("```") {
+        Some("```")
+    } else if line.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let content = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, content))
+}
+
+fn setext_level(line: &str) -> Option<usize> {
+    let line = line.trim_end();
+    if line.is_empty() {
+        None
+    } else if line.bytes().all(|b| b == b'=') {
+        Some(1)
+    } else if line.bytes().all(|b| b == b'-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim_end();
+    ['-', '*', '_'].iter().any(|&ch| {
+        line.chars().filter(|&c| c == ch).count() >= 3 && line.chars().all(|c| c == ch || c == ' ')
+    })
+}
+
+fn is_table_sep(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-')
+        && line.contains(['|', ':'])
+        && line.chars().all(|c| matches!(c, '|' | ':' | '-' | ' '))
+}
+
+fn split_row(line: &str) -> Vec<Vec<Inline>> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    if next != '|' {
+                        cell.push('\\');
+                    }
+                    cell.push(next);
+                }
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(ch),
+        }
+    }
+    cells.push(cell);
+
+    cells
+        .iter()
+        .map(|cell| trim_inlines(parse_inlines(cell.trim())))
+        .collect()
+}
+
+/// Checks whether a line interrupts a paragraph.
+fn starts_block(line: &str) -> bool {
+    code_fence(line).is_some()
+        || atx_heading(line).is_some()
+        || is_rule(line)
+        || line.starts_with('>')
+        || matches!(list_marker(line), Some((false, _)))
+        || line.starts_with("1.")
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = indent_of(line).min(indent);
+    &line[spaces..]
+}
+
+/// Parses the inline elements of a text.
+fn parse_inlines(text: &str) -> Vec<Inline> {
+    let mut inlines = vec![];
+    let mut buf = String::new();
+    let mut i = 0;
+
+    macro_rules! flush {
+        () => {
+            if !buf.is_empty() {
+                inlines.push(Inline::Text(std::mem::take(&mut buf)));
+            }
+        };
+    }
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let ch = rest.chars().next().unwrap_or_default();
+        match ch {
+            '\\' => {
+                let next = rest[1..].chars().next();
+                match next {
+                    Some('\n') => {
+                        flush!();
+                        inlines.push(Inline::LineBreak);
+                        i += 2;
+                    }
+                    Some(next) if next.is_ascii_punctuation() => {
+                        buf.push(next);
+                        i += 2;
+                    }
+                    _ => {
+                        buf.push('\\');
+                        i += 1;
+                    }
+                }
+                continue;
+            }
+            '\n' if buf.ends_with("  ") => {
+                buf.truncate(buf.trim_end_matches(' ').len());
+                flush!();
+                inlines.push(Inline::LineBreak);
+                i += 1;
+                continue;
+            }
+            '`' => {
+                let ticks = rest.bytes().take_while(|&b| b == b'`').count();
+                let fence = &rest[..ticks];
+                if let Some(end) = rest[ticks..].find(fence) {
+                    let code = &rest[ticks..ticks + end];
+                    let code = match code.strip_prefix(' ').and_then(|it| it.strip_suffix(' ')) {
+                        Some(inner) if !inner.trim().is_empty() => inner,
+                        _ => code,
+                    };
+                    flush!();
+                    inlines.push(Inline::Code(code.replace('\n', " ")));
+                    i += ticks + end + ticks;
+                    continue;
+                }
+                buf.push_str(fence);
+                i += ticks;
+                continue;
+            }
+            '!' if rest.starts_with("![") => {
+                if let Some((alt, url, len)) = parse_link(&rest[1..]) {
+                    flush!();
+                    inlines.push(Inline::Image {
+                        src: url.to_owned(),
+                        alt: alt.to_owned(),
+                    });
+                    i += 1 + len;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((content, url, len)) = parse_link(rest) {
+                    flush!();
+                    inlines.push(Inline::Link {
+                        url: url.to_owned(),
+                        content: parse_inlines(content),
+                    });
+                    i += len;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(end) = rest.find('>') {
+                    let url = &rest[1..end];
+                    let is_url = ["http://", "https://", "mailto:"]
+                        .iter()
+                        .any(|scheme| url.starts_with(scheme))
+                        && !url.contains(char::is_whitespace);
+                    if is_url {
+                        flush!();
+                        inlines.push(Inline::Link {
+                            url: url.to_owned(),
+                            content: vec![],
+                        });
+                        i += end + 1;
+                        continue;
+                    }
+                }
+            }
+            '*' | '_' => {
+                if let Some((inline, len)) = parse_emphasis(text, i) {
+                    flush!();
+                    inlines.push(inline);
+                    i += len;
+                    continue;
+                }
+                let run = rest.bytes().take_while(|&b| b == ch as u8).count();
+                buf.push_str(&rest[..run]);
+                i += run;
+                continue;
+            }
+            _ => {}
+        }
+
+        buf.push(ch);
+        i += ch.len_utf8();
+    }
+    flush!();
+
+    inlines
+}
+
+/// Parses a link like `[content](url "title")`, returning the content, the
+/// url and the length of the link.
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let mut depth = 0;
+    let mut content_end = None;
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    content_end = Some(idx);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let content_end = content_end?;
+    let rest = text[content_end + 1..].strip_prefix('(')?;
+    let dest_end = rest.find(')')?;
+    let dest = rest[..dest_end].trim();
+    let url = dest.split_whitespace().next().unwrap_or_default();
+    let url = url
+        .strip_prefix('<')
+        .and_then(|url| url.strip_suffix('>'))
+        .unwrap_or(url);
+
+    Some((&text[1..content_end], url, content_end + 2 + dest_end + 1))
+}
+
+/// Parses an emphasis starting at the given position, returning the element
+/// and its length.
+fn parse_emphasis(text: &str, start: usize) -> Option<(Inline, usize)> {
+    let rest = &text[start..];
+    let ch = rest.chars().next()?;
+    let run = rest.bytes().take_while(|&b| b == ch as u8).count().min(3);
+    let delim = &rest[..run];
+
+    // Underscores inside words are not delimiters.
+    let prev = text[..start].chars().next_back();
+    if ch == '_' && prev.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    if rest[run..].starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let mut search = run;
+    let end = loop {
+        let found = search + rest[search..].find(delim)?;
+        let before = rest[..found].chars().next_back();
+        let after = rest[found + run..].chars().next();
+        let closes = found > run
+            && before.is_some_and(|c| !c.is_whitespace() && c != ch)
+            && after != Some(ch)
+            && !(ch == '_' && after.is_some_and(char::is_alphanumeric));
+        if closes {
+            break found;
+        }
+        search = found + 1;
+    };
+
+    let content = parse_inlines(&rest[run..end]);
+    let inline = match run {
+        1 => Inline::Emph(content),
+        2 => Inline::Strong(content),
+        _ => Inline::Strong(vec![Inline::Emph(content)]),
+    };
+    Some((inline, end + run))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_markdown() {
+        let markdown = r#"# Title
+
+Some *emphasis*, **strong** and `code` with [a link](https://typst.app).
+
+- one
+- two
+  1. nested
+
+| a | b |
+|---|:-:|
+| 1 | 2 |
+
+```rust
+fn main() {}
+```
+"#;
+        assert_eq!(
+            markdown_to_typst(markdown),
+            r#"= Title
+
+Some _emphasis_, *strong* and `code` with #link("https://typst.app")[a link].
+
+- one
+- two
+  + nested
+
+#table(
+  columns: 2,
+  table.header([a], [b]),
+  [1], [2],
+)
+
+```rust
+fn main() {}
+```"#
+        );
+    }
+}
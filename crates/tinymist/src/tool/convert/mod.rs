@@ -0,0 +1,365 @@
+//! Converts HTML and Markdown into Typst markup, e.g. when pasting rich text
+//! into a document, and LaTeX math into Typst math.
+//!
+//! Only the common structures are converted, i.e. headings, paragraphs,
+//! emphasis, lists, tables, links, images, raw code and quotes. The other
+//! elements are flattened into their text content.
+
+mod html;
+mod latex;
+mod markdown;
+
+pub use html::html_to_typst;
+pub use latex::{convert_latex_main, latex_math_to_typst, ConvertLatexArgs};
+pub use markdown::markdown_to_typst;
+
+use typst::foundations::{Repr, Str};
+
+/// A block of the converted document.
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    /// A heading with its level.
+    Heading(usize, Vec<Inline>),
+    /// A paragraph.
+    Paragraph(Vec<Inline>),
+    /// A bullet or numbered list.
+    List {
+        ordered: bool,
+        items: Vec<Vec<Block>>,
+    },
+    /// A table with an optional header row.
+    Table {
+        header: Option<Vec<Vec<Inline>>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    /// A raw block.
+    Code { lang: Option<String>, code: String },
+    /// A block quote.
+    Quote(Vec<Block>),
+    /// A horizontal rule.
+    Rule,
+}
+
+/// An inline element of the converted document.
+#[derive(Debug, Clone, PartialEq)]
+enum Inline {
+    Text(String),
+    Emph(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Code(String),
+    Link { url: String, content: Vec<Inline> },
+    Image { src: String, alt: String },
+    LineBreak,
+}
+
+/// Writes the blocks as Typst markup.
+fn write_typst(blocks: &[Block]) -> String {
+    let mut writer = TypstWriter::default();
+    writer.blocks(blocks, "\n\n");
+    writer.out
+}
+
+#[derive(Default)]
+struct TypstWriter {
+    out: String,
+    /// Whether the last written element is an embedded code expression, which
+    /// would be continued by a following `(`, `[` or field access.
+    after_code: bool,
+}
+
+impl TypstWriter {
+    fn blocks(&mut self, blocks: &[Block], sep: &str) {
+        for (idx, block) in blocks.iter().enumerate() {
+            if idx > 0 {
+                self.push(sep);
+            }
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Heading(level, content) => {
+                self.push(&"=".repeat((*level).clamp(1, 6)));
+                self.push(" ");
+                self.inlines(content);
+            }
+            Block::Paragraph(content) => self.inlines(content),
+            Block::List { ordered, items } => {
+                let marker = if *ordered { "+ " } else { "- " };
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        self.push("\n");
+                    }
+                    let mut inner = TypstWriter::default();
+                    inner.blocks(item, "\n");
+                    self.push(marker);
+                    self.push(&indent(&inner.out, "  "));
+                }
+            }
+            Block::Table { header, rows } => {
+                let columns = header
+                    .iter()
+                    .chain(rows)
+                    .map(|row| row.len())
+                    .max()
+                    .unwrap_or(1);
+                self.push(&format!("#table(\n  columns: {columns},\n"));
+                if let Some(header) = header {
+                    self.push("  table.header(");
+                    self.cells(header);
+                    self.push("),\n");
+                }
+                for row in rows {
+                    self.push("  ");
+                    self.cells(row);
+                    self.push(",\n");
+                }
+                self.push(")");
+                self.after_code = true;
+            }
+            Block::Code { lang, code } => {
+                let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+                self.push(&fence);
+                if let Some(lang) = lang {
+                    self.push(lang);
+                }
+                self.push("\n");
+                self.push(code.trim_end_matches('\n'));
+                self.push("\n");
+                self.push(&fence);
+            }
+            Block::Quote(content) => {
+                let mut inner = TypstWriter::default();
+                inner.blocks(content, "\n\n");
+                self.push("#quote(block: true)[\n");
+                self.push(&indent(&format!("  {}", inner.out), "  "));
+                self.push("\n]");
+                self.after_code = true;
+            }
+            Block::Rule => {
+                self.push("#line(length: 100%)");
+                self.after_code = true;
+            }
+        }
+    }
+
+    fn cells(&mut self, cells: &[Vec<Inline>]) {
+        for (idx, cell) in cells.iter().enumerate() {
+            if idx > 0 {
+                self.push(", ");
+            }
+            let mut inner = TypstWriter::default();
+            inner.inlines(cell);
+            self.push(&format!("[{}]", inner.out.trim()));
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for (idx, inline) in inlines.iter().enumerate() {
+            let next = inlines.get(idx + 1).and_then(first_char);
+            self.inline(inline, next);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline, next: Option<char>) {
+        match inline {
+            Inline::Text(text) => self.text(text),
+            Inline::Emph(content) => self.delimited("_", "emph", content, next),
+            Inline::Strong(content) => self.delimited("*", "strong", content, next),
+            Inline::Code(code) => {
+                if code.contains('`') || code.is_empty() {
+                    self.code(&format!("#raw({})", Str::from(code.as_str()).repr()));
+                } else {
+                    self.push(&format!("`{code}`"));
+                }
+            }
+            Inline::Link { url, content } => {
+                let url_lit = Str::from(url.as_str()).repr();
+                let is_bare = match content.as_slice() {
+                    [] => true,
+                    [Inline::Text(text)] => text.trim() == url,
+                    _ => false,
+                };
+                if is_bare {
+                    self.code(&format!("#link({url_lit})"));
+                } else {
+                    self.push(&format!("#link({url_lit})["));
+                    self.inlines(content);
+                    self.code("]");
+                }
+            }
+            Inline::Image { src, alt } => {
+                let src = Str::from(src.as_str()).repr();
+                if alt.is_empty() {
+                    self.code(&format!("#image({src})"));
+                } else {
+                    let alt = Str::from(alt.as_str()).repr();
+                    self.code(&format!("#image({src}, alt: {alt})"));
+                }
+            }
+            Inline::LineBreak => self.push("\\\n"),
+        }
+    }
+
+    /// Writes strong or emphasized content with the shorthand delimiters, or
+    /// with the function if the delimiters would be inside a word.
+    fn delimited(&mut self, delim: &str, func: &str, content: &[Inline], next: Option<char>) {
+        let in_word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+        if in_word(self.out.chars().next_back()) || in_word(next) {
+            self.push(&format!("#{func}["));
+            self.inlines(content);
+            self.code("]");
+        } else {
+            self.push(delim);
+            self.inlines(content);
+            self.push(delim);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let next = chars.peek().copied();
+            let line_start = self.out.is_empty() || self.out.ends_with('\n');
+
+            let escape = match ch {
+                '\\' | '*' | '_' | '`' | '#' | '$' | '@' | '<' | '>' | '[' | ']' | '~' => true,
+                '/' => {
+                    matches!(next, Some('/' | '*'))
+                        || (line_start && matches!(next, Some(' ') | None))
+                }
+                '(' => self.after_code,
+                '.' => self.after_code && next.is_some_and(char::is_alphabetic),
+                '=' | '-' | '+' if line_start => matches!(next, Some(' ') | None),
+                _ if line_start && ch.is_ascii_digit() => {
+                    // Escapes the dot of an enumeration marker like `1.`.
+                    self.out.push(ch);
+                    self.after_code = false;
+                    while let Some(digit) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                        self.out.push(digit);
+                    }
+                    if chars.peek() == Some(&'.') {
+                        self.out.push('\\');
+                    }
+                    continue;
+                }
+                _ => false,
+            };
+            if escape {
+                self.out.push('\\');
+            }
+            self.out.push(ch);
+            self.after_code = false;
+        }
+    }
+
+    /// Writes an embedded code expression.
+    fn code(&mut self, code: &str) {
+        self.push(code);
+        self.after_code = true;
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.after_code = false;
+    }
+}
+
+/// Gets the first character of an inline element.
+fn first_char(inline: &Inline) -> Option<char> {
+    match inline {
+        Inline::Text(text) => text.chars().next(),
+        Inline::Emph(content) | Inline::Strong(content) => content.first().and_then(first_char),
+        _ => None,
+    }
+}
+
+/// Indents all lines except the first one.
+fn indent(text: &str, prefix: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for (idx, line) in text.split('\n').enumerate() {
+        if idx > 0 {
+            res.push('\n');
+            if !line.is_empty() {
+                res.push_str(prefix);
+            }
+        }
+        res.push_str(line);
+    }
+    res
+}
+
+/// Gets the length of the longest run of a character.
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == ch {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// Collapses the whitespace in a text into single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut in_space = false;
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            if !in_space {
+                res.push(' ');
+            }
+            in_space = true;
+        } else {
+            res.push(ch);
+            in_space = false;
+        }
+    }
+    res
+}
+
+/// Trims the whitespace around inline elements.
+fn trim_inlines(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().to_owned();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().to_owned();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+    inlines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_text() {
+        let blocks = vec![
+            Block::Paragraph(vec![Inline::Text("1. a *b* #c // d".to_owned())]),
+            Block::Paragraph(vec![
+                Inline::Text("in".to_owned()),
+                Inline::Strong(vec![Inline::Text("word".to_owned())]),
+                Inline::Text(" and ".to_owned()),
+                Inline::Emph(vec![Inline::Text("free".to_owned())]),
+            ]),
+            Block::Paragraph(vec![
+                Inline::Link {
+                    url: "https://typst.app".to_owned(),
+                    content: vec![],
+                },
+                Inline::Text("(home)".to_owned()),
+            ]),
+        ];
+        assert_eq!(
+            write_typst(&blocks),
+            "1\\. a \\*b\\* \\#c \\// d\n\nin#strong[word] and _free_\n\n#link(\"https://typst.app\")\\(home)"
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! Lists the fonts available to the compiler, e.g. for finding out why a font
+//! requested by a document is not used.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use clap::ValueHint;
+use reflexo::ImmutPath;
+use reflexo_typst::debug_loc::DataSource;
+use reflexo_typst::WorldComputeGraph;
+use serde::Serialize;
+use tinymist_std::error::prelude::*;
+use tinymist_std::typst::TypstDocument;
+use typst::layout::{Frame, FrameItem};
+use typst::text::{FontStretch, FontStyle, FontWeight};
+
+use crate::project::*;
+use crate::world::font::{FontResolver, FontResolverImpl};
+use crate::{CompileFontArgs, CompilePackageArgs};
+
+/// The format to print the fonts in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FontsFormat {
+    /// Prints the fonts in a human-readable list.
+    #[default]
+    Human,
+    /// Prints the fonts as JSON.
+    Json,
+}
+
+/// Arguments for listing the fonts.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct FontsArgs {
+    /// Common font arguments.
+    #[clap(flatten)]
+    pub font: CompileFontArgs,
+
+    /// Lists the variants of the families along with their files.
+    #[clap(long)]
+    pub variants: bool,
+
+    /// Explains why a font family is not used, e.g. `--verify "Inter"`.
+    #[clap(long, value_name = "FAMILY")]
+    pub verify: Option<String>,
+
+    /// The document requesting the family to verify, which is compiled to
+    /// check whether the family is used.
+    #[clap(requires = "verify", value_hint = ValueHint::FilePath)]
+    pub input: Option<String>,
+
+    /// Configures the project root (for absolute paths).
+    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
+    pub root: Option<String>,
+
+    /// Common package arguments.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+
+    /// The format to print the fonts in.
+    #[clap(long, value_enum, default_value = "human")]
+    pub format: FontsFormat,
+}
+
+/// Where a font is discovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FontOrigin {
+    /// The font is embedded in the binary.
+    Embedded,
+    /// The font is found in the paths given by `--font-path`.
+    CustomPath,
+    /// The font is installed on the system.
+    System,
+}
+
+impl FontOrigin {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Embedded => "embedded",
+            Self::CustomPath => "custom path",
+            Self::System => "system",
+        }
+    }
+}
+
+/// A variant of a font family.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontVariantItem {
+    pub style: FontStyle,
+    pub weight: FontWeight,
+    pub stretch: FontStretch,
+    /// The file containing the font, if it is not embedded.
+    pub path: Option<String>,
+    pub origin: FontOrigin,
+}
+
+/// A font family.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontFamilyItem {
+    pub family: String,
+    pub variants: Vec<FontVariantItem>,
+}
+
+/// The explanation of whether a font family is used.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontVerification {
+    /// The requested family.
+    pub family: String,
+    /// The available family matching the requested one, which is matched
+    /// case-insensitively by Typst.
+    pub found: Option<FontFamilyItem>,
+    /// Whether the document uses the family, if a document is given.
+    pub used: Option<bool>,
+    /// The reasons why the family may be not used.
+    pub reasons: Vec<String>,
+}
+
+/// Lists the fonts or verifies a font family.
+pub fn fonts_main(args: FontsArgs) -> Result<()> {
+    let lock_dir: ImmutPath = std::env::current_dir().context("lock directory")?.into();
+
+    // The document is compiled with the fonts, so that the fonts are resolved
+    // once.
+    let (fonts, compiled) = match &args.input {
+        Some(input) => {
+            let input = DocNewArgs {
+                id: DocIdArgs {
+                    name: None,
+                    input: input.clone(),
+                },
+                root: args.root.clone(),
+                font: args.font.clone(),
+                package: args.package.clone(),
+            }
+            .to_input();
+            let verse = (input, lock_dir).resolve()?;
+            let graph = WorldComputeGraph::from_world(verse.snapshot());
+            let compiled = CompiledArtifact::from_graph(graph, false);
+            (compiled.world().font_resolver.clone(), Some(compiled))
+        }
+        None => {
+            let fonts = LspUniverseBuilder::resolve_fonts(args.font.clone())?;
+            (Arc::new(fonts), None)
+        }
+    };
+
+    let Some(family) = &args.verify else {
+        let families = font_families(&fonts);
+        match args.format {
+            FontsFormat::Human => print_families(&families, args.variants),
+            FontsFormat::Json => {
+                let json = serde_json::to_string_pretty(&families).context("serialize fonts")?;
+                println!("{json}");
+            }
+        }
+        return Ok(());
+    };
+
+    let verification = verify_family(&fonts, family, &args.font, compiled.as_ref())?;
+    match args.format {
+        FontsFormat::Human => {
+            match &verification.found {
+                Some(found) => print_families(std::slice::from_ref(found), true),
+                None => println!("no font of the family `{family}` is found"),
+            }
+            match verification.used {
+                Some(true) => println!("the family is used by the document"),
+                Some(false) => println!("the family is not used by the document"),
+                None => {}
+            }
+            for reason in &verification.reasons {
+                println!("- {reason}");
+            }
+        }
+        FontsFormat::Json => {
+            let json = serde_json::to_string_pretty(&verification).context("serialize fonts")?;
+            println!("{json}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Collects the font families in the order of the font book.
+fn font_families(fonts: &FontResolverImpl) -> Vec<FontFamilyItem> {
+    let book = fonts.font_book();
+    book.families()
+        .map(|(family, _)| family_item(fonts, family))
+        .collect()
+}
+
+fn family_item(fonts: &FontResolverImpl, family: &str) -> FontFamilyItem {
+    let book = fonts.font_book();
+    let variants = book.select_family(&family.to_lowercase()).flat_map(|id| {
+        let info = book.info(id)?;
+        let (path, origin) = match fonts.describe_font_by_id(id).as_deref() {
+            Some(DataSource::Fs(fs)) => {
+                let path = Path::new(&fs.path);
+                let is_custom = fonts.font_paths().iter().any(|dir| path.starts_with(dir));
+                let origin = if is_custom {
+                    FontOrigin::CustomPath
+                } else {
+                    FontOrigin::System
+                };
+                (Some(fs.path.clone()), origin)
+            }
+            _ => (None, FontOrigin::Embedded),
+        };
+
+        Some(FontVariantItem {
+            style: info.variant.style,
+            weight: info.variant.weight,
+            stretch: info.variant.stretch,
+            path,
+            origin,
+        })
+    });
+
+    FontFamilyItem {
+        family: family.to_owned(),
+        variants: variants.collect(),
+    }
+}
+
+fn print_families(families: &[FontFamilyItem], variants: bool) {
+    for family in families {
+        let origins = family.variants.iter().map(|v| v.origin);
+        let origins = origins.collect::<BTreeSet<_>>();
+        let origins = origins.into_iter().map(FontOrigin::name);
+        println!(
+            "{} ({} variant(s), {})",
+            family.family,
+            family.variants.len(),
+            origins.collect::<Vec<_>>().join(", ")
+        );
+        if !variants {
+            continue;
+        }
+
+        for variant in &family.variants {
+            let style = format!("{:?}", variant.style).to_lowercase();
+            let weight = variant.weight.to_number();
+            let stretch = variant.stretch.to_ratio();
+            let source = variant.path.as_deref().unwrap_or("embedded");
+            println!("  {style} {weight} {stretch:?}: {source}");
+        }
+    }
+}
+
+/// Explains why a font family is not used, or whether it is used by the
+/// document.
+fn verify_family(
+    fonts: &FontResolverImpl,
+    family: &str,
+    font_args: &CompileFontArgs,
+    compiled: Option<&LspCompiledArtifact>,
+) -> Result<FontVerification> {
+    let lower = family.to_lowercase();
+    let book = fonts.font_book();
+    let found = book
+        .families()
+        .find(|(name, _)| name.to_lowercase() == lower)
+        .map(|(name, _)| family_item(fonts, name));
+
+    let mut reasons = vec![];
+    if found.is_none() {
+        reasons.extend(missing_family_reasons(fonts, &lower, font_args)?);
+    }
+
+    let used = compiled.map(|compiled| {
+        let used = match &compiled.doc {
+            Some(TypstDocument::Paged(doc)) => {
+                let mut used = BTreeSet::new();
+                for page in &doc.pages {
+                    used_families(&page.frame, &mut used);
+                }
+                used.contains(&lower)
+            }
+            _ => false,
+        };
+
+        if found.is_some() && !used {
+            reasons.extend(unused_family_reasons(compiled, &lower));
+        }
+        used
+    });
+
+    Ok(FontVerification {
+        family: family.to_owned(),
+        found,
+        used,
+        reasons,
+    })
+}
+
+/// Explains why a family is not found.
+fn missing_family_reasons(
+    fonts: &FontResolverImpl,
+    lower: &str,
+    font_args: &CompileFontArgs,
+) -> Result<Vec<String>> {
+    let mut reasons = vec![];
+
+    // A common mistake is to request the full name or the PostScript name of a
+    // font, e.g. `Inter Bold`, instead of its family.
+    let names = [
+        ttf_parser::name_id::FULL_NAME,
+        ttf_parser::name_id::POST_SCRIPT_NAME,
+    ];
+    let mut named = BTreeSet::new();
+    for (idx, (info, _)) in fonts.fonts().enumerate() {
+        let is_candidate = lower
+            .split([' ', '-'])
+            .next()
+            .is_some_and(|word| info.family.to_lowercase().contains(word));
+        if !is_candidate {
+            continue;
+        }
+        let Some(font) = fonts.font(idx) else {
+            continue;
+        };
+        if names.iter().any(|id| {
+            font.find_name(*id)
+                .is_some_and(|name| name.to_lowercase() == lower)
+        }) {
+            named.insert(info.family.to_lowercase());
+        }
+    }
+    for family in book_families(fonts).filter(|family| named.contains(&family.to_lowercase())) {
+        reasons.push(format!(
+            "`{lower}` is the name of a font of the family `{family}`, which should be requested by the family along with the `weight`, `style` or `stretch` of the text"
+        ));
+    }
+
+    let similar = book_families(fonts)
+        .filter(|family| {
+            let family = family.to_lowercase();
+            !named.contains(&family) && (family.contains(lower) || lower.contains(&family))
+        })
+        .collect::<Vec<_>>();
+    if !similar.is_empty() {
+        reasons.push(format!(
+            "similar families are available: {}",
+            similar.join(", ")
+        ));
+    }
+
+    if font_args.ignore_system_fonts {
+        let system = LspUniverseBuilder::resolve_fonts(CompileFontArgs {
+            font_paths: vec![],
+            ignore_system_fonts: false,
+        })?;
+        if book_families(&system).any(|family| family.to_lowercase() == lower) {
+            reasons.push(
+                "the family is installed on the system, but the system fonts are ignored by `--ignore-system-fonts`".to_owned(),
+            );
+        }
+    }
+
+    if reasons.is_empty() {
+        let paths = fonts
+            .font_paths()
+            .iter()
+            .map(|path| path.display().to_string());
+        let paths = paths.collect::<Vec<_>>();
+        reasons.push(if paths.is_empty() {
+            "the family is not installed on the system, consider adding the directory containing the font files by `--font-path`".to_owned()
+        } else {
+            format!(
+                "the family is neither installed on the system nor found in the font paths {}",
+                paths.join(", ")
+            )
+        });
+    }
+
+    Ok(reasons)
+}
+
+fn book_families(fonts: &FontResolverImpl) -> impl Iterator<Item = String> + '_ {
+    let book = fonts.font_book();
+    book.families().map(|(family, _)| family.to_owned())
+}
+
+/// Explains why an available family is not used by the document.
+fn unused_family_reasons(compiled: &LspCompiledArtifact, lower: &str) -> Vec<String> {
+    let mut reasons = vec![];
+    if compiled.has_errors() {
+        reasons.push("the document fails to compile".to_owned());
+        return reasons;
+    }
+
+    let world = compiled.world();
+    let requested = world.depended_files().into_iter().any(|fid| {
+        let is_source = fid.vpath().as_rooted_path().extension() == Some("typ".as_ref());
+        is_source
+            && typst::World::source(world, fid)
+                .is_ok_and(|source| source.text().to_lowercase().contains(lower))
+    });
+    if !requested {
+        reasons.push("the family is not mentioned in the source files of the document".to_owned());
+        return reasons;
+    }
+
+    reasons.push(
+        "the family is mentioned in the source files, but no text is shaped by it, which happens when:".to_owned(),
+    );
+    reasons.push(
+        "  the `set text(font: ..)` rule requesting it is overridden by a later rule or only applies to an empty part of the document".to_owned(),
+    );
+    reasons.push(
+        "  the families listed before it in the `font` array cover all the characters of the text"
+            .to_owned(),
+    );
+    reasons.push(
+        "  it lacks the characters of the text, e.g. the glyphs of the script, so other families are used as fallbacks".to_owned(),
+    );
+    reasons.push(
+        "  it is requested for equations by `set text(font: ..)`, while the math font is set by `show math.equation: set text(font: ..)` and must contain a MATH table".to_owned(),
+    );
+    reasons
+}
+
+/// Collects the lowercase families of the fonts shaping the texts in a frame.
+fn used_families(frame: &Frame, used: &mut BTreeSet<String>) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => used_families(&group.frame, used),
+            FrameItem::Text(text) => {
+                used.insert(text.font.info().family.to_lowercase());
+            }
+            _ => {}
+        }
+    }
+}
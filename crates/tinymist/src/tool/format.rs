@@ -0,0 +1,151 @@
+//! Formats the Typst files with the formatter configured in the workspace, e.g.
+//! for checking the formatting in CI.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueHint;
+use serde_json::{Map, Value as JsonValue};
+use tinymist_std::error::prelude::*;
+use typst::syntax::Source;
+
+use crate::{Config, FormatterMode};
+
+/// Arguments for formatting the Typst files.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct FormatArgs {
+    /// The files or directories to format. Formats the stdin to the stdout if
+    /// no path is given.
+    #[clap(value_hint = ValueHint::AnyPath)]
+    pub paths: Vec<PathBuf>,
+
+    /// Checks that the files are formatted without writing them, exiting with
+    /// a nonzero code if any of them is not.
+    #[clap(long)]
+    pub check: bool,
+
+    /// The formatter to use, overriding the `formatterMode` in
+    /// `tinymist.toml`. Defaults to `typstyle`.
+    #[clap(long, value_parser = ["typstyle", "typstfmt"])]
+    pub mode: Option<String>,
+
+    /// The maximum width of the lines, overriding the `formatterPrintWidth` in
+    /// `tinymist.toml`.
+    #[clap(long)]
+    pub print_width: Option<u32>,
+
+    /// The number of spaces of an indent, overriding the
+    /// `formatterIndentSize` in `tinymist.toml`.
+    #[clap(long)]
+    pub indent_size: Option<u32>,
+
+    /// Whether to wrap the prose, overriding the `formatterProseWrap` in
+    /// `tinymist.toml`.
+    #[clap(long)]
+    pub prose_wrap: Option<bool>,
+}
+
+/// Formats the Typst files or the stdin.
+pub fn format_main(args: FormatArgs) -> Result<()> {
+    let cwd = std::env::current_dir().context("current directory")?;
+
+    // The options in the command line take precedence over the ones in the
+    // `tinymist.toml` of the current directory.
+    let mut update = Map::new();
+    let mut option = |key: &str, value: Option<JsonValue>| {
+        if let Some(value) = value {
+            update.insert(key.to_owned(), value);
+        }
+    };
+    option("formatterMode", args.mode.map(JsonValue::from));
+    option("formatterPrintWidth", args.print_width.map(JsonValue::from));
+    option("formatterIndentSize", args.indent_size.map(JsonValue::from));
+    option("formatterProseWrap", args.prose_wrap.map(JsonValue::from));
+
+    let mut config = Config::default();
+    config.entry_resolver.roots = vec![cwd.as_path().into()];
+    config.update_by_map(&update)?;
+    for warning in &config.warnings {
+        eprintln!("warning: {warning}");
+    }
+    if config.formatter_mode == FormatterMode::Disable {
+        config.formatter_mode = FormatterMode::Typstyle;
+    }
+    let formatter = config.formatter().config;
+
+    if args.paths.is_empty() {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("read stdin")?;
+        let formatted = formatter
+            .format(Source::detached(text.clone()))
+            .context("cannot format the stdin, which may have syntax errors")?;
+        if args.check {
+            if formatted != text {
+                bail!("the stdin is not formatted");
+            }
+        } else {
+            std::io::stdout()
+                .write_all(formatted.as_bytes())
+                .context("write stdout")?;
+        }
+        return Ok(());
+    }
+
+    let files = args.paths.iter().flat_map(|path| typst_files(path));
+    let (mut total, mut unformatted, mut failed) = (0, 0, 0);
+    for path in files {
+        total += 1;
+        let text = std::fs::read_to_string(&path).context("read file")?;
+        let Some(formatted) = formatter.format(Source::detached(text.clone())) else {
+            eprintln!(
+                "{}: cannot format, which may have syntax errors",
+                path.display()
+            );
+            failed += 1;
+            continue;
+        };
+        if formatted == text {
+            continue;
+        }
+
+        unformatted += 1;
+        if args.check {
+            eprintln!("{}: not formatted", path.display());
+        } else {
+            std::fs::write(&path, formatted).context("write file")?;
+            eprintln!("{}: formatted", path.display());
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {total} files cannot be formatted");
+    }
+    if args.check && unformatted > 0 {
+        bail!("{unformatted} of {total} files are not formatted");
+    }
+
+    Ok(())
+}
+
+/// Collects the Typst files of a path, which are the path itself if it is a
+/// file, or the Typst files in it if it is a directory.
+pub(crate) fn typst_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_owned()];
+    }
+
+    let mut files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "typ"))
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
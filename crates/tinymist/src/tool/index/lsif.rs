@@ -0,0 +1,163 @@
+//! Writes the index in the [LSIF](https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/)
+//! format, which is a graph of vertices and edges in JSON lines.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::{json, Value as JsonValue};
+use tinymist_query::code_index::CodeIndex;
+use tinymist_std::error::prelude::*;
+
+/// Writes the index as an LSIF dump.
+pub(crate) fn write_lsif(index: &CodeIndex, root: &Path) -> Result<Vec<u8>> {
+    let root_uri = tinymist_query::path_to_url(root).context("project root")?;
+
+    let mut dump = LsifDump::default();
+    dump.vertex(
+        "metaData",
+        json!({
+            "version": "0.6.0",
+            "projectRoot": root_uri.as_str(),
+            "positionEncoding": "utf-16",
+            "toolInfo": { "name": "tinymist", "version": env!("CARGO_PKG_VERSION") },
+        }),
+    );
+    let project = dump.vertex("project", json!({ "kind": "typst" }));
+
+    // The result sets shared by the occurrences of each symbol.
+    let mut result_sets = vec![];
+    for symbol in &index.symbols {
+        let result_set = dump.vertex("resultSet", json!({}));
+        if let Some(docs) = &symbol.docs {
+            let hover = dump.vertex(
+                "hoverResult",
+                json!({ "result": { "contents": { "kind": "markdown", "value": docs } } }),
+            );
+            dump.edge("textDocument/hover", result_set, hover);
+        }
+        if !symbol.is_local {
+            let moniker = dump.vertex(
+                "moniker",
+                json!({ "scheme": "tinymist", "identifier": symbol.id, "kind": "export" }),
+            );
+            dump.edge("moniker", result_set, moniker);
+        }
+        result_sets.push(result_set);
+    }
+
+    // The ranges of the definitions and references by symbol and document.
+    let mut definitions = HashMap::<usize, Vec<(u64, u64)>>::new();
+    let mut references = HashMap::<usize, Vec<(u64, u64)>>::new();
+    let mut documents = vec![];
+    for doc in &index.documents {
+        let document = dump.vertex(
+            "document",
+            json!({ "uri": doc.uri.as_str(), "languageId": "typst" }),
+        );
+        documents.push(document);
+
+        let mut ranges = vec![];
+        for occ in &doc.occurrences {
+            let range = dump.vertex(
+                "range",
+                json!({ "start": occ.range.start, "end": occ.range.end }),
+            );
+            dump.edge("next", range, result_sets[occ.symbol]);
+            ranges.push(range);
+
+            let by_kind = if occ.is_definition {
+                &mut definitions
+            } else {
+                &mut references
+            };
+            by_kind
+                .entry(occ.symbol)
+                .or_default()
+                .push((document, range));
+        }
+        if !ranges.is_empty() {
+            dump.edges("contains", document, &ranges);
+        }
+    }
+    if !documents.is_empty() {
+        dump.edges("contains", project, &documents);
+    }
+
+    for (symbol, &result_set) in result_sets.iter().enumerate() {
+        let defs = definitions
+            .get(&symbol)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let refs = references
+            .get(&symbol)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        if !defs.is_empty() {
+            let result = dump.vertex("definitionResult", json!({}));
+            dump.edge("textDocument/definition", result_set, result);
+            dump.items(result, defs, None);
+        }
+
+        let result = dump.vertex("referenceResult", json!({}));
+        dump.edge("textDocument/references", result_set, result);
+        dump.items(result, defs, Some("definitions"));
+        dump.items(result, refs, Some("references"));
+    }
+
+    Ok(dump.out)
+}
+
+/// An LSIF dump being written.
+#[derive(Default)]
+struct LsifDump {
+    out: Vec<u8>,
+    next_id: u64,
+}
+
+impl LsifDump {
+    fn vertex(&mut self, label: &str, data: JsonValue) -> u64 {
+        self.element("vertex", label, data)
+    }
+
+    fn edge(&mut self, label: &str, out_v: u64, in_v: u64) {
+        self.element("edge", label, json!({ "outV": out_v, "inV": in_v }));
+    }
+
+    fn edges(&mut self, label: &str, out_v: u64, in_vs: &[u64]) {
+        self.element("edge", label, json!({ "outV": out_v, "inVs": in_vs }));
+    }
+
+    /// Emits the `item` edges from a result to the ranges grouped by their
+    /// documents.
+    fn items(&mut self, result: u64, ranges: &[(u64, u64)], property: Option<&str>) {
+        let mut by_document = Vec::<(u64, Vec<u64>)>::new();
+        for &(document, range) in ranges {
+            match by_document.iter_mut().find(|(doc, _)| *doc == document) {
+                Some((_, ranges)) => ranges.push(range),
+                None => by_document.push((document, vec![range])),
+            }
+        }
+
+        for (document, ranges) in by_document {
+            let mut data = json!({ "outV": result, "inVs": ranges, "document": document });
+            if let Some(property) = property {
+                data["property"] = property.into();
+            }
+            self.element("edge", "item", data);
+        }
+    }
+
+    fn element(&mut self, ty: &str, label: &str, mut data: JsonValue) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        data["id"] = id.into();
+        data["type"] = ty.into();
+        data["label"] = label.into();
+        // Each element is written in a single line.
+        serde_json::to_writer(&mut self.out, &data).expect("serialize lsif element");
+        self.out.push(b'\n');
+        id
+    }
+}
@@ -0,0 +1,72 @@
+//! Exports the code-intelligence index of a project, e.g. for code browsers
+//! like Sourcegraph.
+
+mod lsif;
+mod scip;
+
+use std::path::Path;
+
+use reflexo_typst::EntryReader;
+use tinymist_query::analysis::Analysis;
+use tinymist_query::code_index::code_index;
+use tinymist_std::error::prelude::*;
+
+use crate::project::*;
+
+/// Arguments for exporting the code-intelligence index.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct IndexArgs {
+    /// The argument to compile once.
+    #[clap(flatten)]
+    pub compile: CompileOnceArgs,
+
+    /// The format of the index.
+    #[clap(long, default_value = "scip")]
+    pub format: IndexFormat,
+
+    /// The path to the output file. Defaults to `index.scip` for SCIP and
+    /// `dump.lsif` for LSIF.
+    #[clap(long, short)]
+    pub output: Option<String>,
+}
+
+/// The format of the code-intelligence index.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum IndexFormat {
+    /// The [SCIP](https://github.com/sourcegraph/scip) format in protobuf.
+    Scip,
+    /// The [LSIF](https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/)
+    /// format in JSON lines.
+    Lsif,
+}
+
+/// Exports the code-intelligence index of a project.
+pub fn index_main(args: IndexArgs) -> Result<()> {
+    let universe = args.compile.resolve()?;
+    let world = universe.snapshot();
+    let root = world.entry_state().root().context("no project root")?;
+
+    let analysis = Analysis::default();
+    let mut ctx = analysis.enter(world);
+    let index = code_index(&mut ctx);
+    log::info!(
+        "indexed {} files with {} symbols",
+        index.documents.len(),
+        index.symbols.len()
+    );
+
+    let output = args.output.unwrap_or_else(|| {
+        match args.format {
+            IndexFormat::Scip => "index.scip",
+            IndexFormat::Lsif => "dump.lsif",
+        }
+        .to_owned()
+    });
+    let data = match args.format {
+        IndexFormat::Scip => scip::write_scip(&index, &root)?,
+        IndexFormat::Lsif => lsif::write_lsif(&index, &root)?,
+    };
+    std::fs::write(Path::new(&output), data).context("write index")?;
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Writes the index in the [SCIP](https://github.com/sourcegraph/scip/blob/main/scip.proto)
+//! format, which is a protobuf message.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use tinymist_query::code_index::CodeIndex;
+use tinymist_query::LspRange;
+use tinymist_std::error::prelude::*;
+
+/// The `UTF8` variant of the `TextEncoding` enum.
+const TEXT_ENCODING_UTF8: u64 = 1;
+/// The `UTF16CodeUnitOffsetFromLineStart` variant of the `PositionEncoding`
+/// enum, which is the default encoding of the analysis.
+const POSITION_ENCODING_UTF16: u64 = 2;
+/// The `Definition` flag of the `SymbolRole` enum.
+const SYMBOL_ROLE_DEFINITION: u64 = 1;
+
+/// Writes the index as a SCIP `Index` message.
+pub(crate) fn write_scip(index: &CodeIndex, root: &Path) -> Result<Vec<u8>> {
+    let root_uri = tinymist_query::path_to_url(root).context("project root")?;
+
+    let mut tool_info = Message::default();
+    tool_info.string(1, "tinymist");
+    tool_info.string(2, env!("CARGO_PKG_VERSION"));
+
+    let mut metadata = Message::default();
+    metadata.message(2, tool_info);
+    metadata.string(3, root_uri.as_str());
+    metadata.uint(4, TEXT_ENCODING_UTF8);
+
+    let mut scip = Message::default();
+    scip.message(1, metadata);
+
+    let mut defined = HashSet::new();
+    for doc in &index.documents {
+        let mut document = Message::default();
+        document.string(1, &doc.path);
+        document.string(4, "typst");
+        document.uint(6, POSITION_ENCODING_UTF16);
+
+        for occ in &doc.occurrences {
+            let symbol = &index.symbols[occ.symbol];
+
+            let mut occurrence = Message::default();
+            occurrence.packed(1, &scip_range(&occ.range));
+            occurrence.string(2, &symbol.id);
+            if occ.is_definition {
+                occurrence.uint(3, SYMBOL_ROLE_DEFINITION);
+            }
+            document.message(2, occurrence);
+        }
+
+        // Describes the symbols defined in the document.
+        for occ in doc.occurrences.iter().filter(|occ| occ.is_definition) {
+            if !defined.insert(occ.symbol) {
+                continue;
+            }
+            document.message(3, symbol_information(index, occ.symbol));
+        }
+
+        scip.message(2, document);
+    }
+
+    // Describes the symbols defined outside of the project, e.g. in packages.
+    for idx in 0..index.symbols.len() {
+        if !defined.contains(&idx) && !index.symbols[idx].is_local {
+            scip.message(3, symbol_information(index, idx));
+        }
+    }
+
+    Ok(scip.0)
+}
+
+fn symbol_information(index: &CodeIndex, idx: usize) -> Message {
+    let symbol = &index.symbols[idx];
+
+    let mut info = Message::default();
+    info.string(1, &symbol.id);
+    if let Some(docs) = &symbol.docs {
+        info.string(3, docs);
+    }
+    info.string(6, &symbol.name);
+    info
+}
+
+/// Encodes a range as `[startLine, startCharacter, endLine, endCharacter]`,
+/// or `[startLine, startCharacter, endCharacter]` for a single-line range.
+fn scip_range(range: &LspRange) -> Vec<u64> {
+    let (start, end) = (range.start, range.end);
+    if start.line == end.line {
+        vec![
+            start.line.into(),
+            start.character.into(),
+            end.character.into(),
+        ]
+    } else {
+        vec![
+            start.line.into(),
+            start.character.into(),
+            end.line.into(),
+            end.character.into(),
+        ]
+    }
+}
+
+/// A protobuf message being encoded. The fields with default values are
+/// omitted as required by the protobuf encoding.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint((u64::from(field) << 3) | u64::from(wire_type));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.key(field, 0);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    fn message(&mut self, field: u32, message: Message) {
+        self.bytes(field, &message.0);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u64]) {
+        let mut packed = Message::default();
+        for value in values {
+            packed.varint(*value);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_message() {
+        let mut inner = Message::default();
+        inner.string(1, "a");
+        let mut msg = Message::default();
+        msg.uint(1, 300);
+        msg.uint(2, 0);
+        msg.message(3, inner);
+        msg.packed(4, &[1, 2]);
+        assert_eq!(
+            msg.0,
+            [0x08, 0xac, 0x02, 0x1a, 0x03, 0x0a, 0x01, b'a', 0x22, 0x02, 0x01, 0x02]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Lints the Typst documents in batch, e.g. for checking the documents in CI.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use clap::ValueHint;
+use reflexo::{path::unix_slash, ImmutPath};
+use reflexo_typst::vfs::WorkspaceResolver;
+use reflexo_typst::WorldComputeGraph;
+use serde::Serialize;
+use tinymist_query::analysis::Analysis;
+use tinymist_std::{bail, error::prelude::*};
+use typst::diag::{Severity, SourceDiagnostic};
+use typst::World;
+
+use super::format::typst_files;
+use crate::project::*;
+use crate::world::system::print_diagnostics;
+use crate::{CompileFontArgs, CompilePackageArgs};
+
+/// The format to print the diagnostics in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LintFormat {
+    /// Prints the diagnostics along with the source code to the stderr.
+    #[default]
+    Human,
+    /// Prints the diagnostics as a JSON array to the stdout.
+    Json,
+}
+
+/// The severity of the diagnostics matching a [`SeverityRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LintLevel {
+    /// Reports the diagnostics as errors.
+    Error,
+    /// Reports the diagnostics as warnings.
+    Warning,
+    /// Doesn't report the diagnostics.
+    Off,
+}
+
+/// Overrides the severity of the diagnostics whose message contains a
+/// pattern, written as `<pattern>=<level>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeverityRule {
+    /// The pattern to search in the messages.
+    pub pattern: String,
+    /// The severity of the matching diagnostics.
+    pub level: LintLevel,
+}
+
+impl FromStr for SeverityRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, level) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("expected `<pattern>=<level>`, got `{s}`"))?;
+        let level = <LintLevel as clap::ValueEnum>::from_str(level, true)?;
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            level,
+        })
+    }
+}
+
+/// Arguments for linting the documents.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct LintArgs {
+    /// The entry files to lint, which can also be directories or glob
+    /// patterns, e.g. `chapters/*.typ`. The files depended by the entries in
+    /// the workspace are linted as well.
+    #[clap(required = true, value_hint = ValueHint::AnyPath)]
+    pub inputs: Vec<String>,
+
+    /// Configures the project root (for absolute paths).
+    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
+    pub root: Option<String>,
+
+    /// Common font arguments.
+    #[clap(flatten)]
+    pub font: CompileFontArgs,
+
+    /// Common package arguments.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+
+    /// The format to print the diagnostics in.
+    #[clap(long, value_enum, default_value = "human")]
+    pub format: LintFormat,
+
+    /// Overrides the severity of the diagnostics whose message contains a
+    /// pattern, e.g. `--severity "is deprecated=error"` or
+    /// `--severity "variable font=off"`. The last matching rule wins.
+    #[clap(long, value_name = "PATTERN=LEVEL")]
+    pub severity: Vec<SeverityRule>,
+
+    /// Fails on warnings.
+    #[clap(long)]
+    pub deny_warnings: bool,
+
+    /// Lints only the workspace files, without reporting the compiler
+    /// diagnostics of the entries.
+    #[clap(long)]
+    pub no_compile: bool,
+}
+
+/// A diagnostic in the JSON output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LintDiagnostic {
+    /// The path of the file, relative to the current directory if possible.
+    path: Option<String>,
+    /// The 1-based line and column of the start of the diagnostic.
+    start: Option<(usize, usize)>,
+    /// The 1-based line and column of the end of the diagnostic.
+    end: Option<(usize, usize)>,
+    severity: &'static str,
+    message: String,
+    hints: Vec<String>,
+}
+
+/// Lints the documents and prints the diagnostics.
+pub fn lint_main(args: LintArgs) -> Result<()> {
+    let cwd = std::env::current_dir().context("current directory")?;
+    let lock_dir: ImmutPath = cwd.as_path().into();
+
+    let mut entries = vec![];
+    for input in &args.inputs {
+        entries.extend(entry_files(input)?);
+    }
+
+    let mut analysis = Analysis::default();
+    let mut reported = HashSet::new();
+    let mut linted = HashSet::new();
+    let mut output = vec![];
+    let (mut errors, mut warnings) = (0, 0);
+    for entry in entries {
+        let input = DocNewArgs {
+            id: DocIdArgs {
+                name: None,
+                input: entry.to_string_lossy().into_owned(),
+            },
+            root: args.root.clone(),
+            font: args.font.clone(),
+            package: args.package.clone(),
+        }
+        .to_input();
+        let verse = (input, lock_dir.clone()).resolve()?;
+        let graph = WorldComputeGraph::from_world(verse.snapshot());
+        let compiled = CompiledArtifact::from_graph(graph, false);
+        let world = compiled.world();
+
+        let mut diags = vec![];
+        if !args.no_compile {
+            diags.extend(compiled.diagnostics().cloned());
+        }
+        let mut ctx = analysis.enter(world.clone());
+        for dep in compiled.depended_files() {
+            if WorkspaceResolver::is_package_file(*dep) {
+                continue;
+            }
+            let Ok(path) = world.path_for_id(*dep) else {
+                continue;
+            };
+            if !linted.insert(path.as_path().to_owned()) {
+                continue;
+            }
+            if let Ok(source) = world.source(*dep) {
+                diags.extend(ctx.lint(&source));
+            }
+        }
+
+        let diags = diags
+            .into_iter()
+            .filter_map(|diag| apply_rules(&args.severity, diag))
+            .map(|diag| (to_lint_diagnostic(world, &cwd, &diag), diag))
+            // The entries may share the files, along with their diagnostics.
+            .filter(|(json, _)| {
+                reported.insert((
+                    json.path.clone(),
+                    json.start,
+                    json.end,
+                    json.message.clone(),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        for (_, diag) in &diags {
+            match diag.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+        match args.format {
+            LintFormat::Human => {
+                print_diagnostics(
+                    world,
+                    diags.iter().map(|(_, diag)| diag),
+                    DiagnosticFormat::Human,
+                )
+                .context_ut("print diagnostics")?;
+            }
+            LintFormat::Json => output.extend(diags.into_iter().map(|(json, _)| json)),
+        }
+    }
+
+    if args.format == LintFormat::Json {
+        let json = serde_json::to_string_pretty(&output).context("serialize diagnostics")?;
+        println!("{json}");
+    }
+
+    if errors > 0 {
+        bail!("{errors} error(s) and {warnings} warning(s) found");
+    }
+    if warnings > 0 && args.deny_warnings {
+        bail!("{warnings} warning(s) are denied");
+    }
+    if warnings > 0 {
+        eprintln!("{warnings} warning(s) found");
+    }
+
+    Ok(())
+}
+
+/// Collects the entry files of an input, which is a file, a directory or a
+/// glob pattern.
+fn entry_files(input: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(input);
+    if path.exists() {
+        return Ok(typst_files(path));
+    }
+
+    let glob = globset::GlobBuilder::new(input)
+        .literal_separator(true)
+        .build()
+        .context_ut("invalid glob pattern")?
+        .compile_matcher();
+
+    // Walks from the longest prefix of the pattern without glob characters.
+    let is_glob = |s: &str| s.contains(['*', '?', '[', '{']);
+    let base = path
+        .components()
+        .take_while(|c| !matches!(c, Component::Normal(s) if is_glob(&s.to_string_lossy())))
+        .collect::<PathBuf>();
+    let base = if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    };
+
+    let mut files = typst_files(&base)
+        .into_iter()
+        .filter(|file| glob.is_match(file.strip_prefix("./").unwrap_or(file)))
+        .collect::<Vec<_>>();
+    files.sort();
+    if files.is_empty() {
+        bail!("no file matches `{input}`");
+    }
+    Ok(files)
+}
+
+/// Applies the last matching rule to a diagnostic, or drops it if the rule
+/// turns it off.
+fn apply_rules(rules: &[SeverityRule], mut diag: SourceDiagnostic) -> Option<SourceDiagnostic> {
+    let rule = rules
+        .iter()
+        .rev()
+        .find(|rule| diag.message.contains(rule.pattern.as_str()));
+    match rule.map(|rule| rule.level) {
+        Some(LintLevel::Off) => return None,
+        Some(LintLevel::Error) => diag.severity = Severity::Error,
+        Some(LintLevel::Warning) => diag.severity = Severity::Warning,
+        None => {}
+    }
+    Some(diag)
+}
+
+fn to_lint_diagnostic(world: &LspWorld, cwd: &Path, diag: &SourceDiagnostic) -> LintDiagnostic {
+    let id = diag.span.id();
+    let path = id.and_then(|id| world.path_for_id(id).ok()).map(|path| {
+        let path = path.as_path();
+        unix_slash(path.strip_prefix(cwd).unwrap_or(path))
+    });
+
+    let range = id.and_then(|id| {
+        let source = world.source(id).ok()?;
+        let range = source.range(diag.span)?;
+        let position = |offset| {
+            let line = source.byte_to_line(offset)?;
+            Some((line + 1, source.byte_to_column(offset)? + 1))
+        };
+        Some((position(range.start), position(range.end)))
+    });
+    let (start, end) = range.unwrap_or_default();
+
+    LintDiagnostic {
+        path,
+        start,
+        end,
+        severity: match diag.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        message: diag.message.to_string(),
+        hints: diag.hints.iter().map(|hint| hint.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_rule() {
+        let rule = "a=b=error".parse::<SeverityRule>().unwrap();
+        assert_eq!(rule.pattern, "a=b");
+        assert_eq!(rule.level, LintLevel::Error);
+        assert!("deprecated".parse::<SeverityRule>().is_err());
+        assert!("deprecated=fatal".parse::<SeverityRule>().is_err());
+
+        let rules = [
+            "deprecated=error".parse().unwrap(),
+            "font=off".parse().unwrap(),
+        ];
+        let diag = |msg| SourceDiagnostic::warning(typst::syntax::Span::detached(), msg);
+        let deprecated = apply_rules(&rules, diag("`x` is deprecated")).unwrap();
+        assert_eq!(deprecated.severity, Severity::Error);
+        assert!(apply_rules(&rules, diag("variable font is not supported")).is_none());
+        let other = apply_rules(&rules, diag("unknown")).unwrap();
+        assert_eq!(other.severity, Severity::Warning);
+    }
+}
@@ -0,0 +1,237 @@
+//! Compiles a document once per row of a dataset, e.g. for generating
+//! certificates or invoices from a table of recipients.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::ValueHint;
+use reflexo::ImmutPath;
+use reflexo_typst::WorldComputeGraph;
+use serde::Serialize;
+use tinymist_std::error::prelude::*;
+use typst::foundations::{Dict, Str, Value};
+use typst::utils::LazyHash;
+
+use crate::project::*;
+use crate::task::ExportTask;
+use crate::world::system::print_diagnostics;
+
+/// Arguments for compiling a document once per row of a dataset.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct MergeArgs {
+    /// Inherits the compile task arguments. The output path is a template, in
+    /// which `{i}` is replaced by the one-indexed row number and `{<field>}`
+    /// by the value of the field in the row, e.g. `out/{name}.pdf`. Defaults
+    /// to `<input>-{i}`.
+    #[clap(flatten)]
+    pub compile: TaskCompileArgs,
+
+    /// Path to the dataset, either a CSV file whose first row names the
+    /// columns, or a JSON file containing an array of objects. The fields of
+    /// each row are visible through `sys.inputs`.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub data: PathBuf,
+
+    /// Path to write the summary of the compilations to, in JSON. Prints the
+    /// summary to stdout if not provided.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub summary: Option<PathBuf>,
+}
+
+/// A row of a dataset.
+type Row = Vec<(String, String)>;
+
+/// The result of compiling a document for a row.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MergedRow {
+    /// The one-indexed row number.
+    row: usize,
+    /// The path of the artifact.
+    output: PathBuf,
+    /// Whether the artifact is written.
+    success: bool,
+    /// The reason of the failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Compiles a document once per row of a dataset.
+pub fn merge_main(args: MergeArgs) -> Result<()> {
+    let rows = read_rows(&args.data)?;
+
+    let input_path = PathBuf::from(&args.compile.declare.id.input);
+    let template = args.compile.output.clone().unwrap_or_else(|| {
+        let stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
+        format!("{stem}-{{i}}")
+    });
+
+    let input = args.compile.declare.to_input();
+    let task = args.compile.to_task(input.id.clone())?.task;
+    let is_html = matches!(task, ProjectTask::ExportHtml(..));
+
+    let lock_dir: ImmutPath = std::env::current_dir().context("lock directory")?.into();
+    let verse = (input, lock_dir).resolve()?;
+    let base_inputs = verse.inputs();
+
+    let mut merged = Vec::with_capacity(rows.len());
+    for (idx, row) in rows.iter().enumerate() {
+        let mut inputs = Dict::clone(&base_inputs);
+        for (key, value) in row {
+            inputs.insert(Str::from(key.as_str()), Value::Str(value.as_str().into()));
+        }
+
+        let world = verse.snapshot_with(Some(TaskInputs {
+            entry: None,
+            inputs: Some(Arc::new(LazyHash::new(inputs))),
+        }));
+        let graph = WorldComputeGraph::from_world(world);
+        let compiled = CompiledArtifact::from_graph(graph, is_html);
+        print_diagnostics(
+            compiled.world(),
+            compiled.diagnostics(),
+            DiagnosticFormat::Human,
+        )
+        .context_ut("print diagnostics")?;
+
+        let output = output_path(&template, idx + 1, row, task.extension());
+        let res = export_row(&task, &compiled, &output);
+        if let Err(err) = &res {
+            eprintln!("row {}: {err}", idx + 1);
+        }
+        merged.push(MergedRow {
+            row: idx + 1,
+            output,
+            success: res.is_ok(),
+            error: res.err().map(|err| err.to_string()),
+        });
+    }
+
+    let summary = serde_json::to_string_pretty(&merged).context("serialize summary")?;
+    match &args.summary {
+        Some(path) => std::fs::write(path, summary).context("write summary")?,
+        None => println!("{summary}"),
+    }
+
+    let failed = merged.iter().filter(|row| !row.success).count();
+    if failed > 0 {
+        bail!("{failed} of {} rows failed", merged.len());
+    }
+
+    Ok(())
+}
+
+/// Exports the document compiled for a row.
+fn export_row(task: &ProjectTask, compiled: &LspCompiledArtifact, output: &Path) -> Result<()> {
+    if compiled.has_errors() {
+        bail!("compilation failed");
+    }
+    let doc = compiled.doc.as_ref().context("no document")?;
+    let bytes = ExportTask::export_bytes(task.clone(), &compiled.graph, doc)?;
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).context("create output directory")?;
+    }
+    std::fs::write(output, bytes).context("write artifact")
+}
+
+/// Substitutes the row number and the fields of a row into the output path
+/// template, appending the extension of the artifact if missing.
+fn output_path(template: &str, idx: usize, row: &Row, extension: &str) -> PathBuf {
+    let mut path = template.replace("{i}", &idx.to_string());
+    for (key, value) in row {
+        // The values must not escape the directory of the template.
+        let value = value.replace(['/', '\\'], "_");
+        path = path.replace(&format!("{{{key}}}"), &value);
+    }
+
+    let mut path = PathBuf::from(path);
+    if path.extension().is_none() {
+        path.set_extension(extension);
+    }
+    path
+}
+
+/// Reads the rows of a CSV or JSON dataset.
+fn read_rows(path: &Path) -> Result<Vec<Row>> {
+    let text = std::fs::read_to_string(path).context("read dataset")?;
+    let is_json = path.extension().is_some_and(|ext| ext == "json");
+    if !is_json {
+        let mut records = parse_csv(&text).into_iter();
+        let header = records.next().unwrap_or_default();
+        return Ok(records
+            .filter(|record| record.iter().any(|field| !field.is_empty()))
+            .map(|record| header.iter().cloned().zip(record).collect())
+            .collect());
+    }
+
+    let data: Vec<serde_json::Map<String, serde_json::Value>> =
+        serde_json::from_str(&text).context("parse dataset")?;
+    Ok(data
+        .into_iter()
+        .map(|row| {
+            let fields = row.into_iter().map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            });
+            fields.collect()
+        })
+        .collect())
+}
+
+/// Parses the records of a CSV text, with the quoted fields of RFC 4180.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let records = parse_csv("name,amount\r\nAlice,1\n\"Bob, Jr.\",\"say \"\"hi\"\"\"\n");
+        assert_eq!(
+            records,
+            vec![
+                vec!["name", "amount"],
+                vec!["Alice", "1"],
+                vec!["Bob, Jr.", "say \"hi\""],
+            ]
+        );
+    }
+
+    #[test]
+    fn output_template() {
+        let row = vec![("name".to_owned(), "a/b".to_owned())];
+        let path = output_path("out/{name}-{i}", 3, &row, "pdf");
+        assert_eq!(path, PathBuf::from("out/a_b-3.pdf"));
+    }
+}
@@ -1,8 +1,20 @@
 //! All the language tools provided by the `tinymist` crate.
 
+pub mod analysis;
 pub mod ast;
+pub mod ci;
+pub mod convert;
+pub mod fonts;
+pub mod format;
+pub mod index;
+pub mod lint;
+pub mod merge;
 pub mod package;
+pub mod probe;
 pub mod project;
+pub mod query;
+pub mod self_test;
+pub mod serve;
 pub mod testing;
 pub mod word_count;
 
@@ -0,0 +1,86 @@
+//! Actions for pinning the packages used by the documents in the lock file.
+
+use std::collections::BTreeMap;
+
+use reflexo_typst::WorldComputeGraph;
+use tinymist_std::{bail, error::prelude::*, ImmutPath};
+
+use crate::project::*;
+use crate::world::system::print_diagnostics;
+
+/// Arguments for pinning the packages in the lock file.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct PackageLockArgs {
+    /// Checks that the lock file agrees with the imports and the contents of
+    /// the packages instead of updating it, and fails otherwise.
+    #[clap(long)]
+    pub check: bool,
+}
+
+/// Pins the packages used by the documents in the lock file of the current
+/// directory, or checks the pins.
+pub fn lock_main(args: PackageLockArgs) -> Result<()> {
+    let lock_dir: ImmutPath = std::env::current_dir().context("lock directory")?.into();
+    let lock = LockFile::read(&lock_dir)?;
+
+    let mut packages = BTreeMap::new();
+    let mut disagreements = 0;
+    for input in &lock.document {
+        let is_html = lock.task.iter().any(|task| {
+            task.document == input.id && matches!(task.task, ProjectTask::ExportHtml(..))
+        });
+
+        let verse = (input.clone(), lock_dir.clone()).resolve()?;
+        let graph = WorldComputeGraph::from_world(verse.snapshot());
+        let compiled = CompiledArtifact::from_graph(graph, is_html);
+        let world = compiled.world();
+
+        if args.check {
+            let diags = lock.check_imports(world);
+            disagreements += diags.len();
+            print_diagnostics(world, diags.iter(), DiagnosticFormat::Human)
+                .context_ut("print diagnostics")?;
+        }
+
+        for spec in used_packages(world) {
+            let package = ProjectPackage::pin(world, &spec);
+            packages.insert(package.spec.clone(), package);
+        }
+    }
+
+    if !args.check {
+        let count = packages.len();
+        LockFile::update(&lock_dir, |state| {
+            state.package = packages.into_values().collect();
+            Ok(())
+        })?;
+
+        eprintln!("pinned {count} package(s) in {LOCK_FILENAME}");
+        return Ok(());
+    }
+
+    for package in packages.values() {
+        let Some(spec) = package.parse() else {
+            continue;
+        };
+        match lock.get_package(&spec) {
+            None => {
+                disagreements += 1;
+                println!("unpinned {spec}");
+            }
+            Some(pinned) if pinned.checksum != package.checksum => {
+                disagreements += 1;
+                let expected = pinned.checksum.as_deref().unwrap_or("none");
+                let actual = package.checksum.as_deref().unwrap_or("none");
+                println!("changed  {spec} (expected {expected}, found {actual})");
+            }
+            Some(..) => {}
+        }
+    }
+
+    if disagreements > 0 {
+        bail!("{disagreements} package(s) disagree with {LOCK_FILENAME}");
+    }
+
+    Ok(())
+}
@@ -2,3 +2,9 @@
 
 mod init;
 pub use init::*;
+
+mod lock;
+pub use lock::*;
+
+mod verify;
+pub use verify::*;
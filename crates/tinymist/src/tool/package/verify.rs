@@ -0,0 +1,72 @@
+//! Actions for auditing the package cache.
+
+use tinymist_project::package::registry::PackageIntegrity;
+use tinymist_std::{bail, error::prelude::*};
+
+use crate::project::LspUniverseBuilder;
+use crate::CompilePackageArgs;
+
+/// Package management commands.
+#[derive(Debug, Clone, clap::Subcommand)]
+#[clap(rename_all = "kebab-case")]
+pub enum PackageCommands {
+    /// Verifies the downloaded packages against their recorded checksums.
+    Verify(PackageVerifyArgs),
+    /// Pins the packages used by the documents in the lock file.
+    Lock(super::PackageLockArgs),
+}
+
+/// Arguments for verifying the package cache.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct PackageVerifyArgs {
+    /// The package storage arguments.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+
+    /// Moves the tampered packages out of the cache, so that they are
+    /// downloaded again on next use.
+    #[clap(long)]
+    pub quarantine: bool,
+}
+
+/// Package commands' main
+pub fn package_main(args: PackageCommands) -> Result<()> {
+    match args {
+        PackageCommands::Verify(args) => verify_main(args),
+        PackageCommands::Lock(args) => super::lock_main(args),
+    }
+}
+
+/// Verifies the package cache and reports the tampered packages.
+fn verify_main(args: PackageVerifyArgs) -> Result<()> {
+    let registry = LspUniverseBuilder::resolve_package(None, Some(&args.package));
+
+    let mut tampered = 0;
+    for (spec, dir, integrity) in registry.verify_cache() {
+        match integrity {
+            Ok(PackageIntegrity::Verified) => println!("verified   {spec}"),
+            Ok(PackageIntegrity::Unverified) => println!("unverified {spec}"),
+            Ok(PackageIntegrity::Unrecorded) => println!("unrecorded {spec}"),
+            Ok(PackageIntegrity::Tampered { expected, actual }) => {
+                tampered += 1;
+                println!("tampered   {spec} (expected {expected}, found {actual})");
+                if args.quarantine {
+                    let target = registry
+                        .quarantine_package(&spec, &dir)
+                        .context_ut("failed to quarantine package")?;
+                    println!("           moved to {}", target.display());
+                }
+            }
+            Err(err) => {
+                tampered += 1;
+                println!("error      {spec}: {err}");
+            }
+        }
+    }
+
+    if tampered > 0 {
+        bail!("{tampered} package(s) failed verification");
+    }
+
+    Ok(())
+}
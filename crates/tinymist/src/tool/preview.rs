@@ -1,5 +1,6 @@
 //! Document preview tool for Typst
 
+pub use crate::utils::{generate_token, is_loopback_addr};
 pub use compile::{PreviewCompileView, ProjectPreviewHandler};
 pub use http::{make_http_server, HttpServer};
 
@@ -13,6 +14,7 @@ use futures::{SinkExt, StreamExt, TryStreamExt};
 use hyper_tungstenite::{tungstenite::Message, HyperWebsocket, HyperWebsocketStream};
 use lsp_types::notification::Notification;
 use lsp_types::Url;
+use parking_lot::Mutex;
 use reflexo_typst::error::prelude::*;
 use serde::Serialize;
 use serde_json::Value as JsonValue;
@@ -20,10 +22,12 @@ use sync_ls::just_ok;
 use tinymist_assets::TYPST_PREVIEW_HTML;
 use tinymist_preview::{
     frontend_html, ControlPlaneMessage, ControlPlaneRx, ControlPlaneTx, DocToSrcJumpInfo,
-    PreviewArgs, PreviewBuilder, PreviewMode, Previewer, WsMessage,
+    ExportViewRequest, ExportViewResponse, PreviewArgs, PreviewBuilder, PreviewMode, Previewer,
+    WsMessage,
 };
 use tinymist_query::{LspPosition, LspRange};
 use tinymist_std::error::IgnoreLogging;
+use tinymist_std::hash::FxHashSet;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::actor::preview::{PreviewActor, PreviewRequest, PreviewTab};
@@ -57,13 +61,14 @@ pub struct PreviewCliArgs {
     #[clap(long = "preview-mode", default_value = "document", value_name = "MODE")]
     pub preview_mode: PreviewMode,
 
-    /// Data plane server will bind to this address. Note: if it equals to
+    /// Data plane server will bind to this address, e.g. `0.0.0.0:23625` to
+    /// expose the preview to the network, which requires a token. A random
+    /// port is used if the port is `0` or omitted. Note: if it equals to
     /// `static_file_host`, same address will be used.
     #[clap(
         long = "data-plane-host",
         default_value = "127.0.0.1:23625",
-        value_name = "HOST",
-        hide(true)
+        value_name = "HOST"
     )]
     pub data_plane_host: String,
 
@@ -99,12 +104,53 @@ pub struct PreviewCliArgs {
     /// set as well, this flag will win.
     #[clap(long = "no-open")]
     pub no_open: bool,
+
+    /// Runs the renderer without the bundled frontend, for third-party
+    /// viewers. The data plane only serves the frames over websocket, no
+    /// browser is opened, and the addresses of the data plane and the control
+    /// plane are printed to stdout as a JSON line.
+    #[clap(long = "headless")]
+    pub headless: bool,
+
+    /// Requires the token in the URLs of the preview, e.g.
+    /// `http://127.0.0.1:23625/?token=<TOKEN>`. A token is generated if it
+    /// is not given. The token is always required if the preview is bound to
+    /// an address reachable from the network.
+    #[clap(
+        long = "token",
+        value_name = "TOKEN",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    pub token: Option<String>,
 }
 
 impl PreviewCliArgs {
     /// Whether to open the preview in the browser after compilation.
     pub fn open_in_browser(&self, default: bool) -> bool {
-        !self.no_open && (self.open || default)
+        !self.headless && !self.no_open && (self.open || default)
+    }
+
+    /// Resolves the token required by the preview servers.
+    pub fn resolve_token(&self) -> Option<String> {
+        match self.token.as_deref() {
+            Some("") => Some(generate_token()),
+            Some(token) => Some(token.to_owned()),
+            None if !is_loopback_addr(&self.data_plane_host)
+                || !is_loopback_addr(&self.control_plane_host) =>
+            {
+                Some(generate_token())
+            }
+            None => None,
+        }
+    }
+}
+
+/// The path of the preview page carrying the token, if any.
+fn preview_path(token: Option<&str>) -> String {
+    match token {
+        Some(token) => format!("/?token={token}"),
+        None => "/".to_owned(),
     }
 }
 
@@ -115,6 +161,8 @@ pub struct StartPreviewResponse {
     static_server_port: Option<u16>,
     static_server_addr: Option<String>,
     data_plane_port: Option<u16>,
+    /// The token required in the URLs of the preview, e.g. `/?token=<TOKEN>`.
+    token: Option<String>,
     is_primary: bool,
 }
 
@@ -184,6 +232,13 @@ impl ServerState {
         if task_id == "primary" {
             return Err(invalid_params("task id 'primary' is reserved"));
         }
+        // The previews of different documents run simultaneously, each in its
+        // own project, so a task id must not be shared by two previews.
+        if self.preview.running.lock().contains(&task_id) {
+            return Err(invalid_params(format!(
+                "task id '{task_id}' is used by another preview"
+            )));
+        }
 
         if cli_args.not_as_primary && matches!(kind, PreviewKind::Background) {
             return Err(invalid_params(
@@ -245,6 +300,8 @@ pub struct PreviewState {
     preview_tx: mpsc::UnboundedSender<PreviewRequest>,
     /// the watchers for the preview
     pub(crate) watchers: ProjectPreviewState,
+    /// The task ids of the running previews.
+    pub(crate) running: Arc<Mutex<FxHashSet<String>>>,
     /// Whether to send show document requests with customized notification.
     pub customized_show_document: bool,
 }
@@ -257,6 +314,7 @@ impl PreviewState {
         client: TypedLspClient<Self>,
     ) -> Self {
         let (preview_tx, preview_rx) = mpsc::unbounded_channel();
+        let running = Arc::new(Mutex::new(FxHashSet::default()));
 
         client.handle.spawn(
             PreviewActor {
@@ -264,6 +322,7 @@ impl PreviewState {
                 tabs: HashMap::default(),
                 preview_rx,
                 watchers: watchers.clone(),
+                running: running.clone(),
             }
             .run(),
         );
@@ -272,6 +331,7 @@ impl PreviewState {
             client,
             preview_tx,
             watchers,
+            running,
             customized_show_document: config.customized_show_document,
         }
     }
@@ -289,6 +349,7 @@ impl PreviewState {
                 .log_error_with(|| format!("failed to send kill request({:?})", watcher.task_id()));
         }
         watchers.clear();
+        self.running.lock().clear();
     }
 }
 
@@ -315,6 +376,11 @@ impl PreviewState {
         if !args.static_file_host.is_empty() && (args.static_file_host != args.data_plane_host) {
             return Err(internal_error("--static-file-host is removed"));
         }
+        if !self.running.lock().insert(task_id.clone()) {
+            return Err(invalid_params(format!(
+                "task id '{task_id}' is used by another preview"
+            )));
+        }
 
         let (lsp_tx, lsp_rx) = ControlPlaneTx::new(false);
         let ControlPlaneRx {
@@ -350,6 +416,8 @@ impl PreviewState {
                         }
                     }
                     Outline(s) => client.send_notification::<NotifDocumentOutline>(&s),
+                    // answered by the `tinymist.exportPreviewView` command instead.
+                    ExportView(..) => {}
                 }
             }
 
@@ -373,6 +441,7 @@ impl PreviewState {
         });
 
         let preview_tx = self.preview_tx.clone();
+        let running = self.running.clone();
         just_future(async move {
             let mut previewer = previewer.await;
             bind_streams(&mut previewer, websocket_rx);
@@ -382,26 +451,42 @@ impl PreviewState {
             compile_handler.flush_compile();
 
             // Replace the data plane port in the html to self
-            let frontend_html = frontend_html(TYPST_PREVIEW_HTML, args.preview_mode, "/");
-
-            let srv = make_http_server(frontend_html, args.data_plane_host, websocket_tx).await;
+            let token = args.resolve_token();
+            let path = preview_path(token.as_deref());
+            let frontend_html = frontend_html(TYPST_PREVIEW_HTML, args.preview_mode, &path);
+
+            let srv = make_http_server(
+                frontend_html,
+                args.data_plane_host,
+                token.clone(),
+                websocket_tx,
+            )
+            .await;
+            let srv = match srv {
+                Ok(srv) => srv,
+                Err(err) => {
+                    running.lock().remove(&task_id);
+                    return Err(internal_error(err));
+                }
+            };
             let addr = srv.addr;
-            log::info!("PreviewTask({task_id}): preview server listening on: {addr}");
+            log::info!("PreviewTask({task_id}): preview server listening on: http://{addr}{path}");
 
             let resp = StartPreviewResponse {
                 static_server_port: Some(addr.port()),
                 static_server_addr: Some(addr.to_string()),
                 data_plane_port: Some(addr.port()),
+                token,
                 is_primary,
             };
 
             if open_in_browser {
-                open::that_detached(format!("http://127.0.0.1:{}", addr.port()))
+                open::that_detached(format!("http://127.0.0.1:{}{path}", addr.port()))
                     .log_error("failed to open browser for preview");
             }
 
             let sent = preview_tx.send(PreviewRequest::Started(PreviewTab {
-                task_id,
+                task_id: task_id.clone(),
                 previewer,
                 srv,
                 ctl_tx,
@@ -409,7 +494,10 @@ impl PreviewState {
                 is_primary,
                 is_background,
             }));
-            sent.map_err(|_| internal_error("failed to register preview tab"))?;
+            if sent.is_err() {
+                running.lock().remove(&task_id);
+                return Err(internal_error("failed to register preview tab"));
+            }
 
             Ok(resp)
         })
@@ -443,6 +531,35 @@ impl PreviewState {
         just_ok(JsonValue::Null)
     }
 
+    /// Exports the page viewed in a preview, or a region of it, as an image.
+    /// The first preview is used if the task id is absent.
+    pub fn export_view(
+        &self,
+        task_id: Option<String>,
+        req: ExportViewRequest,
+    ) -> SchedulableResponse<ExportViewResponse> {
+        let watcher = {
+            let watchers = self.watchers.inner.lock();
+            let mut watchers = watchers.values();
+            match &task_id {
+                Some(task_id) => watchers.find(|watcher| watcher.task_id() == task_id),
+                None => watchers.next(),
+            }
+            .cloned()
+        };
+        let Some(watcher) = watcher else {
+            return Err(invalid_params(format!("no preview is found: {task_id:?}")));
+        };
+
+        just_future(async move {
+            let resp = watcher.export_view(&req);
+            match resp.error {
+                Some(err) => Err(internal_error(err)),
+                None => Ok(resp),
+            }
+        })
+    }
+
     /// Scroll all preview panels to a given position.
     pub fn scroll_all(&self, req: ControlPlaneMessage) -> AnySchedulableResponse {
         let sent = self.preview_tx.send(PreviewRequest::ScrollAll(req));
@@ -458,12 +575,15 @@ pub async fn preview_main(args: PreviewCliArgs) -> Result<()> {
     let handle = tokio::runtime::Handle::current();
 
     let open_in_browser = args.open_in_browser(true);
-    let static_file_host =
-        if args.static_file_host == args.data_plane_host || !args.static_file_host.is_empty() {
-            Some(args.static_file_host)
-        } else {
-            None
-        };
+    let token = args.resolve_token();
+    let path = preview_path(token.as_deref());
+    let static_file_host = if args.headless {
+        None
+    } else if args.static_file_host == args.data_plane_host || !args.static_file_host.is_empty() {
+        Some(args.static_file_host)
+    } else {
+        None
+    };
 
     exit_on_ctrl_c();
 
@@ -505,12 +625,27 @@ pub async fn preview_main(args: PreviewCliArgs) -> Result<()> {
 
     let (lsp_tx, mut lsp_rx) = ControlPlaneTx::new(true);
 
+    let (control_addr_tx, control_addr_rx) = tokio::sync::oneshot::channel();
+    let control_token = token.clone();
     let control_plane_server_handle = tokio::spawn(async move {
         let (control_sock_tx, mut control_sock_rx) = mpsc::unbounded_channel();
 
-        let srv =
-            make_http_server(String::default(), args.control_plane_host, control_sock_tx).await;
+        let srv = make_http_server(
+            String::default(),
+            args.control_plane_host,
+            control_token,
+            control_sock_tx,
+        )
+        .await;
+        let srv = match srv {
+            Ok(srv) => srv,
+            Err(err) => {
+                log::error!("cannot start control plane server: {err}");
+                return;
+            }
+        };
         log::info!("Control panel server listening on: {}", srv.addr);
+        let _ = control_addr_tx.send(srv.addr);
 
         let control_websocket = control_sock_rx.recv().await.unwrap();
         let ws = control_websocket.await.unwrap();
@@ -571,24 +706,50 @@ pub async fn preview_main(args: PreviewCliArgs) -> Result<()> {
 
     bind_streams(&mut previewer, websocket_rx);
 
-    let frontend_html = frontend_html(TYPST_PREVIEW_HTML, args.preview_mode, "/");
+    let frontend_html = if args.headless {
+        String::default()
+    } else {
+        frontend_html(TYPST_PREVIEW_HTML, args.preview_mode, &path)
+    };
 
     let static_server = if let Some(static_file_host) = static_file_host {
         log::warn!("--static-file-host is deprecated, which will be removed in the future. Use --data-plane-host instead.");
         let html = frontend_html.clone();
-        Some(make_http_server(html, static_file_host, websocket_tx.clone()).await)
+        let token = token.clone();
+        Some(make_http_server(html, static_file_host, token, websocket_tx.clone()).await?)
     } else {
         None
     };
 
-    let srv = make_http_server(frontend_html, args.data_plane_host, websocket_tx).await;
+    let srv = make_http_server(
+        frontend_html,
+        args.data_plane_host,
+        token.clone(),
+        websocket_tx,
+    )
+    .await?;
     log::info!("Data plane server listening on: {}", srv.addr);
 
+    if args.headless {
+        // Tells the viewer spawning the renderer where to connect.
+        let control_plane_addr = control_addr_rx.await.ok();
+        let addrs = serde_json::json!({
+            "dataPlaneAddr": srv.addr,
+            "controlPlaneAddr": control_plane_addr,
+            "token": token,
+        });
+        println!("{addrs}");
+    }
+
     let static_server_addr = static_server.as_ref().map(|s| s.addr).unwrap_or(srv.addr);
     log::info!("Static file server listening on: {static_server_addr}");
+    if token.is_some() {
+        // The token is needed to open the preview from other machines.
+        eprintln!("Preview: http://{static_server_addr}{path}");
+    }
 
     if open_in_browser {
-        open::that_detached(format!("http://{static_server_addr}"))
+        open::that_detached(format!("http://{static_server_addr}{path}"))
             .log_error("failed to open browser for preview");
     }
 
@@ -1,7 +1,9 @@
 //! Document preview tool for Typst
 
-use std::net::SocketAddr;
-use std::sync::LazyLock;
+use std::borrow::Cow;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 
 use hyper::header::HeaderValue;
 use hyper::service::service_fn;
@@ -9,9 +11,13 @@ use hyper_tungstenite::HyperWebsocket;
 use hyper_util::rt::TokioIo;
 use hyper_util::server::graceful::GracefulShutdown;
 use lsp_types::Url;
+use parking_lot::Mutex;
+use tinymist_std::error::prelude::*;
 use tinymist_std::error::IgnoreLogging;
 use tokio::sync::{mpsc, oneshot};
 
+use crate::utils::{has_token, query_param};
+
 /// created by `make_http_server`
 pub struct HttpServer {
     /// The address the server is listening on.
@@ -22,32 +28,108 @@ pub struct HttpServer {
     pub join: tokio::task::JoinHandle<()>,
 }
 
-/// Create a http server for the previewer.
+/// The timer of a presentation, which is shared by the presenter and the
+/// audience views of a preview.
+#[derive(Debug, Default)]
+struct PresentationTimer {
+    /// The time elapsed before the timer was started for the last time.
+    elapsed: Duration,
+    /// When the timer was started for the last time, if it is running.
+    started: Option<Instant>,
+}
+
+impl PresentationTimer {
+    fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    fn start(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    fn pause(&mut self) {
+        self.elapsed = self.elapsed();
+        self.started = None;
+    }
+
+    /// Applies an action of the `/timer` endpoint.
+    fn apply(&mut self, action: &str) -> bool {
+        match action {
+            "start" => self.start(),
+            "pause" => self.pause(),
+            "toggle" if self.started.is_some() => self.pause(),
+            "toggle" => self.start(),
+            "reset" => *self = Self::default(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::json!({
+            "elapsed": self.elapsed().as_secs_f64(),
+            "running": self.started.is_some(),
+        })
+        .to_string()
+    }
+}
+
+/// Create a http server for the previewer. If a token is given, the requests
+/// must carry it in the URL, e.g. `/?token=<TOKEN>`.
+///
+/// The server is bound to a random port if the address has no port or the
+/// port `0`. Otherwise, it fails if the port is taken, since the clients
+/// expect the server at the given port.
 pub async fn make_http_server(
     frontend_html: String,
     static_file_addr: String,
+    token: Option<String>,
     websocket_tx: mpsc::UnboundedSender<HyperWebsocket>,
-) -> HttpServer {
+) -> Result<HttpServer> {
     use http_body_util::Full;
     use hyper::body::{Bytes, Incoming};
     type Server = hyper_util::server::conn::auto::Builder<hyper_util::rt::TokioExecutor>;
 
-    let listener = tokio::net::TcpListener::bind(&static_file_addr)
+    let listener = tokio::net::TcpListener::bind(bind_addr(&static_file_addr).as_ref())
         .await
-        .unwrap();
-    let addr = listener.local_addr().unwrap();
+        .map_err(
+            |err| error_once!("cannot bind preview server", addr: static_file_addr, err: err),
+        )?;
+    let addr = listener.local_addr().context("local address")?;
     log::info!("preview server listening on http://{addr}");
 
     let frontend_html = hyper::body::Bytes::from(frontend_html);
+    let timer = Arc::new(Mutex::new(PresentationTimer::default()));
     let make_service = move || {
         let frontend_html = frontend_html.clone();
         let websocket_tx = websocket_tx.clone();
         let static_file_addr = static_file_addr.clone();
+        let timer = timer.clone();
+        let token = token.clone();
         service_fn(move |mut req: hyper::Request<Incoming>| {
             let frontend_html = frontend_html.clone();
             let websocket_tx = websocket_tx.clone();
             let static_file_addr = static_file_addr.clone();
+            let timer = timer.clone();
+            let token = token.clone();
             async move {
+                // The previews exposed to the network, e.g. over SSH tunnels or LAN, require
+                // the token, which is only known by the clients given the URL of the preview.
+                // Since other websites cannot know the token either, the `Origin` header is
+                // not checked then.
+                if let Some(token) = &token {
+                    if !has_token(req.uri().query(), token) {
+                        let res = hyper::Response::builder()
+                            .status(hyper::StatusCode::UNAUTHORIZED)
+                            .body(Full::<Bytes>::from("missing or invalid token"))
+                            .unwrap();
+                        return Ok(res);
+                    }
+                }
+
                 // When a user visits a website in a browser, that website can try to connect to
                 // our http / websocket server on `127.0.0.1` which may leak sensitive
                 // information. We could use CORS headers to explicitly disallow
@@ -63,8 +145,9 @@ pub async fn make_http_server(
                 // http / websocket server towards a legitimate frontend/html client.
                 // This requires additional protection that may be added in the future.
                 let origin_header = req.headers().get("Origin");
-                if origin_header
-                    .is_some_and(|h| !is_valid_origin(h, &static_file_addr, addr.port()))
+                if token.is_none()
+                    && origin_header
+                        .is_some_and(|h| !is_valid_origin(h, &static_file_addr, addr.port()))
                 {
                     anyhow::bail!(
                         "Connection with unexpected `Origin` header. Closing connection."
@@ -73,7 +156,7 @@ pub async fn make_http_server(
 
                 // Check if the request is a websocket upgrade request.
                 if hyper_tungstenite::is_upgrade_request(&req) {
-                    if origin_header.is_none() {
+                    if token.is_none() && origin_header.is_none() {
                         log::error!("websocket connection is not set `Origin` header, which will be a hard error in the future.");
                     }
 
@@ -94,11 +177,36 @@ pub async fn make_http_server(
                         .body(Full::<Bytes>::from(frontend_html))
                         .unwrap();
                     Ok(res)
+                } else if req.uri().path() == "/timer" {
+                    // `GET /timer` reads the timer of the presentation, and `POST
+                    // /timer?action=<start|pause|toggle|reset>` controls it.
+                    let mut timer = timer.lock();
+                    let status = if req.method() == hyper::Method::POST {
+                        let action = query_param(req.uri().query(), "action");
+                        if action.is_some_and(|action| timer.apply(action)) {
+                            hyper::StatusCode::OK
+                        } else {
+                            hyper::StatusCode::BAD_REQUEST
+                        }
+                    } else {
+                        hyper::StatusCode::OK
+                    };
+                    let res = hyper::Response::builder()
+                        .status(status)
+                        .header(hyper::header::CONTENT_TYPE, "application/json")
+                        .header(hyper::header::CACHE_CONTROL, "no-store")
+                        .body(Full::<Bytes>::from(timer.to_json()))
+                        .unwrap();
+                    Ok(res)
                 } else {
-                    // jump to /
+                    // jump to /, keeping the token
+                    let location = match req.uri().query() {
+                        Some(query) => format!("/?{query}"),
+                        None => "/".to_owned(),
+                    };
                     let res = hyper::Response::builder()
                         .status(hyper::StatusCode::FOUND)
-                        .header(hyper::header::LOCATION, "/")
+                        .header(hyper::header::LOCATION, location)
                         .body(Full::<Bytes>::default())
                         .unwrap();
                     Ok(res)
@@ -161,10 +269,21 @@ pub async fn make_http_server(
         log::info!("Preview server joined");
     });
 
-    HttpServer {
+    Ok(HttpServer {
         addr,
         shutdown_tx,
         join,
+    })
+}
+
+/// Gets the address to bind, which takes a random port if the address has no
+/// port, e.g. `127.0.0.1` or `[::1]`.
+fn bind_addr(addr: &str) -> Cow<'_, str> {
+    match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => Cow::Owned(format!("[{ip}]:0")),
+        Ok(IpAddr::V4(ip)) => Cow::Owned(format!("{ip}:0")),
+        Err(_) if addr.ends_with(']') || !addr.contains(':') => Cow::Owned(format!("{addr}:0")),
+        Err(_) => Cow::Borrowed(addr),
     }
 }
 
@@ -240,6 +359,55 @@ mod tests {
         is_valid_origin(&HeaderValue::from_static(origin), static_file_addr, port)
     }
 
+    #[test]
+    fn test_presentation_timer() {
+        let mut timer = PresentationTimer::default();
+        assert!(timer.apply("toggle"));
+        assert!(timer.started.is_some());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(timer.apply("toggle"));
+        let elapsed = timer.elapsed();
+        assert!(elapsed >= Duration::from_millis(2));
+        assert_eq!(timer.elapsed(), elapsed);
+
+        assert!(!timer.apply("rewind"));
+        assert!(timer.apply("reset"));
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+        assert!(timer.started.is_none());
+    }
+
+    #[test]
+    fn test_bind_addr() {
+        assert_eq!(bind_addr("127.0.0.1"), "127.0.0.1:0");
+        assert_eq!(bind_addr("::1"), "[::1]:0");
+        assert_eq!(bind_addr("[::1]"), "[::1]:0");
+        assert_eq!(bind_addr("localhost"), "localhost:0");
+        assert_eq!(bind_addr("127.0.0.1:0"), "127.0.0.1:0");
+        assert_eq!(bind_addr("127.0.0.1:23625"), "127.0.0.1:23625");
+        assert_eq!(bind_addr("[::1]:23625"), "[::1]:23625");
+        assert_eq!(bind_addr("localhost:23625"), "localhost:23625");
+    }
+
+    #[test]
+    fn test_bind_taken_port() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let taken = taken.local_addr().unwrap().to_string();
+            let (tx, _rx) = mpsc::unbounded_channel();
+            let res = make_http_server(String::new(), taken, None, tx.clone()).await;
+            assert!(res.is_err(), "a taken port must not fall back");
+
+            let srv = make_http_server(String::new(), "127.0.0.1".into(), None, tx).await;
+            let srv = srv.expect("an address without port binds a random port");
+            assert_ne!(srv.addr.port(), 0);
+            let _ = srv.shutdown_tx.send(());
+        });
+    }
+
     #[test]
     fn test_valid_origin_localhost() {
         assert!(check_origin("http://127.0.0.1:42", "127.0.0.1:42", 42));
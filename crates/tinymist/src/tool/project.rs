@@ -2,20 +2,29 @@
 
 use std::{
     borrow::Cow,
+    collections::HashMap,
+    fmt::Write as _,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use clap::ValueEnum;
 use clap_complete::Shell;
 use parking_lot::Mutex;
 use reflexo::{path::unix_slash, ImmutPath};
-use reflexo_typst::WorldComputeGraph;
-use tinymist_query::analysis::Analysis;
+use reflexo_typst::vfs::WorkspaceResolver;
+use reflexo_typst::{EntryState, WorldComputeGraph};
+use serde::Serialize;
+use tinymist_query::{analysis::Analysis, PositionEncoding};
+use tinymist_std::typst::{TypstDocument, TypstPagedDocument};
 use tinymist_std::{bail, error::prelude::*};
 use tokio::sync::mpsc;
+use typst::diag::SourceDiagnostic;
 
+use crate::tool::word_count::{document_statistics, DEFAULT_WORDS_PER_MINUTE};
+use crate::utils::exit_on_ctrl_c;
 use crate::{actor::editor::EditorRequest, world::system::print_diagnostics, Config};
-use crate::{project::*, task::ExportTask};
+use crate::{project::*, task::ExportTask, timings::CompileTimer};
 
 /// Arguments for project compilation.
 #[derive(Debug, Clone, clap::Parser)]
@@ -32,16 +41,160 @@ pub struct CompileArgs {
     /// set, the lock file will be saved.
     #[clap(long)]
     pub lockfile: Option<PathBuf>,
+
+    /// Watches the files read by the compilation, including the package and
+    /// asset files, and recompiles the document on changes.
+    #[clap(long, short)]
+    pub watch: bool,
+
+    /// The format to print the diagnostics in.
+    #[clap(long, value_enum, default_value = "human")]
+    pub diagnostic_format: CompileDiagnosticFormat,
+
+    /// Prints the timings of the compilation and the export, and writes the
+    /// Chrome trace of them to the path if given, which can be viewed as a
+    /// flamegraph in `chrome://tracing` or Perfetto.
+    #[clap(long, value_name = "OUTPUT_JSON", num_args = 0..=1)]
+    pub timings: Option<Option<PathBuf>>,
+
+    /// Prints the numbers of the words, the characters and the sentences of
+    /// the document and its estimated reading time, per heading section.
+    #[clap(long)]
+    pub statistics: bool,
+}
+
+/// The format to print the diagnostics of a compilation in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompileDiagnosticFormat {
+    /// Prints the diagnostics along with the source code.
+    #[default]
+    Human,
+    /// Prints a line per diagnostic.
+    Short,
+    /// Prints the diagnostics of a compilation as a JSON array in a line. The
+    /// ranges are zero-based and the columns are counted in UTF-16 code
+    /// units, as in the language server protocol.
+    Json,
+}
+
+/// A diagnostic in the JSON output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonDiagnostic {
+    /// The path of the file, relative to the current directory if possible.
+    file: Option<String>,
+    range: lsp_types::Range,
+    severity: &'static str,
+    code: Option<lsp_types::NumberOrString>,
+    message: String,
+    /// The trace of the diagnostic and the imports of the file.
+    related_information: Vec<JsonRelatedInformation>,
+}
+
+/// A location related to a diagnostic in the JSON output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRelatedInformation {
+    file: Option<String>,
+    range: lsp_types::Range,
+    message: String,
+}
+
+/// Prints the diagnostics of a compilation to the stderr.
+fn report_diagnostics<'d>(
+    world: &LspWorld,
+    diags: impl Iterator<Item = &'d SourceDiagnostic>,
+    format: CompileDiagnosticFormat,
+) -> Result<()> {
+    let format = match format {
+        CompileDiagnosticFormat::Human => DiagnosticFormat::Human,
+        CompileDiagnosticFormat::Short => DiagnosticFormat::Short,
+        CompileDiagnosticFormat::Json => {
+            let cwd = std::env::current_dir().context("current directory")?;
+            let json = json_diagnostics(world, diags, &cwd);
+            eprintln!(
+                "{}",
+                serde_json::to_string(&json).context("serialize diagnostics")?
+            );
+            return Ok(());
+        }
+    };
+
+    print_diagnostics(world, diags, format).context_ut("print diagnostics")
+}
+
+/// Converts the diagnostics to the JSON output, whose paths are relative to
+/// the base directory if possible.
+fn json_diagnostics<'d>(
+    world: &LspWorld,
+    diags: impl Iterator<Item = &'d SourceDiagnostic>,
+    base: &Path,
+) -> Vec<JsonDiagnostic> {
+    let file = |uri: &lsp_types::Url| {
+        let path = uri.to_file_path().ok()?;
+        Some(unix_slash(path.strip_prefix(base).unwrap_or(&path)))
+    };
+
+    let diags = tinymist_query::convert_diagnostics(world, diags, PositionEncoding::Utf16);
+    let mut diags = diags
+        .iter()
+        .flat_map(|(uri, diags)| diags.iter().map(move |diag| (uri, diag)))
+        .map(|(uri, diag)| JsonDiagnostic {
+            file: file(uri),
+            range: diag.range,
+            severity: match diag.severity {
+                Some(lsp_types::DiagnosticSeverity::WARNING) => "warning",
+                Some(lsp_types::DiagnosticSeverity::INFORMATION) => "info",
+                Some(lsp_types::DiagnosticSeverity::HINT) => "hint",
+                _ => "error",
+            },
+            code: diag.code.clone(),
+            message: diag.message.clone(),
+            related_information: diag
+                .related_information
+                .iter()
+                .flatten()
+                .map(|info| JsonRelatedInformation {
+                    file: file(&info.location.uri),
+                    range: info.location.range,
+                    message: info.message.clone(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    // The diagnostics are grouped by files in a hash map.
+    diags.sort_by_key(|d| (d.file.clone(), d.range.start.line, d.range.start.character));
+
+    diags
+}
+
+/// The build system to generate a build script for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScriptFormat {
+    /// A shell script running the tasks in order.
+    #[default]
+    Shell,
+    /// A `justfile` with a recipe per task.
+    Just,
+    /// A `Makefile` with a rule per exported file.
+    Make,
+    /// A `build.ninja` with a build edge per exported file.
+    Ninja,
 }
 
 /// Arguments for generating a build script.
 #[derive(Debug, Clone, clap::Parser)]
 pub struct GenerateScriptArgs {
-    /// The shell to generate the completion script for. If not provided, it
-    /// will be inferred from the environment.
+    /// The shell to generate the shell script for. If not provided, it will
+    /// be inferred from the environment.
     #[clap(value_enum)]
     pub shell: Option<Shell>,
-    /// The path to the output script.
+    /// The build system to generate the script for. The tasks reading the
+    /// files exported by other tasks depend on those tasks.
+    #[clap(long, value_enum, default_value = "shell")]
+    pub format: ScriptFormat,
+    /// The path to the output script. Defaults to `build.sh` or `build.ps1`,
+    /// `justfile`, `Makefile` or `build.ninja`, according to the format.
     #[clap(short, long)]
     pub output: Option<String>,
 }
@@ -137,107 +290,245 @@ pub async fn compile_main(args: CompileArgs) -> Result<()> {
 
     // Prepares for the compilation
     let universe = (input, lock_dir.clone()).resolve()?;
+    if args.watch {
+        let lock = LockFile::read(&lock_dir).ok();
+        let lock_dir = save_lock.then_some(lock_dir);
+        let format = args.diagnostic_format;
+        return compile_watch(universe, output.task, lock, lock_dir, format).await;
+    }
+
     let world = universe.snapshot();
     let graph = WorldComputeGraph::from_world(world);
 
     // Compiles the project
+    let mut timer = args.timings.is_some().then(CompileTimer::start);
     let is_html = matches!(output.task, ProjectTask::ExportHtml(..));
     let compiled = CompiledArtifact::from_graph(graph, is_html);
+    if let Some(timer) = &mut timer {
+        timer.compiled();
+    }
+
+    // Reports the imports disagreeing with the pinned packages
+    let lock_diags = LockFile::read(&lock_dir)
+        .map(|lock| lock.check_imports(compiled.world()))
+        .unwrap_or_default();
 
-    let diag = compiled.diagnostics();
-    print_diagnostics(compiled.world(), diag, DiagnosticFormat::Human)
-        .context_ut("print diagnostics")?;
+    let diag = compiled.diagnostics().chain(lock_diags.iter());
+    report_diagnostics(compiled.world(), diag, args.diagnostic_format)?;
 
     if compiled.has_errors() {
         // todo: we should process case of compile error in fn main function
         std::process::exit(1);
     }
 
+    // Reports the savings of downscaling the images
+    if let (ProjectTask::ExportPdf(task), Some(TypstDocument::Paged(doc))) =
+        (&output.task, &compiled.doc)
+    {
+        if let Some(max_dpi) = task.image_max_dpi {
+            report_image_savings(doc, max_dpi);
+        }
+    }
+
+    // Reports the statistics of the document
+    if let (true, Some(doc)) = (args.statistics, &compiled.doc) {
+        document_statistics(compiled.world(), doc, DEFAULT_WORDS_PER_MINUTE).print();
+    }
+
     // Exports the compiled project
+    let graph = compiled.graph.clone();
     let lock_dir = save_lock.then_some(lock_dir);
+    let start = std::time::Instant::now();
     ExportTask::do_export(output.task, compiled, lock_dir).await?;
 
+    // Reports the timings
+    if let Some(mut timer) = timer {
+        timer.exported(start.elapsed());
+        let trace_output = args.timings.flatten();
+        timer
+            .finish(graph.world(), trace_output.as_deref())?
+            .print();
+    }
+
     Ok(())
 }
 
-/// Generates a build script for compilation
-pub fn generate_script_main(args: GenerateScriptArgs) -> Result<()> {
-    let Some(shell) = args.shell.or_else(Shell::from_env) else {
-        bail!("could not infer shell");
+/// Compiles the document on changes of the files read by the compilation and
+/// exports it if there is no error.
+async fn compile_watch(
+    verse: LspUniverse,
+    task: ProjectTask,
+    lock: Option<LockFile>,
+    lock_dir: Option<ImmutPath>,
+    format: CompileDiagnosticFormat,
+) -> Result<()> {
+    exit_on_ctrl_c();
+
+    let is_html = matches!(task, ProjectTask::ExportHtml(..));
+    let opts = ProjectOpts {
+        export_target: if is_html {
+            ExportTarget::Html
+        } else {
+            ExportTarget::Paged
+        },
+        ..ProjectOpts::default()
     };
-    let output = Path::new(args.output.as_deref().unwrap_or("build"));
 
-    let output = match shell {
-        Shell::Bash | Shell::Zsh | Shell::Elvish | Shell::Fish => output.with_extension("sh"),
-        Shell::PowerShell => output.with_extension("ps1"),
-        _ => bail!("unsupported shell: {shell:?}"),
-    };
+    let handle = tokio::runtime::Handle::current();
+    let StartProjectResult {
+        service,
+        mut editor_rx,
+        ..
+    } = start_project(verse, Some(opts), move |c, mut i, next| {
+        if let Interrupt::Compiled(artifact) = &mut i {
+            // Clear the screen and then move the cursor to the top left corner,
+            // while the JSON diagnostics are printed in a line per compilation.
+            if format != CompileDiagnosticFormat::Json {
+                eprint!("\x1B[2J\x1B[1;1H");
+            }
 
-    let script = match shell {
-        Shell::Bash | Shell::Zsh | Shell::PowerShell => shell_build_script(shell)?,
-        _ => bail!("unsupported shell: {shell:?}"),
-    };
+            let world = artifact.world();
+            let lock_diags = lock
+                .as_ref()
+                .map(|lock| lock.check_imports(world))
+                .unwrap_or_default();
+            let diags = artifact.diagnostics().chain(lock_diags.iter());
+            if let Err(err) = report_diagnostics(world, diags, format) {
+                log::error!("failed to print diagnostics: {err}");
+            }
 
-    std::fs::write(output, script).context("write script")?;
+            let has_errors = artifact.has_errors();
+            let status = if has_errors {
+                "compiled with errors"
+            } else if artifact.diagnostics().next().is_some() || !lock_diags.is_empty() {
+                "compiled with warnings"
+            } else {
+                "compiled successfully"
+            };
+            eprintln!("{status}");
+
+            let (task, artifact, lock_dir) = (task.clone(), artifact.clone(), lock_dir.clone());
+            handle.spawn(async move {
+                if !has_errors {
+                    match ExportTask::do_export(task, artifact, lock_dir).await {
+                        Ok(Some(path)) => eprintln!("exported to {}", path.display()),
+                        Ok(None) => {}
+                        Err(err) => eprintln!("failed to export: {err}"),
+                    }
+                }
+                eprintln!("watching for changes, press Ctrl-C to exit");
+            });
+        }
 
-    Ok(())
-}
+        next(c, i)
+    });
 
-/// Generates a build script for shell-like shells
-fn shell_build_script(shell: Shell) -> Result<String> {
-    let mut output = String::new();
+    // Consume editor_rx
+    tokio::spawn(async move { while editor_rx.recv().await.is_some() {} });
 
-    match shell {
-        Shell::Bash => {
-            output.push_str("#!/usr/bin/env bash\n\n");
-        }
-        Shell::Zsh => {
-            output.push_str("#!/usr/bin/env zsh\n\n");
-        }
-        Shell::PowerShell => {}
-        _ => {}
-    }
+    service.run().await;
 
-    let lock_dir = std::env::current_dir().context("current directory")?;
+    Ok(())
+}
 
-    let lock = LockFile::read(&lock_dir)?;
+/// Prints the savings of downscaling the images exceeding the resolution.
+fn report_image_savings(doc: &TypstPagedDocument, max_dpi: u32) {
+    let (_, savings) = tinymist_task::optimize_images(doc, max_dpi);
+
+    let kib = |len: usize| len as f64 / 1024.;
+    let mut total = 0;
+    for saving in &savings {
+        let (w, h) = saving.original;
+        let (new_w, new_h) = saving.optimized;
+        eprintln!(
+            "page {}: downscaled image from {w}x{h} ({:.0} dpi, {:.1} KiB) to {new_w}x{new_h} ({max_dpi} dpi, {:.1} KiB)",
+            saving.page,
+            saving.dpi,
+            kib(saving.original_len),
+            kib(saving.optimized_len),
+        );
+        total += saving.original_len - saving.optimized_len;
+    }
 
-    struct CmdBuilder(Vec<Cow<'static, str>>);
+    if !savings.is_empty() {
+        eprintln!(
+            "downscaled {} image(s), saving {:.1} KiB",
+            savings.len(),
+            kib(total)
+        );
+    }
+}
 
-    impl CmdBuilder {
-        fn new() -> Self {
-            Self(vec![])
+/// Generates a build script for compilation
+pub fn generate_script_main(args: GenerateScriptArgs) -> Result<()> {
+    let (script, default_output) = match args.format {
+        ScriptFormat::Shell => {
+            let Some(shell) = args.shell.or_else(Shell::from_env) else {
+                bail!("could not infer shell");
+            };
+            let output = Path::new(args.output.as_deref().unwrap_or("build"));
+            let output = match shell {
+                Shell::Bash | Shell::Zsh => output.with_extension("sh"),
+                Shell::PowerShell => output.with_extension("ps1"),
+                _ => bail!("unsupported shell: {shell:?}"),
+            };
+            (shell_build_script(shell, &build_steps()?), output)
         }
+        ScriptFormat::Just => (just_build_script(&build_steps()?), "justfile".into()),
+        ScriptFormat::Make => (make_build_script(&build_steps()?), "Makefile".into()),
+        ScriptFormat::Ninja => (ninja_build_script(&build_steps()?), "build.ninja".into()),
+    };
+    let output = match args.format {
+        ScriptFormat::Shell => default_output,
+        _ => args.output.map_or(default_output, PathBuf::from),
+    };
 
-        fn extend(&mut self, args: impl IntoIterator<Item = impl Into<Cow<'static, str>>>) {
-            for arg in args {
-                self.0.push(arg.into());
-            }
-        }
+    std::fs::write(output, script).context("write script")?;
 
-        fn push(&mut self, arg: impl Into<Cow<'static, str>>) {
-            self.0.push(arg.into());
-        }
+    Ok(())
+}
 
-        fn build(self) -> String {
-            self.0.join(" ")
-        }
-    }
+/// A compile command running an export task of the lock file.
+struct BuildStep {
+    /// The name of the step, i.e. the id of the task.
+    name: String,
+    /// The description of the step.
+    comment: String,
+    /// The arguments of the command, which are not quoted.
+    args: Vec<String>,
+    /// The exported file, relative to the lock directory.
+    output: Option<String>,
+    /// The workspace files read by the compilation, relative to the lock
+    /// directory.
+    inputs: Vec<String>,
+    /// The steps exporting the files read by the compilation.
+    deps: Vec<usize>,
+}
 
-    let quote_escape = |s: &str| s.replace("'", r#"'"'"'"#);
-    let quote = |s: &str| format!("'{}'", s.replace("'", r#"'"'"'"#));
+/// Collects the compile commands of the export tasks in the lock file, along
+/// with the files they read and write, in an order where the steps come after
+/// the steps they depend on.
+fn build_steps() -> Result<Vec<BuildStep>> {
+    let lock_dir = std::env::current_dir().context("current directory")?;
+    let lock_dir: ImmutPath = lock_dir.into();
 
+    let lock = LockFile::read(&lock_dir)?;
+
+    let rel_path = |path: &Path| unix_slash(path.strip_prefix(&lock_dir).unwrap_or(path));
     let path_of = |p: &ResourcePath, loc: &str| {
         let Some(path) = p.to_rel_path(&lock_dir) else {
             log::error!("could not resolve path for {loc}, path: {p:?}");
             return String::default();
         };
 
-        quote(&unix_slash(&path))
+        unix_slash(&path)
     };
 
     let base_cmd: Vec<&str> = vec!["tinymist", "compile", "--save-lock"];
 
+    // The documents are compiled once to collect the files they read.
+    let mut compiled = HashMap::new();
+    let mut steps = vec![];
     for task in lock.task.iter() {
         let Some(input) = lock.get_document(&task.document) else {
             log::warn!(
@@ -252,58 +543,53 @@ fn shell_build_script(shell: Shell) -> Result<String> {
             continue;
         };
 
-        let mut cmd = CmdBuilder::new();
-        cmd.extend(base_cmd.iter().copied());
-        cmd.push("--task");
-        cmd.push(quote(&task.id.to_string()));
+        let mut cmd: Vec<String> = base_cmd.iter().map(|s| s.to_string()).collect();
+        cmd.push("--task".into());
+        cmd.push(task.id.to_string());
 
         cmd.push(path_of(&input.main, "main"));
 
         if let Some(root) = &input.root {
-            cmd.push("--root");
+            cmd.push("--root".into());
             cmd.push(path_of(root, "root"));
         }
 
         for (k, v) in &input.inputs {
-            cmd.push(format!(
-                r#"--input='{}={}'"#,
-                quote_escape(k),
-                quote_escape(v)
-            ));
+            cmd.push(format!("--input={k}={v}"));
         }
 
         for p in &input.font_paths {
-            cmd.push("--font-path");
+            cmd.push("--font-path".into());
             cmd.push(path_of(p, "font-path"));
         }
 
         if !input.system_fonts {
-            cmd.push("--ignore-system-fonts");
+            cmd.push("--ignore-system-fonts".into());
         }
 
         if let Some(p) = &input.package_path {
-            cmd.push("--package-path");
+            cmd.push("--package-path".into());
             cmd.push(path_of(p, "package-path"));
         }
 
         if let Some(p) = &input.package_cache_path {
-            cmd.push("--package-cache-path");
+            cmd.push("--package-cache-path".into());
             cmd.push(path_of(p, "package-cache-path"));
         }
 
         if let Some(p) = &export.output {
-            cmd.push("--output");
-            cmd.push(quote(&p.to_string()));
+            cmd.push("--output".into());
+            cmd.push(p.to_string());
         }
 
         for t in &export.transform {
             match t {
                 ExportTransform::Pretty { .. } => {
-                    cmd.push("--pretty");
+                    cmd.push("--pretty".into());
                 }
                 ExportTransform::Pages { ranges } => {
                     for r in ranges {
-                        cmd.push("--pages");
+                        cmd.push("--pages".into());
                         cmd.push(r.to_string());
                     }
                 }
@@ -315,54 +601,399 @@ fn shell_build_script(shell: Shell) -> Result<String> {
         match &task.task {
             ProjectTask::Preview(..) | ProjectTask::Query(..) => {}
             ProjectTask::ExportPdf(task) => {
-                cmd.push("--format=pdf");
+                cmd.push("--format=pdf".into());
 
                 for s in &task.pdf_standards {
-                    cmd.push("--pdf-standard");
-                    let s = serde_json::to_string(s).context("pdf standard")?;
-                    cmd.push(s);
+                    cmd.push("--pdf-standard".into());
+                    let s = serde_json::to_value(s).context("pdf standard")?;
+                    cmd.push(s.as_str().unwrap_or_default().to_owned());
                 }
 
                 if let Some(output) = &task.creation_timestamp {
-                    cmd.push("--creation-timestamp");
+                    cmd.push("--creation-timestamp".into());
                     cmd.push(output.to_string());
                 }
+
+                if let Some(max_dpi) = &task.image_max_dpi {
+                    cmd.push("--image-max-dpi".into());
+                    cmd.push(max_dpi.to_string());
+                }
+
+                if task.tagged {
+                    cmd.push("--pdf-tagged".into());
+                }
             }
-            ProjectTask::ExportSvg(..) => {
-                cmd.push("--format=svg");
+            ProjectTask::ExportSvg(task) => {
+                cmd.push("--format=svg".into());
+
+                if let Some(template) = &task.page_template {
+                    cmd.push("--svg-page-template".into());
+                    cmd.push(template.to_string());
+                }
             }
             ProjectTask::ExportSvgHtml(..) => {
-                cmd.push("--format=svg_html");
+                cmd.push("--format=svg_html".into());
             }
-            ProjectTask::ExportMd(..) => {
-                cmd.push("--format=md");
+            ProjectTask::ExportMd(task) => {
+                cmd.push("--format=md".into());
+
+                if let Some(processor) = &task.processor {
+                    cmd.push("--md-processor".into());
+                    cmd.push(processor.to_string());
+                }
+                if let Some(assets_path) = &task.assets_path {
+                    cmd.push("--md-assets-path".into());
+                    cmd.push(unix_slash(assets_path));
+                }
+                let lowerings = [
+                    ("--md-math", task.math.to_possible_value()),
+                    ("--md-figure", task.figure.to_possible_value()),
+                    ("--md-raw", task.raw.to_possible_value()),
+                ];
+                for (flag, value) in lowerings {
+                    if let Some(value) = value {
+                        cmd.push(flag.into());
+                        cmd.push(value.get_name().to_owned());
+                    }
+                }
             }
             ProjectTask::ExportTeX(..) => {
-                cmd.push("--format=tex");
+                cmd.push("--format=tex".into());
+            }
+            ProjectTask::ExportDocx(task) => {
+                cmd.push("--format=docx".into());
+
+                if let Some(processor) = &task.processor {
+                    cmd.push("--docx-processor".into());
+                    cmd.push(processor.to_string());
+                }
             }
-            ProjectTask::ExportPng(..) => {
-                cmd.push("--format=png");
+            ProjectTask::ExportPng(task) => {
+                cmd.push("--format=png".into());
+
+                cmd.push("--ppi".into());
+                cmd.push(task.ppi.to_f32().to_string());
+                if let Some(template) = &task.page_template {
+                    cmd.push("--png-page-template".into());
+                    cmd.push(template.to_string());
+                }
             }
             ProjectTask::ExportText(..) => {
-                cmd.push("--format=txt");
+                cmd.push("--format=txt".into());
             }
-            ProjectTask::ExportHtml(..) => {
-                cmd.push("--format=html");
+            ProjectTask::ExportHtml(task) => {
+                cmd.push("--format=html".into());
+
+                if let Some(hook) = &task.post_export {
+                    cmd.push("--post-export".into());
+                    cmd.push(hook.to_string());
+                }
+                if task.heading_anchors != HeadingAnchors::default() {
+                    if let Some(value) = task.heading_anchors.to_possible_value() {
+                        cmd.push("--heading-anchors".into());
+                        cmd.push(value.get_name().to_owned());
+                    }
+                }
             }
         }
 
+        if !compiled.contains_key(&task.document) {
+            let result = compile_for_inputs(input, &lock_dir);
+            if let Err(err) = &result {
+                log::warn!("could not compile document {:?}: {err}", task.document);
+            }
+            compiled.insert(task.document.clone(), result.ok());
+        }
+        let (entry, inputs) = match &compiled[&task.document] {
+            Some((entry, inputs)) => (Some(entry), inputs.iter().map(|p| rel_path(p)).collect()),
+            None => (None, vec![path_of(&input.main, "main")]),
+        };
+        let output = entry.and_then(|entry| {
+            let output = export.output.clone().unwrap_or_default();
+            let output = output.substitute(entry)?;
+            Some(rel_path(&output.with_extension(task.task.extension())))
+        });
+
         let ext = task.task.extension();
+        steps.push(BuildStep {
+            name: task.id.to_string(),
+            comment: format!("From {} to {} ({ext})", task.doc_id(), task.id),
+            args: cmd,
+            output,
+            inputs,
+            deps: vec![],
+        });
+    }
+
+    for idx in 0..steps.len() {
+        let deps = (0..steps.len()).filter(|&dep| {
+            dep != idx
+                && steps[dep]
+                    .output
+                    .as_ref()
+                    .is_some_and(|output| steps[idx].inputs.contains(output))
+        });
+        steps[idx].deps = deps.collect();
+    }
+
+    Ok(sort_steps(steps))
+}
+
+/// Compiles a document and collects the workspace files read by the
+/// compilation.
+fn compile_for_inputs(
+    input: &ProjectInput,
+    lock_dir: &ImmutPath,
+) -> Result<(EntryState, Vec<PathBuf>)> {
+    let verse = (input.clone(), lock_dir.clone()).resolve()?;
+    let graph = WorldComputeGraph::from_world(verse.snapshot());
+    let compiled = CompiledArtifact::from_graph(graph, false);
+    let world = compiled.world();
+
+    let mut inputs = compiled
+        .depended_files()
+        .iter()
+        .filter(|fid| !WorkspaceResolver::is_package_file(**fid))
+        .filter_map(|fid| world.path_for_id(*fid).ok())
+        .map(|path| path.as_path().to_owned())
+        .collect::<Vec<_>>();
+    inputs.sort();
+    inputs.dedup();
+
+    Ok((world.entry_state(), inputs))
+}
+
+/// Sorts the steps so that the steps come after the steps they depend on,
+/// keeping the order of the lock file otherwise.
+fn sort_steps(steps: Vec<BuildStep>) -> Vec<BuildStep> {
+    let mut order = vec![];
+    let mut state = vec![0u8; steps.len()]; // 0: unvisited, 1: visiting, 2: visited
+    fn visit(steps: &[BuildStep], idx: usize, state: &mut [u8], order: &mut Vec<usize>) {
+        match state[idx] {
+            1 => {
+                log::warn!("the task {} depends on itself", steps[idx].name);
+                return;
+            }
+            2 => return,
+            _ => {}
+        }
+        state[idx] = 1;
+        for &dep in &steps[idx].deps {
+            visit(steps, dep, state, order);
+        }
+        state[idx] = 2;
+        order.push(idx);
+    }
+    for idx in 0..steps.len() {
+        visit(&steps, idx, &mut state, &mut order);
+    }
+
+    // Remaps the dependencies to the sorted indices.
+    let mut position = vec![0; steps.len()];
+    for (pos, &idx) in order.iter().enumerate() {
+        position[idx] = pos;
+    }
+    let mut steps = steps.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .iter()
+        .map(|&idx| {
+            let mut step = steps[idx].take().unwrap();
+            step.deps = step.deps.iter().map(|&dep| position[dep]).collect();
+            step
+        })
+        .collect()
+}
+
+/// Quotes an argument for POSIX shells if necessary.
+fn quote_sh(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.into();
+    }
+    format!("'{}'", arg.replace("'", r#"'"'"'"#)).into()
+}
+
+/// Quotes an argument for PowerShell if necessary.
+fn quote_ps(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.into();
+    }
+    format!("'{}'", arg.replace("'", "''")).into()
+}
+
+fn command(step: &BuildStep, quote: impl Fn(&str) -> Cow<'_, str>) -> String {
+    step.args
+        .iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Generates a build script for shell-like shells
+fn shell_build_script(shell: Shell, steps: &[BuildStep]) -> String {
+    let mut output = String::new();
+
+    match shell {
+        Shell::Bash => {
+            output.push_str("#!/usr/bin/env bash\n\n");
+        }
+        Shell::Zsh => {
+            output.push_str("#!/usr/bin/env zsh\n\n");
+        }
+        Shell::PowerShell => {}
+        _ => {}
+    }
+
+    for step in steps {
+        let cmd = match shell {
+            Shell::PowerShell => command(step, quote_ps),
+            _ => command(step, quote_sh),
+        };
+        let _ = writeln!(output, "# {}", step.comment);
+        let _ = writeln!(output, "{cmd}");
+    }
+
+    output
+}
+
+/// Gets the names of the steps as the names of the recipes or the targets,
+/// which are unique and contain only the letters, the digits, `-` and `_`.
+fn step_names(steps: &[BuildStep]) -> Vec<String> {
+    let mut names = Vec::<String>::with_capacity(steps.len());
+    for step in steps {
+        let name = step
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>();
+        let name = name.trim_matches('-');
+        let name = if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            name.to_owned()
+        } else {
+            format!("task-{name}")
+        };
+
+        let mut unique = name.clone();
+        let mut counter = 1;
+        while names.contains(&unique) {
+            counter += 1;
+            unique = format!("{name}-{counter}");
+        }
+        names.push(unique);
+    }
+    names
+}
+
+/// Generates a `justfile` with a recipe per task, depending on the recipes
+/// exporting the files it reads.
+fn just_build_script(steps: &[BuildStep]) -> String {
+    let names = step_names(steps);
+    let mut output = String::new();
+
+    let _ = writeln!(output, "default: {}", names.join(" "));
+    for (step, name) in steps.iter().zip(&names) {
+        let deps = step.deps.iter().map(|&dep| names[dep].as_str());
+        let deps = deps.collect::<Vec<_>>().join(" ");
+        // `{{` starts an interpolation in the recipes.
+        let cmd = command(step, quote_sh).replace("{{", "{{{{");
+
+        let _ = writeln!(output, "\n# {}", step.comment);
+        let _ = writeln!(output, "{}", format!("{name}: {deps}").trim_end());
+        let _ = writeln!(output, "    {cmd}");
+    }
 
-        output.push_str(&format!(
-            "# From {} to {} ({ext})\n",
-            task.doc_id(),
-            task.id
-        ));
-        output.push_str(&cmd.build());
-        output.push('\n');
+    output
+}
+
+/// Generates a `Makefile` with a rule per exported file, depending on the
+/// files read by the compilation.
+fn make_build_script(steps: &[BuildStep]) -> String {
+    let names = step_names(steps);
+    let targets = steps
+        .iter()
+        .zip(&names)
+        .map(|(step, name)| step.output.as_deref().map_or(name.clone(), escape_make))
+        .collect::<Vec<_>>();
+    // The tasks without a known output are always run.
+    let phony = steps
+        .iter()
+        .zip(&names)
+        .filter(|(step, _)| step.output.is_none())
+        .map(|(_, name)| name.as_str());
+
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        ".PHONY: all {}",
+        phony.collect::<Vec<_>>().join(" ")
+    );
+    let _ = writeln!(output, "all: {}", targets.join(" "));
+    for (step, target) in steps.iter().zip(&targets) {
+        let inputs = step.inputs.iter().map(|input| escape_make(input));
+        let inputs = inputs.collect::<Vec<_>>().join(" ");
+        let cmd = command(step, quote_sh).replace('$', "$$");
+
+        let _ = writeln!(output, "\n# {}", step.comment);
+        let _ = writeln!(output, "{}", format!("{target}: {inputs}").trim_end());
+        let _ = writeln!(output, "\t{cmd}");
+    }
+
+    output
+}
+
+/// Generates a `build.ninja` with a build edge per exported file, depending on
+/// the files read by the compilation.
+fn ninja_build_script(steps: &[BuildStep]) -> String {
+    let names = step_names(steps);
+    let targets = steps
+        .iter()
+        .zip(&names)
+        .map(|(step, name)| step.output.as_deref().map_or(name.clone(), escape_ninja))
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+    let _ = writeln!(output, "rule tinymist");
+    let _ = writeln!(output, "  command = $cmd");
+    let _ = writeln!(output, "  description = $desc");
+    for (step, target) in steps.iter().zip(&targets) {
+        let inputs = step.inputs.iter().map(|input| escape_ninja(input));
+        let inputs = inputs.collect::<Vec<_>>().join(" ");
+        let cmd = command(step, quote_sh).replace('$', "$$");
+
+        let _ = writeln!(output, "\n# {}", step.comment);
+        let _ = writeln!(
+            output,
+            "{}",
+            format!("build {target}: tinymist {inputs}").trim_end()
+        );
+        let _ = writeln!(output, "  cmd = {cmd}");
+        let _ = writeln!(output, "  desc = {}", step.comment.replace('$', "$$"));
     }
+    let _ = writeln!(output, "\ndefault {}", targets.join(" "));
 
-    Ok(output)
+    output
+}
+
+/// Escapes a path in the targets or the prerequisites of a `Makefile`.
+fn escape_make(path: &str) -> String {
+    path.replace('$', "$$")
+        .replace(' ', "\\ ")
+        .replace('#', "\\#")
+        .replace(':', "\\:")
+}
+
+/// Escapes a path in the outputs or the inputs of a `build.ninja`.
+fn escape_ninja(path: &str) -> String {
+    path.replace('$', "$$")
+        .replace(' ', "$ ")
+        .replace(':', "$:")
 }
 
 /// Project document commands' main
@@ -464,6 +1095,7 @@ where
         analysis: opts.analysis,
         status_revision: Mutex::default(),
         notified_revision: Mutex::default(),
+        lock_file: Mutex::default(),
     });
 
     let mut compiler = ProjectCompiler::new(
@@ -522,3 +1154,123 @@ where
         log::info!("Project compiler exited");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_quoting() {
+        assert_eq!(quote_sh("--input=a=b"), "--input=a=b");
+        assert_eq!(quote_sh("a b"), "'a b'");
+        assert_eq!(quote_sh("it's"), r#"'it'"'"'s'"#);
+        assert_eq!(quote_sh(""), "''");
+        assert_eq!(quote_ps("it's"), "'it''s'");
+        assert_eq!(quote_ps("@x"), "'@x'");
+
+        let step = |name: &str| BuildStep {
+            name: name.into(),
+            comment: String::new(),
+            args: vec![],
+            output: None,
+            inputs: vec![],
+            deps: vec![],
+        };
+        let names = step_names(&[step("main.pdf"), step("main:pdf"), step("1")]);
+        assert_eq!(names, ["main-pdf", "main-pdf-2", "task-1"]);
+    }
+
+    #[test]
+    fn path_escaping() {
+        assert_eq!(escape_make("out/main.pdf"), "out/main.pdf");
+        assert_eq!(escape_make("my doc.pdf"), r"my\ doc.pdf");
+        assert_eq!(escape_make("$HOME.pdf"), "$$HOME.pdf");
+        assert_eq!(escape_make("C:/doc#1.pdf"), r"C\:/doc\#1.pdf");
+
+        assert_eq!(escape_ninja("out/main.pdf"), "out/main.pdf");
+        assert_eq!(escape_ninja("my doc.pdf"), "my$ doc.pdf");
+        assert_eq!(escape_ninja("$HOME.pdf"), "$$HOME.pdf");
+        assert_eq!(escape_ninja("C:/doc.pdf"), "C$:/doc.pdf");
+    }
+
+    #[test]
+    fn json_diagnostic_format() {
+        let root = std::env::temp_dir().join("tinymist-json-diagnostics");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("main.typ"), "#include \"lib.typ\"\n#undefined\n").unwrap();
+        std::fs::write(root.join("lib.typ"), "**\n").unwrap();
+
+        let entry = EntryState::new_rooted(
+            root.as_path().into(),
+            Some(typst::syntax::VirtualPath::new("main.typ")),
+        );
+        let fonts = Arc::new(LspUniverseBuilder::only_embedded_fonts().unwrap());
+        let verse = LspUniverseBuilder::build(
+            entry,
+            ExportTarget::Paged,
+            Default::default(),
+            Default::default(),
+            LspUniverseBuilder::resolve_package(None, None),
+            fonts,
+        );
+        let world = verse.snapshot();
+        let doc = typst::compile::<TypstPagedDocument>(&world);
+        let errors = doc.output.as_ref().err().into_iter().flatten();
+
+        let diags = json_diagnostics(&world, doc.warnings.iter().chain(errors), &root);
+        let json = serde_json::to_string_pretty(&diags).unwrap();
+        insta::assert_snapshot!(json, @r###"
+        [
+          {
+            "file": "lib.typ",
+            "range": {
+              "start": {
+                "line": 0,
+                "character": 0
+              },
+              "end": {
+                "line": 0,
+                "character": 2
+              }
+            },
+            "severity": "warning",
+            "code": null,
+            "message": "no text within stars\nHint: using multiple consecutive stars (e.g. **) has no additional effect",
+            "relatedInformation": [
+              {
+                "file": "main.typ",
+                "range": {
+                  "start": {
+                    "line": 0,
+                    "character": 1
+                  },
+                  "end": {
+                    "line": 0,
+                    "character": 18
+                  }
+                },
+                "message": "`/lib.typ` is included here"
+              }
+            ]
+          },
+          {
+            "file": "main.typ",
+            "range": {
+              "start": {
+                "line": 1,
+                "character": 1
+              },
+              "end": {
+                "line": 1,
+                "character": 10
+              }
+            },
+            "severity": "error",
+            "code": null,
+            "message": "unknown variable: undefined",
+            "relatedInformation": []
+          }
+        ]
+        "###);
+    }
+}
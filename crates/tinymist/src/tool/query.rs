@@ -0,0 +1,91 @@
+//! Queries the elements of a compiled document by a selector, e.g. for
+//! extracting the metadata of a document in a build pipeline.
+
+use std::path::PathBuf;
+
+use clap::ValueHint;
+use reflexo::ImmutPath;
+use reflexo_typst::WorldComputeGraph;
+use tinymist_std::error::prelude::*;
+
+use crate::project::{ExportTask as ProjectExportTask, *};
+use crate::task::ExportTask;
+use crate::world::system::print_diagnostics;
+
+/// Arguments for querying the elements of a document.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct QuerySelectorArgs {
+    /// The selector of the elements to retrieve, e.g. `heading`, `<label>`,
+    /// or `metadata.where(value: "x")`.
+    pub selector: String,
+
+    /// Argument to identify the document.
+    #[clap(flatten)]
+    pub declare: DocNewArgs,
+
+    /// The format to serialize the elements in.
+    #[clap(long, short, default_value = "json", value_parser = ["json", "yaml", "csv", "txt"])]
+    pub format: String,
+
+    /// Extracts just one field from all retrieved elements.
+    #[clap(long)]
+    pub field: Option<String>,
+
+    /// Expects and retrieves exactly one element.
+    #[clap(long)]
+    pub one: bool,
+
+    /// Pretty prints the JSON output.
+    #[clap(long)]
+    pub pretty: bool,
+
+    /// Queries the HTML document instead of the paged document.
+    #[clap(long)]
+    pub html: bool,
+
+    /// Path to write the result to. Prints the result to stdout if not
+    /// provided.
+    #[clap(long, short, value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+/// Queries the elements of a document and prints them.
+pub fn query_selector_main(args: QuerySelectorArgs) -> Result<()> {
+    let input = args.declare.to_input();
+    let lock_dir: ImmutPath = std::env::current_dir().context("lock directory")?.into();
+    let verse = (input, lock_dir).resolve()?;
+
+    let graph = WorldComputeGraph::from_world(verse.snapshot());
+    let compiled = CompiledArtifact::from_graph(graph, args.html);
+    print_diagnostics(
+        compiled.world(),
+        compiled.diagnostics(),
+        DiagnosticFormat::Human,
+    )
+    .context_ut("print diagnostics")?;
+    if compiled.has_errors() {
+        bail!("compilation failed");
+    }
+
+    let mut export = ProjectExportTask::new(TaskWhen::Never);
+    if args.pretty {
+        export.apply_pretty();
+    }
+    let task = ProjectTask::Query(QueryTask {
+        export,
+        format: args.format,
+        output_extension: None,
+        selector: args.selector,
+        field: args.field,
+        one: args.one,
+    });
+
+    let doc = compiled.doc.as_ref().context("no document")?;
+    let bytes = ExportTask::export_bytes(task, &compiled.graph, doc)?;
+    match &args.output {
+        Some(path) => std::fs::write(path, bytes).context("write query result")?,
+        None => println!("{}", String::from_utf8_lossy(&bytes)),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Tests the analyses of the language server on random documents.
+
+use std::sync::Arc;
+
+use tinymist_query::analysis::{fuzz_analysis, Analysis};
+use tinymist_std::error::prelude::*;
+use typst::syntax::VirtualPath;
+
+use crate::project::*;
+use crate::{CompileFontArgs, CompilePackageArgs};
+
+/// Arguments for testing the analyses on random documents.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct SelfTestArgs {
+    /// The seed of the first document.
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// The number of the documents to test.
+    #[clap(long, default_value_t = 1000)]
+    pub cases: u64,
+
+    /// The arguments to resolve fonts.
+    #[clap(flatten)]
+    pub font: CompileFontArgs,
+
+    /// The arguments to resolve packages.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+}
+
+/// Tests the analyses on random documents, and fails if they panic on any of
+/// them.
+pub fn self_test_main(args: SelfTestArgs) -> Result<()> {
+    let root = std::env::temp_dir().join("tinymist-self-test");
+    let entry = EntryState::new_rooted(
+        root.as_path().into(),
+        Some(VirtualPath::new("__fuzz__.typ")),
+    );
+    let fonts = Arc::new(LspUniverseBuilder::resolve_fonts(args.font)?);
+    let packages = LspUniverseBuilder::resolve_package(None, Some(&args.package));
+    let verse = LspUniverseBuilder::build(
+        entry,
+        ExportTarget::Paged,
+        Default::default(),
+        Default::default(),
+        packages,
+        fonts,
+    );
+
+    // The panics are reported with their documents below.
+    let prev_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let seeds = args.seed..args.seed.saturating_add(args.cases);
+    let failures = fuzz_analysis(&Analysis::default(), &verse.snapshot(), seeds);
+    std::panic::set_hook(prev_hook);
+
+    for failure in &failures {
+        eprintln!("seed {}: {}", failure.seed, failure.message);
+        eprintln!("{}\n", failure.source);
+    }
+    if !failures.is_empty() {
+        bail!("{} of {} documents failed", failures.len(), args.cases);
+    }
+
+    eprintln!("{} documents passed", args.cases);
+    Ok(())
+}
@@ -0,0 +1,628 @@
+//! A compile server exposing the compilation, export and query of documents as
+//! a JSON API over HTTP, so that CI machines and thin editors can offload the
+//! compilation to a shared server whose fonts and packages are kept warm.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{header, Method, Request, Response, StatusCode, Uri};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use parking_lot::Mutex;
+use reflexo_typst::WorldComputeGraph;
+use serde::{Deserialize, Serialize};
+use tinymist_std::error::prelude::*;
+use tinymist_std::typst::TypstDocument;
+use typst::diag::{Severity, SourceDiagnostic};
+use typst::foundations::{Dict, Str, Value};
+use typst::syntax::VirtualPath;
+use typst::utils::LazyHash;
+use typst::World;
+
+use crate::project::*;
+use crate::task::ExportTask;
+use crate::utils::{generate_token, has_token, is_loopback_addr};
+use crate::world::font::FontResolverImpl;
+use crate::{CompileFontArgs, CompilePackageArgs};
+
+/// Arguments for running the compile server.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ServeArgs {
+    /// The address to serve the JSON API over HTTP, e.g. `127.0.0.1:23627`,
+    /// or `0.0.0.0:23627` to serve the network, which requires a token.
+    #[clap(long)]
+    pub http: String,
+
+    /// Requires the token in the URLs of the requests, e.g.
+    /// `http://127.0.0.1:23627/compile?token=<TOKEN>`. A token is generated
+    /// if it is not given. The token is always required if the server is
+    /// bound to an address reachable from the network.
+    #[clap(
+        long = "token",
+        value_name = "TOKEN",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    pub token: Option<String>,
+
+    /// The root directory of the documents, to which the paths of the main
+    /// files in the requests are relative. Defaults to the current directory.
+    #[clap(long)]
+    pub root: Option<PathBuf>,
+
+    /// The arguments to resolve fonts.
+    #[clap(flatten)]
+    pub font: CompileFontArgs,
+
+    /// The arguments to resolve packages.
+    #[clap(flatten)]
+    pub package: CompilePackageArgs,
+}
+
+impl ServeArgs {
+    /// Resolves the token required by the compile server.
+    pub fn resolve_token(&self) -> Option<String> {
+        match self.token.as_deref() {
+            Some("") => Some(generate_token()),
+            Some(token) => Some(token.to_owned()),
+            None if !is_loopback_addr(&self.http) => Some(generate_token()),
+            None => None,
+        }
+    }
+}
+
+/// The document to compile in a request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentParams {
+    /// The path of the main file, relative to the root.
+    main: String,
+    /// The `sys.inputs` of the document.
+    #[serde(default)]
+    inputs: BTreeMap<String, String>,
+}
+
+/// The parameters of the `/export` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportParams {
+    #[serde(flatten)]
+    doc: DocumentParams,
+    /// The format to export, one of `pdf`, `png`, `svg`, `html`, `md`,
+    /// `text` and `docx`.
+    format: String,
+    /// The pixels per inch of the PNG export.
+    ppi: Option<f32>,
+}
+
+/// The parameters of the `/query` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryParams {
+    #[serde(flatten)]
+    doc: DocumentParams,
+    /// The selector of the elements to retrieve.
+    selector: String,
+    /// The field to extract from the retrieved elements.
+    field: Option<String>,
+    /// Whether to expect exactly one element.
+    #[serde(default)]
+    one: bool,
+}
+
+/// The response of the `/compile` endpoint.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileResponse {
+    /// Whether the document is compiled without errors.
+    success: bool,
+    /// The number of the pages of the compiled document.
+    pages: Option<usize>,
+    /// The errors and warnings of the compilation.
+    diagnostics: Vec<DiagnosticJson>,
+}
+
+/// A diagnostic of the compilation.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosticJson {
+    /// The severity, either `error` or `warning`.
+    severity: &'static str,
+    /// The message of the diagnostic.
+    message: String,
+    /// The path of the file, relative to the root.
+    path: Option<String>,
+    /// The zero-based line and column where the diagnostic starts.
+    start: Option<(usize, usize)>,
+    /// The zero-based line and column where the diagnostic ends.
+    end: Option<(usize, usize)>,
+    /// The hints to fix the diagnostic.
+    hints: Vec<String>,
+}
+
+/// The error of a request, responded with the status code.
+struct ApiError(StatusCode, serde_json::Value);
+
+impl ApiError {
+    fn bad_request(err: impl std::fmt::Display) -> Self {
+        Self(
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({ "error": err.to_string() }),
+        )
+    }
+
+    fn internal(err: impl std::fmt::Display) -> Self {
+        Self(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!({ "error": err.to_string() }),
+        )
+    }
+}
+
+/// The state shared by the requests.
+struct ServeState {
+    /// The universe, whose fonts, packages and memoized layouts are reused by
+    /// the compilations.
+    verse: Mutex<LspUniverse>,
+    /// The token required in the URLs of the requests, if any.
+    token: Option<String>,
+}
+
+impl ServeState {
+    /// Creates the state serving the documents in the root.
+    fn new(
+        root: &Path,
+        fonts: Arc<FontResolverImpl>,
+        package: Option<&CompilePackageArgs>,
+        token: Option<String>,
+    ) -> Self {
+        let packages = LspUniverseBuilder::resolve_package(None, package);
+        let verse = LspUniverseBuilder::build(
+            EntryState::new_rooted(root.into(), None),
+            ExportTarget::Paged,
+            Default::default(),
+            Default::default(),
+            packages,
+            fonts,
+        );
+        Self {
+            verse: Mutex::new(verse),
+            token,
+        }
+    }
+
+    /// Compiles a document, reading the files from the disk again so that the
+    /// changes since the last request are seen.
+    fn compile(&self, params: &DocumentParams) -> Result<LspCompiledArtifact, ApiError> {
+        let main = VirtualPath::new(&params.main);
+        let inputs: Dict = params
+            .inputs
+            .iter()
+            .map(|(k, v)| (Str::from(k.as_str()), Value::Str(Str::from(v.as_str()))))
+            .collect();
+
+        let world = {
+            let mut verse = self.verse.lock();
+            verse.reset();
+            let entry = verse
+                .entry_state()
+                .select_in_workspace(main.as_rooted_path());
+            verse.snapshot_with(Some(TaskInputs {
+                entry: Some(entry),
+                inputs: Some(Arc::new(LazyHash::new(inputs))),
+            }))
+        };
+        world
+            .source(World::main(&world))
+            .map_err(ApiError::bad_request)?;
+
+        let graph = WorldComputeGraph::from_world(world);
+        Ok(CompiledArtifact::from_graph(graph, false))
+    }
+
+    /// Handles the `/compile` endpoint.
+    fn compile_doc(&self, params: DocumentParams) -> Result<Response<Full<Bytes>>, ApiError> {
+        let compiled = self.compile(&params)?;
+        let pages = match &compiled.doc {
+            Some(TypstDocument::Paged(doc)) => Some(doc.pages.len()),
+            _ => None,
+        };
+        json_response(
+            StatusCode::OK,
+            &CompileResponse {
+                success: !compiled.has_errors(),
+                pages,
+                diagnostics: diagnostics_json(&compiled),
+            },
+        )
+    }
+
+    /// Handles the `/export` endpoint.
+    fn export_doc(&self, params: ExportParams) -> Result<Response<Full<Bytes>>, ApiError> {
+        let (task, mime) = match params.format.as_str() {
+            "pdf" => (
+                ProjectTask::ExportPdf(Default::default()),
+                "application/pdf",
+            ),
+            "png" => {
+                let ppi = params.ppi.unwrap_or(144.0);
+                let ppi = ppi.try_into().map_err(ApiError::bad_request)?;
+                let task = ExportPngTask {
+                    export: Default::default(),
+                    ppi,
+                    fill: None,
+                    page_template: None,
+                };
+                (ProjectTask::ExportPng(task), "image/png")
+            }
+            "svg" => (ProjectTask::ExportSvg(Default::default()), "image/svg+xml"),
+            "html" => (ProjectTask::ExportHtml(Default::default()), "text/html"),
+            "md" => (ProjectTask::ExportMd(Default::default()), "text/markdown"),
+            "text" => (ProjectTask::ExportText(Default::default()), "text/plain"),
+            "docx" => (
+                ProjectTask::ExportDocx(Default::default()),
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            ),
+            format => {
+                return Err(ApiError::bad_request(format!(
+                    "unsupported format: {format}"
+                )))
+            }
+        };
+
+        self.run_task(&params.doc, task, mime)
+    }
+
+    /// Handles the `/query` endpoint.
+    fn query_doc(&self, params: QueryParams) -> Result<Response<Full<Bytes>>, ApiError> {
+        let task = ProjectTask::Query(QueryTask {
+            export: Default::default(),
+            format: "json".to_owned(),
+            output_extension: None,
+            selector: params.selector,
+            field: params.field,
+            one: params.one,
+        });
+
+        self.run_task(&params.doc, task, "application/json")
+    }
+
+    /// Compiles a document and runs an export task on it.
+    fn run_task(
+        &self,
+        params: &DocumentParams,
+        task: ProjectTask,
+        mime: &str,
+    ) -> Result<Response<Full<Bytes>>, ApiError> {
+        let compiled = self.compile(params)?;
+        let Some(doc) = compiled.doc.as_ref().filter(|_| !compiled.has_errors()) else {
+            return Err(ApiError(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                serde_json::json!({
+                    "error": "compilation failed",
+                    "diagnostics": diagnostics_json(&compiled),
+                }),
+            ));
+        };
+
+        let data =
+            ExportTask::export_bytes(task, &compiled.graph, doc).map_err(ApiError::internal)?;
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, mime)
+            .body(Full::new(Bytes::copy_from_slice(data.as_slice())))
+            .unwrap())
+    }
+}
+
+/// Runs the compile server until it is killed.
+pub async fn serve_main(args: ServeArgs) -> Result<()> {
+    let root = match args.root {
+        Some(root) => root,
+        None => std::env::current_dir().context("current directory")?,
+    };
+    let root = root.canonicalize().context("resolve root")?;
+
+    let token = args.resolve_token();
+    let fonts = Arc::new(LspUniverseBuilder::resolve_fonts(args.font)?);
+    let state = ServeState::new(&root, fonts, Some(&args.package), token.clone());
+    let state = Arc::new(state);
+
+    let listener = tokio::net::TcpListener::bind(&args.http)
+        .await
+        .context("bind address")?;
+    let addr = listener.local_addr().context("local address")?;
+    log::info!("compile server listening on http://{addr}");
+    if let Some(token) = &token {
+        // The token is needed to send the requests from other machines.
+        eprintln!("Compile server: http://{addr}/compile?token={token}");
+    }
+
+    let server = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+    loop {
+        let (stream, _peer_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                log::error!("accept error: {err}");
+                continue;
+            }
+        };
+
+        let state = state.clone();
+        let service = service_fn(move |req| handle(state.clone(), req));
+        let conn = server
+            .serve_connection(TokioIo::new(stream), service)
+            .into_owned();
+        tokio::spawn(async move {
+            if let Err(err) = conn.await {
+                log::error!("cannot serve http: {err}");
+            }
+        });
+    }
+}
+
+/// Dispatches a request to the endpoints.
+async fn handle(
+    state: Arc<ServeState>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, std::convert::Infallible> {
+    let (method, uri) = (req.method().clone(), req.uri().clone());
+    let result = match read_body(req.into_body()).await {
+        Ok(body) => {
+            // Compiles in the blocking threads, as the compilation may take
+            // seconds.
+            tokio::task::spawn_blocking(move || route_request(&state, method, &uri, &body))
+                .await
+                .unwrap_or_else(|err| Err(ApiError::internal(err)))
+        }
+        Err(err) => Err(err),
+    };
+
+    Ok(result.unwrap_or_else(|ApiError(status, body)| {
+        json_response(status, &body).expect("serialize error response")
+    }))
+}
+
+/// The maximum size of a request body.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reads a request body, which is responded with 413 if it is larger than
+/// [`MAX_BODY_SIZE`].
+async fn read_body<B>(body: B) -> Result<Bytes, ApiError>
+where
+    B: hyper::body::Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    match Limited::new(body, MAX_BODY_SIZE).collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(err) if err.is::<LengthLimitError>() => Err(ApiError(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            serde_json::json!({ "error": format!("request body exceeds {MAX_BODY_SIZE} bytes") }),
+        )),
+        Err(err) => Err(ApiError::bad_request(err)),
+    }
+}
+
+fn route_request(
+    state: &ServeState,
+    method: Method,
+    uri: &Uri,
+    body: &[u8],
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    fn parse<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+        serde_json::from_slice(body).map_err(ApiError::bad_request)
+    }
+
+    // The servers exposed to the network require the token, as the preview
+    // does, since the requests read the files in the root.
+    if let Some(token) = &state.token {
+        if !has_token(uri.query(), token) {
+            return Err(ApiError(
+                StatusCode::UNAUTHORIZED,
+                serde_json::json!({ "error": "missing or invalid token" }),
+            ));
+        }
+    }
+
+    match (method, uri.path()) {
+        (Method::POST, "/compile") => state.compile_doc(parse(body)?),
+        (Method::POST, "/export") => state.export_doc(parse(body)?),
+        (Method::POST, "/query") => state.query_doc(parse(body)?),
+        (_, path) => Err(ApiError(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": format!("no such endpoint: {path}") }),
+        )),
+    }
+}
+
+fn json_response<T: Serialize>(
+    status: StatusCode,
+    value: &T,
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    let body = serde_json::to_vec(value).map_err(ApiError::internal)?;
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap())
+}
+
+fn diagnostics_json(compiled: &LspCompiledArtifact) -> Vec<DiagnosticJson> {
+    let world = compiled.world();
+    let locate = |diag: &SourceDiagnostic| {
+        let id = diag.span.id()?;
+        let source = world.source(id).ok()?;
+        let range = source.range(diag.span)?;
+        let position = |offset| {
+            let line = source.byte_to_line(offset)?;
+            Some((line, source.byte_to_column(offset)?))
+        };
+        let path = id.vpath().as_rootless_path().display().to_string();
+        Some((path, position(range.start), position(range.end)))
+    };
+
+    compiled
+        .diagnostics()
+        .map(|diag| {
+            let (path, start, end) = locate(diag).unwrap_or_default();
+            DiagnosticJson {
+                severity: match diag.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                message: diag.message.to_string(),
+                path: Some(path).filter(|path| !path.is_empty()),
+                start,
+                end,
+                hints: diag.hints.iter().map(|hint| hint.to_string()).collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn serve(main: &str, token: Option<&str>) -> (tempfile::TempDir, ServeState) {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("main.typ"), main).unwrap();
+
+        let fonts = Arc::new(LspUniverseBuilder::only_embedded_fonts().unwrap());
+        let state = ServeState::new(root.path(), fonts, None, token.map(ToOwned::to_owned));
+        (root, state)
+    }
+
+    fn request(state: &ServeState, uri: &str, body: serde_json::Value) -> (StatusCode, Bytes) {
+        let uri = uri.parse::<Uri>().unwrap();
+        let body = serde_json::to_vec(&body).unwrap();
+        let res = route_request(state, Method::POST, &uri, &body)
+            .unwrap_or_else(|ApiError(status, body)| json_response(status, &body).unwrap());
+        let status = res.status();
+        let body = futures::executor::block_on(res.into_body().collect()).unwrap();
+        (status, body.to_bytes())
+    }
+
+    fn json(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    #[test]
+    fn compile() {
+        let (_root, state) = serve("= Hello\n#pagebreak()\nWorld", None);
+        let (status, body) = request(
+            &state,
+            "/compile",
+            serde_json::json!({ "main": "main.typ" }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        let body = json(&body);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["pages"], 2);
+
+        let params = serde_json::json!({ "main": "missing.typ" });
+        let (status, _) = request(&state, "/compile", params);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn compile_error() {
+        let (_root, state) = serve("#let x = ", None);
+        let params = serde_json::json!({ "main": "main.typ" });
+        let (status, body) = request(&state, "/compile", params.clone());
+        assert_eq!(status, StatusCode::OK);
+        let body = json(&body);
+        assert_eq!(body["success"], false);
+        assert_eq!(body["diagnostics"][0]["severity"], "error");
+        assert_eq!(body["diagnostics"][0]["path"], "main.typ");
+
+        let (status, body) = request(
+            &state,
+            "/export",
+            serde_json::json!({ "main": "main.typ", "format": "pdf" }),
+        );
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json(&body)["error"], "compilation failed");
+    }
+
+    #[test]
+    fn export_and_query() {
+        let (_root, state) = serve("= Hello\n#metadata(\"x\") <meta>", None);
+        let params = |format: &str| serde_json::json!({ "main": "main.typ", "format": format });
+
+        let (status, body) = request(&state, "/export", params("pdf"));
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(b"%PDF"));
+        let (status, body) = request(&state, "/export", params("text"));
+        assert_eq!(status, StatusCode::OK);
+        assert!(std::str::from_utf8(&body).unwrap().contains("Hello"));
+        let (status, _) = request(&state, "/export", params("jpeg"));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let params = serde_json::json!({
+            "main": "main.typ",
+            "selector": "<meta>",
+            "field": "value",
+            "one": true,
+        });
+        let (status, body) = request(&state, "/query", params);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json(&body), "x");
+    }
+
+    #[test]
+    fn bad_requests() {
+        let (_root, state) = serve("", None);
+        let (status, _) = request(&state, "/compile", serde_json::json!({}));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = request(&state, "/missing", serde_json::json!({}));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn token() {
+        let (_root, state) = serve("Hello", Some("abc"));
+        let params = serde_json::json!({ "main": "main.typ" });
+
+        let (status, _) = request(&state, "/compile", params.clone());
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = request(&state, "/compile?token=abd", params.clone());
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = request(&state, "/compile?token=abc", params);
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[test]
+    fn body_limit() {
+        let read = |size| {
+            let body = Full::new(Bytes::from(vec![b' '; size]));
+            futures::executor::block_on(read_body(body)).map(|body| body.len())
+        };
+
+        assert_eq!(read(MAX_BODY_SIZE).ok(), Some(MAX_BODY_SIZE));
+        let status = read(MAX_BODY_SIZE + 1)
+            .err()
+            .map(|ApiError(status, _)| status);
+        assert_eq!(status, Some(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    #[test]
+    fn resolve_token() {
+        let args = |args: &[&str]| {
+            let args = ["serve", "--http"].iter().chain(args);
+            ServeArgs::try_parse_from(args).unwrap().resolve_token()
+        };
+
+        assert_eq!(args(&["127.0.0.1:23627"]), None);
+        assert_eq!(args(&["0.0.0.0:23627"]).map(|token| token.len()), Some(32));
+        assert_eq!(
+            args(&["127.0.0.1:23627", "--token", "abc"]).as_deref(),
+            Some("abc")
+        );
+        assert!(args(&["127.0.0.1:23627", "--token"]).is_some());
+    }
+}
@@ -1,51 +1,81 @@
 //! Testing utilities
 
 use core::fmt;
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{atomic::AtomicBool, Arc};
 
-use itertools::Either;
 use parking_lot::Mutex;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use reflexo::ImmutPath;
+use reflexo::{path::unix_slash, ImmutPath};
 use reflexo_typst::{vfs::FileId, TypstDocument, TypstHtmlDocument};
 use tinymist_debug::CoverageResult;
 use tinymist_project::world::{system::print_diagnostics, DiagnosticFormat};
 use tinymist_query::analysis::Analysis;
 use tinymist_query::syntax::{cast_include_expr, find_source_by_expr, node_ancestors};
-use tinymist_query::testing::{TestCaseKind, TestSuites};
+use tinymist_query::testing::{DocExample, TestCase, TestCaseKind, TestSuites};
+use tinymist_std::xml::escape_xml;
 use tinymist_std::{bail, error::prelude::*, fs::paths::write_atomic, typst::TypstPagedDocument};
 use typst::diag::{Severity, SourceDiagnostic};
-use typst::ecow::EcoVec;
+use typst::ecow::{eco_format, EcoVec};
 use typst::foundations::{Context, Label};
 use typst::syntax::{ast, LinkedNode, Source, Span};
 use typst::{utils::PicoStr, World};
 use typst_shim::eval::TypstEngine;
 
 use super::project::{start_project, StartProjectResult};
-use crate::world::{with_main, SourceWorld};
+use crate::world::{with_main, with_main_source, SourceWorld};
 use crate::{project::*, utils::exit_on_ctrl_c};
 
 const TEST_EVICT_MAX_AGE: usize = 30;
 const PREFIX_LEN: usize = 7;
 
+/// Coverage arguments
+#[derive(Debug, Clone, clap::Parser)]
+pub struct CoverageArgs {
+    /// The argument to compile once.
+    #[clap(flatten)]
+    pub compile: CompileOnceArgs,
+
+    /// The formats of the coverage reports.
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "json")]
+    pub coverage_format: Vec<CoverageFormat>,
+}
+
+/// The format of a coverage report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CoverageFormat {
+    /// The coverage data of VS Code, written to `target/coverage.json`.
+    Json,
+    /// The LCOV tracefile, written to `target/lcov.info`.
+    Lcov,
+    /// The Cobertura XML, written to `target/cobertura.xml`.
+    Cobertura,
+}
+
+impl CoverageFormat {
+    fn path(self) -> &'static Path {
+        Path::new(match self {
+            Self::Json => "target/coverage.json",
+            Self::Lcov => "target/lcov.info",
+            Self::Cobertura => "target/cobertura.xml",
+        })
+    }
+}
+
 /// Runs coverage test on a document
-pub fn coverage_main(args: CompileOnceArgs) -> Result<()> {
+pub fn coverage_main(args: CoverageArgs) -> Result<()> {
     // Prepares for the compilation
-    let universe = args.resolve()?;
+    let universe = args.compile.resolve()?;
     let world = universe.snapshot();
 
     let result = Ok(()).and_then(|_| -> Result<()> {
         let res = tinymist_debug::collect_coverage::<TypstPagedDocument, _>(&world)?;
-        let cov_path = Path::new("target/coverage.json");
-        let res = serde_json::to_string(&res.to_json(&world)).context("coverage")?;
-
-        std::fs::create_dir_all(cov_path.parent().context("parent")?).context("create coverage")?;
-        std::fs::write(cov_path, res).context("write coverage")?;
-
-        Ok(())
+        std::fs::create_dir_all("target").context("create coverage")?;
+        write_coverage(&world, &res, &args.coverage_format)
     });
 
     print_diag_or_error(&world, result)
@@ -94,9 +124,62 @@ pub struct TestConfigArgs {
     #[clap(long)]
     pub coverage: bool,
 
+    /// The formats of the coverage reports.
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "json")]
+    pub coverage_format: Vec<CoverageFormat>,
+
     /// Style of printing coverage.
     #[clap(long, default_value = "short")]
     pub print_coverage: PrintCovStyle,
+
+    /// The number of threads to run the test cases. Defaults to the number of
+    /// CPUs.
+    #[clap(long, short)]
+    pub jobs: Option<usize>,
+
+    /// The maximum ratio of the pixels of a rendered page differing from its
+    /// reference image for the page to match the reference, e.g. `0.001`.
+    #[clap(long, default_value_t = 0.0)]
+    pub diff_tolerance: f64,
+
+    /// The maximum difference of a color channel for a pixel to be the same as
+    /// the reference, which absorbs the differences of the antialiasing.
+    #[clap(long, default_value_t = 0)]
+    pub diff_threshold: u8,
+
+    /// Writes a report of the test cases, e.g. `--report junit=report.xml`.
+    #[clap(long, value_name = "FORMAT=PATH")]
+    pub report: Vec<TestReport>,
+}
+
+/// The format of a test report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TestReportFormat {
+    /// The JUnit XML, which is rendered natively by most CI systems.
+    Junit,
+}
+
+/// A test report to write, written as `<format>=<path>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestReport {
+    /// The format of the report.
+    pub format: TestReportFormat,
+    /// The path to write the report to.
+    pub path: PathBuf,
+}
+
+impl std::str::FromStr for TestReport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `<format>=<path>`, got `{s}`"))?;
+        Ok(Self {
+            format: <TestReportFormat as clap::ValueEnum>::from_str(format, true)?,
+            path: PathBuf::from(path),
+        })
+    }
 }
 
 /// Style of printing coverage.
@@ -157,11 +240,12 @@ pub async fn test_main(args: TestArgs) -> Result<()> {
         args: args.config,
         out_file,
         analysis: Analysis::default(),
+        failed_cases: Arc::default(),
     };
 
     if !args.watch {
         let snap = verse.snapshot();
-        return match test_once(&snap, &config) {
+        return match test_once(&snap, &config, None) {
             Ok(true) => Ok(()),
             Ok(false) | Err(..) => std::process::exit(1),
         };
@@ -169,6 +253,10 @@ pub async fn test_main(args: TestArgs) -> Result<()> {
 
     let ctx = Arc::new(Mutex::new(config.clone()));
     let repl_ctx = ctx.clone();
+    // The hashes of the files read by the last run, which are compared with
+    // the new ones to only rerun the test cases impacted by the changes.
+    let last_files = Arc::new(Mutex::new(None::<HashMap<FileId, u128>>));
+    let repl_last_files = last_files.clone();
 
     let mut is_first = true;
     let StartProjectResult {
@@ -190,9 +278,13 @@ pub async fn test_main(args: TestArgs) -> Result<()> {
             }
             // Sets is_compiling to track dependencies
             let mut world = artifact.snap.world.clone();
+            let mut last_files = last_files.lock();
+            // Collecting the coverage needs to run all the test cases.
+            let last = last_files.as_ref().filter(|_| !config.args.coverage);
             world.set_is_compiling(true);
-            let res = test_once(&world, &config);
+            let res = test_once(&world, &config, last);
             world.set_is_compiling(false);
+            *last_files = Some(file_hashes(&world));
 
             if let Err(err) = res {
                 test_error!("Fatal:", "{err}");
@@ -217,9 +309,11 @@ pub async fn test_main(args: TestArgs) -> Result<()> {
             std::io::stdin().read_line(&mut line).unwrap();
             match line.trim() {
                 "r" => {
+                    *repl_last_files.lock() = None;
                     let _ = intr_tx.send(Interrupt::Compile(proj_id.clone()));
                 }
                 "u" => {
+                    *repl_last_files.lock() = None;
                     let mut repl_ctx = repl_ctx.lock();
                     repl_ctx.args.update = true;
                     let _ = intr_tx.send(Interrupt::Compile(proj_id.clone()));
@@ -239,23 +333,92 @@ pub async fn test_main(args: TestArgs) -> Result<()> {
     Ok(())
 }
 
-fn test_once(world: &LspWorld, ctx: &TestContext) -> Result<bool> {
+/// Runs the tests of a document once without the dashboard, returning whether
+/// all the tests passed.
+pub(crate) fn run_tests(world: &LspWorld, config: TestConfigArgs) -> Result<bool> {
+    let root = world.entry_state().root().context("cannot find root")?;
+    let ctx = TestContext {
+        root,
+        args: config,
+        out_file: None,
+        analysis: Analysis::default(),
+        failed_cases: Arc::default(),
+    };
+    test_once(world, &ctx, None)
+}
+
+/// Runs the tests of a document once.
+///
+/// If the hashes of the files read by the last run are given, only the test
+/// cases impacted by the changes since the last run are run, along with the
+/// test cases failed in the last run.
+fn test_once(
+    world: &LspWorld,
+    ctx: &TestContext,
+    last_files: Option<&HashMap<FileId, u128>>,
+) -> Result<bool> {
     let mut actx = ctx.analysis.enter(world.clone());
     let doc = typst::compile::<TypstPagedDocument>(&actx.world).output?;
 
-    let suites =
+    let mut suites =
         tinymist_query::testing::test_suites(&mut actx, &TypstDocument::from(Arc::new(doc)))
             .context("failed to discover tests")?;
     log_info!(
-        "Found {} tests and {} examples",
+        "Found {} tests, {} examples and {} docstring examples",
         suites.tests.len(),
-        suites.examples.len()
+        suites.examples.len(),
+        suites.doc_examples.len()
     );
 
+    let last_failed = std::mem::take(&mut *ctx.failed_cases.lock());
+    // The test cases not impacted by the changes, which are only reported.
+    let mut skipped = TestSuites::default();
+    if let Some(last_files) = last_files {
+        let current = file_hashes(world);
+        let changed = last_files
+            .iter()
+            .filter(|(fid, hash)| current.get(fid) != Some(hash))
+            .map(|(fid, _)| *fid)
+            .collect::<HashSet<_>>();
+        if !changed.is_empty() {
+            let mut paths = changed
+                .iter()
+                .map(|fid| unix_slash(fid.vpath().as_rooted_path()))
+                .collect::<Vec<_>>();
+            paths.sort();
+            log_info!("Changed files: {}", paths.join(", "));
+        }
+
+        let impacted = tinymist_query::impacted_files(&mut actx, &changed);
+        // The new test files are not read by the last run.
+        let is_impacted = |fid: &FileId| impacted.contains(fid) || !last_files.contains_key(fid);
+
+        (suites.tests, skipped.tests) =
+            std::mem::take(&mut suites.tests)
+                .into_iter()
+                .partition(|test| {
+                    is_impacted(&test.location) || last_failed.contains(test.name.as_str())
+                });
+        (suites.examples, skipped.examples) = std::mem::take(&mut suites.examples)
+            .into_iter()
+            .partition(|example| {
+                is_impacted(&example.id()) || last_failed.contains(example_name(example))
+            });
+        (suites.doc_examples, skipped.doc_examples) = std::mem::take(&mut suites.doc_examples)
+            .into_iter()
+            .partition(|example| {
+                is_impacted(&example.location) || last_failed.contains(example.name.as_str())
+            });
+        let count = skipped.tests.len() + skipped.examples.len() + skipped.doc_examples.len();
+        if count > 0 {
+            log_info!("Skipped {count} test cases not impacted by the changes");
+        }
+    }
+
     let result = if ctx.args.coverage {
         let (cov, result) = tinymist_debug::with_cov(world, |world| {
             let suites = suites.recheck(world);
-            let runner = TestRunner::new(ctx, world, &suites);
+            let runner = TestRunner::new(ctx, world, &suites, &skipped);
             let result = print_diag_or_error(world, runner.run());
             comemo::evict(TEST_EVICT_MAX_AGE);
             result
@@ -264,7 +427,7 @@ fn test_once(world: &LspWorld, ctx: &TestContext) -> Result<bool> {
         result
     } else {
         let suites = suites.recheck(world);
-        let runner = TestRunner::new(ctx, world, &suites);
+        let runner = TestRunner::new(ctx, world, &suites, &skipped);
         comemo::evict(TEST_EVICT_MAX_AGE);
         runner.run()
     };
@@ -279,20 +442,51 @@ fn test_once(world: &LspWorld, ctx: &TestContext) -> Result<bool> {
     passed
 }
 
+/// Hashes the files read by the compilations of a world.
+fn file_hashes(world: &LspWorld) -> HashMap<FileId, u128> {
+    world
+        .depended_files()
+        .into_iter()
+        .map(|fid| (fid, tinymist_std::hash::hash128(&world.file(fid).ok())))
+        .collect()
+}
+
+/// Writes the coverage reports in the formats.
+fn write_coverage(
+    world: &LspWorld,
+    cov: &CoverageResult,
+    formats: &[CoverageFormat],
+) -> Result<()> {
+    for format in formats {
+        let res = match format {
+            CoverageFormat::Json => {
+                serde_json::to_string(&cov.to_json(world)).context("coverage")?
+            }
+            CoverageFormat::Lcov => cov.to_lcov(world),
+            CoverageFormat::Cobertura => cov.to_cobertura(world),
+        };
+        let cov_path = format.path();
+        write_atomic(cov_path, res).context("write coverage")?;
+        log_info!("Written coverage to {} ...", cov_path.display());
+    }
+
+    Ok(())
+}
+
 #[derive(Clone)]
 struct TestContext {
     analysis: Analysis,
     root: ImmutPath,
     args: TestConfigArgs,
     out_file: Option<Arc<Mutex<std::fs::File>>>,
+    /// The names of the test cases failed in the last run, which are rerun
+    /// in the watch mode even if they are not impacted by the changes.
+    failed_cases: Arc<Mutex<HashSet<String>>>,
 }
 
 impl TestContext {
     pub fn handle_cov(&self, world: &LspWorld, cov: CoverageResult) -> Result<()> {
-        let cov_path = Path::new("target/coverage.json");
-        let res = serde_json::to_string(&cov.to_json(world)).context("coverage")?;
-        write_atomic(cov_path, res).context("write coverage")?;
-        log_info!("Written coverage to {} ...", cov_path.display());
+        write_coverage(world, &cov, &self.args.coverage_format)?;
 
         const COV_PREFIX: &str = "    \x1b[1;32mCov\x1b[0m ";
         match self.args.print_coverage {
@@ -312,23 +506,48 @@ struct TestRunner<'a> {
     ctx: &'a TestContext,
     world: &'a dyn SourceWorld,
     suites: &'a TestSuites,
+    /// The test cases not run, which are reported as skipped.
+    skipped: &'a TestSuites,
     diagnostics: Mutex<Vec<EcoVec<SourceDiagnostic>>>,
     examples: Mutex<HashSet<String>>,
+    /// The paths of the created reference files.
+    created_refs: Mutex<Vec<PathBuf>>,
+    /// The paths of the reference files updated as they differ.
+    updated_refs: Mutex<Vec<PathBuf>>,
+    /// Whether any reference file differs without being updated.
+    mismatched: AtomicBool,
     failed: AtomicBool,
 }
 
 impl<'a> TestRunner<'a> {
-    fn new(ctx: &'a TestContext, world: &'a dyn SourceWorld, suites: &'a TestSuites) -> Self {
+    fn new(
+        ctx: &'a TestContext,
+        world: &'a dyn SourceWorld,
+        suites: &'a TestSuites,
+        skipped: &'a TestSuites,
+    ) -> Self {
         Self {
             ctx,
             world,
             suites,
+            skipped,
             diagnostics: Mutex::new(Vec::new()),
             examples: Mutex::new(HashSet::new()),
+            created_refs: Mutex::new(Vec::new()),
+            updated_refs: Mutex::new(Vec::new()),
+            mismatched: AtomicBool::new(false),
             failed: AtomicBool::new(false),
         }
     }
 
+    /// Pushes the diagnostics to the output of the running test case.
+    fn push_diagnostics(&self, diags: EcoVec<SourceDiagnostic>) {
+        CASE_OUTPUT.with_borrow_mut(|output| match output {
+            Some(output) => output.diagnostics.push(diags),
+            None => self.diagnostics.lock().push(diags),
+        });
+    }
+
     fn put_log(&self, args: fmt::Arguments) {
         if let Some(file) = &self.ctx.out_file {
             writeln!(file.lock(), "{args}").unwrap();
@@ -343,6 +562,13 @@ impl<'a> TestRunner<'a> {
     fn mark_failed(&self, kind: &str, name: &str, args: impl fmt::Display) {
         test_log!(Error, "Failed", "{kind}({name}): {args}");
         self.put_log(format_args!("#failed-{kind}({name:?})"));
+        let failure = format!("{kind}({name}): {args}");
+        CASE_OUTPUT.with_borrow_mut(|output| {
+            if let Some(output) = output {
+                output.failures.push(failure);
+            }
+        });
+        self.ctx.failed_cases.lock().insert(name.to_owned());
         self.failed.store(true, std::sync::atomic::Ordering::SeqCst);
     }
 
@@ -367,60 +593,48 @@ impl<'a> TestRunner<'a> {
             self.suites.examples.len()
         ));
 
-        let examples = self.suites.examples.par_iter().map(Either::Left);
-        let tests = self.suites.tests.par_iter().map(Either::Right);
-
-        examples.chain(tests).for_each(|case| {
-            let test = match case {
-                Either::Left(test) => {
-                    self.run_example(test);
-                    return;
-                }
-                Either::Right(test) => test,
-            };
+        let examples = self.suites.examples.par_iter().map(Case::Example);
+        let tests = self.suites.tests.par_iter().map(Case::Test);
+        let doc_examples = self.suites.doc_examples.par_iter().map(Case::DocExample);
 
-            let name = &test.name;
-            let func = &test.function;
-
-            let world = with_main(self.world.as_world(), test.location);
-            let mut engine = TypstEngine::new(&world);
-
-            // Executes the function
-            match test.kind {
-                TestCaseKind::Test | TestCaseKind::Bench => {
-                    self.running("test", name);
-                    if let Err(err) = engine.call(func, Context::default()) {
-                        self.diagnostics.lock().push(err);
-                        self.failed_test(name, format_args!("call error"));
-                    } else {
-                        self.mark_passed("test", name);
-                    }
-                }
-                TestCaseKind::Panic => {
-                    self.running("test", name);
-                    match engine.call(func, Context::default()) {
-                        Ok(..) => {
-                            self.failed_test(name, "exited normally, expected panic");
-                        }
-                        Err(err) => {
-                            let all_panic = err.iter().all(|p| p.message.contains("panic"));
-                            if !all_panic {
-                                self.diagnostics.lock().push(err);
-                                self.failed_test(name, "exited with error, expected panic");
-                            } else {
-                                self.mark_passed("test", name);
-                            }
-                        }
-                    }
-                }
-                TestCaseKind::Example => {
-                    match get_example_file(&world, name, test.location, func.span()) {
-                        Ok(example) => self.run_example(&example),
-                        Err(err) => self.failed_test(name, format_args!("not found: {err}")),
-                    };
+        let mut pool = rayon::ThreadPoolBuilder::new();
+        if let Some(jobs) = self.ctx.args.jobs {
+            pool = pool.num_threads(jobs);
+        }
+        let pool = pool.build().context("build thread pool")?;
+
+        // The outputs are collected in the order of the test cases.
+        let outputs = pool.install(|| {
+            examples
+                .chain(tests)
+                .chain(doc_examples)
+                .map(|case| {
+                    let instant = std::time::Instant::now();
+                    let mut output = capture_output(|| self.run_case(case));
+                    output.duration = instant.elapsed();
+                    (case, output)
+                })
+                .collect::<Vec<_>>()
+        });
+        for (_, output) in &outputs {
+            for line in &output.logs {
+                eprintln!("{line}");
+            }
+        }
+        for report in &self.ctx.args.report {
+            match report.format {
+                TestReportFormat::Junit => {
+                    let junit = junit_report(&self.reported_cases(&outputs));
+                    write_atomic(&report.path, junit).context("write test report")?;
                 }
             }
-        });
+            log_info!("Written test report to {} ...", report.path.display());
+        }
+        for (_, output) in outputs {
+            self.diagnostics.lock().extend(output.diagnostics);
+        }
+
+        self.summarize_refs();
 
         {
             let diagnostics = self.diagnostics.into_inner();
@@ -439,9 +653,103 @@ impl<'a> TestRunner<'a> {
         Ok(!self.failed.load(std::sync::atomic::Ordering::SeqCst))
     }
 
+    /// Locates the test cases run and skipped for the reports.
+    fn reported_cases<'b>(
+        &'b self,
+        outputs: &'b [(Case<'b>, CaseOutput)],
+    ) -> Vec<ReportedCase<'b>> {
+        let skipped = (self.skipped.examples.iter().map(Case::Example))
+            .chain(self.skipped.tests.iter().map(Case::Test))
+            .chain(self.skipped.doc_examples.iter().map(Case::DocExample))
+            .map(|case| (case, None));
+        let run = outputs.iter().map(|(case, output)| (*case, Some(output)));
+
+        let world = self.world.as_world();
+        run.chain(skipped)
+            .map(|(case, output)| {
+                let (name, fid, line) = match case {
+                    Case::Example(example) => (example_name(example), example.id(), None),
+                    Case::Test(test) => {
+                        let span = test.function.span();
+                        let line = world
+                            .source(test.location)
+                            .ok()
+                            .and_then(|source| source.byte_to_line(source.range(span)?.start));
+                        (test.name.as_str(), test.location, line)
+                    }
+                    Case::DocExample(example) => {
+                        (example.name.as_str(), example.location, Some(example.line))
+                    }
+                };
+                ReportedCase {
+                    name,
+                    file: unix_slash(fid.vpath().as_rootless_path()),
+                    line: line.unwrap_or(0) + 1,
+                    output,
+                }
+            })
+            .collect()
+    }
+
+    /// Runs a test case or an example.
+    fn run_case(&self, case: Case) {
+        let test = match case {
+            Case::Example(example) => {
+                self.run_example(example);
+                return;
+            }
+            Case::DocExample(example) => {
+                self.run_doc_example(example);
+                return;
+            }
+            Case::Test(test) => test,
+        };
+
+        let name = &test.name;
+        let func = &test.function;
+
+        let world = with_main(self.world.as_world(), test.location);
+        let mut engine = TypstEngine::new(&world);
+
+        // Executes the function
+        match test.kind {
+            TestCaseKind::Test | TestCaseKind::Bench => {
+                self.running("test", name);
+                if let Err(err) = engine.call(func, Context::default()) {
+                    self.push_diagnostics(err);
+                    self.failed_test(name, format_args!("call error"));
+                } else {
+                    self.mark_passed("test", name);
+                }
+            }
+            TestCaseKind::Panic => {
+                self.running("test", name);
+                match engine.call(func, Context::default()) {
+                    Ok(..) => {
+                        self.failed_test(name, "exited normally, expected panic");
+                    }
+                    Err(err) => {
+                        let all_panic = err.iter().all(|p| p.message.contains("panic"));
+                        if !all_panic {
+                            self.push_diagnostics(err);
+                            self.failed_test(name, "exited with error, expected panic");
+                        } else {
+                            self.mark_passed("test", name);
+                        }
+                    }
+                }
+            }
+            TestCaseKind::Example => {
+                match get_example_file(&world, name, test.location, func.span()) {
+                    Ok(example) => self.run_example(&example),
+                    Err(err) => self.failed_test(name, format_args!("not found: {err}")),
+                };
+            }
+        }
+    }
+
     fn run_example(&self, test: &Source) {
-        let id = test.id().vpath().as_rooted_path().with_extension("");
-        let name = id.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+        let name = example_name(test);
         self.running("example", name);
 
         if !self.examples.lock().insert(name.to_string()) {
@@ -466,16 +774,48 @@ impl<'a> TestRunner<'a> {
         }
     }
 
+    /// Compiles an example in a docstring, which imports the definitions of
+    /// the file containing the docstring.
+    fn run_doc_example(&self, example: &DocExample) {
+        let name = example.name.as_str();
+        self.running("doc-example", name);
+
+        // The example is placed next to the file to import it.
+        let fid = example.location;
+        let file_name = fid.vpath().as_rooted_path().file_name();
+        let file_name = file_name.and_then(|s| s.to_str()).unwrap_or_default();
+        let id = fid.join(&format!("{file_name}.doc-example-{}.typ", example.line + 1));
+        let text = format!("#import \"{file_name}\": *\n{}", example.code);
+        let source = Source::new(id, text);
+
+        let world = with_main_source(self.world.as_world(), source.clone());
+        let result = typst::compile::<TypstPagedDocument>(&world);
+        let has_err = result.output.is_err();
+        let diags = result.output.err().into_iter().flatten();
+        let diags = diags.chain(result.warnings);
+        let diags = diags.map(|diag| locate_doc_diagnostic(example, &source, diag));
+        let diags = diags.collect::<EcoVec<_>>();
+        if !diags.is_empty() {
+            self.push_diagnostics(diags);
+        }
+
+        if has_err {
+            self.mark_failed("doc-example", name, "has error");
+        } else {
+            self.mark_passed("doc-example", name);
+        }
+    }
+
     fn build_example<T: typst::Document>(&self, world: &dyn World) -> (bool, Option<T>) {
         let result = typst::compile::<T>(world);
         if !result.warnings.is_empty() {
-            self.diagnostics.lock().push(result.warnings);
+            self.push_diagnostics(result.warnings);
         }
 
         match result.output {
             Ok(v) => (false, Some(v)),
             Err(e) => {
-                self.diagnostics.lock().push(e);
+                self.push_diagnostics(e);
                 (true, None)
             }
         }
@@ -501,7 +841,7 @@ impl<'a> TestRunner<'a> {
         let output = match typst_html::html(doc) {
             Ok(output) => self.update_example(example, output.as_bytes(), "html"),
             Err(err) => {
-                self.diagnostics.lock().push(err);
+                self.push_diagnostics(err);
                 Err(error_once!("render error"))
             }
         };
@@ -531,7 +871,11 @@ impl<'a> TestRunner<'a> {
             None
         };
 
-        let equal = existing_hash.map(|existing| existing.as_slice() == hash.as_bytes());
+        let mut equal = existing_hash.map(|existing| existing.as_slice() == hash.as_bytes());
+        if equal == Some(false) && !self.ctx.args.update && kind == "paged" {
+            equal = Some(self.compare_pixels(example, &path, data)?);
+        }
+
         match (self.ctx.args.update, equal) {
             // Doesn't exist, create it
             (_, None) => {}
@@ -539,6 +883,8 @@ impl<'a> TestRunner<'a> {
             (true, Some(false)) => log_info!("example({example}): ref {kind}"),
             (false, Some(false)) => {
                 write_atomic(tmp_path, data).context("write tmp ref")?;
+                self.mismatched
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
 
                 self.failed_example(example, format_args!("mismatch {kind}"));
                 log_hint!("example({example}): compare {kind} at {}", path.display());
@@ -551,8 +897,10 @@ impl<'a> TestRunner<'a> {
             }
         }
 
-        if std::fs::exists(tmp_path).context("exists tmp")? {
-            std::fs::remove_file(tmp_path).context("remove tmp")?;
+        for stale_path in [tmp_path, &path.with_extension("diff.png")] {
+            if std::fs::exists(stale_path).context("exists tmp")? {
+                std::fs::remove_file(stale_path).context("remove tmp")?;
+            }
         }
 
         if matches!(equal, Some(true)) {
@@ -560,12 +908,78 @@ impl<'a> TestRunner<'a> {
         }
 
         std::fs::create_dir_all(path.parent().context("parent")?).context("create ref")?;
-        write_atomic(path, data).context("write ref")?;
+        write_atomic(&path, data).context("write ref")?;
         write_atomic(hash_path, hash).context("write hash ref")?;
 
+        let refs = if equal.is_some() {
+            &self.updated_refs
+        } else {
+            &self.created_refs
+        };
+        refs.lock().push(path);
+
         Ok(())
     }
 
+    /// Compares a rendered page with its reference image pixel by pixel,
+    /// returning whether the differences are within the tolerance. Otherwise,
+    /// writes an image highlighting the differing pixels next to the reference.
+    fn compare_pixels(&self, example: &str, path: &Path, data: &[u8]) -> Result<bool> {
+        let Ok(expected) = std::fs::read(path) else {
+            return Ok(false);
+        };
+        let Some(diff) = diff_images(&expected, data, self.ctx.args.diff_threshold)? else {
+            log_hint!("example({example}): the size of the page differs from the reference");
+            return Ok(false);
+        };
+
+        let (differing, total) = (diff.differing, diff.total.max(1));
+        let ratio = differing as f64 / total as f64;
+        if ratio <= self.ctx.args.diff_tolerance {
+            if differing > 0 {
+                log_info!("example({example}): {differing} of {total} pixels differ in tolerance");
+            }
+            return Ok(true);
+        }
+
+        let diff_path = path.with_extension("diff.png");
+        let mut buf = vec![];
+        diff.image
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .context_ut("encode diff image")?;
+        write_atomic(&diff_path, buf).context("write diff image")?;
+        log_hint!(
+            "example({example}): {differing} of {total} pixels differ ({:.3}%), see {}",
+            ratio * 100.0,
+            diff_path.display()
+        );
+        Ok(false)
+    }
+
+    /// Prints the reference files created or updated by the run.
+    fn summarize_refs(&self) {
+        let root = &self.ctx.root;
+        let summarize = |action: &str, refs: &Mutex<Vec<PathBuf>>| {
+            let mut refs = std::mem::take(&mut *refs.lock());
+            if refs.is_empty() {
+                return;
+            }
+
+            refs.sort();
+            log_info!("{action} {} reference files", refs.len());
+            for path in refs {
+                let path = path.strip_prefix(root).unwrap_or(&path);
+                log_info!("  {}", unix_slash(path));
+            }
+        };
+        summarize("Created", &self.created_refs);
+        summarize("Updated", &self.updated_refs);
+
+        if self.mismatched.load(std::sync::atomic::Ordering::SeqCst) {
+            log_hint!("Run with `--update` to update the mismatched reference files");
+        }
+    }
+
     fn can_html(&self, doc: Option<&TypstPagedDocument>) -> bool {
         let Some(doc) = doc else {
             return false;
@@ -577,6 +991,50 @@ impl<'a> TestRunner<'a> {
     }
 }
 
+/// A test case to run.
+#[derive(Clone, Copy)]
+enum Case<'a> {
+    Example(&'a Source),
+    Test(&'a TestCase),
+    DocExample(&'a DocExample),
+}
+
+/// Moves a diagnostic of an example in a docstring to the docstring, as the
+/// example is not a file of the world.
+fn locate_doc_diagnostic(
+    example: &DocExample,
+    source: &Source,
+    mut diag: SourceDiagnostic,
+) -> SourceDiagnostic {
+    diag.trace
+        .retain(|point| point.span.id() != Some(source.id()));
+    if diag.span.id() != Some(source.id()) {
+        return diag;
+    }
+
+    let position = source.range(diag.span).and_then(|range| {
+        let line = source.byte_to_line(range.start)?;
+        Some((line, source.byte_to_column(range.start)?))
+    });
+    // The first line of the example imports the file.
+    if let Some((line, column)) = position.filter(|(line, _)| *line > 0) {
+        let indent = example.indents.get(line - 1).copied().unwrap_or_default();
+        let path = unix_slash(example.location.vpath().as_rootless_path());
+        let (line, column) = (example.line + line + 1, column + indent + 1);
+        diag.message = eco_format!("{path}:{line}:{column}: {}", diag.message);
+    }
+    diag.span = Span::detached();
+    diag
+}
+
+/// Gets the name of an example, which is the stem of its file name.
+fn example_name(example: &Source) -> &str {
+    let path = example.id().vpath().as_rooted_path();
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+}
+
 fn get_example_file(world: &dyn World, name: &str, id: FileId, span: Span) -> Result<Source> {
     let source = world.source(id).context_ut("cannot find file")?;
     let node = LinkedNode::new(source.root());
@@ -608,6 +1066,97 @@ fn print_diag_or_error<T>(world: &impl SourceWorld, result: Result<T>) -> Result
     }
 }
 
+/// A test case in the reports.
+struct ReportedCase<'a> {
+    name: &'a str,
+    /// The path of the file defining the test case.
+    file: String,
+    /// The one-based line defining the test case.
+    line: usize,
+    /// The output of the test case, or `None` if it is skipped.
+    output: Option<&'a CaseOutput>,
+}
+
+impl ReportedCase<'_> {
+    fn failed(&self) -> bool {
+        self.output
+            .is_some_and(|output| !output.failures.is_empty())
+    }
+
+    fn time(&self) -> f64 {
+        self.output
+            .map_or(0., |output| output.duration.as_secs_f64())
+    }
+}
+
+/// Builds the JUnit report of the test cases, with a test suite per file.
+fn junit_report(cases: &[ReportedCase]) -> String {
+    let mut suites = BTreeMap::<&str, Vec<_>>::new();
+    for case in cases {
+        suites.entry(case.file.as_str()).or_default().push(case);
+    }
+
+    let mut out = String::new();
+    let total = cases.len();
+    let failures = cases.iter().filter(|case| case.failed()).count();
+    let skipped = cases.iter().filter(|case| case.output.is_none()).count();
+    let time = cases.iter().map(ReportedCase::time).sum::<f64>();
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        out,
+        r#"<testsuites name="tinymist" tests="{total}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#
+    );
+    for (file, cases) in suites {
+        let file = escape_xml(file);
+        let total = cases.len();
+        let failures = cases.iter().filter(|case| case.failed()).count();
+        let skipped = cases.iter().filter(|case| case.output.is_none()).count();
+        let time = cases.iter().map(|case| case.time()).sum::<f64>();
+        let _ = writeln!(
+            out,
+            r#"  <testsuite name="{file}" tests="{total}" failures="{failures}" errors="0" skipped="{skipped}" time="{time:.3}">"#
+        );
+        for case in cases {
+            let name = escape_xml(case.name);
+            let line = case.line;
+            let time = case.time();
+            let _ = write!(
+                out,
+                r#"    <testcase name="{name}" classname="{file}" file="{file}" line="{line}" time="{time:.3}""#
+            );
+            let Some(output) = case.output else {
+                let _ = writeln!(out, ">");
+                let _ = writeln!(
+                    out,
+                    r#"      <skipped message="not impacted by the changes"/>"#
+                );
+                let _ = writeln!(out, "    </testcase>");
+                continue;
+            };
+            if output.failures.is_empty() {
+                let _ = writeln!(out, "/>");
+                continue;
+            }
+
+            let message = escape_xml(&output.failures.join("; "));
+            let details = output.diagnostics.iter().flatten();
+            let details = details
+                .map(|diag| diag.message.as_str())
+                .collect::<Vec<_>>();
+            let _ = writeln!(out, ">");
+            let _ = writeln!(
+                out,
+                r#"      <failure message="{message}">{}</failure>"#,
+                escape_xml(&details.join("\n"))
+            );
+            let _ = writeln!(out, "    </testcase>");
+        }
+        let _ = writeln!(out, "  </testsuite>");
+    }
+    let _ = writeln!(out, "</testsuites>");
+    out
+}
+
 enum Level {
     Error,
     Info,
@@ -621,5 +1170,160 @@ fn msg(level: Level, prefix: &str, msg: fmt::Arguments) {
         Level::Hint => "\x1b[1;36m",
     };
     let reset = "\x1b[0m";
-    eprintln!("{color}{prefix:>PREFIX_LEN$}{reset} {msg}");
+    let line = format!("{color}{prefix:>PREFIX_LEN$}{reset} {msg}");
+    CASE_OUTPUT.with_borrow_mut(|output| match output {
+        Some(output) => output.logs.push(line),
+        None => eprintln!("{line}"),
+    });
+}
+
+thread_local! {
+    /// The output of the test case running on the thread, which is printed
+    /// after all the test cases finish, to keep the output in order.
+    static CASE_OUTPUT: RefCell<Option<CaseOutput>> = const { RefCell::new(None) };
+}
+
+/// The output of a test case.
+#[derive(Default)]
+struct CaseOutput {
+    logs: Vec<String>,
+    diagnostics: Vec<EcoVec<SourceDiagnostic>>,
+    /// The reasons of the failures.
+    failures: Vec<String>,
+    /// The time spent on the test case.
+    duration: std::time::Duration,
+}
+
+/// The differences between a rendered page and its reference image.
+struct ImageDiff {
+    /// The number of the pixels differing from the reference.
+    differing: usize,
+    /// The number of the pixels.
+    total: usize,
+    /// The reference image faded, with the differing pixels in red.
+    image: image::RgbaImage,
+}
+
+/// Compares two PNG images pixel by pixel, returning `None` if their sizes
+/// differ.
+fn diff_images(expected: &[u8], actual: &[u8], threshold: u8) -> Result<Option<ImageDiff>> {
+    let decode = |data: &[u8]| -> Result<image::RgbaImage> {
+        let image = image::load_from_memory_with_format(data, image::ImageFormat::Png);
+        Ok(image.context_ut("decode image")?.to_rgba8())
+    };
+    let (expected, actual) = (decode(expected)?, decode(actual)?);
+    if expected.dimensions() != actual.dimensions() {
+        return Ok(None);
+    }
+
+    let (width, height) = expected.dimensions();
+    let mut image = image::RgbaImage::new(width, height);
+    let mut differing = 0;
+    let pixels = expected.pixels().zip(actual.pixels());
+    for ((expected, actual), pixel) in pixels.zip(image.pixels_mut()) {
+        let channels = expected.0.iter().zip(actual.0.iter());
+        if channels.any(|(e, a)| e.abs_diff(*a) > threshold) {
+            differing += 1;
+            *pixel = image::Rgba([255, 0, 0, 255]);
+        } else {
+            let [r, g, b, _] = expected.0.map(u32::from);
+            let luma = (r * 299 + g * 587 + b * 114) / 1000;
+            let faded = (255 - (255 - luma) / 4) as u8;
+            *pixel = image::Rgba([faded, faded, faded, 255]);
+        }
+    }
+
+    Ok(Some(ImageDiff {
+        differing,
+        total: (width * height) as usize,
+        image,
+    }))
+}
+
+/// Runs a test case, capturing its output.
+///
+/// The previous output is restored afterwards, as the thread may run another
+/// test case while waiting for the nested parallel jobs of a test case.
+fn capture_output(f: impl FnOnce()) -> CaseOutput {
+    let prev = CASE_OUTPUT.replace(Some(CaseOutput::default()));
+    f();
+    CASE_OUTPUT.replace(prev).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut image = image::RgbaImage::new(pixels.len() as u32, 1);
+        for (pixel, rgba) in image.pixels_mut().zip(pixels) {
+            *pixel = image::Rgba(*rgba);
+        }
+        let mut buf = vec![];
+        image
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn image_diff() {
+        let white = [255, 255, 255, 255];
+        let expected = png(&[white, white, [0, 0, 0, 255]]);
+        let actual = png(&[white, [250, 250, 250, 255], [0, 0, 0, 255]]);
+
+        let diff = diff_images(&expected, &actual, 0).unwrap().unwrap();
+        assert_eq!((diff.differing, diff.total), (1, 3));
+        assert_eq!(diff.image.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        let diff = diff_images(&expected, &actual, 5).unwrap().unwrap();
+        assert_eq!(diff.differing, 0);
+
+        assert!(diff_images(&expected, &png(&[white]), 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn junit() {
+        let passed = CaseOutput {
+            duration: std::time::Duration::from_millis(1500),
+            ..Default::default()
+        };
+        let failed = CaseOutput {
+            diagnostics: vec![typst::ecow::eco_vec![SourceDiagnostic::error(
+                Span::detached(),
+                "expected <1> & got \"2\"",
+            )]],
+            failures: vec!["compare <a>".into()],
+            ..Default::default()
+        };
+        let case = |name, file: &str, line, output| ReportedCase {
+            name,
+            file: file.into(),
+            line,
+            output,
+        };
+
+        let report = junit_report(&[
+            case("a & b", "tests/a.typ", 1, Some(&passed)),
+            case("<c>", "tests/a.typ", 3, Some(&failed)),
+            case("d", "tests/\"b\".typ", 1, None),
+        ]);
+        assert_eq!(
+            report,
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="tinymist" tests="3" failures="1" skipped="1" time="1.500">
+  <testsuite name="tests/&quot;b&quot;.typ" tests="1" failures="0" errors="0" skipped="1" time="0.000">
+    <testcase name="d" classname="tests/&quot;b&quot;.typ" file="tests/&quot;b&quot;.typ" line="1" time="0.000">
+      <skipped message="not impacted by the changes"/>
+    </testcase>
+  </testsuite>
+  <testsuite name="tests/a.typ" tests="2" failures="1" errors="0" skipped="0" time="1.500">
+    <testcase name="a &amp; b" classname="tests/a.typ" file="tests/a.typ" line="1" time="1.500"/>
+    <testcase name="&lt;c&gt;" classname="tests/a.typ" file="tests/a.typ" line="3" time="0.000">
+      <failure message="compare &lt;a&gt;">expected &lt;1&gt; &amp; got &quot;2&quot;</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"##
+        );
+    }
 }
@@ -1,15 +1,23 @@
 //! Word count tool for documents.
 
 use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use reflexo_typst::debug_loc::SourceSpanOffset;
 use serde::{Deserialize, Serialize};
-use tinymist_std::typst::TypstDocument;
+use sync_ls::{internal_error, just_future, SchedulableResponse};
+use tinymist_project::LspWorld;
+use tinymist_std::typst::{TypstDocument, TypstPagedDocument};
 use tinymist_task::TextExport;
+use typst::foundations::StyleChain;
+use typst::World;
 use typst::{syntax::Span, text::TextItem};
 use unicode_script::{Script, UnicodeScript};
 
+use crate::world::TaskInputs;
+use crate::ServerState;
+
 /// Words count for a document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,14 +38,18 @@ pub fn word_count(doc: &TypstDocument) -> WordsCount {
     // the mapping is still not use, so we prevent the warning here
     let _ = TextContent::map_back_spans;
 
+    // First, get text representation of the document.
+    let content = TextExport::run_on_doc(doc).unwrap_or_default();
+    count_words(&content)
+}
+
+/// Count words in a text.
+fn count_words(content: &str) -> WordsCount {
     let mut words = 0;
     let mut chars = 0;
     let mut cjk_chars = 0;
     let mut spaces = 0;
 
-    // First, get text representation of the document.
-    let content = TextExport::run_on_doc(doc).unwrap_or_default();
-
     /// A automaton to count words.
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     enum CountState {
@@ -49,13 +61,6 @@ pub fn word_count(doc: &TypstDocument) -> WordsCount {
         InCJK,
     }
 
-    fn is_cjk(c: char) -> bool {
-        matches!(
-            c.script(),
-            Script::Han | Script::Hiragana | Script::Katakana | Script::Hangul
-        )
-    }
-
     let mut state = CountState::InSpace;
     for c in content.chars() {
         chars += 1;
@@ -91,6 +96,266 @@ pub fn word_count(doc: &TypstDocument) -> WordsCount {
     }
 }
 
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c.script(),
+        Script::Han | Script::Hiragana | Script::Katakana | Script::Hangul
+    )
+}
+
+/// The default reading speed, in words per minute.
+pub const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+
+/// The statistics of some text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStatistics {
+    /// Number of words, where a CJK character is counted as a word.
+    pub words: usize,
+    /// Number of characters, excluding the whitespaces.
+    pub characters: usize,
+    /// Number of sentences, i.e. the words ending with a sentence terminator,
+    /// e.g. `.` or `?`.
+    pub sentences: usize,
+    /// The estimated reading time in seconds.
+    pub reading_time: u64,
+}
+
+impl TextStatistics {
+    fn new(text: &str) -> Self {
+        let count = count_words(text);
+        Self {
+            words: count.words,
+            characters: text.chars().filter(|c| !c.is_whitespace()).count(),
+            sentences: count_sentences(text),
+            reading_time: 0,
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.sentences += other.sentences;
+    }
+
+    fn with_reading_time(mut self, words_per_minute: u32) -> Self {
+        let words_per_minute = words_per_minute.max(1) as f64;
+        self.reading_time = (self.words as f64 * 60. / words_per_minute).ceil() as u64;
+        self
+    }
+}
+
+/// The statistics of a section starting at a heading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionStatistics {
+    /// The plain text of the heading.
+    pub heading: String,
+    /// The level of the heading, starting from 1.
+    pub level: usize,
+    /// The path of the file containing the heading.
+    pub path: Option<PathBuf>,
+    /// The 1-based line of the heading.
+    pub line: Option<usize>,
+    /// The statistics of the section, including the heading and the
+    /// subsections.
+    pub statistics: TextStatistics,
+}
+
+/// The statistics of a document, broken down per heading section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStatistics {
+    /// The reading speed by which the reading time is estimated.
+    pub words_per_minute: u32,
+    /// The statistics of the whole document.
+    pub total: TextStatistics,
+    /// The statistics of the text before the first heading.
+    pub preamble: TextStatistics,
+    /// The statistics of the sections, in the order of the headings.
+    pub sections: Vec<SectionStatistics>,
+}
+
+impl DocumentStatistics {
+    /// Prints the statistics to the stderr.
+    pub fn print(&self) {
+        let row = |name: &str, stats: &TextStatistics| {
+            let minutes = stats.reading_time.div_ceil(60);
+            eprintln!(
+                "  {name:<40} {:>8} words {:>9} chars {:>6} sentences {minutes:>4} min",
+                stats.words, stats.characters, stats.sentences
+            );
+        };
+
+        eprintln!("statistics:");
+        row("total", &self.total);
+        if self.preamble.words > 0 {
+            row("(before the first heading)", &self.preamble);
+        }
+        for section in &self.sections {
+            let indent = "  ".repeat(section.level.saturating_sub(1));
+            let mut name = format!("{indent}{}", section.heading);
+            if name.chars().count() > 40 {
+                name = name.chars().take(39).chain(['…']).collect();
+            }
+            row(&name, &section.statistics);
+        }
+    }
+}
+
+/// Computes the statistics of a document from its text, broken down per
+/// heading section.
+pub fn document_statistics(
+    world: &LspWorld,
+    doc: &TypstDocument,
+    words_per_minute: u32,
+) -> DocumentStatistics {
+    let sections = TextExport::sections_of_doc(doc);
+    let own = sections
+        .iter()
+        .map(|section| TextStatistics::new(&section.text))
+        .collect::<Vec<_>>();
+
+    let mut total = TextStatistics::default();
+    for stats in &own {
+        total.add(stats);
+    }
+
+    let mut preamble = TextStatistics::default();
+    let mut section_stats = vec![];
+    for (idx, section) in sections.iter().enumerate() {
+        let Some(heading) = &section.heading else {
+            preamble.add(&own[idx]);
+            continue;
+        };
+
+        // A section ends at the next heading of the same or a higher level.
+        let level = heading.resolve_level(StyleChain::default()).get();
+        let mut statistics = own[idx].clone();
+        for (next, stats) in sections.iter().zip(&own).skip(idx + 1) {
+            let next_level = next
+                .heading
+                .as_ref()
+                .map(|heading| heading.resolve_level(StyleChain::default()).get());
+            if next_level.is_some_and(|next_level| next_level <= level) {
+                break;
+            }
+            statistics.add(stats);
+        }
+
+        let (path, line) = heading_location(world, heading.span()).unzip();
+        section_stats.push(SectionStatistics {
+            heading: heading.body.plain_text().trim().to_owned(),
+            level,
+            path,
+            line,
+            statistics: statistics.with_reading_time(words_per_minute),
+        });
+    }
+
+    DocumentStatistics {
+        words_per_minute,
+        total: total.with_reading_time(words_per_minute),
+        preamble: preamble.with_reading_time(words_per_minute),
+        sections: section_stats,
+    }
+}
+
+/// The `tinymist/documentStatistics` request, which computes the statistics
+/// of a document, broken down per heading section.
+pub struct DocumentStatisticsRequest;
+impl lsp_types::request::Request for DocumentStatisticsRequest {
+    type Params = DocumentStatisticsParams;
+    type Result = Option<DocumentStatistics>;
+    const METHOD: &'static str = "tinymist/documentStatistics";
+}
+
+/// The parameters of the `tinymist/documentStatistics` request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStatisticsParams {
+    /// The path of the document, defaulting to the main document.
+    pub path: Option<PathBuf>,
+    /// The reading speed by which the reading time is estimated, defaulting to
+    /// 200 words per minute.
+    pub words_per_minute: Option<u32>,
+}
+
+impl ServerState {
+    /// Computes the statistics of a document. The last successfully compiled
+    /// document is used for the main document, and other documents are
+    /// compiled on demand.
+    pub(crate) fn document_statistics(
+        &mut self,
+        params: DocumentStatisticsParams,
+    ) -> SchedulableResponse<Option<DocumentStatistics>> {
+        let entry = params
+            .path
+            .map(|path| self.entry_resolver().resolve(Some(path.into())));
+        let graph = self.snapshot().map_err(internal_error)?;
+        let words_per_minute = params.words_per_minute.unwrap_or(DEFAULT_WORDS_PER_MINUTE);
+
+        just_future(async move {
+            let Some(entry) = entry else {
+                let doc = graph.snap.success_doc.as_ref();
+                return Ok(doc.map(|doc| document_statistics(graph.world(), doc, words_per_minute)));
+            };
+
+            let graph = graph.task(TaskInputs {
+                entry: Some(entry),
+                inputs: None,
+            });
+            let world = graph.world();
+            let doc = typst::compile::<TypstPagedDocument>(world).output.ok();
+            let doc = doc.map(|doc| TypstDocument::Paged(Arc::new(doc)));
+            Ok(doc.map(|doc| document_statistics(world, &doc, words_per_minute)))
+        })
+    }
+}
+
+/// Gets the path and the 1-based line of a heading.
+fn heading_location(world: &LspWorld, span: Span) -> Option<(PathBuf, usize)> {
+    let id = span.id()?;
+    let source = world.source(id).ok()?;
+    let line = source.byte_to_line(source.range(span)?.start)?;
+    let path = world.path_for_id(id).ok()?.to_err().ok()?;
+    Some((path.to_path_buf(), line + 1))
+}
+
+/// Counts the sentences in a text, i.e. the words followed by a sentence
+/// terminator, which is followed by a whitespace, a closing punctuation or the
+/// end of the text. The CJK terminators end a sentence regardless. The text
+/// without a terminator, e.g. a heading, is counted along with the next
+/// sentence.
+fn count_sentences(text: &str) -> usize {
+    let is_terminator = |c: char| matches!(c, '.' | '!' | '?' | '…' | '‽');
+    let is_cjk_terminator = |c: char| matches!(c, '。' | '！' | '？');
+    let is_closing = |c: char| matches!(c, '"' | '\'' | '”' | '’' | ')' | ']' | '»' | '」' | '』');
+
+    let mut sentences = 0;
+    let mut in_sentence = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_cjk_terminator(c) {
+            sentences += usize::from(in_sentence);
+            in_sentence = false;
+        } else if is_terminator(c) {
+            while chars
+                .next_if(|&c| is_terminator(c) || is_closing(c))
+                .is_some()
+            {}
+            let ends = chars.peek().is_none_or(|c| c.is_whitespace());
+            if in_sentence && ends {
+                sentences += 1;
+                in_sentence = false;
+            }
+        } else if c.is_alphanumeric() {
+            in_sentence = true;
+        }
+    }
+    sentences
+}
+
 /// Given a text range, map it back to the original document.
 #[derive(Debug, Clone)]
 pub struct MappedSpan {
@@ -256,3 +521,25 @@ impl SpanMapper {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences() {
+        assert_eq!(count_sentences("Hello world. How are you? Fine!"), 3);
+        assert_eq!(count_sentences("Version 1.2 is out... \"Really?\" Yes."), 3);
+        assert_eq!(count_sentences("Introduction\nIt starts here."), 1);
+        assert_eq!(count_sentences("你好。今天好吗？"), 2);
+        assert_eq!(count_sentences("..."), 0);
+    }
+
+    #[test]
+    fn reading_time() {
+        let stats = TextStatistics::new("one two three four five").with_reading_time(200);
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.characters, 19);
+        assert_eq!(stats.reading_time, 2);
+    }
+}
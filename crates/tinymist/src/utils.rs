@@ -1,5 +1,6 @@
 use core::fmt;
 
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
@@ -141,3 +142,75 @@ impl AsyncWrite for ConnWithCancel {
         Pin::new(&mut Pin::into_inner(self).stream).poll_shutdown(context)
     }
 }
+
+/// Generates a token to authenticate the clients of the servers, e.g. the
+/// preview and the compile server.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("cannot generate a token");
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Whether the address, i.e. `host:port`, is only reachable from this
+/// machine.
+pub fn is_loopback_addr(addr: &str) -> bool {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => host == "localhost",
+    }
+}
+
+/// Gets the value of a parameter in the query of a URL.
+pub(crate) fn query_param<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
+    let mut pairs = query?.split('&').filter_map(|pair| pair.split_once('='));
+    pairs.find(|(name, _)| *name == key).map(|(_, value)| value)
+}
+
+/// Whether the query of a URL carries the token, e.g. `?token=<TOKEN>`.
+pub(crate) fn has_token(query: Option<&str>, token: &str) -> bool {
+    query_param(query, "token").is_some_and(|given| token_eq(given, token))
+}
+
+/// Compares the tokens in constant time, which doesn't leak the length of
+/// the common prefix.
+fn token_eq(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token() {
+        assert_eq!(generate_token().len(), 32);
+        assert_ne!(generate_token(), generate_token());
+
+        let query = Some("action=reset&token=abc");
+        assert_eq!(query_param(query, "token"), Some("abc"));
+        assert_eq!(query_param(query, "missing"), None);
+        assert!(token_eq("abc", "abc"));
+        assert!(!token_eq("abd", "abc"));
+        assert!(!token_eq("ab", "abc"));
+        assert!(has_token(query, "abc"));
+        assert!(!has_token(query, "abd"));
+        assert!(!has_token(None, "abc"));
+    }
+
+    #[test]
+    fn test_loopback_addr() {
+        assert!(is_loopback_addr("127.0.0.1:23625"));
+        assert!(is_loopback_addr("localhost:0"));
+        assert!(is_loopback_addr("[::1]:23625"));
+        assert!(!is_loopback_addr("0.0.0.0:23625"));
+        assert!(!is_loopback_addr("192.168.1.2:23625"));
+        assert!(!is_loopback_addr("example.com:80"));
+    }
+}
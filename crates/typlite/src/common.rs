@@ -213,6 +213,26 @@ impl InlineNode {
     }
 }
 
+/// Footnote node, which is kept as a note in the formats supporting notes
+#[derive(Debug, PartialEq, Clone)]
+#[custom_node(block = false, html_impl = false)]
+pub struct FootnoteNode {
+    /// The inline content of the footnote
+    pub content: Vec<Node>,
+}
+
+impl FootnoteNode {
+    fn write_custom(&self, writer: &mut CommonMarkWriter) -> WriteResult<()> {
+        let mut temp_writer = CommonMarkWriter::with_options(writer.options.clone());
+        for node in &self.content {
+            temp_writer.write(node)?;
+        }
+        let content = temp_writer.into_string();
+        writer.write_str(&format!("^[{}]", content))?;
+        Ok(())
+    }
+}
+
 /// Verbatim node for raw text output
 #[derive(Debug, PartialEq, Clone)]
 #[custom_node(block = true, html_impl = false)]
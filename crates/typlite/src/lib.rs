@@ -36,7 +36,7 @@ use crate::tinymist_std::typst::{LazyHash, TypstDict};
 pub type Result<T, Err = Error> = std::result::Result<T, Err>;
 
 pub use cmark_writer::ast;
-pub use tinymist_project::CompileOnceArgs;
+pub use tinymist_project::{CompileOnceArgs, FigureLowering, MathLowering, RawLowering};
 pub use tinymist_std;
 
 #[derive(Clone)]
@@ -176,6 +176,12 @@ pub struct TypliteFeat {
     /// It resembles the regular typst show rule function, like `#show:
     /// article`.
     pub processor: Option<String>,
+    /// How the math equations are lowered.
+    pub math: MathLowering,
+    /// How the figures are lowered.
+    pub figure: FigureLowering,
+    /// How the raw blocks are lowered.
+    pub raw: RawLowering,
 }
 
 impl TypliteFeat {
@@ -212,6 +218,16 @@ impl TypliteFeat {
         if format == Format::Text || self.remove_html {
             dict.insert("x-remove-html".into(), Str("true".into()));
         }
+        if self.math == MathLowering::Text {
+            dict.insert("x-math".into(), Str("text".into()));
+        }
+        if self.raw == RawLowering::Svg {
+            dict.insert("x-raw".into(), Str("svg".into()));
+        }
+        #[cfg(feature = "docx")]
+        if format == Format::Docx {
+            dict.insert("x-footnote".into(), Str("note".into()));
+        }
 
         let task_inputs = TaskInputs {
             entry: Some(entry.select_in_workspace(main_id.vpath().as_rooted_path())),
@@ -12,7 +12,7 @@ use tinymist_project::{
     base::print_diagnostics, DiagnosticFormat, LspWorld, SourceWorld, WorldProvider,
 };
 use tinymist_std::{error::prelude::*, Result};
-use typlite::{common::Format, TypliteFeat};
+use typlite::{common::Format, FigureLowering, MathLowering, RawLowering, TypliteFeat};
 use typlite::{CompileOnceArgs, Typlite};
 use typst::foundations::Bytes;
 
@@ -47,6 +47,18 @@ pub struct CompileArgs {
     /// ```
     #[clap(long = "processor", default_value = None, value_name = "PACKAGE_SPEC")]
     pub processor: Option<String>,
+
+    /// How the math equations are lowered.
+    #[clap(long, default_value = "svg")]
+    pub math: MathLowering,
+
+    /// How the figures are lowered.
+    #[clap(long, default_value = "html")]
+    pub figure: FigureLowering,
+
+    /// How the raw blocks are lowered.
+    #[clap(long, default_value = "fenced")]
+    pub raw: RawLowering,
 }
 
 fn main() -> Result<()> {
@@ -89,6 +101,9 @@ fn run(args: CompileArgs, world: Arc<LspWorld>) -> Result<()> {
         .with_feature(TypliteFeat {
             assets_path: args.assets_path,
             processor: args.processor,
+            math: args.math,
+            figure: args.figure,
+            raw: args.raw,
             ..Default::default()
         })
         .convert_doc(output_format)?;
@@ -106,6 +106,7 @@
   ),
   "",
 )
+#let md-footnote(body) = html.elem("span", html.elem("m1footnote", body))
 #let md-figure(body, caption: none) = html.elem(
   "m1figure",
   attrs: (
@@ -180,6 +181,17 @@
   }
 }
 
+// Whether the equations are lowered to their plain text.
+#let math-as-text = (
+  sys.inputs.at("x-remove-html", default: none) == "true" or sys.inputs.at("x-math", default: none) == "text"
+)
+// Whether the footnotes are kept as notes, instead of the default rendering.
+#let footnote-as-note = sys.inputs.at("x-footnote", default: none) == "note"
+// Whether the raw blocks are rendered as images.
+#let raw-as-svg = (
+  sys.inputs.at("x-remove-html", default: none) != "true" and sys.inputs.at("x-raw", default: none) == "svg"
+)
+
 #let md-doc(body) = context {
   // distinguish parbreak from <p> tag
   show parbreak: it => if-not-paged(it, md-parbreak)
@@ -190,7 +202,14 @@
   // todo: icc?
   show image: it => if-not-paged(it, md-image(src: it.source, alt: it.alt))
 
-  show raw: it => if-not-paged(it, md-raw(lang: it.lang, block: it.block, it.text))
+  show raw: it => if-not-paged(
+    it,
+    if it.block and raw-as-svg {
+      html.elem("p", html.frame(it))
+    } else {
+      md-raw(lang: it.lang, block: it.block, it.text)
+    },
+  )
   show link: it => if-not-paged(it, md-link(dest: it.dest, it.body))
   show ref: it => if-not-paged(it, md-ref(it))
 
@@ -205,14 +224,14 @@
     it,
     html.elem(
       "m1eqinline",
-      if sys.inputs.at("x-remove-html", default: none) != "true" { html.frame(box(inset: 0.5em, it)) } else {
+      if not math-as-text { html.frame(box(inset: 0.5em, it)) } else {
         process-math-eq(it.body).flatten().join()
       },
     ),
   )
   show math.equation.where(block: true): it => if-not-paged(
     it,
-    if sys.inputs.at("x-remove-html", default: none) != "true" {
+    if not math-as-text {
       html.elem(
         "m1eqblock",
         html.frame(block(inset: 0.5em, it)),
@@ -227,6 +246,7 @@
 
   show linebreak: it => if-not-paged(it, md-linebreak)
   show figure: it => if-not-paged(it, md-figure(it.body, caption: it.caption))
+  show footnote: it => if-not-paged(it, if footnote-as-note { md-footnote(it.body) } else { it })
 
   html.elem("m1document", body)
 }
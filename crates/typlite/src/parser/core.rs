@@ -118,6 +118,7 @@ impl HtmlToAstParser {
             }
 
             md_tag::figure => self.convert_figure(element),
+            md_tag::footnote => self.convert_footnote(element),
             md_tag::highlight => self.convert_highlight(element),
             md_tag::strike => self.convert_strikethrough(element),
             md_tag::link => self.convert_link(element),
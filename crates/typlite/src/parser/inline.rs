@@ -4,8 +4,8 @@ use cmark_writer::ast::Node;
 use typst::html::HtmlElement;
 
 use crate::attributes::{FigureAttr, ImageAttr, LinkAttr, TypliteAttrsParser};
-use crate::common::{CenterNode, FigureNode, HighlightNode};
-use crate::Result;
+use crate::common::{CenterNode, FigureNode, FootnoteNode, HighlightNode};
+use crate::{FigureLowering, Result};
 
 use super::core::HtmlToAstParser;
 
@@ -35,6 +35,15 @@ impl HtmlToAstParser {
         Ok(())
     }
 
+    /// Convert footnote element
+    pub fn convert_footnote(&mut self, element: &HtmlElement) -> Result<()> {
+        let mut content = Vec::new();
+        self.convert_children_into(&mut content, element)?;
+        self.inline_buffer
+            .push(Node::Custom(Box::new(FootnoteNode { content })));
+        Ok(())
+    }
+
     /// Convert strikethrough element
     pub fn convert_strikethrough(&mut self, element: &HtmlElement) -> Result<()> {
         let mut content = Vec::new();
@@ -78,6 +87,18 @@ impl HtmlToAstParser {
         // Find image and body content
         let mut body_content = Vec::new();
         self.convert_children_into(&mut body_content, element)?;
+
+        // Writes the caption as a plain paragraph following the body
+        if self.feat.figure == FigureLowering::Markdown {
+            self.blocks.push(Node::Paragraph(body_content));
+            if !caption.is_empty() {
+                let caption = vec![Node::Text(caption.into())];
+                self.blocks
+                    .push(Node::Paragraph(vec![Node::Emphasis(caption)]));
+            }
+            return Ok(());
+        }
+
         let body = Box::new(Node::Paragraph(body_content));
 
         // Create figure node with centering
@@ -36,6 +36,7 @@ pub mod md_tag {
         grid -> m1grid
         // grid_cell -> m1gridcell
         figure -> m1figure
+        footnote -> m1footnote
 
         math_equation_inline -> m1eqinline
         math_equation_block -> m1eqblock
@@ -83,6 +83,10 @@ impl DocxStyles {
             .size(16)
             .align(AlignmentType::Center);
 
+        let footnote_text = Style::new("FootnoteText", StyleType::Paragraph)
+            .name("Footnote Text")
+            .size(18);
+
         let table = Style::new("Table", StyleType::Table)
             .name("Table")
             .table_align(TableAlignmentType::Center);
@@ -102,6 +106,7 @@ impl DocxStyles {
             .add_style(hyperlink)
             .add_style(blockquote)
             .add_style(caption)
+            .add_style(footnote_text)
             .add_style(table)
     }
 }
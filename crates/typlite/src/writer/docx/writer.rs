@@ -8,7 +8,7 @@ use std::fs;
 use std::io::Cursor;
 
 use crate::common::{
-    CenterNode, FigureNode, FormatWriter, HighlightNode, InlineNode, VerbatimNode,
+    CenterNode, FigureNode, FootnoteNode, FormatWriter, HighlightNode, InlineNode, VerbatimNode,
 };
 use crate::Result;
 
@@ -250,6 +250,21 @@ impl DocxWriter {
                     run = self.process_inline_to_run(run, child)?;
                 }
             }
+            node if node.is_custom_type::<FootnoteNode>() => {
+                let footnote_node = node.as_custom_type::<FootnoteNode>().unwrap();
+                // The footnote is referenced by a superscript mark, and its content is
+                // moved to the bottom of the page by Word
+                let mut para = Paragraph::new().style("FootnoteText");
+                for child in &footnote_node.content {
+                    let child_run = self.process_inline_to_run(Run::new(), child)?;
+                    if !child_run.children.is_empty() {
+                        para = para.add_run(child_run);
+                    }
+                }
+                run = run
+                    .vert_align(VertAlignType::SuperScript)
+                    .add_footnote_reference(Footnote::new().add_content(para));
+            }
             node if node.is_custom_type::<VerbatimNode>() => {
                 let node = node.as_custom_type::<VerbatimNode>().unwrap();
                 eprintln!("Warning: `m1verbatim` is ignored {:?}.", node.content);
@@ -10,8 +10,8 @@ use crate::actor::render::RenderActorRequest;
 use crate::debug_loc::{InternQuery, SpanInterner};
 use crate::outline::Outline;
 use crate::{
-    ChangeCursorPositionRequest, DocToSrcJumpInfo, EditorServer, MemoryFiles, MemoryFilesShort,
-    ResolveSourceLocRequest,
+    ChangeCursorPositionRequest, DocToSrcJumpInfo, EditorServer, ExportViewRequest,
+    ExportViewResponse, MemoryFiles, MemoryFilesShort, ResolveSourceLocRequest, ViewExporter,
 };
 
 use super::webview::WebviewActorRequest;
@@ -114,6 +114,7 @@ pub struct EditorActor<T> {
     webview_sender: broadcast::Sender<WebviewActorRequest>,
 
     span_interner: SpanInterner,
+    exporter: ViewExporter,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -133,6 +134,8 @@ pub enum ControlPlaneMessage {
     UpdateMemoryFiles(MemoryFiles),
     #[serde(rename = "removeMemoryFiles")]
     RemoveMemoryFiles(MemoryFilesShort),
+    #[serde(rename = "exportView")]
+    ExportView(ExportViewRequest),
 }
 
 #[derive(Debug, Serialize)]
@@ -146,6 +149,8 @@ pub enum ControlPlaneResponse {
     CompileStatus(CompileStatus),
     #[serde(rename = "outline")]
     Outline(Outline),
+    #[serde(rename = "exportView")]
+    ExportView(ExportViewResponse),
 }
 
 impl<T: EditorServer> EditorActor<T> {
@@ -156,6 +161,7 @@ impl<T: EditorServer> EditorActor<T> {
         renderer_sender: broadcast::Sender<RenderActorRequest>,
         webview_sender: broadcast::Sender<WebviewActorRequest>,
         span_interner: SpanInterner,
+        exporter: ViewExporter,
     ) -> Self {
         Self {
             server,
@@ -165,6 +171,7 @@ impl<T: EditorServer> EditorActor<T> {
             webview_sender,
 
             span_interner,
+            exporter,
         }
     }
 
@@ -250,6 +257,11 @@ impl<T: EditorServer> EditorActor<T> {
                                 self.server.remove_memory_files(req).await,
                             );
                         }
+                        ControlPlaneMessage::ExportView(req) => {
+                            log::debug!("EditorActor: received message from editor: {:?}", req);
+                            let resp = self.exporter.export(&req);
+                            self.editor_conn.resp_ctl_plane("ExportView", ControlPlaneResponse::ExportView(resp)).await;
+                        }
                     };
                 }
             }
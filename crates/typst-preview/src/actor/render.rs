@@ -5,14 +5,24 @@ use reflexo_typst::debug_loc::{
     DocumentPosition, ElementPoint, LspPosition, SourceLocation, SourceSpanOffset,
 };
 use reflexo_vec2svg::IncrSvgDocServer;
-use tinymist_std::typst::TypstDocument;
-use tokio::sync::{broadcast, mpsc};
+use tinymist_std::typst::{TypstDocument, TypstPagedDocument};
+use tokio::sync::{broadcast, mpsc, watch};
+use typst::layout::{Frame, Page};
 
 use super::{editor::EditorActorRequest, webview::WebviewActorRequest};
 use crate::debug_loc::SpanInterner;
 use crate::outline::Outline;
+use crate::recolor::Recolor;
 use crate::{ChangeCursorPositionRequest, CompileView, DocToSrcJumpInfo, ResolveSourceLocRequest};
 
+/// The number of pages rendered before and after the visible pages, so that
+/// the placeholders are not revealed by scrolling a bit.
+const PREFETCH_PAGES: usize = 2;
+
+/// The indices of the pages visible in the viewport of the client, or `None`
+/// if the client doesn't send viewport hints, which renders all the pages.
+pub type VisiblePages = Option<Range<usize>>;
+
 #[derive(Debug, Clone)]
 pub struct ResolveSpanRequest(pub Vec<ElementPoint>);
 
@@ -25,6 +35,8 @@ pub enum RenderActorRequest {
     WebviewResolveFrameLoc(DocumentPosition),
     ResolveSourceLoc(ResolveSourceLocRequest),
     ChangeCursorPosition(ChangeCursorPositionRequest),
+    LayoutGuides,
+    SpeakerNotes,
 }
 
 impl RenderActorRequest {
@@ -37,6 +49,8 @@ impl RenderActorRequest {
             Self::ResolveSourceLoc(_) => false,
             Self::WebviewResolveFrameLoc(_) => false,
             Self::ChangeCursorPosition(_) => false,
+            Self::LayoutGuides => false,
+            Self::SpeakerNotes => false,
         }
     }
 }
@@ -48,6 +62,13 @@ pub struct RenderActor {
     editor_conn_sender: mpsc::UnboundedSender<EditorActorRequest>,
     svg_sender: mpsc::UnboundedSender<Vec<u8>>,
     webview_sender: broadcast::Sender<WebviewActorRequest>,
+    visible: watch::Receiver<VisiblePages>,
+    recolor: Option<Recolor>,
+    /// The document whose pages are marked in `rendered`.
+    rendered_doc: Option<Arc<TypstPagedDocument>>,
+    /// Whether the pages of the document have been rendered, i.e. have ever
+    /// been near the viewport.
+    rendered: Vec<bool>,
 }
 
 impl RenderActor {
@@ -57,6 +78,8 @@ impl RenderActor {
         editor_conn_sender: mpsc::UnboundedSender<EditorActorRequest>,
         svg_sender: mpsc::UnboundedSender<Vec<u8>>,
         webview_sender: broadcast::Sender<WebviewActorRequest>,
+        visible: watch::Receiver<VisiblePages>,
+        recolor: Option<Recolor>,
     ) -> Self {
         let mut res = Self {
             mailbox,
@@ -65,6 +88,10 @@ impl RenderActor {
             editor_conn_sender,
             svg_sender,
             webview_sender,
+            visible,
+            recolor,
+            rendered_doc: None,
+            rendered: vec![],
         };
         res.renderer.set_should_attach_debug_info(true);
         res
@@ -116,6 +143,26 @@ impl RenderActor {
 
                 self.change_cursor_position(req);
             }
+            RenderActorRequest::LayoutGuides => {
+                log::debug!("RenderActor: collecting LayoutGuides");
+
+                if let Some(document) = self.view().and_then(|view| view.doc()) {
+                    let guides = crate::layout_guide::layout_guides(&document);
+                    let _ = self
+                        .webview_sender
+                        .send(WebviewActorRequest::LayoutGuides(Arc::new(guides)));
+                }
+            }
+            RenderActorRequest::SpeakerNotes => {
+                log::debug!("RenderActor: collecting SpeakerNotes");
+
+                if let Some(document) = self.view().and_then(|view| view.doc()) {
+                    let notes = crate::speaker_note::speaker_notes(&document);
+                    let _ = self
+                        .webview_sender
+                        .send(WebviewActorRequest::SpeakerNotes(Arc::new(notes)));
+                }
+            }
             RenderActorRequest::RenderFullLatest | RenderActorRequest::RenderIncremental => {}
         }
 
@@ -126,16 +173,25 @@ impl RenderActor {
         loop {
             let mut has_full_render = false;
             log::debug!("RenderActor: waiting for message");
-            match self.mailbox.recv().await {
-                Ok(msg) => {
-                    has_full_render |= self.process_message(msg).await;
-                }
-                Err(broadcast::error::RecvError::Closed) => {
-                    log::info!("RenderActor: no more messages");
-                    break;
-                }
-                Err(broadcast::error::RecvError::Lagged(_)) => {
-                    log::info!("RenderActor: lagged message. Some events are dropped");
+            tokio::select! {
+                msg = self.mailbox.recv() => match msg {
+                    Ok(msg) => {
+                        has_full_render |= self.process_message(msg).await;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        log::info!("RenderActor: no more messages");
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        log::info!("RenderActor: lagged message. Some events are dropped");
+                    }
+                },
+                changed = self.visible.changed() => {
+                    if changed.is_err() {
+                        log::info!("RenderActor: webview is closed");
+                        break;
+                    }
+                    log::debug!("RenderActor: visible pages changed");
                 }
             }
             // read the queue to empty
@@ -161,6 +217,11 @@ impl RenderActor {
     }
 
     fn render(&mut self, has_full_render: bool, document: &TypstDocument) -> Vec<u8> {
+        let mut document = self.visible_part(document);
+        if let Some(recolor) = &self.recolor {
+            document = recolor.document(&document);
+        }
+        let document = &document;
         if has_full_render {
             if let Some(data) = self.render_full() {
                 data
@@ -182,6 +243,52 @@ impl RenderActor {
         self.renderer.pack_delta(document)
     }
 
+    /// Replaces the pages far from the viewport with blank pages of the same
+    /// size, which are rendered lazily once they are scrolled near the
+    /// viewport. The pages rendered for the document are kept until it is
+    /// changed, so that scrolling back doesn't render them again.
+    fn visible_part(&mut self, document: &TypstDocument) -> TypstDocument {
+        let visible = self.visible.borrow_and_update().clone();
+        let (Some(visible), TypstDocument::Paged(doc)) = (visible, document) else {
+            return document.clone();
+        };
+
+        if !self
+            .rendered_doc
+            .as_ref()
+            .is_some_and(|rendered| Arc::ptr_eq(rendered, doc))
+        {
+            self.rendered_doc = Some(doc.clone());
+            self.rendered.clear();
+        }
+        self.rendered.resize(doc.pages.len(), false);
+        let start = visible.start.saturating_sub(PREFETCH_PAGES);
+        let end = visible.end.saturating_add(PREFETCH_PAGES);
+        for rendered in self.rendered.iter_mut().take(end).skip(start) {
+            *rendered = true;
+        }
+        if self.rendered.iter().all(|rendered| *rendered) {
+            return document.clone();
+        }
+
+        let pages = doc.pages.iter().zip(&self.rendered);
+        let pages = pages.map(|(page, rendered)| {
+            if *rendered {
+                page.clone()
+            } else {
+                Page {
+                    frame: Frame::hard(page.frame.size()),
+                    ..page.clone()
+                }
+            }
+        });
+        TypstDocument::Paged(Arc::new(TypstPagedDocument {
+            pages: pages.collect(),
+            info: doc.info.clone(),
+            introspector: doc.introspector.clone(),
+        }))
+    }
+
     fn view(&self) -> Option<Arc<dyn CompileView>> {
         self.view.read().clone()
     }
@@ -1,11 +1,18 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use futures::{SinkExt, StreamExt};
 use reflexo_typst::debug_loc::{DocumentPosition, ElementPoint};
 use tinymist_std::error::IgnoreLogging;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, watch};
 
 use crate::{
-    actor::{editor::DocToSrcJumpResolveRequest, render::ResolveSpanRequest},
-    Message, WsError,
+    actor::{
+        editor::DocToSrcJumpResolveRequest,
+        render::{ResolveSpanRequest, VisiblePages},
+    },
+    protocol::{Hello, ScrollPosition, Sessions, PROTOCOL_VERSION, RESUME},
+    LayoutGuides, Message, SpeakerNote, WsError,
 };
 
 use super::{editor::EditorActorRequest, render::RenderActorRequest};
@@ -19,6 +26,10 @@ pub enum WebviewActorRequest {
     SrcToDocJump(Vec<SrcToDocJumpInfo>),
     // CursorPosition(CursorPosition),
     CursorPaths(Vec<Vec<ElementPoint>>),
+    LayoutGuides(Arc<LayoutGuides>),
+    /// The slide shown by one of the clients, which is followed by the others.
+    Slide(usize),
+    SpeakerNotes(Arc<Vec<SpeakerNote>>),
 }
 
 fn position_req(
@@ -37,6 +48,15 @@ fn positions_req(event: &'static str, positions: Vec<DocumentPosition>) -> Strin
             .join(",")
 }
 
+/// Parses the visible pages reported by the client, in form of `first last`,
+/// where the page numbers are 1-based and inclusive.
+fn parse_visible_pages(pages: &str) -> Option<std::ops::Range<usize>> {
+    let (first, last) = pages.trim().split_once(' ')?;
+    let first = first.parse::<usize>().ok()?.checked_sub(1)?;
+    let last = last.parse::<usize>().ok()?;
+    (first < last).then_some(first..last)
+}
+
 pub struct WebviewActor<
     'a,
     C: futures::Sink<Message, Error = WsError> + futures::Stream<Item = Result<Message, WsError>>,
@@ -48,6 +68,17 @@ pub struct WebviewActor<
     broadcast_sender: broadcast::Sender<WebviewActorRequest>,
     editor_sender: mpsc::UnboundedSender<EditorActorRequest>,
     render_sender: broadcast::Sender<RenderActorRequest>,
+    visible_sender: watch::Sender<VisiblePages>,
+
+    sessions: Sessions,
+    /// The 1-based page viewed by the clients, which is exported by
+    /// `exportView`.
+    viewed_page: Arc<AtomicUsize>,
+    /// The session of the client, if it resumes its session after
+    /// reconnecting.
+    session: Option<String>,
+    /// The scroll position to replay after sending the next frame.
+    resume_position: Option<ScrollPosition>,
 }
 
 pub struct Channels {
@@ -74,6 +105,9 @@ impl<
         mailbox: broadcast::Receiver<WebviewActorRequest>,
         editor_sender: mpsc::UnboundedSender<EditorActorRequest>,
         render_sender: broadcast::Sender<RenderActorRequest>,
+        visible_sender: watch::Sender<VisiblePages>,
+        sessions: Sessions,
+        viewed_page: Arc<AtomicUsize>,
     ) -> Self {
         Self {
             webview_websocket_conn: websocket_conn,
@@ -82,6 +116,11 @@ impl<
             broadcast_sender,
             editor_sender,
             render_sender,
+            visible_sender,
+            sessions,
+            viewed_page,
+            session: None,
+            resume_position: None,
         }
     }
 
@@ -111,6 +150,23 @@ impl<
                             self.webview_websocket_conn.send(Message::Binary(msg.into_bytes()))
                               .await.log_error("WebViewActor");
                         }
+                        WebviewActorRequest::LayoutGuides(guides) => {
+                            let json = serde_json::to_string(guides.as_ref()).unwrap();
+                            let msg = format!("layout-guides,{json}");
+                            self.webview_websocket_conn.send(Message::Binary(msg.into_bytes()))
+                              .await.log_error("WebViewActor");
+                        }
+                        WebviewActorRequest::Slide(page) => {
+                            let msg = format!("slide,{page}");
+                            self.webview_websocket_conn.send(Message::Binary(msg.into_bytes()))
+                              .await.log_error("WebViewActor");
+                        }
+                        WebviewActorRequest::SpeakerNotes(notes) => {
+                            let json = serde_json::to_string(notes.as_ref()).unwrap();
+                            let msg = format!("speaker-notes,{json}");
+                            self.webview_websocket_conn.send(Message::Binary(msg.into_bytes()))
+                              .await.log_error("WebViewActor");
+                        }
                     }
                 }
                 Some(svg) = self.svg_receiver.recv() => {
@@ -118,6 +174,11 @@ impl<
                     let _scope = typst_timing::TimingScope::new("webview_actor_send_svg");
                     self.webview_websocket_conn.send(Message::Binary(svg))
                     .await.log_error("WebViewActor");
+                    // The position is replayed once the latest frame is sent.
+                    if let Some(position) = self.resume_position.take() {
+                        self.webview_websocket_conn.send(Message::Binary(position.to_message().into_bytes()))
+                        .await.log_error("WebViewActor");
+                    }
                 }
                 Some(msg) = self.webview_websocket_conn.next() => {
                     log::trace!("WebviewActor: received message from websocket: {:?}", msg);
@@ -131,8 +192,54 @@ impl<
                         .await;
                         break;
                     };
-                    if msg == "current" {
+                    if let Some(hello) = msg.strip_prefix("hello,") {
+                        let Ok(hello) = serde_json::from_str::<Hello>(hello) else {
+                            log::info!("WebviewActor: invalid hello: {hello}");
+                            continue;
+                        };
+                        if hello.version != PROTOCOL_VERSION {
+                            let _ = self.webview_websocket_conn.send(Message::Text(format!("error, unsupported protocol version {}, expected {PROTOCOL_VERSION}", hello.version)))
+                            .await;
+                            log::info!("WebviewActor: unsupported protocol version {}", hello.version);
+                            break;
+                        }
+                        let resume = hello.has(RESUME);
+                        if let Some(session) = hello.session.filter(|_| resume) {
+                            self.resume_position = self.sessions.get(&session);
+                            self.session = Some(session);
+                        }
+                    } else if let Some(position) = msg.strip_prefix("scroll-position,") {
+                        let Some(position) = ScrollPosition::parse(position) else {
+                            log::info!("WebviewActor: invalid scroll position: {position}");
+                            continue;
+                        };
+                        self.viewed_page.store(position.page, Ordering::Relaxed);
+                        if let Some(session) = &self.session {
+                            self.sessions.update(session, position);
+                        }
+                    } else if msg == "current" {
                         self.render_sender.send(RenderActorRequest::RenderFullLatest).log_error("WebViewActor");
+                    } else if msg == "layout-guides" {
+                        self.render_sender.send(RenderActorRequest::LayoutGuides).log_error("WebViewActor");
+                    } else if msg == "speaker-notes" {
+                        self.render_sender.send(RenderActorRequest::SpeakerNotes).log_error("WebViewActor");
+                    } else if let Some(page) = msg.strip_prefix("slide,") {
+                        let Ok(page) = page.trim().parse() else {
+                            log::info!("WebviewActor: invalid slide: {page}");
+                            continue;
+                        };
+                        self.viewed_page.store(page, Ordering::Relaxed);
+                        self.broadcast_sender.send(WebviewActorRequest::Slide(page)).log_error("WebViewActor");
+                    } else if let Some(pages) = msg.strip_prefix("visible-pages,") {
+                        let Some(pages) = parse_visible_pages(pages) else {
+                            log::info!("WebviewActor: invalid visible pages: {pages}");
+                            continue;
+                        };
+                        self.visible_sender.send_if_modified(|visible| {
+                            let changed = visible.as_ref() != Some(&pages);
+                            *visible = Some(pages);
+                            changed
+                        });
                     } else if msg.starts_with("srclocation") {
                         let location = msg.split(' ').nth(1).unwrap();
                         self.editor_sender.send(EditorActorRequest::DocToSrcJumpResolve(
@@ -28,8 +28,9 @@ pub enum RefreshStyle {
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "clap", derive(clap::Parser))]
 pub struct PreviewArgs {
-    /// Only render visible part of the document. This can improve performance
-    /// but still being experimental.
+    /// Only render visible part of the document, i.e. the pages near the
+    /// viewport of the client. This can improve performance but still being
+    /// experimental.
     #[cfg_attr(feature = "clap", clap(long = "partial-rendering"))]
     pub enable_partial_rendering: bool,
 
@@ -60,6 +61,24 @@ pub struct PreviewArgs {
     #[cfg_attr(feature = "clap", clap(long, default_value = "never"))]
     pub invert_colors: String,
 
+    /// Recolor the pages on the server (useful for dark themes). Unlike
+    /// `--invert-colors`, the images keep their colors.
+    ///
+    /// Possible values:
+    /// - `never`: Keeps the colors.
+    /// - `invert`: Inverts the lightness of the colors, preserving their hues,
+    ///   e.g. the white pages become black while the red text stays red.
+    /// - A color map in JSON format, which replaces the colors in it and keeps
+    ///   the others.
+    ///
+    /// ## Example
+    ///
+    /// ```shell
+    /// --recolor='{"#ffffff": "#1e1e1e", "#000000": "#d4d4d4"}'
+    /// ```
+    #[cfg_attr(feature = "clap", clap(long, default_value = "never"))]
+    pub recolor: String,
+
     /// Used by lsp for identifying the task.
     #[cfg_attr(
         feature = "clap",
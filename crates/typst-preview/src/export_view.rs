@@ -0,0 +1,179 @@
+//! Exports the page viewed in the preview, or a region of it, as an image.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use typst::layout::{Abs, Frame, Page, Point, Size};
+
+use crate::CompileView;
+
+/// The default pixels per inch of the exported PNG images.
+const DEFAULT_PPI: f32 = 144.;
+
+/// The format of the exported images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+/// A region of a page, in points.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ViewRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The request to export the viewed page as an image.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportViewRequest {
+    /// The 1-based page to export, defaulting to the page viewed in the
+    /// preview.
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// The region of the page to export, defaulting to the whole page.
+    #[serde(default)]
+    pub region: Option<ViewRegion>,
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// The pixels per inch of the PNG images, defaulting to 144.
+    #[serde(default)]
+    pub ppi: Option<f32>,
+    /// The path to save the image to. If absent, the image is returned in the
+    /// response.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+/// The exported image.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportViewResponse {
+    /// The 1-based page exported.
+    pub page: usize,
+    pub format: ImageFormat,
+    /// The path the image is saved to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// The image if it is not saved, i.e. the base64 encoded PNG image or the
+    /// SVG image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// Why the image cannot be exported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Exports the latest document at the page viewed in the preview.
+#[derive(Clone)]
+pub struct ViewExporter {
+    pub(crate) doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
+    /// The 1-based page viewed in the preview, or `0` if no page is reported.
+    pub(crate) viewed_page: Arc<AtomicUsize>,
+}
+
+impl ViewExporter {
+    pub(crate) fn new(doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>) -> Self {
+        Self {
+            doc_sender,
+            viewed_page: Arc::default(),
+        }
+    }
+
+    /// Gets the 1-based page viewed in the preview.
+    pub fn viewed_page(&self) -> usize {
+        self.viewed_page.load(Ordering::Relaxed).max(1)
+    }
+
+    /// Exports the requested page, reporting the errors in the response.
+    pub fn export(&self, req: &ExportViewRequest) -> ExportViewResponse {
+        let page = req.page.unwrap_or_else(|| self.viewed_page());
+        let res = self.export_page(page, req);
+        let (path, data, error) = match res {
+            Ok(data) => match &req.path {
+                Some(path) => match std::fs::write(path, data) {
+                    Ok(()) => (Some(path.clone()), None, None),
+                    Err(err) => (None, None, Some(format!("cannot write {path:?}: {err}"))),
+                },
+                None if req.format == ImageFormat::Png => (
+                    None,
+                    Some(base64::engine::general_purpose::STANDARD.encode(data)),
+                    None,
+                ),
+                None => (
+                    None,
+                    Some(String::from_utf8_lossy(&data).into_owned()),
+                    None,
+                ),
+            },
+            Err(err) => (None, None, Some(err)),
+        };
+
+        ExportViewResponse {
+            page,
+            format: req.format,
+            path,
+            data,
+            error,
+        }
+    }
+
+    fn export_page(&self, page: usize, req: &ExportViewRequest) -> Result<Vec<u8>, String> {
+        let view = self.doc_sender.read().clone();
+        let doc = view.and_then(|view| view.doc());
+        let doc = match doc {
+            Some(TypstDocument::Paged(doc)) => doc,
+            Some(TypstDocument::Html(..)) => return Err("cannot export HTML documents".into()),
+            None => return Err("no document is compiled".into()),
+        };
+        let page = page
+            .checked_sub(1)
+            .and_then(|idx| doc.pages.get(idx))
+            .ok_or_else(|| format!("page {page} is out of range 1..={}", doc.pages.len()))?;
+        let page = match req.region {
+            Some(region) => crop(page, region)?,
+            None => page.clone(),
+        };
+
+        match req.format {
+            ImageFormat::Png => {
+                let ppi = req.ppi.unwrap_or(DEFAULT_PPI);
+                if ppi <= 1e-6 {
+                    return Err(format!("invalid ppi: {ppi}"));
+                }
+                typst_render::render(&page, ppi / 72.)
+                    .encode_png()
+                    .map_err(|err| format!("failed to encode PNG ({err})"))
+            }
+            ImageFormat::Svg => Ok(typst_svg::svg(&page).into_bytes()),
+        }
+    }
+}
+
+/// Crops a page to a region, which is clipped by the bounds of the page.
+fn crop(page: &Page, region: ViewRegion) -> Result<Page, String> {
+    let size = page.frame.size();
+    let x = region.x.clamp(0., size.x.to_pt());
+    let y = region.y.clamp(0., size.y.to_pt());
+    let width = region.width.min(size.x.to_pt() - x);
+    let height = region.height.min(size.y.to_pt() - y);
+    if width <= 0. || height <= 0. {
+        return Err(format!("the region {region:?} is empty"));
+    }
+
+    let mut frame = Frame::hard(Size::new(Abs::pt(width), Abs::pt(height)));
+    frame.push_frame(Point::new(Abs::pt(-x), Abs::pt(-y)), page.frame.clone());
+    Ok(Page {
+        frame,
+        ..page.clone()
+    })
+}
@@ -0,0 +1,135 @@
+//! Layout guides of the pages, which are drawn over the preview to help
+//! fine-tuning the spacing of templates.
+
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use typst::layout::{Frame, FrameItem, Geometry, Point, Size};
+
+/// The maximum number of element boxes reported for a page, which keeps the
+/// overlay responsive on dense pages.
+const MAX_BOXES_PER_PAGE: usize = 4096;
+
+/// The layout guides of a document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutGuides {
+    /// The guides of the pages.
+    pub pages: Vec<PageGuides>,
+}
+
+/// The layout guides of a page. All the lengths are in points, relative to the
+/// top-left corner of the page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageGuides {
+    /// The width of the page.
+    pub width: f64,
+    /// The height of the page.
+    pub height: f64,
+    /// The area covered by the text, i.e. `[x, y, width, height]`.
+    pub text_area: Option<[f64; 4]>,
+    /// The distances from the edges of the page to the text area, i.e. `[left,
+    /// top, right, bottom]`.
+    pub margins: Option<[f64; 4]>,
+    /// The vertical positions of the baselines.
+    pub baselines: Vec<f64>,
+    /// The bounding boxes of the elements.
+    pub boxes: Vec<ElementBox>,
+}
+
+/// The bounding box of an element.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementBox {
+    /// The kind of the element, i.e. `group`, `text`, `shape` or `image`.
+    pub kind: String,
+    /// The box, i.e. `[x, y, width, height]`.
+    pub rect: [f64; 4],
+}
+
+/// Collects the layout guides of a document, which is empty for the HTML
+/// documents.
+pub fn layout_guides(document: &TypstDocument) -> LayoutGuides {
+    let TypstDocument::Paged(doc) = document else {
+        return LayoutGuides::default();
+    };
+
+    let pages = doc.pages.iter().map(|page| page_guides(&page.frame));
+    LayoutGuides {
+        pages: pages.collect(),
+    }
+}
+
+fn page_guides(frame: &Frame) -> PageGuides {
+    let mut guides = PageGuides {
+        width: round(frame.width().to_pt()),
+        height: round(frame.height().to_pt()),
+        ..PageGuides::default()
+    };
+    let mut text_area: Option<(Point, Point)> = None;
+    collect(frame, Point::zero(), &mut guides, &mut text_area);
+
+    guides.baselines.sort_by(f64::total_cmp);
+    guides.baselines.dedup();
+    if let Some((min, max)) = text_area {
+        let (x, y) = (min.x.to_pt(), min.y.to_pt());
+        let (w, h) = ((max.x - min.x).to_pt(), (max.y - min.y).to_pt());
+        guides.text_area = Some([x, y, w, h].map(round));
+        guides.margins = Some([x, y, guides.width - x - w, guides.height - y - h].map(round));
+    }
+
+    guides
+}
+
+// todo: handle the transformations other than translations
+fn collect(
+    frame: &Frame,
+    origin: Point,
+    guides: &mut PageGuides,
+    text_area: &mut Option<(Point, Point)>,
+) {
+    for (pos, item) in frame.items() {
+        let pos = origin + *pos;
+        let (kind, pos, size) = match item {
+            FrameItem::Group(group) => {
+                collect(&group.frame, pos, guides, text_area);
+                ("group", pos, group.frame.size())
+            }
+            FrameItem::Text(text) => {
+                guides.baselines.push(round(pos.y.to_pt()));
+
+                let min = Point::new(pos.x, pos.y - text.size);
+                let max = Point::new(pos.x + text.width(), pos.y);
+                *text_area = Some(match *text_area {
+                    Some((lo, hi)) => (
+                        Point::new(lo.x.min(min.x), lo.y.min(min.y)),
+                        Point::new(hi.x.max(max.x), hi.y.max(max.y)),
+                    ),
+                    None => (min, max),
+                });
+                ("text", min, Size::new(text.width(), text.size))
+            }
+            FrameItem::Shape(shape, _) => {
+                let Geometry::Rect(size) = shape.geometry else {
+                    continue;
+                };
+                ("shape", pos, size)
+            }
+            FrameItem::Image(_, size, _) => ("image", pos, *size),
+            _ => continue,
+        };
+
+        if guides.boxes.len() < MAX_BOXES_PER_PAGE {
+            let rect = [pos.x, pos.y, size.x, size.y].map(|v| round(v.to_pt()));
+            guides.boxes.push(ElementBox {
+                kind: kind.to_owned(),
+                rect,
+            });
+        }
+    }
+}
+
+/// Rounds a length to two decimal places, which is enough for the overlay.
+fn round(v: f64) -> f64 {
+    (v * 100.).round() / 100.
+}
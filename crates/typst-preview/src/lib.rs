@@ -1,15 +1,28 @@
 mod actor;
 mod args;
 mod debug_loc;
+mod export_view;
+mod layout_guide;
 mod outline;
+mod protocol;
+mod recolor;
+mod speaker_note;
 
 pub use actor::editor::{
     CompileStatus, ControlPlaneMessage, ControlPlaneResponse, ControlPlaneRx, ControlPlaneTx,
     PanelScrollByPositionRequest,
 };
 pub use args::*;
+pub use export_view::{
+    ExportViewRequest, ExportViewResponse, ImageFormat, ViewExporter, ViewRegion,
+};
+pub use layout_guide::{ElementBox, LayoutGuides, PageGuides};
 pub use outline::Outline;
+pub use protocol::PROTOCOL_VERSION;
+pub use recolor::Recolor;
+pub use speaker_note::SpeakerNote;
 
+use std::sync::atomic::AtomicUsize;
 use std::sync::OnceLock;
 use std::{collections::HashMap, future::Future, path::PathBuf, pin::Pin, sync::Arc};
 
@@ -26,6 +39,7 @@ use actor::editor::{EditorActor, EditorActorRequest};
 use actor::render::RenderActorRequest;
 use actor::webview::WebviewActorRequest;
 use debug_loc::SpanInterner;
+use protocol::{Hello, Sessions};
 
 type StopFuture = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
 
@@ -100,6 +114,10 @@ impl Previewer {
                 let conn: C = caster(conn.await).unwrap();
                 tokio::pin!(conn);
 
+                let hello = serde_json::to_string(&Hello::server()).unwrap();
+                conn.send(WsMessage::Binary(format!("hello,{hello}").into()))
+                    .await
+                    .log_error("SendHello");
                 if h.enable_partial_rendering {
                     conn.send(WsMessage::Binary("partial-rendering,true".into()))
                         .await
@@ -112,8 +130,19 @@ impl Previewer {
                     .await
                     .log_error("SendInvertColor");
                 }
+                if let Some(recolor) = &h.recolor {
+                    // The client fills the pages before they are rendered.
+                    let color = recolor.color(typst::visualize::Color::WHITE).to_hex();
+                    conn.send(WsMessage::Binary(format!("page-color,{color}").into()))
+                        .await
+                        .log_error("SendPageColor");
+                }
                 let actor::webview::Channels { svg } =
                     actor::webview::WebviewActor::<'_, C>::set_up_channels();
+                // With partial rendering, only the first page is rendered until the
+                // client reports its viewport.
+                let (visible_tx, visible_rx) =
+                    tokio::sync::watch::channel(h.enable_partial_rendering.then_some(0..1));
                 let webview_actor = actor::webview::WebviewActor::new(
                     conn,
                     svg.1,
@@ -121,6 +150,9 @@ impl Previewer {
                     h.webview_tx.subscribe(),
                     h.editor_tx.clone(),
                     h.renderer_tx.clone(),
+                    visible_tx,
+                    h.sessions,
+                    h.viewed_page,
                 );
                 let render_actor = actor::render::RenderActor::new(
                     h.renderer_tx.subscribe(),
@@ -128,6 +160,8 @@ impl Previewer {
                     h.editor_tx.clone(),
                     svg.0,
                     h.webview_tx,
+                    visible_rx,
+                    h.recolor,
                 );
                 tokio::spawn(render_actor.run());
                 let outline_render_actor = actor::render::OutlineRenderActor::new(
@@ -196,19 +230,22 @@ pub struct PreviewBuilder {
     editor_conn: MpScChannel<EditorActorRequest>,
     webview_conn: BroadcastChannel<WebviewActorRequest>,
     doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
+    exporter: ViewExporter,
 
     compile_watcher: OnceLock<Arc<CompileWatcher>>,
 }
 
 impl PreviewBuilder {
     pub fn new(arguments: PreviewArgs) -> Self {
+        let doc_sender = Arc::new(parking_lot::RwLock::new(None));
         Self {
             arguments,
             shutdown_tx: None,
             renderer_mailbox: broadcast::channel(1024),
             editor_conn: mpsc::unbounded_channel(),
             webview_conn: broadcast::channel(32),
-            doc_sender: Arc::new(parking_lot::RwLock::new(None)),
+            exporter: ViewExporter::new(doc_sender.clone()),
+            doc_sender,
             compile_watcher: OnceLock::new(),
         }
     }
@@ -226,6 +263,7 @@ impl PreviewBuilder {
                 doc_sender: self.doc_sender.clone(),
                 editor_tx: self.editor_conn.0.clone(),
                 render_tx: self.renderer_mailbox.0.clone(),
+                exporter: self.exporter.clone(),
             })
         })
     }
@@ -238,6 +276,7 @@ impl PreviewBuilder {
             editor_conn: (editor_tx, editor_rx),
             webview_conn: (webview_tx, _),
             doc_sender,
+            exporter,
             ..
         } = self;
 
@@ -253,6 +292,7 @@ impl PreviewBuilder {
             renderer_mailbox.0.clone(),
             webview_tx.clone(),
             span_interner.clone(),
+            exporter.clone(),
         );
         let control_plane_handle = tokio::spawn(editor_actor.run());
         log::info!("Previewer: editor actor spawned");
@@ -263,6 +303,12 @@ impl PreviewBuilder {
             webview_tx: webview_tx.clone(),
             editor_tx: editor_tx.clone(),
             invert_colors: arguments.invert_colors.clone(),
+            recolor: Recolor::parse(&arguments.recolor).unwrap_or_else(|err| {
+                log::warn!("Previewer: ignoring --recolor: {err}");
+                None
+            }),
+            sessions: Sessions::default(),
+            viewed_page: exporter.viewed_page,
             renderer_tx: renderer_mailbox.0.clone(),
             enable_partial_rendering: arguments.enable_partial_rendering,
             doc_sender,
@@ -396,6 +442,7 @@ pub struct CompileWatcher {
     doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
     editor_tx: mpsc::UnboundedSender<EditorActorRequest>,
     render_tx: broadcast::Sender<RenderActorRequest>,
+    exporter: ViewExporter,
 }
 
 impl CompileWatcher {
@@ -403,6 +450,11 @@ impl CompileWatcher {
         &self.task_id
     }
 
+    /// Exports the page viewed in the preview, or a region of it, as an image.
+    pub fn export_view(&self, req: &ExportViewRequest) -> ExportViewResponse {
+        self.exporter.export(req)
+    }
+
     pub fn status(&self, status: CompileStatus) {
         let _ = self
             .editor_tx
@@ -451,6 +503,11 @@ struct DataPlane {
     editor_tx: mpsc::UnboundedSender<EditorActorRequest>,
     enable_partial_rendering: bool,
     invert_colors: String,
+    recolor: Option<Recolor>,
+    /// The sessions resumed by the clients after reconnecting.
+    sessions: Sessions,
+    /// The 1-based page viewed by the clients.
+    viewed_page: Arc<AtomicUsize>,
     renderer_tx: broadcast::Sender<RenderActorRequest>,
     doc_sender: Arc<parking_lot::RwLock<Option<Arc<dyn CompileView>>>>,
 }
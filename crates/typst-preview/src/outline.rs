@@ -2,7 +2,7 @@ use std::num::NonZeroUsize;
 
 use reflexo_typst::debug_loc::DocumentPosition;
 use serde::{Deserialize, Serialize};
-use tinymist_std::typst::TypstDocument;
+use tinymist_std::typst::{HeadingSlugs, TypstDocument};
 use typst::foundations::{Content, NativeElement, Packed, StyleChain};
 use typst::introspection::Introspector;
 use typst::model::HeadingElem;
@@ -18,6 +18,8 @@ pub(crate) struct HeadingNode {
     position: DocumentPosition,
     level: NonZeroUsize,
     bookmarked: bool,
+    /// The anchor of the heading in the HTML export.
+    anchor: String,
     children: Vec<HeadingNode>,
 }
 
@@ -30,10 +32,13 @@ pub(crate) fn get_outline(introspector: &Introspector) -> Option<Vec<HeadingNode
     // Therefore, its next descendant must be added at its level, which is
     // enforced in the manner shown below.
     let mut last_skipped_level = None;
+    // The anchors are made for all the headings, as the HTML export does.
+    let mut slugs = HeadingSlugs::default();
     let elements = introspector.query(&HeadingElem::elem().select());
     for elem in elements.iter() {
         let heading = elem.to_packed::<HeadingElem>().unwrap();
-        let leaf = HeadingNode::leaf(introspector, heading);
+        let anchor = slugs.slug(&heading.body.plain_text());
+        let leaf = HeadingNode::leaf(introspector, heading, anchor);
 
         if leaf.bookmarked {
             let mut children = &mut tree;
@@ -97,7 +102,7 @@ pub(crate) fn get_outline(introspector: &Introspector) -> Option<Vec<HeadingNode
 }
 
 impl HeadingNode {
-    fn leaf(introspector: &Introspector, element: &Packed<HeadingElem>) -> Self {
+    fn leaf(introspector: &Introspector, element: &Packed<HeadingElem>, anchor: String) -> Self {
         let position = {
             let loc = element.location().unwrap();
             let pos = introspector.position(loc);
@@ -117,6 +122,7 @@ impl HeadingNode {
                 .unwrap_or_else(|| element.outlined(StyleChain::default())),
             body: element.body.clone(),
             span: element.span(),
+            anchor,
             children: Vec::new(),
         }
     }
@@ -135,6 +141,9 @@ struct OutlineItem {
     span: Option<String>,
     /// The resolved position in the document.
     position: Option<DocumentPosition>,
+    /// The anchor of the heading in the HTML export, made from its title in
+    /// the style of GitHub.
+    anchor: String,
     /// The children of the outline item.
     children: Vec<OutlineItem>,
 }
@@ -174,6 +183,7 @@ fn outline_item(interner: &mut SpanInternerImpl, src: &HeadingNode, res: &mut Ve
         title,
         span: Some(span.to_hex()),
         position: Some(src.position),
+        anchor: src.anchor.clone(),
         children,
     });
 }
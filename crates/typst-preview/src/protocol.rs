@@ -0,0 +1,111 @@
+//! The handshake of the data plane, which negotiates the version of the wire
+//! protocol and the capabilities of the server and the client, and the
+//! sessions resumed by the clients after reconnecting.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the wire protocol of the data plane, which is bumped on
+/// incompatible changes of the messages.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The capabilities of the server.
+pub const CAPABILITIES: &[&str] = &[
+    "partial-rendering",
+    "visible-pages",
+    "layout-guides",
+    "speaker-notes",
+    "slide-sync",
+    "page-color",
+    "resume",
+];
+
+/// The capability of the clients that resume their sessions after
+/// reconnecting.
+pub const RESUME: &str = "resume";
+
+/// The maximum number of the sessions kept for resuming.
+const MAX_SESSIONS: usize = 32;
+
+/// The `hello` message exchanged by the server and the client when the
+/// connection is opened.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hello {
+    /// The version of the wire protocol.
+    pub version: u32,
+    /// The capabilities of the sender.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// The session of the client, which is kept across the reconnects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+impl Hello {
+    /// Creates the `hello` message of the server.
+    pub fn server() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(|it| it.to_string()).collect(),
+            session: None,
+        }
+    }
+
+    /// Checks whether the sender has the capability.
+    pub fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|it| it == capability)
+    }
+}
+
+/// The scroll position reported by a client, in form of `page left top`, where
+/// the page is 1-based and the offsets are the scroll offsets of the window in
+/// CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollPosition {
+    pub page: usize,
+    pub left: f32,
+    pub top: f32,
+}
+
+impl ScrollPosition {
+    pub fn parse(position: &str) -> Option<Self> {
+        let mut parts = position.split_whitespace();
+        let page = parts.next()?.parse().ok()?;
+        let left = parts.next()?.parse().ok()?;
+        let top = parts.next()?.parse().ok()?;
+        Some(Self { page, left, top })
+    }
+
+    /// Gets the message replaying the position to the client.
+    pub fn to_message(self) -> String {
+        let Self { page, left, top } = self;
+        format!("scroll-position,{page} {left} {top}")
+    }
+}
+
+/// The sessions of the clients, which keep the latest scroll positions of the
+/// clients to replay them after reconnecting.
+#[derive(Debug, Clone, Default)]
+pub struct Sessions(Arc<parking_lot::Mutex<Vec<(String, ScrollPosition)>>>);
+
+impl Sessions {
+    /// Gets the latest scroll position of a session.
+    pub fn get(&self, session: &str) -> Option<ScrollPosition> {
+        let sessions = self.0.lock();
+        let (_, position) = sessions.iter().find(|(id, _)| id == session)?;
+        Some(*position)
+    }
+
+    /// Updates the latest scroll position of a session, evicting the least
+    /// recently updated session if there are too many.
+    pub fn update(&self, session: &str, position: ScrollPosition) {
+        let mut sessions = self.0.lock();
+        sessions.retain(|(id, _)| id != session);
+        if sessions.len() >= MAX_SESSIONS {
+            sessions.remove(0);
+        }
+        sessions.push((session.to_owned(), position));
+    }
+}
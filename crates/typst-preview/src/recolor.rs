@@ -0,0 +1,140 @@
+//! Recolors the pages on the server, so that the previews fit the dark themes
+//! without inverting the images.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tinymist_std::typst::{TypstDocument, TypstPagedDocument};
+use typst::ecow::EcoString;
+use typst::foundations::Smart;
+use typst::layout::{Frame, FrameItem, GroupItem, Page};
+use typst::visualize::{Color, ColorSpace, FixedStroke, Oklab, Paint, Shape};
+
+/// The strategy to recolor the pages, given by `--recolor`.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub enum Recolor {
+    /// Inverts the lightness of the colors in the Oklab color space, which
+    /// preserves their hues, e.g. the red text stays red.
+    Invert,
+    /// Replaces the colors in the map, keyed by their hex codes, and keeps the
+    /// others.
+    Map(Vec<(EcoString, Color)>),
+}
+
+impl Recolor {
+    /// Parses the argument, i.e. `never`, `invert` or a color map in JSON
+    /// format. It is `None` if the colors are kept.
+    pub fn parse(arg: &str) -> Result<Option<Self>, String> {
+        match arg.trim() {
+            "" | "never" => return Ok(None),
+            "invert" => return Ok(Some(Self::Invert)),
+            _ => {}
+        }
+
+        let map = serde_json::from_str::<BTreeMap<String, String>>(arg)
+            .map_err(|err| format!("expected `never`, `invert` or a color map: {err}"))?;
+        let parse = |color: &str| {
+            Color::from_str(color).map_err(|err| format!("invalid color {color:?}: {err}"))
+        };
+        let map = map
+            .iter()
+            .map(|(from, to)| Ok((parse(from)?.to_hex(), parse(to)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(Some(Self::Map(map)))
+    }
+
+    /// Recolors a color.
+    pub fn color(&self, color: Color) -> Color {
+        match self {
+            Self::Invert => {
+                let [l, a, b, alpha] = color.to_space(ColorSpace::Oklab).to_vec4();
+                Color::Oklab(Oklab::new(1.0 - l, a, b, alpha))
+            }
+            Self::Map(map) => {
+                let hex = color.to_hex();
+                map.iter()
+                    .find(|(from, _)| *from == hex)
+                    .map_or(color, |(_, to)| *to)
+            }
+        }
+    }
+
+    /// Recolors the pages of a document. The backgrounds of the pages are
+    /// drawn into their frames, since the client fills the pages with white.
+    pub fn document(&self, document: &TypstDocument) -> TypstDocument {
+        let TypstDocument::Paged(doc) = document else {
+            return document.clone();
+        };
+
+        let pages = doc.pages.iter().map(|page| {
+            let mut frame = recolor_frame(&page.frame, self);
+            let fill = match &page.fill {
+                Smart::Auto => Some(Paint::Solid(Color::WHITE)),
+                Smart::Custom(fill) => fill.clone(),
+            };
+            if let Some(fill) = fill {
+                frame.fill(self.paint(&fill));
+            }
+            Page {
+                frame,
+                fill: Smart::Custom(None),
+                ..page.clone()
+            }
+        });
+        TypstDocument::Paged(Arc::new(TypstPagedDocument {
+            pages: pages.collect(),
+            info: doc.info.clone(),
+            introspector: doc.introspector.clone(),
+        }))
+    }
+
+    /// Recolors a paint. The gradients and the tilings are kept.
+    fn paint(&self, paint: &Paint) -> Paint {
+        match paint {
+            Paint::Solid(color) => Paint::Solid(self.color(*color)),
+            paint => paint.clone(),
+        }
+    }
+
+    fn stroke(&self, stroke: &FixedStroke) -> FixedStroke {
+        FixedStroke {
+            paint: self.paint(&stroke.paint),
+            ..stroke.clone()
+        }
+    }
+}
+
+/// Recolors the items of a frame, which is memoized since the frames are
+/// mostly unchanged between the compilations.
+#[comemo::memoize]
+fn recolor_frame(frame: &Frame, recolor: &Recolor) -> Frame {
+    let mut recolored = frame.clone();
+    recolored.clear();
+    for (pos, item) in frame.items() {
+        let item = match item {
+            FrameItem::Group(group) => FrameItem::Group(GroupItem {
+                frame: recolor_frame(&group.frame, recolor),
+                ..group.clone()
+            }),
+            FrameItem::Text(text) => {
+                let mut text = text.clone();
+                text.fill = recolor.paint(&text.fill);
+                text.stroke = text.stroke.as_ref().map(|stroke| recolor.stroke(stroke));
+                FrameItem::Text(text)
+            }
+            FrameItem::Shape(shape, span) => FrameItem::Shape(
+                Shape {
+                    fill: shape.fill.as_ref().map(|fill| recolor.paint(fill)),
+                    stroke: shape.stroke.as_ref().map(|stroke| recolor.stroke(stroke)),
+                    ..shape.clone()
+                },
+                *span,
+            ),
+            // The images keep their colors.
+            item => item.clone(),
+        };
+        recolored.push(*pos, item);
+    }
+    recolored
+}
@@ -0,0 +1,46 @@
+//! Speaker notes of the slides, which are shown to the presenter in the slide
+//! mode.
+
+use serde::{Deserialize, Serialize};
+use tinymist_std::typst::TypstDocument;
+use typst::foundations::{Label, Repr, Selector, Value};
+use typst::introspection::MetadataElem;
+use typst::utils::PicoStr;
+
+/// The label of the metadata containing a speaker note, e.g.
+/// `#metadata[Greet the audience] <speaker-note>`.
+const SPEAKER_NOTE_LABEL: &str = "speaker-note";
+
+/// A speaker note of a slide.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerNote {
+    /// The 1-based number of the page containing the note.
+    pub page: usize,
+    /// The text of the note.
+    pub note: String,
+}
+
+/// Collects the speaker notes of a document, in the order of the document.
+pub fn speaker_notes(document: &TypstDocument) -> Vec<SpeakerNote> {
+    let TypstDocument::Paged(doc) = document else {
+        return vec![];
+    };
+
+    let introspector = &doc.introspector;
+    let label = Label::new(PicoStr::intern(SPEAKER_NOTE_LABEL));
+    let notes = introspector.query(&Selector::Label(label));
+    notes
+        .iter()
+        .filter_map(|elem| {
+            let metadata = elem.to_packed::<MetadataElem>()?;
+            let page = introspector.page(elem.location()?).get();
+            let note = match &metadata.value {
+                Value::Str(note) => note.to_string(),
+                Value::Content(note) => note.plain_text().to_string(),
+                value => value.repr().to_string(),
+            };
+            Some(SpeakerNote { page, note })
+        })
+        .collect()
+}
@@ -51,3 +51,23 @@
   config_item(key, cfg)
 }
 
+= Workspace Configuration File
+
+A `tinymist.toml` file at the root of a workspace folder is read when the configuration is updated or the file changes, so that a team shares the configuration by checking it in. The keys are the same as the ones above without the `tinymist.` prefix. The values sent by the editor take precedence over the ones in the file, unless they are absent or null. Note that some editors, e.g. VS Code, send their defaults for the items unset by the user, which then take precedence as well.
+
+Besides, the `entries` key specifies the entry files compiled besides the main file, each with its own diagnostics. The paths are relative to the root.
+
+In a workspace with multiple folders, each folder is a separate scope with its own configuration, i.e. the configuration from the editor merged with the `tinymist.toml` of the folder. The files are rooted at the innermost folder containing them and are resolved by the configuration of that folder, the `entries` of every folder are compiled, and the focused document is compiled with the configuration of its folder. For example, the folders can use different package directories by setting `typstExtraArgs = ["--package-path", "packages"]` in their own `tinymist.toml`, where the relative directories are resolved against the folder. The folders can be added or removed without restarting the server.
+
+```toml
+entries = ["thesis.typ", "slides.typ"]
+fontPaths = ["fonts"]
+exportPdf = "onSave"
+outputPath = "$root/target/$dir/$name"
+formatterMode = "typstyle"
+
+[lint]
+enabled = true
+when = "onSave"
+```
+
@@ -2,7 +2,7 @@
 
 #show: book-page.with(title: [Command Line Interface (CLI)])
 
-The difference between typst-cli and tinymist-cli is that the latter one focuses on the features requiring code analysis or helping the language server. For example, `tinymist-cli` also provides a `compile` command, but it doesn't provide a `query` or `watch` command, which are provided by `typst-cli`. This is because `tinymist compile` also collects and saves the compilation commands needed by the language server.
+The difference between typst-cli and tinymist-cli is that the latter one focuses on the features requiring code analysis or helping the language server. For example, `tinymist-cli` also provides a `compile` command, which also collects and saves the compilation commands needed by the language server.
 
 = Servers
 
@@ -62,6 +62,205 @@ tinymist compile --lockfile some/tinymist.lock path/to/main.typ
 
 The lock file feature is in development. It is to help the language server to understand the structure of your projects. See #github-link("/editors/vscode/Configuration.md#tinymistprojectresolution")[Configuration: tinymist.projectResolution].
 
+To recompile and export the document whenever a file read by the compilation changes, including the images, the data files, and the files of the packages:
+
+```bash
+tinymist compile --watch path/to/main.typ
+```
+
+To downscale and recompress the raster images that exceed 300 dpi at their largest size in the document, which shrinks image-heavy PDFs:
+
+```bash
+tinymist compile --image-max-dpi 300 path/to/main.typ
+```
+
+The images are only replaced in the exported PDF, and the savings are reported per image. The original files are kept for editing.
+
+To export a PDF conforming to the PDF/A-2b archival standard:
+
+```bash
+tinymist compile --pdf-standard a-2b path/to/main.typ
+```
+
+The standards are saved to the lock file, and can also be given by the `pdf.standard` option of the export tasks in VS Code. PDF/A-2b and PDF/A-3b cannot be enforced together. If the document uses features disallowed by the standards, e.g. the attachments in PDF/A-2b, the export fails with the locations of the features.
+
+To export a tagged PDF for accessibility:
+
+```bash
+tinymist compile --pdf-tagged path/to/main.typ
+```
+
+The export warns if the document has no title, set by `set document(title: ..)`, or if its images have no alternative texts, given by `image(.., alt: ..)`. The option can also be given by the `pdf.tagged` option of the export tasks in VS Code. The document language is taken from `set text(lang: ..)`. The linter also warns about the figure images without alternative texts while editing.
+
+To export a document to HTML, which copies the local files referenced by the `src` and `href` attributes, e.g. images and stylesheets, from the directory of the main file to the directory of the output:
+
+```bash
+tinymist compile --format html path/to/main.typ dist/index.html
+```
+
+The `--post-export` flag runs a shell command after the export, in the directory of the output, whose path is passed by the `TINYMIST_OUTPUT` environment variable:
+
+```bash
+tinymist compile --format html --post-export 'npx prettier -w "$TINYMIST_OUTPUT"' path/to/main.typ dist/index.html
+```
+
+The headings get stable anchors, which are made from their titles in the style of GitHub by default, e.g. `#hello-world` for `= Hello, World!`. Pass `--heading-anchors label` to use the labels of the headings instead, or `--heading-anchors none` to keep the headings as is. The links to missing anchors and the internal links lost by the HTML export, e.g. `link(<label>)`, are reported as warnings. The anchors are also listed in the outline of the preview.
+
+Markdown is exported by typlite with `--format md`. The `--md-math text|svg`, `--md-figure html|markdown`, and `--md-raw fenced|svg` flags choose how equations, figures, and raw blocks are lowered:
+
+```bash
+tinymist compile --format md --md-math text --md-figure markdown path/to/main.typ README.md
+```
+
+A Word document is exported with `--format docx`, or inferred from the `.docx` extension. The headings, lists, tables, images, and footnotes are kept as Word elements:
+
+```bash
+tinymist compile path/to/main.typ main.docx
+```
+
+The text of a document is exported in reading order with `--format txt`, or inferred from the `.txt` extension. The lines follow the layout, the columns are read one after another, and the cells of a table row are separated by tabs, which suits spell checkers and word diffs:
+
+```bash
+tinymist compile path/to/main.typ main.txt
+```
+
+The pages exported to PNG are selected by `--pages`, and rendered at the resolution given by `--ppi` (or `--dpi`). To export one file per page, name the files by a template, in which `{p}` is replaced by the page number, `{0p}` by the zero-padded page number and `{t}` by the page count:
+
+```bash
+tinymist compile --pages 2,5-7 --ppi 300 path/to/main.typ 'page-{0p}.png'
+```
+
+The template can also be given by `--png-page-template`, which is saved along with the resolution to the lock file. Without a template, the selected pages are merged into a single image.
+
+The SVG export selects pages and names the files in the same way, e.g. to extract the figures on pages 3 to 5 for the web:
+
+```bash
+tinymist compile --pages 3-5 path/to/main.typ 'figure-{p}.svg'
+```
+
+The template can also be given by `--svg-page-template`, which is saved to the lock file. Without a template, the selected pages are merged into a single SVG.
+
+To print the diagnostics in a machine-readable format for the wrappers and the editors calling the CLI:
+
+```bash
+tinymist compile --diagnostic-format json path/to/main.typ
+```
+
+The diagnostics of a compilation are printed to the stderr as a JSON array in a line, of `{ file, range, severity, code, message, relatedInformation }` objects. The ranges are zero-based and the columns are counted in UTF-16 code units, as in the language server protocol. The related information includes the trace of a diagnostic and the imports leading to its file. With `--watch`, a line is printed per compilation. `--diagnostic-format short` prints a line per diagnostic instead.
+
+To find out where the compilation spends its time:
+
+```bash
+tinymist compile --timings trace.json path/to/main.typ
+```
+
+The time spent on parsing, evaluation, layout (along with the layout of each page) and export is printed after the export, along with the slowest scopes recorded by the compiler. When a path is given, the Chrome trace of the compilation is written to it, which can be viewed as a flamegraph in `chrome://tracing` or #link("https://ui.perfetto.dev")[Perfetto]. The language server reports the same timings for the incremental compilation of a document by the `tinymist/compileTimings` request.
+
+To count the words of a document, e.g. to check the length limits of a submission:
+
+```bash
+tinymist compile --statistics path/to/main.typ
+```
+
+The numbers of the words, the characters (excluding the whitespaces) and the sentences, along with the reading time estimated at 200 words per minute, are printed for the whole document and for each heading section, including its subsections. They are computed from the text of the compiled document, so the text generated by the show rules and the templates is counted, while the code and the comments are not. A CJK character is counted as a word. The language server computes the same statistics by the `tinymist/documentStatistics` request, whose parameters are an optional `path` of the document, defaulting to the main document, and an optional `wordsPerMinute`.
+
+== Querying a Document
+
+To retrieve the elements of a document by a selector, e.g. the metadata of the document for a build pipeline:
+
+```bash
+tinymist query selector "<meta>" path/to/main.typ --field value --one
+```
+
+The elements are printed as JSON by default. Use `--format yaml`, `--format csv` (one row per element and one column per field), or `--format txt` (a single string) to change the format, `--pretty` to pretty print JSON, and `--output` to write the result to a file. In VS Code, the `query` format of the export tasks runs the same queries.
+
+To generate the documentation of a package as a single browsable Markdown file, with a table of contents and an anchor per module and symbol:
+
+```bash
+tinymist query packageDocs --id @preview/cetz:0.2.2 --format markdown --output cetz.md
+```
+
+Use `--format json` to write the definitions of the package as JSON instead.
+
+To check a package before publishing it:
+
+```bash
+tinymist query checkPackage --id @preview/cetz:0.2.2 --path . --output issues.json
+```
+
+The command validates the name, the version, the entrypoint and the exclude patterns of the manifest, checks the parameters documented in the docstrings of the exported functions against their signatures, including the types of the default values, and compiles the `example` blocks in the docstrings. The issues are printed and written to the output path as a JSON array of `{ kind, severity, message, path, line }` objects. The command fails if there is any error.
+
+== Pinning Packages
+
+The lock file also pins the versions and the checksums of the packages used by the documents, so that the documents are built with the same packages across machines. When a document is saved to the lock file, the packages not pinned yet are pinned. Both the compile command and the language server warn about the imports whose versions disagree with the pinned ones.
+
+To pin the packages currently used by the documents in the lock file, replacing the existing pins:
+
+```bash
+tinymist package lock
+```
+
+To check that the imports and the contents of the downloaded packages agree with the lock file, e.g. in CI:
+
+```bash
+tinymist package lock --check
+```
+
+== Formatting Files
+
+To format the Typst files in a directory with the formatter configured by the `formatterMode`, `formatterPrintWidth`, `formatterIndentSize`, and `formatterProseWrap` keys of the `tinymist.toml` in the current directory:
+
+```bash
+tinymist format src/
+```
+
+The options can be overridden by `--mode`, `--print-width`, `--indent-size`, and `--prose-wrap`. Typstyle is used if no formatter is configured. The stdin is formatted to the stdout if no path is given. To check the formatting in CI without writing the files, which exits with a nonzero code if any file is not formatted:
+
+```bash
+tinymist format --check src/
+```
+
+== Listing Fonts
+
+To list the font families available to the compiler, along with whether they are embedded, installed on the system, or found in the paths given by `--font-path`:
+
+```bash
+tinymist fonts --variants
+```
+
+`--variants` lists the style, the weight, the stretch and the file of each font. To find out why a family requested by a document is not used:
+
+```bash
+tinymist fonts --verify "Inter" path/to/main.typ
+```
+
+The command reports the variants of the family if it is found. Otherwise, it reports the families whose full names or PostScript names match the requested one, the similar families, and whether the family is installed on the system but ignored by `--ignore-system-fonts`. If a document is given, it is compiled to check whether any text is shaped by the family. The fonts and the verification are printed as JSON by `--format json`.
+
+== Linting Documents
+
+To lint the documents along with the workspace files they depend on, e.g. in CI, where the inputs can be files, directories, or glob patterns:
+
+```bash
+tinymist lint main.typ "chapters/*.typ"
+```
+
+Both the compiler diagnostics and the lints are reported, which exits with a nonzero code on errors, or on warnings if `--deny-warnings` is given. The diagnostics are printed as JSON by `--format json`. The severity of the diagnostics can be overridden by the patterns of their messages, where the last matching rule wins:
+
+```bash
+tinymist lint --severity "is deprecated=error" --severity "variable font=off" main.typ
+```
+
+== Converting LaTeX Math
+
+To convert a LaTeX formula into Typst math, e.g. when migrating a LaTeX document:
+
+```bash
+tinymist convert-latex '\frac{a}{b} \leq \sqrt[3]{x}'
+# frac(a, b) lt.eq root(3, x)
+```
+
+The stdin is converted if no formula is given. The symbols, fractions, roots, fonts, accents, operators, spaces, `\left` and `\right` delimiters, and the matrix, cases and alignment environments are converted, while the other commands are kept by their names. A formula delimited by `$`, `$$`, `\(` or `\[` is converted into a Typst equation. In VS Code, the "Typst: Convert LaTeX Math in Selection or Clipboard to Typst" command replaces the selected formula, or inserts the copied one if nothing is selected.
+
 == Running Tests
 
 To run tests, you can use the `test` command, which is also compatible with `typst compile`:
@@ -78,6 +277,33 @@ The `test` command will defaultly run all the functions whose names are staring
 
 See #cross-link("/feature/testing.typ")[Docs: Testing Features] for more information.
 
+== Running the Pipeline in CI
+
+The `ci` command runs the canonical pipeline on the documents in the lock file, so that a project needs a single command in CI:
+
++ `check`: compiles the documents and reports the diagnostics, along with the imports disagreeing with the pinned packages.
++ `lint`: lints the workspace files used by the documents.
++ `test`: runs the tests of the documents.
++ `compile`: runs the export tasks of the documents saved by `tinymist compile --save-lock`.
+
+```bash
+tinymist ci --fail-fast --summary target/ci.json
+```
+
+The command fails if any step fails. `--fail-fast` stops the pipeline at the first failure, `--deny-warnings` also fails the check and lint steps on warnings, and `--skip lint,test` skips the given steps. The summary of the steps per document is written in JSON to the path given by `--summary`, or to stdout with `--summary -`.
+
+In GitHub Actions, the output of each step is folded into a group and the diagnostics are shown as annotations of the source files. This is detected by the `GITHUB_ACTIONS` environment variable, and can be forced by `--annotations github` or turned off by `--annotations none`.
+
+== Checking the Installation
+
+`tinymist probe` exits immediately, which is used by the editors to check that the binary runs. To check the installation, e.g. when reporting an issue:
+
+```bash
+tinymist probe --doctor
+```
+
+The health check reports the version and the build of tinymist, the enabled features, the number of the available fonts, the package paths and whether the package cache is writable, the proxy settings in the environment with the credentials hidden, and the result of compiling and exporting a tiny document. The report is printed as JSON by `--json`. The command exits with a nonzero code if no font is found, the package cache is not writable, or the test compilation fails.
+
 == Generating shell completion script
 
 To generate a bash-compatible completion script:
@@ -118,6 +118,14 @@ Example:
         "format": "markdown"
       }
     },
+    {
+      "label": "Export as Word Document",
+      "type": "typst",
+      "command": "export",
+      "export": {
+        "format": "docx"
+      }
+    },
     {
       "label": "Export as Plain Text",
       "type": "typst",
@@ -194,7 +202,14 @@ You can call the following export commands.
 - `tinymist.exportHtml`
 - `tinymist.exportMarkdown`
 - `tinymist.exportTeX`
+- `tinymist.exportDocx`
 - `tinymist.exportText`
 - `tinymist.exportQuery`
 
 The first argument is the path to the file you want to export and the second argument is an object containing additional options.
+
+= Exporting a Selection as an Image
+
+The `tinymist.exportSelection` command renders a range of a document as a PNG or SVG image, e.g. to paste a fragment into a chat or an issue. The range is compiled after the top-level imports and set and show rules of the document, so it looks the same as in the document, and the page is shrunk to fit it.
+
+The arguments are the path to the document, the LSP range to render, and an optional object of the `format` (`"png"` by default or `"svg"`) and the `ppi` of the PNG image (144 by default). The result contains the `data` of the image, i.e. the base64 encoded PNG image or the SVG image, and the `diagnostics` of the compilation. In VSCode, run the command `Typst: Export Selection as PNG or SVG Image` to save the image of the selection.
@@ -8,6 +8,8 @@ Language service (LSP) features:
 // formatting
 - #link("https://code.visualstudio.com/api/language-extensions/programmatic-language-features#provide-code-actions")[Code actions]
   - Also known as "quick fixes" or "refactorings".
+  - On a top-level heading, splits a long document into chapter files under `chapters/`, hoisting the leading imports and rules into `preamble.typ`.
+  - On the path of an `#include`, inlines the included file, adjusting its relative paths, and optionally deletes the file if nothing else includes or imports it.
 - #link("https://code.visualstudio.com/api/language-extensions/programmatic-language-features#format-source-code-in-an-editor")[Formatting (Reformatting)]
   - Provide the user with support for formatting whole documents, using #link("https://github.com/astrale-sharp/typstfmt")[typstfmt] or #link("https://github.com/Enter-tainer/typstyle")[typstyle].
 - #link("https://code.visualstudio.com/api/language-extensions/programmatic-language-features#highlight-all-occurrences-of-a-symbol-in-a-document")[Document highlight]
@@ -19,6 +21,8 @@ Language service (LSP) features:
   - Renders path or link references in the document, such as `image("path.png")` or `bibliography(style: "path.csl")`.
 - #link("https://code.visualstudio.com/docs/getstarted/userinterface#_outline-view")[Document symbols]
   - Also known as "document outline" or "table of contents" _in Typst_.
+  - Once the document is compiled, the headings are prefixed with their numbers as they are numbered in the PDF, respecting `set heading(numbering: ..)` and the updates to the counters.
+  - The `tinymist/resolvedOutline` request gives the outline of the whole compiled document, including the numbered block equations, with their resolved numbers and locations.
 - #link("https://burkeholland.gitbook.io/vs-code-can-do-that/exercise-3-navigation-and-refactoring/folding-sections")[Folding ranges]
   - You can collapse code/content blocks and headings.
 - #link("https://code.visualstudio.com/api/language-extensions/programmatic-language-features#show-definitions-of-a-symbol")[Goto definitions]
@@ -23,3 +23,31 @@ If enabled, the linter will run on save or on type, depending on your configurat
   - `onType` run linting as you type.
 
 
+
+= Checking the Index Terms
+
+For documents with a back-of-book index, the linter also checks the terms marked by the functions in `tinymist.index.markers`, which defaults to `["index"]`. The positional string or content arguments of a mark are the term and its subterms, e.g. `#index("Typesetting", "system")`. It warns about:
+- the terms spelled differently but sorted as the same term, e.g. `#index[Typst]` and `#index[typst]`,
+- the terms marked more than once in a paragraph, and
+- the marked terms if none of the functions in `tinymist.index.displays`, which defaults to `["make-index"]`, is called.
+
+The `tinymist.getIndexTerms` command collects the marked terms of the workspace, grouped by their initial letters and sorted by the locale in `tinymist.index.locale`, or by the locale passed as its argument. For example, `ö` is sorted after `z` in Swedish (`sv`) but along with `o` in German (`de`).
+
+= Checking the Spelling
+
+When `tinymist.spell.enabled` is set, the linter also checks the spelling of the prose in markup, ignoring the code, the equations and the raw blocks. The words are checked against the Hunspell dictionaries of the language set by `text.lang` and `text.region`, e.g. `#set text(lang: "de")` or `#text(lang: "fr")[...]`. The text not set by them is in the language of `tinymist.spell.lang`, which defaults to English.
+
+The dictionaries, e.g. `en_US.aff` and `en_US.dic`, are searched in the directories of `tinymist.spell.dictionaryPaths`, the directories of the `DICPATH` environment variable, and the system directories, e.g. `/usr/share/hunspell` on Linux or `~/Library/Spelling` on macOS. The words in the languages without a dictionary are not checked. Single letters, numbers, acronyms and words in camel case, e.g. `mRNA`, are not checked either.
+
+The quick fixes of an unknown word replace it with the suggestions of the dictionary or add it to the project word list, which is `project-words.txt` in the workspace root by default or the file of `tinymist.spell.wordList`. The word list has one word per line, and the lines starting with `#` are comments.
+
+= Checking the Bibliography
+
+The BibTeX and BibLaTeX files loaded by `bibliography` are also checked, and the diagnostics point into the `.bib` files themselves. It reports:
+- the syntax errors, e.g. a missing comma or brace, and the unknown abbreviations,
+- the keys defined by more than one entry, and
+- the required fields missing for the entry types, e.g. `journaltitle` for `@article`, and the fields that cannot be parsed, e.g. a `volume` that is not a number.
+
+The Hayagriva YAML files loaded by the compilation are checked as well, if any entry has a `type` field. The unknown fields, which are silently ignored by Typst, the unknown entry types, the malformed dates and the entries without a type are reported.
+
+In VS Code, the field names of the entries, the persons and the `affiliated` field, and the values of the `type` and `role` fields are completed in the YAML files having a `type`, `title`, `author`, `date` or `parent` field. Other editors can complete them by the `tinymist.completeYamlBib` command, with the text of the file and the position as arguments.
@@ -88,6 +88,54 @@ value is `["--data-plane-host=127.0.0.1:23635", "--invert-colors=auto"]`. Exampl
 }
 ```
 
+== Previewing Multiple Documents
+
+Multiple documents can be previewed at the same time, e.g. a paper and its slides. The first regular preview is served by the primary compiler, which keeps compiling the previewed document regardless of the focused file, and the other previews are served by their own compilers. Each preview has its own websocket server, so the previews should be bound to random ports, e.g. `--data-plane-host=127.0.0.1:0` or `--data-plane-host=127.0.0.1`, as the default arguments of the editors do. A preview fails to start if the port given by `--data-plane-host` is taken. The previews are identified by their `--task-id`, which must not be shared.
+
+== Dark Themes
+
+`--invert-colors` (`tinymist.preview.invertColors`) inverts the colors of the preview in the webview, which is free but also inverts the hues, e.g. the red text becomes cyan. Instead, `--recolor` (`tinymist.preview.recolor`) recolors the pages on the server before they are rendered:
+- `invert`: inverts the lightness of the colors while preserving their hues, so that the white pages become black while the red text stays red.
+- A color map, e.g. `{"#ffffff": "#1e1e1e", "#000000": "#d4d4d4"}`, which replaces the colors in it and keeps the others.
+
+The images and the gradients keep their colors in both cases.
+
+== Previewing Long Documents
+
+With `--partial-rendering` (`tinymist.preview.partialRendering`), the preview reports the pages in its viewport to the server, which only renders and sends the pages near the viewport, plus two pages before and after them. The other pages are sent as blank pages of the same size and rendered once they are scrolled near the viewport, which keeps the previews of documents with hundreds of pages responsive. Note that the whole document is still compiled on each change.
+
+== Exposing Previews to the Network
+
+The preview servers are bound to `127.0.0.1` by default. To preview a document on another device, e.g. a tablet, bind the data plane to the network with `--data-plane-host=0.0.0.0:23635`. When any server is bound to a non-loopback address, tinymist generates a random token and requires it on every HTTP and WebSocket request, so that others on the network cannot read the document. The preview URL with the token is printed to the stderr:
+
+```
+Preview: http://0.0.0.0:23635/?token=...
+```
+
+Pass `--token` to require a token on the loopback addresses as well, or `--token=<token>` to choose the token.
+
+== Exporting the View as an Image <export-view>
+
+The page viewed in the preview, or a region of it, can be exported as a PNG or SVG image, e.g. for pasting into a chat. The `tinymist.exportPreviewView` command takes an optional task id of the preview, defaulting to the first preview, and the options:
+
+```json
+{
+  "page": 1,
+  "region": { "x": 72, "y": 72, "width": 200, "height": 100 },
+  "format": "png",
+  "ppi": 144,
+  "path": "/abs/path/to/view.png"
+}
+```
+
+- `page`: the 1-based page, defaulting to the page in the center of the preview, or the current slide.
+- `region`: the region of the page in points, defaulting to the whole page.
+- `format`: `png` (default) or `svg`.
+- `ppi`: the pixels per inch of the PNG images, defaulting to 144.
+- `path`: the path to save the image to. If absent, the image is returned in the `data` field of the response, i.e. the base64 encoded PNG image or the SVG image.
+
+The same options are accepted by the `exportView` message of the control plane.
+
 == CLI Integration
 
 ```bash
@@ -137,6 +185,82 @@ There is a `version` field in the `preview-args` object, which will increase whe
 }
 ```
 
+== Layout Guides
+
+Press `l` in the preview panel to toggle the layout guides, which draw the text area, the baselines, and the bounding boxes of the elements over the pages. Hover over a box to see its size and position in points, and over the text area to see the margins of the page. The guides are computed from the compiled frames, so they are updated along with the document.
+
+== Presenting Slides
+
+Run the preview in the slide mode, e.g. `tinymist preview slides.typ --preview-mode=slide`, to show one page per screen. The slides are switched by the arrow keys and the space key, and all the viewers of the same preview follow the slide switched in any of them, so a presenter view on the laptop drives the audience view on the projector.
+
+Press `n` to toggle the speaker notes of the current slide along with the timer of the presentation, which is started or paused by `t` and reset by `r`. The speaker notes are the metadata labelled `<speaker-note>` on the slide:
+
+```typ
+#metadata[Greet the audience.] <speaker-note>
+```
+
+The timer is shared by the viewers, and is served at `/timer` of the preview server, e.g. for external clocks. `GET /timer` returns `{"elapsed": 12.5, "running": true}` in seconds, and `POST /timer?action=<start|pause|toggle|reset>` controls it.
+
+== Headless Render Service <headless-preview>
+
+The renderer can run without any LSP client and without the bundled frontend, so that a third-party viewer can embed the incremental renderer:
+
+```bash
+tinymist preview /abs-path/to/main.typ --headless \
+  --data-plane-host=127.0.0.1:0 --control-plane-host=127.0.0.1:0
+```
+
+Once the servers are listening, the addresses are printed to stdout as a JSON line:
+
+```json
+{"dataPlaneAddr":"127.0.0.1:51234","controlPlaneAddr":"127.0.0.1:51235"}
+```
+
+The viewer then connects to both addresses by websocket.
+
+=== Data Plane
+
+The data plane streams the frames to the viewer. The server sends binary messages, whose text prefix before the first comma tells the kind of the message:
+- `hello,<json>`: the first message, carrying the version of the protocol and the capabilities of the server, `{"version": 1, "capabilities": ["resume", ...]}`.
+- `new,<data>`, `diff-v1,<data>`: the full or incremental frames of the document, in the vector format of `reflexo-vec2svg`.
+- `jump,<page> <x> <y>,...`: the positions in the document to scroll to, after a source-to-document jump.
+- `viewport,<page> <x> <y>`: the position to scroll the viewport to.
+- `cursor-paths,<json>`: the element paths under the cursor of the editor.
+- `page-color,<color>`: the color to fill the pages before they are rendered, if they are recolored by `--recolor`.
+- `slide,<page>`: the slide shown by another viewer, in the slide mode.
+- `speaker-notes,<json>`: the speaker notes, `[{"page": 1, "note": "..."}, ...]`.
+- `scroll-position,<page> <left> <top>`: the latest scroll position of a resumed session, sent after the first frame.
+
+The viewer sends text messages:
+- `hello,<json>`: the version of the protocol, the capabilities of the viewer and its session, `{"version": 1, "capabilities": ["resume", ...], "session": "..."}`. The server closes the connection if the versions mismatch. The viewers without the handshake are still served.
+- `current`: requests the full frames of the latest document.
+- `scroll-position,<page> <left> <top>`: the scroll position of the viewer, i.e. the 1-based page and the scroll offsets of the window in CSS pixels. If the viewer has the `resume` capability, the server keeps the position of its session, and replays it when the viewer reconnects with the same session.
+- `srclocation <span>`: jumps to the source of a span, in hex, which is carried by the frames.
+- `srcpath <json>`: jumps to the source of an element path, `[[kind, index, fingerprint], ...]`.
+- `src-point <json>`: jumps to the source of a point, `{"page_no": 1, "x": 0.0, "y": 0.0}`.
+- `outline-sync,<page> <x> <y>`: scrolls the viewport to a position.
+- `slide,<page>`: shows a slide in all the viewers, in the slide mode.
+- `speaker-notes`: requests the speaker notes.
+- `visible-pages,<first> <last>`: the 1-based and inclusive range of the pages in the viewport. With `--partial-rendering`, only the pages near them are rendered, and the other pages are sent as blank pages of the same size.
+
+=== Control Plane
+
+The control plane exchanges JSON text messages tagged by the `event` field. The viewer sends:
+- `panelScrollTo`: `{"event": "panelScrollTo", "filepath": "...", "line": 0, "character": 0}`, jumps from a source position to the document.
+- `changeCursorPosition`: the same fields as `panelScrollTo`, highlights the elements under the cursor.
+- `panelScrollByPosition`: `{"event": "panelScrollByPosition", "position": {"page_no": 1, "x": 0.0, "y": 0.0}}`.
+- `sourceScrollBySpan`: `{"event": "sourceScrollBySpan", "span": "<hex>"}`.
+- `syncMemoryFiles`, `updateMemoryFiles`: `{"event": "...", "files": {"/abs/path.typ": "content"}}`, overrides the files on disk with unsaved content.
+- `removeMemoryFiles`: `{"event": "removeMemoryFiles", "files": ["/abs/path.typ"]}`.
+- `exportView`: `{"event": "exportView", "page": 1, "region": {"x": 0, "y": 0, "width": 100, "height": 100}, "format": "png" | "svg", "ppi": 144, "path": "/abs/path.png"}`, exports the viewed page as an image. All the fields are optional, see @export-view.
+
+The server sends:
+- `editorScrollTo`: `{"event": "editorScrollTo", "filepath": "...", "start": [line, column], "end": [line, column]}`, the jump-to-source information, zero-based.
+- `compileStatus`: `{"event": "compileStatus", "kind": "Compiling" | "CompileSuccess" | "CompileError"}`.
+- `outline`: the outline of the document.
+- `syncEditorChanges`: asks the viewer to send the unsaved files by `syncMemoryFiles`.
+- `exportView`: `{"event": "exportView", "page": 1, "format": "png", "path": "...", "data": "...", "error": "..."}`, the exported image.
+
 == Developer Guide
 
 See #link("https://enter-tainer.github.io/typst-preview/arch.html")[Typst-Preview Developer Guide].
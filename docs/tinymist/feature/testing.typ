@@ -22,6 +22,7 @@ Given a file, tinymist will try to discover tests related to the file.
     - If a function is named `test-*`, it is considered a test function and will be called directly.
     - If a function is named `bench-*`, it is considered a benchmark function and will be called once to collect coverage.
     - If a function is named `panic-on-*`, it will only pass the test if a panic occurs during execution.
+  - The fenced `example` blocks in the docstrings (`///` comments) will be compiled as hidden tests, importing all the definitions of the file, so that the documentation of the packages stays correct. The errors are reported at the lines of the docstrings.
 
 Example Entry File:
 ```typ
@@ -61,6 +62,8 @@ Check #github-link("/crates/crityp/")[crityp] for more information.
 
 VS Cod(e,ium) will show the overall coverage in the editor.
 
+Other editors can collect the coverage of a document using the command `tinymist.runCoverage` with the path of the document. The server compiles the document and sends a `tinymist/coverage` notification for each covered file, with the uri of the file and the hits of its lines, e.g. `{ "uri": "file:///main.typ", "lines": [{ "line": 0, "hits": 1 }, { "line": 3, "hits": 0 }] }`, where the lines are 0-based and the lines not listed have no executable code. The editors can then paint the covered and uncovered lines in the gutter. The command returns the numbers of the covered files, the lines and the lines hit.
+
 = CLI Support
 
 You can run tests and check coverage in the CLI.
@@ -73,6 +76,14 @@ tinymist test tests/main.typ
 
 You can pass same arguments as `typst compile` to `tinymist test`.
 
+The test cases run in parallel, each in a world with the file of the test case as the main file, and their outputs are printed in the order of the test cases. Use `--jobs` to limit the number of threads, e.g. `--jobs 1` to run the test cases one by one.
+
+To let the CI systems render the test results natively, write a JUnit report, which lists the test cases per file along with their locations, their durations, and the reasons of their failures:
+
+```bash
+tinymist test tests/main.typ --report junit=target/junit.xml
+```
+
 To watch for changes and run tests automatically, use the `--watch` option:
 
 ```bash
@@ -88,6 +99,10 @@ h/r/u/c/q: help/run/update/quit
 
 For example, according to the help message, update the reference files using the command `u` (update).
 
+After a change, the test runner only reruns the test cases impacted by the changed files, i.e. the test cases defined in the changed files or in the files importing, including, or reading them, along with the test cases failed in the last run. The changed files are printed before the run, and the other test cases are marked as skipped in the JUnit reports. The commands `r` (run) and `u` (update) run all the test cases again. All the test cases are run when collecting coverage.
+
+The language server also provides the `tinymist.getChangeImpact` command, which reports the entrypoints, the test cases, and the export tasks in the lock file impacted by a modified file.
+
 = Collecting Coverage with CLI
 
 You can collect coverage using the `--coverage` option.
@@ -111,6 +126,12 @@ tinymist test tests/main.typ --coverage --print-coverage=full
    Info All test cases passed...
 ```
 
+Use `--coverage-format` to write the coverage in other formats, e.g. to upload it to Codecov, or to render it in the merge requests of GitLab. The LCOV tracefile is written to `target/lcov.info` and the Cobertura XML is written to `target/cobertura.xml`, where the paths are relative to the root of the workspace and the files of the packages are not included.
+
+```bash
+tinymist test tests/main.typ --coverage --coverage-format=json,lcov,cobertura
+```
+
 = Debugging tests with CLI
 
 If any test fails, the CLI will return a non-zero exit code.
@@ -127,6 +148,15 @@ To update the reference files, you can run:
 tinymist test tests/main.typ --update
 ```
 
+The reference files that differ from the outputs are rewritten, and the created and updated reference files are listed after the run:
+
+```bash
+tinymist test tests/main.typ --update
+...
+   Info Updated 1 reference files
+   Info   refs/paged/example-hello-world.png
+```
+
 To get image files to diff you can use grep to find the image files to update:
 
 ```bash
@@ -137,6 +167,12 @@ tinymist test tests/main.typ 2> >(grep Hint) > >(grep "compare image")
 
 You can use your favorite image `diff` tool to compare the images, e.g. `magick compare`.
 
+The rendered pages are also compared with the reference images pixel by pixel. If they differ, an image highlighting the differing pixels in red is written next to the reference image, e.g. `refs/paged/example-hello-world.diff.png`. To tolerate the small differences, e.g. of the antialiasing across the platforms, set the maximum difference of a color channel for a pixel to be the same, and the maximum ratio of the differing pixels for a page to match:
+
+```bash
+tinymist test tests/main.typ --diff-threshold 8 --diff-tolerance 0.001
+```
+
 = Tips: Reproducible Rendering
 
 To ensure that the rendering is reproducible, you can ignore system fonts.
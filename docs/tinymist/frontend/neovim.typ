@@ -167,6 +167,18 @@ vim.lsp.buf.execute_command({ command = 'tinymist.pinMain', arguments = { vim.v.
 ```
 
 
+If a project has several entry files, e.g. `thesis.typ` and `slides.typ`, you can register them to compile all of them at the same time, each with its own diagnostics, and focus a buffer on one of them:
+```lua
+-- compile `slides.typ` besides the main file
+vim.lsp.buf.execute_command({ command = 'tinymist.addEntry', arguments = { '/path/to/slides.typ' } })
+-- use `slides.typ` as the main file while the current buffer is focused
+vim.lsp.buf.execute_command({ command = 'tinymist.focusEntry', arguments = { vim.api.nvim_buf_get_name(0), '/path/to/slides.typ' } })
+-- reset the current buffer to the implicit main file
+vim.lsp.buf.execute_command({ command = 'tinymist.focusEntry', arguments = { vim.api.nvim_buf_get_name(0), vim.v.null } })
+```
+
+The entries are unregistered by `tinymist.removeEntry` with the same arguments as `tinymist.addEntry`.
+
 It also doesn't remember the pinned main file across sessions, so you may need to run the command again after restarting Neovim.
 
 This could be improved in the future.
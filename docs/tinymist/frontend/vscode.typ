@@ -213,3 +213,5 @@ by other settings.
 = Contributing
 <contributing>
 You can submit issues or make PRs to #link("https://github.com/Myriad-Dreamin/tinymist")[GitHub];.
+
+When reporting a bug in the analysis, e.g. a wrong completion or hover, run the command `Typst: Dump the AST, Scopes, and Types of the Current File for Bug Reports` on a file reproducing the bug, and attach the opened JSON to the issue. The dump contains the syntax tree, the resolved expressions, the scopes, and the inferred types of the file, which are given by the `tinymist/debugAst` request in other editors.
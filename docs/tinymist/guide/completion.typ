@@ -46,6 +46,17 @@ A frequently asked question is how to completing labels in sub files when writin
   See #github-link("/editors/helix#working-with-multiple-files-projects")[Helix: Working with Multiple File Projects].
 ]
 
+== Bibliography Style Completion
+
+The `style` argument of `bibliography` and `cite` is completed with both the built-in CSL styles, e.g. `"apa"` or `"ieee"`, and the `.csl` files in the workspace. The completion details describe a style by its title and citation format, e.g. "author-date" or "numeric", and, for a `.csl` file, the date when it was last updated.
+
+== Math Symbol Completion
+
+In math mode, the symbols are completed with their glyphs shown beside the names. The symbols can also be found by their LaTeX commands and shorthands:
++ typing a LaTeX command, e.g. ```typ $\alpha$``` or ```typ $\Rightarrow$```, completes the symbol and replaces the command with the name of the symbol, e.g. `arrow.r.double`. The commands are matched fuzzily, so ```typ $\rarr$``` also finds `\rightarrow`.
++ typing a command without the backslash, e.g. ```typ $leq$```, finds the symbol if it is named differently in Typst, e.g. `lt.eq`.
++ triggering the completion manually after a shorthand, e.g. ```typ $=>$```, lists the symbols which can be written by the shorthand or its longer forms, e.g. `arrow.r.double` and `arrow.r.double.long`.
+
 = Using Snippet-Based Completion
 
 #pro-tip[
@@ -30,6 +30,7 @@ import { labelActivate } from "./features/label";
 import { packageActivate } from "./features/package";
 import { toolActivate } from "./features/tool";
 import { copyAndPasteActivate, dragAndDropActivate } from "./features/drop-paste";
+import { yamlBibActivate } from "./features/yaml-bib";
 import { testingActivate } from "./features/testing";
 import { testingDebugActivate } from "./features/testing/debug";
 import { FeatureEntry, tinymistActivate, tinymistDeactivate } from "./extension.shared";
@@ -46,6 +47,7 @@ const systemActivateTable = (): FeatureEntry[] => [
   [extensionState.features.tool, toolActivate],
   [extensionState.features.dragAndDrop, dragAndDropActivate],
   [extensionState.features.copyAndPaste, copyAndPasteActivate],
+  [extensionState.features.lsp, yamlBibActivate],
   [extensionState.features.export, exportActivate],
   [extensionState.features.task, taskActivate],
   [extensionState.features.testing, testingActivate],
@@ -214,7 +216,11 @@ async function languageActivate(context: IContext) {
     commands.registerCommand("tinymist.clearCache", commandClearCache),
     commands.registerCommand("tinymist.runCodeLens", commandRunCodeLens),
     commands.registerCommand("tinymist.copyAnsiHighlight", commandCopyAnsiHighlight),
+    commands.registerCommand("tinymist.explainFontFallback", commandExplainFontFallback),
+    commands.registerCommand("tinymist.convertLatexToTypst", commandConvertLatexToTypst),
+    commands.registerCommand("tinymist.exportSelectionAsImage", commandExportSelectionAsImage),
     commands.registerCommand("tinymist.viewAst", commandViewAst(context)),
+    commands.registerCommand("tinymist.dumpDebugAst", commandDumpDebugAst),
 
     commands.registerCommand("tinymist.pinMainToCurrent", () => commandPinMain(true)),
     commands.registerCommand("tinymist.unpinMain", () => commandPinMain(false)),
@@ -291,6 +297,148 @@ async function commandCopyAnsiHighlight(): Promise<void> {
   await vscode.env.clipboard.writeText(res);
 }
 
+interface FontCluster {
+  text: string;
+  font: string;
+  reason: "primary" | "fallback" | "requested" | "missing";
+  candidates: string[];
+  explanation: string;
+}
+
+async function commandExplainFontFallback(): Promise<void> {
+  const editor = activeTypstEditor();
+  if (editor === undefined) {
+    return;
+  }
+
+  const client = await tinymist.clientPromise;
+  const selection = editor.selection.isEmpty
+    ? editor.document.lineAt(editor.selection.active.line).range
+    : editor.selection;
+  const res = await tinymist.executeCommand<{ clusters: FontCluster[] } | null>(
+    "tinymist.explainFontFallback",
+    [editor.document.uri.fsPath, client.code2ProtocolConverter.asRange(selection)],
+  );
+
+  if (!res || res.clusters.length === 0) {
+    await window.showInformationMessage("No rendered text is found in the selection.");
+    return;
+  }
+
+  await window.showQuickPick(
+    res.clusters.map((cluster) => ({
+      label: `${JSON.stringify(cluster.text)} → ${cluster.font}`,
+      description: cluster.reason,
+      detail:
+        cluster.reason === "primary"
+          ? cluster.explanation
+          : `${cluster.explanation}. Fonts covering it: ${cluster.candidates.join(", ") || "none"}`,
+    })),
+    { title: "Fonts used for the selected text", matchOnDescription: true },
+  );
+}
+
+async function commandConvertLatexToTypst(): Promise<void> {
+  const editor = activeTypstEditor();
+  if (editor === undefined) {
+    return;
+  }
+
+  // Converts the selected formula, or the copied one if nothing is selected.
+  const selection = editor.selection;
+  const latex = selection.isEmpty
+    ? await vscode.env.clipboard.readText()
+    : editor.document.getText(selection);
+  if (!latex.trim()) {
+    await window.showInformationMessage("No LaTeX math is selected or copied.");
+    return;
+  }
+
+  const typst = await tinymist.executeCommand<string>("tinymist.convertLatexMath", [latex]);
+  await editor.edit((edit) => edit.replace(selection, typst));
+}
+
+async function commandDumpDebugAst(): Promise<void> {
+  const editor = activeTypstEditor();
+  if (editor === undefined) {
+    return;
+  }
+
+  // Dumps the analysis of the document as JSON, which is attached to bug reports.
+  const client = await tinymist.getClient();
+  const dump = await client.sendRequest("tinymist/debugAst", {
+    textDocument: client.code2ProtocolConverter.asTextDocumentIdentifier(editor.document),
+  });
+  if (!dump) {
+    await window.showErrorMessage("Failed to dump the analysis of the document.");
+    return;
+  }
+
+  const document = await vscode.workspace.openTextDocument({
+    language: "json",
+    content: JSON.stringify(dump, null, 2),
+  });
+  await window.showTextDocument(document, vscode.ViewColumn.Beside);
+}
+
+interface ExportSelectionResponse {
+  format: "png" | "svg";
+  data?: string;
+  diagnostics: { message: string }[];
+}
+
+async function commandExportSelectionAsImage(): Promise<void> {
+  const editor = activeTypstEditor();
+  if (editor === undefined) {
+    return;
+  }
+  if (editor.selection.isEmpty) {
+    await window.showInformationMessage("Select the markup to export as an image.");
+    return;
+  }
+
+  const format = await window.showQuickPick(["png", "svg"], {
+    placeHolder: "The format of the image",
+  });
+  if (format === undefined) {
+    return;
+  }
+
+  // Compiles the selection with the imports and rules of the document.
+  const client = await tinymist.getClient();
+  const range = client.code2ProtocolConverter.asRange(editor.selection);
+  const res = await tinymist.executeCommand<ExportSelectionResponse | null>(
+    "tinymist.exportSelection",
+    [editor.document.uri.fsPath, range, { format }],
+  );
+  if (!res?.data) {
+    const errors = res?.diagnostics.map((diag) => diag.message).join("; ");
+    await window.showErrorMessage(`Failed to render the selection: ${errors || "unknown error"}`);
+    return;
+  }
+
+  const dest = await window.showSaveDialog({
+    defaultUri: vscode.Uri.file(
+      path.join(path.dirname(editor.document.uri.fsPath), `selection.${format}`),
+    ),
+    filters: { [format.toUpperCase()]: [format] },
+  });
+  if (dest === undefined) {
+    return;
+  }
+
+  const data =
+    format === "png" ? Buffer.from(res.data, "base64") : new TextEncoder().encode(res.data);
+  await vscode.workspace.fs.writeFile(dest, data);
+  const action = await window.showInformationMessage(
+    `Exported the selection to ${dest.fsPath}`,
+    "Open",
+  );
+  if (action === "Open") {
+    await vscode.env.openExternal(dest);
+  }
+}
+
 function commandViewAst(ctx: IContext) {
   const scheme = "tinymist-ast";
   const uri = `${scheme}://viewAst/ast.typ`;
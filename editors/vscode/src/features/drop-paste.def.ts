@@ -68,9 +68,22 @@ export const typstUriEditKind = typstPasteLinkEditKind.append("uri");
 
 export const typstImageEditKind = typstPasteLinkEditKind.append("image");
 
+/** Kind for pasted text whose paths and urls are rewritten by the language server */
+export const typstSmartPasteEditKind = vscode.DocumentDropOrPasteEditKind.Empty.append(
+  "typst",
+  "smart",
+);
+
+/** Kind for pasted HTML or Markdown that is converted into Typst markup */
+export const typstConvertEditKind = vscode.DocumentDropOrPasteEditKind.Empty.append(
+  "typst",
+  "convert",
+);
+
 export const Mime = {
   textUriList: "text/uri-list",
   textPlain: "text/plain",
+  textHtml: "text/html",
 } as const;
 
 export const typstSupportedMimes = new Set([
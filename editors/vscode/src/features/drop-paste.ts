@@ -13,6 +13,8 @@ import {
   typstImageEditKind,
   typstPasteLinkEditKind,
   typstUriEditKind,
+  typstSmartPasteEditKind,
+  typstConvertEditKind,
   Schemes,
 } from "./drop-paste.def";
 import { IContext } from "../context";
@@ -36,6 +38,14 @@ export function copyAndPasteActivate(context: IContext) {
       providedPasteEditKinds: providedEditKinds,
       pasteMimeTypes: PasteResourceProvider.mimeTypes,
     }),
+    vscode.languages.registerDocumentPasteEditProvider(sel, new SmartPasteProvider(), {
+      providedPasteEditKinds: [typstSmartPasteEditKind],
+      pasteMimeTypes: SmartPasteProvider.mimeTypes,
+    }),
+    vscode.languages.registerDocumentPasteEditProvider(sel, new PasteAsTypstProvider(), {
+      providedPasteEditKinds: [typstConvertEditKind],
+      pasteMimeTypes: PasteAsTypstProvider.mimeTypes,
+    }),
   );
 }
 
@@ -580,6 +590,87 @@ export class PasteUriProvider implements vscode.DocumentPasteEditProvider {
   }
 }
 
+/**
+ * Rewrites the pasted text by the language server, which makes absolute paths relative to the
+ * document and converts bare urls into `link` calls.
+ */
+export class SmartPasteProvider implements vscode.DocumentPasteEditProvider {
+  public static readonly mimeTypes = [Mime.textPlain];
+
+  public async provideDocumentPasteEdits(
+    document: vscode.TextDocument,
+    ranges: readonly vscode.Range[],
+    dataTransfer: vscode.DataTransfer,
+    _context: vscode.DocumentPasteEditContext,
+    token: vscode.CancellationToken,
+  ): Promise<vscode.DocumentPasteEdit[] | undefined> {
+    const text = await dataTransfer.get(Mime.textPlain)?.asString();
+    if (token.isCancellationRequested || !text || document.uri.scheme !== Schemes.file) {
+      return;
+    }
+
+    const position = ranges[0]?.start;
+    if (!position) {
+      return;
+    }
+
+    const pasted = await vscode.commands.executeCommand<string | null>(
+      "tinymist.smartPaste",
+      document.uri.fsPath,
+      { line: position.line, character: position.character },
+      text,
+    );
+    if (token.isCancellationRequested || !pasted) {
+      return;
+    }
+
+    const edit = new vscode.DocumentPasteEdit(
+      pasted,
+      "Paste with Relative Paths and Links (Typst)",
+      typstSmartPasteEditKind,
+    );
+    edit.yieldTo = [vscode.DocumentDropOrPasteEditKind.Text];
+    return [edit];
+  }
+}
+
+/**
+ * Converts the pasted HTML, or the pasted text as Markdown, into Typst markup by the language
+ * server.
+ */
+export class PasteAsTypstProvider implements vscode.DocumentPasteEditProvider {
+  public static readonly mimeTypes = [Mime.textHtml, Mime.textPlain];
+
+  public async provideDocumentPasteEdits(
+    _document: vscode.TextDocument,
+    _ranges: readonly vscode.Range[],
+    dataTransfer: vscode.DataTransfer,
+    _context: vscode.DocumentPasteEditContext,
+    token: vscode.CancellationToken,
+  ): Promise<vscode.DocumentPasteEdit[] | undefined> {
+    const html = await dataTransfer.get(Mime.textHtml)?.asString();
+    const format = html ? "html" : "markdown";
+    const text = html || (await dataTransfer.get(Mime.textPlain)?.asString());
+    if (token.isCancellationRequested || !text) {
+      return;
+    }
+    const title = html ? "Paste HTML as Typst" : "Paste Markdown as Typst";
+
+    const converted = await vscode.commands.executeCommand<string>(
+      "tinymist.pasteAsTypst",
+      format,
+      text,
+    );
+    if (token.isCancellationRequested || !converted) {
+      return;
+    }
+
+    const edit = new vscode.DocumentPasteEdit(converted, title, typstConvertEditKind);
+    edit.yieldTo = [vscode.DocumentDropOrPasteEditKind.Text, typstSmartPasteEditKind];
+    return [edit];
+  }
+}
+
 type OverwriteBehavior = "overwrite" | "nameIncrementally";
 
 export interface CopyFileConfiguration {
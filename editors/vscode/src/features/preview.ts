@@ -477,6 +477,11 @@ async function launchPreviewLsp(task: LaunchInBrowserTask | LaunchInWebViewTask)
       : [];
     const ivArgs = getPreviewConfCompat("invertColors");
     const invertColorsArgs = ivArgs ? ["--invert-colors", JSON.stringify(ivArgs)] : [];
+    const rcArgs = getPreviewConfCompat<string | Record<string, string>>("recolor");
+    const recolorArgs =
+      rcArgs && rcArgs !== "never"
+        ? ["--recolor", typeof rcArgs === "string" ? rcArgs : JSON.stringify(rcArgs)]
+        : [];
     const previewInSlideModeArgs = task.mode === "slide" ? ["--preview-mode=slide"] : [];
     const dataPlaneHostArgs = !isDev ? ["--data-plane-host", "127.0.0.1:0"] : [];
 
@@ -488,6 +493,7 @@ async function launchPreviewLsp(task: LaunchInBrowserTask | LaunchInWebViewTask)
       ...dataPlaneHostArgs,
       ...partialRenderingArgs,
       ...invertColorsArgs,
+      ...recolorArgs,
       ...previewInSlideModeArgs,
       ...(isNotPrimary ? ["--not-primary"] : []),
       filePath,
@@ -794,6 +800,8 @@ interface OutlineItemData {
   title: string;
   span?: string;
   position?: CursorPosition;
+  /** The anchor of the heading in the HTML export. */
+  anchor?: string;
   children: OutlineItemData[];
 }
 
@@ -854,6 +862,7 @@ export class OutlineItem extends vscode.TreeItem {
     const detachedHint = span ? `` : `, detached`;
 
     const pos = this.data.position;
+    const anchorHint = this.data.anchor ? `, #${this.data.anchor}` : ``;
 
     const label = this.label
       ? typeof this.label === "string"
@@ -862,10 +871,10 @@ export class OutlineItem extends vscode.TreeItem {
       : "<no-label>";
 
     if (pos) {
-      this.tooltip = `${label} in page ${pos.page_no}, at (${pos.x.toFixed(3)} pt, ${pos.y.toFixed(3)} pt)${detachedHint}`;
+      this.tooltip = `${label} in page ${pos.page_no}, at (${pos.x.toFixed(3)} pt, ${pos.y.toFixed(3)} pt)${anchorHint}${detachedHint}`;
       this.description = `page: ${pos.page_no}, at (${pos.x.toFixed(1)} pt, ${pos.y.toFixed(1)} pt)${detachedHint}`;
     } else {
-      this.tooltip = `${label}${detachedHint}`;
+      this.tooltip = `${label}${anchorHint}${detachedHint}`;
       this.description = `no pos`;
     }
   }
@@ -3,7 +3,16 @@ import { tinymist } from "../lsp";
 import { VirtualConsole } from "../util";
 import { extensionState } from "../state";
 
-type ExportFormat = "pdf" | "png" | "svg" | "html" | "markdown" | "text" | "query" | "pdfpc";
+type ExportFormat =
+  | "pdf"
+  | "png"
+  | "svg"
+  | "html"
+  | "markdown"
+  | "docx"
+  | "text"
+  | "query"
+  | "pdfpc";
 
 interface ExportArgs {
   format: ExportFormat | ExportFormat[];
@@ -11,7 +20,13 @@ interface ExportArgs {
   outputPath: string;
 
   "pdf.creationTimestamp"?: string | null;
+  "pdf.standard"?: string | string[];
+  "pdf.tagged"?: boolean;
   "png.ppi"?: number;
+  "png.pages"?: string;
+  "png.pageTemplate"?: string;
+  "svg.pages"?: string;
+  "svg.pageTemplate"?: string;
 
   fill?: string;
   "png.fill"?: string;
@@ -24,6 +39,8 @@ interface ExportArgs {
   "png.merged.gap"?: string;
   "svg.merged.gap"?: string;
 
+  "html.headingAnchors"?: "none" | "github" | "label";
+
   "query.format"?: string;
   "query.outputExtension"?: string;
   "query.strict"?: boolean;
@@ -35,6 +52,7 @@ interface ExportArgs {
   processor?: string;
   "markdown.processor"?: string;
   "tex.processor"?: string;
+  "docx.processor"?: string;
   assetsPath?: string;
   "markdown.assetsPath"?: string;
   "tex.assetsPath"?: string;
@@ -117,11 +135,21 @@ const exportOps = (exportArgs: ExportArgs) => ({
   },
 });
 
+const pdfStandards = (standard?: string | string[]) => {
+  if (standard === undefined) {
+    return undefined;
+  }
+  const standards = typeof standard === "string" ? standard.split(",") : standard;
+  return standards.map((s) => s.trim()).filter((s) => s.length > 0);
+};
+
 const provideFormats = (exportArgs: ExportArgs, ops = exportOps(exportArgs)) => ({
   pdf: {
     opts() {
       return {
         creationTimestamp: exportArgs["pdf.creationTimestamp"],
+        pdf_standard: pdfStandards(exportArgs["pdf.standard"]),
+        tagged: exportArgs["pdf.tagged"],
       };
     },
     export: tinymist.exportPdf,
@@ -132,6 +160,8 @@ const provideFormats = (exportArgs: ExportArgs, ops = exportOps(exportArgs)) =>
         ppi: exportArgs["png.ppi"] || 96,
         fill: exportArgs["png.fill"] || exportArgs["fill"],
         page: ops.resolvePageOpts("png"),
+        pages: exportArgs["png.pages"]?.split(",").map((range) => range.trim()),
+        page_template: exportArgs["png.pageTemplate"],
       };
     },
     export: tinymist.exportPng,
@@ -140,13 +170,17 @@ const provideFormats = (exportArgs: ExportArgs, ops = exportOps(exportArgs)) =>
     opts() {
       return {
         page: ops.resolvePageOpts("svg"),
+        pages: exportArgs["svg.pages"]?.split(",").map((range) => range.trim()),
+        page_template: exportArgs["svg.pageTemplate"],
       };
     },
     export: tinymist.exportSvg,
   },
   html: {
     opts() {
-      return {};
+      return {
+        heading_anchors: exportArgs["html.headingAnchors"],
+      };
     },
     export: tinymist.exportHtml,
   },
@@ -168,6 +202,14 @@ const provideFormats = (exportArgs: ExportArgs, ops = exportOps(exportArgs)) =>
     },
     export: tinymist.exportTeX,
   },
+  docx: {
+    opts() {
+      return {
+        processor: exportArgs["docx.processor"] || exportArgs["processor"],
+      };
+    },
+    export: tinymist.exportDocx,
+  },
   text: {
     opts() {
       return {};
@@ -0,0 +1,45 @@
+/**
+ * Completion in the Hayagriva YAML bibliographies.
+ */
+
+import * as vscode from "vscode";
+import type * as lc from "vscode-languageclient";
+import { IContext } from "../context";
+import { tinymist } from "../lsp";
+
+const yamlDocumentSelector = [
+  { scheme: "file", language: "yaml" },
+  { scheme: "untitled", language: "yaml" },
+];
+
+export function yamlBibActivate(context: IContext) {
+  context.subscriptions.push(
+    vscode.languages.registerCompletionItemProvider(
+      yamlDocumentSelector,
+      new YamlBibCompletionProvider(),
+    ),
+  );
+}
+
+/**
+ * Completes the field names and the values of the enumerated fields, e.g. `type`, by the language
+ * server. The YAML files are not opened in the language server, so their text is passed along.
+ */
+class YamlBibCompletionProvider implements vscode.CompletionItemProvider {
+  async provideCompletionItems(
+    document: vscode.TextDocument,
+    position: vscode.Position,
+    token: vscode.CancellationToken,
+  ): Promise<vscode.CompletionList | undefined> {
+    const client = await tinymist.getClient();
+    const list = await tinymist.executeCommand<lc.CompletionList | null>(
+      "tinymist.completeYamlBib",
+      [document.getText(), { line: position.line, character: position.character }],
+    );
+    if (token.isCancellationRequested || !list) {
+      return;
+    }
+
+    return client.protocol2CodeConverter.asCompletionResult(list);
+  }
+}
@@ -326,6 +326,7 @@ export class LanguageState {
   exportHtml = exportCommand("tinymist.exportHtml");
   exportMarkdown = exportCommand("tinymist.exportMarkdown");
   exportTeX = exportCommand("tinymist.exportTeX");
+  exportDocx = exportCommand("tinymist.exportDocx");
   exportText = exportCommand("tinymist.exportText");
   exportQuery = exportCommand("tinymist.exportQuery");
   exportAnsiHighlight = exportCommand("tinymist.exportAnsiHighlight");
@@ -339,6 +340,18 @@ export class LanguageState {
     return tinymist.executeCommand<SymbolInformation[]>("tinymist.getWorkspaceLabels", []);
   }
 
+  getIndexTerms(locale?: string) {
+    return tinymist.executeCommand<IndexTermsResponse>("tinymist.getIndexTerms", [locale ?? null]);
+  }
+
+  getChangeImpact(path: string) {
+    return tinymist.executeCommand<ChangeImpactResponse>("tinymist.getChangeImpact", [path]);
+  }
+
+  runCoverage(path: string) {
+    return tinymist.executeCommand<RunCoverageResponse>("tinymist.runCoverage", [path]);
+  }
+
   showLog() {
     if (this.client) {
       this.client.outputChannel.show();
@@ -642,6 +655,36 @@ export interface SymbolInfo {
   children: SymbolInfo[];
 }
 
+export interface IndexTermsResponse {
+  locale: string | null;
+  groups: {
+    initial: string;
+    terms: { path: string[]; locations: lc.Location[] }[];
+  }[];
+}
+
+export interface ChangeImpactResponse {
+  files: string[];
+  documents: { id: string | null; main: string }[];
+  tasks: { id: string; document: string; format: string }[];
+  tests: { name: string; kind: string; location: string }[];
+}
+
+export interface RunCoverageResponse {
+  files: number;
+  lines: number;
+  linesHit: number;
+}
+
+/**
+ * The params of the `tinymist/coverage` notification, sent once per covered file by the
+ * `tinymist.runCoverage` command.
+ */
+export interface CoverageParams {
+  uri: string;
+  lines: { line: number; hits: number }[];
+}
+
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 function isCodeActionWithoutEditsAndCommands(value: any): boolean {
   const candidate: lc.CodeAction = value;
@@ -0,0 +1,45 @@
+//! Fuzzes the analyses of the language server on the random documents
+//! generated from the fuzzed seeds.
+//!
+//! Runs with `cargo fuzz run analysis` in this directory.
+
+#![no_main]
+
+use std::sync::{Arc, LazyLock};
+
+use libfuzzer_sys::fuzz_target;
+use tinymist_project::{CompileFontArgs, EntryState, ExportTarget, LspUniverseBuilder, LspWorld};
+use tinymist_query::analysis::{fuzz_analysis, Analysis};
+
+/// The world shared by the fuzzed documents, which uses the embedded fonts
+/// only.
+static WORLD: LazyLock<LspWorld> = LazyLock::new(|| {
+    let root = std::env::temp_dir().join("tinymist-fuzz");
+    let entry = EntryState::new_rooted(root.as_path().into(), None);
+    let fonts = LspUniverseBuilder::resolve_fonts(CompileFontArgs {
+        ignore_system_fonts: true,
+        ..Default::default()
+    })
+    .expect("failed to resolve fonts");
+    let verse = LspUniverseBuilder::build(
+        entry,
+        ExportTarget::Paged,
+        Default::default(),
+        Default::default(),
+        LspUniverseBuilder::resolve_package(None, None),
+        Arc::new(fonts),
+    );
+    verse.snapshot()
+});
+
+fuzz_target!(|seed: u64| {
+    // Keeps the range of a single seed non-empty.
+    let seed = seed % u64::MAX;
+    let failures = fuzz_analysis(&Analysis::default(), &WORLD, seed..seed + 1);
+    if let Some(failure) = failures.first() {
+        panic!(
+            "seed {}: {}\n{}",
+            failure.seed, failure.message, failure.source
+        );
+    }
+});
@@ -28,6 +28,9 @@ export interface Options {
   isContentPreview?: boolean;
   sourceMapping?: boolean;
   retrieveDOMState?: () => ContainerDOMState;
+  /// called with the 1-based and inclusive range of the pages in the viewport
+  /// when partial rendering is enabled, so that the server can render them lazily
+  onVisiblePages?: (first: number, last: number) => void;
 }
 
 export type GConstructor<T = {}> = new (...args: any[]) => T;
@@ -97,6 +100,8 @@ export class TypstDocumentContext<O = any> {
   outline: any = undefined;
   /// cursor position in form of [page, x, y]
   cursorPosition?: [number, number, number] = undefined;
+  /// last reported pages in the viewport in form of [first, last]
+  visiblePages?: [number, number] = undefined;
   // id: number = rnd++;
 
   /// Cache fields
@@ -152,6 +157,8 @@ export class TypstDocumentContext<O = any> {
 
   reset() {
     this.kModule.reset();
+    // a new connection knows nothing about the viewport
+    this.visiblePages = undefined;
     this.moduleInitialized = false;
   }
 
@@ -473,6 +480,14 @@ export class TypstDocumentContext<O = any> {
     return true;
   }
 
+  reportVisiblePages(first: number, last: number) {
+    if (this.visiblePages?.[0] === first && this.visiblePages?.[1] === last) {
+      return;
+    }
+    this.visiblePages = [first, last];
+    this.opts.onVisiblePages?.(first, last);
+  }
+
   getPartialPageNumber(): number {
     return this.partialRenderPage + 1;
   }
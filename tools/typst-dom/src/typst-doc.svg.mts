@@ -162,6 +162,9 @@ export function provideSvgDoc<
       }
       const page = pagesInfo[pageOffset];
       hi.y = lo.y + page.height;
+      if (this.partialRendering) {
+        this.reportVisiblePages(pageOffset + 1, pageOffset + 1);
+      }
       hi.x = page.width;
 
       console.log("render_in_window for slide mode", lo.x, lo.y, hi.x, hi.y);
@@ -194,8 +197,10 @@ export function provideSvgDoc<
           const pages = Array.from(ch).filter((x) => x.classList.contains("typst-page"));
           let minTop = 1e33,
             maxBottom = -1e33,
-            accumulatedHeight = 0;
-          for (const page of pages) {
+            accumulatedHeight = 0,
+            firstPage = pages.length,
+            lastPage = 0;
+          for (const [idx, page] of pages.entries()) {
             const pageHeight = Number.parseFloat(page.getAttribute("data-page-height")!);
             const translateY = Number.parseFloat(page.getAttribute("data-y")!);
             if (translateY + pageHeight > topEstimate) {
@@ -204,12 +209,19 @@ export function provideSvgDoc<
             if (translateY < bottomEstimate) {
               maxBottom = Math.max(maxBottom, accumulatedHeight + pageHeight);
             }
+            if (translateY + pageHeight > topEstimate && translateY < bottomEstimate) {
+              firstPage = Math.min(firstPage, idx + 1);
+              lastPage = Math.max(lastPage, idx + 1);
+            }
             accumulatedHeight += pageHeight;
           }
 
           if (pages.length != 0) {
             topEstimate = minTop;
             bottomEstimate = maxBottom;
+            if (firstPage <= lastPage) {
+              this.reportVisiblePages(firstPage, lastPage);
+            }
           } else {
             topEstimate = 0;
             bottomEstimate = 1e33;
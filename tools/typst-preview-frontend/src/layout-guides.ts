@@ -0,0 +1,83 @@
+/** The layout guides of a page, in points relative to the top-left corner. */
+export interface PageGuides {
+  width: number;
+  height: number;
+  /** The area covered by the text, i.e. `[x, y, width, height]`. */
+  textArea?: [number, number, number, number];
+  /** The distances from the edges to the text area, i.e. `[left, top, right, bottom]`. */
+  margins?: [number, number, number, number];
+  baselines: number[];
+  boxes: { kind: string; rect: [number, number, number, number] }[];
+}
+
+/** The layout guides of a document, sent by the `layout-guides` message. */
+export interface LayoutGuides {
+  pages: PageGuides[];
+}
+
+const SVG_NS = "http://www.w3.org/2000/svg";
+const GUIDES_CLASS = "typst-layout-guides";
+
+const fmt = (v: number) => `${Math.round(v * 100) / 100}pt`;
+
+/** Removes the layout guides drawn over the pages. */
+export function clearLayoutGuides(root: Element) {
+  for (const elem of Array.from(root.getElementsByClassName(GUIDES_CLASS))) {
+    elem.remove();
+  }
+}
+
+/** Draws the layout guides over the pages, which show their measurements on hover. */
+export function drawLayoutGuides(root: Element, guides: LayoutGuides) {
+  clearLayoutGuides(root);
+
+  const pages = Array.from(root.getElementsByClassName("typst-page"));
+  guides.pages.forEach((page, idx) => {
+    const pageElem = pages[idx];
+    // Only the pages rendered as SVG are supported.
+    if (!(pageElem instanceof SVGGElement)) {
+      return;
+    }
+
+    const group = document.createElementNS(SVG_NS, "g");
+    group.setAttribute("class", GUIDES_CLASS);
+    group.setAttribute("pointer-events", "none");
+
+    const rect = (x: number, y: number, w: number, h: number, kind: string, title: string) => {
+      const elem = document.createElementNS(SVG_NS, "rect");
+      elem.setAttribute("class", `${GUIDES_CLASS}-${kind}`);
+      elem.setAttribute("x", `${x}`);
+      elem.setAttribute("y", `${y}`);
+      elem.setAttribute("width", `${w}`);
+      elem.setAttribute("height", `${h}`);
+      elem.setAttribute("pointer-events", "visibleStroke");
+      const titleElem = document.createElementNS(SVG_NS, "title");
+      titleElem.textContent = title;
+      elem.appendChild(titleElem);
+      group.appendChild(elem);
+    };
+
+    for (const y of page.baselines) {
+      const line = document.createElementNS(SVG_NS, "line");
+      line.setAttribute("class", `${GUIDES_CLASS}-baseline`);
+      line.setAttribute("x1", "0");
+      line.setAttribute("x2", `${page.width}`);
+      line.setAttribute("y1", `${y}`);
+      line.setAttribute("y2", `${y}`);
+      group.appendChild(line);
+    }
+
+    for (const { kind, rect: [x, y, w, h] } of page.boxes) {
+      rect(x, y, w, h, `box-${kind}`, `${kind}: ${fmt(w)} × ${fmt(h)} at (${fmt(x)}, ${fmt(y)})`);
+    }
+
+    if (page.textArea) {
+      const [x, y, w, h] = page.textArea;
+      const [l, t, r, b] = page.margins || [0, 0, 0, 0];
+      const margins = `margins: ${fmt(l)} ${fmt(t)} ${fmt(r)} ${fmt(b)}`;
+      rect(x, y, w, h, "text-area", `text area: ${fmt(w)} × ${fmt(h)}, ${margins}`);
+    }
+
+    pageElem.appendChild(group);
+  });
+}
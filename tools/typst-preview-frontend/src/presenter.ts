@@ -0,0 +1,98 @@
+/** A speaker note of a slide, sent by the `speaker-notes` message. */
+export interface SpeakerNote {
+  /** The 1-based number of the page containing the note. */
+  page: number;
+  note: string;
+}
+
+/** The timer of the presentation, served by the `/timer` endpoint. */
+interface TimerState {
+  /** The elapsed time in seconds. */
+  elapsed: number;
+  running: boolean;
+}
+
+const PANEL_ID = "typst-speaker-notes";
+
+/** The presenter panel in the slide mode, which shows the speaker notes of the
+ * current slide and the timer of the presentation. */
+export class PresenterPanel {
+  private notes: SpeakerNote[] = [];
+  private page = 1;
+  private timer?: ReturnType<typeof setInterval>;
+  private timerUrl: string;
+
+  constructor(wsUrl: string) {
+    const url = new URL(wsUrl);
+    url.protocol = url.protocol.replace("ws", "http");
+    url.pathname = "/timer";
+    this.timerUrl = url.href;
+  }
+
+  get visible() {
+    return !document.getElementById(PANEL_ID)?.classList.contains("hidden");
+  }
+
+  toggle() {
+    const panel = document.getElementById(PANEL_ID);
+    if (!panel) {
+      return;
+    }
+    panel.classList.toggle("hidden");
+    if (this.visible) {
+      window.typstWebsocket?.send("speaker-notes");
+      this.timer = setInterval(() => this.controlTimer(), 1000);
+      this.controlTimer();
+    } else {
+      clearInterval(this.timer);
+    }
+  }
+
+  setNotes(notes: SpeakerNote[]) {
+    this.notes = notes;
+    this.render();
+  }
+
+  setPage(page: number) {
+    this.page = page;
+    this.render();
+  }
+
+  /** Sends an action to the timer, or reads it if the action is absent. */
+  async controlTimer(action?: "toggle" | "reset") {
+    const url = new URL(this.timerUrl);
+    if (action) {
+      url.searchParams.set("action", action);
+    }
+    try {
+      const res = await fetch(url.href, { method: action ? "POST" : "GET" });
+      this.renderTimer(await res.json());
+    } catch (e) {
+      console.warn("cannot reach the presentation timer", e);
+    }
+  }
+
+  dispose() {
+    clearInterval(this.timer);
+  }
+
+  private render() {
+    const notesElem = document.getElementById(`${PANEL_ID}-content`);
+    if (!notesElem) {
+      return;
+    }
+    const notes = this.notes.filter((note) => note.page === this.page).map((note) => note.note);
+    notesElem.textContent = notes.length ? notes.join("\n\n") : "No speaker notes.";
+  }
+
+  private renderTimer({ elapsed, running }: TimerState) {
+    const timerElem = document.getElementById(`${PANEL_ID}-timer`);
+    if (!timerElem) {
+      return;
+    }
+    const minutes = Math.floor(elapsed / 60);
+    const seconds = Math.floor(elapsed % 60);
+    timerElem.textContent = `${minutes}:${seconds.toString().padStart(2, "0")}`;
+    timerElem.classList.toggle("paused", !running);
+  }
+}
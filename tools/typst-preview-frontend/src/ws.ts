@@ -10,6 +10,8 @@ import renderModule from "@myriaddreamin/typst-ts-renderer/pkg/typst_ts_renderer
 import { RenderSession } from "@myriaddreamin/typst.ts/dist/esm/renderer.mjs";
 import { WebSocketSubject, webSocket } from "rxjs/webSocket";
 import { Subject, Subscription, buffer, debounceTime, fromEvent, tap } from "rxjs";
+import { clearLayoutGuides, drawLayoutGuides, type LayoutGuides } from "./layout-guides";
+import { PresenterPanel, type SpeakerNote } from "./presenter";
 export { PreviewMode } from "typst-dom/typst-doc.mjs";
 
 // for debug propose
@@ -21,6 +23,10 @@ const enc = new TextEncoder();
 const dec = new TextDecoder();
 const NOT_AVAILABLE = "current not available";
 const COMMA = enc.encode(",");
+/** The version of the wire protocol, which must match the version of the server. */
+const PROTOCOL_VERSION = 1;
+/** The capabilities of the client, which are sent to the server in the handshake. */
+const CAPABILITIES = ["resume", "visible-pages", "layout-guides", "speaker-notes", "slide-sync"];
 export interface WsArgs {
   url: string;
   previewMode: PreviewMode;
@@ -39,6 +45,39 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
   let disposed = false;
   let $ws: WebSocketSubject<ArrayBuffer> | undefined = undefined;
   const subsribes: Subscription[] = [];
+  // Whether to draw the layout guides, which are requested after each update.
+  let layoutGuidesEnabled = false;
+  // The speaker notes and the timer, which are only available in the slide mode.
+  const presenter = previewMode === PreviewMode.Slide ? new PresenterPanel(url) : undefined;
+  // The session kept across the reconnects, with which the server replays the
+  // latest scroll position.
+  const session = previewSession(url);
+
+  /// Follows a slide changed by this view, and lets the other views follow it.
+  function onSlideChanged(page: number) {
+    presenter?.setPage(page);
+    window.typstWebsocket?.send(`slide,${page}`);
+    window.typstWebsocket?.send(`scroll-position,${page} 0 0`);
+  }
+
+  /// Reports the scroll position to the server, which replays it after reconnecting.
+  function reportScrollPosition() {
+    const rootElem = document.getElementById("typst-app")?.firstElementChild;
+    const page = (rootElem && window.currentPosition(rootElem)?.page) || 1;
+    window.typstWebsocket?.send(`scroll-position,${page} ${window.scrollX} ${window.scrollY}`);
+  }
+
+  function setLayoutGuides(enabled: boolean) {
+    layoutGuidesEnabled = enabled;
+    if (enabled) {
+      window.typstWebsocket?.send("layout-guides");
+    } else {
+      const hookedElem = document.getElementById("typst-app");
+      if (hookedElem) {
+        clearLayoutGuides(hookedElem);
+      }
+    }
+  }
 
   function createSvgDocument(kModule: RenderSession) {
     const hookedElem = document.getElementById("typst-app")!;
@@ -62,6 +101,10 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
           boundingRect: resizeTarget.getBoundingClientRect(),
         };
       },
+      // lets the server render the pages near the viewport first
+      onVisiblePages(first, last) {
+        window.typstWebsocket?.send(`visible-pages,${first} ${last}`);
+      },
     });
 
     // drag (panal resizing) -> rescaling
@@ -72,7 +115,12 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
       subsribes.push(
         fromEvent(window, "scroll")
           .pipe(debounceTime(500))
-          .subscribe(() => svgDoc.addViewportChange()),
+          .subscribe(() => {
+            svgDoc.addViewportChange();
+            if (previewMode === PreviewMode.Doc) {
+              reportScrollPosition();
+            }
+          }),
       );
     }
 
@@ -85,7 +133,23 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
             svgDoc.setOutineData(message.outline);
             break;
           }
+          case "layoutGuides": {
+            setLayoutGuides(message.enabled ?? !layoutGuidesEnabled);
+            break;
+          }
+        }
+      }),
+    );
+
+    // `l` toggles the layout guides.
+    subsribes.push(
+      fromEvent<KeyboardEvent>(window, "keydown").subscribe((e) => {
+        const target = e.target as HTMLElement | null;
+        if (e.key !== "l" || e.ctrlKey || e.metaKey || e.altKey || target?.tagName === "INPUT") {
+          return;
         }
+        setLayoutGuides(!layoutGuidesEnabled);
+        e.preventDefault();
       }),
     );
 
@@ -100,7 +164,9 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
               return;
             }
             const page = Number.parseInt(inpPageSelector.value);
-            svgDoc.setPartialPageNumber(page);
+            if (svgDoc.setPartialPageNumber(page)) {
+              onSlideChanged(page);
+            }
           });
         }
       }
@@ -141,6 +207,7 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
           if (svgDoc.setPartialPageNumber(page)) {
             pageSelector.value = page.toString();
             blurInput();
+            onSlideChanged(page);
           }
         }
       };
@@ -199,6 +266,15 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
             removeHelp();
             focusInput();
             break;
+          case "n":
+            presenter?.toggle();
+            break;
+          case "t":
+            presenter?.controlTimer("toggle");
+            break;
+          case "r":
+            presenter?.controlTimer("reset");
+            break;
           case "Escape":
             removeHelp();
             blurInput();
@@ -232,6 +308,8 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
           console.log("WebSocket connection opened", sock);
           window.typstWebsocket = sock as any;
           svgDoc.reset();
+          const hello = { version: PROTOCOL_VERSION, capabilities: CAPABILITIES, session };
+          window.typstWebsocket.send(`hello,${JSON.stringify(hello)}`);
           window.typstWebsocket.send("current");
         },
       },
@@ -251,6 +329,7 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
     const dispose = () => {
       disposed = true;
       svgDoc.dispose();
+      presenter?.dispose();
       const index = window.documents.indexOf(svgDoc);
       if (index >= 0) {
         window.documents.splice(index, 1);
@@ -366,6 +445,7 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
             if (pageSelector) {
               pageSelector.value = page.toString();
             }
+            presenter?.setPage(page);
             // pageToJump = 1;
             // todo: hint location
             return;
@@ -396,6 +476,44 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
         console.log("cursor-paths", paths);
         svgDoc.impl.setCursorPaths(paths);
         return;
+      } else if (message[0] === "hello") {
+        const hello = JSON.parse(dec.decode((message[1] as any).buffer));
+        if (hello.version !== PROTOCOL_VERSION) {
+          console.warn(
+            `The preview server speaks protocol version ${hello.version}, expected ${PROTOCOL_VERSION}`,
+          );
+        }
+        console.log("Server capabilities:", hello.capabilities);
+        return;
+      } else if (message[0] === "scroll-position") {
+        // the position before reconnecting, which is replayed after the latest frame
+        const [page, left, top] = dec
+          .decode((message[1] as any).buffer)
+          .split(" ")
+          .map(Number);
+        if (previewMode === PreviewMode.Slide) {
+          retryOnFrames(() => {
+            if (!svgDoc.setPartialPageNumber(page)) {
+              return false;
+            }
+            const pageSelector = document.getElementById("typst-page-selector") as
+              | HTMLSelectElement
+              | undefined;
+            if (pageSelector) {
+              pageSelector.value = page.toString();
+            }
+            presenter?.setPage(page);
+            return true;
+          });
+        } else {
+          // the pages are rendered asynchronously, so waits until they are tall enough
+          retryOnFrames(() => {
+            const maxTop = document.documentElement.scrollHeight - window.innerHeight;
+            window.scrollTo(left, Math.min(top, maxTop));
+            return maxTop >= top;
+          });
+        }
+        return;
       } else if (message[0] === "partial-rendering") {
         console.log("Experimental feature: partial rendering enabled");
         svgDoc.setPartialRendering(true);
@@ -408,12 +526,51 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
         console.log("Experimental feature: invert colors strategy taken:", strategy);
         ensureInvertColors(document.getElementById("typst-app"), strategy);
         return;
+      } else if (message[0] === "slide") {
+        // the slide changed by another view, e.g. the presenter view
+        const page = Number.parseInt(dec.decode((message[1] as any).buffer));
+        if (previewMode !== PreviewMode.Slide || page === svgDoc.getPartialPageNumber()) {
+          return;
+        }
+        if (svgDoc.setPartialPageNumber(page)) {
+          const pageSelector = document.getElementById("typst-page-selector") as
+            | HTMLSelectElement
+            | undefined;
+          if (pageSelector) {
+            pageSelector.value = page.toString();
+          }
+          presenter?.setPage(page);
+        }
+        return;
+      } else if (message[0] === "speaker-notes") {
+        const notes: SpeakerNote[] = JSON.parse(dec.decode((message[1] as any).buffer));
+        presenter?.setNotes(notes);
+        return;
+      } else if (message[0] === "page-color") {
+        // the pages are recolored by the server
+        svgDoc.setPageColor(dec.decode((message[1] as any).buffer).trim());
+        return;
       } else if (message[0] === "outline") {
         console.log("Experimental feature: outline rendering");
         return;
+      } else if (message[0] === "layout-guides") {
+        const hookedElem = document.getElementById("typst-app");
+        if (layoutGuidesEnabled && hookedElem) {
+          const guides: LayoutGuides = JSON.parse(dec.decode((message[1] as any).buffer));
+          drawLayoutGuides(hookedElem, guides);
+        }
+        return;
       }
 
       svgDoc.addChangement(message as any);
+      if (layoutGuidesEnabled) {
+        // The guides are redrawn since the pages may be replaced by the update.
+        window.typstWebsocket?.send("layout-guides");
+      }
+      if (presenter?.visible) {
+        // The notes may be changed by the update.
+        window.typstWebsocket?.send("speaker-notes");
+      }
     }
 
     return dispose;
@@ -441,6 +598,31 @@ export async function wsMain({ url, previewMode, isContentPreview }: WsArgs) {
   );
 }
 
+/** Gets the session of the preview, which is kept across the reloads of the page if possible. */
+function previewSession(url: string): string {
+  const key = `typst-preview-session:${url}`;
+  try {
+    const session = sessionStorage.getItem(key);
+    if (session) {
+      return session;
+    }
+  } catch {}
+
+  const session = Math.random().toString(36).slice(2) + Date.now().toString(36);
+  try {
+    sessionStorage.setItem(key, session);
+  } catch {}
+  return session;
+}
+
+/** Calls the function on the next animation frames until it succeeds. */
+function retryOnFrames(f: () => boolean, frames = 60) {
+  if (f() || frames <= 1) {
+    return;
+  }
+  requestAnimationFrame(() => retryOnFrames(f, frames - 1));
+}
+
 /** The strategy to set invert colors, see editors/vscode/package.json for enum descriptions */
 const INVERT_COLORS_STRATEGY = ["never", "auto", "always"] as const;
 /** The value of strategy constant */